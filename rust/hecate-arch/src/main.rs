@@ -1,8 +1,10 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use colored::*;
+use petgraph::dot::{Config as DotConfig, Dot};
 use petgraph::graph::{DiGraph, NodeIndex};
-use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::path::Path;
 use walkdir::WalkDir;
@@ -10,10 +12,22 @@ use walkdir::WalkDir;
 #[derive(Parser)]
 #[command(author, version, about = "HecateOS architecture validator")]
 struct Cli {
+    /// Output format
+    #[arg(long, global = true, value_enum, default_value = "text")]
+    format: OutputFormat,
+    
     #[command(subcommand)]
     command: Commands,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+    /// Graphviz DOT, for `diagram` only
+    Dot,
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Validate project structure
@@ -22,69 +36,114 @@ enum Commands {
     Cycles,
     /// Show module boundaries
     Boundaries,
+    /// Check that dependencies only point to lower layers
+    Layers,
     /// Validate port configuration
     Ports,
     /// Generate architecture diagram
-    Diagram,
+    Diagram {
+        /// Color crates by architecture layer (DOT output)
+        #[arg(long)]
+        color_layers: bool,
+    },
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    let root = Path::new(".");
+    
+    if cli.format == OutputFormat::Dot && !matches!(cli.command, Commands::Diagram { .. }) {
+        anyhow::bail!("--format dot is only supported by the diagram command");
+    }
     
     match cli.command {
-        Commands::Validate => validate_structure()?,
-        Commands::Cycles => check_cycles()?,
+        Commands::Validate => validate_structure(root, cli.format)?,
+        Commands::Cycles => check_cycles(root, cli.format)?,
         Commands::Boundaries => show_boundaries()?,
-        Commands::Ports => validate_ports()?,
-        Commands::Diagram => generate_diagram()?,
+        Commands::Layers => check_layers(root, cli.format)?,
+        Commands::Ports => validate_ports(root, cli.format)?,
+        Commands::Diagram { color_layers } => match cli.format {
+            OutputFormat::Dot => print!("{}", render_dot(&dependency_graph(root)?, color_layers)),
+            OutputFormat::Text => generate_diagram()?,
+            OutputFormat::Json => anyhow::bail!("diagram supports --format text or dot"),
+        },
     }
     
     Ok(())
 }
 
-fn validate_structure() -> Result<()> {
-    println!("{} Validating architecture...", "→".blue());
-    
-    let required_structure = vec![
-        ("rust/hecate-core", "Core library"),
-        ("rust/hecate-daemon", "System daemon"),
-        ("rust/hecate-gpu", "GPU management"),
-        ("rust/hecate-pkg", "Package manager"),
-        ("rust/hecate-dev", "Development tools"),
-        ("hecate-dashboard", "Web dashboard"),
-        ("docs", "Documentation"),
-        ("scripts", "System scripts"),
-        ("config", "Configuration"),
-    ];
-    
-    let mut all_valid = true;
-    
-    for (path, description) in required_structure {
-        if Path::new(path).exists() {
-            println!("  {} {} - {}", "✓".green(), path, description.dimmed());
-        } else {
-            println!("  {} {} - {} {}", "✗".red(), path, description.dimmed(), "MISSING".red());
-            all_valid = false;
-        }
+/// Print `report` as JSON on stdout
+fn print_json<T: Serialize>(report: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(report)?);
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct StructureReport {
+    missing: Vec<String>,
+    present: Vec<String>,
+    valid: bool,
+}
+
+const REQUIRED_STRUCTURE: &[(&str, &str)] = &[
+    ("rust/hecate-core", "Core library"),
+    ("rust/hecate-daemon", "System daemon"),
+    ("rust/hecate-gpu", "GPU management"),
+    ("rust/hecate-pkg", "Package manager"),
+    ("rust/hecate-dev", "Development tools"),
+    ("hecate-dashboard", "Web dashboard"),
+    ("docs", "Documentation"),
+    ("scripts", "System scripts"),
+    ("config", "Configuration"),
+];
+
+fn check_structure(root: &Path) -> StructureReport {
+    let (present, missing): (Vec<_>, Vec<_>) = REQUIRED_STRUCTURE.iter()
+        .map(|(path, _)| path.to_string())
+        .partition(|path| root.join(path).exists());
+    
+    StructureReport {
+        valid: missing.is_empty(),
+        missing,
+        present,
     }
+}
+
+fn validate_structure(root: &Path, format: OutputFormat) -> Result<()> {
+    let report = check_structure(root);
     
-    if all_valid {
-        println!("\n{} Architecture structure is valid", "✓".green().bold());
+    if format == OutputFormat::Json {
+        print_json(&report)?;
     } else {
+        println!("{} Validating architecture...", "→".blue());
+        for (path, description) in REQUIRED_STRUCTURE {
+            if report.present.iter().any(|p| p == path) {
+                println!("  {} {} - {}", "✓".green(), path, description.dimmed());
+            } else {
+                println!("  {} {} - {} {}", "✗".red(), path, description.dimmed(), "MISSING".red());
+            }
+        }
+        
+        if report.valid {
+            println!("\n{} Architecture structure is valid", "✓".green().bold());
+        }
+    }
+    
+    if !report.valid {
         anyhow::bail!("Architecture validation failed");
     }
     
     Ok(())
 }
 
-fn check_cycles() -> Result<()> {
-    println!("{} Checking for circular dependencies...", "→".blue());
-    
+/// Inter-crate dependency graph from every Cargo.toml under `root/rust`
+fn dependency_graph(root: &Path) -> Result<DiGraph<String, ()>> {
     let mut graph = DiGraph::new();
     let mut nodes: HashMap<String, NodeIndex> = HashMap::new();
     
     // Parse Cargo.toml files to build dependency graph
-    for entry in WalkDir::new("rust")
+    for entry in WalkDir::new(root.join("rust"))
+        .sort_by_file_name()
         .into_iter()
         .filter_map(|e| e.ok())
         .filter(|e| e.file_name() == "Cargo.toml")
@@ -120,12 +179,201 @@ fn check_cycles() -> Result<()> {
         }
     }
     
-    // Check for cycles using Tarjan's algorithm
-    if petgraph::algo::is_cyclic_directed(&graph) {
-        println!("{} Circular dependencies detected!", "✗".red().bold());
+    Ok(graph)
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct CycleReport {
+    /// One dependency path per cycle, ending where it starts, e.g.
+    /// `["hecate-core", "hecate-pkg", "hecate-core"]`
+    cycles: Vec<Vec<String>>,
+    valid: bool,
+}
+
+/// A path from `start` back to itself that stays inside `component`
+fn cycle_path(graph: &DiGraph<String, ()>, component: &HashSet<NodeIndex>, start: NodeIndex) -> Vec<NodeIndex> {
+    // Depth-first search; every node of a strongly connected component
+    // reaches every other, so this always finds its way back
+    let mut path = vec![start];
+    let mut visited = HashSet::from([start]);
+    let mut stack = vec![graph.neighbors(start).collect::<Vec<_>>()];
+    
+    while let Some(next) = stack.last_mut() {
+        let Some(node) = next.pop() else {
+            stack.pop();
+            path.pop();
+            continue;
+        };
+        if node == start {
+            path.push(start);
+            return path;
+        }
+        if component.contains(&node) && visited.insert(node) {
+            path.push(node);
+            stack.push(graph.neighbors(node).collect());
+        }
+    }
+    
+    Vec::new()
+}
+
+/// Cycles from Tarjan's strongly connected components: every component
+/// with more than one crate, or a crate depending on itself
+fn find_cycles(graph: &DiGraph<String, ()>) -> CycleReport {
+    let mut cycles: Vec<Vec<String>> = petgraph::algo::tarjan_scc(graph)
+        .into_iter()
+        .filter(|component| component.len() > 1 || graph.contains_edge(component[0], component[0]))
+        .map(|component| {
+            // Start from the first crate by name so output is stable
+            let start = *component.iter().min_by_key(|&&node| &graph[node]).unwrap();
+            let members: HashSet<NodeIndex> = component.into_iter().collect();
+            cycle_path(graph, &members, start)
+                .into_iter()
+                .map(|node| graph[node].clone())
+                .collect()
+        })
+        .collect();
+    cycles.sort();
+    
+    CycleReport {
+        valid: cycles.is_empty(),
+        cycles,
+    }
+}
+
+fn check_cycles(root: &Path, format: OutputFormat) -> Result<()> {
+    if format == OutputFormat::Text {
+        println!("{} Checking for circular dependencies...", "→".blue());
+    }
+    
+    let report = find_cycles(&dependency_graph(root)?);
+    
+    if format == OutputFormat::Json {
+        print_json(&report)?;
+    } else if report.valid {
+        println!("{} No circular dependencies found", "✓".green().bold());
+    } else {
+        println!("{} Circular dependencies detected:", "✗".red().bold());
+        for cycle in &report.cycles {
+            println!("  {}", cycle.join(" → "));
+        }
+    }
+    
+    if !report.valid {
         anyhow::bail!("Circular dependencies found in module graph");
+    }
+    
+    Ok(())
+}
+
+/// Architecture layers, lowest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+enum Layer {
+    Core,
+    Domain,
+    Services,
+    Applications,
+}
+
+/// Layer of every crate; dependencies may only point to a lower layer
+const LAYERS: &[(&str, Layer)] = &[
+    ("hecate-core", Layer::Core),
+    ("hecate-gpu", Layer::Domain),
+    ("hecate-pkg", Layer::Domain),
+    ("hecate-sign", Layer::Domain),
+    ("hecate-ml", Layer::Domain),
+    ("hecate-daemon", Layer::Services),
+    ("hecate-monitor", Layer::Services),
+    ("hecate-update", Layer::Services),
+    ("hecate-cli", Layer::Applications),
+    ("hecate-dashboard", Layer::Applications),
+    ("hecate-bench", Layer::Applications),
+    ("hecate-iso-builder", Layer::Applications),
+    ("hecate-arch", Layer::Applications),
+    ("hecate-changelog", Layer::Applications),
+    ("hecate-deps", Layer::Applications),
+    ("hecate-dev", Layer::Applications),
+    ("hecate-hooks", Layer::Applications),
+    ("hecate-lint", Layer::Applications),
+];
+
+fn layer_of(name: &str) -> Option<Layer> {
+    LAYERS.iter().find(|(crate_name, _)| *crate_name == name).map(|&(_, layer)| layer)
+}
+
+/// A dependency on a crate in the same or a higher layer
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct LayerViolation {
+    from: String,
+    from_layer: Layer,
+    to: String,
+    to_layer: Layer,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct LayerReport {
+    violations: Vec<LayerViolation>,
+    /// Crates missing from the layer table, which are not checked
+    unassigned: Vec<String>,
+    valid: bool,
+}
+
+fn find_layer_violations(graph: &DiGraph<String, ()>) -> LayerReport {
+    let mut violations = Vec::new();
+    for edge in graph.raw_edges() {
+        let (from, to) = (&graph[edge.source()], &graph[edge.target()]);
+        if let (Some(from_layer), Some(to_layer)) = (layer_of(from), layer_of(to)) {
+            if to_layer >= from_layer {
+                violations.push(LayerViolation {
+                    from: from.clone(),
+                    from_layer,
+                    to: to.clone(),
+                    to_layer,
+                });
+            }
+        }
+    }
+    violations.sort_by(|a, b| (&a.from, &a.to).cmp(&(&b.from, &b.to)));
+    
+    let mut unassigned: Vec<String> = graph.node_weights()
+        .filter(|name| layer_of(name).is_none())
+        .cloned()
+        .collect();
+    unassigned.sort();
+    
+    LayerReport {
+        valid: violations.is_empty(),
+        violations,
+        unassigned,
+    }
+}
+
+fn check_layers(root: &Path, format: OutputFormat) -> Result<()> {
+    if format == OutputFormat::Text {
+        println!("{} Checking layer boundaries...", "→".blue());
+    }
+    
+    let report = find_layer_violations(&dependency_graph(root)?);
+    
+    if format == OutputFormat::Json {
+        print_json(&report)?;
     } else {
-        println!("{} No circular dependencies found", "✓".green().bold());
+        for name in &report.unassigned {
+            println!("  {} {} has no layer assigned", "⚠".yellow(), name);
+        }
+        if report.valid {
+            println!("{} All dependencies flow downward", "✓".green().bold());
+        } else {
+            println!("{} Layer violations detected:", "✗".red().bold());
+            for violation in &report.violations {
+                println!("  {} ({:?}) → {} ({:?})",
+                    violation.from, violation.from_layer, violation.to, violation.to_layer);
+            }
+        }
+    }
+    
+    if !report.valid {
+        anyhow::bail!("Dependencies violate the layer architecture");
     }
     
     Ok(())
@@ -160,51 +408,140 @@ fn show_boundaries() -> Result<()> {
     Ok(())
 }
 
-fn validate_ports() -> Result<()> {
-    println!("{} Validating port configuration...", "→".blue());
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct PortEntry {
+    name: String,
+    port: u16,
+    description: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct DuplicatePort {
+    port: u16,
+    names: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct PortReport {
+    configured: Vec<PortEntry>,
+    duplicates: Vec<DuplicatePort>,
+    missing: Vec<PortEntry>,
+    valid: bool,
+}
+
+const PORTS_CONFIG: &str = "config/hecate/ports.conf";
+
+const EXPECTED_PORTS: &[(&str, u16, &str)] = &[
+    ("HECATE_MONITOR_PORT", 9313, "WebSocket monitoring"),
+    ("HECATE_PKG_PORT", 9314, "Package manager API"),
+    ("HECATE_REMOTE_PORT", 9315, "Remote management"),
+    ("HECATE_BENCH_PORT", 9316, "Benchmark server"),
+    ("HECATE_GPU_PORT", 9317, "GPU management"),
+];
+
+/// `NAME=PORT` assignments in a ports.conf, ignoring comments
+fn parse_ports(config: &str) -> Vec<(String, u16)> {
+    config.lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let (name, value) = line.split_once('=')?;
+            Some((name.trim().to_string(), value.trim().trim_matches('"').parse().ok()?))
+        })
+        .collect()
+}
+
+fn check_ports(config: &str) -> PortReport {
+    let assigned = parse_ports(config);
     
-    let expected_ports = vec![
-        ("MONITOR", 9313, "WebSocket monitoring"),
-        ("PKG_API", 9314, "Package manager API"),
-        ("REMOTE", 9315, "Remote management"),
-        ("BENCH", 9316, "Benchmark server"),
-        ("GPU", 9317, "GPU management"),
-    ];
+    let (configured, missing): (Vec<_>, Vec<_>) = EXPECTED_PORTS.iter()
+        .map(|&(name, port, description)| PortEntry {
+            name: name.to_string(),
+            port,
+            description: description.to_string(),
+        })
+        .partition(|entry| assigned.iter().any(|(name, port)| *name == entry.name && *port == entry.port));
     
-    let config_path = "config/hecate/ports.conf";
-    let config = if Path::new(config_path).exists() {
-        fs::read_to_string(config_path)?
+    // Any port given to more than one service
+    let mut by_port: BTreeMap<u16, Vec<String>> = BTreeMap::new();
+    for (name, port) in assigned {
+        by_port.entry(port).or_default().push(name);
+    }
+    let duplicates: Vec<DuplicatePort> = by_port.into_iter()
+        .filter(|(_, names)| names.len() > 1)
+        .map(|(port, names)| DuplicatePort { port, names })
+        .collect();
+    
+    PortReport {
+        valid: missing.is_empty() && duplicates.is_empty(),
+        configured,
+        duplicates,
+        missing,
+    }
+}
+
+fn validate_ports(root: &Path, format: OutputFormat) -> Result<()> {
+    let config_path = root.join(PORTS_CONFIG);
+    let config = if config_path.exists() {
+        fs::read_to_string(&config_path)?
     } else {
         String::new()
     };
     
-    let mut all_found = true;
-    let mut used_ports = HashSet::new();
-    
-    for (name, port, description) in &expected_ports {
-        let pattern = format!("{}={}", name, port);
-        if config.contains(&pattern) {
-            println!("  {} Port {} ({}) - {}", "✓".green(), port, name, description.dimmed());
-            
-            if !used_ports.insert(port) {
-                println!("    {} Duplicate port detected!", "⚠".yellow());
-                all_found = false;
-            }
-        } else {
-            println!("  {} Port {} ({}) - {} {}", "✗".red(), port, name, description.dimmed(), "NOT CONFIGURED".red());
-            all_found = false;
+    let report = check_ports(&config);
+    
+    if format == OutputFormat::Json {
+        print_json(&report)?;
+    } else {
+        println!("{} Validating port configuration...", "→".blue());
+        for entry in &report.configured {
+            println!("  {} Port {} ({}) - {}", "✓".green(), entry.port, entry.name, entry.description.dimmed());
+        }
+        for entry in &report.missing {
+            println!("  {} Port {} ({}) - {} {}", "✗".red(), entry.port, entry.name, entry.description.dimmed(), "NOT CONFIGURED".red());
+        }
+        for duplicate in &report.duplicates {
+            println!("    {} Duplicate port {}: {}", "⚠".yellow(), duplicate.port, duplicate.names.join(", "));
+        }
+        
+        if report.valid {
+            println!("\n{} Port configuration is valid", "✓".green().bold());
         }
     }
     
-    if all_found {
-        println!("\n{} Port configuration is valid", "✓".green().bold());
-    } else {
+    if !report.valid {
         anyhow::bail!("Port configuration issues detected");
     }
     
     Ok(())
 }
 
+/// Fill color for crates in `layer`
+fn layer_color(layer: Option<Layer>) -> &'static str {
+    match layer {
+        Some(Layer::Core) => "#f4cccc",
+        Some(Layer::Domain) => "#fff2cc",
+        Some(Layer::Services) => "#d9ead3",
+        Some(Layer::Applications) => "#cfe2f3",
+        None => "#ffffff",
+    }
+}
+
+/// The dependency graph as Graphviz DOT, edges pointing at dependencies
+fn render_dot(graph: &DiGraph<String, ()>, color_layers: bool) -> String {
+    // Dot needs displayable edge weights even when they are not shown
+    let graph = graph.map(|_, name| name.as_str(), |_, _| "");
+    let node_attributes = |_, (_, name): (NodeIndex, &&str)| {
+        if color_layers {
+            format!("style = filled, fillcolor = \"{}\"", layer_color(layer_of(name)))
+        } else {
+            String::new()
+        }
+    };
+    let dot = Dot::with_attr_getters(&graph, &[DotConfig::EdgeNoLabel], &|_, _| String::new(), &node_attributes);
+    format!("{}", dot)
+}
+
 fn generate_diagram() -> Result<()> {
     println!("{} Generating architecture diagram...", "→".blue());
     
@@ -258,4 +595,168 @@ HecateOS Architecture
     println!("\n{} Diagram saved to {}", "✓".green().bold(), output_path);
     
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<T: Serialize + for<'de> Deserialize<'de>>(report: &T) -> T {
+        serde_json::from_str(&serde_json::to_string(report).unwrap()).unwrap()
+    }
+
+    fn write_crate(root: &Path, name: &str, deps: &[&str]) {
+        let dir = root.join("rust").join(name);
+        fs::create_dir_all(&dir).unwrap();
+        let deps: String = deps.iter().map(|dep| format!("{} = {{ path = \"../{}\" }}\n", dep, dep)).collect();
+        fs::write(dir.join("Cargo.toml"), format!("[package]\nname = \"{}\"\n\n[dependencies]\n{}", name, deps)).unwrap();
+    }
+
+    #[test]
+    fn test_broken_structure_json() {
+        let root = tempfile::tempdir().unwrap();
+        for path in ["rust/hecate-core", "rust/hecate-daemon", "docs", "config"] {
+            fs::create_dir_all(root.path().join(path)).unwrap();
+        }
+
+        let report = round_trip(&check_structure(root.path()));
+        assert_eq!(report, StructureReport {
+            missing: vec!["rust/hecate-gpu", "rust/hecate-pkg", "rust/hecate-dev", "hecate-dashboard", "scripts"]
+                .into_iter().map(String::from).collect(),
+            present: vec!["rust/hecate-core", "rust/hecate-daemon", "docs", "config"]
+                .into_iter().map(String::from).collect(),
+            valid: false,
+        });
+        assert!(validate_structure(root.path(), OutputFormat::Json).is_err());
+    }
+
+    #[test]
+    fn test_cyclic_graph_json() {
+        let root = tempfile::tempdir().unwrap();
+        write_crate(root.path(), "hecate-core", &[]);
+        write_crate(root.path(), "hecate-gpu", &["hecate-core", "hecate-daemon"]);
+        write_crate(root.path(), "hecate-daemon", &["hecate-core", "hecate-gpu"]);
+
+        let report = round_trip(&find_cycles(&dependency_graph(root.path()).unwrap()));
+        assert!(!report.valid);
+        assert_eq!(report.cycles, vec![vec!["hecate-daemon", "hecate-gpu", "hecate-daemon"]]);
+        assert!(check_cycles(root.path(), OutputFormat::Json).is_err());
+    }
+
+    #[test]
+    fn test_broken_ports_json() {
+        let config = "# ports\nHECATE_MONITOR_PORT=9313\nHECATE_PKG_PORT=9313\nHECATE_BENCH_PORT=9316\nHECATE_GPU_PORT=9317\n";
+        let report = round_trip(&check_ports(config));
+
+        let names = |entries: &[PortEntry]| entries.iter().map(|e| e.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&report.configured), vec!["HECATE_MONITOR_PORT", "HECATE_BENCH_PORT", "HECATE_GPU_PORT"]);
+        assert_eq!(names(&report.missing), vec!["HECATE_PKG_PORT", "HECATE_REMOTE_PORT"]);
+        assert_eq!(report.duplicates, vec![DuplicatePort {
+            port: 9313,
+            names: vec!["HECATE_MONITOR_PORT".to_string(), "HECATE_PKG_PORT".to_string()],
+        }]);
+        assert!(!report.valid);
+    }
+
+    fn graph(edges: &[(&str, &str)]) -> DiGraph<String, ()> {
+        let mut graph = DiGraph::new();
+        let mut nodes: HashMap<&str, NodeIndex> = HashMap::new();
+        for &(from, to) in edges {
+            let from = *nodes.entry(from).or_insert_with(|| graph.add_node(from.to_string()));
+            let to = *nodes.entry(to).or_insert_with(|| graph.add_node(to.to_string()));
+            graph.add_edge(from, to, ());
+        }
+        graph
+    }
+
+    #[test]
+    fn test_three_cycle_path() {
+        let report = find_cycles(&graph(&[
+            ("hecate-pkg", "hecate-core"),
+            ("hecate-core", "hecate-gpu"),
+            ("hecate-gpu", "hecate-pkg"),
+            ("hecate-cli", "hecate-pkg"),
+            ("hecate-cli", "hecate-core"),
+        ]));
+
+        assert_eq!(report.cycles, vec![vec!["hecate-core", "hecate-gpu", "hecate-pkg", "hecate-core"]]);
+        assert!(!report.valid);
+    }
+
+    #[test]
+    fn test_self_loop_and_acyclic() {
+        let report = find_cycles(&graph(&[("hecate-pkg", "hecate-pkg"), ("hecate-pkg", "hecate-core")]));
+        assert_eq!(report.cycles, vec![vec!["hecate-pkg", "hecate-pkg"]]);
+
+        let report = find_cycles(&graph(&[("hecate-pkg", "hecate-core"), ("hecate-cli", "hecate-pkg")]));
+        assert!(report.valid && report.cycles.is_empty());
+    }
+
+    #[test]
+    fn test_inverted_layer_dependency() {
+        let root = tempfile::tempdir().unwrap();
+        write_crate(root.path(), "hecate-core", &["hecate-cli"]);
+        write_crate(root.path(), "hecate-gpu", &["hecate-core", "hecate-pkg"]);
+        write_crate(root.path(), "hecate-pkg", &["hecate-core"]);
+        write_crate(root.path(), "hecate-cli", &["hecate-gpu", "hecate-experimental"]);
+
+        let report = round_trip(&find_layer_violations(&dependency_graph(root.path()).unwrap()));
+        assert_eq!(report.violations, vec![
+            LayerViolation {
+                from: "hecate-core".to_string(),
+                from_layer: Layer::Core,
+                to: "hecate-cli".to_string(),
+                to_layer: Layer::Applications,
+            },
+            LayerViolation {
+                from: "hecate-gpu".to_string(),
+                from_layer: Layer::Domain,
+                to: "hecate-pkg".to_string(),
+                to_layer: Layer::Domain,
+            },
+        ]);
+        assert_eq!(report.unassigned, vec!["hecate-experimental"]);
+        assert!(!report.valid);
+        assert!(check_layers(root.path(), OutputFormat::Json).is_err());
+    }
+
+    #[test]
+    fn test_downward_layers_are_valid() {
+        let root = tempfile::tempdir().unwrap();
+        write_crate(root.path(), "hecate-core", &[]);
+        write_crate(root.path(), "hecate-pkg", &["hecate-core"]);
+        write_crate(root.path(), "hecate-update", &["hecate-core", "hecate-pkg"]);
+        write_crate(root.path(), "hecate-cli", &["hecate-update", "hecate-core"]);
+
+        let report = find_layer_violations(&dependency_graph(root.path()).unwrap());
+        assert!(report.valid && report.violations.is_empty() && report.unassigned.is_empty());
+    }
+
+    #[test]
+    fn test_dot_diagram() {
+        let root = tempfile::tempdir().unwrap();
+        write_crate(root.path(), "hecate-core", &[]);
+        write_crate(root.path(), "hecate-gpu", &[]);
+        write_crate(root.path(), "hecate-cli", &["hecate-core", "hecate-gpu"]);
+        write_crate(root.path(), "hecate-daemon", &["hecate-core"]);
+        let graph = dependency_graph(root.path()).unwrap();
+
+        let dot = render_dot(&graph, false);
+        assert!(dot.starts_with("digraph {"));
+        let node = |name: &str| graph.node_indices().find(|&idx| graph[idx] == name).unwrap().index();
+        for name in ["hecate-core", "hecate-gpu", "hecate-cli", "hecate-daemon"] {
+            assert!(dot.contains(&format!("{} [ label = \"{}\" ]", node(name), name)), "{}", dot);
+        }
+        for (from, to) in [("hecate-cli", "hecate-core"), ("hecate-cli", "hecate-gpu"), ("hecate-daemon", "hecate-core")] {
+            assert!(dot.contains(&format!("{} -> {} [ ]", node(from), node(to))), "{}", dot);
+        }
+        assert_eq!(dot.matches(" -> ").count(), 3);
+
+        let colored = render_dot(&graph, true);
+        assert!(colored.contains(&format!(
+            "{} [ label = \"hecate-core\" style = filled, fillcolor = \"{}\"]",
+            node("hecate-core"),
+            layer_color(Some(Layer::Core)),
+        )), "{}", colored);
+    }
+}
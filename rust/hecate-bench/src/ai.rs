@@ -0,0 +1,228 @@
+//! Matrix multiplication and convolution kernels for the AI benchmarks
+//!
+//! Each kernel has a naive scalar version and an optimized version built
+//! on `matrixmultiply`'s cache-blocked SIMD GEMM. The optimized numbers
+//! show what the machine can achieve; the naive ones show how well plain
+//! loops compile, and are kept for comparison with older results.
+
+use std::time::{Duration, Instant};
+
+/// Matrix dimension for the matmul benchmark
+pub const MATMUL_N: usize = 512;
+
+/// Shape of a 2D convolution over a square multi-channel input, with
+/// stride 1 and no padding
+#[derive(Debug, Clone, Copy)]
+pub struct ConvShape {
+    pub size: usize,
+    pub channels_in: usize,
+    pub channels_out: usize,
+    pub kernel: usize,
+}
+
+impl ConvShape {
+    pub fn out_size(&self) -> usize {
+        self.size - self.kernel + 1
+    }
+
+    /// Multiply-adds counted as two operations
+    pub fn ops(&self) -> u64 {
+        let out = self.out_size() as u64;
+        2 * out * out * (self.channels_out * self.channels_in * self.kernel * self.kernel) as u64
+    }
+}
+
+/// Convolution layer measured by the benchmark
+pub const CONV_SHAPE: ConvShape = ConvShape {
+    size: 112,
+    channels_in: 16,
+    channels_out: 32,
+    kernel: 3,
+};
+
+/// `c = a * b` for row-major `n` x `n` matrices, with plain loops
+pub fn matmul_naive(a: &[f32], b: &[f32], c: &mut [f32], n: usize) {
+    for i in 0..n {
+        for j in 0..n {
+            let mut sum = 0.0;
+            for k in 0..n {
+                sum += a[i * n + k] * b[k * n + j];
+            }
+            c[i * n + j] = sum;
+        }
+    }
+}
+
+/// `c = a * b` for row-major `n` x `n` matrices, with blocked SIMD GEMM
+pub fn matmul_fast(a: &[f32], b: &[f32], c: &mut [f32], n: usize) {
+    gemm(a, b, c, n, n, n);
+}
+
+/// Row-major `c (m x n) = a (m x k) * b (k x n)`
+fn gemm(a: &[f32], b: &[f32], c: &mut [f32], m: usize, k: usize, n: usize) {
+    assert!(a.len() >= m * k && b.len() >= k * n && c.len() >= m * n);
+    // SAFETY: the slices are large enough for the given dimensions and strides
+    unsafe {
+        matrixmultiply::sgemm(
+            m, k, n,
+            1.0,
+            a.as_ptr(), k as isize, 1,
+            b.as_ptr(), n as isize, 1,
+            0.0,
+            c.as_mut_ptr(), n as isize, 1,
+        );
+    }
+}
+
+/// Convolve `input` (`[channels_in][size][size]`) with `weights`
+/// (`[channels_out][channels_in][kernel][kernel]`), with plain loops
+///
+/// Returns `[channels_out][out_size][out_size]`.
+pub fn conv2d_naive(input: &[f32], weights: &[f32], shape: ConvShape) -> Vec<f32> {
+    let ConvShape { size, channels_in, channels_out, kernel } = shape;
+    let out_size = shape.out_size();
+    let mut output = vec![0.0f32; channels_out * out_size * out_size];
+
+    for co in 0..channels_out {
+        for y in 0..out_size {
+            for x in 0..out_size {
+                let mut sum = 0.0;
+                for ci in 0..channels_in {
+                    for ky in 0..kernel {
+                        for kx in 0..kernel {
+                            let pixel = input[(ci * size + y + ky) * size + x + kx];
+                            let weight = weights[((co * channels_in + ci) * kernel + ky) * kernel + kx];
+                            sum += pixel * weight;
+                        }
+                    }
+                }
+                output[(co * out_size + y) * out_size + x] = sum;
+            }
+        }
+    }
+
+    output
+}
+
+/// Same as [`conv2d_naive`], lowered to a GEMM with im2col
+pub fn conv2d_fast(input: &[f32], weights: &[f32], shape: ConvShape) -> Vec<f32> {
+    let ConvShape { size, channels_in, channels_out, kernel } = shape;
+    let out_size = shape.out_size();
+    let patch = channels_in * kernel * kernel;
+    let pixels = out_size * out_size;
+
+    // columns[patch][pixels]: each column holds the receptive field of one
+    // output pixel, in the same order as a filter's weights
+    let mut columns = vec![0.0f32; patch * pixels];
+    for ci in 0..channels_in {
+        for ky in 0..kernel {
+            for kx in 0..kernel {
+                let row = (ci * kernel + ky) * kernel + kx;
+                for y in 0..out_size {
+                    let src = (ci * size + y + ky) * size + kx;
+                    let dst = row * pixels + y * out_size;
+                    columns[dst..dst + out_size].copy_from_slice(&input[src..src + out_size]);
+                }
+            }
+        }
+    }
+
+    let mut output = vec![0.0f32; channels_out * pixels];
+    gemm(weights, &columns, &mut output, channels_out, patch, pixels);
+    output
+}
+
+/// Repeat `pass` until `duration` has elapsed, at least once, and return
+/// billions of operations per second given `ops` per pass
+pub fn throughput(duration: Duration, ops: u64, mut pass: impl FnMut()) -> f64 {
+    let start = Instant::now();
+    let mut passes = 0u64;
+    while passes == 0 || start.elapsed() < duration {
+        pass();
+        passes += 1;
+    }
+    (passes * ops) as f64 / start.elapsed().as_secs_f64() / 1e9
+}
+
+/// Deterministic pseudo-random inputs in [-1, 1)
+pub fn test_data(len: usize, seed: u32) -> Vec<f32> {
+    let mut state = seed.wrapping_mul(2654435761).max(1);
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            (state as f32 / u32::MAX as f32) * 2.0 - 1.0
+        })
+        .collect()
+}
+
+/// Matmul throughput in GFLOPS
+pub fn matmul_gflops(duration: Duration, naive: bool) -> f64 {
+    let n = MATMUL_N;
+    let a = test_data(n * n, 1);
+    let b = test_data(n * n, 2);
+    let mut c = vec![0.0f32; n * n];
+
+    let ops = 2 * (n * n * n) as u64;
+    throughput(duration, ops, || {
+        if naive {
+            matmul_naive(&a, &b, &mut c, n);
+        } else {
+            matmul_fast(&a, &b, &mut c, n);
+        }
+        std::hint::black_box(&c);
+    })
+}
+
+/// Convolution throughput in GOPS
+pub fn conv_gops(duration: Duration, naive: bool) -> f64 {
+    let shape = CONV_SHAPE;
+    let input = test_data(shape.channels_in * shape.size * shape.size, 3);
+    let weights = test_data(shape.channels_out * shape.channels_in * shape.kernel * shape.kernel, 4);
+
+    throughput(duration, shape.ops(), || {
+        let output = if naive {
+            conv2d_naive(&input, &weights, shape)
+        } else {
+            conv2d_fast(&input, &weights, shape)
+        };
+        std::hint::black_box(output);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(fast: &[f32], naive: &[f32]) {
+        assert_eq!(fast.len(), naive.len());
+        for (i, (f, n)) in fast.iter().zip(naive).enumerate() {
+            assert!((f - n).abs() <= 1e-4 * n.abs().max(1.0), "element {}: {} vs {}", i, f, n);
+        }
+    }
+
+    #[test]
+    fn test_matmul_backends_agree() {
+        let n = 37;
+        let a = test_data(n * n, 11);
+        let b = test_data(n * n, 12);
+        let mut naive = vec![0.0; n * n];
+        let mut fast = vec![1.0; n * n];
+
+        matmul_naive(&a, &b, &mut naive, n);
+        matmul_fast(&a, &b, &mut fast, n);
+        assert_close(&fast, &naive);
+    }
+
+    #[test]
+    fn test_conv_backends_agree() {
+        let shape = ConvShape { size: 10, channels_in: 3, channels_out: 4, kernel: 3 };
+        let input = test_data(shape.channels_in * shape.size * shape.size, 21);
+        let weights = test_data(shape.channels_out * shape.channels_in * 9, 22);
+
+        let naive = conv2d_naive(&input, &weights, shape);
+        assert_eq!(naive.len(), 4 * 8 * 8);
+        assert_close(&conv2d_fast(&input, &weights, shape), &naive);
+    }
+}
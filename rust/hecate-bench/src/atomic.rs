@@ -0,0 +1,80 @@
+//! Atomic result file writes
+//!
+//! Results are written to a temporary file in the target directory and
+//! renamed over the target, so a reader such as `compare` or node_exporter
+//! sees either the old file or the new one, never a truncated mix.
+
+use crate::scratch::ScratchFile;
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::Path;
+
+/// Write `contents` to a temporary file next to `path` and rename it into
+/// place, creating missing parent directories first
+///
+/// The temporary name does not end in `.prom`, so node_exporter's textfile
+/// collector ignores it while it is being written.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let file_name = path.file_name()
+        .ok_or_else(|| anyhow::anyhow!("Invalid output path {}", path.display()))?
+        .to_string_lossy();
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create output directory {}", dir.display()))?;
+    }
+    // Deleted unless renamed into place
+    let tmp = ScratchFile::new(path.with_file_name(format!(".{}.{}.tmp", file_name, std::process::id())));
+    
+    let result = (|| -> Result<()> {
+        let mut file = std::fs::File::create(tmp.path())?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        std::fs::rename(tmp.path(), path)?;
+        Ok(())
+    })();
+    result.map_err(|e| e.context(format!("Failed to write {}", path.display())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[test]
+    fn test_creates_parent_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nightly/2026/results.json");
+        write_atomic(&path, b"{}").unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"{}");
+        // Only the target is left behind
+        assert_eq!(std::fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+
+        std::fs::write(dir.path().join("file"), b"").unwrap();
+        let err = write_atomic(&dir.path().join("file/results.json"), b"{}").unwrap_err();
+        assert!(err.to_string().starts_with("Failed to create output directory"), "{}", err);
+    }
+
+    #[test]
+    fn test_concurrent_reader_never_sees_partial_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("results.json");
+        let small = vec![b'a'; 64 * 1024];
+        let large = vec![b'b'; 4 * 1024 * 1024];
+        write_atomic(&path, &small).unwrap();
+
+        let done = AtomicBool::new(false);
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                for i in 0..50 {
+                    write_atomic(&path, if i % 2 == 0 { &large } else { &small }).unwrap();
+                }
+                done.store(true, Ordering::Relaxed);
+            });
+
+            while !done.load(Ordering::Relaxed) {
+                let contents = std::fs::read(&path).unwrap();
+                assert!(contents == small || contents == large, "read a partial file of {} bytes", contents.len());
+            }
+        });
+    }
+}
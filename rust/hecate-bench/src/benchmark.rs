@@ -0,0 +1,282 @@
+//! Benchmarks behind a common interface
+//!
+//! A [`Benchmark`] reports a [`MetricSet`] instead of filling in one of the
+//! fixed result structs, so new workloads plug into the same harness,
+//! output formats and comparison without touching the built-in suites.
+//! Registered benchmarks are listed by `hecate-bench list` and run with
+//! `hecate-bench bench <name>`; their metrics are stored under
+//! `custom_results` in result files.
+//!
+//! With the `plugins` feature, executables in the plugin directory are
+//! registered as well; see [`ExternalBenchmark`].
+
+use crate::compare::Direction;
+use crate::stats::{self, Measurement, RunConfig};
+use crate::BenchmarkResults;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Settings shared by every benchmark in a run
+#[derive(Debug, Clone, Copy)]
+pub struct BenchContext {
+    pub config: RunConfig,
+}
+
+/// One figure reported by a benchmark
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomMetric {
+    /// Stable snake_case identifier, unique across all benchmarks
+    pub key: String,
+    pub name: String,
+    pub unit: String,
+    pub value: Measurement,
+    pub direction: Direction,
+}
+
+/// Everything one benchmark reported
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MetricSet {
+    pub metrics: Vec<CustomMetric>,
+}
+
+impl MetricSet {
+    pub fn push(&mut self, key: &str, name: &str, unit: &str, value: Measurement, direction: Direction) {
+        self.metrics.push(CustomMetric {
+            key: key.to_string(),
+            name: name.to_string(),
+            unit: unit.to_string(),
+            value,
+            direction,
+        });
+    }
+
+    pub fn get(&self, key: &str) -> Option<Measurement> {
+        self.metrics.iter().find(|m| m.key == key).map(|m| m.value)
+    }
+}
+
+pub trait Benchmark: Send + Sync {
+    /// Unique `<suite>.<test>` name used on the command line
+    fn name(&self) -> &str;
+
+    /// One line for `hecate-bench list`
+    fn description(&self) -> &str;
+
+    /// Run for about `duration` in total, including warmup runs
+    fn run(&self, duration: Duration, ctx: &BenchContext) -> Result<MetricSet>;
+}
+
+/// Benchmarks the CLI can run by name
+pub struct Registry {
+    benchmarks: Vec<Box<dyn Benchmark>>,
+}
+
+impl Registry {
+    /// The benchmarks built into hecate-bench
+    pub fn builtin() -> Self {
+        let mut registry = Self { benchmarks: Vec::new() };
+        registry.register(Box::new(CpuCrypto));
+        registry
+    }
+
+    /// Add a benchmark, replacing any with the same name
+    pub fn register(&mut self, benchmark: Box<dyn Benchmark>) {
+        self.benchmarks.retain(|b| b.name() != benchmark.name());
+        self.benchmarks.push(benchmark);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Benchmark> {
+        self.benchmarks.iter().find(|b| b.name() == name).map(|b| b.as_ref())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Benchmark> {
+        self.benchmarks.iter().map(|b| b.as_ref())
+    }
+
+    /// Run the benchmark called `name` and store its metrics in `results`
+    pub fn run(&self, name: &str, duration: Duration, ctx: &BenchContext, results: &mut BenchmarkResults) -> Result<()> {
+        let benchmark = self.get(name).ok_or_else(|| {
+            let known: Vec<&str> = self.iter().map(|b| b.name()).collect();
+            anyhow::anyhow!("Unknown benchmark {}; registered benchmarks are {}", name, known.join(", "))
+        })?;
+        let metrics = benchmark.run(duration, ctx)?;
+        results.custom_results.insert(name.to_string(), metrics);
+        Ok(())
+    }
+}
+
+/// SHA-256 throughput over a 1 MiB buffer
+///
+/// The reference implementation of [`Benchmark`]; the CPU suite runs it for
+/// `cpu crypto`.
+pub struct CpuCrypto;
+
+impl CpuCrypto {
+    pub const KEY: &'static str = "cpu_crypto_mb_s";
+}
+
+impl Benchmark for CpuCrypto {
+    fn name(&self) -> &str {
+        "cpu.crypto"
+    }
+
+    fn description(&self) -> &str {
+        "SHA-256 throughput of one core"
+    }
+
+    fn run(&self, duration: Duration, ctx: &BenchContext) -> Result<MetricSet> {
+        use sha2::{Digest, Sha256};
+
+        let per_run = Duration::from_secs(ctx.config.per_run_secs(duration.as_secs()));
+        let data = vec![0u8; 1_048_576]; // 1MB
+
+        let mb_s = stats::measure_blocking(ctx.config, || {
+            let start = Instant::now();
+            let mut bytes_processed = 0u64;
+            while start.elapsed() < per_run {
+                let mut hasher = Sha256::new();
+                hasher.update(&data);
+                std::hint::black_box(hasher.finalize());
+                bytes_processed += data.len() as u64;
+            }
+            Ok(bytes_processed as f64 / start.elapsed().as_secs_f64() / 1_048_576.0)
+        })?;
+
+        let mut metrics = MetricSet::default();
+        metrics.push(Self::KEY, "CPU crypto", "MB/s", mb_s, Direction::HigherIsBetter);
+        Ok(metrics)
+    }
+}
+
+/// An executable benchmark from the plugin directory
+///
+/// It is run as `<executable> <seconds>` and must print a [`MetricSet`] as
+/// JSON on stdout. Its name is the file name, e.g. `db.query-mix`.
+#[cfg(feature = "plugins")]
+pub struct ExternalBenchmark {
+    name: String,
+    description: String,
+    path: std::path::PathBuf,
+}
+
+#[cfg(feature = "plugins")]
+impl ExternalBenchmark {
+    /// Add every executable file in `dir` to `registry`; a missing directory
+    /// has none
+    pub fn load_dir(mut registry: Registry, dir: &std::path::Path) -> Result<Registry> {
+        use anyhow::Context;
+        use std::os::unix::fs::PermissionsExt;
+
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(registry),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read plugin directory {}", dir.display())),
+        };
+
+        for entry in entries {
+            let path = entry?.path();
+            let executable = std::fs::metadata(&path)
+                .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+                .unwrap_or(false);
+            if !executable {
+                continue;
+            }
+            let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+            registry.register(Box::new(Self {
+                description: format!("External benchmark {}", path.display()),
+                name,
+                path,
+            }));
+        }
+        Ok(registry)
+    }
+}
+
+#[cfg(feature = "plugins")]
+impl Benchmark for ExternalBenchmark {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn run(&self, duration: Duration, _ctx: &BenchContext) -> Result<MetricSet> {
+        use anyhow::Context;
+
+        let output = std::process::Command::new(&self.path)
+            .arg(duration.as_secs().to_string())
+            .stderr(std::process::Stdio::inherit())
+            .output()
+            .with_context(|| format!("Failed to run {}", self.path.display()))?;
+        if !output.status.success() {
+            anyhow::bail!("{} failed with {}", self.path.display(), output.status);
+        }
+        serde_json::from_slice(&output.stdout)
+            .with_context(|| format!("{} printed invalid metrics", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compare::metrics;
+    use crate::schema::parse_results;
+
+    /// A domain-specific workload registered from outside the built-in suites
+    struct QueryMix;
+
+    impl Benchmark for QueryMix {
+        fn name(&self) -> &str {
+            "db.query-mix"
+        }
+
+        fn description(&self) -> &str {
+            "Mixed point and range queries"
+        }
+
+        fn run(&self, _duration: Duration, ctx: &BenchContext) -> Result<MetricSet> {
+            let mut metrics = MetricSet::default();
+            let qps = Measurement { value: 5230.0, stddev: 12.5, samples: ctx.config.runs };
+            metrics.push("db_query_mix_qps", "DB query mix", "queries/s", qps, Direction::HigherIsBetter);
+            metrics.push("db_query_mix_p99_ms", "DB query mix p99", "ms", Measurement::single(4.2), Direction::LowerIsBetter);
+            Ok(metrics)
+        }
+    }
+
+    #[test]
+    fn test_registered_benchmark_lands_in_output() {
+        let mut registry = Registry::builtin();
+        registry.register(Box::new(QueryMix));
+        let names: Vec<&str> = registry.iter().map(|b| b.name()).collect();
+        assert_eq!(names, ["cpu.crypto", "db.query-mix"]);
+
+        let mut results = parse_results(include_str!("../tests/fixtures/results-v1.json")).unwrap();
+        let ctx = BenchContext { config: RunConfig { runs: 3, warmup: 0 } };
+        registry.run("db.query-mix", Duration::from_secs(1), &ctx, &mut results).unwrap();
+        assert!(registry.run("db.typo", Duration::from_secs(1), &ctx, &mut results).is_err());
+
+        let qps = metrics(&results).into_iter().find(|m| m.key == "db_query_mix_qps").unwrap();
+        assert_eq!(qps.value, 5230.0);
+        assert_eq!(qps.direction, Direction::HigherIsBetter);
+
+        // Saved files round-trip the custom figures
+        let saved = parse_results(&serde_json::to_string(&results).unwrap()).unwrap();
+        assert_eq!(saved.custom_results["db.query-mix"].get("db_query_mix_qps").unwrap().samples, 3);
+
+        let prometheus = crate::prometheus::render(&results);
+        assert!(prometheus.contains("hecate_bench_db_query_mix_p99_ms{host=\"hecate-ws\"} 4.2\n"), "{}", prometheus);
+        let mut csv = Vec::new();
+        crate::csv_export::write_results(&results, &mut csv).unwrap();
+        assert!(String::from_utf8(csv).unwrap().contains("DB query mix,5230,queries/s\n"));
+    }
+
+    #[test]
+    fn test_cpu_crypto_reports_throughput() {
+        let ctx = BenchContext { config: RunConfig { runs: 1, warmup: 0 } };
+        let metrics = CpuCrypto.run(Duration::from_secs(1), &ctx).unwrap();
+        assert!(metrics.get(CpuCrypto::KEY).unwrap().value > 0.0);
+    }
+}
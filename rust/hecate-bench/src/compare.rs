@@ -0,0 +1,348 @@
+//! Comparison of two result files
+//!
+//! Every metric present in both files is classified against a percentage
+//! threshold, taking into account whether higher or lower values are better.
+
+use crate::schema::load_results;
+use crate::stats::Measurement;
+use crate::BenchmarkResults;
+use anyhow::Result;
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// Whether a larger value of a metric is an improvement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    HigherIsBetter,
+    LowerIsBetter,
+}
+
+/// Classification of a metric's change
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Improvement,
+    Regression,
+    Unchanged,
+}
+
+/// One metric measured in both runs
+#[derive(Debug, Clone)]
+pub struct MetricComparison {
+    pub name: Cow<'static, str>,
+    pub unit: Cow<'static, str>,
+    pub baseline: f64,
+    pub current: f64,
+    /// Relative change, positive when the value went up
+    pub change_percent: f64,
+    pub verdict: Verdict,
+}
+
+/// A single figure from a result file
+#[derive(Debug, Clone)]
+pub struct Metric {
+    /// Stable snake_case identifier, used for exported metric names
+    pub key: Cow<'static, str>,
+    pub name: Cow<'static, str>,
+    pub unit: Cow<'static, str>,
+    pub value: f64,
+    pub direction: Direction,
+}
+
+fn metric(key: &'static str, name: &'static str, unit: &'static str, value: Option<f64>, direction: Direction) -> Option<Metric> {
+    Some(Metric { key: key.into(), name: name.into(), unit: unit.into(), value: value?, direction })
+}
+
+fn value(measurement: &Option<Measurement>) -> Option<f64> {
+    measurement.as_ref().map(|m| m.value)
+}
+
+/// Every metric populated in `results`
+///
+/// Benchmarks that were not run have no figure and are left out.
+pub fn metrics(results: &BenchmarkResults) -> Vec<Metric> {
+    use Direction::*;
+
+    let mut metrics = Vec::new();
+
+    if let Some(cpu) = &results.cpu_results {
+        metrics.extend([
+            metric("cpu_single_thread_score", "CPU single-thread", "ops/s", value(&cpu.single_thread_score), HigherIsBetter),
+            metric("cpu_multi_thread_score", "CPU multi-thread", "ops/s", value(&cpu.multi_thread_score), HigherIsBetter),
+            metric("cpu_float_mflops", "CPU float", "MFLOPS", value(&cpu.float_mflops), HigherIsBetter),
+            metric("cpu_integer_mips", "CPU integer", "MIPS", value(&cpu.integer_mips), HigherIsBetter),
+            metric("cpu_crypto_mb_s", "CPU crypto", "MB/s", value(&cpu.crypto_mb_s), HigherIsBetter),
+            metric("cpu_cache_latency_ns", "CPU cache latency", "ns", value(&cpu.cache_latency_ns), LowerIsBetter),
+            metric("cpu_branch_mpred_s", "CPU branch prediction", "M/s", value(&cpu.branch_mpred_s), HigherIsBetter),
+        ].into_iter().flatten());
+    }
+
+    if let Some(gpu) = &results.gpu_results {
+        metrics.extend([
+            metric("gpu_compute_gflops", "GPU compute", "GFLOPS", gpu.cuda_gflops, HigherIsBetter),
+            metric("gpu_matmul_tflops", "GPU matmul", "TFLOPS", gpu.tensor_tflops, HigherIsBetter),
+            metric("gpu_memory_bandwidth_gb_s", "GPU memory bandwidth", "GB/s", gpu.memory_bandwidth_gb_s, HigherIsBetter),
+            metric("gpu_raytracing_mrays_s", "GPU ray tracing", "Mrays/s", gpu.raytracing_mrays_s, HigherIsBetter),
+            metric("gpu_inference_images_s", "GPU inference", "images/s", gpu.inference_images_s, HigherIsBetter),
+        ].into_iter().flatten());
+    }
+
+    if let Some(mem) = &results.memory_results {
+        metrics.extend([
+            metric("memory_seq_read_gb_s", "Memory sequential read", "GB/s", mem.seq_read_gb_s, HigherIsBetter),
+            metric("memory_seq_write_gb_s", "Memory sequential write", "GB/s", mem.seq_write_gb_s, HigherIsBetter),
+            metric("memory_random_access_mops", "Memory random access", "Mops", mem.random_access_mops, HigherIsBetter),
+            metric("memory_latency_ns", "Memory latency", "ns", mem.latency_ns, LowerIsBetter),
+            metric("memory_bandwidth_gb_s", "Memory bandwidth", "GB/s", mem.bandwidth_gb_s, HigherIsBetter),
+        ].into_iter().flatten());
+    }
+
+    if let Some(disk) = &results.disk_results {
+        metrics.extend([
+            metric("disk_seq_read_mb_s", "Disk sequential read", "MB/s", disk.seq_read_mb_s, HigherIsBetter),
+            metric("disk_seq_write_mb_s", "Disk sequential write", "MB/s", disk.seq_write_mb_s, HigherIsBetter),
+            metric("disk_random_4k_read_iops", "Disk 4K random read", "IOPS", disk.random_4k_read_iops.map(|iops| iops as f64), HigherIsBetter),
+            metric("disk_random_4k_write_iops", "Disk 4K random write", "IOPS", disk.random_4k_write_iops.map(|iops| iops as f64), HigherIsBetter),
+        ].into_iter().flatten());
+    }
+
+    if let Some(net) = &results.network_results {
+        metrics.extend([
+            metric("network_bandwidth_mbps", "Network bandwidth", "Mbps", net.bandwidth_mbps, HigherIsBetter),
+            metric("network_latency_ms", "Network latency", "ms", net.latency_ms, LowerIsBetter),
+            metric("network_jitter_ms", "Network jitter", "ms", net.jitter_ms, LowerIsBetter),
+            metric("network_packet_loss_percent", "Network packet loss", "%", net.packet_loss_percent, LowerIsBetter),
+        ].into_iter().flatten());
+    }
+
+    if let Some(ai) = &results.ai_results {
+        metrics.extend([
+            metric("ai_matmul_naive_gflops", "AI matmul (naive)", "GFLOPS", ai.matmul_naive_gflops, HigherIsBetter),
+            metric("ai_conv_naive_gops", "AI convolution (naive)", "GOPS", ai.conv_naive_gops, HigherIsBetter),
+            metric("ai_matmul_gflops", "AI matmul", "GFLOPS", ai.matmul_gflops, HigherIsBetter),
+            metric("ai_conv_gops", "AI convolution", "GOPS", ai.conv_gops, HigherIsBetter),
+            metric("ai_transformer_tokens_s", "AI transformer", "tokens/s", ai.transformer_tokens_s, HigherIsBetter),
+            metric("ai_training_samples_s", "AI training", "samples/s", ai.training_samples_s, HigherIsBetter),
+        ].into_iter().flatten());
+    }
+
+    // Registered benchmarks; a key already reported above wins
+    for set in results.custom_results.values() {
+        for custom in &set.metrics {
+            if !metrics.iter().any(|m| m.key == custom.key) {
+                metrics.push(Metric {
+                    key: custom.key.clone().into(),
+                    name: custom.name.clone().into(),
+                    unit: custom.unit.clone().into(),
+                    value: custom.value.value,
+                    direction: custom.direction,
+                });
+            }
+        }
+    }
+
+    metrics
+}
+
+/// Compare every metric present in both `baseline` and `current`
+///
+/// Changes within `threshold_percent` either way count as unchanged.
+pub fn compare(baseline: &BenchmarkResults, current: &BenchmarkResults, threshold_percent: f64) -> Vec<MetricComparison> {
+    let current_metrics = metrics(current);
+
+    metrics(baseline)
+        .into_iter()
+        .filter_map(|base| {
+            let curr = current_metrics.iter().find(|m| m.name == base.name)?;
+            // Any change from zero is infinitely large
+            let change_percent = if curr.value == base.value {
+                0.0
+            } else {
+                (curr.value - base.value) / base.value.abs() * 100.0
+            };
+
+            let better = match base.direction {
+                Direction::HigherIsBetter => change_percent,
+                Direction::LowerIsBetter => -change_percent,
+            };
+            let verdict = if better > threshold_percent {
+                Verdict::Improvement
+            } else if better < -threshold_percent {
+                Verdict::Regression
+            } else {
+                Verdict::Unchanged
+            };
+
+            Some(MetricComparison {
+                name: base.name,
+                unit: base.unit,
+                baseline: base.value,
+                current: curr.value,
+                change_percent,
+                verdict,
+            })
+        })
+        .collect()
+}
+
+/// Load two result files and compare them
+pub fn compare_files(baseline_path: &str, current_path: &str, threshold_percent: f64) -> Result<Vec<MetricComparison>> {
+    let baseline = load_results(baseline_path)?;
+    let current = load_results(current_path)?;
+    Ok(compare(&baseline, &current, threshold_percent))
+}
+
+/// Process exit code for a comparison: 1 if regressions should fail the run
+/// and there are any
+pub fn exit_code(comparisons: &[MetricComparison], fail_on_regression: bool) -> i32 {
+    let regressed = comparisons.iter().any(|c| c.verdict == Verdict::Regression);
+    if fail_on_regression && regressed {
+        1
+    } else {
+        0
+    }
+}
+
+pub fn print_comparison(comparisons: &[MetricComparison], threshold_percent: f64) {
+    println!("{}", "=== Performance Comparison ===".bright_cyan());
+
+    if comparisons.is_empty() {
+        println!("\nNo metrics present in both result files");
+        return;
+    }
+
+    println!();
+    for c in comparisons {
+        let change = format!("{:+.1}%", c.change_percent);
+        let change = match c.verdict {
+            Verdict::Improvement => change.green(),
+            Verdict::Regression => change.red(),
+            Verdict::Unchanged => change.normal(),
+        };
+        println!(
+            "  {:<26} {:>12.2} -> {:>12.2} {:<9} {}",
+            c.name, c.baseline, c.current, c.unit, change
+        );
+    }
+
+    let count = |verdict| comparisons.iter().filter(|c| c.verdict == verdict).count();
+    println!(
+        "\n{} improved, {} regressed, {} unchanged (threshold ±{}%)",
+        count(Verdict::Improvement).to_string().green(),
+        count(Verdict::Regression).to_string().red(),
+        count(Verdict::Unchanged),
+        threshold_percent
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MemoryResults, NetworkResults, SystemInfo};
+
+    fn results(single: f64, multi: f64, memory_latency_ns: f64) -> BenchmarkResults {
+        BenchmarkResults {
+            schema_version: crate::schema::CURRENT_VERSION,
+            timestamp: chrono::Utc::now(),
+            profile: None,
+            system_info: SystemInfo {
+                hostname: "bench".into(),
+                os: "HecateOS".into(),
+                kernel: "6.8.0".into(),
+                cpu_model: "Test CPU".into(),
+                cpu_cores: 8,
+                cpu_physical_cores: 8,
+                cpu_threads: 8,
+                memory_total_gb: 32.0,
+                gpu_info: vec![],
+            },
+            cpu_results: Some(crate::CpuResults {
+                single_thread_score: Some(Measurement::single(single)),
+                multi_thread_score: Some(Measurement::single(multi)),
+                ..Default::default()
+            }),
+            gpu_results: None,
+            memory_results: Some(MemoryResults {
+                seq_read_gb_s: Some(20.0),
+                seq_write_gb_s: Some(18.0),
+                random_access_mops: Some(100.0),
+                latency_ns: Some(memory_latency_ns),
+                bandwidth_gb_s: Some(19.0),
+                latency_curve: vec![],
+            }),
+            disk_results: None,
+            network_results: None,
+            ai_results: None,
+            custom_results: Default::default(),
+        }
+    }
+
+    fn write(dir: &tempfile::TempDir, name: &str, results: &BenchmarkResults) -> String {
+        let path = dir.path().join(name);
+        std::fs::write(&path, serde_json::to_string(results).unwrap()).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    fn verdict(comparisons: &[MetricComparison], name: &str) -> Verdict {
+        comparisons.iter().find(|c| c.name == name).unwrap().verdict
+    }
+
+    #[test]
+    fn test_regression_fails_comparison() {
+        let dir = tempfile::tempdir().unwrap();
+        let baseline = write(&dir, "baseline.json", &results(1000.0, 8000.0, 80.0));
+        // Single-thread drops 10%, multi-thread rises 2%, memory latency rises 20%
+        let current = write(&dir, "current.json", &results(900.0, 8160.0, 96.0));
+
+        let comparisons = compare_files(&baseline, &current, 5.0).unwrap();
+        assert_eq!(verdict(&comparisons, "CPU single-thread"), Verdict::Regression);
+        assert_eq!(verdict(&comparisons, "CPU multi-thread"), Verdict::Unchanged);
+        assert_eq!(verdict(&comparisons, "Memory latency"), Verdict::Regression);
+        assert_eq!(verdict(&comparisons, "Memory bandwidth"), Verdict::Unchanged);
+        // Benchmarks that were not run are not compared
+        assert!(comparisons.iter().all(|c| c.name != "CPU float"));
+
+        assert_eq!(exit_code(&comparisons, true), 1);
+        assert_eq!(exit_code(&comparisons, false), 0);
+
+        // A looser threshold tolerates the single-thread drop but not the latency
+        let loose = compare_files(&baseline, &current, 15.0).unwrap();
+        assert_eq!(verdict(&loose, "CPU single-thread"), Verdict::Unchanged);
+        assert_eq!(exit_code(&loose, true), 1);
+    }
+
+    #[test]
+    fn test_improvements_pass_comparison() {
+        let dir = tempfile::tempdir().unwrap();
+        let baseline = write(&dir, "baseline.json", &results(1000.0, 8000.0, 80.0));
+        let current = write(&dir, "current.json", &results(1200.0, 8000.0, 60.0));
+
+        let comparisons = compare_files(&baseline, &current, 5.0).unwrap();
+        assert_eq!(verdict(&comparisons, "CPU single-thread"), Verdict::Improvement);
+        // Lower latency is better
+        assert_eq!(verdict(&comparisons, "Memory latency"), Verdict::Improvement);
+        assert_eq!(exit_code(&comparisons, true), 0);
+    }
+
+    #[test]
+    fn test_zero_figures_are_compared() {
+        let loss = |percent| {
+            let mut results = results(1000.0, 8000.0, 80.0);
+            results.network_results = Some(NetworkResults {
+                packet_loss_percent: Some(percent),
+                ..Default::default()
+            });
+            results
+        };
+
+        let comparisons = compare(&loss(0.0), &loss(0.0), 5.0);
+        assert_eq!(verdict(&comparisons, "Network packet loss"), Verdict::Unchanged);
+        assert_eq!(comparisons.iter().find(|c| c.name == "Network packet loss").unwrap().change_percent, 0.0);
+        // Only packet loss was probed
+        assert!(comparisons.iter().all(|c| c.name != "Network latency"));
+
+        let comparisons = compare(&loss(0.0), &loss(2.0), 5.0);
+        assert_eq!(verdict(&comparisons, "Network packet loss"), Verdict::Regression);
+    }
+}
@@ -0,0 +1,119 @@
+//! CSV output of benchmark results
+//!
+//! A `Metric,Value,Unit` table: run metadata first, then one row per
+//! populated metric in the same order as `compare`.
+
+use crate::compare::metrics;
+use crate::latency::format_size;
+use crate::BenchmarkResults;
+use anyhow::Result;
+use std::io::Write;
+
+/// Write `results` as CSV to `out`
+pub fn write_results<W: Write>(results: &BenchmarkResults, out: W) -> Result<()> {
+    // Fields with commas or quotes, like CPU model names, are quoted
+    let mut wtr = csv::Writer::from_writer(out);
+    let info = &results.system_info;
+
+    wtr.write_record(["Metric", "Value", "Unit"])?;
+
+    wtr.write_record(["Hostname", &info.hostname, ""])?;
+    wtr.write_record(["Timestamp", &results.timestamp.to_rfc3339(), ""])?;
+    if let Some(profile) = &results.profile {
+        wtr.write_record(["Profile", profile, ""])?;
+    }
+    wtr.write_record(["OS", &info.os, ""])?;
+    wtr.write_record(["Kernel", &info.kernel, ""])?;
+    wtr.write_record(["CPU", &info.cpu_model, ""])?;
+    wtr.write_record(["Memory", &format!("{:.2}", info.memory_total_gb), "GB"])?;
+
+    for metric in metrics(results) {
+        wtr.write_record([metric.name.as_ref(), &metric.value.to_string(), metric.unit.as_ref()])?;
+    }
+
+    if let Some(cpu) = &results.cpu_results {
+        for (core, score) in cpu.per_core_scores.iter().enumerate() {
+            wtr.write_record([&format!("CPU core {}", core), &score.to_string(), "ops/s"])?;
+        }
+        for point in &cpu.scaling {
+            wtr.write_record([
+                &format!("CPU scaling {} threads", point.threads),
+                &(point.efficiency * 100.0).to_string(),
+                "%",
+            ])?;
+        }
+    }
+
+    if let Some(mem) = &results.memory_results {
+        for point in &mem.latency_curve {
+            wtr.write_record([
+                &format!("Memory latency {}", format_size(point.working_set_bytes)),
+                &point.latency_ns.to_string(),
+                "ns",
+            ])?;
+        }
+    }
+
+    wtr.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::parse_results;
+    use crate::AiResults;
+    use crate::latency::LatencyPoint;
+
+    const V1_FIXTURE: &str = include_str!("../tests/fixtures/results-v1.json");
+
+    #[test]
+    fn test_every_metric_is_written() {
+        let mut results = parse_results(V1_FIXTURE).unwrap();
+        results.system_info.cpu_model = "AMD Ryzen 9 7950X, 16-Core".into();
+        results.ai_results = Some(AiResults {
+            matmul_gflops: Some(410.0),
+            conv_gops: Some(220.0),
+            matmul_naive_gflops: Some(3.5),
+            conv_naive_gops: None,
+            transformer_tokens_s: Some(95.0),
+            training_samples_s: Some(1200.0),
+        });
+        results.cpu_results.as_mut().unwrap().per_core_scores = vec![1800.0, 1795.0];
+        results.memory_results.as_mut().unwrap().latency_curve = vec![
+            LatencyPoint { working_set_bytes: 16 * 1024, latency_ns: 1.1 },
+            LatencyPoint { working_set_bytes: 256 * 1024 * 1024, latency_ns: 82.5 },
+        ];
+
+        let mut out = Vec::new();
+        write_results(&results, &mut out).unwrap();
+
+        let mut reader = csv::Reader::from_reader(out.as_slice());
+        assert_eq!(reader.headers().unwrap().iter().collect::<Vec<_>>(), ["Metric", "Value", "Unit"]);
+        let rows: Vec<(String, String, String)> = reader.records()
+            .map(|r| {
+                let r = r.unwrap();
+                (r[0].to_string(), r[1].to_string(), r[2].to_string())
+            })
+            .collect();
+        let value = |name: &str| rows.iter().find(|r| r.0 == name).map(|r| r.1.clone());
+
+        // The comma in the model name survives quoting
+        assert_eq!(value("CPU").as_deref(), Some("AMD Ryzen 9 7950X, 16-Core"));
+        assert_eq!(value("Hostname").as_deref(), Some("hecate-ws"));
+        assert_eq!(value("Kernel").as_deref(), Some("6.6.15"));
+        assert!(value("Timestamp").unwrap().starts_with("2024-03-02T10:15:00"));
+
+        for metric in metrics(&results) {
+            let row = rows.iter().find(|r| r.0 == metric.name)
+                .unwrap_or_else(|| panic!("missing row for {}", metric.name));
+            assert_eq!(row.1.parse::<f64>().unwrap(), metric.value);
+            assert_eq!(row.2, metric.unit);
+        }
+        for name in ["CPU single-thread", "GPU compute", "Memory latency", "Disk 4K random write", "Network latency", "AI training"] {
+            assert!(value(name).is_some(), "missing {}", name);
+        }
+        assert_eq!(value("CPU core 1").as_deref(), Some("1795"));
+        assert_eq!(value("Memory latency 256 MiB").as_deref(), Some("82.5"));
+    }
+}
@@ -0,0 +1,278 @@
+//! Disk benchmarks that measure the device rather than the page cache
+//!
+//! Test files are opened with `O_DIRECT` so reads and writes go straight to
+//! the device. Direct I/O requires buffers, offsets and lengths aligned to
+//! the logical block size; everything here uses multiples of [`ALIGN`],
+//! which covers 512-byte and 4K-sector devices. Some filesystems (tmpfs,
+//! older overlayfs, some network filesystems) reject `O_DIRECT`; there the
+//! files are opened normally, cached pages are dropped with
+//! `posix_fadvise(POSIX_FADV_DONTNEED)` before each read pass and writes are
+//! fsynced, which is close but not identical. On non-Linux platforms only
+//! the fsync is available, so reads may still be served from cache.
+
+use crate::scratch::ScratchFile;
+use anyhow::{Context, Result};
+use std::alloc::{alloc_zeroed, dealloc, Layout};
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Alignment of buffers, offsets and transfer sizes
+pub const ALIGN: usize = 4096;
+
+/// Size of the sequential and random test files; a multiple of [`ALIGN`]
+pub const FILE_SIZE: usize = 256 * 1024 * 1024;
+
+/// Transfer size for sequential passes
+const SEQ_CHUNK: usize = 1024 * 1024;
+
+/// Transfer size for random I/O
+const RANDOM_BLOCK: usize = 4096;
+
+/// Heap buffer aligned for direct I/O
+struct AlignedBuffer {
+    ptr: *mut u8,
+    layout: Layout,
+}
+
+// The buffer owns its allocation exclusively
+unsafe impl Send for AlignedBuffer {}
+
+impl AlignedBuffer {
+    fn new(len: usize) -> Self {
+        let layout = Layout::from_size_align(len, ALIGN).expect("valid buffer layout");
+        // SAFETY: len is non-zero for every caller
+        let ptr = unsafe { alloc_zeroed(layout) };
+        if ptr.is_null() {
+            std::alloc::handle_alloc_error(layout);
+        }
+        Self { ptr, layout }
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        // SAFETY: ptr points to layout.size() initialized bytes
+        unsafe { std::slice::from_raw_parts(self.ptr, self.layout.size()) }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        // SAFETY: as above, and we hold the only reference
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.layout.size()) }
+    }
+}
+
+impl Drop for AlignedBuffer {
+    fn drop(&mut self) {
+        // SAFETY: allocated with this layout in new
+        unsafe { dealloc(self.ptr, self.layout) }
+    }
+}
+
+/// A benchmark file opened for direct I/O where possible, deleted when
+/// dropped or on interrupt
+struct TestFile {
+    file: File,
+    direct: bool,
+    _scratch: ScratchFile,
+}
+
+impl TestFile {
+    /// Create or open `name` in `dir` for reading and writing
+    fn open(dir: &Path, name: &str) -> Result<Self> {
+        let scratch = ScratchFile::new(dir.join(name));
+        let path = scratch.path();
+
+        match open_options(true).open(path) {
+            Ok(file) => Ok(Self { file, direct: true, _scratch: scratch }),
+            Err(e) => {
+                tracing::debug!("O_DIRECT unavailable for {} ({}), dropping caches instead", path.display(), e);
+                let file = open_options(false)
+                    .open(path)
+                    .with_context(|| format!("Failed to open {}", path.display()))?;
+                Ok(Self { file, direct: false, _scratch: scratch })
+            }
+        }
+    }
+
+    /// Create the file at [`FILE_SIZE`] with every block written and synced,
+    /// so reads hit allocated extents rather than holes
+    fn create(dir: &Path, name: &str) -> Result<Self> {
+        let test_file = Self::open(dir, name)?;
+        let mut buffer = AlignedBuffer::new(SEQ_CHUNK);
+        fill(buffer.as_mut_slice());
+
+        for offset in (0..FILE_SIZE).step_by(SEQ_CHUNK) {
+            write_at(&test_file.file, buffer.as_slice(), offset as u64)?;
+        }
+        test_file.file.sync_all()?;
+
+        Ok(test_file)
+    }
+
+    /// Evict the file from the page cache when it was not opened direct
+    fn drop_cache(&self) {
+        if self.direct {
+            return;
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::unix::io::AsRawFd;
+            // SAFETY: the descriptor is valid for the lifetime of self.file
+            unsafe {
+                libc::posix_fadvise(self.file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED);
+            }
+        }
+    }
+}
+
+fn open_options(direct: bool) -> OpenOptions {
+    let mut options = OpenOptions::new();
+    options.read(true).write(true).create(true);
+
+    #[cfg(target_os = "linux")]
+    if direct {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.custom_flags(libc::O_DIRECT);
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = direct;
+
+    options
+}
+
+/// Non-zero, non-repeating-page content so compressing or deduplicating
+/// filesystems cannot skip the work
+fn fill(buffer: &mut [u8]) {
+    let mut state = 0x9e3779b97f4a7c15u64;
+    for chunk in buffer.chunks_mut(8) {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        chunk.copy_from_slice(&state.to_le_bytes()[..chunk.len()]);
+    }
+}
+
+fn read_at(file: &File, buffer: &mut [u8], offset: u64) -> std::io::Result<()> {
+    use std::os::unix::fs::FileExt;
+    file.read_exact_at(buffer, offset)
+}
+
+fn write_at(file: &File, buffer: &[u8], offset: u64) -> std::io::Result<()> {
+    use std::os::unix::fs::FileExt;
+    file.write_all_at(buffer, offset)
+}
+
+/// Sequential read throughput of `dir`'s device, in MB/s
+pub fn seq_read(dir: &Path, duration: Duration) -> Result<f64> {
+    let test_file = TestFile::create(dir, "hecate_bench_read.tmp")?;
+    let mut buffer = AlignedBuffer::new(SEQ_CHUNK);
+
+    let start = Instant::now();
+    let mut bytes = 0u64;
+    while bytes == 0 || start.elapsed() < duration {
+        test_file.drop_cache();
+        for offset in (0..FILE_SIZE).step_by(SEQ_CHUNK) {
+            read_at(&test_file.file, buffer.as_mut_slice(), offset as u64)?;
+            bytes += SEQ_CHUNK as u64;
+        }
+    }
+
+    Ok(bytes as f64 / start.elapsed().as_secs_f64() / 1_048_576.0)
+}
+
+/// Sequential write throughput of `dir`'s device including the final
+/// fsync of every pass, in MB/s
+pub fn seq_write(dir: &Path, duration: Duration) -> Result<f64> {
+    let test_file = TestFile::open(dir, "hecate_bench_write.tmp")?;
+    let mut buffer = AlignedBuffer::new(SEQ_CHUNK);
+    fill(buffer.as_mut_slice());
+
+    let start = Instant::now();
+    let mut bytes = 0u64;
+    while bytes == 0 || start.elapsed() < duration {
+        for offset in (0..FILE_SIZE).step_by(SEQ_CHUNK) {
+            write_at(&test_file.file, buffer.as_slice(), offset as u64)?;
+            bytes += SEQ_CHUNK as u64;
+        }
+        test_file.file.sync_data()?;
+    }
+
+    Ok(bytes as f64 / start.elapsed().as_secs_f64() / 1_048_576.0)
+}
+
+/// Random 4K read operations per second
+pub fn random_read(dir: &Path, duration: Duration) -> Result<u64> {
+    let test_file = TestFile::create(dir, "hecate_bench_random.tmp")?;
+    test_file.drop_cache();
+    let mut buffer = AlignedBuffer::new(RANDOM_BLOCK);
+
+    random_ops(duration, |offset| read_at(&test_file.file, buffer.as_mut_slice(), offset))
+}
+
+/// Random 4K write operations per second, each synced to the device
+pub fn random_write(dir: &Path, duration: Duration) -> Result<u64> {
+    let test_file = TestFile::create(dir, "hecate_bench_random_write.tmp")?;
+    let mut buffer = AlignedBuffer::new(RANDOM_BLOCK);
+    fill(buffer.as_mut_slice());
+
+    random_ops(duration, |offset| {
+        write_at(&test_file.file, buffer.as_slice(), offset)?;
+        test_file.file.sync_data()
+    })
+}
+
+/// Run `op` at random block-aligned offsets within the test file for
+/// `duration` and return the rate
+fn random_ops(duration: Duration, mut op: impl FnMut(u64) -> std::io::Result<()>) -> Result<u64> {
+    use rand::prelude::*;
+    let mut rng = thread_rng();
+    let blocks = FILE_SIZE / RANDOM_BLOCK;
+
+    let start = Instant::now();
+    let mut operations = 0u64;
+    while operations == 0 || start.elapsed() < duration {
+        for _ in 0..100 {
+            let offset = rng.gen_range(0..blocks) * RANDOM_BLOCK;
+            op(offset as u64)?;
+            operations += 1;
+        }
+    }
+
+    Ok((operations as f64 / start.elapsed().as_secs_f64()) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sizes_are_aligned() {
+        assert_eq!(FILE_SIZE % ALIGN, 0);
+        assert_eq!(SEQ_CHUNK % ALIGN, 0);
+        assert_eq!(RANDOM_BLOCK % ALIGN, 0);
+        assert_eq!(FILE_SIZE % SEQ_CHUNK, 0);
+    }
+
+    #[test]
+    fn test_buffers_are_aligned() {
+        let buffer = AlignedBuffer::new(SEQ_CHUNK);
+        assert_eq!(buffer.as_slice().as_ptr() as usize % ALIGN, 0);
+        assert_eq!(buffer.as_slice().len(), SEQ_CHUNK);
+    }
+
+    #[tokio::test]
+    #[ignore = "writes a 256 MiB file and needs a temp dir on a real disk, not tmpfs"]
+    async fn test_read_is_not_served_from_memory() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let disk_mb_s = seq_read(dir.path(), Duration::from_secs(1)).unwrap();
+        let memory_gb_s = crate::benchmark_memory_bandwidth(1).await.unwrap();
+
+        assert!(disk_mb_s > 0.0);
+        assert!(
+            disk_mb_s < memory_gb_s * 1024.0,
+            "disk read {:.0} MB/s is not below memory bandwidth {:.1} GB/s",
+            disk_mb_s, memory_gb_s
+        );
+    }
+}
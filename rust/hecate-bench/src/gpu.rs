@@ -0,0 +1,257 @@
+//! GPU benchmarks using wgpu compute shaders
+//!
+//! Runs on any Vulkan, Metal or DX12 capable GPU, so NVIDIA, AMD and Intel
+//! cards are measured the same way without CUDA.
+
+use anyhow::{Context, Result};
+use std::borrow::Cow;
+use std::time::{Duration, Instant};
+
+/// Invocations per FMA dispatch
+const FMA_INVOCATIONS: u32 = 256 * 4096;
+
+/// FMA iterations per invocation, each on a vec4; matches the loop in
+/// `FMA_SHADER`
+const FMA_ITERATIONS: u32 = 1024;
+
+const FMA_SHADER: &str = r#"
+@group(0) @binding(0) var<storage, read_write> data: array<vec4<f32>>;
+
+@compute @workgroup_size(256)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    var a = data[id.x];
+    let b = vec4<f32>(1.0001, 0.9999, 1.0002, 0.9998);
+    let c = vec4<f32>(0.0001, 0.0002, 0.0003, 0.0004);
+    for (var i = 0u; i < 1024u; i = i + 1u) {
+        a = fma(a, b, c);
+    }
+    data[id.x] = a;
+}
+"#;
+
+/// Matrix dimension for the matmul benchmark
+const MATMUL_N: u32 = 1024;
+
+const MATMUL_SHADER: &str = r#"
+@group(0) @binding(0) var<storage, read> a: array<f32>;
+@group(0) @binding(1) var<storage, read> b: array<f32>;
+@group(0) @binding(2) var<storage, read_write> c: array<f32>;
+
+const N: u32 = 1024u;
+
+@compute @workgroup_size(16, 16)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let row = id.y;
+    let col = id.x;
+    var sum = 0.0;
+    for (var k = 0u; k < N; k = k + 1u) {
+        sum = sum + a[row * N + k] * b[k * N + col];
+    }
+    c[row * N + col] = sum;
+}
+"#;
+
+/// Bytes per buffer in the copy bandwidth benchmark
+const COPY_BYTES: u64 = 128 * 1024 * 1024;
+
+/// A GPU device ready to run benchmarks
+pub struct GpuBench {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pub name: String,
+}
+
+impl GpuBench {
+    /// Open the highest performance adapter, failing if there is none
+    pub async fn new() -> Result<Self> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                compatible_surface: None,
+                force_fallback_adapter: false,
+            })
+            .await
+            .ok_or_else(|| anyhow::anyhow!("No GPU adapter available"))?;
+
+        let name = adapter.get_info().name;
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: Some("hecate-bench"),
+                    required_features: wgpu::Features::empty(),
+                    required_limits: adapter.limits(),
+                },
+                None,
+            )
+            .await
+            .with_context(|| format!("Failed to open GPU {}", name))?;
+
+        Ok(Self { device, queue, name })
+    }
+
+    /// Compute throughput of a fused multiply-add loop, in GFLOPS
+    pub fn fma_gflops(&self, duration: Duration) -> Result<f64> {
+        let buffer = self.storage_buffer("fma", FMA_INVOCATIONS as u64 * 16);
+        let pipeline = self.pipeline("fma", FMA_SHADER);
+        let bind_group = self.bind_group(&pipeline, &[&buffer]);
+
+        let (runs, elapsed) = self.repeat(duration, |encoder| {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: None,
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(FMA_INVOCATIONS / 256, 1, 1);
+        });
+
+        // Each iteration is one FMA (two flops) on four lanes
+        let flops = runs as f64 * FMA_INVOCATIONS as f64 * FMA_ITERATIONS as f64 * 4.0 * 2.0;
+        Ok(flops / elapsed.as_secs_f64() / 1e9)
+    }
+
+    /// Device memory bandwidth of buffer-to-buffer copies, in GB/s
+    pub fn copy_bandwidth_gb_s(&self, duration: Duration) -> Result<f64> {
+        let src = self.storage_buffer("copy-src", COPY_BYTES);
+        let dst = self.storage_buffer("copy-dst", COPY_BYTES);
+
+        let (runs, elapsed) = self.repeat(duration, |encoder| {
+            encoder.copy_buffer_to_buffer(&src, 0, &dst, 0, COPY_BYTES);
+        });
+
+        // Every byte is read once and written once
+        let bytes = runs as f64 * COPY_BYTES as f64 * 2.0;
+        Ok(bytes / elapsed.as_secs_f64() / 1e9)
+    }
+
+    /// Throughput of a dense f32 matrix multiplication, in GFLOPS
+    pub fn matmul_gflops(&self, duration: Duration) -> Result<f64> {
+        let bytes = MATMUL_N as u64 * MATMUL_N as u64 * 4;
+        let a = self.storage_buffer("matmul-a", bytes);
+        let b = self.storage_buffer("matmul-b", bytes);
+        let c = self.storage_buffer("matmul-c", bytes);
+        let pipeline = self.pipeline("matmul", MATMUL_SHADER);
+        let bind_group = self.bind_group(&pipeline, &[&a, &b, &c]);
+
+        let (runs, elapsed) = self.repeat(duration, |encoder| {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: None,
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(MATMUL_N / 16, MATMUL_N / 16, 1);
+        });
+
+        let n = MATMUL_N as f64;
+        Ok(runs as f64 * 2.0 * n * n * n / elapsed.as_secs_f64() / 1e9)
+    }
+
+    /// Keep the GPU busy with the FMA kernel until `stop` returns true,
+    /// returning the number of dispatches
+    pub fn stress(&self, stop: impl Fn() -> bool) -> u64 {
+        let buffer = self.storage_buffer("stress", FMA_INVOCATIONS as u64 * 16);
+        let pipeline = self.pipeline("stress", FMA_SHADER);
+        let bind_group = self.bind_group(&pipeline, &[&buffer]);
+
+        let mut dispatches = 0;
+        while !stop() {
+            let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            {
+                let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: None,
+                    timestamp_writes: None,
+                });
+                pass.set_pipeline(&pipeline);
+                pass.set_bind_group(0, &bind_group, &[]);
+                pass.dispatch_workgroups(FMA_INVOCATIONS / 256, 1, 1);
+            }
+            self.queue.submit(Some(encoder.finish()));
+            // Wait, so `stop` is checked at least once per dispatch
+            let _ = self.device.poll(wgpu::Maintain::Wait);
+            dispatches += 1;
+        }
+        dispatches
+    }
+
+    fn storage_buffer(&self, label: &str, size: u64) -> wgpu::Buffer {
+        self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn pipeline(&self, label: &str, source: &str) -> wgpu::ComputePipeline {
+        let module = self.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(label),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(source)),
+        });
+        self.device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some(label),
+            layout: None,
+            module: &module,
+            entry_point: "main",
+        })
+    }
+
+    fn bind_group(&self, pipeline: &wgpu::ComputePipeline, buffers: &[&wgpu::Buffer]) -> wgpu::BindGroup {
+        let entries: Vec<wgpu::BindGroupEntry> = buffers.iter()
+            .enumerate()
+            .map(|(i, buffer)| wgpu::BindGroupEntry {
+                binding: i as u32,
+                resource: buffer.as_entire_binding(),
+            })
+            .collect();
+
+        self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &entries,
+        })
+    }
+
+    /// Submit the commands recorded by `record` until `duration` has passed,
+    /// after one untimed warmup run
+    ///
+    /// Returns the number of timed runs and the time they took, waiting for
+    /// the GPU to finish each one.
+    fn repeat(&self, duration: Duration, record: impl Fn(&mut wgpu::CommandEncoder)) -> (u64, Duration) {
+        let submit = || {
+            let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            record(&mut encoder);
+            self.queue.submit(Some(encoder.finish()));
+            let _ = self.device.poll(wgpu::Maintain::Wait);
+        };
+
+        // The first submission includes shader compilation and allocation
+        submit();
+
+        let start = Instant::now();
+        let mut runs = 0u64;
+        while runs == 0 || start.elapsed() < duration {
+            submit();
+            runs += 1;
+        }
+
+        (runs, start.elapsed())
+    }
+}
+
+/// Names of the GPUs wgpu can see, excluding software renderers
+pub fn adapter_names() -> Vec<String> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    let mut names: Vec<String> = instance.enumerate_adapters(wgpu::Backends::all())
+        .into_iter()
+        .map(|adapter| adapter.get_info())
+        .filter(|info| info.device_type != wgpu::DeviceType::Cpu)
+        .map(|info| info.name)
+        .collect();
+
+    // The same GPU shows up once per backend
+    names.sort();
+    names.dedup();
+    names
+}
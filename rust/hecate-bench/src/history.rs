@@ -0,0 +1,268 @@
+//! Per-host history of benchmark runs
+//!
+//! Runs stored with `--store` are appended as one JSON line each to
+//! `<dir>/<hostname>.jsonl`, using the same serialization as saved result
+//! files, so every line migrates forward like any other result file.
+//! `trend` reads them back to show how one metric moved over recent runs.
+
+use crate::compare::metrics;
+use crate::schema::parse_results;
+use crate::BenchmarkResults;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Width of the longest bar in an ASCII trend
+const BAR_WIDTH: usize = 40;
+
+/// Where history lives unless `--history-dir` says otherwise:
+/// `$XDG_DATA_HOME/hecate-bench`, falling back to `~/.local/share/hecate-bench`
+pub fn default_dir() -> PathBuf {
+    std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))
+        .unwrap_or_else(|| PathBuf::from("/var/tmp"))
+        .join("hecate-bench")
+}
+
+/// The stored runs of one host
+pub struct History {
+    path: PathBuf,
+}
+
+/// One run's value of a metric
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrendPoint {
+    pub timestamp: DateTime<Utc>,
+    pub value: f64,
+}
+
+/// How a metric moved over the most recent runs, oldest first
+#[derive(Debug, Clone)]
+pub struct Trend {
+    pub key: String,
+    pub name: String,
+    pub unit: String,
+    pub points: Vec<TrendPoint>,
+}
+
+impl History {
+    /// History of `host` kept under `dir`
+    pub fn for_host(dir: &Path, host: &str) -> Self {
+        let file_name: String = host.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' })
+            .collect();
+        Self { path: dir.join(format!("{}.jsonl", file_name)) }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append a run to the history
+    pub fn append(&self, results: &BenchmarkResults) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        // A single write of the whole line, so concurrent runs cannot interleave
+        let mut line = serde_json::to_string(results)?;
+        line.push('\n');
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .with_context(|| format!("Failed to append to {}", self.path.display()))
+    }
+
+    /// Every stored run, oldest first; an empty history if nothing was stored yet
+    ///
+    /// Lines that don't parse, such as one cut short by a crash, are skipped
+    /// with a warning so one bad run doesn't hide the rest.
+    pub fn load(&self) -> Result<Vec<BenchmarkResults>> {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", self.path.display())),
+        };
+
+        Ok(content.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .filter_map(|(number, line)| match parse_results(line) {
+                Ok(results) => Some(results),
+                Err(e) => {
+                    tracing::warn!("Skipping invalid run at {}:{}: {:#}", self.path.display(), number + 1, e);
+                    None
+                }
+            })
+            .collect())
+    }
+
+    /// The last `last` runs that measured `metric`
+    ///
+    /// `metric` is a metric key such as `cpu_single_thread_score`, or any
+    /// unambiguous prefix of one, with `.` or `-` accepted for `_`
+    /// (`cpu.single_thread`).
+    pub fn trend(&self, metric: &str, last: usize) -> Result<Trend> {
+        let runs = self.load()?;
+        let wanted = metric.replace(['.', '-'], "_");
+
+        let mut keys: Vec<String> = runs.iter()
+            .flat_map(metrics)
+            .map(|m| m.key.into_owned())
+            .filter(|key| key.starts_with(&wanted))
+            .collect();
+        keys.sort_unstable();
+        keys.dedup();
+        let exact = keys.iter().find(|key| **key == wanted).cloned();
+        let key = match (exact, keys.as_slice()) {
+            (Some(key), _) => key,
+            (None, [key]) => key.clone(),
+            (None, []) => anyhow::bail!("No stored run in {} measured {}", self.path.display(), metric),
+            (None, _) => anyhow::bail!("{} is ambiguous: {}", metric, keys.join(", ")),
+        };
+
+        let mut name = String::new();
+        let mut unit = String::new();
+        let mut points: Vec<TrendPoint> = runs.iter()
+            .filter_map(|run| {
+                let found = metrics(run).into_iter().find(|m| m.key == key)?;
+                name = found.name.into_owned();
+                unit = found.unit.into_owned();
+                Some(TrendPoint { timestamp: run.timestamp, value: found.value })
+            })
+            .collect();
+        points.drain(..points.len().saturating_sub(last));
+
+        Ok(Trend { key, name, unit, points })
+    }
+}
+
+/// Render a trend as one bar per run, scaled to the largest value
+pub fn render_ascii(trend: &Trend) -> String {
+    let mut out = format!("{} ({}), last {} runs\n", trend.name, trend.unit, trend.points.len());
+    let max = trend.points.iter().map(|p| p.value).fold(0.0, f64::max);
+    let first = trend.points.first().map(|p| p.value);
+
+    for point in &trend.points {
+        let width = if max > 0.0 { (point.value / max * BAR_WIDTH as f64).round() as usize } else { 0 };
+        let change = match first {
+            Some(first) if first != 0.0 => format!("{:+.1}%", (point.value - first) / first * 100.0),
+            _ => String::new(),
+        };
+        out.push_str(&format!(
+            "{}  {:>14.2}  {:<width$}  {}\n",
+            point.timestamp.format("%Y-%m-%d %H:%M"),
+            point.value,
+            "#".repeat(width),
+            change,
+            width = BAR_WIDTH,
+        ));
+    }
+    out
+}
+
+/// Write a trend as CSV with one row per run
+pub fn write_csv<W: Write>(trend: &Trend, out: W) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+    writer.write_record(["Timestamp", &trend.key, "Unit"])?;
+    for point in &trend.points {
+        writer.write_record([point.timestamp.to_rfc3339(), point.value.to_string(), trend.unit.clone()])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::Measurement;
+    use chrono::TimeZone;
+
+    fn run(day: u32, single: f64) -> BenchmarkResults {
+        let mut results = parse_results(include_str!("../tests/fixtures/results-v1.json")).unwrap();
+        results.timestamp = Utc.with_ymd_and_hms(2026, 3, day, 2, 0, 0).unwrap();
+        results.cpu_results.as_mut().unwrap().single_thread_score = Some(Measurement::single(single));
+        results
+    }
+
+    #[test]
+    fn test_append_and_query_last_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::for_host(dir.path(), "hecate-ws");
+        assert!(history.load().unwrap().is_empty());
+
+        for (day, score) in [(1, 100.0), (2, 110.0), (3, 90.0), (4, 120.0)] {
+            history.append(&run(day, score)).unwrap();
+        }
+        // A run without CPU results does not count towards the last N
+        let mut memory_only = run(5, 0.0);
+        memory_only.cpu_results = None;
+        history.append(&memory_only).unwrap();
+        assert_eq!(history.load().unwrap().len(), 5);
+
+        let trend = history.trend("cpu.single_thread", 3).unwrap();
+        assert_eq!(trend.key, "cpu_single_thread_score");
+        let values: Vec<f64> = trend.points.iter().map(|p| p.value).collect();
+        assert_eq!(values, vec![110.0, 90.0, 120.0]);
+        assert_eq!(trend.points[0].timestamp, Utc.with_ymd_and_hms(2026, 3, 2, 2, 0, 0).unwrap());
+
+        assert_eq!(history.trend("cpu_single_thread_score", 10).unwrap().points.len(), 4);
+    }
+
+    #[test]
+    fn test_corrupt_lines_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::for_host(dir.path(), "hecate-ws");
+        history.append(&run(1, 100.0)).unwrap();
+        // A run cut short by a crash, then a later one
+        std::fs::OpenOptions::new().append(true).open(history.path()).unwrap()
+            .write_all(b"{\"version\": 8, \"timest\n").unwrap();
+        history.append(&run(2, 110.0)).unwrap();
+
+        let runs = history.load().unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(history.trend("cpu.single_thread", 10).unwrap().points.len(), 2);
+    }
+
+    #[test]
+    fn test_metric_lookup() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::for_host(dir.path(), "host/with spaces");
+        assert_eq!(history.path().file_name().unwrap(), "host_with_spaces.jsonl");
+        history.append(&run(1, 100.0)).unwrap();
+
+        assert_eq!(history.trend("memory.bandwidth", 5).unwrap().key, "memory_bandwidth_gb_s");
+        assert!(format!("{:#}", history.trend("cpu", 5).unwrap_err()).contains("ambiguous"));
+        assert!(history.trend("gpu.fps", 5).is_err());
+    }
+
+    #[test]
+    fn test_render_trend() {
+        let trend = Trend {
+            key: "cpu_single_thread_score".into(),
+            name: "CPU single-thread".into(),
+            unit: "ops/s".into(),
+            points: vec![
+                TrendPoint { timestamp: Utc.with_ymd_and_hms(2026, 3, 1, 2, 0, 0).unwrap(), value: 50.0 },
+                TrendPoint { timestamp: Utc.with_ymd_and_hms(2026, 3, 2, 2, 0, 0).unwrap(), value: 100.0 },
+            ],
+        };
+
+        let ascii = render_ascii(&trend);
+        let lines: Vec<&str> = ascii.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1].matches('#').count(), BAR_WIDTH / 2);
+        assert_eq!(lines[2].matches('#').count(), BAR_WIDTH);
+        assert!(lines[2].ends_with("+100.0%"), "{}", lines[2]);
+
+        let mut csv = Vec::new();
+        write_csv(&trend, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.starts_with("Timestamp,cpu_single_thread_score,Unit\n"));
+        assert!(csv.contains("2026-03-02T02:00:00+00:00,100,ops/s"));
+    }
+}
@@ -0,0 +1,120 @@
+//! Memory latency by pointer chasing
+//!
+//! The buffer is split into cache-line nodes linked in one random cycle,
+//! and the benchmark follows the links. Every load's address comes from the
+//! previous load, so neither the prefetcher nor memory-level parallelism can
+//! hide the latency. Chasing working sets of growing size makes each level
+//! of the cache hierarchy visible, ending well past any last-level cache.
+
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+const KIB: usize = 1024;
+const MIB: usize = 1024 * KIB;
+
+/// Working sets chased, from inside L1 to past the largest LLCs
+pub const WORKING_SETS: &[usize] = &[16 * KIB, 128 * KIB, MIB, 8 * MIB, 64 * MIB, 256 * MIB];
+
+/// Dependent loads timed per working set
+const ACCESSES: usize = 1 << 22;
+
+/// Average latency of a dependent load within one working set
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LatencyPoint {
+    pub working_set_bytes: u64,
+    pub latency_ns: f64,
+}
+
+/// One cache line holding the index of the next node to visit
+#[repr(align(64))]
+struct Node {
+    next: usize,
+}
+
+/// Link `count` nodes into a single cycle visiting them in random order
+fn build_chain(count: usize) -> Vec<Node> {
+    let mut order: Vec<usize> = (0..count).collect();
+    order.shuffle(&mut rand::thread_rng());
+
+    let mut nodes: Vec<Node> = (0..count).map(|_| Node { next: 0 }).collect();
+    for (i, &node) in order.iter().enumerate() {
+        nodes[node].next = order[(i + 1) % count];
+    }
+    nodes
+}
+
+/// Average nanoseconds per load when chasing `accesses` links through a
+/// working set of `bytes`
+pub fn chase(bytes: usize, accesses: usize) -> f64 {
+    let count = (bytes / std::mem::size_of::<Node>()).max(2);
+    let nodes = build_chain(count);
+
+    // One lap first, so the timed loads find the set as warm as it gets
+    let mut index = 0;
+    for _ in 0..count {
+        index = nodes[index].next;
+    }
+
+    let start = Instant::now();
+    for _ in 0..accesses {
+        index = nodes[index].next;
+    }
+    let elapsed = start.elapsed();
+    std::hint::black_box(index);
+
+    elapsed.as_nanos() as f64 / accesses as f64
+}
+
+/// Latency at every size in [`WORKING_SETS`], smallest first
+pub fn latency_curve() -> Vec<LatencyPoint> {
+    WORKING_SETS.iter()
+        .map(|&bytes| LatencyPoint {
+            working_set_bytes: bytes as u64,
+            latency_ns: chase(bytes, ACCESSES),
+        })
+        .collect()
+}
+
+/// Human-readable size of a working set
+pub fn format_size(bytes: u64) -> String {
+    match bytes as usize {
+        b if b >= MIB && b % MIB == 0 => format!("{} MiB", b / MIB),
+        b if b >= KIB && b % KIB == 0 => format!("{} KiB", b / KIB),
+        b => format!("{} B", b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chain_is_one_cycle() {
+        let nodes = build_chain(1000);
+        let mut seen = vec![false; nodes.len()];
+        let mut index = 0;
+        for _ in 0..nodes.len() {
+            assert!(!seen[index], "node {} visited twice", index);
+            seen[index] = true;
+            index = nodes[index].next;
+        }
+        assert_eq!(index, 0);
+        assert!(seen.iter().all(|&s| s));
+    }
+
+    #[test]
+    fn test_latency_increases_with_working_set() {
+        // L1-resident against far beyond any LLC on a test machine
+        let small = chase(16 * KIB, 1 << 20);
+        let large = chase(256 * MIB, 1 << 20);
+        assert!(large > small * 2.0, "16 KiB: {:.2} ns, 256 MiB: {:.2} ns", small, large);
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(16 * KIB as u64), "16 KiB");
+        assert_eq!(format_size(256 * MIB as u64), "256 MiB");
+        assert_eq!(format_size(1000), "1000 B");
+    }
+}
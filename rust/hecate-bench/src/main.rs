@@ -10,6 +10,34 @@ use serde::{Deserialize, Serialize};
 use std::time::Instant;
 use sysinfo::System;
 
+mod ai;
+mod atomic;
+mod benchmark;
+mod compare;
+mod csv_export;
+mod disk;
+#[cfg(feature = "gpu")]
+mod gpu;
+mod history;
+mod latency;
+mod net;
+mod profile;
+mod prometheus;
+mod schema;
+mod scratch;
+mod stats;
+mod stress;
+mod thermal;
+mod topology;
+
+use atomic::write_atomic;
+use benchmark::{Benchmark, BenchContext, Registry};
+use profile::{BenchTest, Profile};
+use stats::{Measurement, RunConfig};
+
+/// Seconds each test gets when run on its own
+const DEFAULT_TEST_SECS: u64 = 10;
+
 // ============================================================================
 // CLI STRUCTURE
 // ============================================================================
@@ -21,17 +49,39 @@ struct Cli {
     #[command(subcommand)]
     command: Commands,
     
-    /// Output format (text, json, csv)
+    /// Output format (text, json, csv, prometheus)
     #[arg(short, long, default_value = "text")]
     format: OutputFormat,
     
-    /// Save results to file
+    /// Save results to file, as JSON or in Prometheus format with
+    /// `--format prometheus`
     #[arg(short, long)]
     output: Option<String>,
     
     /// Enable verbose output
     #[arg(short, long)]
     verbose: bool,
+    
+    /// Measured repetitions of each CPU benchmark
+    #[arg(long, default_value = "3", global = true)]
+    runs: usize,
+    
+    /// Discarded warmup repetitions before the measured ones
+    #[arg(long, default_value = "1", global = true)]
+    warmup: usize,
+    
+    /// Append the results to this host's run history
+    #[arg(long, global = true)]
+    store: bool,
+    
+    /// Directory holding run history [default: ~/.local/share/hecate-bench]
+    #[arg(long, global = true)]
+    history_dir: Option<std::path::PathBuf>,
+    
+    /// Directory of executable benchmark plugins
+    #[cfg(feature = "plugins")]
+    #[arg(long, default_value = "/usr/lib/hecate-bench/plugins", global = true)]
+    plugin_dir: std::path::PathBuf,
 }
 
 #[derive(Subcommand)]
@@ -43,6 +93,27 @@ enum Commands {
         duration: u64,
     },
     
+    /// Run the tests listed in a profile file
+    Run {
+        /// TOML profile naming the tests and their durations
+        #[arg(long)]
+        profile: std::path::PathBuf,
+    },
+    
+    /// List registered benchmarks
+    List,
+    
+    /// Run registered benchmarks by name
+    Bench {
+        /// Benchmark names, as shown by `list`
+        #[arg(required = true)]
+        names: Vec<String>,
+        
+        /// Duration for each benchmark in seconds
+        #[arg(short, long, default_value = "10")]
+        duration: u64,
+    },
+    
     /// CPU benchmark
     Cpu {
         #[command(subcommand)]
@@ -63,8 +134,8 @@ enum Commands {
     
     /// Disk benchmark
     Disk {
-        /// Target path for disk tests
-        #[arg(short, long, default_value = "/tmp")]
+        /// Target path for disk tests; /tmp is often tmpfs, which has no disk behind it
+        #[arg(short, long, default_value = "/var/tmp")]
         path: String,
         
         #[command(subcommand)]
@@ -79,6 +150,10 @@ enum Commands {
     
     /// AI/ML benchmark
     Ai {
+        /// Also run the naive scalar kernels for comparison
+        #[arg(long)]
+        naive: bool,
+        
         #[command(subcommand)]
         test: AiTest,
     },
@@ -90,12 +165,34 @@ enum Commands {
         
         /// Second result file
         current: String,
+        
+        /// Percentage change either way that counts as unchanged
+        #[arg(long, default_value = "5.0")]
+        threshold: f64,
+        
+        /// Exit with status 1 if any metric regressed beyond the threshold
+        #[arg(long)]
+        fail_on_regression: bool,
+    },
+    
+    /// Show how a metric moved over this host's stored runs
+    Trend {
+        /// Metric key or unambiguous prefix, e.g. cpu.single_thread
+        metric: String,
+        
+        /// Number of most recent runs to show
+        #[arg(short = 'n', long, default_value = "20")]
+        last: usize,
+        
+        /// Host whose history to read [default: this host]
+        #[arg(long)]
+        host: Option<String>,
     },
     
     /// System stress test
     Stress {
-        /// Components to stress (cpu, gpu, memory, disk)
-        #[arg(short, long)]
+        /// Components to stress (cpu, gpu, memory, disk or all)
+        #[arg(short, long, value_delimiter = ',')]
         components: Vec<String>,
         
         /// Duration in seconds
@@ -105,10 +202,14 @@ enum Commands {
         /// Number of threads
         #[arg(short, long)]
         threads: Option<usize>,
+        
+        /// Stop once any CPU or GPU sensor exceeds this temperature (°C)
+        #[arg(long, default_value = "90")]
+        max_temp: f64,
     },
 }
 
-#[derive(Subcommand)]
+#[derive(Subcommand, Debug, Clone, Copy, PartialEq, Eq)]
 enum CpuTest {
     /// Single-threaded performance
     Single,
@@ -124,15 +225,17 @@ enum CpuTest {
     Cache,
     /// Branch prediction
     Branch,
+    /// Per-core scores and thread scaling
+    Cores,
     /// All CPU tests
     All,
 }
 
-#[derive(Subcommand)]
+#[derive(Subcommand, Debug, Clone, Copy, PartialEq, Eq)]
 enum GpuTest {
-    /// CUDA cores performance
+    /// Compute shader FMA throughput
     Cuda,
-    /// Tensor cores performance
+    /// Matrix multiplication throughput
     Tensor,
     /// Memory bandwidth
     Memory,
@@ -144,7 +247,7 @@ enum GpuTest {
     All,
 }
 
-#[derive(Subcommand)]
+#[derive(Subcommand, Debug, Clone, Copy, PartialEq, Eq)]
 enum MemoryTest {
     /// Sequential read
     SeqRead,
@@ -160,7 +263,7 @@ enum MemoryTest {
     All,
 }
 
-#[derive(Subcommand)]
+#[derive(Subcommand, Debug, Clone, Copy, PartialEq, Eq)]
 enum DiskTest {
     /// Sequential read
     SeqRead,
@@ -185,15 +288,35 @@ enum NetworkTest {
     Latency {
         /// Host to ping
         host: String,
+        
+        /// Number of probes
+        #[arg(short, long, default_value = "10")]
+        count: usize,
+        
+        /// TCP port to time connections to when ICMP is not permitted
+        #[arg(long, default_value = "80")]
+        tcp_port: u16,
     },
     /// Packet loss test
     PacketLoss {
         /// Host to test
         host: String,
+        
+        /// Number of probes
+        #[arg(short, long, default_value = "50")]
+        count: usize,
+        
+        /// Seconds to wait for each reply
+        #[arg(long, default_value = "1")]
+        timeout: u64,
+        
+        /// TCP port to time connections to when ICMP is not permitted
+        #[arg(long, default_value = "80")]
+        tcp_port: u16,
     },
 }
 
-#[derive(Subcommand)]
+#[derive(Subcommand, Debug, Clone, Copy, PartialEq, Eq)]
 enum AiTest {
     /// Matrix multiplication
     Matmul,
@@ -212,6 +335,7 @@ enum OutputFormat {
     Text,
     Json,
     Csv,
+    Prometheus,
 }
 
 impl std::str::FromStr for OutputFormat {
@@ -222,6 +346,7 @@ impl std::str::FromStr for OutputFormat {
             "text" => Ok(OutputFormat::Text),
             "json" => Ok(OutputFormat::Json),
             "csv" => Ok(OutputFormat::Csv),
+            "prometheus" | "prom" => Ok(OutputFormat::Prometheus),
             _ => Err(format!("Unknown format: {}", s)),
         }
     }
@@ -233,7 +358,12 @@ impl std::str::FromStr for OutputFormat {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct BenchmarkResults {
+    /// Layout version of saved files, see [`schema`]
+    schema_version: u32,
     timestamp: chrono::DateTime<chrono::Utc>,
+    /// Profile the run was made from, if any
+    #[serde(default)]
+    profile: Option<String>,
     system_info: SystemInfo,
     cpu_results: Option<CpuResults>,
     gpu_results: Option<GpuResults>,
@@ -241,6 +371,9 @@ struct BenchmarkResults {
     disk_results: Option<DiskResults>,
     network_results: Option<NetworkResults>,
     ai_results: Option<AiResults>,
+    /// Metrics of registered benchmarks, by benchmark name
+    #[serde(default)]
+    custom_results: std::collections::BTreeMap<String, benchmark::MetricSet>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -249,61 +382,103 @@ struct SystemInfo {
     os: String,
     kernel: String,
     cpu_model: String,
+    /// Logical CPUs
     cpu_cores: usize,
+    #[serde(default)]
+    cpu_physical_cores: usize,
+    #[serde(default)]
+    cpu_threads: usize,
     memory_total_gb: f64,
     gpu_info: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 struct CpuResults {
-    single_thread_score: f64,
-    multi_thread_score: f64,
-    float_mflops: f64,
-    integer_mips: f64,
-    crypto_mb_s: f64,
-    cache_latency_ns: f64,
-    branch_mpred_s: f64,
+    single_thread_score: Option<Measurement>,
+    multi_thread_score: Option<Measurement>,
+    float_mflops: Option<Measurement>,
+    integer_mips: Option<Measurement>,
+    crypto_mb_s: Option<Measurement>,
+    cache_latency_ns: Option<Measurement>,
+    branch_mpred_s: Option<Measurement>,
+    /// Single-thread score of each physical core
+    #[serde(default)]
+    per_core_scores: Vec<f64>,
+    /// Multi-thread score as threads are added, physical cores first
+    #[serde(default)]
+    scaling: Vec<topology::ScalingPoint>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 struct GpuResults {
-    cuda_gflops: f64,
-    tensor_tflops: f64,
-    memory_bandwidth_gb_s: f64,
-    raytracing_mrays_s: f64,
-    inference_images_s: f64,
+    /// Adapter the benchmarks ran on
+    #[serde(default)]
+    adapter: String,
+    /// Compute shader FMA throughput
+    cuda_gflops: Option<f64>,
+    /// Dense f32 matrix multiplication throughput
+    tensor_tflops: Option<f64>,
+    memory_bandwidth_gb_s: Option<f64>,
+    /// Not measured yet
+    #[serde(default)]
+    raytracing_mrays_s: Option<f64>,
+    /// Not measured yet
+    #[serde(default)]
+    inference_images_s: Option<f64>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 struct MemoryResults {
-    seq_read_gb_s: f64,
-    seq_write_gb_s: f64,
-    random_access_mops: f64,
-    latency_ns: f64,
-    bandwidth_gb_s: f64,
+    seq_read_gb_s: Option<f64>,
+    seq_write_gb_s: Option<f64>,
+    random_access_mops: Option<f64>,
+    /// Dependent-load latency in the largest working set, i.e. DRAM
+    latency_ns: Option<f64>,
+    bandwidth_gb_s: Option<f64>,
+    /// Dependent-load latency at each working-set size
+    #[serde(default)]
+    latency_curve: Vec<latency::LatencyPoint>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 struct DiskResults {
-    seq_read_mb_s: f64,
-    seq_write_mb_s: f64,
-    random_4k_read_iops: u64,
-    random_4k_write_iops: u64,
+    seq_read_mb_s: Option<f64>,
+    seq_write_mb_s: Option<f64>,
+    random_4k_read_iops: Option<u64>,
+    random_4k_write_iops: Option<u64>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 struct NetworkResults {
-    bandwidth_mbps: f64,
-    latency_ms: f64,
-    packet_loss_percent: f64,
+    bandwidth_mbps: Option<f64>,
+    /// Average round-trip time
+    latency_ms: Option<f64>,
+    /// Latency spread, meaningful when `latency_ms` is set
+    #[serde(default)]
+    latency_min_ms: f64,
+    #[serde(default)]
+    latency_max_ms: f64,
+    #[serde(default)]
+    latency_stddev_ms: f64,
+    #[serde(default)]
+    jitter_ms: Option<f64>,
+    packet_loss_percent: Option<f64>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 struct AiResults {
-    matmul_gflops: f64,
-    conv_gops: f64,
-    transformer_tokens_s: f64,
-    training_samples_s: f64,
+    /// Optimized GEMM backend
+    matmul_gflops: Option<f64>,
+    /// Optimized im2col + GEMM backend
+    conv_gops: Option<f64>,
+    /// Scalar triple loop, with `--naive`
+    #[serde(default)]
+    matmul_naive_gflops: Option<f64>,
+    /// Scalar loops, with `--naive`
+    #[serde(default)]
+    conv_naive_gops: Option<f64>,
+    transformer_tokens_s: Option<f64>,
+    training_samples_s: Option<f64>,
 }
 
 // ============================================================================
@@ -325,6 +500,12 @@ async fn main() -> Result<()> {
             .init();
     }
     
+    // The stress test stops cleanly on its own; everything else deletes
+    // its scratch files and exits
+    if !matches!(cli.command, Commands::Stress { .. }) {
+        scratch::remove_on_interrupt()?;
+    }
+    
     // Print banner
     print_banner();
     
@@ -333,7 +514,9 @@ async fn main() -> Result<()> {
     
     // Initialize results
     let mut results = BenchmarkResults {
+        schema_version: schema::CURRENT_VERSION,
         timestamp: chrono::Utc::now(),
+        profile: None,
         system_info: system_info.clone(),
         cpu_results: None,
         gpu_results: None,
@@ -341,41 +524,91 @@ async fn main() -> Result<()> {
         disk_results: None,
         network_results: None,
         ai_results: None,
+        custom_results: Default::default(),
+    };
+    
+    let registry = Registry::builtin();
+    #[cfg(feature = "plugins")]
+    let registry = benchmark::ExternalBenchmark::load_dir(registry, &cli.plugin_dir)?;
+    
+    let run_config = RunConfig {
+        runs: cli.runs,
+        warmup: cli.warmup,
     };
     
     // Run benchmarks
     match cli.command {
         Commands::All { duration } => {
-            results.cpu_results = Some(run_cpu_benchmarks(duration).await?);
+            results.cpu_results = Some(run_cpu_benchmarks(duration, run_config).await?);
             results.gpu_results = run_gpu_benchmarks(duration).await.ok();
             results.memory_results = Some(run_memory_benchmarks(duration).await?);
-            results.disk_results = Some(run_disk_benchmarks("/tmp", duration).await?);
-            results.ai_results = run_ai_benchmarks(duration).await.ok();
+            results.disk_results = Some(run_disk_benchmarks("/var/tmp", duration).await?);
+            results.ai_results = run_ai_benchmarks(duration, false).await.ok();
+        }
+        Commands::Run { profile } => {
+            let profile = Profile::load(&profile)?;
+            println!("\nRunning profile {}", profile.name.bright_yellow());
+            for scheduled in profile.schedule()? {
+                run_test(scheduled.test, scheduled.duration, run_config, &profile, &mut results).await?;
+            }
+            results.profile = Some(profile.name);
+        }
+        Commands::List => {
+            println!("{}", "Registered benchmarks:".bright_cyan());
+            for benchmark in registry.iter() {
+                println!("  {:<24} {}", benchmark.name().bright_yellow(), benchmark.description());
+            }
+            return Ok(());
+        }
+        Commands::Bench { names, duration } => {
+            let ctx = BenchContext { config: run_config };
+            for name in names {
+                println!("\nRunning {}...", name.bright_yellow());
+                tokio::task::block_in_place(|| {
+                    registry.run(&name, std::time::Duration::from_secs(duration), &ctx, &mut results)
+                })?;
+            }
         }
         Commands::Cpu { test } => {
-            results.cpu_results = Some(run_cpu_test(test).await?);
+            run_cpu_test(test, DEFAULT_TEST_SECS, run_config, results.cpu_results.insert(CpuResults::default())).await?;
         }
         Commands::Gpu { test } => {
-            results.gpu_results = Some(run_gpu_test(test).await?);
+            run_gpu_test(test, DEFAULT_TEST_SECS, results.gpu_results.insert(GpuResults::default())).await?;
         }
         Commands::Memory { test } => {
-            results.memory_results = Some(run_memory_test(test).await?);
+            run_memory_test(test, DEFAULT_TEST_SECS, results.memory_results.insert(MemoryResults::default())).await?;
         }
         Commands::Disk { path, test } => {
-            results.disk_results = Some(run_disk_test(&path, test).await?);
+            run_disk_test(&path, test, DEFAULT_TEST_SECS, results.disk_results.insert(DiskResults::default())).await?;
         }
         Commands::Network { test } => {
             results.network_results = Some(run_network_test(test).await?);
         }
-        Commands::Ai { test } => {
-            results.ai_results = Some(run_ai_test(test).await?);
+        Commands::Ai { naive, test } => {
+            run_ai_test(test, naive, DEFAULT_TEST_SECS, results.ai_results.insert(AiResults::default())).await?;
         }
-        Commands::Compare { baseline, current } => {
-            compare_results(&baseline, &current).await?;
+        Commands::Compare { baseline, current, threshold, fail_on_regression } => {
+            let comparisons = compare::compare_files(&baseline, &current, threshold)?;
+            compare::print_comparison(&comparisons, threshold);
+            
+            let code = compare::exit_code(&comparisons, fail_on_regression);
+            if code != 0 {
+                std::process::exit(code);
+            }
             return Ok(());
         }
-        Commands::Stress { components, duration, threads } => {
-            run_stress_test(components, duration, threads).await?;
+        Commands::Trend { metric, last, host } => {
+            let dir = cli.history_dir.unwrap_or_else(history::default_dir);
+            let host = host.unwrap_or(system_info.hostname);
+            let trend = history::History::for_host(&dir, &host).trend(&metric, last)?;
+            match cli.format {
+                OutputFormat::Csv => history::write_csv(&trend, std::io::stdout())?,
+                _ => print!("{}", history::render_ascii(&trend)),
+            }
+            return Ok(());
+        }
+        Commands::Stress { components, duration, threads, max_temp } => {
+            run_stress_test(components, duration, threads, max_temp).await?;
             return Ok(());
         }
     }
@@ -385,10 +618,17 @@ async fn main() -> Result<()> {
     
     // Save results if requested
     if let Some(output) = cli.output {
-        save_results(&results, &output)?;
+        save_results(&results, &output, &cli.format)?;
         println!("\n{} Results saved to {}", "✓".green(), output);
     }
     
+    if cli.store {
+        let dir = cli.history_dir.unwrap_or_else(history::default_dir);
+        let history = history::History::for_host(&dir, &results.system_info.hostname);
+        history.append(&results)?;
+        println!("{} Run added to {}", "✓".green(), history.path().display());
+    }
+    
     Ok(())
 }
 
@@ -419,12 +659,21 @@ fn collect_system_info() -> Result<SystemInfo> {
     let kernel = System::kernel_version().unwrap_or_else(|| "unknown".to_string());
     let cpu_model = system.cpus()[0].brand().to_string();
     let cpu_cores = system.cpus().len();
+    
+    // Prefer hecate-core's view of the CPU so both tools agree on counts
+    let (cpu_physical_cores, cpu_threads) = match hecate_core::HardwareDetector::new().detect() {
+        Ok(hardware) => (hardware.cpu.cores, hardware.cpu.threads),
+        Err(e) => {
+            tracing::debug!("Hardware detection failed: {}", e);
+            (system.physical_core_count().unwrap_or(cpu_cores), cpu_cores)
+        }
+    };
     let memory_total_gb = system.total_memory() as f64 / 1024.0 / 1024.0 / 1024.0;
     
-    // Detect GPUs
-    let mut gpu_info = Vec::new();
-    // GPU detection would require async runtime, skip for now in sync context
-    gpu_info.push("GPU detection requires async runtime".to_string());
+    #[cfg(feature = "gpu")]
+    let gpu_info = gpu::adapter_names();
+    #[cfg(not(feature = "gpu"))]
+    let gpu_info = Vec::new();
     
     Ok(SystemInfo {
         hostname,
@@ -432,16 +681,51 @@ fn collect_system_info() -> Result<SystemInfo> {
         kernel,
         cpu_model,
         cpu_cores,
+        cpu_physical_cores,
+        cpu_threads,
         memory_total_gb,
         gpu_info,
     })
 }
 
+// ============================================================================
+// PROFILES
+// ============================================================================
+
+/// Run one profile test for `duration` seconds, adding its figures to
+/// those of earlier tests of the same suite
+async fn run_test(
+    test: BenchTest,
+    duration: u64,
+    config: RunConfig,
+    profile: &Profile,
+    results: &mut BenchmarkResults,
+) -> Result<()> {
+    match test {
+        BenchTest::Cpu(test) => {
+            run_cpu_test(test, duration, config, results.cpu_results.get_or_insert_with(Default::default)).await
+        }
+        BenchTest::Gpu(test) => {
+            run_gpu_test(test, duration, results.gpu_results.get_or_insert_with(Default::default)).await
+        }
+        BenchTest::Memory(test) => {
+            run_memory_test(test, duration, results.memory_results.get_or_insert_with(Default::default)).await
+        }
+        BenchTest::Disk(test) => {
+            let disk = results.disk_results.get_or_insert_with(Default::default);
+            run_disk_test(&profile.disk_path, test, duration, disk).await
+        }
+        BenchTest::Ai(test) => {
+            run_ai_test(test, profile.naive, duration, results.ai_results.get_or_insert_with(Default::default)).await
+        }
+    }
+}
+
 // ============================================================================
 // CPU BENCHMARKS
 // ============================================================================
 
-async fn run_cpu_benchmarks(duration: u64) -> Result<CpuResults> {
+async fn run_cpu_benchmarks(duration: u64, config: RunConfig) -> Result<CpuResults> {
     println!("\n{}", "Running CPU Benchmarks...".bright_yellow());
     
     let mp = MultiProgress::new();
@@ -457,75 +741,92 @@ async fn run_cpu_benchmarks(duration: u64) -> Result<CpuResults> {
     multi_pb.set_style(style.clone());
     multi_pb.set_message("Multi-threaded test");
     
+    // Each of the six timed tests gets a sixth of the duration, split over its runs
+    let per_run = config.per_run_secs(duration / 6);
+    
     // Single-threaded benchmark
-    let single_score = benchmark_single_thread(duration / 6, &single_pb).await?;
+    let single_score = stats::measure(config, || benchmark_single_thread(per_run, &single_pb)).await?;
     single_pb.finish_with_message("✓ Complete");
     
     // Multi-threaded benchmark
-    let multi_score = benchmark_multi_thread(duration / 6, &multi_pb).await?;
+    let multi_score = stats::measure(config, || benchmark_multi_thread(per_run, &multi_pb)).await?;
     multi_pb.finish_with_message("✓ Complete");
     
     // Other CPU tests
-    let float_mflops = benchmark_float_ops(duration / 6).await?;
-    let integer_mips = benchmark_integer_ops(duration / 6).await?;
-    let crypto_mb_s = benchmark_crypto(duration / 6).await?;
-    let cache_latency_ns = benchmark_cache_latency().await?;
-    let branch_mpred_s = benchmark_branch_prediction(duration / 6).await?;
+    let float_mflops = stats::measure(config, || benchmark_float_ops(per_run)).await?;
+    let integer_mips = stats::measure(config, || benchmark_integer_ops(per_run)).await?;
+    let crypto_mb_s = run_cpu_crypto(duration / 6, config)?;
+    let cache_latency_ns = stats::measure(config, benchmark_cache_latency).await?;
+    let branch_mpred_s = stats::measure(config, || benchmark_branch_prediction(per_run)).await?;
+    let (per_core_scores, scaling) = benchmark_topology(duration / 6).await?;
     
     Ok(CpuResults {
-        single_thread_score: single_score,
-        multi_thread_score: multi_score,
-        float_mflops,
-        integer_mips,
-        crypto_mb_s,
-        cache_latency_ns,
-        branch_mpred_s,
+        single_thread_score: Some(single_score),
+        multi_thread_score: Some(multi_score),
+        float_mflops: Some(float_mflops),
+        integer_mips: Some(integer_mips),
+        crypto_mb_s: Some(crypto_mb_s),
+        cache_latency_ns: Some(cache_latency_ns),
+        branch_mpred_s: Some(branch_mpred_s),
+        per_core_scores,
+        scaling,
     })
 }
 
-async fn run_cpu_test(test: CpuTest) -> Result<CpuResults> {
-    let duration = 10; // Default duration for individual tests
-    
-    let mut results = CpuResults {
-        single_thread_score: 0.0,
-        multi_thread_score: 0.0,
-        float_mflops: 0.0,
-        integer_mips: 0.0,
-        crypto_mb_s: 0.0,
-        cache_latency_ns: 0.0,
-        branch_mpred_s: 0.0,
-    };
+/// Run one CPU test for `duration` seconds, filling in its figures
+async fn run_cpu_test(test: CpuTest, duration: u64, config: RunConfig, results: &mut CpuResults) -> Result<()> {
+    let per_run = config.per_run_secs(duration);
     
     let pb = ProgressBar::new(100);
     
     match test {
         CpuTest::Single => {
-            results.single_thread_score = benchmark_single_thread(duration, &pb).await?;
+            results.single_thread_score = Some(stats::measure(config, || benchmark_single_thread(per_run, &pb)).await?);
         }
         CpuTest::Multi => {
-            results.multi_thread_score = benchmark_multi_thread(duration, &pb).await?;
+            results.multi_thread_score = Some(stats::measure(config, || benchmark_multi_thread(per_run, &pb)).await?);
         }
         CpuTest::Float => {
-            results.float_mflops = benchmark_float_ops(duration).await?;
+            results.float_mflops = Some(stats::measure(config, || benchmark_float_ops(per_run)).await?);
         }
         CpuTest::Integer => {
-            results.integer_mips = benchmark_integer_ops(duration).await?;
+            results.integer_mips = Some(stats::measure(config, || benchmark_integer_ops(per_run)).await?);
         }
         CpuTest::Crypto => {
-            results.crypto_mb_s = benchmark_crypto(duration).await?;
+            results.crypto_mb_s = Some(run_cpu_crypto(duration, config)?);
         }
         CpuTest::Cache => {
-            results.cache_latency_ns = benchmark_cache_latency().await?;
+            results.cache_latency_ns = Some(stats::measure(config, benchmark_cache_latency).await?);
         }
         CpuTest::Branch => {
-            results.branch_mpred_s = benchmark_branch_prediction(duration).await?;
+            results.branch_mpred_s = Some(stats::measure(config, || benchmark_branch_prediction(per_run)).await?);
+        }
+        CpuTest::Cores => {
+            (results.per_core_scores, results.scaling) = benchmark_topology(duration).await?;
         }
         CpuTest::All => {
-            return run_cpu_benchmarks(duration * 7).await;
+            *results = run_cpu_benchmarks(duration * 7, config).await?;
         }
     }
     
-    Ok(results)
+    Ok(())
+}
+
+/// Per-core scores and the scaling curve, spending about `budget_secs`
+async fn benchmark_topology(budget_secs: u64) -> Result<(Vec<f64>, Vec<topology::ScalingPoint>)> {
+    tokio::task::spawn_blocking(move || {
+        let cores = topology::physical_cores();
+        if cores.is_empty() {
+            anyhow::bail!("CPU topology is unavailable");
+        }
+        
+        // One step per core plus roughly one per scaling point
+        let steps = cores.len() as u32 + cores.len().ilog2() + 3;
+        let step = (std::time::Duration::from_secs(budget_secs.max(1)) / steps)
+            .max(std::time::Duration::from_millis(200));
+        
+        Ok((topology::per_core_scores(&cores, step), topology::scaling_curve(&cores, step)))
+    }).await?
 }
 
 async fn benchmark_single_thread(duration: u64, pb: &ProgressBar) -> Result<f64> {
@@ -627,21 +928,14 @@ async fn benchmark_integer_ops(duration: u64) -> Result<f64> {
     Ok(operations as f64 / duration as f64 / 1_000_000.0) // MIPS
 }
 
-async fn benchmark_crypto(duration: u64) -> Result<f64> {
-    use sha2::{Sha256, Digest};
-    
-    let start = Instant::now();
-    let mut bytes_processed = 0u64;
-    let data = vec![0u8; 1_048_576]; // 1MB
-    
-    while start.elapsed().as_secs() < duration {
-        let mut hasher = Sha256::new();
-        hasher.update(&data);
-        let _ = hasher.finalize();
-        bytes_processed += data.len() as u64;
-    }
-    
-    Ok(bytes_processed as f64 / duration as f64 / 1_048_576.0) // MB/s
+/// The crypto test, which runs through the [`Benchmark`] interface
+fn run_cpu_crypto(duration: u64, config: RunConfig) -> Result<Measurement> {
+    let crypto = benchmark::CpuCrypto;
+    let metrics = tokio::task::block_in_place(|| {
+        crypto.run(std::time::Duration::from_secs(duration), &BenchContext { config })
+    })?;
+    metrics.get(benchmark::CpuCrypto::KEY)
+        .ok_or_else(|| anyhow::anyhow!("{} reported no {}", crypto.name(), benchmark::CpuCrypto::KEY))
 }
 
 async fn benchmark_cache_latency() -> Result<f64> {
@@ -693,30 +987,60 @@ async fn benchmark_branch_prediction(duration: u64) -> Result<f64> {
 // GPU BENCHMARKS
 // ============================================================================
 
-async fn run_gpu_benchmarks(_duration: u64) -> Result<GpuResults> {
+#[cfg(feature = "gpu")]
+async fn run_gpu_benchmarks(duration: u64) -> Result<GpuResults> {
     println!("\n{}", "Running GPU Benchmarks...".bright_yellow());
     
-    // Note: These are placeholder implementations
-    // Real GPU benchmarks would use CUDA/OpenCL
+    let bench = gpu::GpuBench::new().await?;
+    let each = std::time::Duration::from_secs((duration / 3).max(1));
     
     Ok(GpuResults {
-        cuda_gflops: 1000.0,
-        tensor_tflops: 10.0,
-        memory_bandwidth_gb_s: 500.0,
-        raytracing_mrays_s: 100.0,
-        inference_images_s: 1000.0,
+        adapter: bench.name.clone(),
+        cuda_gflops: Some(bench.fma_gflops(each)?),
+        tensor_tflops: Some(bench.matmul_gflops(each)? / 1000.0),
+        memory_bandwidth_gb_s: Some(bench.copy_bandwidth_gb_s(each)?),
+        raytracing_mrays_s: None,
+        inference_images_s: None,
     })
 }
 
-async fn run_gpu_test(_test: GpuTest) -> Result<GpuResults> {
-    // Placeholder implementation
-    Ok(GpuResults {
-        cuda_gflops: 1000.0,
-        tensor_tflops: 10.0,
-        memory_bandwidth_gb_s: 500.0,
-        raytracing_mrays_s: 100.0,
-        inference_images_s: 1000.0,
-    })
+/// Run one GPU test for `secs` seconds, filling in its figures
+#[cfg(feature = "gpu")]
+async fn run_gpu_test(test: GpuTest, secs: u64, results: &mut GpuResults) -> Result<()> {
+    let duration = std::time::Duration::from_secs(secs.max(1));
+    
+    let bench = gpu::GpuBench::new().await?;
+    results.adapter = bench.name.clone();
+    
+    match test {
+        GpuTest::Cuda => {
+            results.cuda_gflops = Some(bench.fma_gflops(duration)?);
+        }
+        GpuTest::Tensor => {
+            results.tensor_tflops = Some(bench.matmul_gflops(duration)? / 1000.0);
+        }
+        GpuTest::Memory => {
+            results.memory_bandwidth_gb_s = Some(bench.copy_bandwidth_gb_s(duration)?);
+        }
+        GpuTest::RayTrace | GpuTest::Inference => {
+            return Err(anyhow::anyhow!("This GPU test is not implemented yet"));
+        }
+        GpuTest::All => {
+            *results = run_gpu_benchmarks(secs * 3).await?;
+        }
+    }
+    
+    Ok(())
+}
+
+#[cfg(not(feature = "gpu"))]
+async fn run_gpu_benchmarks(_duration: u64) -> Result<GpuResults> {
+    Err(anyhow::anyhow!("hecate-bench was built without GPU support"))
+}
+
+#[cfg(not(feature = "gpu"))]
+async fn run_gpu_test(_test: GpuTest, _secs: u64, _results: &mut GpuResults) -> Result<()> {
+    run_gpu_benchmarks(0).await.map(|_| ())
 }
 
 // ============================================================================
@@ -729,51 +1053,44 @@ async fn run_memory_benchmarks(duration: u64) -> Result<MemoryResults> {
     let seq_read_gb_s = benchmark_seq_read(duration / 5).await?;
     let seq_write_gb_s = benchmark_seq_write(duration / 5).await?;
     let random_access_mops = benchmark_random_access(duration / 5).await?;
-    let latency_ns = benchmark_memory_latency().await?;
+    let latency_curve = benchmark_memory_latency().await?;
     let bandwidth_gb_s = benchmark_memory_bandwidth(duration / 5).await?;
     
     Ok(MemoryResults {
-        seq_read_gb_s,
-        seq_write_gb_s,
-        random_access_mops,
-        latency_ns,
-        bandwidth_gb_s,
+        seq_read_gb_s: Some(seq_read_gb_s),
+        seq_write_gb_s: Some(seq_write_gb_s),
+        random_access_mops: Some(random_access_mops),
+        latency_ns: Some(dram_latency(&latency_curve)),
+        bandwidth_gb_s: Some(bandwidth_gb_s),
+        latency_curve,
     })
 }
 
-async fn run_memory_test(test: MemoryTest) -> Result<MemoryResults> {
-    let duration = 10;
-    
-    let mut results = MemoryResults {
-        seq_read_gb_s: 0.0,
-        seq_write_gb_s: 0.0,
-        random_access_mops: 0.0,
-        latency_ns: 0.0,
-        bandwidth_gb_s: 0.0,
-    };
-    
+/// Run one memory test for `duration` seconds, filling in its figures
+async fn run_memory_test(test: MemoryTest, duration: u64, results: &mut MemoryResults) -> Result<()> {
     match test {
         MemoryTest::SeqRead => {
-            results.seq_read_gb_s = benchmark_seq_read(duration).await?;
+            results.seq_read_gb_s = Some(benchmark_seq_read(duration).await?);
         }
         MemoryTest::SeqWrite => {
-            results.seq_write_gb_s = benchmark_seq_write(duration).await?;
+            results.seq_write_gb_s = Some(benchmark_seq_write(duration).await?);
         }
         MemoryTest::Random => {
-            results.random_access_mops = benchmark_random_access(duration).await?;
+            results.random_access_mops = Some(benchmark_random_access(duration).await?);
         }
         MemoryTest::Latency => {
-            results.latency_ns = benchmark_memory_latency().await?;
+            results.latency_curve = benchmark_memory_latency().await?;
+            results.latency_ns = Some(dram_latency(&results.latency_curve));
         }
         MemoryTest::Bandwidth => {
-            results.bandwidth_gb_s = benchmark_memory_bandwidth(duration).await?;
+            results.bandwidth_gb_s = Some(benchmark_memory_bandwidth(duration).await?);
         }
         MemoryTest::All => {
-            return run_memory_benchmarks(duration * 5).await;
+            *results = run_memory_benchmarks(duration * 5).await?;
         }
     }
     
-    Ok(results)
+    Ok(())
 }
 
 async fn benchmark_seq_read(duration: u64) -> Result<f64> {
@@ -833,20 +1150,13 @@ async fn benchmark_random_access(duration: u64) -> Result<f64> {
     Ok(operations as f64 / duration as f64 / 1_000_000.0) // MOPS
 }
 
-async fn benchmark_memory_latency() -> Result<f64> {
-    const SIZE: usize = 10_000_000;
-    let data = vec![0u64; SIZE];
-    
-    let start = Instant::now();
-    let iterations = 1000000;
-    
-    for i in 0..iterations {
-        let index = (i * 17) % SIZE; // Stride access
-        std::hint::black_box(data[index]);
-    }
-    
-    let elapsed = start.elapsed();
-    Ok(elapsed.as_nanos() as f64 / iterations as f64)
+async fn benchmark_memory_latency() -> Result<Vec<latency::LatencyPoint>> {
+    Ok(tokio::task::spawn_blocking(latency::latency_curve).await?)
+}
+
+/// Latency of the largest working set, which no cache holds
+fn dram_latency(curve: &[latency::LatencyPoint]) -> f64 {
+    curve.last().map_or(0.0, |point| point.latency_ns)
 }
 
 async fn benchmark_memory_bandwidth(duration: u64) -> Result<f64> {
@@ -878,161 +1188,56 @@ async fn run_disk_benchmarks(path: &str, duration: u64) -> Result<DiskResults> {
     let random_4k_write_iops = benchmark_disk_random_write(path, duration / 4).await?;
     
     Ok(DiskResults {
-        seq_read_mb_s,
-        seq_write_mb_s,
-        random_4k_read_iops,
-        random_4k_write_iops,
+        seq_read_mb_s: Some(seq_read_mb_s),
+        seq_write_mb_s: Some(seq_write_mb_s),
+        random_4k_read_iops: Some(random_4k_read_iops),
+        random_4k_write_iops: Some(random_4k_write_iops),
     })
 }
 
-async fn run_disk_test(path: &str, test: DiskTest) -> Result<DiskResults> {
-    let duration = 10;
-    
-    let mut results = DiskResults {
-        seq_read_mb_s: 0.0,
-        seq_write_mb_s: 0.0,
-        random_4k_read_iops: 0,
-        random_4k_write_iops: 0,
-    };
-    
+/// Run one disk test under `path` for `duration` seconds, filling in its figures
+async fn run_disk_test(path: &str, test: DiskTest, duration: u64, results: &mut DiskResults) -> Result<()> {
     match test {
         DiskTest::SeqRead => {
-            results.seq_read_mb_s = benchmark_disk_seq_read(path, duration).await?;
+            results.seq_read_mb_s = Some(benchmark_disk_seq_read(path, duration).await?);
         }
         DiskTest::SeqWrite => {
-            results.seq_write_mb_s = benchmark_disk_seq_write(path, duration).await?;
+            results.seq_write_mb_s = Some(benchmark_disk_seq_write(path, duration).await?);
         }
         DiskTest::Random4k => {
-            results.random_4k_read_iops = benchmark_disk_random_read(path, duration).await?;
-            results.random_4k_write_iops = benchmark_disk_random_write(path, duration).await?;
+            results.random_4k_read_iops = Some(benchmark_disk_random_read(path, duration).await?);
+            results.random_4k_write_iops = Some(benchmark_disk_random_write(path, duration).await?);
         }
         DiskTest::Iops => {
-            results.random_4k_read_iops = benchmark_disk_random_read(path, duration).await?;
-            results.random_4k_write_iops = benchmark_disk_random_write(path, duration).await?;
+            results.random_4k_read_iops = Some(benchmark_disk_random_read(path, duration).await?);
+            results.random_4k_write_iops = Some(benchmark_disk_random_write(path, duration).await?);
         }
         DiskTest::All => {
-            return run_disk_benchmarks(path, duration * 4).await;
+            *results = run_disk_benchmarks(path, duration * 4).await?;
         }
     }
     
-    Ok(results)
+    Ok(())
 }
 
 async fn benchmark_disk_seq_read(path: &str, duration: u64) -> Result<f64> {
-    let test_file = format!("{}/hecate_bench_read.tmp", path);
-    let size = 100_000_000; // 100MB
-    let data = vec![0u8; size];
-    
-    // Create test file
-    tokio::fs::write(&test_file, &data).await?;
-    
-    let start = Instant::now();
-    let mut bytes_read = 0u64;
-    
-    while start.elapsed().as_secs() < duration {
-        let _ = tokio::fs::read(&test_file).await?;
-        bytes_read += size as u64;
-    }
-    
-    // Cleanup
-    let _ = tokio::fs::remove_file(&test_file).await;
-    
-    Ok(bytes_read as f64 / duration as f64 / 1_048_576.0) // MB/s
+    let dir = std::path::PathBuf::from(path);
+    tokio::task::spawn_blocking(move || disk::seq_read(&dir, std::time::Duration::from_secs(duration))).await?
 }
 
 async fn benchmark_disk_seq_write(path: &str, duration: u64) -> Result<f64> {
-    let test_file = format!("{}/hecate_bench_write.tmp", path);
-    let size = 10_000_000; // 10MB
-    let data = vec![0u8; size];
-    
-    let start = Instant::now();
-    let mut bytes_written = 0u64;
-    
-    while start.elapsed().as_secs() < duration {
-        tokio::fs::write(&test_file, &data).await?;
-        bytes_written += size as u64;
-    }
-    
-    // Cleanup
-    let _ = tokio::fs::remove_file(&test_file).await;
-    
-    Ok(bytes_written as f64 / duration as f64 / 1_048_576.0) // MB/s
+    let dir = std::path::PathBuf::from(path);
+    tokio::task::spawn_blocking(move || disk::seq_write(&dir, std::time::Duration::from_secs(duration))).await?
 }
 
 async fn benchmark_disk_random_read(path: &str, duration: u64) -> Result<u64> {
-    let test_file = format!("{}/hecate_bench_random.tmp", path);
-    let file_size = 100_000_000; // 100MB
-    let block_size = 4096; // 4KB
-    
-    // Create test file
-    let data = vec![0u8; file_size];
-    tokio::fs::write(&test_file, &data).await?;
-    
-    use rand::prelude::*;
-    let mut rng = thread_rng();
-    
-    let start = Instant::now();
-    let mut operations = 0u64;
-    
-    let file = tokio::fs::File::open(&test_file).await?;
-    use tokio::io::{AsyncReadExt, AsyncSeekExt};
-    
-    while start.elapsed().as_secs() < duration {
-        for _ in 0..100 {
-            let mut file = file.try_clone().await?;
-            let offset = rng.gen_range(0..(file_size - block_size)) as u64;
-            file.seek(std::io::SeekFrom::Start(offset)).await?;
-            
-            let mut buffer = vec![0u8; block_size];
-            file.read_exact(&mut buffer).await?;
-            operations += 1;
-        }
-    }
-    
-    // Cleanup
-    let _ = tokio::fs::remove_file(&test_file).await;
-    
-    Ok(operations / duration) // IOPS
+    let dir = std::path::PathBuf::from(path);
+    tokio::task::spawn_blocking(move || disk::random_read(&dir, std::time::Duration::from_secs(duration))).await?
 }
 
 async fn benchmark_disk_random_write(path: &str, duration: u64) -> Result<u64> {
-    let test_file = format!("{}/hecate_bench_random_write.tmp", path);
-    let file_size = 100_000_000; // 100MB
-    let block_size = 4096; // 4KB
-    
-    // Create test file
-    let data = vec![0u8; file_size];
-    tokio::fs::write(&test_file, &data).await?;
-    
-    use rand::prelude::*;
-    let mut rng = thread_rng();
-    
-    let start = Instant::now();
-    let mut operations = 0u64;
-    
-    let file = tokio::fs::OpenOptions::new()
-        .write(true)
-        .open(&test_file)
-        .await?;
-    
-    use tokio::io::{AsyncWriteExt, AsyncSeekExt};
-    
-    while start.elapsed().as_secs() < duration {
-        for _ in 0..100 {
-            let mut file = file.try_clone().await?;
-            let offset = rng.gen_range(0..(file_size - block_size)) as u64;
-            file.seek(std::io::SeekFrom::Start(offset)).await?;
-            
-            let buffer = vec![0u8; block_size];
-            file.write_all(&buffer).await?;
-            operations += 1;
-        }
-    }
-    
-    // Cleanup
-    let _ = tokio::fs::remove_file(&test_file).await;
-    
-    Ok(operations / duration) // IOPS
+    let dir = std::path::PathBuf::from(path);
+    tokio::task::spawn_blocking(move || disk::random_write(&dir, std::time::Duration::from_secs(duration))).await?
 }
 
 // ============================================================================
@@ -1040,32 +1245,27 @@ async fn benchmark_disk_random_write(path: &str, duration: u64) -> Result<u64> {
 // ============================================================================
 
 async fn run_network_test(test: NetworkTest) -> Result<NetworkResults> {
+    let mut results = NetworkResults::default();
+    
     match test {
         NetworkTest::Bandwidth { server } => {
-            let bandwidth = benchmark_network_bandwidth(&server).await?;
-            Ok(NetworkResults {
-                bandwidth_mbps: bandwidth,
-                latency_ms: 0.0,
-                packet_loss_percent: 0.0,
-            })
-        }
-        NetworkTest::Latency { host } => {
-            let latency = benchmark_network_latency(&host).await?;
-            Ok(NetworkResults {
-                bandwidth_mbps: 0.0,
-                latency_ms: latency,
-                packet_loss_percent: 0.0,
-            })
-        }
-        NetworkTest::PacketLoss { host } => {
-            let loss = benchmark_packet_loss(&host).await?;
-            Ok(NetworkResults {
-                bandwidth_mbps: 0.0,
-                latency_ms: 0.0,
-                packet_loss_percent: loss,
-            })
+            results.bandwidth_mbps = Some(benchmark_network_bandwidth(&server).await?);
+        }
+        NetworkTest::Latency { host, count, tcp_port } => {
+            let stats = benchmark_network_latency(&host, count, tcp_port).await?;
+            results.latency_ms = Some(stats.avg_ms);
+            results.latency_min_ms = stats.min_ms;
+            results.latency_max_ms = stats.max_ms;
+            results.latency_stddev_ms = stats.stddev_ms;
+            results.jitter_ms = Some(stats.jitter_ms);
+        }
+        NetworkTest::PacketLoss { host, count, timeout, tcp_port } => {
+            let timeout = std::time::Duration::from_secs(timeout);
+            results.packet_loss_percent = Some(benchmark_packet_loss(&host, count, timeout, tcp_port).await?);
         }
     }
+    
+    Ok(results)
 }
 
 async fn benchmark_network_bandwidth(_server: &str) -> Result<f64> {
@@ -1073,120 +1273,94 @@ async fn benchmark_network_bandwidth(_server: &str) -> Result<f64> {
     Ok(100.0) // Mbps
 }
 
-async fn benchmark_network_latency(_host: &str) -> Result<f64> {
-    // Placeholder - would use actual ping implementation
-    Ok(10.0) // ms
+async fn benchmark_network_latency(host: &str, count: usize, tcp_port: u16) -> Result<net::LatencyStats> {
+    println!("\n{}", format!("Probing {}...", host).bright_yellow());
+    
+    let report = net::probe(host, count, std::time::Duration::from_secs(1), tcp_port).await?;
+    if let net::ProbeMethod::Tcp(port) = report.method {
+        println!("ICMP not permitted, timing TCP connections to port {}", port);
+    }
+    
+    net::latency_stats(&report.rtts_ms)
+        .ok_or_else(|| anyhow::anyhow!("{} did not answer any of {} probes", host, report.sent))
 }
 
-async fn benchmark_packet_loss(_host: &str) -> Result<f64> {
-    // Placeholder - would use actual packet loss test
-    Ok(0.0) // %
+async fn benchmark_packet_loss(host: &str, count: usize, timeout: std::time::Duration, tcp_port: u16) -> Result<f64> {
+    println!("\n{}", format!("Sending {} probes to {}...", count, host).bright_yellow());
+    
+    let report = net::probe(host, count, timeout, tcp_port).await?;
+    Ok(report.loss_percent())
 }
 
 // ============================================================================
 // AI BENCHMARKS
 // ============================================================================
 
-async fn run_ai_benchmarks(duration: u64) -> Result<AiResults> {
+async fn run_ai_benchmarks(duration: u64, naive: bool) -> Result<AiResults> {
     println!("\n{}", "Running AI/ML Benchmarks...".bright_yellow());
     
-    let matmul_gflops = benchmark_matmul(duration / 4).await?;
-    let conv_gops = benchmark_convolution(duration / 4).await?;
+    let (matmul_gflops, matmul_naive_gflops) = benchmark_matmul(duration / 4, naive).await?;
+    let (conv_gops, conv_naive_gops) = benchmark_convolution(duration / 4, naive).await?;
     let transformer_tokens_s = benchmark_transformer(duration / 4).await?;
     let training_samples_s = benchmark_training(duration / 4).await?;
     
     Ok(AiResults {
-        matmul_gflops,
-        conv_gops,
-        transformer_tokens_s,
-        training_samples_s,
+        matmul_gflops: Some(matmul_gflops),
+        conv_gops: Some(conv_gops),
+        matmul_naive_gflops,
+        conv_naive_gops,
+        transformer_tokens_s: Some(transformer_tokens_s),
+        training_samples_s: Some(training_samples_s),
     })
 }
 
-async fn run_ai_test(test: AiTest) -> Result<AiResults> {
-    let duration = 10;
-    
-    let mut results = AiResults {
-        matmul_gflops: 0.0,
-        conv_gops: 0.0,
-        transformer_tokens_s: 0.0,
-        training_samples_s: 0.0,
-    };
-    
+/// Run one AI test for `duration` seconds, filling in its figures
+async fn run_ai_test(test: AiTest, naive: bool, duration: u64, results: &mut AiResults) -> Result<()> {
     match test {
         AiTest::Matmul => {
-            results.matmul_gflops = benchmark_matmul(duration).await?;
+            let (gflops, naive_gflops) = benchmark_matmul(duration, naive).await?;
+            (results.matmul_gflops, results.matmul_naive_gflops) = (Some(gflops), naive_gflops);
         }
         AiTest::Conv => {
-            results.conv_gops = benchmark_convolution(duration).await?;
+            let (gops, naive_gops) = benchmark_convolution(duration, naive).await?;
+            (results.conv_gops, results.conv_naive_gops) = (Some(gops), naive_gops);
         }
         AiTest::Transformer => {
-            results.transformer_tokens_s = benchmark_transformer(duration).await?;
+            results.transformer_tokens_s = Some(benchmark_transformer(duration).await?);
         }
         AiTest::Training => {
-            results.training_samples_s = benchmark_training(duration).await?;
+            results.training_samples_s = Some(benchmark_training(duration).await?);
         }
         AiTest::All => {
-            return run_ai_benchmarks(duration * 4).await;
+            *results = run_ai_benchmarks(duration * 4, naive).await?;
         }
     }
     
-    Ok(results)
+    Ok(())
 }
 
-async fn benchmark_matmul(duration: u64) -> Result<f64> {
-    let size = 512;
-    let start = Instant::now();
-    let mut operations = 0u64;
-    
-    while start.elapsed().as_secs() < duration {
-        // Simple matrix multiplication
-        let a = vec![vec![1.0f32; size]; size];
-        let b = vec![vec![1.0f32; size]; size];
-        let mut c = vec![vec![0.0f32; size]; size];
-        
-        for i in 0..size {
-            for j in 0..size {
-                for k in 0..size {
-                    c[i][j] += a[i][k] * b[k][j];
-                    operations += 2; // multiply + add
-                }
-            }
+/// Optimized GFLOPS and, if `naive`, the scalar loop figure
+async fn benchmark_matmul(duration: u64, naive: bool) -> Result<(f64, Option<f64>)> {
+    tokio::task::spawn_blocking(move || {
+        let budget = std::time::Duration::from_secs(duration.max(1));
+        if naive {
+            (ai::matmul_gflops(budget / 2, false), Some(ai::matmul_gflops(budget / 2, true)))
+        } else {
+            (ai::matmul_gflops(budget, false), None)
         }
-        
-        std::hint::black_box(c);
-    }
-    
-    Ok(operations as f64 / duration as f64 / 1_000_000_000.0) // GFLOPS
+    }).await.map_err(Into::into)
 }
 
-async fn benchmark_convolution(duration: u64) -> Result<f64> {
-    // Simplified 2D convolution
-    let image_size = 224;
-    let kernel_size = 3;
-    let start = Instant::now();
-    let mut operations = 0u64;
-    
-    while start.elapsed().as_secs() < duration {
-        let image = vec![vec![1.0f32; image_size]; image_size];
-        let kernel = vec![vec![1.0f32; kernel_size]; kernel_size];
-        let mut output = vec![vec![0.0f32; image_size - kernel_size + 1]; image_size - kernel_size + 1];
-        
-        for i in 0..(image_size - kernel_size + 1) {
-            for j in 0..(image_size - kernel_size + 1) {
-                for ki in 0..kernel_size {
-                    for kj in 0..kernel_size {
-                        output[i][j] += image[i + ki][j + kj] * kernel[ki][kj];
-                        operations += 2;
-                    }
-                }
-            }
+/// Optimized GOPS and, if `naive`, the scalar loop figure
+async fn benchmark_convolution(duration: u64, naive: bool) -> Result<(f64, Option<f64>)> {
+    tokio::task::spawn_blocking(move || {
+        let budget = std::time::Duration::from_secs(duration.max(1));
+        if naive {
+            (ai::conv_gops(budget / 2, false), Some(ai::conv_gops(budget / 2, true)))
+        } else {
+            (ai::conv_gops(budget, false), None)
         }
-        
-        std::hint::black_box(output);
-    }
-    
-    Ok(operations as f64 / duration as f64 / 1_000_000_000.0) // GOPS
+    }).await.map_err(Into::into)
 }
 
 async fn benchmark_transformer(duration: u64) -> Result<f64> {
@@ -1250,17 +1424,66 @@ async fn benchmark_training(duration: u64) -> Result<f64> {
 // STRESS TEST
 // ============================================================================
 
-async fn run_stress_test(components: Vec<String>, duration: u64, threads: Option<usize>) -> Result<()> {
+async fn run_stress_test(names: Vec<String>, duration: u64, threads: Option<usize>, max_temp: f64) -> Result<()> {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use stress::Component;
+    
+    let mut components = stress::parse_components(&names)?;
+    
     println!("{}", "=== HecateOS Stress Test ===".bright_red());
+    
+    // A GPU asked for by name must be there; `all` loads it only if it is
+    #[cfg(feature = "gpu")]
+    let gpu = if components.contains(&Component::Gpu) {
+        match gpu::GpuBench::new().await {
+            Ok(gpu) => Some(gpu),
+            Err(e) if !stress::named(&names, Component::Gpu) => {
+                println!("{} skipping the GPU: {:#}", "Warning:".yellow(), e);
+                components.retain(|component| *component != Component::Gpu);
+                None
+            }
+            Err(e) => return Err(e),
+        }
+    } else {
+        None
+    };
+    #[cfg(not(feature = "gpu"))]
+    if components.contains(&Component::Gpu) {
+        if stress::named(&names, Component::Gpu) {
+            anyhow::bail!("GPU stress needs hecate-bench built with the gpu feature");
+        }
+        println!("{} skipping the GPU: hecate-bench was built without the gpu feature", "Warning:".yellow());
+        components.retain(|component| *component != Component::Gpu);
+    }
+    
     println!("Duration: {} seconds", duration);
     println!("Components: {:?}", components);
     
     let num_threads = threads.unwrap_or_else(num_cpus::get);
     println!("Threads: {}", num_threads);
+    #[cfg(feature = "gpu")]
+    if let Some(gpu) = &gpu {
+        if let Some(value) = &gpu.name {
+            println!("GPU: {}", value);
+        }
+    }
+    println!("Thermal limit: {:.0}°C", max_temp);
     
     println!("\n{}", "Starting stress test...".yellow());
     println!("Press Ctrl+C to stop\n");
     
+    // Replaces the default SIGINT and SIGTERM handling, so Ctrl+C lets the
+    // workers finish and clean up instead of killing the process
+    let interrupted = std::sync::Arc::new(AtomicBool::new(false));
+    let mut interrupts = scratch::Interrupts::new()?;
+    let ctrl_c = tokio::spawn({
+        let interrupted = interrupted.clone();
+        async move {
+            interrupts.recv().await;
+            interrupted.store(true, Ordering::Relaxed);
+        }
+    });
+    
     let pb = ProgressBar::new(duration);
     pb.set_style(
         ProgressStyle::default_bar()
@@ -1269,27 +1492,92 @@ async fn run_stress_test(components: Vec<String>, duration: u64, threads: Option
     );
     
     let start = Instant::now();
+    let guard = thermal::ThermalGuard::new(max_temp);
+    let stress_file = |worker: usize| {
+        std::env::temp_dir().join(format!("hecate_stress.{}.{}.tmp", std::process::id(), worker))
+    };
     
-    // Run stress workloads in parallel
-    use rayon::prelude::*;
-    
-    (0..num_threads).into_par_iter().for_each(|_| {
-        while start.elapsed().as_secs() < duration {
-            if components.contains(&"cpu".to_string()) {
-                stress_cpu();
-            }
-            if components.contains(&"memory".to_string()) {
-                stress_memory();
+    std::thread::scope(|s| {
+        // Sample sensors once a second until the workers are done
+        s.spawn(|| {
+            while !guard.should_stop() {
+                guard.record(&hecate_core::thermal::read_all());
+                if start.elapsed().as_secs() >= duration || interrupted.load(Ordering::Relaxed) {
+                    guard.stop();
+                    break;
+                }
+                std::thread::sleep(std::time::Duration::from_secs(1));
             }
-            if components.contains(&"disk".to_string()) {
-                let _ = stress_disk();
+        });
+        
+        // One submitting thread is enough to keep the GPU saturated
+        #[cfg(feature = "gpu")]
+        if let Some(gpu) = &gpu {
+            s.spawn(|| gpu.stress(|| guard.should_stop()));
+        }
+        
+        // With only the GPU loaded there is nothing for the workers to do,
+        // so just keep the progress bar moving
+        if components == [Component::Gpu] {
+            while !guard.should_stop() {
+                pb.set_position(start.elapsed().as_secs());
+                std::thread::sleep(std::time::Duration::from_millis(200));
             }
-            
-            pb.set_position(start.elapsed().as_secs());
+            return;
         }
+        
+        // Run stress workloads in parallel
+        use rayon::prelude::*;
+        
+        (0..num_threads).into_par_iter().for_each(|worker| {
+            while start.elapsed().as_secs() < duration && !guard.should_stop() {
+                if components.contains(&Component::Cpu) {
+                    stress_cpu();
+                }
+                if components.contains(&Component::Memory) {
+                    stress_memory();
+                }
+                if components.contains(&Component::Disk) {
+                    let _ = stress_disk(stress_file(worker));
+                }
+                
+                pb.set_position(start.elapsed().as_secs());
+            }
+        });
+        guard.stop();
     });
+    ctrl_c.abort();
     
-    pb.finish_with_message("Stress test complete!");
+    let tripped = guard.tripped();
+    if tripped.is_some() {
+        pb.abandon_with_message("Stopped on thermal limit");
+    } else if interrupted.load(Ordering::Relaxed) {
+        pb.abandon_with_message("Interrupted");
+    } else {
+        pb.finish_with_message("Stress test complete!");
+    }
+    
+    let peaks = guard.peaks();
+    if !peaks.is_empty() {
+        println!("\n{}", "Peak Temperatures:".bright_cyan());
+        for (sensor, celsius) in &peaks {
+            let line = format!("  {:<32} {:.1}°C", sensor, celsius);
+            if *celsius > max_temp {
+                println!("{}", line.red());
+            } else {
+                println!("{}", line);
+            }
+        }
+    } else {
+        println!("\n{} No temperature sensors found", "⚠".yellow());
+    }
+    
+    if let Some(reading) = tripped {
+        anyhow::bail!(
+            "Stress test stopped after {}s: {} reached {:.1}°C (limit {:.0}°C)",
+            start.elapsed().as_secs(), reading.sensor, reading.celsius, guard.max_celsius()
+        );
+    }
     
     Ok(())
 }
@@ -1313,12 +1601,12 @@ fn stress_memory() {
     std::hint::black_box(data);
 }
 
-fn stress_disk() -> Result<()> {
+fn stress_disk(path: std::path::PathBuf) -> Result<()> {
     // Disk intensive workload
+    let scratch = scratch::ScratchFile::new(path);
     let data = vec![0u8; 1_048_576]; // 1MB
-    std::fs::write("/tmp/hecate_stress.tmp", &data)?;
-    let _ = std::fs::read("/tmp/hecate_stress.tmp")?;
-    let _ = std::fs::remove_file("/tmp/hecate_stress.tmp");
+    std::fs::write(scratch.path(), &data)?;
+    let _ = std::fs::read(scratch.path())?;
     Ok(())
 }
 
@@ -1333,6 +1621,7 @@ fn display_results(results: &BenchmarkResults, format: &OutputFormat) -> Result<
             println!("{}", serde_json::to_string_pretty(results)?);
         }
         OutputFormat::Csv => display_results_csv(results)?,
+        OutputFormat::Prometheus => display_results_prometheus(results),
     }
     
     Ok(())
@@ -1341,13 +1630,21 @@ fn display_results(results: &BenchmarkResults, format: &OutputFormat) -> Result<
 fn display_results_text(results: &BenchmarkResults) {
     println!("\n{}", "=== Benchmark Results ===".bright_green());
     
+    if let Some(profile) = &results.profile {
+        println!("\n{} {}", "Profile:".bright_cyan(), profile);
+    }
+    
     // System Info
     println!("\n{}", "System Information:".bright_cyan());
     println!("  Hostname:   {}", results.system_info.hostname);
     println!("  OS:         {}", results.system_info.os);
     println!("  Kernel:     {}", results.system_info.kernel);
     println!("  CPU:        {}", results.system_info.cpu_model);
-    println!("  Cores:      {}", results.system_info.cpu_cores);
+    if results.system_info.cpu_physical_cores > 0 {
+        println!("  Cores:      {} physical, {} threads", results.system_info.cpu_physical_cores, results.system_info.cpu_threads);
+    } else {
+        println!("  Cores:      {}", results.system_info.cpu_cores);
+    }
     println!("  Memory:     {:.2} GB", results.system_info.memory_total_gb);
     if !results.system_info.gpu_info.is_empty() {
         println!("  GPUs:       {:?}", results.system_info.gpu_info);
@@ -1356,102 +1653,175 @@ fn display_results_text(results: &BenchmarkResults) {
     // CPU Results
     if let Some(cpu) = &results.cpu_results {
         println!("\n{}", "CPU Performance:".bright_cyan());
-        println!("  Single-thread:  {:.0} ops/s", cpu.single_thread_score);
-        println!("  Multi-thread:   {:.0} ops/s", cpu.multi_thread_score);
-        println!("  Float:          {:.2} MFLOPS", cpu.float_mflops);
-        println!("  Integer:        {:.2} MIPS", cpu.integer_mips);
-        println!("  Crypto:         {:.2} MB/s", cpu.crypto_mb_s);
-        println!("  Cache Latency:  {:.2} ns", cpu.cache_latency_ns);
-        println!("  Branch Pred:    {:.2} M/s", cpu.branch_mpred_s);
+        if let Some(score) = &cpu.single_thread_score {
+            println!("  Single-thread:  {:.0} ops/s (n={})", score, score.samples);
+        }
+        if let Some(score) = &cpu.multi_thread_score {
+            println!("  Multi-thread:   {:.0} ops/s (n={})", score, score.samples);
+        }
+        if let Some(value) = &cpu.float_mflops {
+            println!("  Float:          {:.2} MFLOPS", value);
+        }
+        if let Some(value) = &cpu.integer_mips {
+            println!("  Integer:        {:.2} MIPS", value);
+        }
+        if let Some(value) = &cpu.crypto_mb_s {
+            println!("  Crypto:         {:.2} MB/s", value);
+        }
+        if let Some(value) = &cpu.cache_latency_ns {
+            println!("  Cache Latency:  {:.2} ns", value);
+        }
+        if let Some(value) = &cpu.branch_mpred_s {
+            println!("  Branch Pred:    {:.2} M/s", value);
+        }
+        
+        if !cpu.per_core_scores.is_empty() {
+            println!("  Per-core:");
+            for (core, score) in cpu.per_core_scores.iter().enumerate() {
+                println!("    Core {:<3}      {:.0} ops/s", core, score);
+            }
+        }
+        if !cpu.scaling.is_empty() {
+            println!("  Scaling:");
+            for point in &cpu.scaling {
+                println!(
+                    "    {:>3} threads{}  {:.0} ops/s  {:.0}%",
+                    point.threads,
+                    if point.smt { " (SMT)" } else { "      " },
+                    point.score,
+                    point.efficiency * 100.0
+                );
+            }
+        }
     }
     
     // GPU Results
     if let Some(gpu) = &results.gpu_results {
         println!("\n{}", "GPU Performance:".bright_cyan());
-        println!("  CUDA:           {:.2} GFLOPS", gpu.cuda_gflops);
-        println!("  Tensor:         {:.2} TFLOPS", gpu.tensor_tflops);
-        println!("  Memory BW:      {:.2} GB/s", gpu.memory_bandwidth_gb_s);
-        println!("  Ray Tracing:    {:.2} Mrays/s", gpu.raytracing_mrays_s);
-        println!("  Inference:      {:.2} img/s", gpu.inference_images_s);
+        println!("  Adapter:        {}", gpu.adapter);
+        if let Some(value) = &gpu.cuda_gflops {
+            println!("  Compute (FMA):  {:.2} GFLOPS", value);
+        }
+        if let Some(value) = &gpu.tensor_tflops {
+            println!("  MatMul:         {:.2} TFLOPS", value);
+        }
+        if let Some(value) = &gpu.memory_bandwidth_gb_s {
+            println!("  Memory BW:      {:.2} GB/s", value);
+        }
+        if let Some(mrays) = gpu.raytracing_mrays_s {
+            println!("  Ray Tracing:    {:.2} Mrays/s", mrays);
+        }
+        if let Some(images) = gpu.inference_images_s {
+            println!("  Inference:      {:.2} img/s", images);
+        }
     }
     
     // Memory Results
     if let Some(mem) = &results.memory_results {
         println!("\n{}", "Memory Performance:".bright_cyan());
-        println!("  Seq Read:       {:.2} GB/s", mem.seq_read_gb_s);
-        println!("  Seq Write:      {:.2} GB/s", mem.seq_write_gb_s);
-        println!("  Random Access:  {:.2} MOPS", mem.random_access_mops);
-        println!("  Latency:        {:.2} ns", mem.latency_ns);
-        println!("  Bandwidth:      {:.2} GB/s", mem.bandwidth_gb_s);
+        if let Some(value) = &mem.seq_read_gb_s {
+            println!("  Seq Read:       {:.2} GB/s", value);
+        }
+        if let Some(value) = &mem.seq_write_gb_s {
+            println!("  Seq Write:      {:.2} GB/s", value);
+        }
+        if let Some(value) = &mem.random_access_mops {
+            println!("  Random Access:  {:.2} MOPS", value);
+        }
+        if let Some(value) = &mem.latency_ns {
+            println!("  Latency:        {:.2} ns", value);
+        }
+        if let Some(value) = &mem.bandwidth_gb_s {
+            println!("  Bandwidth:      {:.2} GB/s", value);
+        }
+        
+        if !mem.latency_curve.is_empty() {
+            println!("  Latency by working set:");
+            for point in &mem.latency_curve {
+                println!("    {:>8}      {:.2} ns", latency::format_size(point.working_set_bytes), point.latency_ns);
+            }
+        }
     }
     
     // Disk Results
     if let Some(disk) = &results.disk_results {
         println!("\n{}", "Disk Performance:".bright_cyan());
-        println!("  Seq Read:       {:.2} MB/s", disk.seq_read_mb_s);
-        println!("  Seq Write:      {:.2} MB/s", disk.seq_write_mb_s);
-        println!("  4K Read:        {} IOPS", disk.random_4k_read_iops);
-        println!("  4K Write:       {} IOPS", disk.random_4k_write_iops);
+        if let Some(value) = &disk.seq_read_mb_s {
+            println!("  Seq Read:       {:.2} MB/s", value);
+        }
+        if let Some(value) = &disk.seq_write_mb_s {
+            println!("  Seq Write:      {:.2} MB/s", value);
+        }
+        if let Some(value) = &disk.random_4k_read_iops {
+            println!("  4K Read:        {} IOPS", value);
+        }
+        if let Some(value) = &disk.random_4k_write_iops {
+            println!("  4K Write:       {} IOPS", value);
+        }
+    }
+    
+    // Network Results
+    if let Some(network) = &results.network_results {
+        println!("\n{}", "Network Performance:".bright_cyan());
+        if let Some(bandwidth) = network.bandwidth_mbps {
+            println!("  Bandwidth:      {:.2} Mbps", bandwidth);
+        }
+        if let Some(latency) = network.latency_ms {
+            println!("  Latency:        {:.2} ms (min {:.2}, max {:.2}, stddev {:.2})",
+                latency, network.latency_min_ms, network.latency_max_ms, network.latency_stddev_ms);
+        }
+        if let Some(jitter) = network.jitter_ms {
+            println!("  Jitter:         {:.2} ms", jitter);
+        }
+        if let Some(loss) = network.packet_loss_percent {
+            println!("  Packet Loss:    {:.1}%", loss);
+        }
     }
     
     // AI Results
     if let Some(ai) = &results.ai_results {
         println!("\n{}", "AI/ML Performance:".bright_cyan());
-        println!("  MatMul:         {:.2} GFLOPS", ai.matmul_gflops);
-        println!("  Convolution:    {:.2} GOPS", ai.conv_gops);
-        println!("  Transformer:    {:.2} tokens/s", ai.transformer_tokens_s);
-        println!("  Training:       {:.2} samples/s", ai.training_samples_s);
+        if let Some(gflops) = ai.matmul_gflops {
+            match ai.matmul_naive_gflops {
+                Some(naive) => println!("  MatMul:         {:.2} GFLOPS (naive {:.2})", gflops, naive),
+                None => println!("  MatMul:         {:.2} GFLOPS", gflops),
+            }
+        }
+        if let Some(gops) = ai.conv_gops {
+            match ai.conv_naive_gops {
+                Some(naive) => println!("  Convolution:    {:.2} GOPS (naive {:.2})", gops, naive),
+                None => println!("  Convolution:    {:.2} GOPS", gops),
+            }
+        }
+        if let Some(value) = &ai.transformer_tokens_s {
+            println!("  Transformer:    {:.2} tokens/s", value);
+        }
+        if let Some(value) = &ai.training_samples_s {
+            println!("  Training:       {:.2} samples/s", value);
+        }
+    }
+    
+    // Registered benchmarks
+    for (name, set) in &results.custom_results {
+        println!("\n{}", format!("{}:", name).bright_cyan());
+        for metric in &set.metrics {
+            println!("  {:<15} {:.2} {}", format!("{}:", metric.name), metric.value, metric.unit);
+        }
     }
 }
 
 fn display_results_csv(results: &BenchmarkResults) -> Result<()> {
-    let mut wtr = csv::Writer::from_writer(std::io::stdout());
-    
-    wtr.write_record(&["Metric", "Value", "Unit"])?;
-    
-    if let Some(cpu) = &results.cpu_results {
-        wtr.write_record(&["CPU Single-thread", &cpu.single_thread_score.to_string(), "ops/s"])?;
-        wtr.write_record(&["CPU Multi-thread", &cpu.multi_thread_score.to_string(), "ops/s"])?;
-        wtr.write_record(&["CPU Float", &cpu.float_mflops.to_string(), "MFLOPS"])?;
-        wtr.write_record(&["CPU Integer", &cpu.integer_mips.to_string(), "MIPS"])?;
-    }
-    
-    if let Some(mem) = &results.memory_results {
-        wtr.write_record(&["Memory Seq Read", &mem.seq_read_gb_s.to_string(), "GB/s"])?;
-        wtr.write_record(&["Memory Seq Write", &mem.seq_write_gb_s.to_string(), "GB/s"])?;
-    }
-    
-    wtr.flush()?;
-    Ok(())
+    csv_export::write_results(results, std::io::stdout())
 }
 
-fn save_results(results: &BenchmarkResults, path: &str) -> Result<()> {
-    let json = serde_json::to_string_pretty(results)?;
-    std::fs::write(path, json)?;
-    Ok(())
+fn display_results_prometheus(results: &BenchmarkResults) {
+    print!("{}", prometheus::render(results));
 }
 
-async fn compare_results(baseline_path: &str, current_path: &str) -> Result<()> {
-    let baseline: BenchmarkResults = serde_json::from_str(&std::fs::read_to_string(baseline_path)?)?;
-    let current: BenchmarkResults = serde_json::from_str(&std::fs::read_to_string(current_path)?)?;
-    
-    println!("{}", "=== Performance Comparison ===".bright_cyan());
-    
-    // Compare CPU
-    if let (Some(base_cpu), Some(curr_cpu)) = (&baseline.cpu_results, &current.cpu_results) {
-        println!("\n{}", "CPU Performance:".bright_yellow());
-        
-        let single_diff = (curr_cpu.single_thread_score - base_cpu.single_thread_score) 
-            / base_cpu.single_thread_score * 100.0;
-        let multi_diff = (curr_cpu.multi_thread_score - base_cpu.multi_thread_score) 
-            / base_cpu.multi_thread_score * 100.0;
-        
-        let _single_color = if single_diff > 0.0 { "green" } else { "red" };
-        let _multi_color = if multi_diff > 0.0 { "green" } else { "red" };
-        
-        println!("  Single-thread: {:+.1}%", single_diff);
-        println!("  Multi-thread:  {:+.1}%", multi_diff);
-    }
-    
-    Ok(())
-}
\ No newline at end of file
+fn save_results(results: &BenchmarkResults, path: &str, format: &OutputFormat) -> Result<()> {
+    let contents = match format {
+        OutputFormat::Prometheus => prometheus::render(results),
+        _ => serde_json::to_string_pretty(results)?,
+    };
+    write_atomic(std::path::Path::new(path), contents.as_bytes())
+}
@@ -0,0 +1,261 @@
+//! Network latency probes
+//!
+//! Uses unprivileged ICMP echo sockets where the kernel allows them
+//! (`net.ipv4.ping_group_range`), and otherwise times TCP connections.
+
+use anyhow::{Context, Result};
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+
+/// How a host was probed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProbeMethod {
+    Icmp,
+    Tcp(u16),
+}
+
+/// Outcome of a series of probes
+#[derive(Debug, Clone)]
+pub struct ProbeReport {
+    pub method: ProbeMethod,
+    pub sent: usize,
+    /// Round-trip times of answered probes, in order
+    pub rtts_ms: Vec<f64>,
+}
+
+impl ProbeReport {
+    pub fn loss_percent(&self) -> f64 {
+        if self.sent == 0 {
+            return 0.0;
+        }
+        (self.sent - self.rtts_ms.len()) as f64 / self.sent as f64 * 100.0
+    }
+}
+
+/// Summary statistics over round-trip times
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencyStats {
+    pub min_ms: f64,
+    pub avg_ms: f64,
+    pub max_ms: f64,
+    pub stddev_ms: f64,
+    /// Mean difference between consecutive round trips
+    pub jitter_ms: f64,
+}
+
+/// Statistics for `rtts`, or `None` if there are none
+pub fn latency_stats(rtts: &[f64]) -> Option<LatencyStats> {
+    if rtts.is_empty() {
+        return None;
+    }
+
+    let n = rtts.len() as f64;
+    let avg = rtts.iter().sum::<f64>() / n;
+    let variance = rtts.iter().map(|r| (r - avg).powi(2)).sum::<f64>() / n;
+    let jitter = if rtts.len() > 1 {
+        rtts.windows(2).map(|w| (w[1] - w[0]).abs()).sum::<f64>() / (n - 1.0)
+    } else {
+        0.0
+    };
+
+    Some(LatencyStats {
+        min_ms: rtts.iter().cloned().fold(f64::INFINITY, f64::min),
+        avg_ms: avg,
+        max_ms: rtts.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+        stddev_ms: variance.sqrt(),
+        jitter_ms: jitter,
+    })
+}
+
+/// Resolve `host` to an address, preferring IPv4
+pub async fn resolve(host: &str) -> Result<IpAddr> {
+    if let Ok(ip) = host.parse() {
+        return Ok(ip);
+    }
+
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, 0)).await
+        .with_context(|| format!("Failed to resolve host {}", host))?
+        .collect();
+
+    addrs.iter()
+        .find(|a| a.is_ipv4())
+        .or_else(|| addrs.first())
+        .map(|a| a.ip())
+        .ok_or_else(|| anyhow::anyhow!("Host {} has no addresses", host))
+}
+
+/// Send `count` probes to `host`, by ICMP if permitted and otherwise by
+/// connecting to `tcp_port`
+pub async fn probe(host: &str, count: usize, timeout: Duration, tcp_port: u16) -> Result<ProbeReport> {
+    let ip = resolve(host).await?;
+
+    let socket = match open_ping_socket(ip) {
+        Ok(socket) => socket,
+        Err(e) => {
+            tracing::debug!("ICMP not permitted ({}), timing TCP connections instead", e);
+            return probe_tcp(SocketAddr::new(ip, tcp_port), count, timeout).await;
+        }
+    };
+
+    tokio::task::spawn_blocking(move || probe_icmp(socket, ip, count, timeout)).await?
+        .with_context(|| format!("Failed to ping {}", host))
+}
+
+/// Open an unprivileged ICMP datagram socket connected to `ip`
+fn open_ping_socket(ip: IpAddr) -> std::io::Result<std::net::UdpSocket> {
+    use socket2::{Domain, Protocol, Socket, Type};
+
+    let (domain, protocol) = match ip {
+        IpAddr::V4(_) => (Domain::IPV4, Protocol::ICMPV4),
+        IpAddr::V6(_) => (Domain::IPV6, Protocol::ICMPV6),
+    };
+
+    // The socket is only ever used through send/recv, which work the same
+    // on ICMP datagram sockets as on UDP ones
+    let socket: std::net::UdpSocket = Socket::new(domain, Type::DGRAM, Some(protocol))?.into();
+    socket.connect(SocketAddr::new(ip, 0))?;
+    Ok(socket)
+}
+
+/// Send echo requests over a ping socket and wait for each reply
+fn probe_icmp(socket: std::net::UdpSocket, ip: IpAddr, count: usize, timeout: Duration) -> std::io::Result<ProbeReport> {
+    let (request, reply) = match ip {
+        IpAddr::V4(_) => (8u8, 0u8),
+        IpAddr::V6(_) => (128u8, 129u8),
+    };
+
+    let mut rtts_ms = Vec::new();
+    let mut buf = [0u8; 1500];
+    for seq in 0..count as u16 {
+        let packet = echo_request(request, seq);
+        let sent_at = Instant::now();
+        if let Err(e) = socket.send(&packet) {
+            if is_unreachable(&e) {
+                return Err(e);
+            }
+            continue;
+        }
+
+        // Skip stray replies until ours arrives or the timeout runs out
+        loop {
+            let remaining = match timeout.checked_sub(sent_at.elapsed()) {
+                Some(remaining) if !remaining.is_zero() => remaining,
+                _ => break,
+            };
+            socket.set_read_timeout(Some(remaining))?;
+
+            match socket.recv(&mut buf) {
+                Ok(n) if n >= 8 && buf[0] == reply && u16::from_be_bytes([buf[6], buf[7]]) == seq => {
+                    rtts_ms.push(sent_at.elapsed().as_secs_f64() * 1000.0);
+                    break;
+                }
+                Ok(_) => continue,
+                Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => break,
+                Err(e) if is_unreachable(&e) => return Err(e),
+                Err(_) => break,
+            }
+        }
+    }
+
+    Ok(ProbeReport {
+        method: ProbeMethod::Icmp,
+        sent: count,
+        rtts_ms,
+    })
+}
+
+/// ICMP echo request; the kernel fills in the identifier for ping sockets
+fn echo_request(kind: u8, seq: u16) -> Vec<u8> {
+    let mut packet = vec![kind, 0, 0, 0, 0, 0];
+    packet.extend_from_slice(&seq.to_be_bytes());
+    packet.extend_from_slice(b"hecate-bench-ping");
+
+    let checksum = icmp_checksum(&packet);
+    packet[2..4].copy_from_slice(&checksum.to_be_bytes());
+    packet
+}
+
+fn icmp_checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data.chunks(2)
+        .map(|c| u16::from_be_bytes([c[0], *c.get(1).unwrap_or(&0)]) as u32)
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+fn is_unreachable(e: &std::io::Error) -> bool {
+    // ENETUNREACH and EHOSTUNREACH on Linux
+    matches!(e.raw_os_error(), Some(101) | Some(113))
+}
+
+/// Time TCP handshakes; a refused connection still proves the host answered
+pub async fn probe_tcp(addr: SocketAddr, count: usize, timeout: Duration) -> Result<ProbeReport> {
+    let mut rtts_ms = Vec::new();
+
+    for _ in 0..count {
+        let started = Instant::now();
+        match tokio::time::timeout(timeout, tokio::net::TcpStream::connect(addr)).await {
+            Ok(Ok(_)) => rtts_ms.push(started.elapsed().as_secs_f64() * 1000.0),
+            Ok(Err(e)) if e.kind() == std::io::ErrorKind::ConnectionRefused => {
+                rtts_ms.push(started.elapsed().as_secs_f64() * 1000.0);
+            }
+            Ok(Err(e)) if is_unreachable(&e) => {
+                return Err(anyhow::anyhow!("{} is unreachable: {}", addr.ip(), e));
+            }
+            Ok(Err(_)) | Err(_) => {}
+        }
+    }
+
+    Ok(ProbeReport {
+        method: ProbeMethod::Tcp(addr.port()),
+        sent: count,
+        rtts_ms,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_stats() {
+        let stats = latency_stats(&[10.0, 12.0, 8.0, 10.0]).unwrap();
+        assert_eq!(stats.min_ms, 8.0);
+        assert_eq!(stats.max_ms, 12.0);
+        assert_eq!(stats.avg_ms, 10.0);
+        assert!((stats.stddev_ms - 2.0f64.sqrt()).abs() < 1e-9);
+        // |12-10| + |8-12| + |10-8| over three steps
+        assert!((stats.jitter_ms - 8.0 / 3.0).abs() < 1e-9);
+
+        assert!(latency_stats(&[]).is_none());
+    }
+
+    #[test]
+    fn test_icmp_checksum_verifies() {
+        let packet = echo_request(8, 7);
+        assert_eq!(icmp_checksum(&packet), 0);
+    }
+
+    #[tokio::test]
+    async fn test_probe_localhost() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                drop(socket);
+            }
+        });
+
+        let report = probe("localhost", 5, Duration::from_secs(1), port).await.unwrap();
+        assert_eq!(report.sent, 5);
+        assert_eq!(report.loss_percent(), 0.0);
+        assert!(latency_stats(&report.rtts_ms).unwrap().max_ms < 1000.0);
+    }
+
+    #[tokio::test]
+    async fn test_unresolvable_host_is_an_error() {
+        assert!(probe("no-such-host.invalid", 1, Duration::from_millis(100), 80).await.is_err());
+    }
+}
@@ -0,0 +1,197 @@
+//! Benchmark profiles
+//!
+//! A profile is a TOML file naming the sub-tests to run and for how long,
+//! so runs like "quick-cpu-only" or "nightly-full" are reproducible:
+//!
+//! ```toml
+//! name = "nightly-full"
+//! duration = 30            # seconds per test unless it sets its own
+//! disk_path = "/var/tmp"
+//!
+//! [[test]]
+//! name = "cpu.all"
+//!
+//! [[test]]
+//! name = "memory.latency"
+//! duration = 5
+//! ```
+//!
+//! Test names are `<suite>.<test>` as on the command line. Network tests
+//! need a host or server and are not available in profiles.
+
+use crate::{AiTest, CpuTest, DiskTest, GpuTest, MemoryTest};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+/// One sub-test of any suite
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BenchTest {
+    Cpu(CpuTest),
+    Gpu(GpuTest),
+    Memory(MemoryTest),
+    Disk(DiskTest),
+    Ai(AiTest),
+}
+
+const TESTS: &[(&str, BenchTest)] = &[
+    ("cpu.single", BenchTest::Cpu(CpuTest::Single)),
+    ("cpu.multi", BenchTest::Cpu(CpuTest::Multi)),
+    ("cpu.float", BenchTest::Cpu(CpuTest::Float)),
+    ("cpu.integer", BenchTest::Cpu(CpuTest::Integer)),
+    ("cpu.crypto", BenchTest::Cpu(CpuTest::Crypto)),
+    ("cpu.cache", BenchTest::Cpu(CpuTest::Cache)),
+    ("cpu.branch", BenchTest::Cpu(CpuTest::Branch)),
+    ("cpu.cores", BenchTest::Cpu(CpuTest::Cores)),
+    ("cpu.all", BenchTest::Cpu(CpuTest::All)),
+    ("gpu.cuda", BenchTest::Gpu(GpuTest::Cuda)),
+    ("gpu.tensor", BenchTest::Gpu(GpuTest::Tensor)),
+    ("gpu.memory", BenchTest::Gpu(GpuTest::Memory)),
+    ("gpu.ray-trace", BenchTest::Gpu(GpuTest::RayTrace)),
+    ("gpu.inference", BenchTest::Gpu(GpuTest::Inference)),
+    ("gpu.all", BenchTest::Gpu(GpuTest::All)),
+    ("memory.seq-read", BenchTest::Memory(MemoryTest::SeqRead)),
+    ("memory.seq-write", BenchTest::Memory(MemoryTest::SeqWrite)),
+    ("memory.random", BenchTest::Memory(MemoryTest::Random)),
+    ("memory.latency", BenchTest::Memory(MemoryTest::Latency)),
+    ("memory.bandwidth", BenchTest::Memory(MemoryTest::Bandwidth)),
+    ("memory.all", BenchTest::Memory(MemoryTest::All)),
+    ("disk.seq-read", BenchTest::Disk(DiskTest::SeqRead)),
+    ("disk.seq-write", BenchTest::Disk(DiskTest::SeqWrite)),
+    ("disk.random4k", BenchTest::Disk(DiskTest::Random4k)),
+    ("disk.iops", BenchTest::Disk(DiskTest::Iops)),
+    ("disk.all", BenchTest::Disk(DiskTest::All)),
+    ("ai.matmul", BenchTest::Ai(AiTest::Matmul)),
+    ("ai.conv", BenchTest::Ai(AiTest::Conv)),
+    ("ai.transformer", BenchTest::Ai(AiTest::Transformer)),
+    ("ai.training", BenchTest::Ai(AiTest::Training)),
+    ("ai.all", BenchTest::Ai(AiTest::All)),
+];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub name: String,
+    /// Seconds per test unless the test sets its own
+    #[serde(default = "default_duration")]
+    pub duration: u64,
+    /// Target path for disk tests
+    #[serde(default = "default_disk_path")]
+    pub disk_path: String,
+    /// Also run the naive AI kernels
+    #[serde(default)]
+    pub naive: bool,
+    #[serde(rename = "test")]
+    pub tests: Vec<ProfileTest>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileTest {
+    pub name: String,
+    pub duration: Option<u64>,
+}
+
+/// A test to run, in profile order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScheduledTest {
+    pub test: BenchTest,
+    pub duration: u64,
+}
+
+fn default_duration() -> u64 {
+    10
+}
+
+fn default_disk_path() -> String {
+    "/var/tmp".to_string()
+}
+
+impl Profile {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read profile {}", path.display()))?;
+        Self::parse(&content).with_context(|| format!("Invalid profile {}", path.display()))
+    }
+
+    /// Parse a profile, rejecting unknown test names
+    pub fn parse(content: &str) -> Result<Self> {
+        let profile: Self = toml::from_str(content)?;
+        if profile.tests.is_empty() {
+            anyhow::bail!("Profile '{}' lists no tests", profile.name);
+        }
+        profile.schedule()?;
+        Ok(profile)
+    }
+
+    /// The tests to run with their durations
+    pub fn schedule(&self) -> Result<Vec<ScheduledTest>> {
+        let mut schedule = Vec::new();
+        let mut unknown = Vec::new();
+        for entry in &self.tests {
+            match TESTS.iter().find(|(name, _)| *name == entry.name) {
+                Some(&(_, test)) => schedule.push(ScheduledTest {
+                    test,
+                    duration: entry.duration.unwrap_or(self.duration),
+                }),
+                None => unknown.push(entry.name.as_str()),
+            }
+        }
+
+        if !unknown.is_empty() {
+            let known: Vec<&str> = TESTS.iter().map(|(name, _)| *name).collect();
+            anyhow::bail!("Unknown tests {}; known tests are {}", unknown.join(", "), known.join(", "));
+        }
+        Ok(schedule)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_profile_schedule() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nightly.toml");
+        std::fs::write(&path, r#"
+            name = "quick-cpu-only"
+            duration = 5
+
+            [[test]]
+            name = "cpu.single"
+
+            [[test]]
+            name = "cpu.multi"
+            duration = 20
+
+            [[test]]
+            name = "memory.latency"
+        "#).unwrap();
+
+        let profile = Profile::load(&path).unwrap();
+        assert_eq!(profile.name, "quick-cpu-only");
+        assert_eq!(profile.disk_path, "/var/tmp");
+        assert_eq!(profile.schedule().unwrap(), vec![
+            ScheduledTest { test: BenchTest::Cpu(CpuTest::Single), duration: 5 },
+            ScheduledTest { test: BenchTest::Cpu(CpuTest::Multi), duration: 20 },
+            ScheduledTest { test: BenchTest::Memory(MemoryTest::Latency), duration: 5 },
+        ]);
+    }
+
+    #[test]
+    fn test_unknown_tests_are_rejected() {
+        let err = Profile::parse(r#"
+            name = "typos"
+            [[test]]
+            name = "cpu.singel"
+            [[test]]
+            name = "network.latency"
+        "#).unwrap_err();
+        let message = format!("{:#}", err);
+        assert!(message.contains("Unknown tests cpu.singel, network.latency"), "{}", message);
+
+        assert!(Profile::parse("name = \"empty\"\ntest = []").is_err());
+        assert!(Profile::parse("name = \"x\"\nduraton = 5\n[[test]]\nname = \"cpu.all\"").is_err());
+    }
+}
@@ -0,0 +1,204 @@
+//! Prometheus text exposition of benchmark results
+//!
+//! The output is meant for node_exporter's textfile collector: write it to
+//! a `.prom` file in the collector directory with [`crate::atomic::write_atomic`].
+
+use crate::compare::metrics;
+use crate::BenchmarkResults;
+use std::fmt::Write;
+
+/// Prefix of every exported metric name
+const PREFIX: &str = "hecate_bench";
+
+/// Render every populated metric in `results` as gauges labelled with the
+/// host name
+pub fn render(results: &BenchmarkResults) -> String {
+    let host = format!("host=\"{}\"", escape_label(&results.system_info.hostname));
+    let mut out = String::new();
+
+    gauge(
+        &mut out,
+        "timestamp_seconds",
+        "Time the benchmark run started",
+        &[(host.clone(), results.timestamp.timestamp() as f64)],
+    );
+
+    for metric in metrics(results) {
+        let help = format!("{} ({})", metric.name, metric.unit);
+        gauge(&mut out, &metric.key, &help, &[(host.clone(), metric.value)]);
+    }
+
+    if let Some(cpu) = &results.cpu_results {
+        if !cpu.per_core_scores.is_empty() {
+            let samples: Vec<(String, f64)> = cpu.per_core_scores.iter()
+                .enumerate()
+                .map(|(core, score)| (format!("{},core=\"{}\"", host, core), *score))
+                .collect();
+            gauge(&mut out, "cpu_core_score", "Single-thread score of each physical core (ops/s)", &samples);
+        }
+
+        if !cpu.scaling.is_empty() {
+            let samples: Vec<(String, f64)> = cpu.scaling.iter()
+                .map(|point| (format!("{},threads=\"{}\"", host, point.threads), point.efficiency))
+                .collect();
+            gauge(&mut out, "cpu_scaling_efficiency", "Multi-thread score relative to linear scaling (ratio)", &samples);
+        }
+    }
+
+    if let Some(mem) = &results.memory_results {
+        if !mem.latency_curve.is_empty() {
+            let samples: Vec<(String, f64)> = mem.latency_curve.iter()
+                .map(|point| (format!("{},working_set_bytes=\"{}\"", host, point.working_set_bytes), point.latency_ns))
+                .collect();
+            gauge(&mut out, "memory_latency_by_size_ns", "Dependent-load latency at each working-set size (ns)", &samples);
+        }
+    }
+
+    out
+}
+
+/// Append one gauge family with its HELP and TYPE lines
+fn gauge(out: &mut String, key: &str, help: &str, samples: &[(String, f64)]) {
+    let name = format!("{}_{}", PREFIX, key);
+    let _ = writeln!(out, "# HELP {} {}", name, escape_help(help));
+    let _ = writeln!(out, "# TYPE {} gauge", name);
+    for (labels, value) in samples {
+        let _ = writeln!(out, "{}{{{}}} {}", name, labels, format_value(*value));
+    }
+}
+
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn escape_help(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::latency::LatencyPoint;
+    use crate::schema::parse_results;
+    use std::collections::HashSet;
+
+    const V1_FIXTURE: &str = include_str!("../tests/fixtures/results-v1.json");
+
+    fn is_metric_name(name: &str) -> bool {
+        let mut chars = name.chars();
+        matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_' || c == ':')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+    }
+
+    /// Check `labels` (without braces) is a comma separated list of
+    /// `name="escaped value"` pairs
+    fn valid_labels(labels: &str) -> bool {
+        let mut rest = labels;
+        while !rest.is_empty() {
+            let Some((name, after)) = rest.split_once("=\"") else {
+                return false;
+            };
+            if !is_metric_name(name) || name.contains(':') {
+                return false;
+            }
+
+            // Find the closing quote, skipping escapes
+            let mut end = None;
+            let mut escaped = false;
+            for (i, c) in after.char_indices() {
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    '"' => {
+                        end = Some(i);
+                        break;
+                    }
+                    '\n' => return false,
+                    _ => {}
+                }
+            }
+            let Some(end) = end else {
+                return false;
+            };
+
+            rest = &after[end + 1..];
+            if let Some(next) = rest.strip_prefix(',') {
+                rest = next;
+            } else if !rest.is_empty() {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Validate text exposition format, returning the sample names
+    fn parse_exposition(text: &str) -> Vec<String> {
+        let mut typed = HashSet::new();
+        let mut helped = HashSet::new();
+        let mut samples = Vec::new();
+
+        for line in text.lines() {
+            if let Some(rest) = line.strip_prefix("# HELP ") {
+                let (name, _) = rest.split_once(' ').expect("HELP has text");
+                assert!(is_metric_name(name), "bad name in {:?}", line);
+                assert!(helped.insert(name.to_string()), "duplicate HELP for {}", name);
+            } else if let Some(rest) = line.strip_prefix("# TYPE ") {
+                let (name, kind) = rest.split_once(' ').expect("TYPE has a kind");
+                assert!(["counter", "gauge", "histogram", "summary", "untyped"].contains(&kind));
+                assert!(typed.insert(name.to_string()), "duplicate TYPE for {}", name);
+            } else {
+                assert!(!line.starts_with('#') && !line.is_empty(), "unexpected line {:?}", line);
+                let (series, value) = line.rsplit_once(' ').expect("sample has a value");
+                let (name, labels) = match series.split_once('{') {
+                    Some((name, labels)) => (name, labels.strip_suffix('}').expect("labels are closed")),
+                    None => (series, ""),
+                };
+                assert!(is_metric_name(name), "bad name in {:?}", line);
+                assert!(valid_labels(labels), "bad labels in {:?}", line);
+                assert!(typed.contains(name), "sample before TYPE in {:?}", line);
+                assert!(
+                    matches!(value, "NaN" | "+Inf" | "-Inf") || value.parse::<f64>().is_ok(),
+                    "bad value in {:?}",
+                    line
+                );
+                samples.push(name.to_string());
+            }
+        }
+
+        samples
+    }
+
+    #[test]
+    fn test_render_is_valid_exposition() {
+        let mut results = parse_results(V1_FIXTURE).unwrap();
+        results.system_info.hostname = "bench \"lab\"\\rack-1".into();
+        results.cpu_results.as_mut().unwrap().per_core_scores = vec![1800.0, 1750.5];
+        results.memory_results.as_mut().unwrap().latency_curve = vec![
+            LatencyPoint { working_set_bytes: 16 * 1024, latency_ns: 1.1 },
+            LatencyPoint { working_set_bytes: 256 * 1024 * 1024, latency_ns: 82.5 },
+        ];
+
+        let text = render(&results);
+        let samples = parse_exposition(&text);
+
+        assert!(samples.contains(&"hecate_bench_cpu_single_thread_score".to_string()));
+        assert!(samples.contains(&"hecate_bench_disk_random_4k_read_iops".to_string()));
+        assert!(samples.contains(&"hecate_bench_memory_latency_ns".to_string()));
+        assert_eq!(samples.iter().filter(|s| *s == "hecate_bench_cpu_core_score").count(), 2);
+        assert_eq!(samples.iter().filter(|s| *s == "hecate_bench_memory_latency_by_size_ns").count(), 2);
+        // AI results were not run
+        assert!(!samples.iter().any(|s| s.starts_with("hecate_bench_ai_")));
+
+        assert!(text.contains("hecate_bench_cpu_single_thread_score{host=\"bench \\\"lab\\\"\\\\rack-1\"} 1843211\n"));
+    }
+}
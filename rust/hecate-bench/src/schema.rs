@@ -0,0 +1,240 @@
+//! Versioning of saved result files
+//!
+//! Result files carry a `schema_version`. Older files are migrated forward
+//! one version at a time as JSON before being deserialized, so baselines
+//! saved by earlier releases keep loading.
+
+use crate::BenchmarkResults;
+use anyhow::{Context, Result};
+use serde_json::{json, Map, Value};
+
+/// Version written by this build; bump it and add a migration whenever
+/// `BenchmarkResults` changes shape
+pub const CURRENT_VERSION: u32 = 8;
+
+/// Read a result file, migrating it from older schema versions
+pub fn load_results(path: &str) -> Result<BenchmarkResults> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path))?;
+    parse_results(&content)
+        .with_context(|| format!("Failed to load results from {}", path))
+}
+
+/// Parse result JSON of any supported schema version
+pub fn parse_results(content: &str) -> Result<BenchmarkResults> {
+    let mut value: Value = serde_json::from_str(content)?;
+    let object = value.as_object_mut()
+        .ok_or_else(|| anyhow::anyhow!("Results are not a JSON object"))?;
+
+    // Files from before versioning have no field and are version 1
+    let mut version = match object.get("schema_version") {
+        None => 1,
+        Some(v) => v.as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| anyhow::anyhow!("Invalid schema_version {}", v))?,
+    };
+
+    if version > CURRENT_VERSION {
+        anyhow::bail!(
+            "Results use schema version {}, but this hecate-bench only supports up to {}; upgrade hecate-bench to read them",
+            version, CURRENT_VERSION
+        );
+    }
+
+    while version < CURRENT_VERSION {
+        match version {
+            1 => migrate_v1_to_v2(object),
+            2 => migrate_v2_to_v3(object),
+            3 => migrate_v3_to_v4(object),
+            4 => migrate_v4_to_v5(object),
+            5 => migrate_v5_to_v6(object),
+            6 => migrate_v6_to_v7(object),
+            7 => migrate_v7_to_v8(object),
+            _ => unreachable!("no migration from schema version {}", version),
+        }
+        version += 1;
+    }
+    object.insert("schema_version".into(), json!(CURRENT_VERSION));
+
+    Ok(serde_json::from_value(value)?)
+}
+
+/// Version 2 added statistics to CPU metrics, the GPU adapter name and
+/// detailed latency figures, and made unmeasured GPU metrics optional
+fn migrate_v1_to_v2(results: &mut Map<String, Value>) {
+    if let Some(Value::Object(cpu)) = results.get_mut("cpu_results") {
+        for (_, metric) in cpu.iter_mut() {
+            if let Some(value) = metric.as_f64() {
+                let samples = if value == 0.0 { 0 } else { 1 };
+                *metric = json!({ "value": value, "stddev": 0.0, "samples": samples });
+            }
+        }
+    }
+
+    if let Some(Value::Object(gpu)) = results.get_mut("gpu_results") {
+        gpu.entry("adapter").or_insert(json!(""));
+        // Version 1 wrote placeholder figures for these, not measurements
+        gpu.insert("raytracing_mrays_s".into(), Value::Null);
+        gpu.insert("inference_images_s".into(), Value::Null);
+    }
+
+    if let Some(Value::Object(network)) = results.get_mut("network_results") {
+        for field in ["latency_min_ms", "latency_max_ms", "latency_stddev_ms", "jitter_ms"] {
+            network.entry(field).or_insert(json!(0.0));
+        }
+    }
+}
+
+/// Version 3 added per-core CPU scores, the scaling curve and physical
+/// core and thread counts
+fn migrate_v2_to_v3(results: &mut Map<String, Value>) {
+    if let Some(Value::Object(system)) = results.get_mut("system_info") {
+        let logical = system.get("cpu_cores").cloned().unwrap_or(json!(0));
+        system.entry("cpu_threads").or_insert(logical);
+        // Unknown for older files
+        system.entry("cpu_physical_cores").or_insert(json!(0));
+    }
+
+    if let Some(Value::Object(cpu)) = results.get_mut("cpu_results") {
+        cpu.entry("per_core_scores").or_insert(json!([]));
+        cpu.entry("scaling").or_insert(json!([]));
+    }
+}
+
+/// Version 4 measures matmul and convolution with an optimized backend.
+/// Older figures came from the naive loops, so they move to the naive
+/// fields and the optimized ones are left unmeasured (zero)
+fn migrate_v3_to_v4(results: &mut Map<String, Value>) {
+    if let Some(Value::Object(ai)) = results.get_mut("ai_results") {
+        for (old, naive) in [("matmul_gflops", "matmul_naive_gflops"), ("conv_gops", "conv_naive_gops")] {
+            let value = ai.insert(old.into(), json!(0.0)).unwrap_or(Value::Null);
+            ai.insert(naive.into(), value);
+        }
+    }
+}
+
+/// Version 5 records the profile a run was made from; older runs had none
+fn migrate_v4_to_v5(results: &mut Map<String, Value>) {
+    results.entry("profile").or_insert(Value::Null);
+}
+
+/// Version 6 added memory latency at each working-set size
+fn migrate_v5_to_v6(results: &mut Map<String, Value>) {
+    if let Some(Value::Object(memory)) = results.get_mut("memory_results") {
+        memory.entry("latency_curve").or_insert(json!([]));
+    }
+}
+
+/// Version 7 added metrics from registered benchmarks
+fn migrate_v6_to_v7(results: &mut Map<String, Value>) {
+    results.entry("custom_results").or_insert(json!({}));
+}
+
+/// Figures that version 8 leaves null when their benchmark was not run
+const MEASURED_FIELDS: &[(&str, &[&str])] = &[
+    ("cpu_results", &[
+        "single_thread_score", "multi_thread_score", "float_mflops", "integer_mips",
+        "crypto_mb_s", "cache_latency_ns", "branch_mpred_s",
+    ]),
+    ("gpu_results", &["cuda_gflops", "tensor_tflops", "memory_bandwidth_gb_s"]),
+    ("memory_results", &["seq_read_gb_s", "seq_write_gb_s", "random_access_mops", "latency_ns", "bandwidth_gb_s"]),
+    ("disk_results", &["seq_read_mb_s", "seq_write_mb_s", "random_4k_read_iops", "random_4k_write_iops"]),
+    ("network_results", &["bandwidth_mbps", "latency_ms", "jitter_ms", "packet_loss_percent"]),
+    ("ai_results", &["matmul_gflops", "conv_gops", "transformer_tokens_s", "training_samples_s"]),
+];
+
+/// Version 8 stores figures of benchmarks that were not run as null.
+/// Older versions wrote zero, so their zero figures count as not run
+fn migrate_v7_to_v8(results: &mut Map<String, Value>) {
+    for (section, fields) in MEASURED_FIELDS {
+        let Some(Value::Object(section)) = results.get_mut(*section) else {
+            continue;
+        };
+        for field in *fields {
+            if let Some(figure) = section.get_mut(*field) {
+                // CPU figures are measurements with statistics
+                if figure.get("value").unwrap_or(figure).as_f64() == Some(0.0) {
+                    *figure = Value::Null;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::Measurement;
+
+    const V1_FIXTURE: &str = include_str!("../tests/fixtures/results-v1.json");
+
+    #[test]
+    fn test_load_v1_fixture() {
+        let results = parse_results(V1_FIXTURE).unwrap();
+        assert_eq!(results.schema_version, CURRENT_VERSION);
+        assert_eq!(results.system_info.cpu_cores, 32);
+        assert_eq!(results.system_info.cpu_threads, 32);
+        assert_eq!(results.system_info.cpu_physical_cores, 0);
+
+        let cpu = results.cpu_results.unwrap();
+        assert_eq!(cpu.single_thread_score, Some(Measurement::single(1843211.0)));
+        assert_eq!(cpu.cache_latency_ns.unwrap().value, 1.2);
+        assert!(cpu.per_core_scores.is_empty());
+
+        let gpu = results.gpu_results.unwrap();
+        assert_eq!(gpu.adapter, "");
+        assert_eq!(gpu.cuda_gflops, Some(82580.0));
+        assert_eq!(gpu.raytracing_mrays_s, None);
+
+        let network = results.network_results.unwrap();
+        assert_eq!(network.latency_ms, Some(12.4));
+        // Version 1 had no jitter, so it was never measured
+        assert_eq!(network.jitter_ms, None);
+
+        let memory = results.memory_results.unwrap();
+        assert_eq!(memory.latency_ns, Some(78.5));
+        assert!(memory.latency_curve.is_empty());
+
+        assert_eq!(results.disk_results.unwrap().random_4k_read_iops, Some(812000));
+        assert!(results.ai_results.is_none());
+    }
+
+    #[test]
+    fn test_naive_ai_figures_migrate() {
+        let mut value: Value = serde_json::from_str(V1_FIXTURE).unwrap();
+        value["schema_version"] = json!(3);
+        value["ai_results"] = json!({
+            "matmul_gflops": 2.5,
+            "conv_gops": 1.5,
+            "transformer_tokens_s": 40.0,
+            "training_samples_s": 300.0,
+        });
+
+        let ai = parse_results(&value.to_string()).unwrap().ai_results.unwrap();
+        assert_eq!(ai.matmul_gflops, None);
+        assert_eq!(ai.matmul_naive_gflops, Some(2.5));
+        assert_eq!(ai.conv_naive_gops, Some(1.5));
+        assert_eq!(ai.transformer_tokens_s, Some(40.0));
+    }
+
+    #[test]
+    fn test_current_version_round_trips() {
+        let migrated = parse_results(V1_FIXTURE).unwrap();
+        let saved = serde_json::to_string(&migrated).unwrap();
+        let loaded = parse_results(&saved).unwrap();
+        assert_eq!(loaded.schema_version, CURRENT_VERSION);
+        assert_eq!(
+            loaded.cpu_results.unwrap().multi_thread_score,
+            migrated.cpu_results.unwrap().multi_thread_score
+        );
+    }
+
+    #[test]
+    fn test_newer_version_is_rejected() {
+        let mut value: Value = serde_json::from_str(V1_FIXTURE).unwrap();
+        value["schema_version"] = json!(CURRENT_VERSION + 1);
+
+        let err = parse_results(&value.to_string()).unwrap_err();
+        assert!(err.to_string().contains("only supports up to"));
+    }
+}
@@ -0,0 +1,142 @@
+//! Cleanup of benchmark scratch files
+//!
+//! Every scratch file is held by a [`ScratchFile`], which deletes it when
+//! dropped, so early returns and panics clean up. A process killed by
+//! SIGINT or SIGTERM runs no destructors, so the live paths are also kept
+//! in a process-wide list that the handler installed by
+//! [`remove_on_interrupt`] deletes before exiting.
+
+use anyhow::Result;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tokio::signal::unix::{signal, Signal, SignalKind};
+
+/// Paths of every live [`ScratchFile`]
+static LIVE: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+
+/// A file deleted when the guard is dropped or the process is interrupted
+///
+/// Create the guard before the file, so there is no moment when the file
+/// exists but would not be cleaned up.
+#[derive(Debug)]
+pub struct ScratchFile {
+    path: PathBuf,
+}
+
+impl ScratchFile {
+    pub fn new(path: PathBuf) -> Self {
+        LIVE.lock().unwrap().insert(path.clone());
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ScratchFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+        LIVE.lock().unwrap().remove(&self.path);
+    }
+}
+
+/// Delete every live scratch file
+fn remove_all() {
+    // A panicked holder must not stop the cleanup
+    let live = LIVE.lock().unwrap_or_else(|e| e.into_inner());
+    for path in live.iter() {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// SIGINT and SIGTERM, which stop delivering their default action once
+/// registered
+pub struct Interrupts {
+    interrupt: Signal,
+    terminate: Signal,
+}
+
+impl Interrupts {
+    /// Register with the current tokio runtime
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            interrupt: signal(SignalKind::interrupt())?,
+            terminate: signal(SignalKind::terminate())?,
+        })
+    }
+
+    /// Wait for either signal, returning the conventional exit code for it
+    /// (128 + the signal number)
+    pub async fn recv(&mut self) -> i32 {
+        tokio::select! {
+            _ = self.interrupt.recv() => 130,
+            _ = self.terminate.recv() => 143,
+        }
+    }
+}
+
+/// Delete the scratch files and exit on SIGINT or SIGTERM
+///
+/// The handler runs on its own thread, so it is not starved by benchmarks
+/// that keep the runtime's workers busy.
+pub fn remove_on_interrupt() -> Result<()> {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    // Registered before returning, so no signal slips through
+    let mut interrupts = {
+        let _context = runtime.enter();
+        Interrupts::new()?
+    };
+
+    std::thread::spawn(move || {
+        let code = runtime.block_on(interrupts.recv());
+        remove_all();
+        std::process::exit(code);
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn live(path: &Path) -> bool {
+        LIVE.lock().unwrap().contains(path)
+    }
+
+    fn fail_midway(path: &Path) -> Result<()> {
+        let scratch = ScratchFile::new(path.to_path_buf());
+        std::fs::write(scratch.path(), vec![0u8; 4096])?;
+        assert!(live(path));
+        anyhow::bail!("benchmark failed")
+    }
+
+    #[test]
+    fn test_guard_removes_file_on_early_return() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hecate_bench_read.tmp");
+
+        assert!(fail_midway(&path).is_err());
+        assert!(!path.exists());
+        assert!(!live(&path));
+
+        // Also when the file was never created
+        drop(ScratchFile::new(path.clone()));
+        assert!(!live(&path));
+    }
+
+    #[test]
+    fn test_guard_removes_file_on_panic() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hecate_stress.tmp");
+
+        let result = std::panic::catch_unwind(|| {
+            let scratch = ScratchFile::new(path.clone());
+            std::fs::write(scratch.path(), b"data").unwrap();
+            panic!("benchmark panicked");
+        });
+        assert!(result.is_err());
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,171 @@
+//! Repeated benchmark runs and their statistics
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+
+/// How many times to repeat each benchmark
+#[derive(Debug, Clone, Copy)]
+pub struct RunConfig {
+    /// Measured runs
+    pub runs: usize,
+    /// Runs before the measured ones whose results are discarded
+    pub warmup: usize,
+}
+
+impl RunConfig {
+    /// Split `total_secs` evenly over every run including warmup, giving
+    /// each at least a second
+    pub fn per_run_secs(&self, total_secs: u64) -> u64 {
+        (total_secs / (self.runs.max(1) + self.warmup) as u64).max(1)
+    }
+}
+
+/// Mean of repeated runs with its sample standard deviation; the default
+/// is a metric that was not run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "MeasurementRepr")]
+pub struct Measurement {
+    pub value: f64,
+    pub stddev: f64,
+    pub samples: usize,
+}
+
+/// Older result files store a bare number per metric
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MeasurementRepr {
+    Plain(f64),
+    Full { value: f64, stddev: f64, samples: usize },
+}
+
+impl From<MeasurementRepr> for Measurement {
+    fn from(repr: MeasurementRepr) -> Self {
+        match repr {
+            MeasurementRepr::Plain(value) => Measurement::single(value),
+            MeasurementRepr::Full { value, stddev, samples } => Measurement { value, stddev, samples },
+        }
+    }
+}
+
+impl Measurement {
+    /// A measurement from one run, or a metric that was not run (0)
+    pub fn single(value: f64) -> Self {
+        Self {
+            value,
+            stddev: 0.0,
+            samples: if value == 0.0 { 0 } else { 1 },
+        }
+    }
+
+    /// Mean and sample standard deviation of `samples`
+    pub fn from_samples(samples: &[f64]) -> Self {
+        let n = samples.len();
+        if n == 0 {
+            return Self::single(0.0);
+        }
+
+        let mean = samples.iter().sum::<f64>() / n as f64;
+        let stddev = if n > 1 {
+            let sum_sq: f64 = samples.iter().map(|s| (s - mean).powi(2)).sum();
+            (sum_sq / (n - 1) as f64).sqrt()
+        } else {
+            0.0
+        };
+
+        Self {
+            value: mean,
+            stddev,
+            samples: n,
+        }
+    }
+}
+
+impl std::fmt::Display for Measurement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let precision = f.precision().unwrap_or(2);
+        if self.samples > 1 {
+            write!(f, "{:.*} ± {:.*}", precision, self.value, precision, self.stddev)
+        } else {
+            write!(f, "{:.*}", precision, self.value)
+        }
+    }
+}
+
+/// Run a benchmark `config.warmup` times discarding the results, then
+/// `config.runs` times and summarize those
+pub async fn measure<F, Fut>(config: RunConfig, mut run: F) -> Result<Measurement>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<f64>>,
+{
+    for _ in 0..config.warmup {
+        run().await?;
+    }
+
+    let mut samples = Vec::with_capacity(config.runs.max(1));
+    for _ in 0..config.runs.max(1) {
+        samples.push(run().await?);
+    }
+
+    Ok(Measurement::from_samples(&samples))
+}
+
+/// [`measure`] for benchmarks that run synchronously
+pub fn measure_blocking<F>(config: RunConfig, mut run: F) -> Result<Measurement>
+where
+    F: FnMut() -> Result<f64>,
+{
+    for _ in 0..config.warmup {
+        run()?;
+    }
+
+    let mut samples = Vec::with_capacity(config.runs.max(1));
+    for _ in 0..config.runs.max(1) {
+        samples.push(run()?);
+    }
+
+    Ok(Measurement::from_samples(&samples))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_samples() {
+        let m = Measurement::from_samples(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]);
+        assert_eq!(m.value, 5.0);
+        assert_eq!(m.samples, 8);
+        // Sum of squared deviations is 32 over n - 1 = 7
+        assert!((m.stddev - (32.0f64 / 7.0).sqrt()).abs() < 1e-12);
+
+        let single = Measurement::from_samples(&[3.5]);
+        assert_eq!(single, Measurement { value: 3.5, stddev: 0.0, samples: 1 });
+    }
+
+    #[tokio::test]
+    async fn test_measure_discards_warmup() {
+        let mut calls = 0.0;
+        let m = measure(RunConfig { runs: 3, warmup: 2 }, || {
+            calls += 1.0;
+            let value = calls;
+            async move { Ok(value) }
+        }).await.unwrap();
+
+        // Runs 1 and 2 are warmup; 3, 4 and 5 are measured
+        assert_eq!(m.value, 4.0);
+        assert_eq!(m.samples, 3);
+        assert_eq!(m.stddev, 1.0);
+    }
+
+    #[test]
+    fn test_plain_numbers_deserialize() {
+        let m: Measurement = serde_json::from_str("12.5").unwrap();
+        assert_eq!(m, Measurement::single(12.5));
+
+        let full = Measurement { value: 1.0, stddev: 0.5, samples: 4 };
+        let round_trip: Measurement = serde_json::from_str(&serde_json::to_string(&full).unwrap()).unwrap();
+        assert_eq!(round_trip, full);
+    }
+}
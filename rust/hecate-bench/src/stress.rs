@@ -0,0 +1,92 @@
+//! Components a stress test can load
+
+use anyhow::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Component {
+    Cpu,
+    Gpu,
+    Memory,
+    Disk,
+}
+
+const COMPONENTS: &[(&str, Component)] = &[
+    ("cpu", Component::Cpu),
+    ("gpu", Component::Gpu),
+    ("memory", Component::Memory),
+    ("disk", Component::Disk),
+];
+
+/// Parse `--components`, where `all` stands for every component
+///
+/// Unknown names are an error, so a typo cannot turn the stress test into
+/// one that loads nothing.
+pub fn parse_components(names: &[String]) -> Result<Vec<Component>> {
+    let mut components = Vec::new();
+    let mut unknown = Vec::new();
+    for name in names {
+        let name = name.trim().to_lowercase();
+        if name == "all" {
+            components.extend(COMPONENTS.iter().map(|&(_, component)| component));
+            continue;
+        }
+        match COMPONENTS.iter().find(|(known, _)| *known == name) {
+            Some(&(_, component)) => components.push(component),
+            None => unknown.push(name),
+        }
+    }
+
+    let known: Vec<&str> = COMPONENTS.iter().map(|(name, _)| *name).collect();
+    if !unknown.is_empty() {
+        anyhow::bail!("Unknown components {}; known components are {} and all", unknown.join(", "), known.join(", "));
+    }
+    if components.is_empty() {
+        anyhow::bail!("No components to stress; pass --components with {} or all", known.join(", "));
+    }
+
+    components.sort_unstable();
+    components.dedup();
+    Ok(components)
+}
+
+/// Whether `component` was asked for by name rather than only through
+/// `all`; only then is it an error when it can't be loaded
+pub fn named(names: &[String], component: Component) -> bool {
+    names.iter().any(|name| {
+        let name = name.trim().to_lowercase();
+        COMPONENTS.iter().any(|&(known, c)| c == component && known == name)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_components() {
+        assert_eq!(
+            parse_components(&names(&["memory", "CPU", "memory"])).unwrap(),
+            vec![Component::Cpu, Component::Memory]
+        );
+        assert_eq!(
+            parse_components(&names(&["disk", "all"])).unwrap(),
+            vec![Component::Cpu, Component::Gpu, Component::Memory, Component::Disk]
+        );
+
+        let message = parse_components(&names(&["cpi", "gpu", "ram"])).unwrap_err().to_string();
+        assert!(message.starts_with("Unknown components cpi, ram;"), "{}", message);
+
+        assert!(parse_components(&[]).is_err());
+    }
+
+    #[test]
+    fn test_named() {
+        assert!(named(&names(&["cpu", "GPU"]), Component::Gpu));
+        assert!(!named(&names(&["all"]), Component::Gpu));
+        assert!(!named(&names(&["cpu"]), Component::Gpu));
+    }
+}
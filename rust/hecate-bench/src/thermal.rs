@@ -0,0 +1,98 @@
+//! Thermal safety limit for stress tests
+
+use hecate_core::thermal::SensorReading;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// Tracks peak temperatures and signals workers to stop once any sensor
+/// passes `max_celsius`
+pub struct ThermalGuard {
+    max_celsius: f64,
+    stop: AtomicBool,
+    peaks: Mutex<BTreeMap<String, f64>>,
+    tripped: Mutex<Option<SensorReading>>,
+}
+
+impl ThermalGuard {
+    pub fn new(max_celsius: f64) -> Self {
+        Self {
+            max_celsius,
+            stop: AtomicBool::new(false),
+            peaks: Mutex::new(BTreeMap::new()),
+            tripped: Mutex::new(None),
+        }
+    }
+
+    /// Record a round of readings, tripping the guard if one is over the
+    /// limit; the first sensor to trip is kept
+    pub fn record(&self, readings: &[SensorReading]) {
+        let mut peaks = self.peaks.lock().unwrap();
+        for reading in readings {
+            let peak = peaks.entry(reading.sensor.clone()).or_insert(reading.celsius);
+            *peak = peak.max(reading.celsius);
+
+            if reading.celsius > self.max_celsius {
+                let mut tripped = self.tripped.lock().unwrap();
+                if tripped.is_none() {
+                    *tripped = Some(reading.clone());
+                }
+                self.stop.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Whether workers should stop, because of temperature or [`Self::stop`]
+    pub fn should_stop(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    /// Stop workers without a thermal trip, e.g. once the duration is up
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// The reading that passed the limit, if any
+    pub fn tripped(&self) -> Option<SensorReading> {
+        self.tripped.lock().unwrap().clone()
+    }
+
+    /// Highest temperature seen per sensor
+    pub fn peaks(&self) -> BTreeMap<String, f64> {
+        self.peaks.lock().unwrap().clone()
+    }
+
+    pub fn max_celsius(&self) -> f64 {
+        self.max_celsius
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(sensor: &str, celsius: f64) -> SensorReading {
+        SensorReading { sensor: sensor.into(), celsius }
+    }
+
+    #[test]
+    fn test_guard_trips_on_first_hot_sensor() {
+        let guard = ThermalGuard::new(90.0);
+
+        guard.record(&[reading("cpu", 70.0), reading("gpu0", 65.0)]);
+        assert!(!guard.should_stop());
+
+        // Exactly at the limit is still allowed
+        guard.record(&[reading("cpu", 90.0), reading("gpu0", 60.0)]);
+        assert!(!guard.should_stop());
+
+        guard.record(&[reading("cpu", 88.0), reading("gpu0", 91.5)]);
+        guard.record(&[reading("cpu", 95.0), reading("gpu0", 80.0)]);
+        assert!(guard.should_stop());
+        assert_eq!(guard.tripped(), Some(reading("gpu0", 91.5)));
+
+        let peaks = guard.peaks();
+        assert_eq!(peaks["cpu"], 95.0);
+        assert_eq!(peaks["gpu0"], 91.5);
+    }
+}
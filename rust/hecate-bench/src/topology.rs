@@ -0,0 +1,217 @@
+//! CPU topology and per-core benchmarks
+//!
+//! Physical cores are read from sysfs so SMT siblings can be told apart.
+//! Workloads are pinned with `core_affinity` so every score belongs to a
+//! known core.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Barrier;
+use std::time::{Duration, Instant};
+
+/// A physical core and the logical CPUs (SMT threads) it runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PhysicalCore {
+    pub package: u32,
+    pub core: u32,
+    /// Logical CPU ids, lowest first
+    pub threads: Vec<usize>,
+}
+
+/// Throughput at one thread count
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScalingPoint {
+    pub threads: usize,
+    /// Whether the threads include SMT siblings sharing a physical core
+    pub smt: bool,
+    pub score: f64,
+    /// `score` relative to perfect linear scaling of the one-thread score
+    pub efficiency: f64,
+}
+
+/// Physical cores of this machine, ordered by package and core id
+///
+/// Falls back to treating every logical CPU as its own core when sysfs
+/// topology is unavailable.
+pub fn physical_cores() -> Vec<PhysicalCore> {
+    let logical: Vec<usize> = core_affinity::get_core_ids()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|id| id.id)
+        .collect();
+
+    let mut cores: Vec<PhysicalCore> = Vec::new();
+    for cpu in logical {
+        let Some((package, core)) = read_topology(cpu) else {
+            cores.push(PhysicalCore { package: 0, core: cpu as u32, threads: vec![cpu] });
+            continue;
+        };
+        match cores.iter_mut().find(|c| c.package == package && c.core == core) {
+            Some(existing) => existing.threads.push(cpu),
+            None => cores.push(PhysicalCore { package, core, threads: vec![cpu] }),
+        }
+    }
+
+    for core in &mut cores {
+        core.threads.sort_unstable();
+    }
+    cores.sort_by_key(|c| (c.package, c.core));
+    cores
+}
+
+fn read_topology(cpu: usize) -> Option<(u32, u32)> {
+    let dir = format!("/sys/devices/system/cpu/cpu{}/topology", cpu);
+    let read = |name: &str| -> Option<u32> {
+        std::fs::read_to_string(format!("{}/{}", dir, name)).ok()?.trim().parse().ok()
+    };
+    Some((read("physical_package_id")?, read("core_id")?))
+}
+
+/// Logical CPUs in the order threads should be added when scaling: the
+/// first thread of every physical core, then their SMT siblings
+pub fn scaling_order(cores: &[PhysicalCore]) -> Vec<usize> {
+    let max_threads = cores.iter().map(|c| c.threads.len()).max().unwrap_or(0);
+    (0..max_threads)
+        .flat_map(|i| cores.iter().filter_map(move |c| c.threads.get(i).copied()))
+        .collect()
+}
+
+/// Single-thread score of each physical core, measured one at a time on
+/// its first logical CPU
+pub fn per_core_scores(cores: &[PhysicalCore], duration: Duration) -> Vec<f64> {
+    cores.iter()
+        .map(|core| run_pinned(&core.threads[..1], duration))
+        .collect()
+}
+
+/// Aggregate score at increasing thread counts: powers of two up to the
+/// physical core count, the physical core count, and all logical CPUs
+pub fn scaling_curve(cores: &[PhysicalCore], duration: Duration) -> Vec<ScalingPoint> {
+    let order = scaling_order(cores);
+    let measurements: Vec<(usize, f64)> = thread_counts(cores.len(), order.len())
+        .into_iter()
+        .map(|n| (n, run_pinned(&order[..n], duration)))
+        .collect();
+
+    scaling_efficiency(&measurements)
+        .into_iter()
+        .zip(measurements)
+        .map(|(efficiency, (threads, score))| ScalingPoint {
+            threads,
+            smt: threads > cores.len(),
+            score,
+            efficiency,
+        })
+        .collect()
+}
+
+fn thread_counts(physical: usize, logical: usize) -> Vec<usize> {
+    let mut counts: Vec<usize> = std::iter::successors(Some(1), |n| Some(n * 2))
+        .take_while(|&n| n < physical)
+        .collect();
+    counts.push(physical);
+    counts.push(logical);
+    counts.retain(|&n| n > 0);
+    counts.dedup();
+    counts
+}
+
+/// Efficiency of each `(threads, score)` measurement relative to linear
+/// scaling of the one-thread measurement
+///
+/// Without a one-thread measurement the lowest thread count is taken as
+/// the per-thread reference.
+pub fn scaling_efficiency(measurements: &[(usize, f64)]) -> Vec<f64> {
+    let per_thread = measurements.iter()
+        .min_by_key(|(threads, _)| *threads)
+        .map(|(threads, score)| score / *threads as f64)
+        .unwrap_or(0.0);
+
+    measurements.iter()
+        .map(|(threads, score)| {
+            if per_thread <= 0.0 || *threads == 0 {
+                0.0
+            } else {
+                score / (per_thread * *threads as f64)
+            }
+        })
+        .collect()
+}
+
+/// Run the prime workload on one thread pinned to each of `cpus` for
+/// `duration`, returning the combined operations per second
+fn run_pinned(cpus: &[usize], duration: Duration) -> f64 {
+    let operations = AtomicU64::new(0);
+    // Start timing only once every thread is pinned
+    let barrier = Barrier::new(cpus.len());
+
+    std::thread::scope(|s| {
+        for &cpu in cpus {
+            let operations = &operations;
+            let barrier = &barrier;
+            s.spawn(move || {
+                if !core_affinity::set_for_current(core_affinity::CoreId { id: cpu }) {
+                    tracing::debug!("Could not pin benchmark thread to CPU {}", cpu);
+                }
+                barrier.wait();
+
+                let start = Instant::now();
+                let mut local = 0u64;
+                while start.elapsed() < duration {
+                    local += count_primes(10_000);
+                }
+                operations.fetch_add(local, Ordering::Relaxed);
+            });
+        }
+    });
+
+    operations.load(Ordering::Relaxed) as f64 / duration.as_secs_f64()
+}
+
+/// Same trial-division workload as the single-thread benchmark
+fn count_primes(limit: u64) -> u64 {
+    (2..limit)
+        .filter(|&n| (2..((n as f64).sqrt() as u64 + 1)).all(|i| n % i != 0))
+        .count() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scaling_efficiency() {
+        let efficiency = scaling_efficiency(&[(1, 100.0), (2, 200.0), (4, 300.0), (8, 400.0)]);
+        assert_eq!(efficiency, vec![1.0, 1.0, 0.75, 0.5]);
+
+        // Superlinear scaling is reported as is
+        assert_eq!(scaling_efficiency(&[(1, 10.0), (2, 25.0)]), vec![1.0, 1.25]);
+
+        assert!(scaling_efficiency(&[]).is_empty());
+        assert_eq!(scaling_efficiency(&[(1, 0.0), (2, 5.0)]), vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_scaling_order_puts_smt_siblings_last() {
+        let cores = vec![
+            PhysicalCore { package: 0, core: 0, threads: vec![0, 4] },
+            PhysicalCore { package: 0, core: 1, threads: vec![1, 5] },
+            PhysicalCore { package: 0, core: 2, threads: vec![2] },
+        ];
+        assert_eq!(scaling_order(&cores), vec![0, 1, 2, 4, 5]);
+    }
+
+    #[test]
+    fn test_thread_counts() {
+        assert_eq!(thread_counts(8, 16), vec![1, 2, 4, 8, 16]);
+        assert_eq!(thread_counts(6, 6), vec![1, 2, 4, 6]);
+        assert_eq!(thread_counts(1, 1), vec![1]);
+    }
+
+    #[test]
+    fn test_physical_cores_cover_every_cpu() {
+        let cores = physical_cores();
+        let threads: usize = cores.iter().map(|c| c.threads.len()).sum();
+        assert_eq!(threads, core_affinity::get_core_ids().map_or(0, |ids| ids.len()));
+    }
+}
@@ -444,10 +444,12 @@ async fn handle_optimize(profile: Option<String>, dry_run: bool) -> Result<()> {
         match p.to_lowercase().as_str() {
             "ai" => SystemProfile::AIFlagship,
             "pro" => SystemProfile::ProWorkstation,
-            "gaming" => SystemProfile::HighPerformance,
+            "gaming" => SystemProfile::Gaming,
             "creator" => SystemProfile::HighPerformance,
             "dev" => SystemProfile::Developer,
             "standard" => SystemProfile::Standard,
+            "laptop" | "battery" => SystemProfile::LaptopBattery,
+            "server" => SystemProfile::Server,
             _ => {
                 eprintln!("Invalid profile: {}", p);
                 return Ok(());
@@ -484,6 +486,15 @@ async fn handle_optimize(profile: Option<String>, dry_run: bool) -> Result<()> {
             println!("  ✓ Game Mode: Enabled");
             println!("  ✓ Network: Low Latency");
         }
+        SystemProfile::Gaming => {
+            println!("  ✓ GPU Power Mode: Maximum Performance");
+            println!("  ✓ Kernel Preemption: Full");
+            println!("  ✓ Game Mode: Enabled");
+        }
+        SystemProfile::LaptopBattery => {
+            println!("  ✓ PCIe ASPM: powersave");
+            println!("  ✓ Disk Writeback: 15s");
+        }
         _ => {}
     }
     
@@ -142,6 +142,7 @@ fn benchmark_serialization(c: &mut Criterion) {
                 nvme_gen: Some(4),
             }
         ],
+        numa_nodes: vec![],
         profile: SystemProfile::AIFlagship,
     };
     
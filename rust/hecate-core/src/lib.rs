@@ -3,6 +3,9 @@
 //! Core functionality for hardware detection, profiling, and optimization
 
 pub mod config;
+pub mod numa;
+pub mod power;
+pub mod thermal;
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
@@ -11,7 +14,7 @@ use std::path::Path;
 use sysinfo::System;
 
 /// System profile based on detected hardware
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SystemProfile {
     /// High-end ML/AI workstation (RTX 4090+, 64GB+ RAM)
     AIFlagship,
@@ -23,6 +26,12 @@ pub enum SystemProfile {
     Developer,
     /// Standard desktop
     Standard,
+    /// Battery-powered laptop, tuned for battery life
+    LaptopBattery,
+    /// Server chassis, tuned for throughput and consistent latency
+    Server,
+    /// Gaming desktop, tuned for low latency
+    Gaming,
 }
 
 /// Detected hardware information
@@ -32,6 +41,9 @@ pub struct HardwareInfo {
     pub memory: MemoryInfo,
     pub gpu: Vec<GpuInfo>,
     pub storage: Vec<StorageInfo>,
+    /// NUMA nodes; a single-socket system has one node or none
+    #[serde(default)]
+    pub numa_nodes: Vec<numa::NumaNode>,
     pub profile: SystemProfile,
 }
 
@@ -101,20 +113,29 @@ impl HardwareDetector {
         Self { system }
     }
 
+    /// Machine architecture as the kernel reports it (`uname -m`), such as
+    /// `x86_64` or `aarch64`
+    pub fn machine_arch() -> Option<String> {
+        System::cpu_arch()
+    }
+
     /// Detect all hardware and create a profile
     pub fn detect(&mut self) -> Result<HardwareInfo> {
         let cpu = self.detect_cpu()?;
         let memory = self.detect_memory()?;
         let gpu = self.detect_gpu()?;
         let storage = self.detect_storage()?;
+        let numa_nodes = numa::read_numa_nodes(Path::new(numa::NODE_ROOT));
         
-        let profile = Self::determine_profile(&cpu, &memory, &gpu);
+        let profile = power::platform_profile(Path::new(power::POWER_SUPPLY_ROOT), Path::new(power::DMI_ROOT))
+            .unwrap_or_else(|| Self::determine_profile(&cpu, &memory, &gpu));
         
         Ok(HardwareInfo {
             cpu,
             memory,
             gpu,
             storage,
+            numa_nodes,
             profile,
         })
     }
@@ -274,7 +295,9 @@ impl HardwareDetector {
         })
     }
 
-    fn determine_profile(_cpu: &CpuInfo, memory: &MemoryInfo, gpus: &[GpuInfo]) -> SystemProfile {
+    /// Profile by performance alone, leaving out what the platform
+    /// dictates (see [`power::platform_profile`])
+    pub fn determine_profile(_cpu: &CpuInfo, memory: &MemoryInfo, gpus: &[GpuInfo]) -> SystemProfile {
         // Check for flagship AI system
         if memory.total_gb >= 64.0 {
             if let Some(gpu) = gpus.first() {
@@ -318,6 +341,9 @@ pub fn apply_optimizations(profile: &SystemProfile) -> Result<()> {
         SystemProfile::HighPerformance => apply_high_performance_optimizations(),
         SystemProfile::Developer => apply_developer_optimizations(),
         SystemProfile::Standard => apply_standard_optimizations(),
+        SystemProfile::LaptopBattery => apply_laptop_battery_optimizations(),
+        SystemProfile::Server => apply_server_optimizations(),
+        SystemProfile::Gaming => apply_gaming_optimizations(),
     }
 }
 
@@ -352,6 +378,21 @@ fn apply_standard_optimizations() -> Result<()> {
     Ok(())
 }
 
+fn apply_laptop_battery_optimizations() -> Result<()> {
+    println!("Applying Laptop (battery) optimizations...");
+    Ok(())
+}
+
+fn apply_server_optimizations() -> Result<()> {
+    println!("Applying Server optimizations...");
+    Ok(())
+}
+
+fn apply_gaming_optimizations() -> Result<()> {
+    println!("Applying Gaming optimizations...");
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -362,4 +403,23 @@ mod tests {
         let info = detector.detect();
         assert!(info.is_ok());
     }
+
+    #[test]
+    fn test_profile_serde_round_trip() {
+        for profile in [
+            SystemProfile::AIFlagship,
+            SystemProfile::ProWorkstation,
+            SystemProfile::HighPerformance,
+            SystemProfile::Developer,
+            SystemProfile::Standard,
+            SystemProfile::LaptopBattery,
+            SystemProfile::Server,
+            SystemProfile::Gaming,
+        ] {
+            let json = serde_json::to_string(&profile).unwrap();
+            assert_eq!(serde_json::from_str::<SystemProfile>(&json).unwrap(), profile);
+            assert!(apply_optimizations(&profile).is_ok());
+        }
+        assert_eq!(serde_json::to_string(&SystemProfile::LaptopBattery).unwrap(), "\"LaptopBattery\"");
+    }
 }
\ No newline at end of file
@@ -0,0 +1,163 @@
+//! HecateOS NUMA Module
+//! 
+//! NUMA node topology from `/sys/devices/system/node`
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Sysfs directory holding `node*` entries
+pub const NODE_ROOT: &str = "/sys/devices/system/node";
+
+/// One NUMA node: its CPUs and local memory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NumaNode {
+    pub id: u32,
+    /// Logical CPU ids, ascending
+    pub cpus: Vec<usize>,
+    pub memory_gb: f64,
+}
+
+impl NumaNode {
+    /// CPUs in the kernel's list format, e.g. `0-15,32-47`
+    pub fn cpu_list(&self) -> String {
+        format_cpulist(&self.cpus)
+    }
+}
+
+/// Parse a kernel CPU list such as `0-3,8,10-11`
+pub fn parse_cpulist(contents: &str) -> Option<Vec<usize>> {
+    let contents = contents.trim();
+    if contents.is_empty() {
+        return Some(Vec::new());
+    }
+
+    let mut cpus = Vec::new();
+    for range in contents.split(',') {
+        match range.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (start.parse::<usize>().ok()?, end.parse::<usize>().ok()?);
+                if start > end {
+                    return None;
+                }
+                cpus.extend(start..=end);
+            }
+            None => cpus.push(range.parse().ok()?),
+        }
+    }
+    cpus.sort_unstable();
+    cpus.dedup();
+    Some(cpus)
+}
+
+/// Format CPU ids in the kernel's list format, collapsing runs into ranges
+pub fn format_cpulist(cpus: &[usize]) -> String {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &cpu in cpus {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == cpu => *end = cpu,
+            _ => ranges.push((cpu, cpu)),
+        }
+    }
+
+    ranges.iter()
+        .map(|&(start, end)| if start == end { start.to_string() } else { format!("{}-{}", start, end) })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// `MemTotal` from a node's `meminfo`, in GB
+///
+/// Lines look like `Node 0 MemTotal:       65775612 kB`.
+pub fn parse_node_memtotal_gb(meminfo: &str) -> Option<f64> {
+    meminfo.lines()
+        .find_map(|line| line.split_once("MemTotal:").map(|(_, rest)| rest))
+        .and_then(|rest| rest.split_whitespace().next())
+        .and_then(|kb| kb.parse::<u64>().ok())
+        .map(|kb| kb as f64 / 1024.0 / 1024.0)
+}
+
+/// Read every NUMA node under `root`, sorted by id
+///
+/// Kernels built without NUMA have no node directory; that reads as no
+/// nodes rather than an error.
+pub fn read_numa_nodes(root: &Path) -> Vec<NumaNode> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+
+    let mut nodes: Vec<NumaNode> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let id = name.strip_prefix("node")?.parse::<u32>().ok()?;
+
+            let cpus = parse_cpulist(&fs::read_to_string(entry.path().join("cpulist")).ok()?)?;
+            let memory_gb = fs::read_to_string(entry.path().join("meminfo"))
+                .ok()
+                .and_then(|meminfo| parse_node_memtotal_gb(&meminfo))
+                .unwrap_or(0.0);
+            Some(NumaNode { id, cpus, memory_gb })
+        })
+        .collect();
+
+    nodes.sort_by_key(|node| node.id);
+    nodes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cpulist() {
+        assert_eq!(parse_cpulist("0-3\n"), Some(vec![0, 1, 2, 3]));
+        assert_eq!(parse_cpulist("0-1,8,10-11"), Some(vec![0, 1, 8, 10, 11]));
+        assert_eq!(parse_cpulist("\n"), Some(vec![]));
+        assert_eq!(parse_cpulist("3-1"), None);
+        assert_eq!(parse_cpulist("0-x"), None);
+    }
+
+    #[test]
+    fn test_format_cpulist() {
+        assert_eq!(format_cpulist(&[0, 1, 2, 3, 8, 10, 11]), "0-3,8,10-11");
+        assert_eq!(format_cpulist(&[5]), "5");
+        assert_eq!(format_cpulist(&[]), "");
+
+        let cpus = parse_cpulist("0-15,32-47").unwrap();
+        assert_eq!(format_cpulist(&cpus), "0-15,32-47");
+    }
+
+    #[test]
+    fn test_parse_node_memtotal() {
+        let meminfo = "Node 1 MemTotal:       67108864 kB\nNode 1 MemFree:        1048576 kB\n";
+        assert_eq!(parse_node_memtotal_gb(meminfo), Some(64.0));
+        assert_eq!(parse_node_memtotal_gb("Node 0 MemFree: 1 kB\n"), None);
+    }
+
+    #[test]
+    fn test_read_numa_nodes() {
+        let root = tempfile::tempdir().unwrap();
+        let node = |name: &str, cpulist: &str, mem_kb: u64| {
+            let dir = root.path().join(name);
+            fs::create_dir(&dir).unwrap();
+            fs::write(dir.join("cpulist"), cpulist).unwrap();
+            fs::write(dir.join("meminfo"), format!("Node 0 MemTotal: {} kB\n", mem_kb)).unwrap();
+        };
+        node("node1", "16-31,48-63\n", 33554432);
+        node("node0", "0-15,32-47\n", 67108864);
+        fs::create_dir(root.path().join("power")).unwrap();
+        fs::write(root.path().join("possible"), "0-1\n").unwrap();
+
+        let nodes = read_numa_nodes(root.path());
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(nodes[0].id, 0);
+        assert_eq!(nodes[0].cpu_list(), "0-15,32-47");
+        assert_eq!(nodes[0].memory_gb, 64.0);
+        assert_eq!(nodes[1].id, 1);
+        assert_eq!(nodes[1].cpus.len(), 32);
+        assert_eq!(nodes[1].memory_gb, 32.0);
+
+        assert!(read_numa_nodes(&root.path().join("missing")).is_empty());
+    }
+}
@@ -0,0 +1,184 @@
+//! HecateOS Power Module
+//!
+//! Batteries, AC adapters and chassis type from `/sys/class/power_supply`
+//! and `/sys/class/dmi/id`, to tell laptops and servers apart from
+//! desktops and to follow a laptop's power source
+
+use std::fs;
+use std::path::Path;
+
+use crate::SystemProfile;
+
+/// Sysfs directory holding one entry per power supply
+pub const POWER_SUPPLY_ROOT: &str = "/sys/class/power_supply";
+
+/// Sysfs directory with the DMI (SMBIOS) identification
+pub const DMI_ROOT: &str = "/sys/class/dmi/id";
+
+/// Where the daemon publishes the profile currently in effect, which on a
+/// laptop follows the power source
+pub const EFFECTIVE_PROFILE_PATH: &str = "/run/hecate/profile";
+
+/// SMBIOS chassis types of servers: main server chassis, rack mount,
+/// multi-system, blade and blade enclosure
+const SERVER_CHASSIS_TYPES: &[u32] = &[17, 23, 25, 28, 29];
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|value| value.trim().to_string())
+}
+
+/// Whether a battery powering the system is present
+///
+/// Batteries of peripherals such as wireless mice also show up under the
+/// power supply class, but with `scope` set to `Device`.
+pub fn has_battery(root: &Path) -> bool {
+    let Ok(entries) = fs::read_dir(root) else {
+        return false;
+    };
+
+    entries.filter_map(|entry| entry.ok()).any(|entry| {
+        let dir = entry.path();
+        let is_battery = read_trimmed(&dir.join("type")).map_or_else(
+            || entry.file_name().to_string_lossy().starts_with("BAT"),
+            |kind| kind == "Battery",
+        );
+        is_battery && read_trimmed(&dir.join("scope")).as_deref() != Some("Device")
+    })
+}
+
+/// Whether the system runs on external power
+///
+/// True if any AC adapter (`Mains` or `USB` supply) reports `online`,
+/// false if there are adapters and none is online, `None` when there is
+/// no adapter to ask.
+pub fn on_ac_power(root: &Path) -> Option<bool> {
+    let entries = fs::read_dir(root).ok()?;
+
+    let adapters: Vec<bool> = entries.filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|dir| {
+            read_trimmed(&dir.join("type")).is_some_and(|kind| kind == "Mains" || kind.starts_with("USB"))
+        })
+        .filter_map(|dir| read_trimmed(&dir.join("online")))
+        .map(|online| online == "1")
+        .collect();
+
+    if adapters.is_empty() {
+        None
+    } else {
+        Some(adapters.contains(&true))
+    }
+}
+
+/// The profile published at `path` by the daemon, if it is running
+pub fn read_effective_profile(path: &Path) -> Option<SystemProfile> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+/// Whether the DMI chassis type is one of the server types
+pub fn is_server_chassis(dmi_root: &Path) -> bool {
+    read_trimmed(&dmi_root.join("chassis_type"))
+        .and_then(|value| value.parse::<u32>().ok())
+        .is_some_and(|chassis| SERVER_CHASSIS_TYPES.contains(&chassis))
+}
+
+/// Profile dictated by the platform rather than by its performance: a
+/// battery makes a laptop whatever its GPU, a server chassis a server
+pub fn platform_profile(power_root: &Path, dmi_root: &Path) -> Option<SystemProfile> {
+    if has_battery(power_root) {
+        Some(SystemProfile::LaptopBattery)
+    } else if is_server_chassis(dmi_root) {
+        Some(SystemProfile::Server)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn supply(root: &Path, name: &str, kind: Option<&str>, scope: Option<&str>) {
+        let dir = root.join(name);
+        fs::create_dir_all(&dir).unwrap();
+        if let Some(kind) = kind {
+            fs::write(dir.join("type"), format!("{}\n", kind)).unwrap();
+        }
+        if let Some(scope) = scope {
+            fs::write(dir.join("scope"), format!("{}\n", scope)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_has_battery() {
+        let root = tempfile::tempdir().unwrap();
+        assert!(!has_battery(root.path()));
+        assert!(!has_battery(&root.path().join("missing")));
+
+        // Desktop: mains and a wireless mouse
+        supply(root.path(), "AC", Some("Mains"), None);
+        supply(root.path(), "hidpp_battery_0", Some("Battery"), Some("Device"));
+        assert!(!has_battery(root.path()));
+
+        supply(root.path(), "BAT0", Some("Battery"), Some("System"));
+        assert!(has_battery(root.path()));
+
+        // Without a type file the name decides
+        let root = tempfile::tempdir().unwrap();
+        supply(root.path(), "BAT1", None, None);
+        assert!(has_battery(root.path()));
+    }
+
+    fn set_online(root: &Path, name: &str, online: bool) {
+        fs::write(root.join(name).join("online"), if online { "1\n" } else { "0\n" }).unwrap();
+    }
+
+    #[test]
+    fn test_on_ac_power() {
+        let root = tempfile::tempdir().unwrap();
+        assert_eq!(on_ac_power(root.path()), None);
+
+        // A battery alone says nothing about the adapter
+        supply(root.path(), "BAT0", Some("Battery"), None);
+        assert_eq!(on_ac_power(root.path()), None);
+
+        supply(root.path(), "AC", Some("Mains"), None);
+        set_online(root.path(), "AC", false);
+        assert_eq!(on_ac_power(root.path()), Some(false));
+
+        // USB-C charging counts as well
+        supply(root.path(), "ucsi-source-psy-USBC000:001", Some("USB"), None);
+        set_online(root.path(), "ucsi-source-psy-USBC000:001", true);
+        assert_eq!(on_ac_power(root.path()), Some(true));
+    }
+
+    #[test]
+    fn test_read_effective_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("profile");
+        assert_eq!(read_effective_profile(&path), None);
+
+        fs::write(&path, serde_json::to_string(&SystemProfile::LaptopBattery).unwrap()).unwrap();
+        assert_eq!(read_effective_profile(&path), Some(SystemProfile::LaptopBattery));
+
+        fs::write(&path, "garbage").unwrap();
+        assert_eq!(read_effective_profile(&path), None);
+    }
+
+    #[test]
+    fn test_platform_profile() {
+        let power = tempfile::tempdir().unwrap();
+        let dmi = tempfile::tempdir().unwrap();
+        supply(power.path(), "AC", Some("Mains"), None);
+        assert_eq!(platform_profile(power.path(), dmi.path()), None);
+
+        fs::write(dmi.path().join("chassis_type"), "3\n").unwrap();
+        assert_eq!(platform_profile(power.path(), dmi.path()), None);
+
+        fs::write(dmi.path().join("chassis_type"), "23\n").unwrap();
+        assert_eq!(platform_profile(power.path(), dmi.path()), Some(SystemProfile::Server));
+
+        supply(power.path(), "BAT0", Some("Battery"), None);
+        assert_eq!(platform_profile(power.path(), dmi.path()), Some(SystemProfile::LaptopBattery));
+    }
+}
@@ -139,6 +139,7 @@ mod unit_tests {
                 storage_type: StorageType::NvmeGen4,
                 nvme_gen: Some(4),
             }],
+            numa_nodes: vec![],
             profile: SystemProfile::ProWorkstation,
         }
     }
@@ -0,0 +1,142 @@
+//! HecateOS Thermal Module
+//! 
+//! Temperature readings from kernel thermal zones and NVIDIA GPUs
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Sysfs directory holding `thermal_zone*` entries
+pub const THERMAL_ROOT: &str = "/sys/class/thermal";
+
+/// A temperature from one sensor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SensorReading {
+    /// Sensor name, e.g. `thermal_zone0 (x86_pkg_temp)` or `gpu0`
+    pub sensor: String,
+    pub celsius: f64,
+}
+
+/// Parse a sysfs temperature in millidegrees Celsius
+pub fn parse_millidegrees(contents: &str) -> Option<f64> {
+    contents.trim().parse::<i64>().ok().map(|milli| milli as f64 / 1000.0)
+}
+
+/// Read every thermal zone under `root`, skipping zones that cannot be read
+///
+/// Zones are named after their directory and, where present, their `type`.
+pub fn read_thermal_zones(root: &Path) -> Vec<SensorReading> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+
+    let mut readings: Vec<SensorReading> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let zone = entry.file_name().to_string_lossy().into_owned();
+            if !zone.starts_with("thermal_zone") {
+                return None;
+            }
+
+            let celsius = parse_millidegrees(&fs::read_to_string(entry.path().join("temp")).ok()?)?;
+            let sensor = match fs::read_to_string(entry.path().join("type")) {
+                Ok(kind) if !kind.trim().is_empty() => format!("{} ({})", zone, kind.trim()),
+                _ => zone,
+            };
+            Some(SensorReading { sensor, celsius })
+        })
+        .collect();
+
+    readings.sort_by(|a, b| a.sensor.cmp(&b.sensor));
+    readings
+}
+
+/// Parse `nvidia-smi --query-gpu=index,temperature.gpu --format=csv,noheader,nounits`
+pub fn parse_nvidia_smi_temperatures(output: &str) -> Vec<SensorReading> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (index, temp) = line.split_once(',')?;
+            Some(SensorReading {
+                sensor: format!("gpu{}", index.trim()),
+                celsius: temp.trim().parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+/// Temperatures of NVIDIA GPUs, empty if nvidia-smi is unavailable
+pub fn read_nvidia_gpus() -> Vec<SensorReading> {
+    if !Path::new("/usr/bin/nvidia-smi").exists() {
+        return Vec::new();
+    }
+
+    match Command::new("nvidia-smi")
+        .args(["--query-gpu=index,temperature.gpu", "--format=csv,noheader,nounits"])
+        .output()
+    {
+        Ok(output) if output.status.success() => {
+            parse_nvidia_smi_temperatures(&String::from_utf8_lossy(&output.stdout))
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// All CPU/platform thermal zones followed by NVIDIA GPUs
+pub fn read_all() -> Vec<SensorReading> {
+    let mut readings = read_thermal_zones(Path::new(THERMAL_ROOT));
+    readings.extend(read_nvidia_gpus());
+    readings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_millidegrees() {
+        assert_eq!(parse_millidegrees("45000\n"), Some(45.0));
+        assert_eq!(parse_millidegrees("87250"), Some(87.25));
+        assert_eq!(parse_millidegrees("-5000"), Some(-5.0));
+        assert_eq!(parse_millidegrees(""), None);
+        assert_eq!(parse_millidegrees("hot"), None);
+    }
+
+    #[test]
+    fn test_read_thermal_zones() {
+        let root = tempfile::tempdir().unwrap();
+        let zone = |name: &str, kind: Option<&str>, temp: &str| {
+            let dir = root.path().join(name);
+            fs::create_dir(&dir).unwrap();
+            if let Some(kind) = kind {
+                fs::write(dir.join("type"), format!("{}\n", kind)).unwrap();
+            }
+            fs::write(dir.join("temp"), temp).unwrap();
+        };
+        zone("thermal_zone0", Some("acpitz"), "27800\n");
+        zone("thermal_zone1", Some("x86_pkg_temp"), "91000\n");
+        zone("thermal_zone2", None, "40000\n");
+        // Unreadable values and non-zone entries are skipped
+        zone("thermal_zone3", Some("iwlwifi_1"), "");
+        zone("cooling_device0", Some("Processor"), "0\n");
+
+        let readings = read_thermal_zones(root.path());
+        assert_eq!(readings, vec![
+            SensorReading { sensor: "thermal_zone0 (acpitz)".into(), celsius: 27.8 },
+            SensorReading { sensor: "thermal_zone1 (x86_pkg_temp)".into(), celsius: 91.0 },
+            SensorReading { sensor: "thermal_zone2".into(), celsius: 40.0 },
+        ]);
+
+        assert!(read_thermal_zones(&root.path().join("missing")).is_empty());
+    }
+
+    #[test]
+    fn test_parse_nvidia_smi_temperatures() {
+        let readings = parse_nvidia_smi_temperatures("0, 64\n1, 83\nbogus\n");
+        assert_eq!(readings, vec![
+            SensorReading { sensor: "gpu0".into(), celsius: 64.0 },
+            SensorReading { sensor: "gpu1".into(), celsius: 83.0 },
+        ]);
+    }
+}
@@ -0,0 +1,181 @@
+//! Daemon configuration
+//!
+//! Read from `/etc/hecate/daemon.json`; every field is optional and falls
+//! back to its default, so a missing file gives the stock behaviour.
+
+use crate::sysfs::SystemFs;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+pub const CONFIG_PATH: &str = "/etc/hecate/daemon.json";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DaemonConfig {
+    pub monitor: MonitorConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MonitorConfig {
+    /// Seconds between health checks
+    pub interval_secs: u64,
+    pub thermal: ThermalConfig,
+    pub memory: MemoryConfig,
+    pub power: PowerConfig,
+    pub status: StatusConfig,
+}
+
+impl Default for MonitorConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 60,
+            thermal: ThermalConfig::default(),
+            memory: MemoryConfig::default(),
+            power: PowerConfig::default(),
+            status: StatusConfig::default(),
+        }
+    }
+}
+
+/// What to do when the CPU stays hot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThermalAction {
+    /// Only log a warning
+    Log,
+    /// Switch every CPU to the `powersave` governor until it cools down
+    Powersave,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThermalConfig {
+    /// Temperature at or above which a sample counts as hot
+    pub high_celsius: f64,
+    /// Temperature below which a sample counts as cool again; keep it
+    /// under `high_celsius` so the policy does not flap
+    pub clear_celsius: f64,
+    /// Consecutive hot (or cool) samples before acting
+    pub sustain_samples: u32,
+    pub action: ThermalAction,
+}
+
+impl Default for ThermalConfig {
+    fn default() -> Self {
+        Self {
+            high_celsius: 85.0,
+            clear_celsius: 75.0,
+            sustain_samples: 3,
+            action: ThermalAction::Powersave,
+        }
+    }
+}
+
+/// What to do when available memory runs low
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryAction {
+    /// Log a memory pressure event
+    Log,
+    /// Drop the page cache, then log
+    DropCaches,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MemoryConfig {
+    /// Available memory below which the system counts as under pressure
+    pub low_available_gb: f64,
+    pub action: MemoryAction,
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self {
+            low_available_gb: 2.0,
+            action: MemoryAction::Log,
+        }
+    }
+}
+
+/// Following a laptop's power source
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PowerConfig {
+    /// Switch between the battery profile and the hardware's own profile
+    /// when the charger is plugged in or out
+    pub follow_ac: bool,
+    /// Seconds between power source checks
+    pub poll_secs: u64,
+    /// Consecutive checks on the new source before switching, so a
+    /// flapping connector does not thrash the governor
+    pub settle_samples: u32,
+}
+
+impl Default for PowerConfig {
+    fn default() -> Self {
+        Self {
+            follow_ac: true,
+            poll_secs: 5,
+            settle_samples: 3,
+        }
+    }
+}
+
+/// JSON status snapshot for hecate-monitor and the dashboard
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatusConfig {
+    /// Serve the snapshot on `socket_path`
+    pub enabled: bool,
+    pub socket_path: String,
+    /// Warnings kept in the snapshot
+    pub recent_warnings: usize,
+}
+
+impl Default for StatusConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            socket_path: "/run/hecate/daemon.sock".to_string(),
+            recent_warnings: 20,
+        }
+    }
+}
+
+impl DaemonConfig {
+    /// Load the configuration at `path`, or the defaults if there is none
+    pub fn load(fs: &dyn SystemFs, path: &str) -> Result<Self> {
+        if !fs.exists(path) {
+            return Ok(Self::default());
+        }
+        let content = fs.read(path).with_context(|| format!("Failed to read {}", path))?;
+        serde_json::from_str(&content).with_context(|| format!("Invalid daemon configuration in {}", path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sysfs::MockFs;
+
+    #[test]
+    fn test_partial_config_uses_defaults() {
+        let fs = MockFs::with_files(&[(
+            CONFIG_PATH,
+            r#"{"monitor": {"thermal": {"high_celsius": 90, "action": "log"}}}"#,
+        )]);
+
+        let config = DaemonConfig::load(&fs, CONFIG_PATH).unwrap();
+        assert_eq!(config.monitor.thermal.high_celsius, 90.0);
+        assert_eq!(config.monitor.thermal.action, ThermalAction::Log);
+        assert_eq!(config.monitor.thermal.clear_celsius, 75.0);
+        assert_eq!(config.monitor.interval_secs, 60);
+        assert_eq!(config.monitor.memory, MemoryConfig::default());
+        assert_eq!(config.monitor.power, PowerConfig::default());
+        assert_eq!(config.monitor.status, StatusConfig::default());
+
+        assert_eq!(DaemonConfig::load(&MockFs::default(), CONFIG_PATH).unwrap(), DaemonConfig::default());
+    }
+}
@@ -0,0 +1,178 @@
+//! Cheap hardware fingerprint for spotting hardware changes at boot
+//!
+//! Full detection is slow, so the daemon only runs it on first boot. To
+//! notice a swapped GPU or added RAM it compares a fingerprint read from
+//! procfs and sysfs against the one saved with the last detection.
+
+use crate::sysfs::SystemFs;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Where the fingerprint of the last detected hardware is kept
+pub const FINGERPRINT_PATH: &str = "/etc/hecate/hardware.fingerprint";
+
+const CPUINFO: &str = "/proc/cpuinfo";
+const MEMINFO: &str = "/proc/meminfo";
+const PCI_DEVICES: &str = "/sys/bus/pci/devices";
+
+/// PCI base class of display controllers
+const DISPLAY_CLASS: &str = "0x03";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fingerprint {
+    pub cpu_model: String,
+    /// Installed memory, rounded to whole GiB so the kernel reserving a
+    /// little more or less after an update doesn't count as a change
+    pub memory_gb: u64,
+    /// `vendor:device` PCI ids of display controllers, sorted
+    pub gpus: Vec<String>,
+}
+
+impl Fingerprint {
+    /// Fingerprint of the running system; unreadable parts are left empty
+    pub fn read(fs: &dyn SystemFs) -> Self {
+        let cpu_model = fs.read(CPUINFO).ok()
+            .and_then(|cpuinfo| parse_cpu_model(&cpuinfo))
+            .unwrap_or_default();
+        let memory_gb = fs.read(MEMINFO).ok()
+            .and_then(|meminfo| parse_mem_total_gb(&meminfo))
+            .unwrap_or_default();
+
+        let mut gpus = Vec::new();
+        for device in fs.list(PCI_DEVICES).unwrap_or_default() {
+            let attr = |name: &str| {
+                fs.read(&format!("{}/{}/{}", PCI_DEVICES, device, name))
+                    .map(|value| value.trim().trim_start_matches("0x").to_string())
+            };
+            let is_display = fs.read(&format!("{}/{}/class", PCI_DEVICES, device))
+                .is_ok_and(|class| class.trim().starts_with(DISPLAY_CLASS));
+            if let (true, Ok(vendor), Ok(id)) = (is_display, attr("vendor"), attr("device")) {
+                gpus.push(format!("{}:{}", vendor, id));
+            }
+        }
+        gpus.sort();
+
+        Self { cpu_model, memory_gb, gpus }
+    }
+
+    /// Load the fingerprint at `path`, or `None` if none was saved
+    pub fn load(fs: &dyn SystemFs, path: &str) -> Result<Option<Self>> {
+        if !fs.exists(path) {
+            return Ok(None);
+        }
+        let content = fs.read(path).with_context(|| format!("Failed to read {}", path))?;
+        let fingerprint = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid hardware fingerprint in {}", path))?;
+        Ok(Some(fingerprint))
+    }
+
+    pub fn save(&self, fs: &dyn SystemFs, path: &str) -> Result<()> {
+        fs.write(path, &serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path))
+    }
+
+    /// What differs between this (saved) fingerprint and `current`, one
+    /// line per change; empty if the hardware is the same
+    pub fn changes(&self, current: &Self) -> Vec<String> {
+        let mut changes = Vec::new();
+        if self.cpu_model != current.cpu_model {
+            changes.push(format!("CPU changed from '{}' to '{}'", self.cpu_model, current.cpu_model));
+        }
+        if self.memory_gb != current.memory_gb {
+            changes.push(format!("Memory changed from {} GB to {} GB", self.memory_gb, current.memory_gb));
+        }
+        if self.gpus != current.gpus {
+            let list = |gpus: &[String]| if gpus.is_empty() { "none".to_string() } else { gpus.join(", ") };
+            changes.push(format!("GPUs changed from {} to {}", list(&self.gpus), list(&current.gpus)));
+        }
+        changes
+    }
+}
+
+fn parse_cpu_model(cpuinfo: &str) -> Option<String> {
+    cpuinfo.lines()
+        .find(|line| line.starts_with("model name"))
+        .and_then(|line| line.split_once(':'))
+        .map(|(_, model)| model.trim().to_string())
+}
+
+fn parse_mem_total_gb(meminfo: &str) -> Option<u64> {
+    let kb: f64 = meminfo.lines()
+        .find_map(|line| line.strip_prefix("MemTotal:"))?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()?;
+    Some((kb / (1024.0 * 1024.0)).round() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sysfs::MockFs;
+
+    fn system(mem_total_kb: &str, gpus: &[(&str, &str, &str)]) -> MockFs {
+        let meminfo = format!("MemTotal:       {} kB\nMemFree:         1024 kB\n", mem_total_kb);
+        let mut files = vec![
+            (CPUINFO.to_string(), "processor\t: 0\nmodel name\t: AMD Ryzen 9 7950X 16-Core Processor\n".to_string()),
+            (MEMINFO.to_string(), meminfo),
+            // A non-display device that must not show up
+            (format!("{}/0000:00:14.0/class", PCI_DEVICES), "0x0c0330\n".to_string()),
+            (format!("{}/0000:00:14.0/vendor", PCI_DEVICES), "0x8086\n".to_string()),
+            (format!("{}/0000:00:14.0/device", PCI_DEVICES), "0x7ae0\n".to_string()),
+        ];
+        for (address, vendor, device) in gpus {
+            let dir = format!("{}/{}", PCI_DEVICES, address);
+            files.push((format!("{}/class", dir), "0x030000\n".to_string()));
+            files.push((format!("{}/vendor", dir), format!("{}\n", vendor)));
+            files.push((format!("{}/device", dir), format!("{}\n", device)));
+        }
+        let files: Vec<(&str, &str)> = files.iter().map(|(p, c)| (p.as_str(), c.as_str())).collect();
+        MockFs::with_files(&files)
+    }
+
+    #[test]
+    fn test_read_fingerprint() {
+        let fs = system("66000000", &[("0000:01:00.0", "0x10de", "0x2684")]);
+        assert_eq!(Fingerprint::read(&fs), Fingerprint {
+            cpu_model: "AMD Ryzen 9 7950X 16-Core Processor".into(),
+            memory_gb: 63,
+            gpus: vec!["10de:2684".into()],
+        });
+        assert_eq!(Fingerprint::read(&MockFs::default()), Fingerprint::default());
+    }
+
+    #[test]
+    fn test_unchanged_hardware() {
+        let saved = Fingerprint::read(&system("66000000", &[("0000:01:00.0", "0x10de", "0x2684")]));
+        // A slightly different kernel reservation and a card in another slot
+        let current = Fingerprint::read(&system("65900000", &[("0000:02:00.0", "0x10de", "0x2684")]));
+        assert!(saved.changes(&current).is_empty());
+    }
+
+    #[test]
+    fn test_changed_hardware() {
+        let saved = Fingerprint::read(&system("33000000", &[("0000:01:00.0", "0x10de", "0x2684")]));
+        let current = Fingerprint::read(&system("66000000", &[
+            ("0000:01:00.0", "0x1002", "0x744c"),
+            ("0000:02:00.0", "0x10de", "0x2684"),
+        ]));
+        assert_eq!(saved.changes(&current), vec![
+            "Memory changed from 31 GB to 63 GB".to_string(),
+            "GPUs changed from 10de:2684 to 1002:744c, 10de:2684".to_string(),
+        ]);
+
+        let removed = Fingerprint { gpus: Vec::new(), ..saved.clone() };
+        assert_eq!(saved.changes(&removed), vec!["GPUs changed from 10de:2684 to none".to_string()]);
+    }
+
+    #[test]
+    fn test_fingerprint_round_trips() {
+        let fs = MockFs::default();
+        assert_eq!(Fingerprint::load(&fs, FINGERPRINT_PATH).unwrap(), None);
+
+        let fingerprint = Fingerprint::read(&system("16384000", &[]));
+        fingerprint.save(&fs, FINGERPRINT_PATH).unwrap();
+        assert_eq!(Fingerprint::load(&fs, FINGERPRINT_PATH).unwrap(), Some(fingerprint));
+    }
+}
@@ -0,0 +1,131 @@
+//! Editing the kernel command line in `/etc/default/grub`
+
+/// GRUB defaults file
+pub const GRUB_PATH: &str = "/etc/default/grub";
+
+const CMDLINE_KEY: &str = "GRUB_CMDLINE_LINUX_DEFAULT=";
+
+/// Value of `GRUB_CMDLINE_LINUX_DEFAULT` without quotes, if the line exists
+pub fn cmdline(content: &str) -> Option<String> {
+    content.lines()
+        .find_map(|line| line.strip_prefix(CMDLINE_KEY))
+        .map(|value| value.trim().trim_matches(|c| c == '"' || c == '\'').to_string())
+}
+
+/// `content` with `GRUB_CMDLINE_LINUX_DEFAULT` set to `value`, appending
+/// the line if missing, or with the line removed if `value` is `None`
+pub fn set_cmdline(content: &str, value: Option<&str>) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut replaced = false;
+
+    for line in content.lines() {
+        if line.starts_with(CMDLINE_KEY) {
+            if let Some(value) = value {
+                if !replaced {
+                    lines.push(format!("{}\"{}\"", CMDLINE_KEY, value));
+                }
+            }
+            replaced = true;
+        } else {
+            lines.push(line.to_string());
+        }
+    }
+
+    if !replaced {
+        if let Some(value) = value {
+            lines.push(format!("{}\"{}\"", CMDLINE_KEY, value));
+        }
+    }
+
+    let mut updated = lines.join("\n");
+    if content.ends_with('\n') || content.is_empty() {
+        updated.push('\n');
+    }
+    updated
+}
+
+/// Parameter name: everything before the first `=`, or the whole flag
+fn param_key(param: &str) -> &str {
+    param.split_once('=').map_or(param, |(key, _)| key)
+}
+
+/// Merge `params` into an existing command line
+///
+/// Parameters already present keep their position but take the value from
+/// `params`; any other existing parameters, such as ones the user added by
+/// hand, are kept as they are. Repeated keys collapse to one, and new
+/// parameters are appended in order.
+pub fn merge_params(existing: &str, params: &[&str]) -> String {
+    let mut merged: Vec<&str> = Vec::new();
+
+    for param in existing.split_whitespace().chain(params.iter().copied()) {
+        let key = param_key(param);
+        let ours = params.iter().rev().find(|p| param_key(p) == key);
+        match merged.iter_mut().find(|p| param_key(p) == key) {
+            Some(slot) => {
+                // Later occurrences win, unless ours already set the value
+                if ours.is_none() {
+                    *slot = param;
+                }
+            }
+            None => merged.push(ours.copied().unwrap_or(param)),
+        }
+    }
+
+    merged.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRUB: &str = "GRUB_DEFAULT=0\nGRUB_CMDLINE_LINUX_DEFAULT=\"quiet splash\"\nGRUB_CMDLINE_LINUX=\"\"\n";
+
+    #[test]
+    fn test_cmdline() {
+        assert_eq!(cmdline(GRUB).as_deref(), Some("quiet splash"));
+        assert_eq!(cmdline("GRUB_DEFAULT=0\n"), None);
+    }
+
+    #[test]
+    fn test_set_cmdline() {
+        assert_eq!(
+            set_cmdline(GRUB, Some("iommu=pt")),
+            "GRUB_DEFAULT=0\nGRUB_CMDLINE_LINUX_DEFAULT=\"iommu=pt\"\nGRUB_CMDLINE_LINUX=\"\"\n"
+        );
+        assert_eq!(
+            set_cmdline("GRUB_DEFAULT=0\n", Some("iommu=pt")),
+            "GRUB_DEFAULT=0\nGRUB_CMDLINE_LINUX_DEFAULT=\"iommu=pt\"\n"
+        );
+        assert_eq!(set_cmdline(GRUB, None), "GRUB_DEFAULT=0\nGRUB_CMDLINE_LINUX=\"\"\n");
+    }
+
+    #[test]
+    fn test_merge_params_keeps_user_params() {
+        assert_eq!(
+            merge_params("quiet splash nvidia-drm.modeset=1", &["iommu=pt", "pcie_aspm=off"]),
+            "quiet splash nvidia-drm.modeset=1 iommu=pt pcie_aspm=off"
+        );
+        assert_eq!(merge_params("", &["iommu=pt"]), "iommu=pt");
+    }
+
+    #[test]
+    fn test_merge_params_overlapping_keys() {
+        // Our value replaces the user's in place, duplicates collapse
+        assert_eq!(
+            merge_params(
+                "quiet mitigations=auto iommu=pt loglevel=3 mitigations=auto,nosmt loglevel=4",
+                &["iommu=pt", "mitigations=off", "processor.max_cstate=1"],
+            ),
+            "quiet mitigations=off iommu=pt loglevel=4 processor.max_cstate=1"
+        );
+    }
+
+    #[test]
+    fn test_merge_params_is_idempotent() {
+        let params = ["intel_pstate=active", "iommu=pt", "mitigations=off"];
+        let once = merge_params("quiet splash mitigations=auto", &params);
+        assert_eq!(once, "quiet splash mitigations=off intel_pstate=active iommu=pt");
+        assert_eq!(merge_params(&once, &params), once);
+    }
+}
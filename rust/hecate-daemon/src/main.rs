@@ -4,19 +4,39 @@
 
 use anyhow::Result;
 use clap::Parser;
+use colored::*;
 use hecate_core::{HardwareDetector, HardwareInfo, SystemProfile, apply_optimizations};
 use std::fs;
 use std::path::Path;
 use std::process::Command;
+use std::time::Duration;
+use tokio::time::Instant;
 use tracing::{info, warn};
 
+mod config;
+mod fingerprint;
+mod grub;
+mod nvidia;
+mod plan;
+mod policy;
+mod state;
+mod status;
+mod sysfs;
+
+use config::{DaemonConfig, MemoryAction, ThermalAction};
+use fingerprint::Fingerprint;
+use policy::{MemoryPolicy, PowerSource, PowerSourcePolicy, ThermalEvent, ThermalPolicy};
+use state::AppliedState;
+use status::StatusTracker;
+use sysfs::{RealFs, SystemFs};
+
 const CONFIG_PATH: &str = "/etc/hecate/hardware.json";
 const FIRST_BOOT_FLAG: &str = "/etc/hecate/.first_boot_complete";
 
 #[derive(Parser)]
 #[command(author, version, about = "HecateOS System Daemon")]
 struct Args {
-    /// Force hardware re-detection
+    /// Force hardware re-detection, even if the hardware looks unchanged
     #[arg(short, long)]
     force: bool,
     
@@ -24,9 +44,15 @@ struct Args {
     #[arg(short, long)]
     once: bool,
     
-    /// Dry run - detect but don't apply optimizations
+    /// Dry run - detect and print the planned changes, then exit without
+    /// applying optimizations or starting the monitoring loop
     #[arg(short, long)]
     dry_run: bool,
+    
+    /// Restore the settings that were in place before optimizations were
+    /// first applied, then exit
+    #[arg(long)]
+    revert: bool,
 }
 
 #[tokio::main]
@@ -40,19 +66,29 @@ async fn main() -> Result<()> {
     
     info!("HecateOS Daemon v{} starting...", env!("CARGO_PKG_VERSION"));
     
-    // Check if this is first boot or forced re-detection
-    let should_detect = !Path::new(FIRST_BOOT_FLAG).exists() || args.force;
+    if args.revert {
+        return revert_optimizations(&RealFs);
+    }
+    
+    // Check if this is first boot, forced re-detection or new hardware
+    let fingerprint = Fingerprint::read(&RealFs);
+    let should_detect = !Path::new(FIRST_BOOT_FLAG).exists()
+        || args.force
+        || hardware_changed(&fingerprint, &RealFs);
     
-    if should_detect {
+    let hardware = if should_detect {
         info!("Starting hardware detection...");
         let hardware = detect_hardware().await?;
         
-        // Save hardware configuration
-        save_hardware_config(&hardware)?;
-        
-        if !args.dry_run {
+        if args.dry_run {
+            print_planned_changes(&plan::planned_changes(&hardware, &RealFs, &nvidia::query_gpus()));
+        } else {
+            // Save hardware configuration
+            save_hardware_config(&hardware)?;
+            fingerprint.save(&RealFs, fingerprint::FINGERPRINT_PATH)?;
+            
             // Apply optimizations based on detected hardware
-            apply_system_optimizations(&hardware).await?;
+            apply_system_optimizations(&hardware, &RealFs).await?;
             
             // Mark first boot as complete
             fs::create_dir_all("/etc/hecate")?;
@@ -60,25 +96,55 @@ async fn main() -> Result<()> {
         }
         
         print_system_summary(&hardware);
+        hardware
     } else {
         // Load existing configuration
         let hardware = load_hardware_config()?;
         info!("Using cached hardware configuration");
         
-        if !args.dry_run {
+        if args.dry_run {
+            print_planned_changes(&plan::planned_changes(&hardware, &RealFs, &nvidia::query_gpus()));
+        } else {
             // Re-apply optimizations (useful after updates)
-            apply_system_optimizations(&hardware).await?;
+            apply_system_optimizations(&hardware, &RealFs).await?;
         }
-    }
+        hardware
+    };
     
-    if !args.once {
+    // The monitoring loop changes governors, power settings and caches
+    if args.dry_run && !args.once {
+        info!("Dry run: not starting the monitoring loop");
+    } else if !args.once {
         // Start monitoring daemon
-        start_monitoring_loop().await?;
+        let config = DaemonConfig::load(&RealFs, config::CONFIG_PATH)?;
+        start_monitoring_loop(&hardware, &RealFs, &config).await?;
     }
     
     Ok(())
 }
 
+/// Whether the hardware differs from what was last detected, logging the
+/// differences
+fn hardware_changed(current: &Fingerprint, fs: &dyn SystemFs) -> bool {
+    let saved = match Fingerprint::load(fs, fingerprint::FINGERPRINT_PATH) {
+        Ok(Some(saved)) => saved,
+        Ok(None) => {
+            info!("No hardware fingerprint recorded, re-detecting hardware");
+            return true;
+        }
+        Err(e) => {
+            warn!("{:#}, re-detecting hardware", e);
+            return true;
+        }
+    };
+    
+    let changes = saved.changes(current);
+    for change in &changes {
+        info!("Hardware change: {}", change);
+    }
+    !changes.is_empty()
+}
+
 async fn detect_hardware() -> Result<HardwareInfo> {
     let mut detector = HardwareDetector::new();
     let hardware = detector.detect()?;
@@ -96,272 +162,398 @@ async fn detect_hardware() -> Result<HardwareInfo> {
     Ok(hardware)
 }
 
-async fn apply_system_optimizations(hardware: &HardwareInfo) -> Result<()> {
+async fn apply_system_optimizations(hardware: &HardwareInfo, fs: &dyn SystemFs) -> Result<()> {
     info!("Applying optimizations for profile: {:?}", hardware.profile);
     
+    let gpus = nvidia::query_gpus();
+    
+    // Record the original values before anything is changed
+    snapshot_original_state(hardware, fs, &gpus)?;
+    
     // Apply core optimizations from library
     apply_optimizations(&hardware.profile)?;
     
+    // Every step only writes values that differ from what is in place, so
+    // re-running after an update is safe; count what actually changed
+    let mut changed = 0;
+    
     // Apply specific kernel parameters
-    apply_kernel_parameters(hardware).await?;
+    changed += apply_kernel_parameters(hardware, fs).await?;
     
     // Configure CPU governor
-    configure_cpu_governor(hardware).await?;
+    changed += configure_cpu_governor(hardware, fs).await?;
     
     // Set up memory management
-    configure_memory_management(hardware).await?;
+    changed += configure_memory_management(hardware, fs).await?;
     
     // Configure storage I/O schedulers
-    configure_storage_io(hardware).await?;
+    changed += configure_storage_io(hardware, fs).await?;
     
     // Set up GPU-specific optimizations
     if !hardware.gpu.is_empty() {
-        configure_gpu_settings(hardware).await?;
+        changed += configure_gpu_settings(hardware, fs, &gpus).await?;
+    }
+    
+    // Tune for NUMA on multi-socket workstations
+    if plan::numa_tuned(hardware) {
+        changed += configure_numa(hardware, fs).await?;
     }
     
-    info!("All optimizations applied successfully");
+    if changed == 0 {
+        info!("No changes: system already optimized");
+    } else {
+        info!("All optimizations applied successfully ({} settings changed)", changed);
+    }
     Ok(())
 }
 
-async fn apply_kernel_parameters(hardware: &HardwareInfo) -> Result<()> {
-    let mut params = vec![
-        "intel_pstate=active",
-        "intel_iommu=on",
-        "iommu=pt",
-        "pcie_aspm=off",
-    ];
-    
-    // Add profile-specific parameters
-    match hardware.profile {
-        SystemProfile::AIFlagship | SystemProfile::ProWorkstation => {
-            params.push("mitigations=off");
-            params.push("processor.max_cstate=1");
-            params.push("intel_idle.max_cstate=0");
-            params.push("nvme_core.default_ps_max_latency_us=0");
-        }
-        SystemProfile::HighPerformance => {
-            params.push("mitigations=auto,nosmt");
-            params.push("processor.max_cstate=2");
-        }
-        _ => {
-            // Keep default parameters for standard systems
-        }
-    }
+fn snapshot_original_state(hardware: &HardwareInfo, fs: &dyn SystemFs, gpus: &[nvidia::NvidiaGpu]) -> Result<()> {
+    fs::create_dir_all("/etc/hecate")?;
     
-    // Update GRUB configuration
-    update_grub_config(&params).await?;
+    let mut state = AppliedState::load(fs, state::STATE_PATH)?.unwrap_or_default();
+    state.record(fs, &plan::settings(hardware, fs), grub::GRUB_PATH);
+    state.record_nvidia(gpus, &plan::nvidia_settings(hardware, gpus));
+    state.save(fs, state::STATE_PATH)?;
     
+    info!("Original settings recorded in {}", state::STATE_PATH);
     Ok(())
 }
 
-async fn update_grub_config(params: &[&str]) -> Result<()> {
-    let params_str = params.join(" ");
-    
-    // Read current GRUB config
-    let grub_path = "/etc/default/grub";
-    let content = fs::read_to_string(grub_path)?;
-    
-    // Update GRUB_CMDLINE_LINUX_DEFAULT
-    let mut lines: Vec<String> = content.lines().map(|s| s.to_string()).collect();
-    let mut updated = false;
+fn revert_optimizations(fs: &dyn SystemFs) -> Result<()> {
+    let Some(state) = AppliedState::load(fs, state::STATE_PATH)? else {
+        anyhow::bail!("No applied optimizations recorded in {}", state::STATE_PATH);
+    };
     
-    for line in &mut lines {
-        if line.starts_with("GRUB_CMDLINE_LINUX_DEFAULT=") {
-            *line = format!("GRUB_CMDLINE_LINUX_DEFAULT=\"{}\"", params_str);
-            updated = true;
-            break;
+    let mut report = state.restore(fs, grub::GRUB_PATH);
+    for saved in &state.nvidia {
+        match nvidia::apply(saved) {
+            Ok(()) => report.restored += 1,
+            Err(e) => report.failed.push((saved.target(), format!("{:#}", e))),
         }
     }
+    for (path, error) in &report.failed {
+        warn!("Failed to restore {}: {}", path, error);
+    }
     
-    if !updated {
-        lines.push(format!("GRUB_CMDLINE_LINUX_DEFAULT=\"{}\"", params_str));
+    if report.grub_changed {
+        Command::new("update-grub").output()?;
+        info!("GRUB configuration restored; takes effect on next boot");
     }
     
-    // Write back
-    fs::write(grub_path, lines.join("\n"))?;
+    if report.failed.is_empty() {
+        fs.remove(state::STATE_PATH)?;
+        info!("Restored {} settings", report.restored);
+        Ok(())
+    } else {
+        // Keep the state file so the revert can be retried
+        anyhow::bail!("Restored {} settings, {} failed", report.restored, report.failed.len())
+    }
+}
+
+async fn apply_kernel_parameters(hardware: &HardwareInfo, fs: &dyn SystemFs) -> Result<usize> {
+    // Update GRUB configuration
+    update_grub_config(hardware, fs).await
+}
+
+async fn update_grub_config(hardware: &HardwareInfo, fs: &dyn SystemFs) -> Result<usize> {
+    // Merge into GRUB_CMDLINE_LINUX_DEFAULT, keeping the user's own parameters
+    let content = fs.read(grub::GRUB_PATH)?;
+    let current = grub::cmdline(&content);
+    let cmdline = plan::grub_cmdline(hardware, current.as_deref());
+    
+    if current.as_deref() == Some(cmdline.as_str()) {
+        return Ok(0);
+    }
+    fs.write(grub::GRUB_PATH, &grub::set_cmdline(&content, Some(&cmdline)))?;
     
     // Update GRUB
     Command::new("update-grub").output()?;
     
-    info!("GRUB configuration updated with: {}", params_str);
-    Ok(())
+    info!("GRUB configuration updated with: {}", cmdline);
+    Ok(1)
 }
 
-async fn configure_cpu_governor(hardware: &HardwareInfo) -> Result<()> {
-    let governor = match hardware.profile {
-        SystemProfile::AIFlagship | SystemProfile::ProWorkstation => "performance",
-        SystemProfile::HighPerformance => "ondemand",
-        _ => "powersave",
-    };
-    
-    // Set governor for all CPUs
-    for cpu_id in 0..hardware.cpu.threads {
-        let path = format!("/sys/devices/system/cpu/cpu{}/cpufreq/scaling_governor", cpu_id);
-        if Path::new(&path).exists() {
-            fs::write(&path, governor)?;
-        }
+/// Write the settings that differ from what is in place, returning how
+/// many were written
+fn write_settings(fs: &dyn SystemFs, settings: &[plan::Setting]) -> Result<usize> {
+    let mut written = 0;
+    for setting in settings.iter().filter(|setting| plan::needs_write(fs, setting)) {
+        fs.write(&setting.path, &setting.value)?;
+        written += 1;
     }
-    
-    info!("CPU governor set to: {}", governor);
-    Ok(())
+    Ok(written)
 }
 
-async fn configure_memory_management(hardware: &HardwareInfo) -> Result<()> {
-    // Determine swappiness based on RAM amount
-    let swappiness = match hardware.memory.total_gb {
-        ram if ram >= 64.0 => 10,
-        ram if ram >= 32.0 => 20,
-        ram if ram >= 16.0 => 40,
-        _ => 60,
-    };
-    
-    fs::write("/proc/sys/vm/swappiness", swappiness.to_string())?;
+async fn configure_cpu_governor(hardware: &HardwareInfo, fs: &dyn SystemFs) -> Result<usize> {
+    // Set governor for all CPUs
+    let changed = write_settings(fs, &plan::governor_settings(hardware, fs))?;
     
-    // Configure transparent hugepages
-    let thp_setting = match hardware.profile {
-        SystemProfile::AIFlagship | SystemProfile::ProWorkstation => "always",
-        _ => "madvise",
-    };
+    if changed > 0 {
+        info!("CPU governor set to: {}", plan::cpu_governor(&hardware.profile));
+    }
+    Ok(changed)
+}
+
+async fn configure_memory_management(hardware: &HardwareInfo, fs: &dyn SystemFs) -> Result<usize> {
+    let changed = write_settings(fs, &plan::memory_settings(hardware))?;
     
-    fs::write("/sys/kernel/mm/transparent_hugepage/enabled", thp_setting)?;
+    if changed > 0 {
+        info!("Memory management configured (swappiness={})", plan::swappiness(&hardware.profile, hardware.memory.total_gb));
+    }
+    Ok(changed)
+}
+
+async fn configure_storage_io(hardware: &HardwareInfo, fs: &dyn SystemFs) -> Result<usize> {
+    let mut changed = 0;
+    for setting in plan::storage_settings(hardware, fs) {
+        if plan::needs_write(fs, &setting) {
+            fs.write(&setting.path, &setting.value)?;
+            info!("{} set to: {}", setting.path, setting.value);
+            changed += 1;
+        }
+    }
     
-    // Set dirty ratios for better I/O performance
-    if hardware.memory.total_gb >= 32.0 {
-        fs::write("/proc/sys/vm/dirty_background_ratio", "5")?;
-        fs::write("/proc/sys/vm/dirty_ratio", "10")?;
+    Ok(changed)
+}
+
+async fn configure_gpu_settings(hardware: &HardwareInfo, fs: &dyn SystemFs, gpus: &[nvidia::NvidiaGpu]) -> Result<usize> {
+    let mut changed = 0;
+    for setting in plan::nvidia_settings(hardware, gpus) {
+        if plan::nvidia_needs_apply(gpus, &setting) {
+            nvidia::apply(&setting)?;
+            info!("{} set to: {}", setting.target(), setting.value);
+            changed += 1;
+        }
     }
     
-    info!("Memory management configured (swappiness={})", swappiness);
-    Ok(())
+    Ok(changed + configure_amd_gpus(hardware, fs))
 }
 
-async fn configure_storage_io(hardware: &HardwareInfo) -> Result<()> {
-    use hecate_core::StorageType;
+async fn configure_numa(hardware: &HardwareInfo, fs: &dyn SystemFs) -> Result<usize> {
+    // IRQs can vanish or refuse affinity changes; don't fail the whole pass
+    let pending: Vec<plan::Setting> = plan::numa_settings(hardware, fs)
+        .into_iter()
+        .filter(|setting| plan::needs_write(fs, setting))
+        .collect();
+    
+    let failed = plan::write_lenient(fs, &pending);
+    for (setting, e) in &failed {
+        warn!("Could not write {} to {}: {}", setting.value, setting.path, e);
+    }
     
-    for storage in &hardware.storage {
-        // Extract device name (e.g., "nvme0n1" from "/dev/nvme0n1")
-        let device_name = storage.device.strip_prefix("/dev/").unwrap_or(&storage.device);
-        let scheduler_path = format!("/sys/block/{}/queue/scheduler", device_name);
-        
-        if Path::new(&scheduler_path).exists() {
-            let scheduler = match storage.storage_type {
-                StorageType::NvmeGen5 | StorageType::NvmeGen4 | StorageType::NvmeGen3 => "none",
-                StorageType::Sata => "mq-deadline",
-                StorageType::Hdd => "bfq",
-                _ => "mq-deadline",
-            };
-            
-            fs::write(&scheduler_path, scheduler)?;
-            info!("I/O scheduler for {} set to: {}", storage.device, scheduler);
-            
-            // Set read-ahead for SSDs
-            if matches!(storage.storage_type, StorageType::NvmeGen5 | StorageType::NvmeGen4 | StorageType::NvmeGen3 | StorageType::Sata) {
-                let ra_path = format!("/sys/block/{}/queue/read_ahead_kb", device_name);
-                fs::write(&ra_path, "256")?;
-            }
-        }
+    let changed = pending.len() - failed.len();
+    if changed > 0 {
+        info!("NUMA tuning applied across {} nodes", hardware.numa_nodes.len());
+    }
+    Ok(changed)
+}
+
+/// Set the AMD power management level; a card without the nodes or with
+/// read-only ones is skipped rather than failing the whole pass
+fn configure_amd_gpus(hardware: &HardwareInfo, fs: &dyn SystemFs) -> usize {
+    let pending: Vec<plan::Setting> = plan::amd_gpu_settings(hardware, fs)
+        .into_iter()
+        .filter(|setting| plan::needs_write(fs, setting))
+        .collect();
+    
+    let failed = plan::write_lenient(fs, &pending);
+    for (setting, e) in &failed {
+        warn!("Could not write {} to {}: {}", setting.value, setting.path, e);
     }
     
-    Ok(())
+    let changed = pending.len() - failed.len();
+    if changed > 0 {
+        info!("AMD GPU performance level set to: {}", plan::amd_performance_level(&hardware.profile));
+    }
+    changed
 }
 
-async fn configure_gpu_settings(hardware: &HardwareInfo) -> Result<()> {
-    use hecate_core::GpuVendor;
+async fn start_monitoring_loop(hardware: &HardwareInfo, fs: &dyn SystemFs, config: &DaemonConfig) -> Result<()> {
+    info!("Starting monitoring daemon...");
     
-    for gpu in &hardware.gpu {
-        match gpu.vendor {
-            GpuVendor::Nvidia => {
-                // Enable persistence mode
-                Command::new("nvidia-smi")
-                    .args(&["-pm", "1"])
-                    .output()?;
+    let monitor = &config.monitor;
+    let mut thermal = ThermalPolicy::new(monitor.thermal.clone());
+    let mut memory = MemoryPolicy::new(monitor.memory.clone());
+    // Governors in place before throttling, restored once the CPU cools.
+    // A previous run may have stopped while throttled and left some behind.
+    let leftover = policy::load_saved_governors(fs, policy::SAVED_GOVERNORS_PATH);
+    if !leftover.is_empty() {
+        info!("Restoring CPU governors saved before an earlier throttle");
+        policy::restore(fs, &leftover);
+        persist_saved_governors(fs, &[]);
+    }
+    let mut saved_governors = Vec::new();
+    
+    // Only laptops follow the power source; the optimizations applied at
+    // startup are the battery ones, so switch right away if plugged in
+    let follow_ac = monitor.power.follow_ac && hardware.profile == SystemProfile::LaptopBattery;
+    let initial = if follow_ac { read_power_source() } else { None };
+    let mut power = PowerSourcePolicy::new(monitor.power.clone(), initial.unwrap_or(PowerSource::Battery));
+    if power.source() == PowerSource::Ac {
+        switch_power_profile(hardware, fs, PowerSource::Ac, &mut saved_governors);
+    } else {
+        publish_effective_profile(&hardware.profile);
+    }
+    
+    let mut status = StatusTracker::new(
+        policy::effective_profile(hardware, power.source()),
+        monitor.status.recent_warnings,
+    );
+    let published = status::Published::default();
+    if monitor.status.enabled {
+        let listener = status::bind(Path::new(&monitor.status.socket_path))?;
+        info!("Serving status on {}", monitor.status.socket_path);
+        tokio::spawn(status::serve(listener, published.clone()));
+        status::publish(&published, &status.snapshot(fs, unix_time()));
+    }
+    
+    let period = Duration::from_secs(monitor.interval_secs);
+    let mut health_checks = tokio::time::interval_at(Instant::now() + period, period);
+    let mut power_checks = tokio::time::interval(Duration::from_secs(monitor.power.poll_secs.max(1)));
+    
+    loop {
+        tokio::select! {
+            _ = health_checks.tick() => {
+                // Check thermal throttling
+                if let Some(event) = check_thermal_status(&mut thermal, monitor.thermal.high_celsius, &mut status) {
+                    match (event, monitor.thermal.action) {
+                        (ThermalEvent::Throttle, ThermalAction::Powersave) => {
+                            status.warn(format!("Sustained high temperature, switching CPUs to {}", policy::THROTTLE_GOVERNOR));
+                            saved_governors = policy::throttle(fs, &plan::governor_settings(hardware, fs));
+                            persist_saved_governors(fs, &saved_governors);
+                        }
+                        (ThermalEvent::Restore, ThermalAction::Powersave) => {
+                            info!("Temperature back to normal, restoring CPU governors");
+                            policy::restore(fs, &saved_governors);
+                            saved_governors.clear();
+                            persist_saved_governors(fs, &saved_governors);
+                        }
+                        (ThermalEvent::Throttle, ThermalAction::Log) => {
+                            status.warn("Sustained high temperature".to_string());
+                        }
+                        (ThermalEvent::Restore, ThermalAction::Log) => {
+                            info!("Temperature back to normal");
+                        }
+                    }
+                }
                 
-                // Set performance mode
-                Command::new("nvidia-smi")
-                    .args(&["-ac", "auto"])
-                    .output()?;
+                status.thermal_throttled = thermal.state() == policy::ThermalState::Throttled;
                 
-                // Set power limit based on profile
-                if matches!(hardware.profile, SystemProfile::AIFlagship | SystemProfile::ProWorkstation) {
-                    Command::new("nvidia-smi")
-                        .args(&["-pl", "500"]) // Max power
-                        .output()?;
-                }
+                // Monitor memory pressure
+                check_memory_pressure(fs, &mut memory, monitor.memory.action, &mut status);
                 
-                info!("NVIDIA GPU configured for maximum performance");
-            }
-            GpuVendor::Amd => {
-                // Set AMD GPU performance level
-                let perf_level = match hardware.profile {
-                    SystemProfile::AIFlagship | SystemProfile::ProWorkstation => "high",
-                    SystemProfile::HighPerformance => "auto",
-                    _ => "low",
-                };
+                // Check for GPU errors
+                check_gpu_health(&mut status).await?;
                 
-                // This would write to /sys/class/drm/card*/device/power_dpm_force_performance_level
-                info!("AMD GPU performance level set to: {}", perf_level);
+                if monitor.status.enabled {
+                    status::publish(&published, &status.snapshot(fs, unix_time()));
+                }
+            }
+            _ = power_checks.tick(), if follow_ac => {
+                if let Some(source) = power.observe(read_power_source()) {
+                    switch_power_profile(hardware, fs, source, &mut saved_governors);
+                    status.profile = policy::effective_profile(hardware, source);
+                    if monitor.status.enabled {
+                        status::publish(&published, &status.snapshot(fs, unix_time()));
+                    }
+                }
             }
-            _ => {}
         }
     }
-    
-    Ok(())
 }
 
-async fn start_monitoring_loop() -> Result<()> {
-    info!("Starting monitoring daemon...");
+fn unix_time() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+fn read_power_source() -> Option<PowerSource> {
+    hecate_core::power::on_ac_power(Path::new(hecate_core::power::POWER_SUPPLY_ROOT))
+        .map(PowerSource::from_ac_online)
+}
+
+/// Re-apply the governor and energy settings for the profile in effect on
+/// `source`, without re-detecting hardware
+///
+/// While thermal throttling holds the CPUs at powersave, the new governors
+/// replace the saved ones and take effect once the CPU cools down.
+fn switch_power_profile(hardware: &HardwareInfo, fs: &dyn SystemFs, source: PowerSource, saved_governors: &mut Vec<plan::Setting>) {
+    let profile = policy::effective_profile(hardware, source);
+    info!("Running on {:?}, switching to profile {:?}", source, profile);
+    
+    let governors = plan::governor_settings_for(hardware, &profile, fs);
+    let mut settings = plan::power_source_settings(hardware, &profile, fs);
+    if saved_governors.is_empty() {
+        settings.extend(governors);
+    } else {
+        *saved_governors = governors;
+        persist_saved_governors(fs, saved_governors);
+    }
+    for (setting, e) in plan::write_lenient(fs, &settings) {
+        warn!("Could not write {} to {}: {}", setting.value, setting.path, e);
+    }
     
-    loop {
-        // Monitor system health every 60 seconds
-        tokio::time::sleep(tokio::time::Duration::from_secs(60)).await;
-        
-        // Check thermal throttling
-        check_thermal_status().await?;
-        
-        // Monitor memory pressure
-        check_memory_pressure().await?;
-        
-        // Check for GPU errors
-        check_gpu_health().await?;
+    publish_effective_profile(&profile);
+}
+
+/// Record the profile in effect for hecate-monitor
+fn publish_effective_profile(profile: &SystemProfile) {
+    let path = Path::new(hecate_core::power::EFFECTIVE_PROFILE_PATH);
+    let result = path.parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|()| fs::write(path, serde_json::to_string(profile).unwrap_or_default()));
+    if let Err(e) = result {
+        warn!("Could not publish the effective profile to {}: {}", path.display(), e);
     }
 }
 
-async fn check_thermal_status() -> Result<()> {
-    // Read CPU temperature
-    let temp_path = "/sys/class/thermal/thermal_zone0/temp";
-    if let Ok(temp_str) = fs::read_to_string(temp_path) {
-        if let Ok(temp) = temp_str.trim().parse::<i32>() {
-            let temp_celsius = temp / 1000;
-            if temp_celsius > 85 {
-                warn!("High CPU temperature detected: {}°C", temp_celsius);
-                // Could trigger fan speed increase or frequency reduction
-            }
-        }
+/// Write the governors to restore after throttling to
+/// [`policy::SAVED_GOVERNORS_PATH`], or remove it when there are none
+fn persist_saved_governors(fs: &dyn SystemFs, saved: &[plan::Setting]) {
+    let path = Path::new(policy::SAVED_GOVERNORS_PATH);
+    let result = path.parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|()| policy::persist_governors(fs, policy::SAVED_GOVERNORS_PATH, saved));
+    if let Err(e) = result {
+        warn!("Could not save the CPU governors to {}: {}", path.display(), e);
     }
-    Ok(())
 }
 
-async fn check_memory_pressure() -> Result<()> {
-    // Check available memory
-    if let Ok(meminfo) = fs::read_to_string("/proc/meminfo") {
-        for line in meminfo.lines() {
-            if line.starts_with("MemAvailable:") {
-                if let Some(kb_str) = line.split_whitespace().nth(1) {
-                    if let Ok(kb) = kb_str.parse::<u64>() {
-                        let gb = kb as f64 / 1024.0 / 1024.0;
-                        if gb < 2.0 {
-                            warn!("Low memory available: {:.1} GB", gb);
-                            // Could trigger cache cleanup or OOM killer tuning
-                        }
-                    }
-                }
-            }
+fn check_thermal_status(policy: &mut ThermalPolicy, high_celsius: f64, status: &mut StatusTracker) -> Option<ThermalEvent> {
+    // Read every thermal zone, not just the first
+    status.temperatures = hecate_core::thermal::read_thermal_zones(Path::new(hecate_core::thermal::THERMAL_ROOT));
+    let hottest = status.temperatures.iter().max_by(|a, b| a.celsius.total_cmp(&b.celsius))?.clone();
+    if hottest.celsius >= high_celsius {
+        status.warn(format!("High temperature on {}: {:.0}°C", hottest.sensor, hottest.celsius));
+    }
+    policy.observe(hottest.celsius)
+}
+
+fn check_memory_pressure(fs: &dyn SystemFs, policy: &mut MemoryPolicy, action: MemoryAction, status: &mut StatusTracker) {
+    let Some(gb) = fs.read("/proc/meminfo").ok().and_then(|meminfo| policy::parse_mem_available_gb(&meminfo)) else {
+        return;
+    };
+    let started = policy.observe(gb);
+    status.memory_available_gb = Some(gb);
+    status.memory_pressure = policy.under_pressure();
+    if !started {
+        return;
+    }
+    
+    // tracing output goes to the journal under systemd
+    status.warn(format!("Memory pressure: {:.1} GB available", gb));
+    if action == MemoryAction::DropCaches {
+        // Flush dirty pages first so dropping the cache frees as much as possible
+        let _ = Command::new("sync").status();
+        match fs.write("/proc/sys/vm/drop_caches", "1") {
+            Ok(()) => info!("Dropped page cache"),
+            Err(e) => warn!("Could not drop page cache: {}", e),
         }
     }
-    Ok(())
 }
 
-async fn check_gpu_health() -> Result<()> {
+async fn check_gpu_health(status: &mut StatusTracker) -> Result<()> {
     // Check NVIDIA GPU if present
     if Path::new("/usr/bin/nvidia-smi").exists() {
         let output = Command::new("nvidia-smi")
@@ -375,7 +567,7 @@ async fn check_gpu_health() -> Result<()> {
             if values.len() >= 3 {
                 if let Ok(temp) = values[0].parse::<i32>() {
                     if temp > 83 {
-                        warn!("High GPU temperature: {}°C", temp);
+                        status.warn(format!("High GPU temperature: {}°C", temp));
                     }
                 }
             }
@@ -384,6 +576,24 @@ async fn check_gpu_health() -> Result<()> {
     Ok(())
 }
 
+fn print_planned_changes(changes: &[plan::PlannedChange]) {
+    println!("\n{}", "Planned changes (dry run, nothing written):".bright_cyan());
+    
+    for change in changes {
+        let current = change.current.as_deref().unwrap_or("<unreadable>");
+        if change.changes() {
+            println!("  {}", change.target.bold());
+            println!("    {} {}", "-".red(), current.red());
+            println!("    {} {}", "+".green(), change.proposed.green());
+        } else {
+            println!("  {} {}", change.target.dimmed(), format!("(unchanged: {})", current).dimmed());
+        }
+    }
+    
+    let count = changes.iter().filter(|c| c.changes()).count();
+    println!("\n{} of {} settings would change", count, changes.len());
+}
+
 fn save_hardware_config(hardware: &HardwareInfo) -> Result<()> {
     fs::create_dir_all("/etc/hecate")?;
     let json = serde_json::to_string_pretty(hardware)?;
@@ -406,6 +616,11 @@ fn print_system_summary(hardware: &HardwareInfo) {
     println!("║ CPU: {}", hardware.cpu.model);
     println!("║   Cores: {} | Threads: {}", hardware.cpu.cores, hardware.cpu.threads);
     println!("║ Memory: {:.1} GB", hardware.memory.total_gb);
+    if hardware.numa_nodes.len() > 1 {
+        for node in &hardware.numa_nodes {
+            println!("║   Node {}: {:.1} GB, CPUs {}", node.id, node.memory_gb, node.cpu_list());
+        }
+    }
     
     for (i, gpu) in hardware.gpu.iter().enumerate() {
         println!("║ GPU {}: {} ({:.1} GB)", i, gpu.model, gpu.vram_gb);
@@ -428,6 +643,20 @@ fn print_system_summary(hardware: &HardwareInfo) {
             println!("║   ✓ GPU optimized                                      ║");
             println!("║   ✓ I/O tuned for NVMe                                 ║");
         }
+        SystemProfile::Gaming => {
+            println!("║   ✓ Performance mode                                    ║");
+            println!("║   ✓ Full preemption                                    ║");
+            println!("║   ✓ Low swappiness                                     ║");
+        }
+        SystemProfile::Server => {
+            println!("║   ✓ Performance mode                                    ║");
+            println!("║   ✓ Low swappiness                                     ║");
+        }
+        SystemProfile::LaptopBattery => {
+            println!("║   ✓ Power saving enabled                               ║");
+            println!("║   ✓ PCIe link power management                         ║");
+            println!("║   ✓ Batched disk writeback                             ║");
+        }
         _ => {
             println!("║   ✓ Balanced performance                               ║");
             println!("║   ✓ Power saving enabled                               ║");
@@ -0,0 +1,143 @@
+//! NVIDIA GPU settings applied through `nvidia-smi`
+//!
+//! These can't be written as files, so they have their own setting type,
+//! but they are planned, recorded and reverted like every other tunable.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+
+/// What an [`NvidiaSetting`] controls
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NvidiaKnob {
+    /// `Enabled` or `Disabled`
+    PersistenceMode,
+    /// Watts, as reported by `nvidia-smi`
+    PowerLimit,
+}
+
+/// A value to set on one GPU
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NvidiaSetting {
+    /// `nvidia-smi` GPU index
+    pub gpu: u32,
+    pub knob: NvidiaKnob,
+    pub value: String,
+}
+
+impl NvidiaSetting {
+    /// Name shown in planned changes
+    pub fn target(&self) -> String {
+        let knob = match self.knob {
+            NvidiaKnob::PersistenceMode => "persistence mode",
+            NvidiaKnob::PowerLimit => "power limit",
+        };
+        format!("nvidia-smi GPU {} {}", self.gpu, knob)
+    }
+
+    fn args(&self) -> Vec<String> {
+        let (flag, value) = match self.knob {
+            NvidiaKnob::PersistenceMode => ("-pm", if self.value == "Enabled" { "1" } else { "0" }),
+            NvidiaKnob::PowerLimit => ("-pl", self.value.as_str()),
+        };
+        vec!["-i".into(), self.gpu.to_string(), flag.into(), value.into()]
+    }
+}
+
+/// Current state of one GPU
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NvidiaGpu {
+    pub index: u32,
+    pub persistence_mode: String,
+    /// `None` if the card doesn't support power management
+    pub power_limit: Option<String>,
+    pub max_power_limit: Option<String>,
+}
+
+/// Current value of what `setting` controls, if its GPU reports it
+pub fn current_value<'a>(gpus: &'a [NvidiaGpu], setting: &NvidiaSetting) -> Option<&'a str> {
+    let gpu = gpus.iter().find(|gpu| gpu.index == setting.gpu)?;
+    match setting.knob {
+        NvidiaKnob::PersistenceMode => Some(&gpu.persistence_mode),
+        NvidiaKnob::PowerLimit => gpu.power_limit.as_deref(),
+    }
+}
+
+const QUERY: &str = "--query-gpu=index,persistence_mode,power.limit,power.max_limit";
+
+/// Parse `nvidia-smi --query-gpu=index,persistence_mode,power.limit,power.max_limit --format=csv,noheader,nounits`
+pub fn parse_gpus(output: &str) -> Vec<NvidiaGpu> {
+    let figure = |value: &str| (!value.starts_with('[')).then(|| value.to_string());
+    output.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let [index, persistence_mode, power_limit, max_power_limit] = fields[..] else {
+                return None;
+            };
+            Some(NvidiaGpu {
+                index: index.parse().ok()?,
+                persistence_mode: persistence_mode.to_string(),
+                power_limit: figure(power_limit),
+                max_power_limit: figure(max_power_limit),
+            })
+        })
+        .collect()
+}
+
+/// State of every NVIDIA GPU, empty if nvidia-smi is unavailable
+pub fn query_gpus() -> Vec<NvidiaGpu> {
+    if !Path::new("/usr/bin/nvidia-smi").exists() {
+        return Vec::new();
+    }
+    match Command::new("nvidia-smi").args([QUERY, "--format=csv,noheader,nounits"]).output() {
+        Ok(output) if output.status.success() => parse_gpus(&String::from_utf8_lossy(&output.stdout)),
+        _ => Vec::new(),
+    }
+}
+
+/// Apply `setting` with nvidia-smi
+pub fn apply(setting: &NvidiaSetting) -> Result<()> {
+    let output = Command::new("nvidia-smi")
+        .args(setting.args())
+        .output()
+        .context("Failed to run nvidia-smi")?;
+    if !output.status.success() {
+        anyhow::bail!("nvidia-smi failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_gpus() {
+        let gpus = parse_gpus("0, Disabled, 350.00, 450.00\n1, Enabled, [N/A], [N/A]\nbogus\n");
+        assert_eq!(gpus, vec![
+            NvidiaGpu {
+                index: 0,
+                persistence_mode: "Disabled".into(),
+                power_limit: Some("350.00".into()),
+                max_power_limit: Some("450.00".into()),
+            },
+            NvidiaGpu {
+                index: 1,
+                persistence_mode: "Enabled".into(),
+                power_limit: None,
+                max_power_limit: None,
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_setting_arguments() {
+        let persistence = NvidiaSetting { gpu: 1, knob: NvidiaKnob::PersistenceMode, value: "Disabled".into() };
+        assert_eq!(persistence.args(), ["-i", "1", "-pm", "0"]);
+        assert_eq!(persistence.target(), "nvidia-smi GPU 1 persistence mode");
+
+        let power = NvidiaSetting { gpu: 0, knob: NvidiaKnob::PowerLimit, value: "450.00".into() };
+        assert_eq!(power.args(), ["-i", "0", "-pl", "450.00"]);
+    }
+}
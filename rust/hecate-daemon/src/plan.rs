@@ -0,0 +1,720 @@
+//! Tunable values for a hardware profile
+//!
+//! Everything the daemon writes to sysfs and procfs is computed here as a
+//! list of [`Setting`]s, so it can be snapshotted before being applied.
+
+use crate::grub;
+use crate::nvidia::{self, NvidiaGpu, NvidiaKnob, NvidiaSetting};
+use crate::sysfs::{current_value, SystemFs};
+use hecate_core::{GpuVendor, HardwareInfo, StorageType, SystemProfile};
+use serde::{Deserialize, Serialize};
+
+/// A value to write to a tunable file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Setting {
+    pub path: String,
+    pub value: String,
+}
+
+impl Setting {
+    fn new(path: impl Into<String>, value: impl ToString) -> Self {
+        Self {
+            path: path.into(),
+            value: value.to_string(),
+        }
+    }
+}
+
+/// Kernel command line parameters for the profile
+pub fn kernel_parameters(hardware: &HardwareInfo) -> Vec<&'static str> {
+    let mut params = vec![
+        "intel_pstate=active",
+        "intel_iommu=on",
+        "iommu=pt",
+    ];
+
+    // Link power management costs latency everywhere but on battery
+    if !matches!(hardware.profile, SystemProfile::LaptopBattery) {
+        params.push("pcie_aspm=off");
+    }
+
+    // Add profile-specific parameters
+    match hardware.profile {
+        SystemProfile::AIFlagship | SystemProfile::ProWorkstation => {
+            params.push("mitigations=off");
+            params.push("processor.max_cstate=1");
+            params.push("intel_idle.max_cstate=0");
+            params.push("nvme_core.default_ps_max_latency_us=0");
+        }
+        SystemProfile::HighPerformance => {
+            params.push("mitigations=auto,nosmt");
+            params.push("processor.max_cstate=2");
+        }
+        SystemProfile::Gaming => {
+            params.push("preempt=full");
+            params.push("processor.max_cstate=2");
+        }
+        SystemProfile::LaptopBattery => {
+            params.push("pcie_aspm.policy=powersave");
+        }
+        _ => {
+            // Keep default parameters for standard systems
+        }
+    }
+
+    params
+}
+
+/// The kernel command line with the profile's parameters merged into
+/// `current`, keeping whatever else is already there
+pub fn grub_cmdline(hardware: &HardwareInfo, current: Option<&str>) -> String {
+    grub::merge_params(current.unwrap_or(""), &kernel_parameters(hardware))
+}
+
+pub fn cpu_governor(profile: &SystemProfile) -> &'static str {
+    match profile {
+        SystemProfile::AIFlagship | SystemProfile::ProWorkstation => "performance",
+        SystemProfile::Server | SystemProfile::Gaming => "performance",
+        SystemProfile::HighPerformance => "ondemand",
+        _ => "powersave",
+    }
+}
+
+/// Governor for every CPU that has cpufreq
+pub fn governor_settings(hardware: &HardwareInfo, fs: &dyn SystemFs) -> Vec<Setting> {
+    governor_settings_for(hardware, &hardware.profile, fs)
+}
+
+/// Governor of `profile` for every CPU that has cpufreq
+pub fn governor_settings_for(hardware: &HardwareInfo, profile: &SystemProfile, fs: &dyn SystemFs) -> Vec<Setting> {
+    let governor = cpu_governor(profile);
+    (0..hardware.cpu.threads)
+        .map(|cpu_id| format!("/sys/devices/system/cpu/cpu{}/cpufreq/scaling_governor", cpu_id))
+        .filter(|path| fs.exists(path))
+        .map(|path| Setting::new(path, governor))
+        .collect()
+}
+
+/// Swappiness based on RAM amount; servers and gaming machines keep their
+/// working set in memory whatever the RAM
+pub fn swappiness(profile: &SystemProfile, total_gb: f64) -> u32 {
+    if matches!(profile, SystemProfile::Server | SystemProfile::Gaming) {
+        return 10;
+    }
+
+    match total_gb {
+        ram if ram >= 64.0 => 10,
+        ram if ram >= 32.0 => 20,
+        ram if ram >= 16.0 => 40,
+        _ => 60,
+    }
+}
+
+const DIRTY_WRITEBACK_PATH: &str = "/proc/sys/vm/dirty_writeback_centisecs";
+
+/// Interval between writeback flushes; the kernel default except on battery
+pub fn dirty_writeback_centisecs(profile: &SystemProfile) -> u32 {
+    match profile {
+        SystemProfile::LaptopBattery => 1500,
+        _ => 500,
+    }
+}
+
+/// Swappiness, transparent hugepages and dirty ratios
+pub fn memory_settings(hardware: &HardwareInfo) -> Vec<Setting> {
+    let thp_setting = match hardware.profile {
+        SystemProfile::AIFlagship | SystemProfile::ProWorkstation => "always",
+        _ => "madvise",
+    };
+
+    let mut settings = vec![
+        Setting::new("/proc/sys/vm/swappiness", swappiness(&hardware.profile, hardware.memory.total_gb)),
+        Setting::new("/sys/kernel/mm/transparent_hugepage/enabled", thp_setting),
+    ];
+
+    // Set dirty ratios for better I/O performance
+    if hardware.memory.total_gb >= 32.0 {
+        settings.push(Setting::new("/proc/sys/vm/dirty_background_ratio", 5));
+        settings.push(Setting::new("/proc/sys/vm/dirty_ratio", 10));
+    }
+
+    // Batch writeback so the disk can stay idle longer on battery
+    if matches!(hardware.profile, SystemProfile::LaptopBattery) {
+        settings.push(Setting::new(DIRTY_WRITEBACK_PATH, dirty_writeback_centisecs(&hardware.profile)));
+    }
+
+    settings
+}
+
+/// I/O scheduler and SSD read-ahead for every block device present
+pub fn storage_settings(hardware: &HardwareInfo, fs: &dyn SystemFs) -> Vec<Setting> {
+    let mut settings = Vec::new();
+
+    for storage in &hardware.storage {
+        // Extract device name (e.g., "nvme0n1" from "/dev/nvme0n1")
+        let device_name = storage.device.strip_prefix("/dev/").unwrap_or(&storage.device);
+        let scheduler_path = format!("/sys/block/{}/queue/scheduler", device_name);
+        if !fs.exists(&scheduler_path) {
+            continue;
+        }
+
+        let scheduler = match storage.storage_type {
+            StorageType::NvmeGen5 | StorageType::NvmeGen4 | StorageType::NvmeGen3 => "none",
+            StorageType::Sata => "mq-deadline",
+            StorageType::Hdd => "bfq",
+            _ => "mq-deadline",
+        };
+        settings.push(Setting::new(scheduler_path, scheduler));
+
+        // Set read-ahead for SSDs
+        if matches!(storage.storage_type, StorageType::NvmeGen5 | StorageType::NvmeGen4 | StorageType::NvmeGen3 | StorageType::Sata) {
+            settings.push(Setting::new(format!("/sys/block/{}/queue/read_ahead_kb", device_name), 256));
+        }
+    }
+
+    settings
+}
+
+/// DRM class directory listing every GPU
+pub const DRM_ROOT: &str = "/sys/class/drm";
+
+/// PCI vendor id of AMD
+const AMD_PCI_VENDOR: &str = "0x1002";
+
+/// AMD power management level for the profile
+pub fn amd_performance_level(profile: &SystemProfile) -> &'static str {
+    match profile {
+        SystemProfile::AIFlagship | SystemProfile::ProWorkstation | SystemProfile::Gaming => "high",
+        SystemProfile::HighPerformance | SystemProfile::Server => "auto",
+        _ => "low",
+    }
+}
+
+/// `device` directories of the DRM cards whose PCI vendor is AMD
+pub fn amd_cards(fs: &dyn SystemFs) -> Vec<String> {
+    let Ok(entries) = fs.list(DRM_ROOT) else {
+        return Vec::new();
+    };
+
+    entries.into_iter()
+        // card0, not connectors like card0-DP-1 or render nodes
+        .filter(|name| name.strip_prefix("card").is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit())))
+        .map(|name| format!("{}/{}/device", DRM_ROOT, name))
+        .filter(|device| {
+            fs.read(&format!("{}/vendor", device))
+                .is_ok_and(|vendor| vendor.trim().eq_ignore_ascii_case(AMD_PCI_VENDOR))
+        })
+        .collect()
+}
+
+/// Forced performance level, and the legacy DPM state where the driver
+/// has one, for every AMD card
+pub fn amd_gpu_settings(hardware: &HardwareInfo, fs: &dyn SystemFs) -> Vec<Setting> {
+    amd_gpu_settings_for(hardware, &hardware.profile, fs)
+}
+
+fn amd_gpu_settings_for(hardware: &HardwareInfo, profile: &SystemProfile, fs: &dyn SystemFs) -> Vec<Setting> {
+    if !hardware.gpu.iter().any(|gpu| matches!(gpu.vendor, GpuVendor::Amd)) {
+        return Vec::new();
+    }
+
+    let level = amd_performance_level(profile);
+    let dpm_state = match level {
+        "high" => "performance",
+        "auto" => "balanced",
+        _ => "battery",
+    };
+
+    let mut settings = Vec::new();
+    for device in amd_cards(fs) {
+        let level_path = format!("{}/power_dpm_force_performance_level", device);
+        if fs.exists(&level_path) {
+            settings.push(Setting::new(level_path, level));
+        }
+        let state_path = format!("{}/power_dpm_state", device);
+        if fs.exists(&state_path) {
+            settings.push(Setting::new(state_path, dpm_state));
+        }
+    }
+    settings
+}
+
+/// Settings that follow the power source when a laptop switches between
+/// `profile`s: AMD GPU power level and disk writeback, governors excluded
+///
+/// Governors are left to the caller because thermal throttling may be
+/// holding them at powersave.
+pub fn power_source_settings(hardware: &HardwareInfo, profile: &SystemProfile, fs: &dyn SystemFs) -> Vec<Setting> {
+    let mut settings = amd_gpu_settings_for(hardware, profile, fs);
+    if fs.exists(DIRTY_WRITEBACK_PATH) {
+        settings.push(Setting::new(DIRTY_WRITEBACK_PATH, dirty_writeback_centisecs(profile)));
+    }
+    settings
+}
+
+/// PCI device directory
+pub const PCI_ROOT: &str = "/sys/bus/pci/devices";
+
+/// PCI class prefixes of devices whose interrupts are pinned to their
+/// node: display controllers, NVMe and network
+const NUMA_PINNED_CLASSES: &[&str] = &["0x03", "0x010802", "0x02"];
+
+/// Whether the profile gets NUMA tuning; single-node systems never do
+pub fn numa_tuned(hardware: &HardwareInfo) -> bool {
+    hardware.numa_nodes.len() > 1
+        && matches!(hardware.profile, SystemProfile::AIFlagship | SystemProfile::ProWorkstation)
+}
+
+/// NUMA settings for multi-socket workstations
+///
+/// Zone reclaim is turned off so a full node spills to remote memory
+/// instead of evicting its page cache, automatic NUMA balancing migrates
+/// pages toward the threads using them, and the interrupts of GPUs, NVMe
+/// drives and NICs are pinned to the CPUs of the node the device hangs
+/// off.
+pub fn numa_settings(hardware: &HardwareInfo, fs: &dyn SystemFs) -> Vec<Setting> {
+    if !numa_tuned(hardware) {
+        return Vec::new();
+    }
+
+    let mut settings = vec![Setting::new("/proc/sys/vm/zone_reclaim_mode", 0)];
+    if fs.exists("/proc/sys/kernel/numa_balancing") {
+        settings.push(Setting::new("/proc/sys/kernel/numa_balancing", 1));
+    }
+    settings.extend(irq_affinity_settings(hardware, fs));
+    settings
+}
+
+/// Pin the MSI interrupts of node-local devices to that node's CPUs
+fn irq_affinity_settings(hardware: &HardwareInfo, fs: &dyn SystemFs) -> Vec<Setting> {
+    let Ok(devices) = fs.list(PCI_ROOT) else {
+        return Vec::new();
+    };
+
+    let mut settings = Vec::new();
+    for device in devices {
+        let dir = format!("{}/{}", PCI_ROOT, device);
+        let pinned = fs.read(&format!("{}/class", dir))
+            .is_ok_and(|class| NUMA_PINNED_CLASSES.iter().any(|prefix| class.trim().starts_with(prefix)));
+        if !pinned {
+            continue;
+        }
+
+        // numa_node is -1 when the firmware does not say
+        let Some(node) = fs.read(&format!("{}/numa_node", dir)).ok()
+            .and_then(|id| id.trim().parse::<u32>().ok())
+            .and_then(|id| hardware.numa_nodes.iter().find(|node| node.id == id))
+        else {
+            continue;
+        };
+
+        for irq in fs.list(&format!("{}/msi_irqs", dir)).unwrap_or_default() {
+            let path = format!("/proc/irq/{}/smp_affinity_list", irq);
+            if fs.exists(&path) {
+                settings.push(Setting::new(path, node.cpu_list()));
+            }
+        }
+    }
+    settings
+}
+
+/// Write each setting, collecting failures instead of stopping at the
+/// first one
+pub fn write_lenient<'a>(fs: &dyn SystemFs, settings: &'a [Setting]) -> Vec<(&'a Setting, std::io::Error)> {
+    settings.iter()
+        .filter(|setting| needs_write(fs, setting))
+        .filter_map(|setting| fs.write(&setting.path, &setting.value).err().map(|e| (setting, e)))
+        .collect()
+}
+
+/// Whether `setting` differs from what is in place; unreadable files count
+/// as differing so the write surfaces the error
+pub fn needs_write(fs: &dyn SystemFs, setting: &Setting) -> bool {
+    fs.read(&setting.path).map_or(true, |contents| current_value(&contents) != setting.value)
+}
+
+/// Every tunable file the daemon writes for `hardware`
+pub fn settings(hardware: &HardwareInfo, fs: &dyn SystemFs) -> Vec<Setting> {
+    let mut settings = governor_settings(hardware, fs);
+    settings.extend(memory_settings(hardware));
+    settings.extend(storage_settings(hardware, fs));
+    settings.extend(amd_gpu_settings(hardware, fs));
+    settings.extend(numa_settings(hardware, fs));
+    settings
+}
+
+/// `nvidia-smi` settings for the NVIDIA GPUs in `gpus`: persistence mode,
+/// and the highest power limit the card allows on workstation profiles
+pub fn nvidia_settings(hardware: &HardwareInfo, gpus: &[NvidiaGpu]) -> Vec<NvidiaSetting> {
+    if !hardware.gpu.iter().any(|gpu| matches!(gpu.vendor, GpuVendor::Nvidia)) {
+        return Vec::new();
+    }
+    let max_power = matches!(hardware.profile, SystemProfile::AIFlagship | SystemProfile::ProWorkstation);
+
+    let mut settings = Vec::new();
+    for gpu in gpus {
+        settings.push(NvidiaSetting { gpu: gpu.index, knob: NvidiaKnob::PersistenceMode, value: "Enabled".into() });
+        if let Some(limit) = gpu.max_power_limit.as_ref().filter(|_| max_power) {
+            settings.push(NvidiaSetting { gpu: gpu.index, knob: NvidiaKnob::PowerLimit, value: limit.clone() });
+        }
+    }
+    settings
+}
+
+/// Whether `setting` differs from the state of `gpus`
+pub fn nvidia_needs_apply(gpus: &[NvidiaGpu], setting: &NvidiaSetting) -> bool {
+    nvidia::current_value(gpus, setting) != Some(setting.value.as_str())
+}
+
+/// A value the daemon would write, next to what is there now
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedChange {
+    /// File path, or `GRUB_CMDLINE_LINUX_DEFAULT` for the kernel command line
+    pub target: String,
+    /// `None` if the current value cannot be read
+    pub current: Option<String>,
+    pub proposed: String,
+}
+
+impl PlannedChange {
+    pub fn changes(&self) -> bool {
+        self.current.as_deref() != Some(self.proposed.as_str())
+    }
+}
+
+/// Everything applying optimizations for `hardware` would write or set
+/// through nvidia-smi on `gpus`, without changing anything
+pub fn planned_changes(hardware: &HardwareInfo, fs: &dyn SystemFs, gpus: &[NvidiaGpu]) -> Vec<PlannedChange> {
+    let current = fs.read(grub::GRUB_PATH).ok().and_then(|content| grub::cmdline(&content));
+    let mut changes = vec![PlannedChange {
+        target: "GRUB_CMDLINE_LINUX_DEFAULT".to_string(),
+        proposed: grub_cmdline(hardware, current.as_deref()),
+        current,
+    }];
+
+    changes.extend(settings(hardware, fs).into_iter().map(|setting| PlannedChange {
+        current: fs.read(&setting.path).ok().map(|contents| current_value(&contents)),
+        target: setting.path,
+        proposed: setting.value,
+    }));
+
+    changes.extend(nvidia_settings(hardware, gpus).into_iter().map(|setting| PlannedChange {
+        target: setting.target(),
+        current: nvidia::current_value(gpus, &setting).map(Into::into),
+        proposed: setting.value,
+    }));
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sysfs::MockFs;
+    use hecate_core::numa::NumaNode;
+    use hecate_core::{CpuInfo, GpuInfo, GpuVendor, MemoryInfo, StorageInfo};
+
+    fn hardware(profile: SystemProfile, total_gb: f64) -> HardwareInfo {
+        HardwareInfo {
+            cpu: CpuInfo {
+                vendor: "GenuineIntel".into(),
+                model: "Intel Core i9-14900K".into(),
+                cores: 2,
+                threads: 2,
+                base_frequency: 3200.0,
+                max_frequency: 6000.0,
+                generation: Some(14),
+            },
+            memory: MemoryInfo { total_gb, speed_mhz: None, memory_type: None },
+            gpu: vec![GpuInfo {
+                vendor: GpuVendor::Nvidia,
+                model: "NVIDIA GeForce RTX 4090".into(),
+                vram_gb: 24.0,
+                driver_version: None,
+                compute_capability: None,
+            }],
+            storage: vec![
+                StorageInfo {
+                    device: "/dev/nvme0n1".into(),
+                    mount_point: "/".into(),
+                    total_gb: 2000.0,
+                    storage_type: StorageType::NvmeGen4,
+                    nvme_gen: Some(4),
+                },
+                StorageInfo {
+                    device: "/dev/sda".into(),
+                    mount_point: "/data".into(),
+                    total_gb: 8000.0,
+                    storage_type: StorageType::Hdd,
+                    nvme_gen: None,
+                },
+            ],
+            numa_nodes: vec![],
+            profile,
+        }
+    }
+
+    fn system() -> MockFs {
+        MockFs::with_files(&[
+            ("/etc/default/grub", "GRUB_DEFAULT=0\nGRUB_CMDLINE_LINUX_DEFAULT=\"quiet splash\"\n"),
+            ("/sys/devices/system/cpu/cpu0/cpufreq/scaling_governor", "powersave\n"),
+            ("/sys/devices/system/cpu/cpu1/cpufreq/scaling_governor", "performance\n"),
+            ("/proc/sys/vm/swappiness", "60\n"),
+            ("/sys/kernel/mm/transparent_hugepage/enabled", "always [madvise] never\n"),
+            ("/proc/sys/vm/dirty_background_ratio", "10\n"),
+            ("/proc/sys/vm/dirty_ratio", "20\n"),
+            ("/sys/block/nvme0n1/queue/scheduler", "[none] mq-deadline kyber\n"),
+            ("/sys/block/nvme0n1/queue/read_ahead_kb", "128\n"),
+            ("/sys/block/sda/queue/scheduler", "none [mq-deadline] bfq\n"),
+        ])
+    }
+
+    fn change(target: &str, current: Option<&str>, proposed: &str) -> PlannedChange {
+        PlannedChange {
+            target: target.into(),
+            current: current.map(Into::into),
+            proposed: proposed.into(),
+        }
+    }
+
+    #[test]
+    fn test_planned_changes_for_ai_flagship() {
+        let fs = system();
+        let changes = planned_changes(&hardware(SystemProfile::AIFlagship, 128.0), &fs, &[nvidia_gpu()]);
+
+        assert_eq!(changes, vec![
+            change(
+                "GRUB_CMDLINE_LINUX_DEFAULT",
+                Some("quiet splash"),
+                "quiet splash intel_pstate=active intel_iommu=on iommu=pt pcie_aspm=off mitigations=off processor.max_cstate=1 intel_idle.max_cstate=0 nvme_core.default_ps_max_latency_us=0",
+            ),
+            change("/sys/devices/system/cpu/cpu0/cpufreq/scaling_governor", Some("powersave"), "performance"),
+            change("/sys/devices/system/cpu/cpu1/cpufreq/scaling_governor", Some("performance"), "performance"),
+            change("/proc/sys/vm/swappiness", Some("60"), "10"),
+            change("/sys/kernel/mm/transparent_hugepage/enabled", Some("madvise"), "always"),
+            change("/proc/sys/vm/dirty_background_ratio", Some("10"), "5"),
+            change("/proc/sys/vm/dirty_ratio", Some("20"), "10"),
+            change("/sys/block/nvme0n1/queue/scheduler", Some("none"), "none"),
+            change("/sys/block/nvme0n1/queue/read_ahead_kb", Some("128"), "256"),
+            change("/sys/block/sda/queue/scheduler", Some("mq-deadline"), "bfq"),
+            change("nvidia-smi GPU 0 persistence mode", Some("Disabled"), "Enabled"),
+            change("nvidia-smi GPU 0 power limit", Some("350.00"), "450.00"),
+        ]);
+
+        let unchanged: Vec<&str> = changes.iter()
+            .filter(|c| !c.changes())
+            .map(|c| c.target.as_str())
+            .collect();
+        assert_eq!(unchanged, vec![
+            "/sys/devices/system/cpu/cpu1/cpufreq/scaling_governor",
+            "/sys/block/nvme0n1/queue/scheduler",
+        ]);
+
+        // Planning writes nothing
+        assert_eq!(fs.get("/proc/sys/vm/swappiness").as_deref(), Some("60\n"));
+    }
+
+    fn nvidia_gpu() -> NvidiaGpu {
+        NvidiaGpu {
+            index: 0,
+            persistence_mode: "Disabled".into(),
+            power_limit: Some("350.00".into()),
+            max_power_limit: Some("450.00".into()),
+        }
+    }
+
+    #[test]
+    fn test_nvidia_settings() {
+        let mut gpus = vec![nvidia_gpu()];
+        let settings = nvidia_settings(&hardware(SystemProfile::AIFlagship, 128.0), &gpus);
+        assert_eq!(settings.len(), 2);
+        assert!(settings.iter().all(|s| nvidia_needs_apply(&gpus, s)));
+
+        // Only the persistence mode is set outside the performance profiles
+        assert_eq!(nvidia_settings(&hardware(SystemProfile::Gaming, 32.0), &gpus), vec![
+            NvidiaSetting { gpu: 0, knob: NvidiaKnob::PersistenceMode, value: "Enabled".into() },
+        ]);
+
+        // Nothing is left to change once applied
+        gpus[0].persistence_mode = "Enabled".into();
+        gpus[0].power_limit = Some("450.00".into());
+        assert!(settings.iter().all(|s| !nvidia_needs_apply(&gpus, s)));
+
+        // Without an NVIDIA card in the hardware nothing is planned
+        let mut hw = hardware(SystemProfile::AIFlagship, 128.0);
+        hw.gpu[0].vendor = GpuVendor::Amd;
+        assert!(nvidia_settings(&hw, &gpus).is_empty());
+    }
+
+    #[test]
+    fn test_amd_gpu_settings() {
+        let mut hw = hardware(SystemProfile::AIFlagship, 128.0);
+        hw.gpu[0].vendor = GpuVendor::Amd;
+
+        let fs = MockFs::with_files(&[
+            ("/sys/class/drm/card0/device/vendor", "0x8086\n"),
+            ("/sys/class/drm/card0/device/power_dpm_force_performance_level", "auto\n"),
+            ("/sys/class/drm/card1/device/vendor", "0x1002\n"),
+            ("/sys/class/drm/card1/device/power_dpm_force_performance_level", "auto\n"),
+            ("/sys/class/drm/card1/device/power_dpm_state", "balanced\n"),
+            ("/sys/class/drm/card1-DP-1/status", "connected\n"),
+            ("/sys/class/drm/card2/device/vendor", "0x1002\n"),
+            ("/sys/class/drm/renderD128/dev", "226:128\n"),
+        ]);
+
+        // The Intel card is skipped, and card2 has no power management nodes
+        assert_eq!(amd_gpu_settings(&hw, &fs), vec![
+            Setting::new("/sys/class/drm/card1/device/power_dpm_force_performance_level", "high"),
+            Setting::new("/sys/class/drm/card1/device/power_dpm_state", "performance"),
+        ]);
+
+        // A read-only node is reported and the rest are still written
+        fs.set_read_only("/sys/class/drm/card1/device/power_dpm_state");
+        let settings = amd_gpu_settings(&hw, &fs);
+        let failed = write_lenient(&fs, &settings);
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0.path, "/sys/class/drm/card1/device/power_dpm_state");
+        assert_eq!(fs.get("/sys/class/drm/card1/device/power_dpm_force_performance_level").as_deref(), Some("high"));
+
+        hw.profile = SystemProfile::Standard;
+        assert_eq!(amd_gpu_settings(&hw, &fs)[0].value, "low");
+
+        // Nothing to do without an AMD GPU
+        hw.gpu[0].vendor = GpuVendor::Nvidia;
+        assert!(amd_gpu_settings(&hw, &fs).is_empty());
+    }
+
+    #[test]
+    fn test_needs_write() {
+        let fs = system();
+        let governor = "/sys/devices/system/cpu/cpu1/cpufreq/scaling_governor";
+        assert!(!needs_write(&fs, &Setting::new(governor, "performance")));
+        assert!(needs_write(&fs, &Setting::new(governor, "powersave")));
+        assert!(!needs_write(&fs, &Setting::new("/sys/block/nvme0n1/queue/scheduler", "none")));
+        assert!(needs_write(&fs, &Setting::new("/sys/block/nvme1n1/queue/scheduler", "none")));
+
+        // Applying the plan leaves nothing to change on the next run
+        let hw = hardware(SystemProfile::AIFlagship, 128.0);
+        assert!(write_lenient(&fs, &settings(&hw, &fs)).is_empty());
+        assert!(planned_changes(&hw, &fs, &[]).iter().skip(1).all(|c| !c.changes()));
+    }
+
+    fn numa_node(id: u32, cpus: std::ops::Range<usize>) -> NumaNode {
+        NumaNode {
+            id,
+            cpus: cpus.collect(),
+            memory_gb: 64.0,
+        }
+    }
+
+    #[test]
+    fn test_numa_settings() {
+        let mut hw = hardware(SystemProfile::AIFlagship, 128.0);
+        let fs = MockFs::with_files(&[
+            ("/proc/sys/vm/zone_reclaim_mode", "1\n"),
+            ("/proc/sys/kernel/numa_balancing", "0\n"),
+            // GPU on node 1, NVMe on node 0, a USB controller and a device
+            // without a node are left alone
+            ("/sys/bus/pci/devices/0000:01:00.0/class", "0x030000\n"),
+            ("/sys/bus/pci/devices/0000:01:00.0/numa_node", "1\n"),
+            ("/sys/bus/pci/devices/0000:01:00.0/msi_irqs/140", "msix\n"),
+            ("/sys/bus/pci/devices/0000:01:00.0/msi_irqs/141", "msix\n"),
+            ("/sys/bus/pci/devices/0000:02:00.0/class", "0x010802\n"),
+            ("/sys/bus/pci/devices/0000:02:00.0/numa_node", "0\n"),
+            ("/sys/bus/pci/devices/0000:02:00.0/msi_irqs/150", "msix\n"),
+            ("/sys/bus/pci/devices/0000:03:00.0/class", "0x0c0330\n"),
+            ("/sys/bus/pci/devices/0000:03:00.0/numa_node", "0\n"),
+            ("/sys/bus/pci/devices/0000:03:00.0/msi_irqs/160", "msi\n"),
+            ("/sys/bus/pci/devices/0000:04:00.0/class", "0x020000\n"),
+            ("/sys/bus/pci/devices/0000:04:00.0/numa_node", "-1\n"),
+            ("/sys/bus/pci/devices/0000:04:00.0/msi_irqs/170", "msix\n"),
+            ("/proc/irq/140/smp_affinity_list", "0-31\n"),
+            ("/proc/irq/141/smp_affinity_list", "0-31\n"),
+            ("/proc/irq/150/smp_affinity_list", "0-31\n"),
+            ("/proc/irq/160/smp_affinity_list", "0-31\n"),
+            ("/proc/irq/170/smp_affinity_list", "0-31\n"),
+        ]);
+
+        // Single-node systems are unaffected
+        hw.numa_nodes = vec![numa_node(0, 0..32)];
+        assert!(numa_settings(&hw, &fs).is_empty());
+
+        hw.numa_nodes = vec![numa_node(0, 0..16), numa_node(1, 16..32)];
+        assert_eq!(numa_settings(&hw, &fs), vec![
+            Setting::new("/proc/sys/vm/zone_reclaim_mode", "0"),
+            Setting::new("/proc/sys/kernel/numa_balancing", "1"),
+            Setting::new("/proc/irq/140/smp_affinity_list", "16-31"),
+            Setting::new("/proc/irq/141/smp_affinity_list", "16-31"),
+            Setting::new("/proc/irq/150/smp_affinity_list", "0-15"),
+        ]);
+
+        hw.profile = SystemProfile::Developer;
+        assert!(numa_settings(&hw, &fs).is_empty());
+    }
+
+    #[test]
+    fn test_new_profile_defaults() {
+        let laptop = hardware(SystemProfile::LaptopBattery, 16.0);
+        assert_eq!(cpu_governor(&laptop.profile), "powersave");
+        assert_eq!(swappiness(&laptop.profile, 16.0), 40);
+        assert_eq!(amd_performance_level(&laptop.profile), "low");
+        let params = kernel_parameters(&laptop);
+        assert!(!params.contains(&"pcie_aspm=off"));
+        assert!(params.contains(&"pcie_aspm.policy=powersave"));
+        let memory = memory_settings(&laptop);
+        assert!(memory.contains(&Setting::new("/sys/kernel/mm/transparent_hugepage/enabled", "madvise")));
+        assert!(memory.contains(&Setting::new("/proc/sys/vm/dirty_writeback_centisecs", "1500")));
+
+        let server = hardware(SystemProfile::Server, 16.0);
+        assert_eq!(cpu_governor(&server.profile), "performance");
+        assert_eq!(swappiness(&server.profile, 16.0), 10);
+        assert_eq!(amd_performance_level(&server.profile), "auto");
+        assert!(kernel_parameters(&server).contains(&"pcie_aspm=off"));
+        assert!(memory_settings(&server).contains(&Setting::new("/sys/kernel/mm/transparent_hugepage/enabled", "madvise")));
+
+        let gaming = hardware(SystemProfile::Gaming, 16.0);
+        assert_eq!(cpu_governor(&gaming.profile), "performance");
+        assert_eq!(swappiness(&gaming.profile, 16.0), 10);
+        assert_eq!(amd_performance_level(&gaming.profile), "high");
+        assert!(kernel_parameters(&gaming).contains(&"preempt=full"));
+        assert!(!memory_settings(&gaming).iter().any(|s| s.path.ends_with("dirty_writeback_centisecs")));
+    }
+
+    #[test]
+    fn test_power_source_settings() {
+        let mut hw = hardware(SystemProfile::LaptopBattery, 16.0);
+        hw.gpu[0].vendor = GpuVendor::Amd;
+        let fs = MockFs::with_files(&[
+            ("/sys/devices/system/cpu/cpu0/cpufreq/scaling_governor", "powersave\n"),
+            ("/proc/sys/vm/dirty_writeback_centisecs", "1500\n"),
+            ("/sys/class/drm/card0/device/vendor", "0x1002\n"),
+            ("/sys/class/drm/card0/device/power_dpm_force_performance_level", "low\n"),
+        ]);
+
+        // Plugged in, the laptop runs as the profile its hardware warrants
+        assert_eq!(power_source_settings(&hw, &SystemProfile::HighPerformance, &fs), vec![
+            Setting::new("/sys/class/drm/card0/device/power_dpm_force_performance_level", "auto"),
+            Setting::new("/proc/sys/vm/dirty_writeback_centisecs", "500"),
+        ]);
+        assert_eq!(governor_settings_for(&hw, &SystemProfile::HighPerformance, &fs), vec![
+            Setting::new("/sys/devices/system/cpu/cpu0/cpufreq/scaling_governor", "ondemand"),
+        ]);
+
+        assert_eq!(power_source_settings(&hw, &SystemProfile::LaptopBattery, &fs), vec![
+            Setting::new("/sys/class/drm/card0/device/power_dpm_force_performance_level", "low"),
+            Setting::new("/proc/sys/vm/dirty_writeback_centisecs", "1500"),
+        ]);
+    }
+
+    #[test]
+    fn test_standard_profile_skips_dirty_ratios() {
+        let changes = planned_changes(&hardware(SystemProfile::Standard, 8.0), &system(), &[]);
+        assert!(changes.iter().all(|c| !c.target.contains("dirty")));
+        assert!(changes.contains(&change("/proc/sys/vm/swappiness", Some("60"), "60")));
+        assert!(changes.contains(&change(
+            "/sys/devices/system/cpu/cpu1/cpufreq/scaling_governor",
+            Some("performance"),
+            "powersave",
+        )));
+    }
+}
@@ -0,0 +1,436 @@
+//! Responses to sustained thermal and memory pressure, and to power
+//! source changes
+//!
+//! The policies are pure state machines fed one sample per monitoring
+//! interval; the monitoring loop carries out the actions they return.
+
+use crate::config::{MemoryConfig, PowerConfig, ThermalConfig};
+use crate::plan::{self, Setting};
+use crate::sysfs::{current_value, SystemFs};
+use hecate_core::{HardwareDetector, HardwareInfo, SystemProfile};
+
+/// Governor used while the CPU is throttled
+pub const THROTTLE_GOVERNOR: &str = "powersave";
+
+/// Whether the thermal response is in effect
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThermalState {
+    Normal,
+    Throttled,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThermalEvent {
+    /// The temperature stayed high; start throttling
+    Throttle,
+    /// The temperature stayed low; undo the throttling
+    Restore,
+}
+
+/// Throttle after `sustain_samples` consecutive samples at or above
+/// `high_celsius`, and restore after as many below `clear_celsius`
+///
+/// The gap between the two thresholds, plus the sample count, keeps the
+/// policy from flapping around a single threshold.
+pub struct ThermalPolicy {
+    config: ThermalConfig,
+    state: ThermalState,
+    streak: u32,
+}
+
+impl ThermalPolicy {
+    pub fn new(config: ThermalConfig) -> Self {
+        Self {
+            config,
+            state: ThermalState::Normal,
+            streak: 0,
+        }
+    }
+
+    pub fn state(&self) -> ThermalState {
+        self.state
+    }
+
+    /// Feed the hottest sensor's temperature
+    pub fn observe(&mut self, celsius: f64) -> Option<ThermalEvent> {
+        let toward_other_state = match self.state {
+            ThermalState::Normal => celsius >= self.config.high_celsius,
+            ThermalState::Throttled => celsius < self.config.clear_celsius,
+        };
+
+        if !toward_other_state {
+            self.streak = 0;
+            return None;
+        }
+
+        self.streak += 1;
+        if self.streak < self.config.sustain_samples.max(1) {
+            return None;
+        }
+
+        self.streak = 0;
+        match self.state {
+            ThermalState::Normal => {
+                self.state = ThermalState::Throttled;
+                Some(ThermalEvent::Throttle)
+            }
+            ThermalState::Throttled => {
+                self.state = ThermalState::Normal;
+                Some(ThermalEvent::Restore)
+            }
+        }
+    }
+}
+
+/// Switch every governor in `governors` to [`THROTTLE_GOVERNOR`]
+///
+/// Returns the governors that were in place, to hand to [`restore`] once
+/// the CPU has cooled down. Files that cannot be read are left alone.
+pub fn throttle(fs: &dyn SystemFs, governors: &[Setting]) -> Vec<Setting> {
+    let saved: Vec<Setting> = governors.iter()
+        .filter_map(|setting| {
+            fs.read(&setting.path).ok().map(|contents| Setting {
+                path: setting.path.clone(),
+                value: current_value(&contents),
+            })
+        })
+        .collect();
+
+    let powersave: Vec<Setting> = saved.iter()
+        .map(|setting| Setting {
+            path: setting.path.clone(),
+            value: THROTTLE_GOVERNOR.to_string(),
+        })
+        .collect();
+    for (setting, e) in plan::write_lenient(fs, &powersave) {
+        tracing::warn!("Could not throttle {}: {}", setting.path, e);
+    }
+
+    saved
+}
+
+/// Put back the governors saved by [`throttle`]
+pub fn restore(fs: &dyn SystemFs, saved: &[Setting]) {
+    for (setting, e) in plan::write_lenient(fs, saved) {
+        tracing::warn!("Could not restore {}: {}", setting.path, e);
+    }
+}
+
+/// Where the governors saved by [`throttle`] are kept while throttled, so a
+/// daemon restarted in the meantime can still put them back
+pub const SAVED_GOVERNORS_PATH: &str = "/run/hecate/throttled-governors.json";
+
+/// Keep `saved` at `path`, removing the file once nothing is saved
+pub fn persist_governors(fs: &dyn SystemFs, path: &str, saved: &[Setting]) -> std::io::Result<()> {
+    if saved.is_empty() {
+        return if fs.exists(path) { fs.remove(path) } else { Ok(()) };
+    }
+    fs.write(path, &serde_json::to_string_pretty(saved)?)
+}
+
+/// Governors left at `path` by a daemon that stopped while throttled
+pub fn load_saved_governors(fs: &dyn SystemFs, path: &str) -> Vec<Setting> {
+    let Ok(contents) = fs.read(path) else {
+        return Vec::new();
+    };
+    serde_json::from_str(&contents).unwrap_or_else(|e| {
+        tracing::warn!("Ignoring invalid saved governors in {}: {}", path, e);
+        Vec::new()
+    })
+}
+
+/// Reports memory pressure once when available memory drops below the
+/// threshold, and again only after it has recovered
+pub struct MemoryPolicy {
+    config: MemoryConfig,
+    under_pressure: bool,
+}
+
+impl MemoryPolicy {
+    pub fn new(config: MemoryConfig) -> Self {
+        Self {
+            config,
+            under_pressure: false,
+        }
+    }
+
+    pub fn under_pressure(&self) -> bool {
+        self.under_pressure
+    }
+
+    /// Feed available memory; true when pressure has just started
+    pub fn observe(&mut self, available_gb: f64) -> bool {
+        let low = available_gb < self.config.low_available_gb;
+        let started = low && !self.under_pressure;
+        self.under_pressure = low;
+        started
+    }
+}
+
+/// Where a laptop draws its power from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerSource {
+    Ac,
+    Battery,
+}
+
+impl PowerSource {
+    pub fn from_ac_online(on_ac: bool) -> Self {
+        if on_ac {
+            Self::Ac
+        } else {
+            Self::Battery
+        }
+    }
+}
+
+/// Reports a switch of power source once the new source has been seen for
+/// `settle_samples` consecutive samples
+///
+/// Samples that cannot be read (`None`) neither count toward a switch nor
+/// break a streak.
+pub struct PowerSourcePolicy {
+    config: PowerConfig,
+    source: PowerSource,
+    streak: u32,
+}
+
+impl PowerSourcePolicy {
+    pub fn new(config: PowerConfig, source: PowerSource) -> Self {
+        Self {
+            config,
+            source,
+            streak: 0,
+        }
+    }
+
+    pub fn source(&self) -> PowerSource {
+        self.source
+    }
+
+    /// Feed the power source read from sysfs; the new source when the
+    /// switch has just settled
+    pub fn observe(&mut self, sample: Option<PowerSource>) -> Option<PowerSource> {
+        let sample = sample?;
+        if sample == self.source {
+            self.streak = 0;
+            return None;
+        }
+
+        self.streak += 1;
+        if self.streak < self.config.settle_samples.max(1) {
+            return None;
+        }
+
+        self.streak = 0;
+        self.source = sample;
+        Some(sample)
+    }
+}
+
+/// The profile to run `hardware` as on `source`
+///
+/// Only laptops follow the power source: on AC they run as the profile
+/// their hardware would get without the battery, from the cached hardware
+/// information rather than a fresh detection.
+pub fn effective_profile(hardware: &HardwareInfo, source: PowerSource) -> SystemProfile {
+    match (&hardware.profile, source) {
+        (SystemProfile::LaptopBattery, PowerSource::Ac) => {
+            HardwareDetector::determine_profile(&hardware.cpu, &hardware.memory, &hardware.gpu)
+        }
+        (profile, _) => profile.clone(),
+    }
+}
+
+/// `MemAvailable` from `/proc/meminfo` contents, in GB
+pub fn parse_mem_available_gb(meminfo: &str) -> Option<f64> {
+    meminfo.lines()
+        .find_map(|line| line.strip_prefix("MemAvailable:"))
+        .and_then(|rest| rest.split_whitespace().next())
+        .and_then(|kb| kb.parse::<u64>().ok())
+        .map(|kb| kb as f64 / 1024.0 / 1024.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{MemoryAction, ThermalAction};
+    use crate::sysfs::MockFs;
+
+    fn policy() -> ThermalPolicy {
+        ThermalPolicy::new(ThermalConfig {
+            high_celsius: 85.0,
+            clear_celsius: 75.0,
+            sustain_samples: 3,
+            action: ThermalAction::Powersave,
+        })
+    }
+
+    fn run(policy: &mut ThermalPolicy, series: &[f64]) -> Vec<Option<ThermalEvent>> {
+        series.iter().map(|&t| policy.observe(t)).collect()
+    }
+
+    #[test]
+    fn test_throttles_only_when_sustained() {
+        let mut policy = policy();
+
+        // Two hot samples then a dip resets the streak
+        let events = run(&mut policy, &[86.0, 90.0, 80.0, 88.0, 87.0]);
+        assert!(events.iter().all(Option::is_none));
+        assert_eq!(policy.state(), ThermalState::Normal);
+
+        assert_eq!(policy.observe(85.0), Some(ThermalEvent::Throttle));
+        assert_eq!(policy.state(), ThermalState::Throttled);
+    }
+
+    #[test]
+    fn test_hysteresis_prevents_flapping() {
+        let mut policy = policy();
+        run(&mut policy, &[90.0, 90.0, 90.0]);
+        assert_eq!(policy.state(), ThermalState::Throttled);
+
+        // Hovering between the thresholds neither restores nor re-throttles
+        let events = run(&mut policy, &[84.0, 80.0, 76.0, 84.0, 79.0, 75.0, 86.0, 90.0]);
+        assert!(events.iter().all(Option::is_none));
+        assert_eq!(policy.state(), ThermalState::Throttled);
+
+        // Sustained cooling below the clear threshold restores
+        let events = run(&mut policy, &[74.0, 70.0, 68.0]);
+        assert_eq!(events, vec![None, None, Some(ThermalEvent::Restore)]);
+        assert_eq!(policy.state(), ThermalState::Normal);
+
+        // A cool reading interrupted by a warm one starts over
+        run(&mut policy, &[90.0, 90.0, 90.0]);
+        let events = run(&mut policy, &[70.0, 70.0, 78.0, 70.0, 70.0]);
+        assert!(events.iter().all(Option::is_none));
+        assert_eq!(policy.observe(70.0), Some(ThermalEvent::Restore));
+    }
+
+    #[test]
+    fn test_throttle_and_restore_governors() {
+        let cpu0 = "/sys/devices/system/cpu/cpu0/cpufreq/scaling_governor";
+        let cpu1 = "/sys/devices/system/cpu/cpu1/cpufreq/scaling_governor";
+        let fs = MockFs::with_files(&[(cpu0, "performance\n"), (cpu1, "schedutil\n")]);
+        let governors: Vec<Setting> = [cpu0, cpu1]
+            .iter()
+            .map(|path| Setting { path: path.to_string(), value: "performance".to_string() })
+            .collect();
+
+        let saved = throttle(&fs, &governors);
+        assert_eq!(fs.get(cpu0).as_deref(), Some(THROTTLE_GOVERNOR));
+        assert_eq!(fs.get(cpu1).as_deref(), Some(THROTTLE_GOVERNOR));
+
+        restore(&fs, &saved);
+        assert_eq!(fs.get(cpu0).as_deref(), Some("performance"));
+        assert_eq!(fs.get(cpu1).as_deref(), Some("schedutil"));
+    }
+
+    #[test]
+    fn test_saved_governors_survive_a_restart() {
+        let cpu0 = "/sys/devices/system/cpu/cpu0/cpufreq/scaling_governor";
+        let fs = MockFs::with_files(&[(cpu0, "schedutil\n")]);
+        let saved = throttle(&fs, &[Setting { path: cpu0.to_string(), value: "performance".to_string() }]);
+        persist_governors(&fs, SAVED_GOVERNORS_PATH, &saved).unwrap();
+
+        // A new daemon finds what the old one saved
+        assert_eq!(load_saved_governors(&fs, SAVED_GOVERNORS_PATH), saved);
+
+        persist_governors(&fs, SAVED_GOVERNORS_PATH, &[]).unwrap();
+        assert!(!fs.exists(SAVED_GOVERNORS_PATH));
+        assert!(load_saved_governors(&fs, SAVED_GOVERNORS_PATH).is_empty());
+    }
+
+    #[test]
+    fn test_memory_pressure_is_edge_triggered() {
+        let mut policy = MemoryPolicy::new(MemoryConfig {
+            low_available_gb: 2.0,
+            action: MemoryAction::Log,
+        });
+
+        let events: Vec<bool> = [4.0, 1.5, 1.0, 1.8, 3.0, 1.9]
+            .iter()
+            .map(|&gb| policy.observe(gb))
+            .collect();
+        assert_eq!(events, vec![false, true, false, false, false, true]);
+    }
+
+    fn power_policy() -> PowerSourcePolicy {
+        PowerSourcePolicy::new(PowerConfig { follow_ac: true, poll_secs: 5, settle_samples: 3 }, PowerSource::Ac)
+    }
+
+    #[test]
+    fn test_power_source_transition() {
+        use PowerSource::{Ac, Battery};
+        let mut policy = power_policy();
+
+        // Staying on AC reports nothing
+        assert_eq!(policy.observe(Some(Ac)), None);
+
+        let events: Vec<_> = [Battery, Battery, Battery].iter().map(|&s| policy.observe(Some(s))).collect();
+        assert_eq!(events, vec![None, None, Some(Battery)]);
+        assert_eq!(policy.source(), Battery);
+
+        // Still unplugged: no repeat
+        assert_eq!(policy.observe(Some(Battery)), None);
+
+        let events: Vec<_> = [Ac, Ac, Ac].iter().map(|&s| policy.observe(Some(s))).collect();
+        assert_eq!(events, vec![None, None, Some(Ac)]);
+    }
+
+    #[test]
+    fn test_power_source_debounce() {
+        use PowerSource::{Ac, Battery};
+        let mut policy = power_policy();
+
+        // A flapping connector never settles on battery
+        let events: Vec<_> = [Battery, Battery, Ac, Battery, Ac, Battery, Battery, Ac]
+            .iter()
+            .map(|&s| policy.observe(Some(s)))
+            .collect();
+        assert!(events.iter().all(Option::is_none));
+        assert_eq!(policy.source(), Ac);
+
+        // Unreadable samples neither switch nor reset the streak
+        assert_eq!(policy.observe(Some(Battery)), None);
+        assert_eq!(policy.observe(None), None);
+        assert_eq!(policy.observe(Some(Battery)), None);
+        assert_eq!(policy.observe(None), None);
+        assert_eq!(policy.observe(Some(Battery)), Some(Battery));
+    }
+
+    #[test]
+    fn test_effective_profile() {
+        use hecate_core::{CpuInfo, MemoryInfo};
+
+        let mut hardware = HardwareInfo {
+            cpu: CpuInfo {
+                vendor: "GenuineIntel".into(),
+                model: "Intel Core Ultra 7 155H".into(),
+                cores: 16,
+                threads: 22,
+                base_frequency: 1400.0,
+                max_frequency: 4800.0,
+                generation: None,
+            },
+            memory: MemoryInfo { total_gb: 32.0, speed_mhz: None, memory_type: None },
+            gpu: vec![],
+            storage: vec![],
+            numa_nodes: vec![],
+            profile: SystemProfile::LaptopBattery,
+        };
+
+        assert_eq!(effective_profile(&hardware, PowerSource::Battery), SystemProfile::LaptopBattery);
+        assert_eq!(effective_profile(&hardware, PowerSource::Ac), SystemProfile::Developer);
+
+        // Desktops keep their profile whatever the readings say
+        hardware.profile = SystemProfile::Gaming;
+        assert_eq!(effective_profile(&hardware, PowerSource::Battery), SystemProfile::Gaming);
+    }
+
+    #[test]
+    fn test_parse_mem_available() {
+        let meminfo = "MemTotal:       65775612 kB\nMemFree:         1234567 kB\nMemAvailable:    2097152 kB\n";
+        assert_eq!(parse_mem_available_gb(meminfo), Some(2.0));
+        assert_eq!(parse_mem_available_gb("MemTotal: 1 kB\n"), None);
+    }
+}
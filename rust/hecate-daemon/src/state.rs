@@ -0,0 +1,253 @@
+//! Record of the values optimizations replaced
+//!
+//! Before the daemon changes a tunable for the first time it saves the
+//! original value here, so `hecated --revert` can put the system back the
+//! way it was. Re-applying only adds entries for newly touched files, so
+//! the originals survive any number of runs.
+
+use crate::grub;
+use crate::nvidia::{self, NvidiaGpu, NvidiaSetting};
+use crate::plan::Setting;
+use crate::sysfs::{current_value, SystemFs};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// Where the original values are kept
+pub const STATE_PATH: &str = "/etc/hecate/applied-state.json";
+
+/// A tunable and the value it had before the daemon changed it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedSetting {
+    pub path: String,
+    pub value: String,
+}
+
+/// The kernel command line before the daemon changed it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedGrub {
+    /// `None` if `GRUB_CMDLINE_LINUX_DEFAULT` was not set
+    pub cmdline: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppliedState {
+    #[serde(default)]
+    pub grub: Option<SavedGrub>,
+    #[serde(default)]
+    pub settings: Vec<SavedSetting>,
+    /// nvidia-smi settings as they were, restored with nvidia-smi
+    #[serde(default)]
+    pub nvidia: Vec<NvidiaSetting>,
+}
+
+/// Outcome of [`AppliedState::restore`]
+#[derive(Debug, Default)]
+pub struct RestoreReport {
+    pub restored: usize,
+    /// Files that could not be written back, with the error
+    pub failed: Vec<(String, String)>,
+    /// Whether the GRUB defaults were rewritten and need `update-grub`
+    pub grub_changed: bool,
+}
+
+impl AppliedState {
+    /// Load the state at `path`, or `None` if nothing has been applied
+    pub fn load(fs: &dyn SystemFs, path: &str) -> Result<Option<Self>> {
+        if !fs.exists(path) {
+            return Ok(None);
+        }
+        let content = fs.read(path).with_context(|| format!("Failed to read {}", path))?;
+        let state = serde_json::from_str(&content).with_context(|| format!("Invalid applied state in {}", path))?;
+        Ok(Some(state))
+    }
+
+    pub fn save(&self, fs: &dyn SystemFs, path: &str) -> Result<()> {
+        fs.write(path, &serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path))
+    }
+
+    /// Save the current value of every setting and of the GRUB command line
+    /// that has not been recorded yet
+    pub fn record(&mut self, fs: &dyn SystemFs, settings: &[Setting], grub_path: &str) {
+        for setting in settings {
+            if self.settings.iter().any(|saved| saved.path == setting.path) {
+                continue;
+            }
+            match fs.read(&setting.path) {
+                Ok(contents) => self.settings.push(SavedSetting {
+                    path: setting.path.clone(),
+                    value: current_value(&contents),
+                }),
+                Err(e) => warn!("Cannot record original value of {}: {}", setting.path, e),
+            }
+        }
+
+        if self.grub.is_none() {
+            if let Ok(content) = fs.read(grub_path) {
+                self.grub = Some(SavedGrub { cmdline: grub::cmdline(&content) });
+            }
+        }
+    }
+
+    /// Save the current state of every nvidia-smi setting that has not been
+    /// recorded yet
+    pub fn record_nvidia(&mut self, gpus: &[NvidiaGpu], settings: &[NvidiaSetting]) {
+        for setting in settings {
+            if self.nvidia.iter().any(|saved| saved.gpu == setting.gpu && saved.knob == setting.knob) {
+                continue;
+            }
+            match nvidia::current_value(gpus, setting) {
+                Some(value) => self.nvidia.push(NvidiaSetting { value: value.to_string(), ..setting.clone() }),
+                None => warn!("Cannot record original value of {}", setting.target()),
+            }
+        }
+    }
+
+    /// Write every recorded value back, continuing past failures
+    pub fn restore(&self, fs: &dyn SystemFs, grub_path: &str) -> RestoreReport {
+        let mut report = RestoreReport::default();
+
+        for saved in &self.settings {
+            match fs.write(&saved.path, &saved.value) {
+                Ok(()) => report.restored += 1,
+                Err(e) => report.failed.push((saved.path.clone(), e.to_string())),
+            }
+        }
+
+        if let Some(saved) = &self.grub {
+            let result = fs.read(grub_path)
+                .and_then(|content| fs.write(grub_path, &grub::set_cmdline(&content, saved.cmdline.as_deref())));
+            match result {
+                Ok(()) => report.grub_changed = true,
+                Err(e) => report.failed.push((grub_path.to_string(), e.to_string())),
+            }
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sysfs::MockFs;
+
+    const GOVERNOR: &str = "/sys/devices/system/cpu/cpu0/cpufreq/scaling_governor";
+    const SCHEDULER: &str = "/sys/block/nvme0n1/queue/scheduler";
+    const SWAPPINESS: &str = "/proc/sys/vm/swappiness";
+    const GRUB: &str = "/etc/default/grub";
+
+    fn system() -> MockFs {
+        MockFs::with_files(&[
+            (GOVERNOR, "powersave\n"),
+            (SCHEDULER, "none [mq-deadline] kyber\n"),
+            (SWAPPINESS, "60\n"),
+            (GRUB, "GRUB_DEFAULT=0\nGRUB_CMDLINE_LINUX_DEFAULT=\"quiet splash\"\n"),
+        ])
+    }
+
+    fn settings() -> Vec<Setting> {
+        vec![
+            Setting { path: GOVERNOR.into(), value: "performance".into() },
+            Setting { path: SCHEDULER.into(), value: "none".into() },
+            Setting { path: SWAPPINESS.into(), value: "10".into() },
+            Setting { path: "/sys/missing".into(), value: "1".into() },
+        ]
+    }
+
+    #[test]
+    fn test_snapshot_round_trips() {
+        let fs = system();
+        let mut state = AppliedState::default();
+        state.record(&fs, &settings(), GRUB);
+
+        assert_eq!(state.settings, vec![
+            SavedSetting { path: GOVERNOR.into(), value: "powersave".into() },
+            SavedSetting { path: SCHEDULER.into(), value: "mq-deadline".into() },
+            SavedSetting { path: SWAPPINESS.into(), value: "60".into() },
+        ]);
+        assert_eq!(state.grub, Some(SavedGrub { cmdline: Some("quiet splash".into()) }));
+
+        state.save(&fs, STATE_PATH).unwrap();
+        assert_eq!(AppliedState::load(&fs, STATE_PATH).unwrap(), Some(state));
+        assert_eq!(AppliedState::load(&MockFs::default(), STATE_PATH).unwrap(), None);
+    }
+
+    #[test]
+    fn test_rerun_keeps_original_values() {
+        let fs = system();
+        let mut state = AppliedState::default();
+        state.record(&fs, &settings(), GRUB);
+
+        // The optimizations are applied, then recorded again on the next boot
+        fs.write(GOVERNOR, "performance").unwrap();
+        fs.write(GRUB, "GRUB_CMDLINE_LINUX_DEFAULT=\"iommu=pt\"\n").unwrap();
+        state.record(&fs, &settings(), GRUB);
+
+        assert_eq!(state.settings[0].value, "powersave");
+        assert_eq!(state.settings.len(), 3);
+        assert_eq!(state.grub.as_ref().unwrap().cmdline.as_deref(), Some("quiet splash"));
+    }
+
+    #[test]
+    fn test_nvidia_originals_are_recorded_once() {
+        use crate::nvidia::NvidiaKnob;
+
+        let mut gpu = NvidiaGpu {
+            index: 0,
+            persistence_mode: "Disabled".into(),
+            power_limit: Some("350.00".into()),
+            max_power_limit: Some("450.00".into()),
+        };
+        let settings = [
+            NvidiaSetting { gpu: 0, knob: NvidiaKnob::PersistenceMode, value: "Enabled".into() },
+            NvidiaSetting { gpu: 0, knob: NvidiaKnob::PowerLimit, value: "450.00".into() },
+        ];
+        let mut state = AppliedState::default();
+        state.record_nvidia(std::slice::from_ref(&gpu), &settings);
+
+        gpu.persistence_mode = "Enabled".into();
+        gpu.power_limit = Some("450.00".into());
+        state.record_nvidia(&[gpu], &settings);
+
+        assert_eq!(state.nvidia, [
+            NvidiaSetting { gpu: 0, knob: NvidiaKnob::PersistenceMode, value: "Disabled".into() },
+            NvidiaSetting { gpu: 0, knob: NvidiaKnob::PowerLimit, value: "350.00".into() },
+        ]);
+    }
+
+    #[test]
+    fn test_restore_writes_originals_back() {
+        let fs = system();
+        let mut state = AppliedState::default();
+        state.record(&fs, &settings(), GRUB);
+
+        for setting in settings() {
+            fs.write(&setting.path, &setting.value).unwrap();
+        }
+        fs.write(GRUB, "GRUB_DEFAULT=0\nGRUB_CMDLINE_LINUX_DEFAULT=\"iommu=pt mitigations=off\"\n").unwrap();
+
+        let report = state.restore(&fs, GRUB);
+        assert_eq!(report.restored, 3);
+        assert!(report.failed.is_empty());
+        assert!(report.grub_changed);
+
+        assert_eq!(fs.get(GOVERNOR).as_deref(), Some("powersave"));
+        assert_eq!(fs.get(SCHEDULER).as_deref(), Some("mq-deadline"));
+        assert_eq!(fs.get(SWAPPINESS).as_deref(), Some("60"));
+        assert_eq!(fs.get(GRUB).as_deref(), Some("GRUB_DEFAULT=0\nGRUB_CMDLINE_LINUX_DEFAULT=\"quiet splash\"\n"));
+    }
+
+    #[test]
+    fn test_restore_removes_added_cmdline() {
+        let fs = MockFs::with_files(&[(GRUB, "GRUB_DEFAULT=0\n")]);
+        let mut state = AppliedState::default();
+        state.record(&fs, &[], GRUB);
+        assert_eq!(state.grub, Some(SavedGrub { cmdline: None }));
+
+        fs.write(GRUB, "GRUB_DEFAULT=0\nGRUB_CMDLINE_LINUX_DEFAULT=\"iommu=pt\"\n").unwrap();
+        state.restore(&fs, GRUB);
+        assert_eq!(fs.get(GRUB).as_deref(), Some("GRUB_DEFAULT=0\n"));
+    }
+}
@@ -0,0 +1,232 @@
+//! Read-only status for hecate-monitor and the dashboard
+//!
+//! The monitoring loop keeps a [`StatusTracker`] up to date and publishes a
+//! JSON [`StatusSnapshot`] after every health check. Anyone connecting to
+//! the status socket gets the latest snapshot as one line of JSON, and the
+//! connection is closed.
+
+use crate::sysfs::{current_value, SystemFs};
+use anyhow::{Context, Result};
+use hecate_core::thermal::SensorReading;
+use hecate_core::SystemProfile;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use tokio::io::AsyncWriteExt;
+use tokio::net::UnixListener;
+
+/// Version of the snapshot layout; bumped when fields change meaning or go away
+pub const SCHEMA_VERSION: u32 = 1;
+
+const GOVERNOR_PATH: &str = "/sys/devices/system/cpu/cpu0/cpufreq/scaling_governor";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusSnapshot {
+    pub schema_version: u32,
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    /// Profile in effect, which follows the power source on laptops
+    pub profile: SystemProfile,
+    pub temperatures: Vec<SensorReading>,
+    /// Governor of the first CPU; `None` without cpufreq
+    pub governor: Option<String>,
+    pub thermal_throttled: bool,
+    pub memory_available_gb: Option<f64>,
+    pub memory_pressure: bool,
+    /// Latest warnings, oldest first
+    pub warnings: Vec<String>,
+}
+
+/// What the monitoring loop has seen since the daemon started
+pub struct StatusTracker {
+    pub profile: SystemProfile,
+    pub temperatures: Vec<SensorReading>,
+    pub thermal_throttled: bool,
+    pub memory_available_gb: Option<f64>,
+    pub memory_pressure: bool,
+    warnings: VecDeque<String>,
+    max_warnings: usize,
+}
+
+impl StatusTracker {
+    pub fn new(profile: SystemProfile, max_warnings: usize) -> Self {
+        Self {
+            profile,
+            temperatures: Vec::new(),
+            thermal_throttled: false,
+            memory_available_gb: None,
+            memory_pressure: false,
+            warnings: VecDeque::new(),
+            max_warnings,
+        }
+    }
+
+    /// Log a warning and keep it for the snapshot, dropping the oldest
+    /// once `max_warnings` are kept
+    pub fn warn(&mut self, message: String) {
+        tracing::warn!("{}", message);
+        if self.max_warnings == 0 {
+            return;
+        }
+        if self.warnings.len() == self.max_warnings {
+            self.warnings.pop_front();
+        }
+        self.warnings.push_back(message);
+    }
+
+    pub fn snapshot(&self, fs: &dyn SystemFs, timestamp: u64) -> StatusSnapshot {
+        StatusSnapshot {
+            schema_version: SCHEMA_VERSION,
+            timestamp,
+            profile: self.profile.clone(),
+            temperatures: self.temperatures.clone(),
+            governor: fs.read(GOVERNOR_PATH).ok().map(|contents| current_value(&contents)),
+            thermal_throttled: self.thermal_throttled,
+            memory_available_gb: self.memory_available_gb,
+            memory_pressure: self.memory_pressure,
+            warnings: self.warnings.iter().cloned().collect(),
+        }
+    }
+}
+
+/// Latest snapshot as JSON, shared between the monitoring loop and the socket
+pub type Published = Arc<RwLock<String>>;
+
+/// Replace the published snapshot
+pub fn publish(published: &Published, snapshot: &StatusSnapshot) {
+    match serde_json::to_string(snapshot) {
+        Ok(json) => *published.write().unwrap_or_else(|e| e.into_inner()) = json,
+        Err(e) => tracing::warn!("Could not serialize status snapshot: {}", e),
+    }
+}
+
+/// Listen on `path`, replacing a socket left behind by an earlier run
+///
+/// Anything else at `path` is left alone and is an error, so a mistyped
+/// `socket_path` cannot delete a file. The socket is world-connectable: it
+/// only ever hands out the snapshot.
+pub fn bind(path: &Path) -> Result<UnixListener> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            std::fs::remove_file(path)
+                .with_context(|| format!("Failed to remove stale socket {}", path.display()))?;
+        }
+        Ok(_) => anyhow::bail!("{} exists and is not a socket", path.display()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e).with_context(|| format!("Failed to inspect {}", path.display())),
+    }
+
+    let listener = UnixListener::bind(path)
+        .with_context(|| format!("Failed to listen on {}", path.display()))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o666))?;
+    Ok(listener)
+}
+
+/// Answer every connection with the latest snapshot; runs until the
+/// daemon exits
+pub async fn serve(listener: UnixListener, published: Published) {
+    loop {
+        let mut stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                tracing::warn!("Status socket accept failed: {}", e);
+                continue;
+            }
+        };
+
+        let mut line = published.read().unwrap_or_else(|e| e.into_inner()).clone();
+        line.push('\n');
+        // A client that hangs up early is its own problem
+        let _ = stream.write_all(line.as_bytes()).await;
+        let _ = stream.shutdown().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sysfs::MockFs;
+    use tokio::io::AsyncReadExt;
+
+    fn tracker() -> StatusTracker {
+        let mut tracker = StatusTracker::new(SystemProfile::Gaming, 2);
+        tracker.temperatures = vec![
+            SensorReading { sensor: "thermal_zone0 (x86_pkg_temp)".into(), celsius: 71.5 },
+            SensorReading { sensor: "thermal_zone1 (acpitz)".into(), celsius: 45.0 },
+        ];
+        tracker.memory_available_gb = Some(12.5);
+        tracker
+    }
+
+    #[test]
+    fn test_snapshot_schema() {
+        let fs = MockFs::with_files(&[(GOVERNOR_PATH, "performance\n")]);
+        let snapshot = tracker().snapshot(&fs, 1_700_000_000);
+
+        assert_eq!(serde_json::to_value(&snapshot).unwrap(), serde_json::json!({
+            "schema_version": 1,
+            "timestamp": 1_700_000_000u64,
+            "profile": "Gaming",
+            "temperatures": [
+                {"sensor": "thermal_zone0 (x86_pkg_temp)", "celsius": 71.5},
+                {"sensor": "thermal_zone1 (acpitz)", "celsius": 45.0},
+            ],
+            "governor": "performance",
+            "thermal_throttled": false,
+            "memory_available_gb": 12.5,
+            "memory_pressure": false,
+            "warnings": [],
+        }));
+
+        let without_cpufreq = tracker().snapshot(&MockFs::default(), 0);
+        assert_eq!(without_cpufreq.governor, None);
+    }
+
+    #[test]
+    fn test_keeps_latest_warnings() {
+        let mut tracker = tracker();
+        for i in 0..3 {
+            tracker.warn(format!("warning {}", i));
+        }
+        let snapshot = tracker.snapshot(&MockFs::default(), 0);
+        assert_eq!(snapshot.warnings, vec!["warning 1".to_string(), "warning 2".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_serves_published_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run/daemon.sock");
+        // A stale socket from an earlier run is replaced
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+
+        let published = Published::default();
+        let snapshot = tracker().snapshot(&MockFs::default(), 42);
+        publish(&published, &snapshot);
+        tokio::spawn(serve(bind(&path).unwrap(), published));
+
+        let mut stream = tokio::net::UnixStream::connect(&path).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.ends_with('\n'));
+        assert_eq!(serde_json::from_str::<StatusSnapshot>(&response).unwrap(), snapshot);
+    }
+
+    #[tokio::test]
+    async fn test_bind_leaves_other_files_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hardware.json");
+        std::fs::write(&path, "{}").unwrap();
+
+        let error = bind(&path).unwrap_err();
+        assert!(error.to_string().contains("not a socket"), "{error:#}");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "{}");
+    }
+}
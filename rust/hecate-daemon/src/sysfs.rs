@@ -0,0 +1,149 @@
+//! Filesystem access for tunables
+//!
+//! Optimizations read and write sysfs, procfs and `/etc` files through
+//! [`SystemFs`] so they can be exercised against an in-memory tree.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// The files optimizations touch
+pub trait SystemFs: Send + Sync {
+    fn read(&self, path: &str) -> io::Result<String>;
+    fn write(&self, path: &str, contents: &str) -> io::Result<()>;
+    fn exists(&self, path: &str) -> bool;
+    fn remove(&self, path: &str) -> io::Result<()>;
+    /// Names of the entries in directory `path`, sorted
+    fn list(&self, path: &str) -> io::Result<Vec<String>>;
+}
+
+/// The real filesystem
+pub struct RealFs;
+
+impl SystemFs for RealFs {
+    fn read(&self, path: &str) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+
+    fn write(&self, path: &str, contents: &str) -> io::Result<()> {
+        fs::write(path, contents)
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        Path::new(path).exists()
+    }
+
+    fn remove(&self, path: &str) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn list(&self, path: &str) -> io::Result<Vec<String>> {
+        let mut names: Vec<String> = fs::read_dir(path)?
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+}
+
+/// The active value of a tunable from its file contents
+///
+/// Selector files such as `scaling_governor` alternatives or
+/// `queue/scheduler` list every option with the active one in brackets,
+/// e.g. `none [mq-deadline] kyber`.
+pub fn current_value(contents: &str) -> String {
+    let contents = contents.trim();
+    match (contents.find('['), contents.find(']')) {
+        (Some(start), Some(end)) if start < end => contents[start + 1..end].to_string(),
+        _ => contents.to_string(),
+    }
+}
+
+#[cfg(test)]
+pub use mock::MockFs;
+
+#[cfg(test)]
+mod mock {
+    use super::SystemFs;
+    use std::collections::{BTreeMap, BTreeSet};
+    use std::io;
+    use std::sync::Mutex;
+
+    /// In-memory [`SystemFs`]; directories exist implicitly as prefixes of
+    /// file paths
+    #[derive(Default)]
+    pub struct MockFs {
+        files: Mutex<BTreeMap<String, String>>,
+        read_only: Mutex<BTreeSet<String>>,
+    }
+
+    impl MockFs {
+        pub fn with_files(files: &[(&str, &str)]) -> Self {
+            Self {
+                files: Mutex::new(files.iter().map(|(p, c)| (p.to_string(), c.to_string())).collect()),
+                read_only: Mutex::default(),
+            }
+        }
+
+        /// Make writes to `path` fail with permission denied
+        pub fn set_read_only(&self, path: &str) {
+            self.read_only.lock().unwrap().insert(path.to_string());
+        }
+
+        pub fn get(&self, path: &str) -> Option<String> {
+            self.files.lock().unwrap().get(path).cloned()
+        }
+    }
+
+    impl SystemFs for MockFs {
+        fn read(&self, path: &str) -> io::Result<String> {
+            self.get(path).ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, path.to_string()))
+        }
+
+        fn write(&self, path: &str, contents: &str) -> io::Result<()> {
+            if self.read_only.lock().unwrap().contains(path) {
+                return Err(io::Error::new(io::ErrorKind::PermissionDenied, path.to_string()));
+            }
+            self.files.lock().unwrap().insert(path.to_string(), contents.to_string());
+            Ok(())
+        }
+
+        fn exists(&self, path: &str) -> bool {
+            self.files.lock().unwrap().contains_key(path)
+        }
+
+        fn remove(&self, path: &str) -> io::Result<()> {
+            self.files.lock().unwrap().remove(path)
+                .map(|_| ())
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, path.to_string()))
+        }
+
+        fn list(&self, path: &str) -> io::Result<Vec<String>> {
+            let prefix = format!("{}/", path.trim_end_matches('/'));
+            let names: BTreeSet<String> = self.files.lock().unwrap()
+                .keys()
+                .filter_map(|file| file.strip_prefix(&prefix))
+                .filter_map(|rest| rest.split('/').next())
+                .map(str::to_string)
+                .collect();
+            if names.is_empty() {
+                return Err(io::Error::new(io::ErrorKind::NotFound, path.to_string()));
+            }
+            Ok(names.into_iter().collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_current_value() {
+        assert_eq!(current_value("performance\n"), "performance");
+        assert_eq!(current_value("none [mq-deadline] kyber bfq\n"), "mq-deadline");
+        assert_eq!(current_value("always [madvise] never\n"), "madvise");
+        assert_eq!(current_value("60"), "60");
+    }
+}
@@ -1,7 +1,14 @@
 use anyhow::{Context, Result};
-use std::process::Command;
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 use tracing::{info, warn, error};
+use walkdir::WalkDir;
 use colored::*;
 
 const COMPONENTS: &[&str] = &[
@@ -17,25 +24,245 @@ const COMPONENTS: &[&str] = &[
     "hecate-iso-builder",
 ];
 
-pub async fn build_all(release: bool, run_tests: bool) -> Result<()> {
+/// Per-mode source fingerprints of the last successful build of each
+/// component, kept under `target/` so `cargo clean` also resets it
+const STATE_FILE: &str = "target/.hecate-build-state.json";
+
+/// Files outside a component that affect every build
+const SHARED_INPUTS: &[&str] = &["Cargo.toml", "Cargo.lock"];
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct BuildState {
+    /// `<mode>/<component>` → fingerprint
+    fingerprints: HashMap<String, String>,
+}
+
+impl BuildState {
+    fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+    
+    fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+    
+    fn key(mode: &str, component: &str) -> String {
+        format!("{}/{}", mode, component)
+    }
+    
+    fn is_current(&self, mode: &str, component: &str, fingerprint: &str) -> bool {
+        self.fingerprints.get(&Self::key(mode, component)).map(String::as_str) == Some(fingerprint)
+    }
+}
+
+/// Path, size and mtime of every source file of `crate_dir`, plus its
+/// dependencies' own summaries; mtimes keep this cheap, and a touched but
+/// unchanged file only costs a rebuild
+fn crate_inputs(rust_dir: &Path, name: &str, seen: &mut Vec<String>) -> Result<String> {
+    let crate_dir = rust_dir.join(name);
+    let mut inputs = String::new();
+    
+    let mut files: Vec<PathBuf> = WalkDir::new(&crate_dir)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != "target")
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .collect();
+    files.sort();
+    
+    for file in files {
+        let metadata = fs::metadata(&file)?;
+        let mtime = metadata.modified()?
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        inputs.push_str(&format!("{}:{}:{}\n", file.strip_prefix(rust_dir)?.display(), metadata.len(), mtime));
+    }
+    
+    // Path dependencies on other components are inputs too
+    let manifest = fs::read_to_string(crate_dir.join("Cargo.toml"))
+        .with_context(|| format!("Failed to read {}/Cargo.toml", name))?;
+    let doc = manifest.parse::<toml_edit::DocumentMut>()?;
+    let mut deps: Vec<String> = doc.get("dependencies")
+        .and_then(|deps| deps.as_table_like())
+        .map(|table| table.iter()
+            .map(|(dep, _)| dep.to_string())
+            .filter(|dep| dep.starts_with("hecate-") && rust_dir.join(dep).join("Cargo.toml").exists())
+            .collect())
+        .unwrap_or_default();
+    deps.sort();
+    
+    for dep in deps {
+        if !seen.contains(&dep) {
+            seen.push(dep.clone());
+            inputs.push_str(&crate_inputs(rust_dir, &dep, seen)?);
+        }
+    }
+    
+    Ok(inputs)
+}
+
+/// Fingerprint of everything that goes into building `component`
+fn fingerprint(rust_dir: &Path, component: &str) -> Result<String> {
+    let mut inputs = crate_inputs(rust_dir, component, &mut vec![component.to_string()])?;
+    for shared in SHARED_INPUTS {
+        if let Ok(content) = fs::read(rust_dir.join(shared)) {
+            inputs.push_str(&format!("{}:{}\n", shared, String::from_utf8_lossy(&content)));
+        }
+    }
+    
+    // Only compared against state written by this tool on this machine,
+    // so std's hasher is stable enough; a mismatch just means a rebuild
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    inputs.hash(&mut hasher);
+    Ok(format!("{:016x}", hasher.finish()))
+}
+
+/// Package name from a cargo package id, in either the old
+/// `name version (source)` or the newer `source#[name@]version` form
+fn package_name(package_id: &str) -> &str {
+    if let Some((name, _)) = package_id.split_once(' ') {
+        return name;
+    }
+    let (source, fragment) = package_id.rsplit_once('#').unwrap_or((package_id, ""));
+    match fragment.split_once('@') {
+        Some((name, _)) => name,
+        None => source.rsplit('/').next().unwrap_or(source),
+    }
+}
+
+/// Outcome of one workspace build
+#[derive(Debug, Default)]
+struct BuildOutcome {
+    /// Seconds from the start of the build until each component finished
+    finished: HashMap<String, f64>,
+    failed: Vec<String>,
+}
+
+/// Build `components` with a single `cargo build -p ...` in the workspace,
+/// letting cargo schedule crates in parallel with at most `jobs` jobs
+fn build_components(rust_dir: &Path, components: &[&str], release: bool, jobs: Option<usize>) -> Result<BuildOutcome> {
+    let mut cmd = Command::new("cargo");
+    cmd.current_dir(rust_dir)
+        .args(["build", "--keep-going", "--message-format=json-render-diagnostics"])
+        // hecate-pkg checks its queries against this database
+        .env("DATABASE_URL", "sqlite:hecate-pkg.db")
+        .stdout(Stdio::piped());
+    for component in components {
+        cmd.args(["-p", component]);
+    }
+    if release {
+        cmd.arg("--release");
+    }
+    if let Some(jobs) = jobs {
+        cmd.arg(format!("--jobs={}", jobs));
+    }
+    
+    let start = Instant::now();
+    let mut child = cmd.spawn().context("Failed to run cargo build")?;
+    let stdout = child.stdout.take().context("cargo build has no stdout")?;
+    let mut outcome = BuildOutcome::default();
+    
+    for line in BufReader::new(stdout).lines() {
+        let line = line?;
+        let Ok(message) = serde_json::from_str::<serde_json::Value>(&line) else {
+            continue;
+        };
+        let package = message["package_id"].as_str().map(package_name).unwrap_or_default();
+        if !components.contains(&package) {
+            continue;
+        }
+        match message["reason"].as_str() {
+            Some("compiler-artifact") => {
+                outcome.finished.insert(package.to_string(), start.elapsed().as_secs_f64());
+            }
+            Some("compiler-message")
+                if message["message"]["level"] == "error" && !outcome.failed.iter().any(|failed| failed == package) =>
+            {
+                outcome.failed.push(package.to_string());
+            }
+            _ => {}
+        }
+    }
+    
+    let status = child.wait()?;
+    // A crate that never finished failed, possibly through a dependency
+    for component in components {
+        if !outcome.finished.contains_key(*component) && !outcome.failed.iter().any(|f| f == component) {
+            outcome.failed.push(component.to_string());
+        }
+    }
+    for failed in &outcome.failed {
+        outcome.finished.remove(failed);
+    }
+    if !status.success() && outcome.failed.is_empty() {
+        anyhow::bail!("cargo build failed");
+    }
+    
+    Ok(outcome)
+}
+
+pub async fn build_all(release: bool, run_tests: bool, jobs: Option<usize>, force: bool) -> Result<()> {
     println!("{}", "Building all HecateOS components...".bright_cyan().bold());
     println!("{}", "=".repeat(50).bright_cyan());
     
     let rust_dir = find_project_root()?;
     let mode = if release { "release" } else { "debug" };
-    
-    let mut failed = Vec::new();
-    let total = COMPONENTS.len();
-    
-    for (idx, component) in COMPONENTS.iter().enumerate() {
-        println!("\n[{}/{}] Building {}...", idx + 1, total, component.bright_yellow());
-        
-        if let Err(e) = build_single_component(&rust_dir, component, release) {
-            error!("Failed to build {}: {}", component, e);
-            failed.push(*component);
+    let state_path = rust_dir.join(STATE_FILE);
+    let mut state = if force { BuildState::default() } else { BuildState::load(&state_path) };
+    
+    let mut stale = Vec::new();
+    let mut fingerprints = HashMap::new();
+    for component in COMPONENTS {
+        if !rust_dir.join(component).is_dir() {
+            warn!("Component directory not found: {}", component);
+            continue;
+        }
+        let print = fingerprint(&rust_dir, component)?;
+        if state.is_current(mode, component, &print) {
+            println!("  {} {} unchanged, skipping", "↷".dimmed(), component);
         } else {
-            println!("  ✅ {} built successfully", component.green());
+            stale.push(*component);
         }
+        fingerprints.insert(*component, print);
+    }
+    
+    let outcome = if stale.is_empty() {
+        BuildOutcome::default()
+    } else {
+        println!("\nBuilding {} component(s): {}", stale.len(), stale.join(", ").bright_yellow());
+        build_components(&rust_dir, &stale, release, jobs)?
+    };
+    
+    for component in outcome.finished.keys() {
+        state.fingerprints.insert(BuildState::key(mode, component), fingerprints[component.as_str()].clone());
+    }
+    for failed in &outcome.failed {
+        error!("Failed to build {}", failed);
+        state.fingerprints.remove(&BuildState::key(mode, failed));
+    }
+    state.save(&state_path)?;
+    
+    // Per-component summary, in build order
+    println!("\n{}", "Build Times:".bold());
+    let mut finished: Vec<(&String, &f64)> = outcome.finished.iter().collect();
+    finished.sort_by(|a, b| a.1.total_cmp(b.1));
+    for (component, seconds) in finished {
+        println!("  {:<20} {} after {:.1}s", component, "✅".green(), seconds);
+    }
+    for failed in &outcome.failed {
+        println!("  {:<20} {}", failed, "❌ failed".red());
+    }
+    for component in COMPONENTS.iter().filter(|c| fingerprints.contains_key(*c) && !stale.contains(c)) {
+        println!("  {:<20} {}", component, "up to date".dimmed());
     }
     
     if run_tests {
@@ -43,9 +270,9 @@ pub async fn build_all(release: bool, run_tests: bool) -> Result<()> {
         run_all_tests(&rust_dir)?;
     }
     
-    if !failed.is_empty() {
+    if !outcome.failed.is_empty() {
         println!("\n{}", "Build Summary:".bright_red());
-        println!("  Failed components: {:?}", failed);
+        println!("  Failed components: {:?}", outcome.failed);
         return Err(anyhow::anyhow!("Some components failed to build"));
     }
     
@@ -318,4 +545,83 @@ pub fn find_project_root() -> Result<PathBuf> {
     Err(anyhow::anyhow!(
         "Could not find HecateOS project root. Set HECATE_ROOT environment variable to /path/to/hecate-os/rust"
     ))
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn workspace() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        write(&root.join("Cargo.lock"), "version = 3\n");
+        write(&root.join("hecate-core/Cargo.toml"), "[package]\nname = \"hecate-core\"\n");
+        write(&root.join("hecate-core/src/lib.rs"), "pub fn core() {}\n");
+        write(&root.join("hecate-cli/Cargo.toml"),
+            "[package]\nname = \"hecate-cli\"\n\n[dependencies]\nhecate-core = { path = \"../hecate-core\" }\nanyhow = \"1\"\n");
+        write(&root.join("hecate-cli/src/main.rs"), "fn main() {}\n");
+        dir
+    }
+
+    #[test]
+    fn test_fingerprint_change_detection() {
+        let dir = workspace();
+        let root = dir.path();
+        let core = fingerprint(root, "hecate-core").unwrap();
+        let cli = fingerprint(root, "hecate-cli").unwrap();
+        assert_eq!(fingerprint(root, "hecate-cli").unwrap(), cli);
+
+        // Build output doesn't count
+        write(&root.join("hecate-cli/target/debug/hecate"), "binary");
+        assert_eq!(fingerprint(root, "hecate-cli").unwrap(), cli);
+
+        // Own sources only affect the crate itself
+        write(&root.join("hecate-cli/src/main.rs"), "fn main() { println!(); }\n");
+        let cli_edited = fingerprint(root, "hecate-cli").unwrap();
+        assert_ne!(cli_edited, cli);
+        assert_eq!(fingerprint(root, "hecate-core").unwrap(), core);
+
+        // Dependencies' sources affect dependents
+        write(&root.join("hecate-core/src/new.rs"), "pub struct New;\n");
+        assert_ne!(fingerprint(root, "hecate-core").unwrap(), core);
+        let cli_with_core = fingerprint(root, "hecate-cli").unwrap();
+        assert_ne!(cli_with_core, cli_edited);
+
+        // So does the lock file
+        write(&root.join("Cargo.lock"), "version = 4\n");
+        assert_ne!(fingerprint(root, "hecate-cli").unwrap(), cli_with_core);
+    }
+
+    #[test]
+    fn test_build_state() {
+        let dir = workspace();
+        let path = dir.path().join(STATE_FILE);
+        assert!(BuildState::load(&path).fingerprints.is_empty());
+
+        let print = fingerprint(dir.path(), "hecate-cli").unwrap();
+        let mut state = BuildState::default();
+        state.fingerprints.insert(BuildState::key("release", "hecate-cli"), print.clone());
+        state.save(&path).unwrap();
+
+        let state = BuildState::load(&path);
+        assert!(state.is_current("release", "hecate-cli", &print));
+        assert!(!state.is_current("debug", "hecate-cli", &print));
+        assert!(!state.is_current("release", "hecate-core", &print));
+
+        // A corrupt state file means building everything
+        fs::write(&path, "{").unwrap();
+        assert!(BuildState::load(&path).fingerprints.is_empty());
+    }
+
+    #[test]
+    fn test_package_name() {
+        assert_eq!(package_name("hecate-cli 0.1.0 (path+file:///src/rust/hecate-cli)"), "hecate-cli");
+        assert_eq!(package_name("path+file:///src/rust/hecate-cli#0.1.0"), "hecate-cli");
+        assert_eq!(package_name("path+file:///src/rust/cli#hecate-cli@0.1.0"), "hecate-cli");
+        assert_eq!(package_name("registry+https://github.com/rust-lang/crates.io-index#anyhow@1.0.80"), "anyhow");
+    }
+}
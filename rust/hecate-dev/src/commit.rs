@@ -17,6 +17,71 @@ const VALID_TYPES: &[&str] = &[
     "revert",   // Reverts a previous commit
 ];
 
+/// A commit message following the conventional commits format
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ConventionalCommit {
+    pub commit_type: String,
+    pub scope: Option<String>,
+    pub description: String,
+    /// `type!:` or a `BREAKING CHANGE:` footer
+    pub breaking: bool,
+    /// Text of the `BREAKING CHANGE:` footer, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub breaking_note: Option<String>,
+    /// Issue and pull request numbers referenced as `#123`
+    #[serde(default)]
+    pub references: Vec<u32>,
+}
+
+/// Parse a full commit message; `None` if the subject isn't conventional
+pub fn parse_message(message: &str) -> Option<ConventionalCommit> {
+    let header = Regex::new(r"^([a-z]+)(?:\(([^)]+)\))?(!)?: (.+)$").unwrap();
+    let reference = Regex::new(r"#(\d+)\b").unwrap();
+    // Squash merges append the pull request as ` (#123)`
+    let trailing_reference = Regex::new(r"\s*\(#\d+\)$").unwrap();
+
+    let mut lines = message.lines();
+    let subject = lines.next()?.trim();
+    let caps = header.captures(subject)?;
+    if !VALID_TYPES.contains(&&caps[1]) {
+        return None;
+    }
+
+    let mut breaking_note: Option<String> = None;
+    let mut in_note = false;
+    for line in lines {
+        let line = line.trim();
+        if let Some(note) = line.strip_prefix("BREAKING CHANGE:").or_else(|| line.strip_prefix("BREAKING-CHANGE:")) {
+            breaking_note = Some(note.trim().to_string());
+            in_note = true;
+        } else if in_note && !line.is_empty() {
+            let note = breaking_note.as_mut().unwrap();
+            note.push(' ');
+            note.push_str(line);
+        } else {
+            in_note = false;
+        }
+    }
+
+    let mut references = Vec::new();
+    for caps in reference.captures_iter(message) {
+        if let Ok(number) = caps[1].parse::<u32>() {
+            if !references.contains(&number) {
+                references.push(number);
+            }
+        }
+    }
+
+    Some(ConventionalCommit {
+        commit_type: caps[1].to_string(),
+        scope: caps.get(2).map(|m| m.as_str().to_string()),
+        description: trailing_reference.replace(&caps[4], "").to_string(),
+        breaking: caps.get(3).is_some() || breaking_note.is_some(),
+        breaking_note: breaking_note.filter(|note| !note.is_empty()),
+        references,
+    })
+}
+
 pub fn validate_commit(message: Option<&str>) -> Result<()> {
     let message = match message {
         Some(m) => m.to_string(),
@@ -173,4 +238,33 @@ fn get_latest_commit_message() -> Result<String> {
     } else {
         anyhow::bail!("Failed to get latest commit message")
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_message() {
+        let commit = parse_message("feat(gpu): add fan curves (#42)\n\nFixes #40 and #42").unwrap();
+        assert_eq!(commit, ConventionalCommit {
+            commit_type: "feat".to_string(),
+            scope: Some("gpu".to_string()),
+            description: "add fan curves".to_string(),
+            breaking: false,
+            breaking_note: None,
+            references: vec![42, 40],
+        });
+
+        let commit = parse_message("refactor!: drop the v1 API").unwrap();
+        assert!(commit.breaking);
+        assert_eq!(commit.breaking_note, None);
+
+        let commit = parse_message("fix(pkg): new lock format\n\nBody.\n\nBREAKING CHANGE: old lock files\nare rejected\n\nRefs: #7").unwrap();
+        assert!(commit.breaking);
+        assert_eq!(commit.breaking_note.as_deref(), Some("old lock files are rejected"));
+        assert_eq!(commit.references, vec![7]);
+
+        assert_eq!(parse_message("Merge branch 'main'"), None);
+        assert_eq!(parse_message("feature: not a valid type"), None);
+    }
+}
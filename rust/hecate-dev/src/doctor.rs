@@ -0,0 +1,261 @@
+//! `hecate-dev doctor`: checks of the development environment
+//!
+//! Every finding is a [`Check`] with a severity, collected into a
+//! [`DoctorReport`] that is either printed for humans or emitted as JSON
+//! with `--json`, so CI can gate on it. Any error-level check fails the
+//! command.
+
+use crate::build;
+use crate::utils::*;
+use anyhow::Result;
+use colored::*;
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Ok,
+    Info,
+    Warning,
+    Error,
+}
+
+/// One finding
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    /// Stable identifier, e.g. `dependency.git` or `disk_space`
+    pub name: String,
+    pub severity: Severity,
+    pub message: String,
+    /// How to resolve it, when there is something to do
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn new(name: &str, severity: Severity, message: impl Into<String>) -> Self {
+        Self { name: name.to_string(), severity, message: message.into(), fix: None }
+    }
+
+    fn with_fix(mut self, fix: impl Into<String>) -> Self {
+        self.fix = Some(fix.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DoctorReport {
+    /// False if any check is an error
+    pub ok: bool,
+    pub checks: Vec<Check>,
+}
+
+impl DoctorReport {
+    pub fn new(checks: Vec<Check>) -> Self {
+        let ok = checks.iter().all(|check| check.severity < Severity::Error);
+        Self { ok, checks }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Check> {
+        self.checks.iter().find(|check| check.name == name)
+    }
+
+    /// Look at the environment
+    pub fn collect(deps: &HashMap<String, DependencyStatus>) -> Self {
+        let mut checks = Vec::new();
+
+        for dep in dependencies() {
+            let installed = deps.get(dep.name).is_some_and(|status| status.installed);
+            let name = format!("dependency.{}", dep.name);
+            checks.push(match (installed, dep.required) {
+                (true, _) => Check::new(&name, Severity::Ok, format!("{} installed", dep.name)),
+                (false, required) => Check::new(
+                    &name,
+                    if required { Severity::Error } else { Severity::Info },
+                    format!("{} not found ({})", dep.name, dep.description),
+                ).with_fix(format!("sudo apt-get install {}", dep.package)),
+            });
+        }
+
+        checks.push(match build::find_project_root() {
+            Ok(dir) => Check::new("project_root", Severity::Ok, format!("Project found at: {}", dir.display())),
+            Err(e) => Check::new("project_root", Severity::Error, format!("Project not found: {}", e))
+                .with_fix("Set HECATE_ROOT environment variable to the rust directory"),
+        });
+
+        // ISO creation and extraction are native; 7z is only a fallback for
+        // images the native reader cannot parse
+        checks.push(if has_iso_extraction_tool() {
+            Check::new("iso_extraction_fallback", Severity::Ok, "ISO extraction fallback: External tool available (7z)")
+        } else {
+            Check::new("iso_extraction_fallback", Severity::Info, "ISO extraction fallback: Optional tool not installed")
+                .with_fix("For unusual images: sudo apt-get install p7zip-full")
+        });
+
+        checks.push(if std::env::var("USER").unwrap_or_default() == "root" {
+            Check::new("running_as_root", Severity::Warning, "Running as root is not recommended")
+        } else {
+            Check::new("running_as_root", Severity::Ok, "Not running as root")
+        });
+
+        checks.push(disk_check(disk_used_percent()));
+
+        Self::new(checks)
+    }
+}
+
+/// Percentage used of the filesystem holding the current directory
+fn disk_used_percent() -> Option<u32> {
+    let output = std::process::Command::new("df").arg("-h").arg(".").output().ok()?;
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    output_str.lines().skip(1).find_map(|line| {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        parts.get(4)?.trim_end_matches('%').parse().ok()
+    })
+}
+
+fn disk_check(percent: Option<u32>) -> Check {
+    match percent {
+        Some(percent) if percent > 90 => Check::new("disk_space", Severity::Error, format!("Low disk space: {}% used", percent)),
+        Some(percent) if percent > 80 => Check::new("disk_space", Severity::Warning, format!("Disk space: {}% used", percent)),
+        Some(percent) => Check::new("disk_space", Severity::Ok, format!("Disk space: {}% used", percent)),
+        None => Check::new("disk_space", Severity::Warning, "Could not determine disk usage"),
+    }
+}
+
+/// Print a check the way the rest of the tool reports status
+fn print_check(check: &Check, fix: bool) {
+    match check.severity {
+        Severity::Ok => success_msg(&check.message),
+        Severity::Info => info_msg(&check.message),
+        Severity::Warning => warn_msg(&check.message),
+        Severity::Error => error_msg(&check.message),
+    }
+    if let (true, Some(hint)) = (fix, &check.fix) {
+        info_msg(&format!("   {}", hint));
+    }
+}
+
+pub async fn run_doctor(fix: bool, json: bool) -> Result<()> {
+    let deps = check_dependencies()?;
+    let report = DoctorReport::collect(&deps);
+    
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&report, &deps, fix).await;
+    }
+    
+    if !report.ok {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+async fn print_report(report: &DoctorReport, deps: &HashMap<String, DependencyStatus>, fix: bool) {
+    print_header("HecateOS Doctor - System Check");
+    
+    // Check dependencies
+    info_msg("Checking system dependencies...");
+    print_dependency_report(deps);
+    
+    // Check project structure
+    println!("\n{}", "Project Status".bright_cyan().bold());
+    println!("{}", "═".repeat(60).bright_cyan());
+    
+    let project = report.get("project_root");
+    if let Some(check) = project {
+        print_check(check, fix);
+    }
+    
+    // Check build status
+    if project.is_some_and(|check| check.severity == Severity::Ok) {
+        let status = build::show_status().await;
+        if status.is_err() {
+            warn_msg("Could not check build status");
+        }
+    }
+    
+    // Check for ISO tools
+    println!("\n{}", "ISO Creation Capabilities".bright_cyan().bold());
+    println!("{}", "═".repeat(60).bright_cyan());
+    
+    // Native ISO creation and extraction
+    success_msg("✅ Native ISO creation: Built-in Rust implementation");
+    success_msg("✅ Native ISO extraction: ISO 9660 with Joliet and Rock Ridge");
+    info_msg("   hecate-iso can create and extract ISOs without external tools!");
+    if let Some(check) = report.get("iso_extraction_fallback") {
+        print_check(check, fix);
+    }
+    
+    // Check for common issues
+    println!("\n{}", "Common Issues".bright_cyan().bold());
+    println!("{}", "═".repeat(60).bright_cyan());
+    
+    // Only worth mentioning when something is off
+    if let Some(check) = report.get("running_as_root").filter(|check| check.severity != Severity::Ok) {
+        print_check(check, fix);
+    }
+    if let Some(check) = report.get("disk_space") {
+        print_check(check, fix);
+    }
+    
+    // Final recommendations
+    println!("\n{}", "Recommendations".bright_cyan().bold());
+    println!("{}", "═".repeat(60).bright_cyan());
+    
+    println!("\n💡 {}", "ISO Creation".bright_green().bold());
+    println!("   HecateOS includes native ISO creation and extraction in Rust!");
+    println!("   No external dependencies required for building ISOs.");
+    println!("   Use: {}", "hecate-iso build".bright_cyan());
+    
+    if deps.get("git").is_none_or(|s| !s.installed) {
+        println!("2. Install git:");
+        println!("   {}", "sudo apt-get install git".bright_yellow());
+    }
+    
+    if deps.get("cargo").is_none_or(|s| !s.installed) {
+        println!("3. Install Rust:");
+        println!("   {}", "curl --proto '=https' --tlsv1.2 -sSf https://sh.rustup.rs | sh".bright_yellow());
+    }
+    
+    println!("\n{}", "Run 'hecate-dev doctor --fix' to see fix instructions".bright_black());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_status_and_json() {
+        let mut checks = vec![
+            Check::new("dependency.git", Severity::Ok, "git installed"),
+            Check::new("dependency.7z", Severity::Info, "7z not found").with_fix("sudo apt-get install p7zip-full"),
+            Check::new("running_as_root", Severity::Warning, "Running as root is not recommended"),
+        ];
+        // Warnings and infos do not fail the report
+        assert!(DoctorReport::new(checks.clone()).ok);
+
+        checks.push(disk_check(Some(95)));
+        let report = DoctorReport::new(checks);
+        assert!(!report.ok);
+
+        let json: serde_json::Value = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["ok"], false);
+        assert_eq!(json["checks"][1]["severity"], "info");
+        assert_eq!(json["checks"][1]["fix"], "sudo apt-get install p7zip-full");
+        assert!(json["checks"][0].get("fix").is_none());
+        assert_eq!(json["checks"][3]["name"], "disk_space");
+        assert_eq!(json["checks"][3]["severity"], "error");
+        assert_eq!(json["checks"][3]["message"], "Low disk space: 95% used");
+    }
+
+    #[test]
+    fn test_disk_thresholds() {
+        assert_eq!(disk_check(Some(50)).severity, Severity::Ok);
+        assert_eq!(disk_check(Some(85)).severity, Severity::Warning);
+        assert_eq!(disk_check(Some(91)).severity, Severity::Error);
+        assert_eq!(disk_check(None).severity, Severity::Warning);
+    }
+}
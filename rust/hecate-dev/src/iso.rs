@@ -15,7 +15,7 @@ pub async fn create_standalone_iso(output: &str) -> Result<()> {
     
     // Build all components first
     println!("\n🔨 Building all components...");
-    super::build::build_all(true, false).await?;
+    super::build::build_all(true, false, None, false).await?;
     
     // Create ISO content directory
     let iso_content = rust_dir.join("hecate-standalone");
@@ -101,29 +101,6 @@ pub async fn create_iso(
     
     print_header("Creating HecateOS ISO");
     
-    // If downloading and no 7z, show requirement
-    if download.is_some() && !has_iso_extraction_tool() {
-        eprintln!("\n╔════════════════════════════════════════════════════════════╗");
-        eprintln!("║         MANDATORY REQUIREMENT: 7z Installation            ║");
-        eprintln!("╚════════════════════════════════════════════════════════════╝");
-        eprintln!("");
-        eprintln!("HecateOS is a COMPLETE LINUX DISTRIBUTION based on Ubuntu LTS.");
-        eprintln!("To build HecateOS, we MUST extract and modify Ubuntu ISO.");
-        eprintln!("");
-        eprintln!("⚠️  7z is MANDATORY - No alternatives, no workarounds.");
-        eprintln!("");
-        eprintln!("Install it now:");
-        eprintln!("    {}", "sudo apt-get install p7zip-full".bright_yellow().bold());
-        eprintln!("");
-        eprintln!("Then run:");
-        eprintln!("    {}", "hecate-dev iso create --download 24.04".bright_green());
-        eprintln!("");
-        eprintln!("📝 Remember: HecateOS = Ubuntu + Optimizations");
-        eprintln!("   Without Ubuntu base, there is no HecateOS!");
-        eprintln!("");
-        return Err(anyhow::anyhow!("7z is MANDATORY. Install: sudo apt-get install p7zip-full"));
-    }
-    
     let rust_dir = find_project_root()?;
     let iso_builder = rust_dir.join("target/release/hecate-iso");
     
@@ -136,7 +113,7 @@ pub async fn create_iso(
     // Build all components first unless skipped
     if !skip_build {
         println!("\n🔨 Building all components...");
-        super::build::build_all(true, false).await?;
+        super::build::build_all(true, false, None, false).await?;
     }
     
     // Prepare ISO builder command
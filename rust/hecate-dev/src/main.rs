@@ -1,6 +1,5 @@
 use anyhow::Result;
 use clap::{Parser, Subcommand};
-use colored::*;
 use tracing::{info, warn};
 use tracing_subscriber::filter::EnvFilter;
 
@@ -12,6 +11,7 @@ mod build;
 mod iso;
 mod utils;
 mod setup;
+mod doctor;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -73,6 +73,10 @@ enum Commands {
         /// Fix common issues automatically
         #[arg(long)]
         fix: bool,
+        
+        /// Print the findings as JSON instead
+        #[arg(long)]
+        json: bool,
     },
     /// Setup development environment
     Setup {
@@ -88,7 +92,8 @@ enum VersionAction {
     Show,
     /// Bump version based on commit type
     Bump {
-        /// Version part to bump (major, minor, patch)
+        /// Version part to bump (major, minor, patch, prerelease), or auto
+        /// to decide from conventional commits since the last tag
         #[arg(value_enum)]
         level: version::BumpLevel,
         
@@ -145,6 +150,14 @@ enum BuildAction {
         /// Run tests after building
         #[arg(short, long)]
         test: bool,
+        
+        /// Maximum parallel build jobs (default: number of CPUs)
+        #[arg(short, long)]
+        jobs: Option<usize>,
+        
+        /// Rebuild components even if their sources are unchanged
+        #[arg(long)]
+        force: bool,
     },
     /// Build specific component
     Component {
@@ -231,6 +244,10 @@ enum ReleaseAction {
         /// Output format (markdown, json)
         #[arg(short, long, default_value = "markdown")]
         format: String,
+        
+        /// Include chore and ci commits
+        #[arg(long)]
+        all: bool,
     },
     /// Prepare release notes
     Notes {
@@ -276,8 +293,8 @@ async fn main() -> Result<()> {
         Commands::Iso { action } => {
             handle_iso_command(action).await?;
         }
-        Commands::Doctor { fix } => {
-            run_doctor(fix).await?;
+        Commands::Doctor { fix, json } => {
+            doctor::run_doctor(fix, json).await?;
         }
         Commands::Setup { auto } => {
             setup::setup_environment(auto).await?;
@@ -338,8 +355,8 @@ async fn handle_release_command(action: ReleaseAction) -> Result<()> {
                 skip_changelog
             ).await?;
         }
-        ReleaseAction::Changelog { range, format } => {
-            release::generate_changelog(range.as_deref(), &format)?;
+        ReleaseAction::Changelog { range, format, all } => {
+            release::generate_changelog(range.as_deref(), &format, all)?;
         }
         ReleaseAction::Notes { version } => {
             release::generate_release_notes(version.as_deref())?;
@@ -350,8 +367,8 @@ async fn handle_release_command(action: ReleaseAction) -> Result<()> {
 
 async fn handle_build_command(action: BuildAction) -> Result<()> {
     match action {
-        BuildAction::All { release, test } => {
-            build::build_all(release, test).await?;
+        BuildAction::All { release, test, jobs, force } => {
+            build::build_all(release, test, jobs, force).await?;
         }
         BuildAction::Component { name, release } => {
             build::build_component(&name, release).await?;
@@ -396,125 +413,6 @@ async fn handle_iso_command(action: IsoAction) -> Result<()> {
     Ok(())
 }
 
-async fn run_doctor(fix: bool) -> Result<()> {
-    use crate::utils::*;
-    
-    print_header("HecateOS Doctor - System Check");
-    
-    // Check dependencies
-    info_msg("Checking system dependencies...");
-    let deps = check_dependencies()?;
-    print_dependency_report(&deps);
-    
-    // Check project structure
-    println!("\n{}", "Project Status".bright_cyan().bold());
-    println!("{}", "═".repeat(60).bright_cyan());
-    
-    // Check if in correct directory
-    let rust_dir = build::find_project_root();
-    let has_project = rust_dir.is_ok();
-    
-    match rust_dir {
-        Ok(dir) => {
-            success_msg(&format!("Project found at: {}", dir.display()));
-        }
-        Err(e) => {
-            error_msg(&format!("Project not found: {}", e));
-            if fix {
-                info_msg("Set HECATE_ROOT environment variable to the rust directory");
-            }
-        }
-    }
-    
-    // Check build status
-    if has_project {
-        let status = build::show_status().await;
-        if status.is_err() {
-            warn_msg("Could not check build status");
-        }
-    }
-    
-    // Check for ISO tools
-    println!("\n{}", "ISO Creation Capabilities".bright_cyan().bold());
-    println!("{}", "═".repeat(60).bright_cyan());
-    
-    // Native ISO creation
-    success_msg("✅ Native ISO creation: Built-in Rust implementation");
-    info_msg("   hecate-iso can create ISOs without external tools!");
-    
-    // Check extraction tools (optional)
-    if has_iso_extraction_tool() {
-        success_msg("ISO extraction: External tool available (7z)");
-    } else {
-        info_msg("ISO extraction: Optional tool not installed");
-        if fix {
-            info_msg("   For faster extraction: sudo apt-get install p7zip-full");
-            info_msg("   Note: Native extraction coming soon!");
-        }
-    }
-    
-    // Check for common issues
-    println!("\n{}", "Common Issues".bright_cyan().bold());
-    println!("{}", "═".repeat(60).bright_cyan());
-    
-    // Check if running as root
-    if std::env::var("USER").unwrap_or_default() == "root" {
-        warn_msg("Running as root is not recommended");
-    }
-    
-    // Check available disk space
-    if let Ok(output) = std::process::Command::new("df")
-        .arg("-h")
-        .arg(".")
-        .output() 
-    {
-        let output_str = String::from_utf8_lossy(&output.stdout);
-        for line in output_str.lines().skip(1) {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 4 {
-                if let Ok(percent) = parts[4].trim_end_matches('%').parse::<u32>() {
-                    if percent > 90 {
-                        error_msg(&format!("Low disk space: {}% used", percent));
-                    } else if percent > 80 {
-                        warn_msg(&format!("Disk space: {}% used", percent));
-                    } else {
-                        success_msg(&format!("Disk space: {}% used", percent));
-                    }
-                    break;
-                }
-            }
-        }
-    }
-    
-    // Final recommendations
-    println!("\n{}", "Recommendations".bright_cyan().bold());
-    println!("{}", "═".repeat(60).bright_cyan());
-    
-    if !has_iso_extraction_tool() {
-        println!("Optional: For faster ISO extraction:");
-        println!("   {}", "sudo apt-get install p7zip-full".bright_yellow());
-    }
-    
-    println!("\n💡 {}", "ISO Creation".bright_green().bold());
-    println!("   HecateOS includes native ISO creation in Rust!");
-    println!("   No external dependencies required for creating ISOs.");
-    println!("   Use: {}", "hecate-iso build".bright_cyan());
-    
-    if deps.get("git").map_or(true, |s| !s.installed) {
-        println!("2. Install git:");
-        println!("   {}", "sudo apt-get install git".bright_yellow());
-    }
-    
-    if deps.get("cargo").map_or(true, |s| !s.installed) {
-        println!("3. Install Rust:");
-        println!("   {}", "curl --proto '=https' --tlsv1.2 -sSf https://sh.rustup.rs | sh".bright_yellow());
-    }
-    
-    println!("\n{}", "Run 'hecate-dev doctor --fix' to see fix instructions".bright_black());
-    
-    Ok(())
-}
-
 async fn init_git_hooks(force: bool) -> Result<()> {
     use std::fs;
     use std::os::unix::fs::PermissionsExt;
@@ -1,9 +1,8 @@
 use anyhow::{Context, Result};
+use crate::commit::ConventionalCommit;
 use chrono::Utc;
 use colored::*;
-use regex::Regex;
 use semver::Version;
-use std::collections::HashMap;
 use std::fs;
 use std::process::Command;
 
@@ -73,13 +72,14 @@ pub async fn create_release(
     Ok(())
 }
 
-pub fn generate_changelog(range: Option<&str>, format: &str) -> Result<()> {
+pub fn generate_changelog(range: Option<&str>, format: &str, include_all: bool) -> Result<()> {
     let range = range.unwrap_or("HEAD");
     let commits = get_commits_in_range(range)?;
+    let changelog = build_changelog(&commits, include_all);
     
     let changelog = match format {
-        "markdown" => format_changelog_markdown(&commits),
-        "json" => format_changelog_json(&commits)?,
+        "markdown" => format_changelog_markdown(&changelog, &repository_url()),
+        "json" => format_changelog_json(&changelog)?,
         _ => anyhow::bail!("Unsupported format: {}", format),
     };
     
@@ -102,40 +102,26 @@ pub fn generate_release_notes(version: Option<&str>) -> Result<()> {
 
 fn determine_next_version() -> Result<String> {
     let current = crate::version::read_version_file()?;
-    let mut version = Version::parse(&current)?;
+    let version = Version::parse(&current)?;
     
-    // Get commits since last tag
-    let last_tag = get_last_tag()?;
-    let commits = get_commits_in_range(&format!("{}..HEAD", last_tag))?;
-    
-    // Analyze commits to determine version bump
-    let mut has_breaking = false;
-    let mut has_features = false;
-    let mut has_fixes = false;
-    
-    for commit in &commits {
-        if commit.breaking {
-            has_breaking = true;
-        }
-        match commit.commit_type.as_str() {
-            "feat" => has_features = true,
-            "fix" => has_fixes = true,
-            _ => {}
-        }
-    }
-    
-    if has_breaking {
-        version.major += 1;
-        version.minor = 0;
-        version.patch = 0;
-    } else if has_features {
-        version.minor += 1;
-        version.patch = 0;
-    } else if has_fixes {
-        version.patch += 1;
-    }
-    
-    Ok(version.to_string())
+    // Analyze commits since the last tag to determine version bump
+    let level = crate::version::level_for_commits(&commits_since_last_tag()?)
+        .context("No commits since the last tag call for a release; pass a version to release anyway")?;
+    Ok(crate::version::bumped(&version, level)?.to_string())
+}
+
+/// `<last tag>..HEAD`, or all of `HEAD` when nothing is tagged yet
+fn range_since_last_tag() -> Result<String> {
+    Ok(match get_last_tag()? {
+        Some(tag) => format!("{}..HEAD", tag),
+        None => "HEAD".to_string(),
+    })
+}
+
+/// Conventional commits since the last tag
+pub fn commits_since_last_tag() -> Result<Vec<ConventionalCommit>> {
+    let commits = get_commits_in_range(&range_since_last_tag()?)?;
+    Ok(commits.into_iter().map(|c| c.conventional).collect())
 }
 
 fn run_tests() -> Result<()> {
@@ -163,175 +149,185 @@ fn create_git_tag(version: &str) -> Result<()> {
     Ok(())
 }
 
-fn get_last_tag() -> Result<String> {
+pub fn get_last_tag() -> Result<Option<String>> {
     let output = Command::new("git")
         .args(&["describe", "--tags", "--abbrev=0"])
         .output()
         .context("Failed to get last tag")?;
     
     if output.status.success() {
-        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        Ok(Some(String::from_utf8_lossy(&output.stdout).trim().to_string()))
     } else {
-        Ok("HEAD~10".to_string()) // Default to last 10 commits if no tags
+        Ok(None)
     }
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 struct Commit {
     hash: String,
-    commit_type: String,
-    scope: Option<String>,
-    description: String,
-    breaking: bool,
+    #[serde(flatten)]
+    conventional: ConventionalCommit,
     author: String,
     date: String,
 }
 
+impl Commit {
+    /// `None` for commits that don't follow the conventions
+    fn parse(hash: &str, author: &str, date: &str, message: &str) -> Option<Self> {
+        Some(Self {
+            hash: hash.chars().take(7).collect(),
+            conventional: crate::commit::parse_message(message)?,
+            author: author.to_string(),
+            date: date.to_string(),
+        })
+    }
+}
+
 fn get_commits_in_range(range: &str) -> Result<Vec<Commit>> {
+    // Full messages, so breaking-change footers and references are seen;
+    // fields are separated by US and records by RS
     let output = Command::new("git")
         .args(&[
             "log",
             range,
-            "--pretty=format:%H|%s|%an|%ad",
+            "--pretty=format:%H%x1f%an%x1f%ad%x1f%B%x1e",
             "--date=short",
         ])
         .output()
         .context("Failed to get git log")?;
     
-    let log = String::from_utf8_lossy(&output.stdout);
-    let mut commits = Vec::new();
-    
-    let commit_re = Regex::new(
-        r"^([a-z]+)(?:\(([^)]+)\))?: (.+)$"
-    )?;
-    
-    for line in log.lines() {
-        let parts: Vec<&str> = line.split('|').collect();
-        if parts.len() != 4 {
-            continue;
-        }
-        
-        let hash = parts[0].to_string();
-        let subject = parts[1];
-        let author = parts[2].to_string();
-        let date = parts[3].to_string();
-        
-        if let Some(caps) = commit_re.captures(subject) {
-            commits.push(Commit {
-                hash: hash[..7].to_string(),
-                commit_type: caps[1].to_string(),
-                scope: caps.get(2).map(|m| m.as_str().to_string()),
-                description: caps[3].to_string(),
-                breaking: subject.contains("BREAKING"),
-                author,
-                date,
-            });
-        }
+    if !output.status.success() {
+        anyhow::bail!("git log {} failed: {}", range, String::from_utf8_lossy(&output.stderr).trim());
     }
     
+    let log = String::from_utf8_lossy(&output.stdout);
+    let commits = log.split('\x1e')
+        .filter_map(|record| {
+            let fields: Vec<&str> = record.trim_start_matches('\n').splitn(4, '\x1f').collect();
+            match fields.as_slice() {
+                [hash, author, date, message] => Commit::parse(hash, author, date, message),
+                _ => None,
+            }
+        })
+        .collect();
+    
     Ok(commits)
 }
 
-fn format_changelog_markdown(commits: &[Commit]) -> String {
-    let mut grouped: HashMap<String, Vec<&Commit>> = HashMap::new();
+/// Changelog sections in display order: type, emoji, title, and whether
+/// the section is only shown when all types are requested
+const SECTIONS: &[(&str, &str, &str, bool)] = &[
+    ("feat", "✨", "Features", false),
+    ("fix", "🐛", "Bug Fixes", false),
+    ("perf", "⚡", "Performance", false),
+    ("revert", "⏪", "Reverts", false),
+    ("refactor", "♻️", "Code Refactoring", false),
+    ("docs", "📝", "Documentation", false),
+    ("build", "📦", "Build System", false),
+    ("test", "✅", "Tests", false),
+    ("style", "💄", "Styles", false),
+    ("chore", "🔧", "Chores", true),
+    ("ci", "👷", "Continuous Integration", true),
+];
+
+#[derive(Debug, serde::Serialize)]
+struct ChangelogSection {
+    commit_type: &'static str,
+    title: &'static str,
+    commits: Vec<Commit>,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct Changelog {
+    /// Breaking commits of any type, including hidden ones
+    breaking: Vec<Commit>,
+    sections: Vec<ChangelogSection>,
+}
+
+/// Group `commits` by type; chore and ci are skipped unless `include_all`
+fn build_changelog(commits: &[Commit], include_all: bool) -> Changelog {
+    let sections = SECTIONS.iter()
+        .filter(|(_, _, _, hidden)| include_all || !hidden)
+        .map(|&(commit_type, _, title, _)| ChangelogSection {
+            commit_type,
+            title,
+            commits: commits.iter().filter(|c| c.conventional.commit_type == commit_type).cloned().collect(),
+        })
+        .filter(|section| !section.commits.is_empty())
+        .collect();
     
-    for commit in commits {
-        grouped
-            .entry(commit.commit_type.clone())
-            .or_default()
-            .push(commit);
+    Changelog {
+        breaking: commits.iter().filter(|c| c.conventional.breaking).cloned().collect(),
+        sections,
     }
-    
+}
+
+/// `https://github.com/<owner>/<repo>` of the origin remote
+fn repository_url() -> String {
+    let origin = Command::new("git")
+        .args(["remote", "get-url", "origin"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string());
+    
+    match origin {
+        Some(url) => {
+            let url = url.trim_end_matches(".git");
+            match url.strip_prefix("git@") {
+                Some(ssh) => format!("https://{}", ssh.replacen(':', "/", 1)),
+                None => url.to_string(),
+            }
+        }
+        None => "https://github.com/Arakiss/hecate-os".to_string(),
+    }
+}
+
+fn format_entry(commit: &Commit, repo_url: &str) -> String {
+    let references: String = commit.conventional.references.iter()
+        .map(|number| format!(" ([#{}]({}/issues/{}))", number, repo_url, number))
+        .collect();
+    format!(
+        "* {}{}{} ({})\n",
+        commit.conventional.scope.as_ref().map(|s| format!("**{}:** ", s)).unwrap_or_default(),
+        commit.conventional.description,
+        references,
+        commit.hash
+    )
+}
+
+fn format_changelog_markdown(changelog: &Changelog, repo_url: &str) -> String {
     let mut output = String::new();
     
     // Breaking changes
-    let breaking: Vec<&Commit> = commits.iter().filter(|c| c.breaking).collect();
-    if !breaking.is_empty() {
+    if !changelog.breaking.is_empty() {
         output.push_str("### ⚠️ BREAKING CHANGES\n\n");
-        for commit in breaking {
-            output.push_str(&format!(
-                "* {}{} ({})\n",
-                commit.scope.as_ref().map(|s| format!("**{}:** ", s)).unwrap_or_default(),
-                commit.description,
-                commit.hash
-            ));
-        }
-        output.push('\n');
-    }
-    
-    // Features
-    if let Some(features) = grouped.get("feat") {
-        output.push_str("### ✨ Features\n\n");
-        for commit in features {
-            output.push_str(&format!(
-                "* {}{} ({})\n",
-                commit.scope.as_ref().map(|s| format!("**{}:** ", s)).unwrap_or_default(),
-                commit.description,
-                commit.hash
-            ));
-        }
-        output.push('\n');
-    }
-    
-    // Bug fixes
-    if let Some(fixes) = grouped.get("fix") {
-        output.push_str("### 🐛 Bug Fixes\n\n");
-        for commit in fixes {
-            output.push_str(&format!(
-                "* {}{} ({})\n",
-                commit.scope.as_ref().map(|s| format!("**{}:** ", s)).unwrap_or_default(),
-                commit.description,
-                commit.hash
-            ));
+        for commit in &changelog.breaking {
+            output.push_str(&format_entry(commit, repo_url));
+            if let Some(note) = &commit.conventional.breaking_note {
+                output.push_str(&format!("  {}\n", note));
+            }
         }
         output.push('\n');
     }
     
-    // Performance
-    if let Some(perfs) = grouped.get("perf") {
-        output.push_str("### ⚡ Performance\n\n");
-        for commit in perfs {
-            output.push_str(&format!(
-                "* {}{} ({})\n",
-                commit.scope.as_ref().map(|s| format!("**{}:** ", s)).unwrap_or_default(),
-                commit.description,
-                commit.hash
-            ));
+    for section in &changelog.sections {
+        let emoji = SECTIONS.iter()
+            .find(|(commit_type, ..)| *commit_type == section.commit_type)
+            .map(|(_, emoji, ..)| *emoji)
+            .unwrap_or_default();
+        output.push_str(&format!("### {} {}\n\n", emoji, section.title));
+        for commit in &section.commits {
+            output.push_str(&format_entry(commit, repo_url));
         }
         output.push('\n');
     }
     
-    // Other changes
-    let other_types = vec!["docs", "style", "refactor", "test", "build", "ci", "chore"];
-    let mut has_other = false;
-    for commit_type in &other_types {
-        if grouped.contains_key(*commit_type) {
-            has_other = true;
-            break;
-        }
-    }
-    
-    if has_other {
-        output.push_str("### 📝 Other Changes\n\n");
-        for commit_type in &other_types {
-            if let Some(commits) = grouped.get(*commit_type) {
-                for commit in commits {
-                    output.push_str(&format!(
-                        "* {}{} ({})\n",
-                        commit.scope.as_ref().map(|s| format!("**{}:** ", s)).unwrap_or_default(),
-                        commit.description,
-                        commit.hash
-                    ));
-                }
-            }
-        }
-    }
-    
     output
 }
 
-fn format_changelog_json(commits: &[Commit]) -> Result<String> {
-    let json = serde_json::to_string_pretty(commits)?;
+fn format_changelog_json(changelog: &Changelog) -> Result<String> {
+    let json = serde_json::to_string_pretty(changelog)?;
     Ok(json)
 }
 
@@ -339,13 +335,12 @@ fn generate_changelog_file(version: &str) -> Result<()> {
     let changelog_path = "CHANGELOG.md";
     let existing = fs::read_to_string(changelog_path).unwrap_or_default();
     
-    let last_tag = get_last_tag()?;
-    let commits = get_commits_in_range(&format!("{}..HEAD", last_tag))?;
+    let commits = get_commits_in_range(&range_since_last_tag()?)?;
     let new_section = format!(
         "## [{}] - {}\n\n{}\n",
         version,
         Utc::now().format("%Y-%m-%d"),
-        format_changelog_markdown(&commits)
+        format_changelog_markdown(&build_changelog(&commits, false), &repository_url())
     );
     
     // Insert new section after the title
@@ -366,8 +361,7 @@ fn generate_changelog_file(version: &str) -> Result<()> {
 }
 
 fn generate_release_notes_content(version: &str) -> Result<String> {
-    let last_tag = get_last_tag()?;
-    let commits = get_commits_in_range(&format!("{}..HEAD", last_tag))?;
+    let commits = get_commits_in_range(&range_since_last_tag()?)?;
     
     let mut notes = format!("# Release v{}\n\n", version);
     notes.push_str(&format!("Released: {}\n\n", Utc::now().format("%Y-%m-%d")));
@@ -376,9 +370,9 @@ fn generate_release_notes_content(version: &str) -> Result<String> {
     notes.push_str("## Summary\n\n");
     notes.push_str("This release includes ");
     
-    let features = commits.iter().filter(|c| c.commit_type == "feat").count();
-    let fixes = commits.iter().filter(|c| c.commit_type == "fix").count();
-    let breaking = commits.iter().filter(|c| c.breaking).count();
+    let features = commits.iter().filter(|c| c.conventional.commit_type == "feat").count();
+    let fixes = commits.iter().filter(|c| c.conventional.commit_type == "fix").count();
+    let breaking = commits.iter().filter(|c| c.conventional.breaking).count();
     
     let mut summary_parts = Vec::new();
     if features > 0 {
@@ -396,7 +390,7 @@ fn generate_release_notes_content(version: &str) -> Result<String> {
     
     // Changelog
     notes.push_str("## Changelog\n\n");
-    notes.push_str(&format_changelog_markdown(&commits));
+    notes.push_str(&format_changelog_markdown(&build_changelog(&commits, false), &repository_url()));
     
     // Installation
     notes.push_str("## Installation\n\n");
@@ -424,3 +418,73 @@ fn generate_release_notes_content(version: &str) -> Result<String> {
     Ok(notes)
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPO: &str = "https://github.com/Arakiss/hecate-os";
+
+    fn fixture() -> Vec<Commit> {
+        [
+            ("a1b2c3d4", "feat(gpu): add fan curves (#42)"),
+            ("b2c3d4e5", "fix: handle missing nvidia-smi\n\nCloses #17"),
+            ("c3d4e5f6", "chore(deps): bump tokio"),
+            ("d4e5f6a7", "perf(monitor): cache sensor paths"),
+            ("e5f6a7b8", "feat(pkg)!: new repository index format\n\nBREAKING CHANGE: run hecate-pkg migrate first"),
+            ("f6a7b8c9", "ci: cache cargo registry"),
+            ("a7b8c9d0", "Merge pull request #50 from fork/branch"),
+            ("b8c9d0e1", "docs: document profiles"),
+        ]
+        .iter()
+        .filter_map(|(hash, message)| Commit::parse(hash, "Dev", "2026-01-01", message))
+        .collect()
+    }
+
+    #[test]
+    fn test_grouped_markdown() {
+        let commits = fixture();
+        assert_eq!(commits.len(), 7);
+
+        assert_eq!(format_changelog_markdown(&build_changelog(&commits, false), REPO), "\
+### ⚠️ BREAKING CHANGES
+
+* **pkg:** new repository index format (e5f6a7b)
+  run hecate-pkg migrate first
+
+### ✨ Features
+
+* **gpu:** add fan curves ([#42](https://github.com/Arakiss/hecate-os/issues/42)) (a1b2c3d)
+* **pkg:** new repository index format (e5f6a7b)
+
+### 🐛 Bug Fixes
+
+* handle missing nvidia-smi ([#17](https://github.com/Arakiss/hecate-os/issues/17)) (b2c3d4e)
+
+### ⚡ Performance
+
+* **monitor:** cache sensor paths (d4e5f6a)
+
+### 📝 Documentation
+
+* document profiles (b8c9d0e)
+
+");
+    }
+
+    #[test]
+    fn test_hidden_types_and_json() {
+        let commits = fixture();
+        let types = |changelog: &Changelog| changelog.sections.iter().map(|s| s.commit_type).collect::<Vec<_>>();
+
+        assert_eq!(types(&build_changelog(&commits, false)), vec!["feat", "fix", "perf", "docs"]);
+        let all = build_changelog(&commits, true);
+        assert_eq!(types(&all), vec!["feat", "fix", "perf", "docs", "chore", "ci"]);
+
+        let json: serde_json::Value = serde_json::from_str(&format_changelog_json(&all).unwrap()).unwrap();
+        assert_eq!(json["breaking"][0]["breaking_note"], "run hecate-pkg migrate first");
+        assert_eq!(json["sections"][0]["title"], "Features");
+        assert_eq!(json["sections"][0]["commits"][0]["references"][0], 42);
+        assert_eq!(json["sections"][4]["commits"][0]["scope"], "deps");
+    }
+}
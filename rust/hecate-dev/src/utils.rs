@@ -19,9 +19,9 @@ pub struct DependencyStatus {
     pub path: Option<String>,
 }
 
-/// Check system dependencies
-pub fn check_dependencies() -> Result<HashMap<String, DependencyStatus>> {
-    let dependencies = vec![
+/// Tools the development workflow uses
+pub fn dependencies() -> Vec<Dependency> {
+    vec![
         Dependency {
             name: "cargo",
             command: "cargo",
@@ -41,13 +41,16 @@ pub fn check_dependencies() -> Result<HashMap<String, DependencyStatus>> {
             command: "7z",
             package: "p7zip-full",
             required: false,
-            description: "Faster ISO extraction (optional)",
+            description: "Fallback ISO extraction (optional)",
         },
-    ];
+    ]
+}
 
+/// Check system dependencies
+pub fn check_dependencies() -> Result<HashMap<String, DependencyStatus>> {
     let mut results = HashMap::new();
     
-    for dep in dependencies {
+    for dep in dependencies() {
         let status = check_command(dep.command);
         results.insert(dep.name.to_string(), status);
     }
@@ -123,7 +126,7 @@ pub fn print_dependency_report(results: &HashMap<String, DependencyStatus>) {
     let dependencies = vec![
         ("cargo", "Rust build tool", "cargo", true),
         ("git", "Version control", "git", true),
-        ("7z", "ISO extraction (faster alternative)", "p7zip-full", false),
+        ("7z", "ISO extraction fallback", "p7zip-full", false),
     ];
     
     let mut missing_required = Vec::new();
@@ -180,8 +183,8 @@ pub fn print_dependency_report(results: &HashMap<String, DependencyStatus>) {
     }
     
     if !missing_optional.is_empty() {
-        println!("\n{} Optional tools for enhanced performance:", "INFO:".blue());
-        println!("  7z provides faster ISO extraction than our native implementation");
+        println!("\n{} Optional tools:", "INFO:".blue());
+        println!("  7z is only used for ISOs the native extractor cannot read");
         println!("  Install if needed: {}", 
             format!("sudo apt-get install {}", 
                 missing_optional.iter()
@@ -190,17 +193,6 @@ pub fn print_dependency_report(results: &HashMap<String, DependencyStatus>) {
                     .join(" "))
             .bright_black());
     }
-    
-    // Check for at least one ISO extraction tool
-    let has_extraction = results.get("7z").map_or(false, |s| s.installed) ||
-                        results.get("bsdtar").map_or(false, |s| s.installed) ||
-                        results.get("xorriso").map_or(false, |s| s.installed);
-    
-    if !has_extraction {
-        println!("\n{} No ISO extraction tool found!", "WARNING:".yellow().bold());
-        println!("You need at least one of these to create ISOs:");
-        println!("  {} (recommended)", "sudo apt-get install p7zip-full".bright_yellow());
-    }
 }
 
 /// Check if we have at least one ISO extraction tool
@@ -6,31 +6,53 @@ use std::fs;
 use std::path::Path;
 use toml_edit::{Document, Item};
 
-#[derive(Debug, Clone, Copy, ValueEnum)]
+use crate::commit::ConventionalCommit;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
 pub enum BumpLevel {
     Major,
     Minor,
     Patch,
     Prerelease,
+    /// Decide from the conventional commits since the last tag
+    Auto,
 }
 
-pub fn show_version() -> Result<()> {
-    let version = read_version_file()?;
-    let cargo_version = read_cargo_version()?;
-    
-    println!("{}: {}", "VERSION file".bold(), version.green());
-    println!("{}: {}", "Cargo.toml".bold(), cargo_version.green());
-    
-    if version != cargo_version {
-        println!("{}: Versions are out of sync!", "Warning".yellow().bold());
+impl std::fmt::Display for BumpLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            BumpLevel::Major => "major",
+            BumpLevel::Minor => "minor",
+            BumpLevel::Patch => "patch",
+            BumpLevel::Prerelease => "prerelease",
+            BumpLevel::Auto => "auto",
+        };
+        f.write_str(name)
     }
-    
-    Ok(())
 }
 
-pub fn bump_version(level: BumpLevel, dry_run: bool) -> Result<()> {
-    let current = read_version_file()?;
-    let mut version = Version::parse(&current)?;
+/// Commit types that change the shipped code and so call for a patch
+/// release; docs, tests, styling, build and chores alone do not
+const PATCH_TYPES: &[&str] = &["fix", "perf", "refactor", "revert"];
+
+/// Bump implied by `commits`: any breaking change is major, any feature
+/// minor, any fix or other code change patch, and `None` when nothing
+/// calls for a release
+pub fn level_for_commits(commits: &[ConventionalCommit]) -> Option<BumpLevel> {
+    if commits.iter().any(|c| c.breaking) {
+        Some(BumpLevel::Major)
+    } else if commits.iter().any(|c| c.commit_type == "feat") {
+        Some(BumpLevel::Minor)
+    } else if commits.iter().any(|c| PATCH_TYPES.contains(&c.commit_type.as_str())) {
+        Some(BumpLevel::Patch)
+    } else {
+        None
+    }
+}
+
+/// `version` bumped by `level`, which must not be `Auto`
+pub fn bumped(version: &Version, level: BumpLevel) -> Result<Version> {
+    let mut version = version.clone();
     
     match level {
         BumpLevel::Major => {
@@ -62,9 +84,57 @@ pub fn bump_version(level: BumpLevel, dry_run: bool) -> Result<()> {
                 }
             }
         }
+        BumpLevel::Auto => anyhow::bail!("The auto bump level must be resolved from commits first"),
     }
     
-    let new_version = version.to_string();
+    Ok(version)
+}
+
+pub fn show_version() -> Result<()> {
+    let version = read_version_file()?;
+    let cargo_version = read_cargo_version()?;
+    
+    println!("{}: {}", "VERSION file".bold(), version.green());
+    println!("{}: {}", "Cargo.toml".bold(), cargo_version.green());
+    
+    if version != cargo_version {
+        println!("{}: Versions are out of sync!", "Warning".yellow().bold());
+    }
+    
+    Ok(())
+}
+
+pub fn bump_version(level: BumpLevel, dry_run: bool) -> Result<()> {
+    let current = read_version_file()?;
+    let version = Version::parse(&current)?;
+    
+    let level = match level {
+        BumpLevel::Auto => {
+            let commits = crate::release::commits_since_last_tag()?;
+            let since = crate::release::get_last_tag()?
+                .map(|tag| format!("since {}", tag))
+                .unwrap_or_else(|| "no previous tag".to_string());
+            let Some(level) = level_for_commits(&commits) else {
+                println!("{}: {} conventional commit(s) {}, none needing a release; version stays {}",
+                    "Nothing to bump".yellow(),
+                    commits.len(),
+                    since,
+                    current
+                );
+                return Ok(());
+            };
+            println!("{}: {} ({} conventional commit(s), {})",
+                "Detected bump level".bold(),
+                level.to_string().green(),
+                commits.len(),
+                since
+            );
+            level
+        }
+        level => level,
+    };
+    
+    let new_version = bumped(&version, level)?.to_string();
     
     if dry_run {
         println!("{}: {} → {}", 
@@ -265,4 +335,39 @@ fn update_package_json_version(path: &str, version: &str) -> Result<()> {
     let updated = serde_json::to_string_pretty(&package)?;
     fs::write(path, updated)?;
     Ok(())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commit::parse_message;
+
+    fn level(messages: &[&str]) -> Option<BumpLevel> {
+        let commits: Vec<ConventionalCommit> = messages.iter().filter_map(|m| parse_message(m)).collect();
+        level_for_commits(&commits)
+    }
+
+    #[test]
+    fn test_level_for_commits() {
+        assert_eq!(level(&["fix: a", "docs: b", "chore: c"]), Some(BumpLevel::Patch));
+        assert_eq!(level(&["perf(gpu): a", "test: b"]), Some(BumpLevel::Patch));
+        assert_eq!(level(&["fix: a", "feat(gpu): b", "docs: c"]), Some(BumpLevel::Minor));
+        assert_eq!(level(&["feat: a", "fix: b\n\nBREAKING CHANGE: c"]), Some(BumpLevel::Major));
+        assert_eq!(level(&["refactor(pkg)!: drop v1 index"]), Some(BumpLevel::Major));
+        // Non-conventional commits don't count
+        assert_eq!(level(&["Merge branch 'feat: x'", "fix: a"]), Some(BumpLevel::Patch));
+
+        // Nothing, or only docs and housekeeping, is no release at all
+        assert_eq!(level(&[]), None);
+        assert_eq!(level(&["docs: a", "chore(deps): b", "ci: c", "style: d", "test: e"]), None);
+    }
+
+    #[test]
+    fn test_bumped() {
+        let version = Version::parse("1.4.2-beta.1").unwrap();
+        assert_eq!(bumped(&version, BumpLevel::Major).unwrap().to_string(), "2.0.0");
+        assert_eq!(bumped(&version, BumpLevel::Minor).unwrap().to_string(), "1.5.0");
+        assert_eq!(bumped(&version, BumpLevel::Patch).unwrap().to_string(), "1.4.3");
+        assert_eq!(bumped(&version, BumpLevel::Prerelease).unwrap().to_string(), "1.4.2-beta.2");
+        assert!(bumped(&version, BumpLevel::Auto).is_err());
+    }
+}
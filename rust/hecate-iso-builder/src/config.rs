@@ -1,6 +1,6 @@
 //! Configuration module for HecateOS ISO builder
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
@@ -12,6 +12,8 @@ pub struct HecateConfig {
     pub optimizations: Optimizations,
     pub branding: Branding,
     pub scripts: Scripts,
+    #[serde(default)]
+    pub compression: Compression,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,6 +30,9 @@ pub struct Components {
     pub include_source: bool,
     pub include_docs: bool,
     pub additional_packages: Vec<String>,
+    /// Strip debug symbols from injected binaries to shrink the ISO
+    #[serde(default)]
+    pub strip_debug: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -48,6 +53,84 @@ pub struct Branding {
     pub motd: String,
 }
 
+/// How the HecateOS payload is compressed on the ISO
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Compression {
+    pub algorithm: CompressionAlgorithm,
+    /// Defaults to the algorithm's highest practical level
+    pub level: Option<i32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressionAlgorithm {
+    Zstd,
+    Xz,
+    Gzip,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self { algorithm: CompressionAlgorithm::Zstd, level: None }
+    }
+}
+
+impl CompressionAlgorithm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Zstd => "zstd",
+            Self::Xz => "xz",
+            Self::Gzip => "gzip",
+        }
+    }
+
+    /// Archive file name extension
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Zstd => "zst",
+            Self::Xz => "xz",
+            Self::Gzip => "gz",
+        }
+    }
+
+    pub fn levels(&self) -> std::ops::RangeInclusive<i32> {
+        match self {
+            Self::Zstd => 1..=22,
+            Self::Xz | Self::Gzip => 0..=9,
+        }
+    }
+
+    fn default_level(&self) -> i32 {
+        match self {
+            Self::Zstd => 19,
+            Self::Xz => 6,
+            Self::Gzip => 9,
+        }
+    }
+}
+
+impl Compression {
+    /// The configured level, or the algorithm's default
+    pub fn level(&self) -> i32 {
+        self.level.unwrap_or_else(|| self.algorithm.default_level())
+    }
+
+    /// Reject levels the algorithm does not support
+    pub fn validate(&self) -> Result<()> {
+        let levels = self.algorithm.levels();
+        if !levels.contains(&self.level()) {
+            bail!(
+                "{} does not support compression level {} (supported: {}-{})",
+                self.algorithm.name(),
+                self.level(),
+                levels.start(),
+                levels.end()
+            );
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Scripts {
     pub pre_install: Option<String>,
@@ -82,6 +165,7 @@ impl Default for HecateConfig {
                     "irqbalance".to_string(),
                     "nvme-cli".to_string(),
                 ],
+                strip_debug: false,
             },
             optimizations: Optimizations {
                 kernel_params: vec![
@@ -125,6 +209,7 @@ impl Default for HecateConfig {
                 post_install: Some(include_str!("scripts/post_install.sh").to_string()),
                 first_boot: Some(include_str!("scripts/first_boot.sh").to_string()),
             },
+            compression: Compression::default(),
         }
     }
 }
@@ -132,7 +217,8 @@ impl Default for HecateConfig {
 impl HecateConfig {
     pub fn from_file(path: &Path) -> Result<Self> {
         let content = fs::read_to_string(path)?;
-        let config = toml::from_str(&content)?;
+        let config: Self = toml::from_str(&content)?;
+        config.compression.validate()?;
         Ok(config)
     }
     
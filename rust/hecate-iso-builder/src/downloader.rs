@@ -1,24 +1,60 @@
 //! ISO download module
+//!
+//! Base ISOs are checked against the release's published `SHA256SUMS`. The
+//! download goes to `<iso>.part` and is resumed with an HTTP range request
+//! if interrupted; only a file that matches its digest is renamed into
+//! place, and a cached ISO that no longer matches is downloaded again.
+//! The digest a download was verified against is kept in `<iso>.sha256`,
+//! so a cached ISO can still be checked when `SHA256SUMS` is unreachable.
 
 use anyhow::{Context, Result};
 use indicatif::{ProgressBar, ProgressStyle};
-use std::fs::File;
-use std::io::Write;
-use std::path::Path;
+use sha2::{Digest, Sha256};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// An Ubuntu release image and where its checksums are published
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UbuntuRelease {
+    pub base_url: &'static str,
+    pub file_name: &'static str,
+}
+
+impl UbuntuRelease {
+    pub fn for_version(version: &str) -> Result<Self> {
+        let (base_url, file_name) = match version {
+            "24.04" | "latest" => ("https://releases.ubuntu.com/24.04.2", "ubuntu-24.04.2-desktop-amd64.iso"),
+            "22.04" => ("https://releases.ubuntu.com/22.04.5", "ubuntu-22.04.5-desktop-amd64.iso"),
+            "server" => ("https://releases.ubuntu.com/24.04.2", "ubuntu-24.04.2-live-server-amd64.iso"),
+            _ => return Err(anyhow::anyhow!("Unsupported Ubuntu version: {}. Use '24.04', '22.04', or 'server'", version)),
+        };
+        Ok(Self { base_url, file_name })
+    }
+
+    pub fn iso_url(&self) -> String {
+        format!("{}/{}", self.base_url, self.file_name)
+    }
+
+    pub fn sums_url(&self) -> String {
+        format!("{}/SHA256SUMS", self.base_url)
+    }
+}
+
+/// State of a previously downloaded ISO
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cached {
+    Missing,
+    Verified,
+    /// Did not match and was deleted
+    Corrupt,
+}
 
 pub struct IsoDownloader;
 
 impl IsoDownloader {
     pub async fn download_ubuntu(version: &str, output_path: &Path) -> Result<()> {
-        let url = match version {
-            "24.04" | "latest" => "https://releases.ubuntu.com/24.04.2/ubuntu-24.04.2-desktop-amd64.iso",
-            "22.04" => "https://releases.ubuntu.com/22.04.5/ubuntu-22.04.5-desktop-amd64.iso",
-            "server" => "https://releases.ubuntu.com/24.04.2/ubuntu-24.04.2-live-server-amd64.iso",
-            _ => return Err(anyhow::anyhow!("Unsupported Ubuntu version: {}. Use '24.04', '22.04', or 'server'", version)),
-        };
-        
-        println!("📥 Downloading Ubuntu {} ISO...", version);
-        println!("   From: {}", url);
+        let release = UbuntuRelease::for_version(version)?;
         
         // Create HTTP client with redirect support
         let client = reqwest::Client::builder()
@@ -26,57 +62,72 @@ impl IsoDownloader {
             .timeout(std::time::Duration::from_secs(3600))
             .build()?;
         
-        let response = client
-            .get(url)
-            .send()
-            .await
-            .context("Failed to start download")?;
-        
-        // Check if the response is successful
-        if !response.status().is_success() {
-            return Err(anyhow::anyhow!("Download failed with status: {}", response.status()));
-        }
-        
-        let total_size = response
-            .content_length()
-            .ok_or_else(|| anyhow::anyhow!("Failed to get content length"))?;
-        
-        // Create progress bar
-        let pb = ProgressBar::new(total_size);
-        pb.set_style(
-            ProgressStyle::default_bar()
-                .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")
-                .unwrap()
-                .progress_chars("#>-")
-        );
-        
-        // Download with progress
-        let mut file = File::create(output_path)
-            .context("Failed to create output file")?;
-        
-        let mut downloaded = 0u64;
-        let mut stream = response.bytes_stream();
+        println!("📥 Downloading Ubuntu {} ISO...", version);
+        println!("   From: {}", release.iso_url());
+        Self::download_checked(&client, &release.sums_url(), &release.iso_url(), release.file_name, output_path).await
+    }
+    
+    /// [`IsoDownloader::download_verified`] against the digest for
+    /// `file_name` in the `SHA256SUMS` at `sums_url`
+    ///
+    /// If the sums can't be fetched, a cached ISO that matches the digest
+    /// recorded when it was downloaded is used as is.
+    pub async fn download_checked(
+        client: &reqwest::Client,
+        sums_url: &str,
+        iso_url: &str,
+        file_name: &str,
+        output_path: &Path,
+    ) -> Result<()> {
+        let sums = match fetch_text(client, sums_url).await {
+            Ok(sums) => sums,
+            Err(e) => {
+                let Some(expected) = recorded_digest(output_path) else {
+                    return Err(e);
+                };
+                if check_cached(output_path, &expected)? != Cached::Verified {
+                    return Err(e);
+                }
+                println!("⚠️  {:#}; using cached ISO {} (SHA256 verified against the recorded digest)", e, output_path.display());
+                return Ok(());
+            }
+        };
+        let expected = parse_sha256sums(&sums, file_name)
+            .ok_or_else(|| anyhow::anyhow!("{} has no checksum for {}", sums_url, file_name))?;
         
-        use futures_util::StreamExt;
-        while let Some(chunk) = stream.next().await {
-            let chunk = chunk.context("Error during download")?;
-            file.write_all(&chunk)?;
-            
-            let new = std::cmp::min(downloaded + (chunk.len() as u64), total_size);
-            downloaded = new;
-            pb.set_position(new);
+        Self::download_verified(client, iso_url, output_path, &expected).await
+    }
+    
+    /// Make `output_path` hold the file at `url` with SHA256 `expected`,
+    /// reusing a verified cached copy and resuming a partial download
+    pub async fn download_verified(client: &reqwest::Client, url: &str, output_path: &Path, expected: &str) -> Result<()> {
+        match check_cached(output_path, expected)? {
+            Cached::Verified => {
+                println!("ℹ️  Using cached ISO: {} (SHA256 verified)", output_path.display());
+                return record_digest(output_path, expected);
+            }
+            Cached::Corrupt => {
+                println!("⚠️  Cached ISO {} failed its SHA256 check, downloading again", output_path.display());
+            }
+            Cached::Missing => {}
         }
         
-        pb.finish_with_message("Download complete");
+        let part = part_path(output_path);
+        fetch(client, url, &part).await?;
         
-        // Verify download
-        let file_size = std::fs::metadata(output_path)?.len();
-        if file_size != total_size {
-            std::fs::remove_file(output_path)?;
-            return Err(anyhow::anyhow!("Download corrupted: size mismatch"));
+        println!("🔍 Verifying SHA256...");
+        let actual = sha256_file(&part)?;
+        if !actual.eq_ignore_ascii_case(expected) {
+            std::fs::remove_file(&part)?;
+            return Err(anyhow::anyhow!(
+                "Download corrupted: SHA256 {} does not match published {}", actual, expected
+            ));
         }
+        std::fs::rename(&part, output_path)
+            .with_context(|| format!("Failed to move download to {}", output_path.display()))?;
+        println!("✓ SHA256 verified: {}", actual);
         
-        Ok(())
+        record_digest(output_path, expected)
     }
     
     pub fn cleanup(path: &Path) -> Result<()> {
@@ -86,4 +137,321 @@ impl IsoDownloader {
         }
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+async fn fetch_text(client: &reqwest::Client, url: &str) -> Result<String> {
+    let response = client.get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .with_context(|| format!("Failed to fetch {}", url))?;
+    Ok(response.text().await?)
+}
+
+/// Keep `digest` in `<iso>.sha256` for checking the ISO offline later
+fn record_digest(iso: &Path, digest: &str) -> Result<()> {
+    let name = iso.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    let path = crate::integrity::sidecar_path(iso, ".sha256");
+    std::fs::write(&path, crate::integrity::sidecar_line(&digest.to_ascii_lowercase(), &name))
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// The digest recorded by [`record_digest`], if it is for this ISO
+fn recorded_digest(iso: &Path) -> Option<String> {
+    let content = std::fs::read_to_string(crate::integrity::sidecar_path(iso, ".sha256")).ok()?;
+    let (digest, name) = crate::integrity::parse_sidecar(&content).ok()?;
+    (iso.file_name()? == name.as_str()).then_some(digest)
+}
+
+/// First byte of a `Content-Range: bytes <first>-<last>/<total>` value
+fn content_range_start(value: &str) -> Option<u64> {
+    let (first, _) = value.trim().strip_prefix("bytes ")?.split_once('-')?;
+    first.parse().ok()
+}
+
+/// Download `url` into `part`, continuing from its current length
+async fn fetch(client: &reqwest::Client, url: &str, part: &Path) -> Result<()> {
+    use futures_util::StreamExt;
+    use reqwest::StatusCode;
+    
+    let offset = std::fs::metadata(part).map(|m| m.len()).unwrap_or(0);
+    let mut request = client.get(url);
+    if offset > 0 {
+        request = request.header(reqwest::header::RANGE, format!("bytes={}-", offset));
+    }
+    let response = request.send().await.context("Failed to start download")?;
+    
+    let (mut file, start) = match response.status() {
+        // Nothing left to fetch; the checksum decides whether it is whole
+        StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => return Ok(()),
+        StatusCode::PARTIAL_CONTENT if offset > 0 => {
+            let resumed_at = response.headers()
+                .get(reqwest::header::CONTENT_RANGE)
+                .and_then(|value| value.to_str().ok())
+                .and_then(content_range_start);
+            if resumed_at != Some(offset) {
+                // Appending would splice the wrong bytes in; start over
+                println!("⚠️  Server resumed at {:?} instead of byte {}, downloading again", resumed_at, offset);
+                drop(response);
+                std::fs::remove_file(part)?;
+                return Box::pin(fetch(client, url, part)).await;
+            }
+            println!("   Resuming at {} bytes", offset);
+            (OpenOptions::new().append(true).open(part)?, offset)
+        }
+        // The server ignored the range, so start over
+        status if status.is_success() => (File::create(part).context("Failed to create output file")?, 0),
+        status => return Err(anyhow::anyhow!("Download failed with status: {}", status)),
+    };
+    
+    let total_size = start + response
+        .content_length()
+        .ok_or_else(|| anyhow::anyhow!("Failed to get content length"))?;
+    
+    // Create progress bar
+    let pb = ProgressBar::new(total_size);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")
+            .unwrap()
+            .progress_chars("#>-")
+    );
+    pb.set_position(start);
+    
+    let mut downloaded = start;
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        // The partial file is kept so the next attempt can resume
+        let chunk = chunk.context("Error during download")?;
+        file.write_all(&chunk)?;
+        
+        downloaded = std::cmp::min(downloaded + chunk.len() as u64, total_size);
+        pb.set_position(downloaded);
+    }
+    file.sync_all()?;
+    
+    pb.finish_with_message("Download complete");
+    
+    let file_size = std::fs::metadata(part)?.len();
+    if file_size != total_size {
+        return Err(anyhow::anyhow!(
+            "Download incomplete: got {} of {} bytes; run again to resume", file_size, total_size
+        ));
+    }
+    Ok(())
+}
+
+/// Verify a cached ISO, deleting it if it does not match `expected`
+pub fn check_cached(path: &Path, expected: &str) -> Result<Cached> {
+    if !path.exists() {
+        return Ok(Cached::Missing);
+    }
+    println!("🔍 Verifying cached ISO {}...", path.display());
+    if sha256_file(path)?.eq_ignore_ascii_case(expected) {
+        return Ok(Cached::Verified);
+    }
+    std::fs::remove_file(path)
+        .with_context(|| format!("Failed to remove corrupt ISO {}", path.display()))?;
+    Ok(Cached::Corrupt)
+}
+
+/// The digest for `file_name` in `sha256sum` output, which marks binary
+/// files with `*` before the name
+pub fn parse_sha256sums(content: &str, file_name: &str) -> Option<String> {
+    content.lines().find_map(|line| {
+        let (digest, name) = line.trim().split_once(char::is_whitespace)?;
+        let name = name.trim_start();
+        let name = name.strip_prefix('*').unwrap_or(name);
+        (name == file_name && digest.len() == 64 && digest.bytes().all(|b| b.is_ascii_hexdigit()))
+            .then(|| digest.to_ascii_lowercase())
+    })
+}
+
+fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1 << 20];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// `<iso>.part`, where an unfinished download lives
+fn part_path(path: &Path) -> PathBuf {
+    let mut part = path.as_os_str().to_owned();
+    part.push(".part");
+    PathBuf::from(part)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    const BODY: &[u8] = b"not really an ubuntu image, but long enough to split in two";
+
+    fn digest(data: &[u8]) -> String {
+        hex::encode(Sha256::digest(data))
+    }
+
+    /// Serve `BODY` over HTTP, honouring `Range: bytes=N-`, and record the
+    /// range header of every request
+    async fn serve() -> (String, Arc<Mutex<Vec<Option<String>>>>) {
+        serve_skewed(0).await
+    }
+
+    /// [`serve`], but resuming `skew` bytes after the requested range start
+    async fn serve_skewed(skew: usize) -> (String, Arc<Mutex<Vec<Option<String>>>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/ubuntu.iso", listener.local_addr().unwrap());
+        let ranges = Arc::new(Mutex::new(Vec::new()));
+
+        let seen = ranges.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let read = socket.read(&mut buffer).await.unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                }
+
+                let request = String::from_utf8(request).unwrap();
+                let range = request.lines()
+                    .find_map(|line| line.to_ascii_lowercase().strip_prefix("range: bytes=").map(str::to_string));
+                seen.lock().unwrap().push(range.clone());
+
+                let start: usize = range.and_then(|r| r.trim_end_matches('-').parse().ok())
+                    .map_or(0, |start: usize| start + skew);
+                let head = if start > 0 {
+                    format!(
+                        "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {}-{}/{}\r\n",
+                        start, BODY.len() - 1, BODY.len()
+                    )
+                } else {
+                    "HTTP/1.1 200 OK\r\n".to_string()
+                };
+                let response = format!("{}Content-Length: {}\r\nConnection: close\r\n\r\n", head, BODY.len() - start);
+                socket.write_all(response.as_bytes()).await.unwrap();
+                socket.write_all(&BODY[start..]).await.unwrap();
+            }
+        });
+
+        (url, ranges)
+    }
+
+    #[test]
+    fn test_parse_sha256sums() {
+        let desktop = "a".repeat(64);
+        let server = "B".repeat(64);
+        let sums = format!(
+            "{} *ubuntu-24.04.2-desktop-amd64.iso\n{}  ubuntu-24.04.2-live-server-amd64.iso\n",
+            desktop, server
+        );
+
+        assert_eq!(parse_sha256sums(&sums, "ubuntu-24.04.2-desktop-amd64.iso"), Some(desktop));
+        assert_eq!(parse_sha256sums(&sums, "ubuntu-24.04.2-live-server-amd64.iso"), Some("b".repeat(64)));
+        assert_eq!(parse_sha256sums(&sums, "ubuntu-24.04.2-desktop-amd64.iso.zsync"), None);
+        assert_eq!(parse_sha256sums("xyz *ubuntu.iso\n", "ubuntu.iso"), None);
+    }
+
+    #[test]
+    fn test_check_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let iso = dir.path().join("ubuntu-24.04.iso");
+        assert_eq!(check_cached(&iso, &digest(BODY)).unwrap(), Cached::Missing);
+
+        std::fs::write(&iso, BODY).unwrap();
+        assert_eq!(check_cached(&iso, &digest(BODY).to_uppercase()).unwrap(), Cached::Verified);
+
+        // Half a download is not a valid ISO
+        std::fs::write(&iso, &BODY[..10]).unwrap();
+        assert_eq!(check_cached(&iso, &digest(BODY)).unwrap(), Cached::Corrupt);
+        assert!(!iso.exists());
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_cache_is_downloaded_again() {
+        let (url, ranges) = serve().await;
+        let dir = tempfile::tempdir().unwrap();
+        let iso = dir.path().join("ubuntu-24.04.iso");
+        std::fs::write(&iso, &BODY[..10]).unwrap();
+
+        let client = reqwest::Client::new();
+        IsoDownloader::download_verified(&client, &url, &iso, &digest(BODY)).await.unwrap();
+        assert_eq!(std::fs::read(&iso).unwrap(), BODY);
+        assert_eq!(*ranges.lock().unwrap(), vec![None]);
+
+        // Now verified, so nothing is fetched
+        IsoDownloader::download_verified(&client, &url, &iso, &digest(BODY)).await.unwrap();
+        assert_eq!(ranges.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_partial_download_resumes() {
+        let (url, ranges) = serve().await;
+        let dir = tempfile::tempdir().unwrap();
+        let iso = dir.path().join("ubuntu-24.04.iso");
+        std::fs::write(part_path(&iso), &BODY[..20]).unwrap();
+
+        let client = reqwest::Client::new();
+        IsoDownloader::download_verified(&client, &url, &iso, &digest(BODY)).await.unwrap();
+        assert_eq!(std::fs::read(&iso).unwrap(), BODY);
+        assert!(!part_path(&iso).exists());
+        assert_eq!(*ranges.lock().unwrap(), vec![Some("20-".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_resume_at_wrong_offset_starts_over() {
+        let (url, ranges) = serve_skewed(5).await;
+        let dir = tempfile::tempdir().unwrap();
+        let iso = dir.path().join("ubuntu-24.04.iso");
+        std::fs::write(part_path(&iso), &BODY[..20]).unwrap();
+
+        let client = reqwest::Client::new();
+        IsoDownloader::download_verified(&client, &url, &iso, &digest(BODY)).await.unwrap();
+        assert_eq!(std::fs::read(&iso).unwrap(), BODY);
+        assert_eq!(*ranges.lock().unwrap(), vec![Some("20-".to_string()), None]);
+        assert_eq!(content_range_start("bytes 20-58/59"), Some(20));
+        assert_eq!(content_range_start("items 20-58/59"), None);
+    }
+
+    #[tokio::test]
+    async fn test_cached_iso_is_used_offline() {
+        let (url, _) = serve().await;
+        let dir = tempfile::tempdir().unwrap();
+        let iso = dir.path().join("ubuntu-24.04.iso");
+        let client = reqwest::Client::new();
+        IsoDownloader::download_verified(&client, &url, &iso, &digest(BODY)).await.unwrap();
+
+        // Nothing listens on the discard port
+        let unreachable = "http://127.0.0.1:9/SHA256SUMS";
+        IsoDownloader::download_checked(&client, unreachable, &url, "ubuntu-24.04.iso", &iso).await.unwrap();
+
+        // Without a good cached copy the unreachable sums are an error
+        std::fs::write(&iso, &BODY[..10]).unwrap();
+        let err = IsoDownloader::download_checked(&client, unreachable, &url, "ubuntu-24.04.iso", &iso).await.unwrap_err();
+        assert!(err.to_string().contains("Failed to fetch"), "{:#}", err);
+    }
+
+    #[tokio::test]
+    async fn test_checksum_mismatch_fails() {
+        let (url, _) = serve().await;
+        let dir = tempfile::tempdir().unwrap();
+        let iso = dir.path().join("ubuntu-24.04.iso");
+
+        let client = reqwest::Client::new();
+        let err = IsoDownloader::download_verified(&client, &url, &iso, &digest(b"other")).await.unwrap_err();
+        assert!(err.to_string().contains("does not match"), "{}", err);
+        assert!(!iso.exists());
+        assert!(!part_path(&iso).exists());
+    }
+}
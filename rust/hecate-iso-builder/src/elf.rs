@@ -0,0 +1,156 @@
+//! ELF header inspection
+//!
+//! Just enough of the ELF header to tell which architecture a binary was
+//! built for, so binaries for the wrong machine never reach the ISO.
+
+use anyhow::{bail, Context, Result};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+const ELF_MAGIC: &[u8; 4] = b"\x7fELF";
+
+/// Architectures HecateOS can target, named as in Debian/Ubuntu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Architecture {
+    Amd64,
+    Arm64,
+    I386,
+    Armhf,
+    Riscv64,
+    Ppc64el,
+    S390x,
+}
+
+impl Architecture {
+    pub const ALL: [Architecture; 7] = [
+        Self::Amd64, Self::Arm64, Self::I386, Self::Armhf, Self::Riscv64, Self::Ppc64el, Self::S390x,
+    ];
+
+    /// Debian architecture name, as used in ISO names and `.disk/info`
+    pub fn debian_name(&self) -> &'static str {
+        match self {
+            Self::Amd64 => "amd64",
+            Self::Arm64 => "arm64",
+            Self::I386 => "i386",
+            Self::Armhf => "armhf",
+            Self::Riscv64 => "riscv64",
+            Self::Ppc64el => "ppc64el",
+            Self::S390x => "s390x",
+        }
+    }
+
+    /// Rust target triple to build for this architecture
+    pub fn rust_target(&self) -> &'static str {
+        match self {
+            Self::Amd64 => "x86_64-unknown-linux-gnu",
+            Self::Arm64 => "aarch64-unknown-linux-gnu",
+            Self::I386 => "i686-unknown-linux-gnu",
+            Self::Armhf => "armv7-unknown-linux-gnueabihf",
+            Self::Riscv64 => "riscv64gc-unknown-linux-gnu",
+            Self::Ppc64el => "powerpc64le-unknown-linux-gnu",
+            Self::S390x => "s390x-unknown-linux-gnu",
+        }
+    }
+
+    /// Parse a Debian name, also accepting the common kernel spellings
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "x86_64" | "x86-64" => Some(Self::Amd64),
+            "aarch64" => Some(Self::Arm64),
+            "i686" | "x86" => Some(Self::I386),
+            _ => Self::ALL.into_iter().find(|arch| arch.debian_name() == name),
+        }
+    }
+
+    /// Match the machine type, class and data encoding (1 for little
+    /// endian, 2 for big endian), so that e.g. big-endian ppc64 is not
+    /// taken for ppc64el
+    fn from_machine(machine: u16, class: u8, data: u8) -> Option<Self> {
+        match (machine, class, data) {
+            (0x3E, 2, 1) => Some(Self::Amd64),
+            (0xB7, 2, 1) => Some(Self::Arm64),
+            (0x03, 1, 1) => Some(Self::I386),
+            (0x28, 1, 1) => Some(Self::Armhf),
+            (0xF3, 2, 1) => Some(Self::Riscv64),
+            (0x15, 2, 1) => Some(Self::Ppc64el),
+            (0x16, 2, 2) => Some(Self::S390x),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Architecture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.debian_name())
+    }
+}
+
+/// Architecture of the ELF image starting with `header`
+pub fn architecture(header: &[u8]) -> Result<Architecture> {
+    if header.len() < 20 || &header[0..4] != ELF_MAGIC {
+        bail!("not an ELF binary");
+    }
+    let (class, data) = (header[4], header[5]);
+    let machine = match data {
+        1 => u16::from_le_bytes([header[18], header[19]]),
+        2 => u16::from_be_bytes([header[18], header[19]]),
+        data => bail!("unknown ELF data encoding {}", data),
+    };
+    Architecture::from_machine(machine, class, data).ok_or_else(|| {
+        let endian = if data == 1 { "little" } else { "big" };
+        anyhow::anyhow!("unsupported ELF machine type {:#x} (class {}, {} endian)", machine, class, endian)
+    })
+}
+
+/// Architecture of the ELF binary at `path`
+pub fn read_architecture(path: &Path) -> Result<Architecture> {
+    let mut header = Vec::with_capacity(20);
+    File::open(path)
+        .and_then(|file| file.take(20).read_to_end(&mut header))
+        .with_context(|| format!("Failed to read ELF header of {}", path.display()))?;
+    architecture(&header).with_context(|| format!("{} is not a usable binary", path.display()))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A 64-byte ELF header for `machine`
+    pub(crate) fn fixture(class: u8, big_endian: bool, machine: u16) -> Vec<u8> {
+        let mut header = vec![0u8; 64];
+        header[0..4].copy_from_slice(ELF_MAGIC);
+        header[4] = class;
+        header[5] = if big_endian { 2 } else { 1 };
+        header[6] = 1;
+        let bytes = if big_endian { machine.to_be_bytes() } else { machine.to_le_bytes() };
+        header[18..20].copy_from_slice(&bytes);
+        header
+    }
+
+    #[test]
+    fn test_architectures() {
+        assert_eq!(architecture(&fixture(2, false, 0x3E)).unwrap(), Architecture::Amd64);
+        assert_eq!(architecture(&fixture(2, false, 0xB7)).unwrap(), Architecture::Arm64);
+        assert_eq!(architecture(&fixture(1, false, 0x28)).unwrap(), Architecture::Armhf);
+        assert_eq!(architecture(&fixture(2, true, 0x16)).unwrap(), Architecture::S390x);
+
+        assert!(architecture(b"#!/bin/sh\necho hi\n\n\n").is_err());
+        // ELFCLASS32 with the x86_64 machine is the x32 ABI, not amd64
+        assert!(architecture(&fixture(1, false, 0x3E)).is_err());
+
+        // ppc64el is little endian only; big-endian ppc64 is another port
+        assert_eq!(architecture(&fixture(2, false, 0x15)).unwrap(), Architecture::Ppc64el);
+        let error = architecture(&fixture(2, true, 0x15)).unwrap_err();
+        assert!(error.to_string().contains("big endian"), "{error}");
+        assert!(architecture(&fixture(2, true, 0xB7)).is_err());
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(Architecture::from_name("amd64"), Some(Architecture::Amd64));
+        assert_eq!(Architecture::from_name("aarch64"), Some(Architecture::Arm64));
+        assert_eq!(Architecture::from_name("ppc64el"), Some(Architecture::Ppc64el));
+        assert_eq!(Architecture::from_name("mips"), None);
+    }
+}
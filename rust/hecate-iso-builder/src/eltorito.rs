@@ -0,0 +1,262 @@
+//! El Torito boot structures
+//!
+//! The boot record volume descriptor, the boot catalog with BIOS and EFI
+//! entries, the isolinux boot info table, and the hybrid MBR that lets the
+//! image boot when written to a USB stick.
+
+use crate::iso_native::SECTOR_SIZE;
+
+/// Boot system identifier of the boot record volume descriptor
+pub const BOOT_SYSTEM_ID: &[u8] = b"EL TORITO SPECIFICATION";
+
+/// Boot indicator of a bootable catalog entry
+pub const BOOTABLE: u8 = 0x88;
+/// Header indicator of the last section header in the catalog
+const FINAL_SECTION_HEADER: u8 = 0x91;
+
+/// Platform id of a catalog entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Platform {
+    Bios = 0x00,
+    Efi = 0xEF,
+}
+
+/// A no-emulation boot image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BootEntry {
+    pub platform: Platform,
+    /// ISO sector the image starts at
+    pub load_rba: u32,
+    /// Image length in 512-byte virtual sectors
+    pub sector_count: u16,
+}
+
+impl BootEntry {
+    /// BIOS loaders such as isolinux.bin load the first 2 KiB; the loader
+    /// pulls in the rest itself
+    pub fn bios(load_rba: u32) -> Self {
+        Self { platform: Platform::Bios, load_rba, sector_count: 4 }
+    }
+
+    /// EFI entries cover the whole FAT image, capped at what fits the field
+    pub fn efi(load_rba: u32, image_len: u64) -> Self {
+        let sector_count = image_len.div_ceil(512).min(u16::MAX as u64) as u16;
+        Self { platform: Platform::Efi, load_rba, sector_count }
+    }
+
+    fn write(&self, entry: &mut [u8]) {
+        entry[0] = BOOTABLE;
+        entry[1] = 0; // no emulation
+        entry[2..4].copy_from_slice(&0u16.to_le_bytes()); // default load segment 0x7C0
+        entry[4] = 0; // system type
+        entry[6..8].copy_from_slice(&self.sector_count.to_le_bytes());
+        entry[8..12].copy_from_slice(&self.load_rba.to_le_bytes());
+    }
+}
+
+/// The boot record volume descriptor pointing at the catalog
+pub fn boot_record(catalog_lba: u32) -> Vec<u8> {
+    let mut descriptor = vec![0u8; SECTOR_SIZE];
+    descriptor[0] = 0; // boot record
+    descriptor[1..6].copy_from_slice(b"CD001");
+    descriptor[6] = 1;
+    descriptor[7..7 + BOOT_SYSTEM_ID.len()].copy_from_slice(BOOT_SYSTEM_ID);
+    descriptor[71..75].copy_from_slice(&catalog_lba.to_le_bytes());
+    descriptor
+}
+
+/// The boot catalog sector
+///
+/// The BIOS image, when there is one, is the default entry and the EFI
+/// image gets its own section; an EFI-only catalog uses the EFI image as
+/// the default entry.
+pub fn boot_catalog(bios: Option<&BootEntry>, efi: Option<&BootEntry>) -> Vec<u8> {
+    let mut catalog = vec![0u8; SECTOR_SIZE];
+    let default = bios.or(efi);
+
+    // Validation entry
+    catalog[0] = 1;
+    catalog[1] = default.map_or(Platform::Bios, |entry| entry.platform) as u8;
+    catalog[4..12].copy_from_slice(b"HECATEOS");
+    catalog[30] = 0x55;
+    catalog[31] = 0xAA;
+    // All 16-bit words of the entry must sum to zero
+    let sum = catalog[0..32]
+        .chunks(2)
+        .fold(0u16, |sum, word| sum.wrapping_add(u16::from_le_bytes([word[0], word[1]])));
+    catalog[28..30].copy_from_slice(&0u16.wrapping_sub(sum).to_le_bytes());
+
+    if let Some(entry) = default {
+        entry.write(&mut catalog[32..64]);
+    }
+
+    if let (Some(_), Some(efi)) = (bios, efi) {
+        catalog[64] = FINAL_SECTION_HEADER;
+        catalog[65] = Platform::Efi as u8;
+        catalog[66..68].copy_from_slice(&1u16.to_le_bytes());
+        efi.write(&mut catalog[96..128]);
+    }
+
+    catalog
+}
+
+/// Fill in the boot info table that isolinux expects at offset 8
+/// (`-boot-info-table`)
+pub fn patch_boot_info_table(image: &mut [u8], pvd_lba: u32, image_lba: u32) {
+    if image.len() < 64 {
+        return;
+    }
+
+    // Checksum of every 32-bit word after the table
+    let checksum = image[64..]
+        .chunks(4)
+        .map(|chunk| {
+            let mut word = [0u8; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            u32::from_le_bytes(word)
+        })
+        .fold(0u32, u32::wrapping_add);
+
+    let len = image.len() as u32;
+    image[8..12].copy_from_slice(&pvd_lba.to_le_bytes());
+    image[12..16].copy_from_slice(&image_lba.to_le_bytes());
+    image[16..20].copy_from_slice(&len.to_le_bytes());
+    image[20..24].copy_from_slice(&checksum.to_le_bytes());
+    image[24..64].fill(0);
+}
+
+/// Layout of the hybrid MBR written to the system area
+pub struct HybridLayout<'a> {
+    /// Boot code such as syslinux's isohdpfx.bin; without it only UEFI can
+    /// boot the image from USB
+    pub mbr_code: Option<&'a [u8]>,
+    /// ISO sector of the BIOS loader
+    pub bios_lba: Option<u32>,
+    /// ISO sector and byte length of the EFI image
+    pub efi: Option<(u32, u64)>,
+    pub total_sectors: u32,
+    pub disk_signature: u32,
+}
+
+/// Bytes of boot code that fit before the disk signature
+const MBR_CODE_LEN: usize = 432;
+
+fn partition_entry(entry: &mut [u8], bootable: bool, kind: u8, start: u32, sectors: u32) {
+    entry[0] = if bootable { 0x80 } else { 0x00 };
+    // CHS addresses are unused by anything that can boot this image
+    entry[1..4].copy_from_slice(&[0xFE, 0xFF, 0xFF]);
+    entry[4] = kind;
+    entry[5..8].copy_from_slice(&[0xFE, 0xFF, 0xFF]);
+    entry[8..12].copy_from_slice(&start.to_le_bytes());
+    entry[12..16].copy_from_slice(&sectors.to_le_bytes());
+}
+
+/// The first 512 bytes of an isohybrid image
+///
+/// Partition 1 covers the whole ISO so BIOS boot code can find the
+/// loader; partition 2 is the EFI System Partition so firmware finds the
+/// EFI image when the ISO is `dd`-ed to a USB stick.
+pub fn hybrid_mbr(layout: &HybridLayout) -> [u8; 512] {
+    let mut mbr = [0u8; 512];
+    let to_512 = |lba: u32| lba * (SECTOR_SIZE as u32 / 512);
+
+    if let Some(code) = layout.mbr_code {
+        let len = code.len().min(MBR_CODE_LEN);
+        mbr[..len].copy_from_slice(&code[..len]);
+        if let Some(bios_lba) = layout.bios_lba {
+            // isohdpfx reads the loader location from here
+            mbr[432..436].copy_from_slice(&to_512(bios_lba).to_le_bytes());
+        }
+    }
+    mbr[440..444].copy_from_slice(&layout.disk_signature.to_le_bytes());
+
+    partition_entry(&mut mbr[446..462], true, 0x17, 0, to_512(layout.total_sectors));
+    if let Some((efi_lba, efi_len)) = layout.efi {
+        partition_entry(&mut mbr[462..478], false, 0xEF, to_512(efi_lba), efi_len.div_ceil(512) as u32);
+    }
+
+    mbr[510] = 0x55;
+    mbr[511] = 0xAA;
+    mbr
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_boot_record() {
+        let record = boot_record(19);
+        assert_eq!(record[0], 0);
+        assert_eq!(&record[1..6], b"CD001");
+        assert_eq!(&record[7..30], BOOT_SYSTEM_ID);
+        assert!(record[30..39].iter().all(|&b| b == 0));
+        assert_eq!(u32::from_le_bytes(record[71..75].try_into().unwrap()), 19);
+    }
+
+    #[test]
+    fn test_validation_entry_checksum() {
+        for catalog in [
+            boot_catalog(Some(&BootEntry::bios(30)), Some(&BootEntry::efi(20, 4 << 20))),
+            boot_catalog(None, Some(&BootEntry::efi(20, 4 << 20))),
+        ] {
+            let sum = catalog[0..32]
+                .chunks(2)
+                .fold(0u16, |sum, word| sum.wrapping_add(u16::from_le_bytes([word[0], word[1]])));
+            assert_eq!(sum, 0);
+            assert_eq!(&catalog[30..32], &[0x55, 0xAA]);
+        }
+    }
+
+    #[test]
+    fn test_efi_only_catalog() {
+        let catalog = boot_catalog(None, Some(&BootEntry::efi(20, 1024)));
+        assert_eq!(catalog[1], Platform::Efi as u8);
+        assert_eq!(catalog[32], BOOTABLE);
+        assert_eq!(u16::from_le_bytes([catalog[38], catalog[39]]), 2);
+        assert_eq!(u32::from_le_bytes(catalog[40..44].try_into().unwrap()), 20);
+        // No section for a second platform
+        assert_eq!(catalog[64], 0);
+    }
+
+    #[test]
+    fn test_boot_info_table() {
+        let mut image = vec![0u8; 2048];
+        image[64..68].copy_from_slice(&1u32.to_le_bytes());
+        image[2044..2048].copy_from_slice(&0xFFFF_FFFFu32.to_le_bytes());
+        image[10] = 0xAB;
+
+        patch_boot_info_table(&mut image, 16, 40);
+        let word = |offset: usize| u32::from_le_bytes(image[offset..offset + 4].try_into().unwrap());
+        assert_eq!(word(8), 16);
+        assert_eq!(word(12), 40);
+        assert_eq!(word(16), 2048);
+        assert_eq!(word(20), 0); // 1 + 0xFFFFFFFF wraps
+    }
+
+    #[test]
+    fn test_hybrid_mbr() {
+        let code = [0xFAu8; 440];
+        let mbr = hybrid_mbr(&HybridLayout {
+            mbr_code: Some(&code),
+            bios_lba: Some(30),
+            efi: Some((20, 3 * 512 + 1)),
+            total_sectors: 1000,
+            disk_signature: 0x1234_5678,
+        });
+
+        assert_eq!(&mbr[510..512], &[0x55, 0xAA]);
+        assert_eq!(mbr[431], 0xFA);
+        assert_eq!(u32::from_le_bytes(mbr[432..436].try_into().unwrap()), 120);
+        assert_eq!(u32::from_le_bytes(mbr[440..444].try_into().unwrap()), 0x1234_5678);
+
+        assert_eq!(mbr[446], 0x80);
+        assert_eq!(mbr[450], 0x17);
+        assert_eq!(u32::from_le_bytes(mbr[458..462].try_into().unwrap()), 4000);
+
+        assert_eq!(mbr[466], 0xEF);
+        assert_eq!(u32::from_le_bytes(mbr[470..474].try_into().unwrap()), 80);
+        assert_eq!(u32::from_le_bytes(mbr[474..478].try_into().unwrap()), 4);
+    }
+}
@@ -0,0 +1,232 @@
+//! Minimal FAT16 image writer
+//!
+//! Builds the EFI System Partition image embedded in the ISO for UEFI
+//! boot: a FAT16 volume holding `EFI/BOOT/` and the loader files.
+
+use anyhow::{bail, Result};
+
+const BYTES_PER_SECTOR: usize = 512;
+const RESERVED_SECTORS: usize = 1;
+const FAT_COUNT: usize = 2;
+const ROOT_ENTRIES: usize = 512;
+const DIR_ENTRY_SIZE: usize = 32;
+/// FAT16 needs at least 4085 clusters or it is read as FAT12
+const MIN_CLUSTERS: usize = 4085 + 16;
+const MAX_CLUSTERS: usize = 65524;
+
+const ATTR_DIRECTORY: u8 = 0x10;
+const ATTR_ARCHIVE: u8 = 0x20;
+/// 1980-01-01, the FAT epoch
+const FAT_DATE: u16 = 0x0021;
+
+/// The 11-byte 8.3 directory name for `name`, if it has one
+pub fn short_name(name: &str) -> Option<[u8; 11]> {
+    let (base, ext) = match name.rsplit_once('.') {
+        Some((base, ext)) => (base, ext),
+        None => (name, ""),
+    };
+    let valid = |part: &str, max: usize| {
+        part.len() <= max
+            && part.bytes().all(|b| b.is_ascii_alphanumeric() || b"!#$%&'()-@^_`{}~".contains(&b))
+    };
+    if base.is_empty() || !valid(base, 8) || !valid(ext, 3) {
+        return None;
+    }
+
+    let mut short = [b' '; 11];
+    short[..base.len()].copy_from_slice(base.to_ascii_uppercase().as_bytes());
+    short[8..8 + ext.len()].copy_from_slice(ext.to_ascii_uppercase().as_bytes());
+    Some(short)
+}
+
+fn dir_entry(name: &[u8; 11], attr: u8, cluster: u16, size: u32) -> [u8; DIR_ENTRY_SIZE] {
+    let mut entry = [0u8; DIR_ENTRY_SIZE];
+    entry[0..11].copy_from_slice(name);
+    entry[11] = attr;
+    entry[16..18].copy_from_slice(&FAT_DATE.to_le_bytes()); // creation date
+    entry[18..20].copy_from_slice(&FAT_DATE.to_le_bytes()); // last access
+    entry[24..26].copy_from_slice(&FAT_DATE.to_le_bytes()); // modification date
+    entry[26..28].copy_from_slice(&cluster.to_le_bytes());
+    entry[28..32].copy_from_slice(&size.to_le_bytes());
+    entry
+}
+
+/// A FAT16 image with `files` under `EFI/BOOT/`
+///
+/// File names must be valid 8.3 names, e.g. `BOOTX64.EFI`; they are stored
+/// uppercased.
+pub fn efi_boot_image(files: &[(String, Vec<u8>)]) -> Result<Vec<u8>> {
+    let names = files.iter()
+        .map(|(name, _)| short_name(name).ok_or_else(|| anyhow::anyhow!("{} is not a valid 8.3 file name", name)))
+        .collect::<Result<Vec<_>>>()?;
+
+    // Smallest cluster size that keeps the cluster count in FAT16 range
+    let mut sectors_per_cluster = 1;
+    let clusters = loop {
+        let cluster_size = sectors_per_cluster * BYTES_PER_SECTOR;
+        // Two directory clusters, then the file data
+        let needed = 2 + files.iter().map(|(_, data)| data.len().div_ceil(cluster_size)).sum::<usize>();
+        let clusters = (needed + 16).max(MIN_CLUSTERS);
+        if clusters <= MAX_CLUSTERS {
+            break clusters;
+        }
+        if sectors_per_cluster == 64 {
+            bail!("EFI boot files are too large for a FAT16 image");
+        }
+        sectors_per_cluster *= 2;
+    };
+    let cluster_size = sectors_per_cluster * BYTES_PER_SECTOR;
+
+    // Each directory is one cluster; two of its entries are . and ..
+    if files.len() + 2 > cluster_size / DIR_ENTRY_SIZE {
+        bail!("Too many EFI boot files ({})", files.len());
+    }
+
+    let fat_sectors = ((clusters + 2) * 2).div_ceil(BYTES_PER_SECTOR);
+    let root_sectors = ROOT_ENTRIES * DIR_ENTRY_SIZE / BYTES_PER_SECTOR;
+    let root_start = (RESERVED_SECTORS + FAT_COUNT * fat_sectors) * BYTES_PER_SECTOR;
+    let data_start = root_start + root_sectors * BYTES_PER_SECTOR;
+    let total_sectors = data_start / BYTES_PER_SECTOR + clusters * sectors_per_cluster;
+
+    let mut image = vec![0u8; total_sectors * BYTES_PER_SECTOR];
+    let cluster_offset = |cluster: usize| data_start + (cluster - 2) * cluster_size;
+
+    // Allocation table: cluster 2 is EFI/, 3 is EFI/BOOT/, files follow
+    let mut fat = vec![0u16; clusters + 2];
+    fat[0] = 0xFFF8;
+    fat[1] = 0xFFFF;
+    fat[2] = 0xFFFF;
+    fat[3] = 0xFFFF;
+
+    let mut boot_dir = Vec::new();
+    boot_dir.extend_from_slice(&dir_entry(b".          ", ATTR_DIRECTORY, 3, 0));
+    boot_dir.extend_from_slice(&dir_entry(b"..         ", ATTR_DIRECTORY, 2, 0));
+
+    let mut next_cluster = 4;
+    for ((_, data), name) in files.iter().zip(&names) {
+        let count = data.len().div_ceil(cluster_size);
+        let first = if count == 0 { 0 } else { next_cluster };
+        for i in 0..count {
+            let cluster = next_cluster + i;
+            fat[cluster] = if i + 1 == count { 0xFFFF } else { (cluster + 1) as u16 };
+            let offset = cluster_offset(cluster);
+            let chunk = &data[i * cluster_size..data.len().min((i + 1) * cluster_size)];
+            image[offset..offset + chunk.len()].copy_from_slice(chunk);
+        }
+        next_cluster += count;
+        boot_dir.extend_from_slice(&dir_entry(name, ATTR_ARCHIVE, first as u16, data.len() as u32));
+    }
+
+    let mut efi_dir = Vec::new();
+    efi_dir.extend_from_slice(&dir_entry(b".          ", ATTR_DIRECTORY, 2, 0));
+    // .. pointing at the root directory uses cluster 0
+    efi_dir.extend_from_slice(&dir_entry(b"..         ", ATTR_DIRECTORY, 0, 0));
+    efi_dir.extend_from_slice(&dir_entry(b"BOOT       ", ATTR_DIRECTORY, 3, 0));
+
+    image[root_start..root_start + DIR_ENTRY_SIZE].copy_from_slice(&dir_entry(b"EFI        ", ATTR_DIRECTORY, 2, 0));
+    image[cluster_offset(2)..cluster_offset(2) + efi_dir.len()].copy_from_slice(&efi_dir);
+    image[cluster_offset(3)..cluster_offset(3) + boot_dir.len()].copy_from_slice(&boot_dir);
+
+    let fat_bytes: Vec<u8> = fat.iter().flat_map(|entry| entry.to_le_bytes()).collect();
+    for copy in 0..FAT_COUNT {
+        let offset = (RESERVED_SECTORS + copy * fat_sectors) * BYTES_PER_SECTOR;
+        image[offset..offset + fat_bytes.len()].copy_from_slice(&fat_bytes);
+    }
+
+    write_boot_sector(&mut image[..BYTES_PER_SECTOR], sectors_per_cluster, fat_sectors, total_sectors);
+    Ok(image)
+}
+
+/// BIOS parameter block for the volume
+fn write_boot_sector(sector: &mut [u8], sectors_per_cluster: usize, fat_sectors: usize, total_sectors: usize) {
+    sector[0..3].copy_from_slice(&[0xEB, 0x3C, 0x90]); // jump over the BPB
+    sector[3..11].copy_from_slice(b"HECATEOS");
+    sector[11..13].copy_from_slice(&(BYTES_PER_SECTOR as u16).to_le_bytes());
+    sector[13] = sectors_per_cluster as u8;
+    sector[14..16].copy_from_slice(&(RESERVED_SECTORS as u16).to_le_bytes());
+    sector[16] = FAT_COUNT as u8;
+    sector[17..19].copy_from_slice(&(ROOT_ENTRIES as u16).to_le_bytes());
+    if total_sectors < 0x10000 {
+        sector[19..21].copy_from_slice(&(total_sectors as u16).to_le_bytes());
+    } else {
+        sector[32..36].copy_from_slice(&(total_sectors as u32).to_le_bytes());
+    }
+    sector[21] = 0xF8; // fixed media
+    sector[22..24].copy_from_slice(&(fat_sectors as u16).to_le_bytes());
+    sector[24..26].copy_from_slice(&32u16.to_le_bytes()); // sectors per track
+    sector[26..28].copy_from_slice(&64u16.to_le_bytes()); // heads
+    sector[36] = 0x80; // drive number
+    sector[38] = 0x29; // extended boot signature
+    sector[39..43].copy_from_slice(&0x4845_4341u32.to_le_bytes()); // volume serial
+    sector[43..54].copy_from_slice(b"EFI        ");
+    sector[54..62].copy_from_slice(b"FAT16   ");
+    sector[510] = 0x55;
+    sector[511] = 0xAA;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u16_at(data: &[u8], offset: usize) -> usize {
+        u16::from_le_bytes([data[offset], data[offset + 1]]) as usize
+    }
+
+    /// Read `EFI/BOOT/<name>` back out of an image
+    fn read_boot_file(image: &[u8], name: &[u8; 11]) -> Option<Vec<u8>> {
+        let cluster_size = image[13] as usize * BYTES_PER_SECTOR;
+        let fat_start = u16_at(image, 14) * BYTES_PER_SECTOR;
+        let fat_sectors = u16_at(image, 22);
+        let root_start = fat_start + image[16] as usize * fat_sectors * BYTES_PER_SECTOR;
+        let data_start = root_start + u16_at(image, 17) * DIR_ENTRY_SIZE;
+        let cluster = |n: usize| &image[data_start + (n - 2) * cluster_size..data_start + (n - 1) * cluster_size];
+        let find = |dir: &[u8], name: &[u8]| {
+            dir.chunks(DIR_ENTRY_SIZE)
+                .find(|entry| &entry[0..11] == name)
+                .map(|entry| (u16_at(entry, 26), u32::from_le_bytes(entry[28..32].try_into().unwrap()) as usize))
+        };
+
+        let (efi, _) = find(&image[root_start..data_start], b"EFI        ")?;
+        let (boot, _) = find(cluster(efi), b"BOOT       ")?;
+        let (mut next, size) = find(cluster(boot), name)?;
+
+        let mut data = Vec::new();
+        while data.len() < size {
+            data.extend_from_slice(cluster(next));
+            next = u16_at(image, fat_start + next * 2);
+        }
+        data.truncate(size);
+        Some(data)
+    }
+
+    #[test]
+    fn test_short_name() {
+        assert_eq!(&short_name("bootx64.efi").unwrap(), b"BOOTX64 EFI");
+        assert_eq!(&short_name("GRUB").unwrap(), b"GRUB       ");
+        assert_eq!(short_name("grubx64.efi.signed"), None);
+        assert_eq!(short_name("toolongname.efi"), None);
+        assert_eq!(short_name(".efi"), None);
+    }
+
+    #[test]
+    fn test_efi_boot_image() {
+        let loader: Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+        let image = efi_boot_image(&[
+            ("BOOTX64.EFI".to_string(), loader.clone()),
+            ("grub.cfg".to_string(), b"configfile /boot/grub/grub.cfg\n".to_vec()),
+        ]).unwrap();
+
+        assert_eq!(&image[510..512], &[0x55, 0xAA]);
+        assert_eq!(&image[54..59], b"FAT16");
+        assert_eq!(image.len() % BYTES_PER_SECTOR, 0);
+
+        assert_eq!(read_boot_file(&image, b"BOOTX64 EFI"), Some(loader));
+        assert_eq!(read_boot_file(&image, b"GRUB    CFG").as_deref(), Some(&b"configfile /boot/grub/grub.cfg\n"[..]));
+        assert_eq!(read_boot_file(&image, b"MMX64   EFI"), None);
+    }
+
+    #[test]
+    fn test_rejects_long_names() {
+        assert!(efi_boot_image(&[("grubx64.efi.signed".to_string(), vec![1])]).is_err());
+    }
+}
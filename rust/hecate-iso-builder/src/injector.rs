@@ -3,9 +3,11 @@
 use anyhow::{Context, Result};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::process::Command;
 use walkdir::WalkDir;
 
 use crate::config::HecateConfig;
+use crate::elf::{self, Architecture};
 
 pub struct ComponentInjector {
     config: HecateConfig,
@@ -33,30 +35,87 @@ impl ComponentInjector {
             return Ok(());
         }
         
-        // Copy specified binaries
+        let target = self.target_architecture(iso_dir)?;
+        self.copy_binaries(&release_dir, &bin_dir, target)
+    }
+    
+    /// Architecture of the ISO being built: the one named in the Ubuntu
+    /// ISO's `.disk/info`, else the configured one
+    pub fn target_architecture(&self, iso_dir: &Path) -> Result<Architecture> {
+        if let Ok(info) = fs::read_to_string(iso_dir.join(".disk/info")) {
+            // e.g. Ubuntu 24.04.2 LTS "Noble Numbat" - Release amd64 (20250215)
+            if let Some(arch) = info.split_whitespace().find_map(Architecture::from_name) {
+                return Ok(arch);
+            }
+        }
+        
+        let configured = &self.config.metadata.architecture;
+        Architecture::from_name(configured)
+            .ok_or_else(|| anyhow::anyhow!("Unknown architecture in configuration: {}", configured))
+    }
+    
+    /// Copy the configured binaries from `release_dir`, refusing all of
+    /// them if any is built for another architecture than `target`
+    fn copy_binaries(&self, release_dir: &Path, bin_dir: &Path, target: Architecture) -> Result<()> {
+        let mut binaries = Vec::new();
+        let mut mismatched = Vec::new();
         for binary_name in &self.config.components.include_binaries {
             let src = release_dir.join(binary_name);
-            if src.exists() {
-                let dst = bin_dir.join(binary_name);
-                fs::copy(&src, &dst)
-                    .context(format!("Failed to copy {}", binary_name))?;
-                
-                // Make executable
-                #[cfg(unix)]
-                {
-                    use std::os::unix::fs::PermissionsExt;
-                    let mut perms = fs::metadata(&dst)?.permissions();
-                    perms.set_mode(0o755);
-                    fs::set_permissions(&dst, perms)?;
-                }
-            } else {
+            if !src.exists() {
                 eprintln!("Warning: Binary not found: {}", binary_name);
+                continue;
+            }
+            let arch = elf::read_architecture(&src)?;
+            if arch != target {
+                mismatched.push(format!("{} ({})", binary_name, arch));
+            }
+            binaries.push((binary_name, src));
+        }
+        
+        if !mismatched.is_empty() {
+            anyhow::bail!(
+                "Refusing to inject binaries built for the wrong architecture: {}. The ISO is {}; rebuild with: cargo build --release --target {}",
+                mismatched.join(", "),
+                target,
+                target.rust_target()
+            );
+        }
+        
+        for (binary_name, src) in binaries {
+            let dst = bin_dir.join(binary_name);
+            fs::copy(&src, &dst)
+                .context(format!("Failed to copy {}", binary_name))?;
+            
+            // Make executable
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                let mut perms = fs::metadata(&dst)?.permissions();
+                perms.set_mode(0o755);
+                fs::set_permissions(&dst, perms)?;
+            }
+            
+            if self.config.components.strip_debug {
+                self.strip_debug(&dst);
             }
         }
         
         Ok(())
     }
     
+    /// Strip debug symbols in place; a failure leaves the binary as is
+    fn strip_debug(&self, binary: &Path) {
+        match Command::new("strip").arg("--strip-debug").arg(binary).output() {
+            Ok(output) if output.status.success() => {}
+            Ok(output) => eprintln!(
+                "Warning: Failed to strip {}: {}",
+                binary.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+            Err(e) => eprintln!("Warning: Failed to run strip on {}: {}", binary.display(), e),
+        }
+    }
+    
     /// Inject source code into ISO
     pub fn inject_source(&self, iso_dir: &Path) -> Result<()> {
         if !self.config.components.include_source {
@@ -262,10 +321,19 @@ echo "  {} Installer"
 echo "  Version: {}"
 echo "==========================================="
 
+# Unpack the compressed payload
+PAYLOAD_DIR=/cdrom/hecateos
+if [ -f /cdrom/hecateos/{payload} ]; then
+    echo "Unpacking HecateOS payload..."
+    PAYLOAD_DIR=$(mktemp -d)
+    trap 'rm -rf "$PAYLOAD_DIR"' EXIT
+    tar -xf /cdrom/hecateos/{payload} -C "$PAYLOAD_DIR"
+fi
+
 # Install binaries
 echo "Installing HecateOS components..."
-if [ -d /cdrom/hecateos/bin ]; then
-    sudo cp -v /cdrom/hecateos/bin/* /usr/local/bin/
+if [ -d "$PAYLOAD_DIR/bin" ]; then
+    sudo cp -v "$PAYLOAD_DIR"/bin/* /usr/local/bin/
     sudo chmod +x /usr/local/bin/hecate*
 fi
 
@@ -302,7 +370,8 @@ echo "Please reboot to complete setup."
             self.config.metadata.version,
             self.config.optimizations.cpu_governor,
             self.config.optimizations.cpu_governor,
-            self.config.branding.motd
+            self.config.branding.motd,
+            payload = crate::payload::archive_name(self.config.compression.algorithm),
         )
     }
     
@@ -364,4 +433,58 @@ WantedBy=multi-user.target"#;
         // Add HecateOS branding to isolinux menu
         content.replace("Ubuntu", &self.config.metadata.name)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf::tests::fixture;
+
+    fn injector(binaries: &[&str]) -> ComponentInjector {
+        let mut config = HecateConfig::default();
+        config.components.include_binaries = binaries.iter().map(|name| name.to_string()).collect();
+        ComponentInjector::new(config)
+    }
+
+    #[test]
+    fn test_target_architecture() {
+        let dir = tempfile::tempdir().unwrap();
+        let injector = injector(&[]);
+        assert_eq!(injector.target_architecture(dir.path()).unwrap(), Architecture::Amd64);
+
+        fs::create_dir_all(dir.path().join(".disk")).unwrap();
+        fs::write(dir.path().join(".disk/info"), "Ubuntu 24.04.2 LTS \"Noble Numbat\" - Release arm64 (20250215)").unwrap();
+        assert_eq!(injector.target_architecture(dir.path()).unwrap(), Architecture::Arm64);
+    }
+
+    #[test]
+    fn test_copy_binaries_checks_architecture() {
+        let dir = tempfile::tempdir().unwrap();
+        let release = dir.path().join("release");
+        let bin = dir.path().join("bin");
+        fs::create_dir_all(&release).unwrap();
+        fs::create_dir_all(&bin).unwrap();
+        fs::write(release.join("hecated"), fixture(2, false, 0x3E)).unwrap();
+        fs::write(release.join("hecate-monitor"), fixture(2, false, 0xB7)).unwrap();
+
+        let injector = injector(&["hecated", "hecate-monitor"]);
+        let error = injector.copy_binaries(&release, &bin, Architecture::Amd64).unwrap_err().to_string();
+        assert!(error.contains("hecate-monitor (arm64)"), "{}", error);
+        assert!(error.contains("--target x86_64-unknown-linux-gnu"), "{}", error);
+        // Nothing is injected when any binary is wrong
+        assert!(!bin.join("hecated").exists());
+
+        injector.copy_binaries(&release, &bin, Architecture::Arm64).unwrap_err();
+        fs::write(release.join("hecate-monitor"), fixture(2, false, 0x3E)).unwrap();
+        injector.copy_binaries(&release, &bin, Architecture::Amd64).unwrap();
+        assert!(bin.join("hecated").exists() && bin.join("hecate-monitor").exists());
+    }
+
+    #[test]
+    fn test_rejects_non_elf_binaries() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("hecated"), "#!/bin/sh\nexit 0\n").unwrap();
+        let error = injector(&["hecated"]).copy_binaries(dir.path(), dir.path(), Architecture::Amd64).unwrap_err();
+        assert!(format!("{:#}", error).contains("not an ELF binary"));
+    }
+}
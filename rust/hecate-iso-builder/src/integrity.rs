@@ -0,0 +1,285 @@
+//! Checksums and signatures for built ISOs
+//!
+//! Each ISO gets `<iso>.sha256` and `<iso>.blake3` sidecars in the
+//! `sha256sum`/`b3sum` format, so they also work with `sha256sum -c`. The
+//! optional `<iso>.sig` is a hecate-sign manifest over those sidecars;
+//! signing the small sidecars instead of the image keeps multi-gigabyte
+//! ISOs out of memory, and the sidecars pin the image contents.
+
+use anyhow::{bail, Context, Result};
+use hecate_sign::{HashPolicy, KeyPair, SignatureManifest, SignaturePurpose, TrustStore};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Trust store consulted when no trusted key is given
+pub const TRUST_STORE_PATH: &str = "/etc/hecate/trust.json";
+
+const SIGNER_NAME: &str = "HecateOS ISO Builder";
+
+/// Digests of an ISO image, hex encoded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checksums {
+    pub sha256: String,
+    pub blake3: String,
+}
+
+/// Hash `path` with SHA256 and BLAKE3 in one pass
+pub fn compute(path: &Path) -> Result<Checksums> {
+    let mut file = File::open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut sha256 = Sha256::new();
+    let mut blake3 = blake3::Hasher::new();
+    let mut buffer = vec![0u8; 1 << 20];
+
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        sha256.update(&buffer[..read]);
+        blake3.update(&buffer[..read]);
+    }
+
+    Ok(Checksums {
+        sha256: hex::encode(sha256.finalize()),
+        blake3: blake3.finalize().to_hex().to_string(),
+    })
+}
+
+/// `<iso><suffix>`, e.g. `hecateos.iso.sha256`
+pub fn sidecar_path(iso: &Path, suffix: &str) -> PathBuf {
+    let mut path = iso.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+fn file_name(iso: &Path) -> Result<String> {
+    iso.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| anyhow::anyhow!("{} has no file name", iso.display()))
+}
+
+/// One `sha256sum`-style line: digest, two spaces, file name
+pub fn sidecar_line(digest: &str, file_name: &str) -> String {
+    format!("{}  {}\n", digest, file_name)
+}
+
+/// Digest and file name from a sidecar written by [`sidecar_line`]
+pub fn parse_sidecar(content: &str) -> Result<(String, String)> {
+    let line = content.lines().next().unwrap_or_default();
+    let (digest, name) = line.split_once("  ")
+        .ok_or_else(|| anyhow::anyhow!("Malformed checksum line: {:?}", line))?;
+    // sha256sum marks binary mode with a leading *
+    let name = name.strip_prefix('*').unwrap_or(name);
+    if digest.is_empty() || !digest.bytes().all(|b| b.is_ascii_hexdigit()) {
+        bail!("Malformed checksum: {:?}", digest);
+    }
+    Ok((digest.to_ascii_lowercase(), name.to_string()))
+}
+
+/// Write the `.sha256` and `.blake3` sidecars next to `iso`
+pub fn write_sidecars(iso: &Path) -> Result<Checksums> {
+    let checksums = compute(iso)?;
+    let name = file_name(iso)?;
+    std::fs::write(sidecar_path(iso, ".sha256"), sidecar_line(&checksums.sha256, &name))?;
+    std::fs::write(sidecar_path(iso, ".blake3"), sidecar_line(&checksums.blake3, &name))?;
+    Ok(checksums)
+}
+
+/// Check `iso` against its sidecars; `Ok(false)` when there are none
+pub fn verify_sidecars(iso: &Path) -> Result<bool> {
+    let sha256_path = sidecar_path(iso, ".sha256");
+    let blake3_path = sidecar_path(iso, ".blake3");
+    if !sha256_path.exists() && !blake3_path.exists() {
+        return Ok(false);
+    }
+
+    let checksums = compute(iso)?;
+    let name = file_name(iso)?;
+    for (path, actual) in [(sha256_path, &checksums.sha256), (blake3_path, &checksums.blake3)] {
+        if !path.exists() {
+            continue;
+        }
+        let (expected, listed) = parse_sidecar(&std::fs::read_to_string(&path)?)?;
+        if listed != name {
+            bail!("{} lists {}, not {}", path.display(), listed, name);
+        }
+        if &expected != actual {
+            bail!("Checksum mismatch in {}: expected {}, got {}", path.display(), expected, actual);
+        }
+    }
+    Ok(true)
+}
+
+/// Sign the sidecars of `iso` into `<iso>.sig`
+pub fn sign(iso: &Path, private_key: &Path, public_key: &Path) -> Result<PathBuf> {
+    let key_pair = KeyPair::load(private_key, public_key)?;
+    let name = file_name(iso)?;
+    let base = iso.parent().unwrap_or(Path::new("."));
+
+    let manifest = hecate_sign::sign_files(
+        base,
+        &[format!("{}.sha256", name), format!("{}.blake3", name)],
+        &key_pair,
+        SIGNER_NAME.to_string(),
+        SignaturePurpose::ISO,
+        HashPolicy::default(),
+    )?;
+
+    let sig_path = sidecar_path(iso, ".sig");
+    std::fs::write(&sig_path, serde_json::to_string_pretty(&manifest)?)?;
+    Ok(sig_path)
+}
+
+/// Verify `<iso>.sig`, if present, and that its signer is trusted
+///
+/// The signer must match `trusted_key` when one is given, otherwise it must
+/// be in the trust store with the same public key; the manifest's key id
+/// alone proves nothing. Returns the signing key id, or `None` when the ISO
+/// is unsigned.
+pub fn verify_signature(iso: &Path, trusted_key: Option<&Path>, trust_store: &Path) -> Result<Option<String>> {
+    let sig_path = sidecar_path(iso, ".sig");
+    if !sig_path.exists() {
+        return Ok(None);
+    }
+
+    let manifest = SignatureManifest::load(&sig_path)?;
+
+    let trusted = match trusted_key {
+        Some(path) => {
+            let key = std::fs::read(path)
+                .with_context(|| format!("Failed to read trusted key {}", path.display()))?;
+            hex::encode(key) == manifest.signer.public_key
+        }
+        None => {
+            let store = TrustStore::load(trust_store)?;
+            store.is_trusted(&manifest.signer.key_id)
+                && store.key(&manifest.signer.key_id).is_some_and(|key| key.public_key == manifest.signer.public_key)
+        }
+    };
+    if !trusted {
+        bail!("{} is signed by untrusted key {}", iso.display(), manifest.signer.key_id);
+    }
+
+    // The signature only covers the sidecars, so they must cover the ISO
+    let name = file_name(iso)?;
+    for suffix in [".sha256", ".blake3"] {
+        if !manifest.files.iter().any(|file| file.path == format!("{}{}", name, suffix)) {
+            bail!("{} does not cover {}{}", sig_path.display(), name, suffix);
+        }
+    }
+    if !verify_sidecars(iso)? {
+        bail!("{} is signed but its checksum files are missing", iso.display());
+    }
+
+    let base = iso.parent().unwrap_or(Path::new("."));
+    if !hecate_sign::verify_manifest(&manifest, base)? {
+        bail!("Invalid signature in {}", sig_path.display());
+    }
+    Ok(Some(manifest.signer.key_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn keys(dir: &Path) -> (PathBuf, PathBuf) {
+        let (private, public) = (dir.join("test.key"), dir.join("test.pub"));
+        KeyPair::generate().save(&private, &public).unwrap();
+        (private, public)
+    }
+
+    #[test]
+    fn test_sidecar_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let iso = dir.path().join("hecateos.iso");
+        fs::write(&iso, b"abc").unwrap();
+        write_sidecars(&iso).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dir.path().join("hecateos.iso.sha256")).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad  hecateos.iso\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("hecateos.iso.blake3")).unwrap(),
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85  hecateos.iso\n"
+        );
+
+        assert!(parse_sidecar("ABCD *x.iso\n").is_err());
+        assert_eq!(parse_sidecar("ABCD  *x.iso\n").unwrap(), ("abcd".to_string(), "x.iso".to_string()));
+        assert!(parse_sidecar("xyz  x.iso").is_err());
+    }
+
+    #[test]
+    fn test_tampered_iso_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let iso = dir.path().join("hecateos.iso");
+        assert!(!verify_sidecars(&iso).unwrap());
+
+        fs::write(&iso, vec![7u8; 5000]).unwrap();
+        write_sidecars(&iso).unwrap();
+        assert!(verify_sidecars(&iso).unwrap());
+
+        let mut data = fs::read(&iso).unwrap();
+        data[4000] ^= 1;
+        fs::write(&iso, data).unwrap();
+        assert!(verify_sidecars(&iso).unwrap_err().to_string().starts_with("Checksum mismatch"));
+    }
+
+    #[test]
+    fn test_signature() {
+        let dir = tempfile::tempdir().unwrap();
+        let (private, public) = keys(dir.path());
+        let store = dir.path().join("trust.json");
+        let iso = dir.path().join("hecateos.iso");
+        fs::write(&iso, b"iso contents").unwrap();
+        assert_eq!(verify_signature(&iso, Some(&public), &store).unwrap(), None);
+
+        write_sidecars(&iso).unwrap();
+        sign(&iso, &private, &public).unwrap();
+        assert!(verify_signature(&iso, Some(&public), &store).unwrap().is_some());
+
+        // Not in the (empty) trust store, and not the key we trust
+        assert!(verify_signature(&iso, None, &store).is_err());
+        let other_dir = dir.path().join("other");
+        fs::create_dir(&other_dir).unwrap();
+        let (_, other) = keys(&other_dir);
+        assert!(verify_signature(&iso, Some(&other), &store).unwrap_err().to_string().contains("untrusted"));
+
+        // Rewriting the checksums to match a modified ISO breaks the signature
+        fs::write(&iso, b"iso c0ntents").unwrap();
+        write_sidecars(&iso).unwrap();
+        assert!(verify_signature(&iso, Some(&public), &store).is_err());
+    }
+
+    #[test]
+    fn test_trusted_key_id_with_foreign_key_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let (private, public) = keys(dir.path());
+        let other_dir = dir.path().join("other");
+        fs::create_dir(&other_dir).unwrap();
+        let (other_private, other_public) = keys(&other_dir);
+
+        let store_path = dir.path().join("trust.json");
+        let trusted = KeyPair::load(&private, &public).unwrap();
+        let mut store = TrustStore::load(&store_path).unwrap();
+        store.add_key("release".to_string(), &hecate_sign::Signer::verifying_key(&trusted)).unwrap();
+
+        let iso = dir.path().join("hecateos.iso");
+        fs::write(&iso, b"iso contents").unwrap();
+        write_sidecars(&iso).unwrap();
+        let sig = sign(&iso, &other_private, &other_public).unwrap();
+        assert!(verify_signature(&iso, None, &store_path).is_err());
+
+        // Claiming the trusted key's id does not make the foreign key trusted
+        let mut manifest = SignatureManifest::load(&sig).unwrap();
+        manifest.signer.key_id = trusted.key_id();
+        fs::write(&sig, serde_json::to_string_pretty(&manifest).unwrap()).unwrap();
+        let error = verify_signature(&iso, None, &store_path).unwrap_err();
+        assert!(error.to_string().contains("untrusted"), "{error:#}");
+    }
+}
+
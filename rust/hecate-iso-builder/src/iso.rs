@@ -29,21 +29,18 @@ impl IsoManager {
         // Try native extraction first
         use crate::iso_extractor;
         
-        match iso_extractor::extract_iso(iso_path, output_dir) {
+        let native_error = match iso_extractor::extract_iso(iso_path, output_dir) {
             Ok(_) => {
                 progress.set_message("✅ ISO extracted successfully with native Rust!");
                 progress.inc(100);
                 return Ok(());
             }
-            Err(e) => {
-                // Native extraction failed, try external tools
-                progress.set_message("Native extraction failed, trying external tools...");
-                eprintln!("Native extraction error: {}", e);
-            }
-        }
+            Err(e) => e,
+        };
         
-        // Fallback to external tools
-        progress.set_message("Trying external extraction tools...");
+        // Only images the native reader cannot parse get here
+        progress.set_message("Native extraction failed, trying 7z...");
+        eprintln!("Native extraction error: {:#}", native_error);
         
         // Create mount point
         let mount_dir = output_dir.parent()
@@ -69,25 +66,22 @@ impl IsoManager {
         if !success {
             // All automatic extraction methods failed
             eprintln!("\n╔════════════════════════════════════════════════════════════╗");
-            eprintln!("║         ISO Extraction Tool Required                      ║");
+            eprintln!("║         ISO Could Not Be Extracted                        ║");
             eprintln!("╚════════════════════════════════════════════════════════════╝");
             eprintln!("");
-            eprintln!("📝 Note: While HecateOS can CREATE ISOs natively in Rust,");
-            eprintln!("   extracting existing ISOs (like Ubuntu) still requires a tool.");
+            eprintln!("📝 The native extractor could not read this ISO:");
+            eprintln!("   {:#}", native_error);
             eprintln!("");
-            eprintln!("🔧 Quick fix: Install 7z (recommended):");
+            eprintln!("🔧 7z can extract images the native reader does not support:");
             eprintln!("   {}", "sudo apt-get install p7zip-full".bright_yellow());
             eprintln!("");
-            eprintln!("📚 Why? Extracting ISOs requires reading complex filesystem");
-            eprintln!("   structures. Native extraction is coming in a future update!");
-            eprintln!("");
             eprintln!("Alternative manual extraction:");
             eprintln!("  sudo mkdir -p {}", mount_dir.display());
             eprintln!("  sudo mount -o loop {} {}", iso_path.display(), mount_dir.display());
             eprintln!("  cp -r {}/* {}", mount_dir.display(), output_dir.display());
             eprintln!("  sudo umount {}", mount_dir.display());
             
-            return Err(anyhow::anyhow!("ISO extraction requires 7z. Install with: sudo apt-get install p7zip-full"));
+            return Err(native_error.context("Native ISO extraction failed and 7z could not extract the image"));
         }
         
         progress.set_message("Copying hidden files...");
@@ -122,7 +116,7 @@ impl IsoManager {
         use crate::iso_native::NativeIsoBuilder;
         
         let mut builder = NativeIsoBuilder::new(volume_id.to_string());
-        match builder.add_directory_tree(source_dir, "/") {
+        match builder.add_directory_tree(source_dir, "/").and_then(|_| builder.detect_boot(source_dir)) {
             Ok(_) => {
                 match builder.build(output_iso) {
                     Ok(_) => {
@@ -1,39 +1,96 @@
 //! Native ISO 9660 extractor in pure Rust
 //! This module can extract ISO files without external dependencies
+//!
+//! Names come from Rock Ridge when the image has it, then from the Joliet
+//! tree, and the plain ISO 9660 identifiers as a last resort.
 
 use std::fs::{self, File};
 use std::io::{self, Read, Seek, SeekFrom, BufReader};
-use std::path::{Path, PathBuf};
-use anyhow::{Context, Result};
-use byteorder::{LittleEndian, ReadBytesExt};
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use anyhow::{bail, Context, Result};
+
+use crate::iso_native::SECTOR_SIZE;
+use crate::joliet;
 
-const SECTOR_SIZE: usize = 2048;
 const VOLUME_DESCRIPTOR_SECTOR: u64 = 16;
+/// Volume descriptors to scan before giving up on a terminator
+const MAX_VOLUME_DESCRIPTORS: u64 = 64;
 
-/// ISO 9660 Volume Descriptor
-#[derive(Debug)]
-struct VolumeDescriptor {
-    type_code: u8,
-    identifier: [u8; 5],
-    version: u8,
-    volume_space_size: u32,
-    root_directory_record: DirectoryRecord,
+const FLAG_DIRECTORY: u8 = 0x02;
+const FLAG_MULTI_EXTENT: u8 = 0x80;
+
+/// Continuation areas followed for one record before assuming a loop
+const MAX_CONTINUATIONS: usize = 32;
+
+/// Which directory tree names are taken from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tree {
+    /// Primary tree with Rock Ridge names, permissions and symlinks
+    RockRidge,
+    /// Joliet tree; names keep their case and length
+    Joliet,
+    /// Primary tree with ISO 9660 identifiers only
+    Primary,
 }
 
 /// ISO 9660 Directory Record
 #[derive(Debug, Clone)]
 struct DirectoryRecord {
-    length: u8,
     location: u32,
     data_length: u32,
-    is_directory: bool,
-    file_identifier: String,
+    flags: u8,
+    file_identifier: Vec<u8>,
+    system_use: Vec<u8>,
+}
+
+impl DirectoryRecord {
+    fn is_directory(&self) -> bool {
+        self.flags & FLAG_DIRECTORY != 0
+    }
+
+    /// `.` and `..` use the single bytes 0 and 1 as identifiers
+    fn is_dot(&self) -> bool {
+        self.file_identifier == [0] || self.file_identifier == [1]
+    }
+}
+
+/// What the Rock Ridge entries of a record say about it
+#[derive(Debug, Default, PartialEq, Eq)]
+struct RockRidge {
+    name: Option<String>,
+    mode: Option<u32>,
+    symlink: Option<String>,
+    /// Directory moved here from deeper in the tree (RE); listed again
+    /// where it belongs
+    relocated: bool,
+    /// Where a relocated directory really lives (CL)
+    child_link: Option<u32>,
+}
+
+/// What a directory entry becomes on disk
+#[derive(Debug)]
+enum EntryKind {
+    Directory { location: u32, size: u32 },
+    /// Extents as (location, length); more than one for multi-extent files
+    File { extents: Vec<(u32, u32)> },
+    Symlink { target: String },
+}
+
+#[derive(Debug)]
+struct Entry {
+    name: String,
+    kind: EntryKind,
+    mode: Option<u32>,
 }
 
 /// Native ISO extractor
 pub struct IsoExtractor {
     file: BufReader<File>,
-    volume_descriptor: Option<VolumeDescriptor>,
+    tree: Tree,
+    root: DirectoryRecord,
+    /// Bytes to skip at the start of every system use area (SP)
+    susp_skip: usize,
 }
 
 impl IsoExtractor {
@@ -41,202 +98,509 @@ impl IsoExtractor {
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
         let file = File::open(path.as_ref())
             .context("Failed to open ISO file")?;
-        
+
+        let mut file = BufReader::new(file);
+        let (primary, joliet) = Self::read_volume_descriptors(&mut file)?;
         let mut extractor = IsoExtractor {
-            file: BufReader::new(file),
-            volume_descriptor: None,
+            file,
+            tree: Tree::Primary,
+            root: primary,
+            susp_skip: 0,
         };
-        
-        extractor.read_volume_descriptor()?;
+
+        if let Some(skip) = extractor.detect_rock_ridge()? {
+            extractor.tree = Tree::RockRidge;
+            extractor.susp_skip = skip;
+        } else if let Some(joliet) = joliet {
+            extractor.tree = Tree::Joliet;
+            extractor.root = joliet;
+        }
+
         Ok(extractor)
     }
-    
+
     /// Extract all files to the specified directory
     pub fn extract_all<P: AsRef<Path>>(&mut self, output_dir: P) -> Result<()> {
         let output_dir = output_dir.as_ref();
         fs::create_dir_all(output_dir)?;
-        
-        let root = self.volume_descriptor
-            .as_ref()
-            .ok_or_else(|| anyhow::anyhow!("No volume descriptor"))?
-            .root_directory_record
-            .clone();
-        
-        self.extract_directory(&root, output_dir, "")?;
-        Ok(())
-    }
-    
-    /// Read the primary volume descriptor
-    fn read_volume_descriptor(&mut self) -> Result<()> {
-        // Seek to volume descriptor location (sector 16)
-        self.file.seek(SeekFrom::Start(VOLUME_DESCRIPTOR_SECTOR * SECTOR_SIZE as u64))?;
-        
-        let mut buffer = vec![0u8; SECTOR_SIZE];
-        self.file.read_exact(&mut buffer)?;
-        
-        // Check volume descriptor type (1 = Primary)
-        if buffer[0] != 1 {
-            return Err(anyhow::anyhow!("Not a primary volume descriptor"));
+
+        // Directory permissions are applied last so read-only directories
+        // can still be filled
+        let mut directory_modes = Vec::new();
+        let mut pending = vec![(self.root.location, self.root.data_length, output_dir.to_path_buf())];
+        let mut visited = std::collections::HashSet::new();
+
+        while let Some((location, size, path)) = pending.pop() {
+            if !visited.insert(location) {
+                bail!("Directory at sector {} is listed twice", location);
+            }
+
+            for entry in self.read_directory(location, size)? {
+                let target = path.join(safe_name(&entry.name)?);
+                match entry.kind {
+                    // Anything already there must not redirect the write
+                    // outside the output directory
+                    EntryKind::Directory { .. } | EntryKind::File { .. } if is_symlink(&target) => {
+                        bail!("Refusing to extract {} through an existing symlink", target.display());
+                    }
+                    EntryKind::Directory { location, size } => {
+                        fs::create_dir_all(&target)
+                            .with_context(|| format!("Failed to create {}", target.display()))?;
+                        if let Some(mode) = entry.mode {
+                            directory_modes.push((target.clone(), mode));
+                        }
+                        pending.push((location, size, target));
+                    }
+                    EntryKind::File { extents } => {
+                        self.extract_file(&extents, &target)?;
+                        if let Some(mode) = entry.mode {
+                            fs::set_permissions(&target, fs::Permissions::from_mode(mode & 0o7777))?;
+                        }
+                    }
+                    EntryKind::Symlink { target: link } => {
+                        if fs::symlink_metadata(&target).is_ok() {
+                            fs::remove_file(&target)?;
+                        }
+                        std::os::unix::fs::symlink(&link, &target)
+                            .with_context(|| format!("Failed to create symlink {}", target.display()))?;
+                    }
+                }
+            }
         }
-        
-        // Check identifier "CD001"
-        if &buffer[1..6] != b"CD001" {
-            return Err(anyhow::anyhow!("Invalid ISO 9660 identifier"));
+
+        for (path, mode) in directory_modes.into_iter().rev() {
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode & 0o7777))?;
         }
-        
-        // Read volume space size (both endian at offset 80)
-        let volume_size = u32::from_le_bytes([
-            buffer[80], buffer[81], buffer[82], buffer[83]
-        ]);
-        
-        // Read root directory record (at offset 156, 34 bytes)
-        let root_dir = self.parse_directory_record(&buffer[156..190])?;
-        
-        self.volume_descriptor = Some(VolumeDescriptor {
-            type_code: buffer[0],
-            identifier: [buffer[1], buffer[2], buffer[3], buffer[4], buffer[5]],
-            version: buffer[6],
-            volume_space_size: volume_size,
-            root_directory_record: root_dir,
-        });
-        
         Ok(())
     }
-    
-    /// Parse a directory record from bytes
-    fn parse_directory_record(&self, data: &[u8]) -> Result<DirectoryRecord> {
-        if data.len() < 33 {
-            return Err(anyhow::anyhow!("Directory record too short"));
-        }
-        
-        let length = data[0];
-        if length == 0 {
-            return Err(anyhow::anyhow!("Invalid directory record length"));
+
+    /// Root records of the primary volume descriptor and, when present,
+    /// the Joliet supplementary one
+    fn read_volume_descriptors(file: &mut BufReader<File>) -> Result<(DirectoryRecord, Option<DirectoryRecord>)> {
+        let mut primary = None;
+        let mut joliet = None;
+        let mut buffer = vec![0u8; SECTOR_SIZE];
+
+        for sector in VOLUME_DESCRIPTOR_SECTOR..VOLUME_DESCRIPTOR_SECTOR + MAX_VOLUME_DESCRIPTORS {
+            file.seek(SeekFrom::Start(sector * SECTOR_SIZE as u64))?;
+            file.read_exact(&mut buffer)
+                .context("ISO ends before the volume descriptor set terminator")?;
+
+            // Check identifier "CD001"
+            if &buffer[1..6] != b"CD001" {
+                bail!("Invalid ISO 9660 identifier in sector {}", sector);
+            }
+
+            match buffer[0] {
+                1 if primary.is_none() => primary = Some(parse_directory_record(&buffer[156..190])?),
+                // Joliet is a supplementary descriptor with a UCS-2 escape sequence
+                2 if joliet.is_none() && matches!(&buffer[88..91], b"%/@" | b"%/C" | b"%/E") => {
+                    joliet = Some(parse_directory_record(&buffer[156..190])?);
+                }
+                255 => break,
+                _ => {}
+            }
         }
-        
-        // Location of extent (LBA)
-        let location = u32::from_le_bytes([
-            data[2], data[3], data[4], data[5]
-        ]);
-        
-        // Data length
-        let data_length = u32::from_le_bytes([
-            data[10], data[11], data[12], data[13]
-        ]);
-        
-        // File flags (bit 1 = directory)
-        let flags = data[25];
-        let is_directory = (flags & 0x02) != 0;
-        
-        // File identifier length
-        let fi_len = data[32] as usize;
-        
-        // File identifier
-        let file_identifier = if fi_len > 0 && data.len() > 33 {
-            let fi_end = (33 + fi_len).min(data.len());
-            String::from_utf8_lossy(&data[33..fi_end]).to_string()
+
+        let primary = primary.ok_or_else(|| anyhow::anyhow!("No primary volume descriptor"))?;
+        Ok((primary, joliet))
+    }
+
+    /// Bytes to skip before the SUSP entries of each record if the root
+    /// `.` record starts with an SP entry
+    fn detect_rock_ridge(&mut self) -> Result<Option<usize>> {
+        let data = self.read_extent(self.root.location, SECTOR_SIZE as u32)?;
+        let dot = parse_directory_record(&data)?;
+        let sp = &dot.system_use;
+        if sp.len() >= 7 && &sp[0..2] == b"SP" && sp[4..6] == [0xBE, 0xEF] {
+            Ok(Some(sp[6] as usize))
         } else {
-            String::new()
-        };
-        
-        Ok(DirectoryRecord {
-            length,
-            location,
-            data_length,
-            is_directory,
-            file_identifier,
-        })
-    }
-    
-    /// Extract a directory and its contents
-    fn extract_directory(&mut self, dir_record: &DirectoryRecord, base_path: &Path, rel_path: &str) -> Result<()> {
-        // Read directory contents
-        let sector_offset = dir_record.location as u64 * SECTOR_SIZE as u64;
-        self.file.seek(SeekFrom::Start(sector_offset))?;
-        
-        let mut dir_data = vec![0u8; dir_record.data_length as usize];
-        self.file.read_exact(&mut dir_data)?;
-        
+            Ok(None)
+        }
+    }
+
+    fn read_extent(&mut self, location: u32, length: u32) -> Result<Vec<u8>> {
+        self.file.seek(SeekFrom::Start(location as u64 * SECTOR_SIZE as u64))?;
+        let mut data = vec![0u8; length as usize];
+        self.file.read_exact(&mut data)
+            .with_context(|| format!("ISO ends inside the extent at sector {}", location))?;
+        Ok(data)
+    }
+
+    /// Entries of the directory at `location`, without `.` and `..`
+    fn read_directory(&mut self, location: u32, size: u32) -> Result<Vec<Entry>> {
+        let data = self.read_extent(location, size)?;
+        let mut entries = Vec::new();
+        let mut extents = Vec::new();
+
         let mut offset = 0;
-        while offset < dir_data.len() {
-            // Check if we've reached padding
-            if dir_data[offset] == 0 {
-                break;
-            }
-            
-            let record_len = dir_data[offset] as usize;
+        while offset < data.len() {
+            // Records never cross a sector; a zero length pads to the next one
+            let record_len = data[offset] as usize;
             if record_len == 0 {
-                break;
-            }
-            
-            let record_end = (offset + record_len).min(dir_data.len());
-            let record = self.parse_directory_record(&dir_data[offset..record_end])?;
-            
-            // Skip . and .. entries
-            if record.file_identifier == "\0" || record.file_identifier == "\x01" {
-                offset += record_len;
+                offset = (offset / SECTOR_SIZE + 1) * SECTOR_SIZE;
                 continue;
             }
-            
-            // Clean up file name (remove version suffix ;1)
-            let clean_name = record.file_identifier
-                .trim_end_matches(";1")
-                .to_string();
-            
-            let item_path = if rel_path.is_empty() {
-                clean_name.clone()
-            } else {
-                format!("{}/{}", rel_path, clean_name)
-            };
-            
-            let full_path = base_path.join(&clean_name);
-            
-            if record.is_directory {
-                // Create directory and recurse
-                fs::create_dir_all(&full_path)?;
-                self.extract_directory(&record, base_path, &item_path)?;
-            } else {
-                // Extract file
-                self.extract_file(&record, &full_path)?;
+            if offset + record_len > data.len() {
+                bail!("Directory record at sector {} runs past its extent", location);
             }
-            
+
+            let record = parse_directory_record(&data[offset..offset + record_len])?;
             offset += record_len;
+            if record.is_dot() {
+                continue;
+            }
+
+            // All but the last extent of a multi-extent file carry the flag
+            extents.push((record.location, record.data_length));
+            if record.flags & FLAG_MULTI_EXTENT != 0 {
+                continue;
+            }
+
+            if let Some(entry) = self.entry(&record, std::mem::take(&mut extents))? {
+                entries.push(entry);
+            }
         }
-        
-        Ok(())
+
+        Ok(entries)
     }
-    
+
+    /// Resolve a record into what gets written, or `None` for records
+    /// that only exist for Rock Ridge bookkeeping
+    fn entry(&mut self, record: &DirectoryRecord, extents: Vec<(u32, u32)>) -> Result<Option<Entry>> {
+        let rock_ridge = match self.tree {
+            Tree::RockRidge => self.rock_ridge(record)?,
+            _ => RockRidge::default(),
+        };
+        if rock_ridge.relocated {
+            return Ok(None);
+        }
+
+        let name = match (rock_ridge.name, self.tree) {
+            (Some(name), _) => name,
+            (None, Tree::Joliet) => strip_version(&joliet::decode(&record.file_identifier)).to_string(),
+            (None, _) => {
+                let name = String::from_utf8_lossy(&record.file_identifier).into_owned();
+                let name = strip_version(&name);
+                // "README." has an empty extension
+                name.strip_suffix('.').unwrap_or(name).to_string()
+            }
+        };
+
+        let kind = if let Some(target) = rock_ridge.symlink {
+            EntryKind::Symlink { target }
+        } else if let Some(location) = rock_ridge.child_link {
+            let data = self.read_extent(location, SECTOR_SIZE as u32)?;
+            let dot = parse_directory_record(&data)?;
+            EntryKind::Directory { location, size: dot.data_length }
+        } else if record.is_directory() {
+            EntryKind::Directory { location: record.location, size: record.data_length }
+        } else {
+            EntryKind::File { extents }
+        };
+
+        Ok(Some(Entry { name, kind, mode: rock_ridge.mode }))
+    }
+
+    /// Parse the SUSP entries of a record, following continuation areas
+    fn rock_ridge(&mut self, record: &DirectoryRecord) -> Result<RockRidge> {
+        let mut result = RockRidge::default();
+        let mut name = Vec::new();
+        let mut symlink: Vec<String> = Vec::new();
+        let mut component_continues = false;
+
+        let mut area = record.system_use.get(self.susp_skip..).unwrap_or_default().to_vec();
+        let mut continuations = 0;
+        loop {
+            let mut continuation = None;
+            let mut offset = 0;
+            while offset + 4 <= area.len() {
+                let len = area[offset + 2] as usize;
+                if len < 4 || offset + len > area.len() {
+                    break;
+                }
+                let entry = &area[offset..offset + len];
+                offset += len;
+
+                match &entry[0..2] {
+                    b"CE" if len >= 28 => {
+                        continuation = Some((u32_at(entry, 4), u32_at(entry, 12), u32_at(entry, 20)));
+                    }
+                    // Flags 0x02 and 0x04 name . and .., which are skipped anyway
+                    b"NM" if len >= 5 && entry[4] & 0x06 == 0 => name.extend_from_slice(&entry[5..]),
+                    b"PX" if len >= 12 => result.mode = Some(u32_at(entry, 4)),
+                    b"SL" if len >= 5 => {
+                        let mut components = &entry[5..];
+                        while components.len() >= 2 {
+                            let (flags, component_len) = (components[0], components[1] as usize);
+                            let content = components.get(2..2 + component_len).unwrap_or_default();
+                            let part = match flags & 0x0E {
+                                0x02 => ".".to_string(),
+                                0x04 => "..".to_string(),
+                                0x08 => String::new(),
+                                _ => String::from_utf8_lossy(content).into_owned(),
+                            };
+                            match symlink.last_mut() {
+                                Some(last) if component_continues => last.push_str(&part),
+                                _ => symlink.push(part),
+                            }
+                            component_continues = flags & 0x01 != 0;
+                            components = &components[(2 + component_len).min(components.len())..];
+                        }
+                    }
+                    b"RE" => result.relocated = true,
+                    b"CL" if len >= 12 => result.child_link = Some(u32_at(entry, 4)),
+                    b"ST" => break,
+                    _ => {}
+                }
+            }
+
+            let Some((block, offset, length)) = continuation else { break };
+            continuations += 1;
+            if continuations > MAX_CONTINUATIONS {
+                bail!("Too many Rock Ridge continuation areas");
+            }
+            self.file.seek(SeekFrom::Start(block as u64 * SECTOR_SIZE as u64 + offset as u64))?;
+            area = vec![0u8; length as usize];
+            self.file.read_exact(&mut area)?;
+        }
+
+        if !name.is_empty() {
+            result.name = Some(String::from_utf8_lossy(&name).into_owned());
+        }
+        if !symlink.is_empty() {
+            // A leading root component joins into the leading /
+            result.symlink = Some(match symlink.as_slice() {
+                [root] if root.is_empty() => "/".to_string(),
+                _ => symlink.join("/"),
+            });
+        }
+        Ok(result)
+    }
+
     /// Extract a single file
-    fn extract_file(&mut self, file_record: &DirectoryRecord, output_path: &Path) -> Result<()> {
-        // Seek to file data
-        let sector_offset = file_record.location as u64 * SECTOR_SIZE as u64;
-        self.file.seek(SeekFrom::Start(sector_offset))?;
-        
+    fn extract_file(&mut self, extents: &[(u32, u32)], output_path: &Path) -> Result<()> {
         // Create output file
-        let mut output = File::create(output_path)?;
-        
-        // Copy file data
-        let mut remaining = file_record.data_length as usize;
-        let mut buffer = vec![0u8; SECTOR_SIZE.min(remaining)];
-        
-        while remaining > 0 {
-            let to_read = buffer.len().min(remaining);
-            self.file.read_exact(&mut buffer[..to_read])?;
-            io::copy(&mut &buffer[..to_read], &mut output)?;
-            remaining -= to_read;
+        let mut output = File::create(output_path)
+            .with_context(|| format!("Failed to create {}", output_path.display()))?;
+
+        for &(location, length) in extents {
+            self.file.seek(SeekFrom::Start(location as u64 * SECTOR_SIZE as u64))?;
+            let copied = io::copy(&mut (&mut self.file).take(length as u64), &mut output)?;
+            if copied != length as u64 {
+                bail!("ISO ends inside {}", output_path.display());
+            }
         }
-        
+
         Ok(())
     }
 }
 
+fn is_symlink(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink())
+}
+
+/// Parse a directory record from bytes
+fn parse_directory_record(data: &[u8]) -> Result<DirectoryRecord> {
+    if data.len() < 34 || (data[0] as usize) < 34 || data[0] as usize > data.len() {
+        bail!("Invalid directory record");
+    }
+    let data = &data[..data[0] as usize];
+
+    // File identifier, padded to an even offset before the system use area
+    let fi_len = data[32] as usize;
+    let fi_end = 33 + fi_len;
+    if fi_end > data.len() {
+        bail!("Directory record identifier runs past the record");
+    }
+    let system_use_start = (fi_end + (fi_len + 1) % 2).min(data.len());
+
+    Ok(DirectoryRecord {
+        location: u32_at(data, 2),
+        data_length: u32_at(data, 10),
+        flags: data[25],
+        file_identifier: data[33..fi_end].to_vec(),
+        system_use: data[system_use_start..].to_vec(),
+    })
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+/// `name` without the `;1` version suffix
+fn strip_version(name: &str) -> &str {
+    match name.rsplit_once(';') {
+        Some((base, version)) if version.bytes().all(|b| b.is_ascii_digit()) => base,
+        _ => name,
+    }
+}
+
+/// Refuse names that would escape the output directory
+fn safe_name(name: &str) -> Result<&str> {
+    if name.is_empty() || name == "." || name == ".." || name.contains('/') || name.contains('\0') {
+        bail!("Refusing to extract unsafe file name {:?}", name);
+    }
+    Ok(name)
+}
+
 /// Simple extraction function for direct use
 pub fn extract_iso<P: AsRef<Path>, Q: AsRef<Path>>(iso_path: P, output_dir: Q) -> Result<()> {
     println!("🔓 Extracting ISO with native Rust implementation...");
-    
+
     let mut extractor = IsoExtractor::open(iso_path)?;
     extractor.extract_all(output_dir)?;
-    
+
     println!("✅ ISO extracted successfully!");
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iso_native::NativeIsoBuilder;
+    use std::collections::BTreeMap;
+    use std::os::unix::fs::MetadataExt;
+    use std::path::PathBuf;
+    use walkdir::WalkDir;
+
+    /// Relative path to (mode, contents or symlink target) for every entry
+    fn snapshot(root: &Path) -> BTreeMap<String, (u32, Vec<u8>)> {
+        WalkDir::new(root)
+            .min_depth(1)
+            .into_iter()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                let relative = entry.path().strip_prefix(root).unwrap().display().to_string();
+                let metadata = entry.path().symlink_metadata().unwrap();
+                let contents = if entry.file_type().is_symlink() {
+                    fs::read_link(entry.path()).unwrap().display().to_string().into_bytes()
+                } else if entry.file_type().is_file() {
+                    fs::read(entry.path()).unwrap()
+                } else {
+                    Vec::new()
+                };
+                // Symlink permissions are not meaningful
+                let mode = if entry.file_type().is_symlink() { 0 } else { metadata.mode() };
+                (relative, (mode, contents))
+            })
+            .collect()
+    }
+
+    fn build_iso(tree: &Path) -> PathBuf {
+        let output = tree.parent().unwrap().join("out.iso");
+        let mut builder = NativeIsoBuilder::new("hecateos".to_string());
+        builder.add_directory_tree(tree, "/").unwrap();
+        builder.build(&output).unwrap();
+        output
+    }
+
+    fn sample_tree(tree: &Path) {
+        fs::create_dir_all(tree.join("casper")).unwrap();
+        fs::create_dir_all(tree.join("a/b/c/d/e/f/g/h/i")).unwrap();
+        fs::create_dir_all(tree.join(".disk")).unwrap();
+        fs::write(tree.join(".disk/info"), b"HecateOS 24.04\n").unwrap();
+        fs::write(tree.join("LongMixedCase.TarGz"), b"archive contents").unwrap();
+        fs::write(tree.join("a/b/c/d/e/f/g/h/i/deep.txt"), b"deep").unwrap();
+        fs::write(tree.join("casper/empty"), b"").unwrap();
+        fs::write(tree.join("n".repeat(200)), b"long name").unwrap();
+
+        let big: Vec<u8> = (0..5 * SECTOR_SIZE as u32 + 17).map(|i| (i % 253) as u8).collect();
+        fs::write(tree.join("casper/filesystem.squashfs"), &big).unwrap();
+
+        fs::write(tree.join("casper/install.sh"), b"#!/bin/sh\n").unwrap();
+        fs::set_permissions(tree.join("casper/install.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+        fs::set_permissions(tree.join("a/b"), fs::Permissions::from_mode(0o700)).unwrap();
+
+        std::os::unix::fs::symlink("casper/install.sh", tree.join("install")).unwrap();
+        std::os::unix::fs::symlink("/usr/lib/../share/./doc", tree.join("casper/doc")).unwrap();
+        std::os::unix::fs::symlink("t".repeat(300), tree.join("casper/far")).unwrap();
+    }
+
+    #[test]
+    fn test_extracts_builder_iso_with_rock_ridge() {
+        let work = tempfile::tempdir().unwrap();
+        let tree = work.path().join("tree");
+        sample_tree(&tree);
+        let iso = build_iso(&tree);
+
+        let mut extractor = IsoExtractor::open(&iso).unwrap();
+        assert_eq!(extractor.tree, Tree::RockRidge);
+        let output = work.path().join("extracted");
+        extractor.extract_all(&output).unwrap();
+
+        assert_eq!(snapshot(&output), snapshot(&tree));
+    }
+
+    #[test]
+    fn test_refuses_to_write_through_symlinks() {
+        let work = tempfile::tempdir().unwrap();
+        let tree = work.path().join("tree");
+        sample_tree(&tree);
+        let iso = build_iso(&tree);
+        let outside = work.path().join("outside");
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(outside.join("victim"), b"original").unwrap();
+
+        // A directory in the ISO over a symlink to a directory
+        let output = work.path().join("through-directory");
+        fs::create_dir_all(&output).unwrap();
+        std::os::unix::fs::symlink(&outside, output.join("casper")).unwrap();
+        let error = IsoExtractor::open(&iso).unwrap().extract_all(&output).unwrap_err();
+        assert!(error.to_string().contains("existing symlink"), "{error:#}");
+        assert_eq!(fs::read_dir(&outside).unwrap().count(), 1);
+
+        // A file in the ISO over a symlink to a file
+        let output = work.path().join("through-file");
+        fs::create_dir_all(&output).unwrap();
+        std::os::unix::fs::symlink(outside.join("victim"), output.join("LongMixedCase.TarGz")).unwrap();
+        assert!(IsoExtractor::open(&iso).unwrap().extract_all(&output).is_err());
+        assert_eq!(fs::read(outside.join("victim")).unwrap(), b"original");
+    }
+
+    #[test]
+    fn test_joliet_and_primary_fallbacks() {
+        let work = tempfile::tempdir().unwrap();
+        let tree = work.path().join("tree");
+        fs::create_dir_all(tree.join("Boot/GRUB")).unwrap();
+        fs::write(tree.join("Boot/GRUB/grub.cfg"), b"set timeout=5\n").unwrap();
+        fs::write(tree.join("LongMixedCase.TarGz"), b"archive contents").unwrap();
+        let iso = build_iso(&tree);
+
+        // Without Rock Ridge the Joliet tree still has the real names
+        let mut extractor = IsoExtractor::open(&iso).unwrap();
+        let (primary, joliet) = IsoExtractor::read_volume_descriptors(&mut extractor.file).unwrap();
+        extractor.tree = Tree::Joliet;
+        extractor.root = joliet.unwrap();
+        let output = work.path().join("joliet");
+        extractor.extract_all(&output).unwrap();
+        assert_eq!(fs::read(output.join("Boot/GRUB/grub.cfg")).unwrap(), b"set timeout=5\n");
+        assert_eq!(fs::read(output.join("LongMixedCase.TarGz")).unwrap(), b"archive contents");
+
+        // The primary tree alone gives ISO 9660 names without versions
+        extractor.tree = Tree::Primary;
+        extractor.root = primary;
+        let output = work.path().join("primary");
+        extractor.extract_all(&output).unwrap();
+        assert_eq!(fs::read(output.join("BOOT/GRUB/GRUB.CFG")).unwrap(), b"set timeout=5\n");
+        assert_eq!(fs::read(output.join("LONGMIXE.TAR")).unwrap(), b"archive contents");
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(strip_version("GRUB.CFG;1"), "GRUB.CFG");
+        assert_eq!(strip_version("a;b"), "a;b");
+        assert!(safe_name("..").is_err());
+        assert!(safe_name("a/b").is_err());
+        assert!(safe_name("").is_err());
+        assert_eq!(safe_name("vmlinuz").unwrap(), "vmlinuz");
+    }
+
+    #[test]
+    fn test_rejects_non_iso() {
+        let work = tempfile::tempdir().unwrap();
+        let path = work.path().join("not.iso");
+        fs::write(&path, vec![0u8; 40 * SECTOR_SIZE]).unwrap();
+        assert!(IsoExtractor::open(&path).is_err());
+    }
+}
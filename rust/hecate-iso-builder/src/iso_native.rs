@@ -3,15 +3,24 @@
 //! This module provides pure Rust ISO 9660 filesystem creation
 //! without any external dependencies.
 
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs::File;
-use std::io::{Write, Read, Seek, SeekFrom};
+use std::io::{self, Write, Read, Seek, SeekFrom};
+use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
-use anyhow::Result;
+use anyhow::{Context, Result};
 use walkdir::WalkDir;
-use chrono::{Utc, Datelike, Timelike};
+use chrono::{DateTime, Utc, Datelike, Timelike};
+use crate::eltorito::{self, BootEntry, HybridLayout};
+use crate::{fat, joliet, rockridge};
 
-const SECTOR_SIZE: usize = 2048;
+pub(crate) const SECTOR_SIZE: usize = 2048;
 const SYSTEM_AREA_SIZE: usize = 16 * SECTOR_SIZE; // 32KB reserved for boot
+/// Sector of the primary volume descriptor
+const PVD_SECTOR: u32 = 16;
+
+/// Where syslinux installs the isohybrid MBR on Debian-based systems
+const SYSTEM_ISOHDPFX: &str = "/usr/lib/ISOLINUX/isohdpfx.bin";
 
 /// ISO 9660 Volume Descriptor types
 #[repr(u8)]
@@ -30,19 +39,204 @@ pub struct NativeIsoBuilder {
     preparer: String,
     files: Vec<IsoFileEntry>,
     directories: Vec<IsoDirEntry>,
+    /// Index into `directories` by path
+    directory_index: HashMap<String, usize>,
+    boot: BootImages,
+    total_sectors: u32,
+    created: DateTime<Utc>,
+}
+
+/// El Torito boot images
+#[derive(Default)]
+struct BootImages {
+    /// Index into `files` of the no-emulation BIOS loader, e.g. isolinux.bin
+    bios: Option<usize>,
+    efi: Option<EfiImage>,
+    /// MBR boot code for booting from USB in BIOS mode
+    mbr_code: Option<Vec<u8>>,
+    catalog_sector: u32,
+}
+
+/// FAT image holding the EFI loader
+enum EfiImage {
+    /// A prebuilt image in the tree, e.g. Ubuntu's boot/grub/efi.img
+    File(usize),
+    /// An image built from the tree's EFI/BOOT directory, stored outside
+    /// the directory tree
+    Embedded { data: Vec<u8>, start_sector: u32 },
+}
+
+impl BootImages {
+    fn is_bootable(&self) -> bool {
+        self.bios.is_some() || self.efi.is_some()
+    }
+}
+
+fn sectors_for(size: u64) -> u32 {
+    size.div_ceil(SECTOR_SIZE as u64) as u32
+}
+
+/// `root` joined with `components`, matching each component regardless
+/// of case, if that directory exists
+fn find_ignore_case(root: &Path, components: &[&str]) -> Option<PathBuf> {
+    let mut dir = root.to_path_buf();
+    for component in components {
+        dir = std::fs::read_dir(&dir).ok()?
+            .flatten()
+            .find(|entry| entry.file_name().to_string_lossy().eq_ignore_ascii_case(component))?
+            .path();
+    }
+    dir.is_dir().then_some(dir)
 }
 
 struct IsoFileEntry {
     path: PathBuf,
+    /// Path in the ISO with its original case, e.g. `/boot/grub/grub.cfg`
     iso_path: String,
+    /// Index of the containing directory
+    dir: usize,
+    name: String,
     size: u64,
     start_sector: u32,
+    mode: u32,
+    symlink: Option<String>,
 }
 
 struct IsoDirEntry {
+    /// `/` for the root
     path: String,
-    parent: Option<String>,
-    start_sector: u32,
+    name: String,
+    parent: Option<usize>,
+    mode: u32,
+    extent: Extent,
+    joliet_extent: Extent,
+}
+
+/// Location and byte length of a directory or file
+#[derive(Debug, Default, Clone, Copy)]
+struct Extent {
+    sector: u32,
+    size: u32,
+}
+
+/// A directory record to be written
+struct Record {
+    identifier: Vec<u8>,
+    target: Target,
+    /// Rock Ridge entries stored in the record itself
+    system_use: Vec<u8>,
+    /// Index into [`Layout::continuations`] of the entries that did not fit
+    continuation: Option<usize>,
+}
+
+#[derive(Clone, Copy)]
+enum Target {
+    Dir(usize),
+    File(usize),
+}
+
+impl Record {
+    fn len(&self) -> usize {
+        let id_len = self.identifier.len();
+        let len = 33 + id_len + (1 - id_len % 2)
+            + self.system_use.len()
+            + if self.continuation.is_some() { rockridge::CE_LEN } else { 0 };
+        len + len % 2
+    }
+}
+
+/// Directory records and path table order for both hierarchies
+struct Layout {
+    /// Records of each directory in the ISO 9660 tree, by directory index
+    primary: Vec<Vec<Record>>,
+    /// Records of each directory in the Joliet tree
+    joliet: Vec<Vec<Record>>,
+    /// Each directory's identifier in its parent
+    primary_names: Vec<Vec<u8>>,
+    joliet_names: Vec<Vec<u8>>,
+    /// Directory indices in path table order
+    primary_order: Vec<usize>,
+    joliet_order: Vec<usize>,
+    /// Rock Ridge continuation areas, and where each one ends up
+    continuations: Vec<Vec<u8>>,
+    continuation_locations: Vec<(u32, u32)>,
+}
+
+/// Mode of directories created implicitly
+const DEFAULT_DIR_MODE: u32 = 0o040755;
+
+/// The bytes of `records`, never letting one cross a sector boundary
+fn extent_size(records: &[Record]) -> u32 {
+    let mut size = 0;
+    for record in records {
+        if size % SECTOR_SIZE + record.len() > SECTOR_SIZE {
+            size = size.next_multiple_of(SECTOR_SIZE);
+        }
+        size += record.len();
+    }
+    size.next_multiple_of(SECTOR_SIZE) as u32
+}
+
+/// ISO 9660 level 1 identifier for `name`: uppercase d-characters in 8.3
+/// form, with `;1` on files; a name already `taken` in the directory gets
+/// a numeric suffix
+fn level1_identifier(name: &str, is_dir: bool, taken: &mut HashSet<Vec<u8>>) -> Vec<u8> {
+    let clean = |part: &str, max: usize| -> String {
+        part.chars()
+            .map(|c| c.to_ascii_uppercase())
+            .map(|c| if c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_' { c } else { '_' })
+            .take(max)
+            .collect()
+    };
+    let (base, ext) = match name.rsplit_once('.') {
+        Some((base, ext)) if !is_dir && !base.is_empty() => (clean(base, 8), clean(ext, 3)),
+        _ => (clean(name, 8), String::new()),
+    };
+    let base = if base.is_empty() { "_".to_string() } else { base };
+    let identifier = |base: &str| if is_dir { base.to_string() } else { format!("{}.{};1", base, ext) };
+
+    let mut id = identifier(&base);
+    let mut n = 1;
+    while !taken.insert(id.clone().into_bytes()) {
+        let suffix = format!("{:03}", n);
+        let kept: String = base.chars().take(8 - suffix.len()).collect();
+        id = identifier(&format!("{}{}", kept, suffix));
+        n += 1;
+    }
+    id.into_bytes()
+}
+
+/// Joliet identifier for `name`, made unique among `taken`
+fn joliet_identifier(name: &str, taken: &mut HashSet<Vec<u8>>) -> Vec<u8> {
+    let base = joliet::name(name);
+    let mut id = base.clone();
+    let mut n = 1;
+    while !taken.insert(joliet::encode(&id)) {
+        let suffix = format!("~{}", n);
+        id = format!("{}{}", joliet::truncate(&base, joliet::NAME_MAX - suffix.len()), suffix);
+        n += 1;
+    }
+    joliet::encode(&id)
+}
+
+/// Split Rock Ridge entries between the record and a continuation area
+fn split_system_use(identifier_len: usize, entries: Vec<Vec<u8>>) -> (Vec<u8>, Option<Vec<u8>>) {
+    // Records are at most 255 bytes and always even
+    let budget = 254 - (33 + identifier_len + (1 - identifier_len % 2));
+    if entries.iter().map(Vec::len).sum::<usize>() <= budget {
+        return (entries.concat(), None);
+    }
+
+    let mut inline = Vec::new();
+    let mut rest = Vec::new();
+    for entry in entries {
+        if rest.is_empty() && inline.len() + entry.len() + rockridge::CE_LEN <= budget {
+            inline.extend_from_slice(&entry);
+        } else {
+            rest.extend_from_slice(&entry);
+        }
+    }
+    (inline, Some(rest))
 }
 
 impl NativeIsoBuilder {
@@ -52,87 +246,468 @@ impl NativeIsoBuilder {
             publisher: "HECATEOS".to_string(),
             preparer: "HECATE-ISO-BUILDER".to_string(),
             files: Vec::new(),
-            directories: Vec::new(),
+            directories: vec![IsoDirEntry {
+                path: "/".to_string(),
+                name: String::new(),
+                parent: None,
+                mode: DEFAULT_DIR_MODE,
+                extent: Extent::default(),
+                joliet_extent: Extent::default(),
+            }],
+            directory_index: HashMap::from([("/".to_string(), 0)]),
+            boot: BootImages::default(),
+            total_sectors: 0,
+            created: Utc::now(),
+        }
+    }
+    
+    /// Index of the file at `iso_path`, ignoring case
+    fn find_file(&self, iso_path: &str) -> Option<usize> {
+        let wanted = format!("/{}", iso_path.trim_start_matches('/'));
+        self.files.iter().position(|file| file.iso_path.eq_ignore_ascii_case(&wanted))
+    }
+    
+    /// Boot BIOS machines from the no-emulation loader at `iso_path`
+    pub fn set_bios_boot(&mut self, iso_path: &str) -> Result<()> {
+        let index = self.find_file(iso_path)
+            .with_context(|| format!("BIOS boot image {} is not in the ISO", iso_path))?;
+        self.boot.bios = Some(index);
+        Ok(())
+    }
+    
+    /// Boot UEFI machines from the FAT image at `iso_path`
+    pub fn set_efi_image(&mut self, iso_path: &str) -> Result<()> {
+        let index = self.find_file(iso_path)
+            .with_context(|| format!("EFI boot image {} is not in the ISO", iso_path))?;
+        self.boot.efi = Some(EfiImage::File(index));
+        Ok(())
+    }
+    
+    /// Boot UEFI machines from a FAT image built around `files`, which
+    /// end up in `EFI/BOOT/` and must include `BOOTX64.EFI`
+    pub fn set_efi_loader(&mut self, files: &[(String, Vec<u8>)]) -> Result<()> {
+        if !files.iter().any(|(name, _)| name.eq_ignore_ascii_case("BOOTX64.EFI")) {
+            anyhow::bail!("EFI boot files must include BOOTX64.EFI");
         }
+        self.boot.efi = Some(EfiImage::Embedded {
+            data: fat::efi_boot_image(files)?,
+            start_sector: 0,
+        });
+        Ok(())
+    }
+    
+    /// Boot code for the hybrid MBR, e.g. syslinux's isohdpfx.bin
+    pub fn set_mbr_code(&mut self, code: Vec<u8>) {
+        self.boot.mbr_code = Some(code);
+    }
+    
+    /// Set up BIOS and UEFI boot from what an extracted distribution ISO
+    /// at `source` contains: isolinux for BIOS, and either a prebuilt
+    /// `boot/grub/efi.img` or the loaders in `EFI/BOOT` for UEFI
+    ///
+    /// Call after [`Self::add_directory_tree`].
+    pub fn detect_boot(&mut self, source: &Path) -> Result<()> {
+        if self.find_file("isolinux/isolinux.bin").is_some() {
+            self.set_bios_boot("isolinux/isolinux.bin")?;
+            
+            let mbr = [source.join("isolinux/isohdpfx.bin"), PathBuf::from(SYSTEM_ISOHDPFX)]
+                .into_iter()
+                .find_map(|path| std::fs::read(path).ok());
+            if let Some(code) = mbr {
+                self.set_mbr_code(code);
+            }
+        }
+        
+        if self.find_file("boot/grub/efi.img").is_some() {
+            self.set_efi_image("boot/grub/efi.img")?;
+        } else if let Some(dir) = find_ignore_case(source, &["EFI", "BOOT"]) {
+            // Loaders without an 8.3 name cannot go in the image
+            let mut files = Vec::new();
+            for entry in std::fs::read_dir(&dir)? {
+                let entry = entry?;
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if entry.file_type()?.is_file() && fat::short_name(&name).is_some() {
+                    files.push((name, std::fs::read(entry.path())?));
+                }
+            }
+            if files.iter().any(|(name, _)| name.eq_ignore_ascii_case("BOOTX64.EFI")) {
+                files.sort();
+                self.set_efi_loader(&files)?;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Index of the directory at `path`, adding it and any missing
+    /// parents
+    fn ensure_directory(&mut self, path: &str) -> usize {
+        if let Some(&index) = self.directory_index.get(path) {
+            return index;
+        }
+        
+        let (parent_path, name) = path.rsplit_once('/').unwrap_or(("", path));
+        let parent = self.ensure_directory(if parent_path.is_empty() { "/" } else { parent_path });
+        self.directories.push(IsoDirEntry {
+            path: path.to_string(),
+            name: name.to_string(),
+            parent: Some(parent),
+            mode: DEFAULT_DIR_MODE,
+            extent: Extent::default(),
+            joliet_extent: Extent::default(),
+        });
+        self.directory_index.insert(path.to_string(), self.directories.len() - 1);
+        self.directories.len() - 1
     }
     
     /// Add a directory tree to the ISO
+    ///
+    /// Names keep their case and length in the Joliet and Rock Ridge
+    /// trees; symlinks are stored as Rock Ridge symlinks rather than
+    /// followed. Device nodes, FIFOs and sockets are skipped.
     pub fn add_directory_tree(&mut self, source: &Path, iso_path: &str) -> Result<()> {
+        let base = iso_path.trim_end_matches('/');
+        
         for entry in WalkDir::new(source) {
             let entry = entry?;
             let path = entry.path();
             let relative = path.strip_prefix(source)?;
-            let iso_name = format!("{}/{}", iso_path, relative.display()).replace("//", "/");
+            let iso_name = if relative.as_os_str().is_empty() {
+                if base.is_empty() { "/".to_string() } else { base.to_string() }
+            } else {
+                format!("{}/{}", base, relative.display())
+            };
+            let metadata = entry.metadata()?;
             
-            if path.is_dir() {
-                self.directories.push(IsoDirEntry {
-                    path: iso_name.to_uppercase(),
-                    parent: None,
-                    start_sector: 0,
-                });
-            } else if path.is_file() {
-                let metadata = path.metadata()?;
-                self.files.push(IsoFileEntry {
-                    path: path.to_path_buf(),
-                    iso_path: iso_name.to_uppercase(),
-                    size: metadata.len(),
-                    start_sector: 0,
-                });
+            if entry.file_type().is_dir() {
+                let index = self.ensure_directory(&iso_name);
+                self.directories[index].mode = metadata.mode();
+                continue;
             }
+            
+            let symlink = if entry.file_type().is_symlink() {
+                Some(std::fs::read_link(path)?.to_string_lossy().into_owned())
+            } else if entry.file_type().is_file() {
+                None
+            } else {
+                continue;
+            };
+            
+            let (dir_path, name) = iso_name.rsplit_once('/').unwrap_or(("", &iso_name));
+            let dir = self.ensure_directory(if dir_path.is_empty() { "/" } else { dir_path });
+            self.files.push(IsoFileEntry {
+                path: path.to_path_buf(),
+                name: name.to_string(),
+                dir,
+                size: if symlink.is_some() { 0 } else { metadata.len() },
+                start_sector: 0,
+                mode: metadata.mode(),
+                symlink,
+                iso_path: iso_name,
+            });
         }
         Ok(())
     }
     
+    /// Rock Ridge entries for a record pointing at `target`; `subdirs`
+    /// counts each directory's subdirectories for its link count
+    fn rock_ridge_entries(&self, target: Target, name: Option<&str>, subdirs: &[Vec<usize>]) -> Vec<Vec<u8>> {
+        let mut entries = Vec::new();
+        match target {
+            Target::Dir(index) => {
+                entries.push(rockridge::px(self.directories[index].mode, 2 + subdirs[index].len() as u32, 0, 0));
+            }
+            Target::File(index) => entries.push(rockridge::px(self.files[index].mode, 1, 0, 0)),
+        }
+        if let Some(name) = name {
+            entries.extend(rockridge::nm(name));
+        }
+        if let Target::File(index) = target {
+            if let Some(link) = &self.files[index].symlink {
+                entries.extend(rockridge::sl(link));
+            }
+        }
+        entries
+    }
+    
+    /// Work out every directory record and the path table order for both
+    /// trees
+    fn plan(&self) -> Result<Layout> {
+        let count = self.directories.len();
+        let mut primary_names = vec![vec![0u8]; count];
+        let mut joliet_names = vec![vec![0u8]; count];
+        let mut primary: Vec<Vec<Record>> = (0..count).map(|_| Vec::new()).collect();
+        let mut joliet: Vec<Vec<Record>> = (0..count).map(|_| Vec::new()).collect();
+        let mut continuations = Vec::new();
+        
+        let mut subdirs: Vec<Vec<usize>> = vec![Vec::new(); count];
+        let mut files: Vec<Vec<usize>> = vec![Vec::new(); count];
+        for (index, dir) in self.directories.iter().enumerate() {
+            if let Some(parent) = dir.parent {
+                subdirs[parent].push(index);
+            }
+        }
+        for (index, file) in self.files.iter().enumerate() {
+            files[file.dir].push(index);
+        }
+        
+        for (index, dir) in self.directories.iter().enumerate() {
+            let parent = dir.parent.unwrap_or(index);
+            
+            // . and .. come first; the root's . also announces Rock Ridge
+            let mut dot = self.rock_ridge_entries(Target::Dir(index), None, &subdirs);
+            if dir.parent.is_none() {
+                dot.insert(0, rockridge::sp());
+                dot.push(rockridge::er());
+            }
+            let dotdot = self.rock_ridge_entries(Target::Dir(parent), None, &subdirs);
+            
+            let mut children: Vec<(&str, Target)> = subdirs[index].iter()
+                .map(|&i| (self.directories[i].name.as_str(), Target::Dir(i)))
+                .chain(files[index].iter().map(|&i| (self.files[i].name.as_str(), Target::File(i))))
+                .collect();
+            children.sort_by(|a, b| a.0.cmp(b.0));
+            
+            let mut records = vec![(vec![0u8], Target::Dir(index), dot), (vec![1u8], Target::Dir(parent), dotdot)];
+            let mut taken = HashSet::new();
+            for &(name, target) in &children {
+                let identifier = level1_identifier(name, matches!(target, Target::Dir(_)), &mut taken);
+                if let Target::Dir(child) = target {
+                    primary_names[child] = identifier.clone();
+                }
+                records.push((identifier, target, self.rock_ridge_entries(target, Some(name), &subdirs)));
+            }
+            records[2..].sort_by(|a, b| a.0.cmp(&b.0));
+            
+            for (identifier, target, entries) in records {
+                let (system_use, rest) = split_system_use(identifier.len(), entries);
+                let continuation = match rest {
+                    Some(rest) if rest.len() > SECTOR_SIZE => {
+                        anyhow::bail!("Rock Ridge data for an entry in {} does not fit a sector", dir.path);
+                    }
+                    Some(rest) => {
+                        continuations.push(rest);
+                        Some(continuations.len() - 1)
+                    }
+                    None => None,
+                };
+                primary[index].push(Record { identifier, target, system_use, continuation });
+            }
+            
+            let mut records = vec![
+                Record { identifier: vec![0], target: Target::Dir(index), system_use: Vec::new(), continuation: None },
+                Record { identifier: vec![1], target: Target::Dir(parent), system_use: Vec::new(), continuation: None },
+            ];
+            let mut taken = HashSet::new();
+            for &(name, target) in &children {
+                let identifier = joliet_identifier(name, &mut taken);
+                if let Target::Dir(child) = target {
+                    joliet_names[child] = identifier.clone();
+                }
+                records.push(Record { identifier, target, system_use: Vec::new(), continuation: None });
+            }
+            records[2..].sort_by(|a, b| a.identifier.cmp(&b.identifier));
+            joliet[index] = records;
+        }
+        
+        let primary_order = self.path_table_order(&primary);
+        let joliet_order = self.path_table_order(&joliet);
+        
+        Ok(Layout {
+            primary,
+            joliet,
+            primary_names,
+            joliet_names,
+            primary_order,
+            joliet_order,
+            continuations,
+            continuation_locations: Vec::new(),
+        })
+    }
+    
+    /// Directories breadth first, each level in record order, as path
+    /// tables require
+    fn path_table_order(&self, records: &[Vec<Record>]) -> Vec<usize> {
+        let mut order = Vec::with_capacity(records.len());
+        let mut queue = VecDeque::from([0]);
+        while let Some(index) = queue.pop_front() {
+            order.push(index);
+            for record in &records[index][2..] {
+                if let Target::Dir(child) = record.target {
+                    queue.push_back(child);
+                }
+            }
+        }
+        order
+    }
+    
     /// Create the ISO file
     pub fn build(&mut self, output: &Path) -> Result<()> {
+        if let Some(file) = self.files.iter().find(|file| file.size > u32::MAX as u64) {
+            anyhow::bail!("{} is larger than 4 GiB, which ISO 9660 cannot hold in one extent", file.iso_path);
+        }
+        
+        let mut layout = self.plan()?;
         let mut iso = File::create(output)?;
+        let bootable = self.boot.is_bootable();
+        
+        // Volume descriptors: primary, the El Torito boot record when
+        // bootable, Joliet, then the terminator
+        let mut current_sector = PVD_SECTOR + 1;
+        if bootable {
+            current_sector += 1;
+        }
+        current_sector += 2;
+        
+        // Boot catalog, followed by the EFI image if it is not in the tree
+        if bootable {
+            self.boot.catalog_sector = current_sector;
+            current_sector += 1;
+            if let Some(EfiImage::Embedded { data, start_sector }) = &mut self.boot.efi {
+                *start_sector = current_sector;
+                current_sector += sectors_for(data.len() as u64);
+            }
+        }
+        
+        // L and M path tables for each tree
+        let primary_table_size = self.path_table(&layout.primary_order, &layout.primary_names, false, false).len();
+        let joliet_table_size = self.path_table(&layout.joliet_order, &layout.joliet_names, true, false).len();
+        let path_tables_sector = current_sector;
+        current_sector += 2 * sectors_for(primary_table_size as u64) + 2 * sectors_for(joliet_table_size as u64);
+        
+        // Directory records
+        for &index in &layout.primary_order {
+            let size = extent_size(&layout.primary[index]);
+            self.directories[index].extent = Extent { sector: current_sector, size };
+            current_sector += sectors_for(size as u64);
+        }
+        for &index in &layout.joliet_order {
+            let size = extent_size(&layout.joliet[index]);
+            self.directories[index].joliet_extent = Extent { sector: current_sector, size };
+            current_sector += sectors_for(size as u64);
+        }
+        
+        // Rock Ridge continuation areas, packed without crossing sectors
+        let mut offset = 0;
+        for area in &layout.continuations {
+            if offset + area.len() > SECTOR_SIZE {
+                current_sector += 1;
+                offset = 0;
+            }
+            layout.continuation_locations.push((current_sector, offset as u32));
+            offset += area.len();
+        }
+        if offset > 0 {
+            current_sector += 1;
+        }
+        
+        // File data
+        for file in &mut self.files {
+            file.start_sector = current_sector;
+            current_sector += sectors_for(file.size);
+        }
+        self.total_sectors = current_sector;
         
         // Write system area (boot area)
         self.write_system_area(&mut iso)?;
         
         // Write primary volume descriptor
-        self.write_primary_volume_descriptor(&mut iso)?;
+        let primary_tables = (path_tables_sector, path_tables_sector + sectors_for(primary_table_size as u64));
+        iso.write_all(&self.volume_descriptor(false, primary_table_size as u32, primary_tables))?;
+        
+        if bootable {
+            iso.write_all(&eltorito::boot_record(self.boot.catalog_sector))?;
+        }
+        
+        // Joliet supplementary volume descriptor
+        let joliet_tables_sector = primary_tables.1 + sectors_for(primary_table_size as u64);
+        let joliet_tables = (joliet_tables_sector, joliet_tables_sector + sectors_for(joliet_table_size as u64));
+        iso.write_all(&self.volume_descriptor(true, joliet_table_size as u32, joliet_tables))?;
         
         // Write volume descriptor set terminator
         self.write_volume_set_terminator(&mut iso)?;
         
-        // Calculate file positions
-        let mut current_sector = 19; // After volume descriptors
-        
-        // Write directory records
-        for dir in &mut self.directories {
-            dir.start_sector = current_sector;
-            current_sector += 1;
+        if bootable {
+            self.write_boot_catalog(&mut iso)?;
         }
         
-        // Write files data
-        for file in &mut self.files {
-            file.start_sector = current_sector;
-            let sectors_needed = ((file.size + SECTOR_SIZE as u64 - 1) / SECTOR_SIZE as u64) as u32;
-            current_sector += sectors_needed;
+        // Path tables
+        iso.seek(SeekFrom::Start(path_tables_sector as u64 * SECTOR_SIZE as u64))?;
+        for (order, names, joliet) in [
+            (&layout.primary_order, &layout.primary_names, false),
+            (&layout.joliet_order, &layout.joliet_names, true),
+        ] {
+            for big_endian in [false, true] {
+                let mut table = self.path_table(order, names, joliet, big_endian);
+                table.resize(table.len().next_multiple_of(SECTOR_SIZE), 0);
+                iso.write_all(&table)?;
+            }
         }
         
         // Write actual directory structures
-        self.write_directory_records(&mut iso)?;
+        self.write_directory_records(&mut iso, &layout)?;
         
         // Write file data
         self.write_file_data(&mut iso)?;
         
+        // Cover every allocated sector, even if the last file is empty
+        iso.set_len(self.total_sectors as u64 * SECTOR_SIZE as u64)?;
+        
         Ok(())
     }
     
+    /// ISO sector and byte length of the EFI image
+    fn efi_extent(&self) -> Option<(u32, u64)> {
+        match self.boot.efi.as_ref()? {
+            EfiImage::File(index) => Some((self.files[*index].start_sector, self.files[*index].size)),
+            EfiImage::Embedded { data, start_sector } => Some((*start_sector, data.len() as u64)),
+        }
+    }
+    
     fn write_system_area(&self, iso: &mut File) -> Result<()> {
         // Write 32KB of zeros for system/boot area
-        let zeros = vec![0u8; SYSTEM_AREA_SIZE];
-        iso.write_all(&zeros)?;
+        let mut system_area = vec![0u8; SYSTEM_AREA_SIZE];
+        
+        // A hybrid MBR makes the image bootable when written to a USB stick
+        if self.boot.is_bootable() {
+            let mbr = eltorito::hybrid_mbr(&HybridLayout {
+                mbr_code: self.boot.mbr_code.as_deref(),
+                bios_lba: self.boot.bios.map(|index| self.files[index].start_sector),
+                efi: self.efi_extent(),
+                total_sectors: self.total_sectors,
+                disk_signature: self.created.timestamp() as u32,
+            });
+            system_area[..mbr.len()].copy_from_slice(&mbr);
+        }
+        
+        iso.write_all(&system_area)?;
         Ok(())
     }
     
-    fn write_primary_volume_descriptor(&self, iso: &mut File) -> Result<()> {
+    fn write_boot_catalog(&self, iso: &mut File) -> Result<()> {
+        let bios = self.boot.bios.map(|index| BootEntry::bios(self.files[index].start_sector));
+        let efi = self.efi_extent().map(|(lba, len)| BootEntry::efi(lba, len));
+        
+        iso.seek(SeekFrom::Start(self.boot.catalog_sector as u64 * SECTOR_SIZE as u64))?;
+        iso.write_all(&eltorito::boot_catalog(bios.as_ref(), efi.as_ref()))?;
+        
+        if let Some(EfiImage::Embedded { data, .. }) = &self.boot.efi {
+            iso.write_all(data)?;
+        }
+        Ok(())
+    }
+    
+    /// The primary volume descriptor, or with `joliet` the Joliet
+    /// supplementary one; `path_tables` holds the L and M table sectors
+    fn volume_descriptor(&self, joliet: bool, path_table_size: u32, path_tables: (u32, u32)) -> Vec<u8> {
         let mut descriptor = vec![0u8; SECTOR_SIZE];
         
         // Volume descriptor type
-        descriptor[0] = VolumeDescriptorType::PrimaryVolume as u8;
+        descriptor[0] = if joliet {
+            VolumeDescriptorType::SupplementaryVolume as u8
+        } else {
+            VolumeDescriptorType::PrimaryVolume as u8
+        };
         
         // Standard identifier "CD001"
         descriptor[1..6].copy_from_slice(b"CD001");
@@ -140,17 +715,39 @@ impl NativeIsoBuilder {
         // Version
         descriptor[6] = 1;
         
+        // Identifiers are a-characters in the primary descriptor and
+        // UCS-2 in the Joliet one
+        let mut text = |range: std::ops::Range<usize>, value: &str| {
+            if joliet {
+                joliet::fill(&mut descriptor[range], value);
+            } else {
+                let padded = format!("{:width$}", value.to_uppercase(), width = range.len());
+                descriptor[range.clone()].copy_from_slice(&padded.as_bytes()[..range.len()]);
+            }
+        };
+        
         // System identifier (32 bytes)
-        let system_id = b"HECATEOS                        ";
-        descriptor[8..40].copy_from_slice(&system_id[..32]);
+        text(8..40, "HECATEOS");
         
         // Volume identifier (32 bytes)
-        let vol_id = format!("{:32}", self.volume_id);
-        descriptor[40..72].copy_from_slice(vol_id.as_bytes());
+        text(40..72, &self.volume_id);
+        
+        // Volume set identifier (128 bytes)
+        text(190..318, "HECATEOS_SET");
+        
+        // Publisher identifier (128 bytes)
+        text(318..446, &self.publisher);
+        
+        // Data preparer identifier (128 bytes)
+        text(446..574, &self.preparer);
         
         // Volume space size (number of logical blocks)
-        let total_sectors = self.calculate_total_sectors();
-        self.write_both_endian_32(&mut descriptor[80..88], total_sectors);
+        self.write_both_endian_32(&mut descriptor[80..88], self.total_sectors);
+        
+        // UCS-2 level 3 escape sequence
+        if joliet {
+            descriptor[88..88 + joliet::ESCAPE_LEVEL3.len()].copy_from_slice(joliet::ESCAPE_LEVEL3);
+        }
         
         // Volume set size
         self.write_both_endian_16(&mut descriptor[120..124], 1);
@@ -161,34 +758,22 @@ impl NativeIsoBuilder {
         // Logical block size
         self.write_both_endian_16(&mut descriptor[128..132], SECTOR_SIZE as u16);
         
-        // Path table size (simplified)
-        let path_table_size = self.calculate_path_table_size();
+        // Path table size
         self.write_both_endian_32(&mut descriptor[132..140], path_table_size);
         
         // Location of L path table
-        descriptor[140..144].copy_from_slice(&20u32.to_le_bytes());
+        descriptor[140..144].copy_from_slice(&path_tables.0.to_le_bytes());
         
         // Location of M path table  
-        descriptor[148..152].copy_from_slice(&22u32.to_be_bytes());
+        descriptor[148..152].copy_from_slice(&path_tables.1.to_be_bytes());
         
         // Root directory record (34 bytes at offset 156)
-        self.write_root_directory_record(&mut descriptor[156..190])?;
-        
-        // Volume set identifier (128 bytes)
-        let volset = format!("{:128}", "HECATEOS_SET");
-        descriptor[190..318].copy_from_slice(volset.as_bytes());
-        
-        // Publisher identifier (128 bytes)
-        let publisher = format!("{:128}", self.publisher);
-        descriptor[318..446].copy_from_slice(publisher.as_bytes());
-        
-        // Data preparer identifier (128 bytes)
-        let preparer = format!("{:128}", self.preparer);
-        descriptor[446..574].copy_from_slice(preparer.as_bytes());
+        let root = &self.directories[0];
+        let extent = if joliet { root.joliet_extent } else { root.extent };
+        descriptor[156..190].copy_from_slice(&self.directory_record(&[0], extent, 0x02, &[]));
         
         // Timestamps (17 bytes each: YYYYMMDDHHMMSSmm + null terminator)
-        let now = Utc::now();
-        let timestamp = self.format_timestamp(&now);
+        let timestamp = self.format_timestamp(&self.created);
         let mut timestamp_bytes = [0u8; 17];
         let ts_bytes = timestamp.as_bytes();
         let len = ts_bytes.len().min(16);
@@ -206,8 +791,7 @@ impl NativeIsoBuilder {
         // File structure version
         descriptor[881] = 1;
         
-        iso.write_all(&descriptor)?;
-        Ok(())
+        descriptor
     }
     
     fn write_volume_set_terminator(&self, iso: &mut File) -> Result<()> {
@@ -220,87 +804,146 @@ impl NativeIsoBuilder {
         Ok(())
     }
     
-    fn write_directory_records(&self, iso: &mut File) -> Result<()> {
-        // Simplified: write basic directory structure
-        // In a full implementation, this would write proper directory records
-        for _ in &self.directories {
-            let dir_record = vec![0u8; SECTOR_SIZE];
-            iso.write_all(&dir_record)?;
+    /// A path table listing directories in `order`, little-endian for the
+    /// L table and big-endian for the M table
+    fn path_table(&self, order: &[usize], names: &[Vec<u8>], joliet: bool, big_endian: bool) -> Vec<u8> {
+        let mut numbers = vec![0u16; self.directories.len()];
+        for (position, &index) in order.iter().enumerate() {
+            numbers[index] = position as u16 + 1;
         }
-        Ok(())
-    }
-    
-    fn write_file_data(&self, iso: &mut File) -> Result<()> {
-        for file in &self.files {
-            // Seek to the file's start sector
-            iso.seek(SeekFrom::Start((file.start_sector as u64) * SECTOR_SIZE as u64))?;
-            
-            // Copy file data
-            let mut source = File::open(&file.path)?;
-            let mut buffer = vec![0u8; SECTOR_SIZE];
+        
+        let mut table = Vec::new();
+        for &index in order {
+            let dir = &self.directories[index];
+            let extent = if joliet { dir.joliet_extent } else { dir.extent };
+            let parent = numbers[dir.parent.unwrap_or(index)];
+            let name = &names[index];
             
-            loop {
-                let bytes_read = source.read(&mut buffer)?;
-                if bytes_read == 0 {
-                    break;
-                }
-                
-                // Pad last sector with zeros
-                if bytes_read < SECTOR_SIZE {
-                    buffer[bytes_read..].fill(0);
-                }
-                
-                iso.write_all(&buffer)?;
-                
-                if bytes_read < SECTOR_SIZE {
-                    break;
-                }
+            table.push(name.len() as u8);
+            table.push(0); // extended attribute record length
+            if big_endian {
+                table.extend_from_slice(&extent.sector.to_be_bytes());
+                table.extend_from_slice(&parent.to_be_bytes());
+            } else {
+                table.extend_from_slice(&extent.sector.to_le_bytes());
+                table.extend_from_slice(&parent.to_le_bytes());
+            }
+            table.extend_from_slice(name);
+            if name.len() % 2 == 1 {
+                table.push(0);
             }
         }
-        Ok(())
+        table
     }
     
-    fn write_root_directory_record(&self, buffer: &mut [u8]) -> Result<()> {
-        // Length of directory record
-        buffer[0] = 34;
-        
-        // Extended attribute record length
-        buffer[1] = 0;
+    /// One directory record; `system_use` must already include any CE entry
+    fn directory_record(&self, identifier: &[u8], extent: Extent, flags: u8, system_use: &[u8]) -> Vec<u8> {
+        let mut record = vec![0u8; 33];
         
-        // Location of extent (root at sector 19)
-        self.write_both_endian_32(&mut buffer[2..10], 19);
-        
-        // Data length (one sector for root)
-        self.write_both_endian_32(&mut buffer[10..18], SECTOR_SIZE as u32);
+        // Location and data length of the extent
+        self.write_both_endian_32(&mut record[2..10], extent.sector);
+        self.write_both_endian_32(&mut record[10..18], extent.size);
         
         // Recording date and time
-        let now = Utc::now();
-        buffer[18] = (now.year() - 1900) as u8;
-        buffer[19] = now.month() as u8;
-        buffer[20] = now.day() as u8;
-        buffer[21] = now.hour() as u8;
-        buffer[22] = now.minute() as u8;
-        buffer[23] = now.second() as u8;
-        buffer[24] = 0; // GMT offset
-        
-        // File flags (directory)
-        buffer[25] = 0x02;
+        record[18] = (self.created.year() - 1900) as u8;
+        record[19] = self.created.month() as u8;
+        record[20] = self.created.day() as u8;
+        record[21] = self.created.hour() as u8;
+        record[22] = self.created.minute() as u8;
+        record[23] = self.created.second() as u8;
+        record[24] = 0; // GMT offset
         
-        // File unit size
-        buffer[26] = 0;
-        
-        // Interleave gap size
-        buffer[27] = 0;
+        // File flags (0x02 for directories)
+        record[25] = flags;
         
         // Volume sequence number
-        self.write_both_endian_16(&mut buffer[28..32], 1);
+        self.write_both_endian_16(&mut record[28..32], 1);
         
-        // Length of file identifier
-        buffer[32] = 1;
+        // File identifier, padded to an even length
+        record[32] = identifier.len() as u8;
+        record.extend_from_slice(identifier);
+        if identifier.len().is_multiple_of(2) {
+            record.push(0);
+        }
         
-        // File identifier (0x00 for root)
-        buffer[33] = 0;
+        record.extend_from_slice(system_use);
+        if record.len() % 2 == 1 {
+            record.push(0);
+        }
+        record[0] = record.len() as u8;
+        record
+    }
+    
+    fn write_directory_records(&self, iso: &mut File, layout: &Layout) -> Result<()> {
+        for (trees, joliet) in [(&layout.primary, false), (&layout.joliet, true)] {
+            for (index, records) in trees.iter().enumerate() {
+                let dir = &self.directories[index];
+                let extent = if joliet { dir.joliet_extent } else { dir.extent };
+                
+                let mut data = Vec::with_capacity(extent.size as usize);
+                for record in records {
+                    let (target_extent, flags) = match record.target {
+                        Target::Dir(i) => {
+                            let dir = &self.directories[i];
+                            (if joliet { dir.joliet_extent } else { dir.extent }, 0x02)
+                        }
+                        Target::File(i) => {
+                            let file = &self.files[i];
+                            (Extent { sector: file.start_sector, size: file.size as u32 }, 0x00)
+                        }
+                    };
+                    
+                    let mut system_use = record.system_use.clone();
+                    if let Some(area) = record.continuation {
+                        let (sector, offset) = layout.continuation_locations[area];
+                        system_use.extend(rockridge::ce(sector, offset, layout.continuations[area].len() as u32));
+                    }
+                    
+                    let bytes = self.directory_record(&record.identifier, target_extent, flags, &system_use);
+                    if data.len() % SECTOR_SIZE + bytes.len() > SECTOR_SIZE {
+                        data.resize(data.len().next_multiple_of(SECTOR_SIZE), 0);
+                    }
+                    data.extend_from_slice(&bytes);
+                }
+                data.resize(extent.size as usize, 0);
+                
+                iso.seek(SeekFrom::Start(extent.sector as u64 * SECTOR_SIZE as u64))?;
+                iso.write_all(&data)?;
+            }
+        }
         
+        for (area, &(sector, offset)) in layout.continuations.iter().zip(&layout.continuation_locations) {
+            iso.seek(SeekFrom::Start(sector as u64 * SECTOR_SIZE as u64 + offset as u64))?;
+            iso.write_all(area)?;
+        }
+        Ok(())
+    }
+    
+    fn write_file_data(&self, iso: &mut File) -> Result<()> {
+        for (index, file) in self.files.iter().enumerate() {
+            // Symlinks and empty files have no data
+            if file.size == 0 {
+                continue;
+            }
+            
+            // Seek to the file's start sector
+            iso.seek(SeekFrom::Start((file.start_sector as u64) * SECTOR_SIZE as u64))?;
+            
+            // isolinux needs its boot info table filled in
+            if self.boot.bios == Some(index) {
+                let mut image = std::fs::read(&file.path)?;
+                eltorito::patch_boot_info_table(&mut image, PVD_SECTOR, file.start_sector);
+                iso.write_all(&image)?;
+                continue;
+            }
+            
+            // Copy exactly the size recorded in the directory
+            let mut source = File::open(&file.path)?.take(file.size);
+            let copied = io::copy(&mut source, iso)?;
+            if copied != file.size {
+                anyhow::bail!("{} changed size while building the ISO", file.path.display());
+            }
+        }
         Ok(())
     }
     
@@ -314,22 +957,6 @@ impl NativeIsoBuilder {
         buffer[4..8].copy_from_slice(&value.to_be_bytes());
     }
     
-    fn calculate_total_sectors(&self) -> u32 {
-        let mut sectors = 19; // System area + volume descriptors
-        sectors += self.directories.len() as u32;
-        
-        for file in &self.files {
-            sectors += ((file.size + SECTOR_SIZE as u64 - 1) / SECTOR_SIZE as u64) as u32;
-        }
-        
-        sectors
-    }
-    
-    fn calculate_path_table_size(&self) -> u32 {
-        // Simplified calculation
-        (self.directories.len() * 12) as u32
-    }
-    
     fn format_timestamp(&self, dt: &chrono::DateTime<Utc>) -> String {
         format!(
             "{:04}{:02}{:02}{:02}{:02}{:02}00",
@@ -343,26 +970,279 @@ impl NativeIsoBuilder {
     }
 }
 
-/// Simplified ISO extraction (using existing tools for now)
-pub fn extract_iso(iso_path: &Path, output_dir: &Path) -> Result<()> {
-    // For extraction, we still use 7z if available since reading ISO is complex
-    std::fs::create_dir_all(output_dir)?;
-    
-    let output = std::process::Command::new("7z")
-        .arg("x")
-        .arg(format!("-o{}", output_dir.display()))
-        .arg("-y")
-        .arg(iso_path)
-        .output();
-    
-    match output {
-        Ok(result) if result.status.success() => Ok(()),
-        _ => {
-            // Fallback message
-            anyhow::bail!(
-                "ISO extraction requires 7z for now. Install with: sudo apt-get install p7zip-full\n\
-                Future versions will include native extraction."
-            )
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eltorito::{Platform, BOOTABLE, BOOT_SYSTEM_ID};
+    use std::fs;
+
+    fn sector(iso: &[u8], lba: u32) -> &[u8] {
+        &iso[lba as usize * SECTOR_SIZE..(lba as usize + 1) * SECTOR_SIZE]
+    }
+
+    fn u32_at(data: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_hybrid_boot_catalog() {
+        let tree = tempfile::tempdir().unwrap();
+        let isolinux: Vec<u8> = (0..4096u32).map(|i| (i % 253) as u8).collect();
+        fs::create_dir_all(tree.path().join("isolinux")).unwrap();
+        fs::write(tree.path().join("isolinux/isolinux.bin"), &isolinux).unwrap();
+        fs::create_dir_all(tree.path().join("EFI/boot")).unwrap();
+        fs::write(tree.path().join("EFI/boot/bootx64.efi"), b"MZ efi loader").unwrap();
+        fs::write(tree.path().join("EFI/boot/grubx64.efi"), b"MZ grub").unwrap();
+
+        let output = tree.path().join("out.iso");
+        let mut builder = NativeIsoBuilder::new("hecateos".to_string());
+        builder.add_directory_tree(tree.path(), "/").unwrap();
+        builder.detect_boot(tree.path()).unwrap();
+        builder.build(&output).unwrap();
+        let iso = fs::read(&output).unwrap();
+        assert_eq!(iso.len() % SECTOR_SIZE, 0);
+
+        // Volume descriptors: primary, boot record, Joliet, terminator
+        assert_eq!(sector(&iso, 16)[0], 1);
+        let record = sector(&iso, 17);
+        assert_eq!((record[0], &record[1..6]), (0, &b"CD001"[..]));
+        assert_eq!(&record[7..7 + BOOT_SYSTEM_ID.len()], BOOT_SYSTEM_ID);
+        assert_eq!(sector(&iso, 18)[0], 2);
+        assert_eq!(sector(&iso, 19)[0], 255);
+
+        // Validation entry: checksum, key bytes, BIOS platform
+        let catalog = sector(&iso, u32_at(record, 71));
+        let sum = catalog[0..32]
+            .chunks(2)
+            .fold(0u16, |sum, word| sum.wrapping_add(u16::from_le_bytes([word[0], word[1]])));
+        assert_eq!(sum, 0);
+        assert_eq!((catalog[0], catalog[1], catalog[30], catalog[31]), (1, Platform::Bios as u8, 0x55, 0xAA));
+
+        // Default entry boots isolinux with its boot info table filled in
+        assert_eq!((catalog[32], catalog[33]), (BOOTABLE, 0));
+        assert_eq!(u16::from_le_bytes([catalog[38], catalog[39]]), 4);
+        let bios_lba = u32_at(catalog, 40);
+        let loader = &iso[bios_lba as usize * SECTOR_SIZE..][..isolinux.len()];
+        assert_eq!(u32_at(loader, 8), PVD_SECTOR);
+        assert_eq!(u32_at(loader, 12), bios_lba);
+        assert_eq!(u32_at(loader, 16), isolinux.len() as u32);
+        assert_eq!(&loader[64..], &isolinux[64..]);
+
+        // Final section header with one EFI entry pointing at a FAT image
+        assert_eq!((catalog[64], catalog[65]), (0x91, Platform::Efi as u8));
+        assert_eq!(u16::from_le_bytes([catalog[66], catalog[67]]), 1);
+        assert_eq!((catalog[96], catalog[97]), (BOOTABLE, 0));
+        let efi_lba = u32_at(catalog, 104);
+        let efi = sector(&iso, efi_lba);
+        assert_eq!(&efi[54..59], b"FAT16");
+        assert_eq!(&efi[510..512], &[0x55, 0xAA]);
+
+        // Hybrid MBR: whole-disk partition and the ESP
+        assert_eq!(&iso[510..512], &[0x55, 0xAA]);
+        assert_eq!((iso[446], iso[450]), (0x80, 0x17));
+        assert_eq!(iso[466], 0xEF);
+        assert_eq!(u32_at(&iso, 470), efi_lba * 4);
+        assert_eq!(u32_at(&iso, 474), u16::from_le_bytes([catalog[102], catalog[103]]) as u32);
+    }
+
+    /// Identifier, extent, size, flags and system use bytes of a record
+    type Record = (Vec<u8>, u32, u32, u8, Vec<u8>);
+
+    /// Records in a directory extent
+    fn records(iso: &[u8], sector: u32, size: u32) -> Vec<Record> {
+        let data = &iso[sector as usize * SECTOR_SIZE..][..size as usize];
+        let mut records = Vec::new();
+        let mut offset = 0;
+        while offset < data.len() {
+            let len = data[offset] as usize;
+            if len == 0 {
+                offset = (offset / SECTOR_SIZE + 1) * SECTOR_SIZE;
+                continue;
+            }
+            let record = &data[offset..offset + len];
+            let id_len = record[32] as usize;
+            let su_start = 33 + id_len + (1 - id_len % 2);
+            records.push((
+                record[33..33 + id_len].to_vec(),
+                u32_at(record, 2),
+                u32_at(record, 10),
+                record[25],
+                record[su_start.min(len)..].to_vec(),
+            ));
+            offset += len;
+        }
+        records
+    }
+
+    /// SUSP entries of a record, following CE continuation areas
+    fn susp_entries(iso: &[u8], system_use: &[u8]) -> Vec<Vec<u8>> {
+        let mut entries = Vec::new();
+        let mut area = system_use.to_vec();
+        loop {
+            let mut next = None;
+            let mut offset = 0;
+            while offset + 4 <= area.len() && area[offset + 2] >= 4 {
+                let entry = area[offset..offset + area[offset + 2] as usize].to_vec();
+                if &entry[..2] == b"CE" {
+                    let start = u32_at(&entry, 4) as usize * SECTOR_SIZE + u32_at(&entry, 12) as usize;
+                    next = Some(iso[start..start + u32_at(&entry, 20) as usize].to_vec());
+                }
+                offset += entry.len();
+                entries.push(entry);
+            }
+            match next {
+                Some(continuation) => area = continuation,
+                None => return entries,
+            }
+        }
+    }
+
+    fn rock_ridge_name(entries: &[Vec<u8>]) -> String {
+        let bytes: Vec<u8> = entries.iter().filter(|e| &e[..2] == b"NM").flat_map(|e| e[5..].to_vec()).collect();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    fn build_tree(tree: &Path) -> Vec<u8> {
+        let output = tree.parent().unwrap().join("out.iso");
+        let mut builder = NativeIsoBuilder::new("hecateos".to_string());
+        builder.add_directory_tree(tree, "/").unwrap();
+        builder.build(&output).unwrap();
+        fs::read(&output).unwrap()
+    }
+
+    #[test]
+    fn test_joliet_preserves_long_mixed_case_names() {
+        let work = tempfile::tempdir().unwrap();
+        let tree = work.path().join("tree");
+        fs::create_dir_all(tree.join("Boot/GRUB")).unwrap();
+        fs::write(tree.join("LongMixedCase.TarGz"), b"archive contents").unwrap();
+        fs::write(tree.join("Boot/GRUB/grub.cfg"), b"set timeout=5\n").unwrap();
+        let iso = build_tree(&tree);
+
+        let svd = sector(&iso, 17);
+        assert_eq!(svd[0], 2);
+        assert_eq!(&svd[88..91], joliet::ESCAPE_LEVEL3);
+        assert_eq!(joliet::decode(&svd[40..56]), "HECATEOS");
+
+        let root = records(&iso, u32_at(svd, 156 + 2), u32_at(svd, 156 + 10));
+        let names: Vec<String> = root[2..].iter().map(|r| joliet::decode(&r.0)).collect();
+        assert_eq!(names, vec!["Boot", "LongMixedCase.TarGz"]);
+
+        let file = &root[3];
+        let data = &iso[file.1 as usize * SECTOR_SIZE..][..file.2 as usize];
+        assert_eq!(data, b"archive contents");
+
+        // Nested directories are reachable through the Joliet tree
+        let boot = records(&iso, root[2].1, root[2].2);
+        assert_eq!(joliet::decode(&boot[2].0), "GRUB");
+        assert_eq!(boot[2].3, 0x02);
+        let grub = records(&iso, boot[2].1, boot[2].2);
+        assert_eq!(joliet::decode(&grub[2].0), "grub.cfg");
+    }
+
+    #[test]
+    fn test_primary_tree_is_level1_with_rock_ridge_names() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let work = tempfile::tempdir().unwrap();
+        let tree = work.path().join("tree");
+        fs::create_dir_all(tree.join("usr/lib")).unwrap();
+        fs::write(tree.join("LongMixedCase.TarGz"), b"archive contents").unwrap();
+        fs::write(tree.join("longmixedcase.tar"), b"other").unwrap();
+        fs::write(tree.join("install.sh"), b"#!/bin/sh\n").unwrap();
+        fs::set_permissions(tree.join("install.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+        symlink("usr/lib", tree.join("lib")).unwrap();
+        let long_name = format!("{}.txt", "very-long-name".repeat(14));
+        fs::write(tree.join(&long_name), b"long").unwrap();
+        let iso = build_tree(&tree);
+
+        let pvd = sector(&iso, 16);
+        let root = records(&iso, u32_at(pvd, 156 + 2), u32_at(pvd, 156 + 10));
+
+        // Root . announces Rock Ridge with SP first and ER in the continuation
+        let dot = susp_entries(&iso, &root[0].4);
+        assert_eq!(&dot[0][..2], b"SP");
+        assert!(dot.iter().any(|e| &e[..2] == b"ER" && &e[8..18] == rockridge::RRIP_ID.as_bytes()));
+
+        let by_name = |name: &str| {
+            root.iter()
+                .find(|r| rock_ridge_name(&susp_entries(&iso, &r.4)) == name)
+                .unwrap_or_else(|| panic!("{} not in the root directory", name))
+        };
+
+        // Base identifiers are unique uppercase d-characters
+        for record in &root[2..] {
+            let id = String::from_utf8(record.0.clone()).unwrap();
+            assert!(id.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || "_.;".contains(c)), "{}", id);
         }
+        assert_eq!(by_name("LongMixedCase.TarGz").0, b"LONGMIXE.TAR;1");
+        assert_eq!(by_name("longmixedcase.tar").0, b"LONGM001.TAR;1");
+
+        // Permissions
+        let script = susp_entries(&iso, &by_name("install.sh").4);
+        let px = script.iter().find(|e| &e[..2] == b"PX").unwrap();
+        assert_eq!(u32_at(px, 4), 0o100755);
+
+        // Symlinks keep their target and carry no data
+        let lib = by_name("lib");
+        assert_eq!(lib.2, 0);
+        let sl: Vec<u8> = susp_entries(&iso, &lib.4).into_iter().filter(|e| &e[..2] == b"SL").flat_map(|e| e[5..].to_vec()).collect();
+        assert_eq!(sl, [&[0, 3][..], b"usr", &[0, 3], b"lib"].concat());
+
+        // A name too long for the record spills into a continuation area
+        let long = by_name(&long_name);
+        assert!(susp_entries(&iso, &long.4).iter().any(|e| &e[..2] == b"CE"));
+        assert_eq!(&iso[long.1 as usize * SECTOR_SIZE..][..4], b"long");
+
+        // L path table: root first, then usr and usr/lib
+        let table_size = u32_at(pvd, 132) as usize;
+        let table = &iso[u32_at(pvd, 140) as usize * SECTOR_SIZE..][..table_size];
+        assert_eq!((table[0], table[8]), (1, 0));
+        assert_eq!(u16::from_le_bytes([table[6], table[7]]), 1);
+        assert_eq!(table_size, 10 + (8 + 4) + (8 + 4));
+        assert_eq!(&table[18..21], b"USR");
+        assert_eq!(u16::from_le_bytes([table[16], table[17]]), 1);
+        assert_eq!(&table[30..33], b"LIB");
+        assert_eq!(u16::from_le_bytes([table[28], table[29]]), 2);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_prebuilt_efi_image_is_used() {
+        let tree = tempfile::tempdir().unwrap();
+        fs::create_dir_all(tree.path().join("boot/grub")).unwrap();
+        fs::write(tree.path().join("boot/grub/efi.img"), vec![0xEE; 3000]).unwrap();
+
+        let output = tree.path().join("out.iso");
+        let mut builder = NativeIsoBuilder::new("hecateos".to_string());
+        builder.add_directory_tree(tree.path(), "/").unwrap();
+        builder.detect_boot(tree.path()).unwrap();
+        builder.build(&output).unwrap();
+        let iso = fs::read(&output).unwrap();
+
+        // EFI-only: the image is the default entry and there is no section
+        let catalog = sector(&iso, u32_at(sector(&iso, 17), 71));
+        assert_eq!(catalog[1], Platform::Efi as u8);
+        assert_eq!(catalog[32], BOOTABLE);
+        assert_eq!(u16::from_le_bytes([catalog[38], catalog[39]]), 6);
+        assert_eq!(catalog[64], 0);
+        let efi_start = u32_at(catalog, 40) as usize * SECTOR_SIZE;
+        assert!(iso[efi_start..efi_start + 3000].iter().all(|&b| b == 0xEE));
+    }
+
+    #[test]
+    fn test_unbootable_tree_has_no_boot_record() {
+        let tree = tempfile::tempdir().unwrap();
+        fs::write(tree.path().join("README"), b"hello").unwrap();
+
+        let output = tree.path().join("out.iso");
+        let mut builder = NativeIsoBuilder::new("hecateos".to_string());
+        builder.add_directory_tree(tree.path(), "/").unwrap();
+        builder.detect_boot(tree.path()).unwrap();
+        builder.build(&output).unwrap();
+        let iso = fs::read(&output).unwrap();
+
+        assert_eq!(sector(&iso, 17)[0], 2);
+        assert_eq!(sector(&iso, 18)[0], 255);
+        assert_eq!(&iso[510..512], &[0, 0]);
+    }
+}
@@ -0,0 +1,82 @@
+//! Joliet names
+//!
+//! The Joliet supplementary volume descriptor carries a second directory
+//! tree whose identifiers are big-endian UCS-2, which keeps case and long
+//! names for Windows and any reader without Rock Ridge.
+
+/// Escape sequence for UCS-2 level 3
+pub const ESCAPE_LEVEL3: &[u8] = b"%/E";
+
+/// Longest identifier in UTF-16 units, as allowed by `-joliet-long`
+pub const NAME_MAX: usize = 103;
+
+/// Big-endian UCS-2 bytes of `s`
+pub fn encode(s: &str) -> Vec<u8> {
+    s.encode_utf16().flat_map(u16::to_be_bytes).collect()
+}
+
+/// `s` back from big-endian UCS-2 bytes
+pub fn decode(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes.chunks_exact(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect();
+    String::from_utf16_lossy(&units)
+}
+
+/// Fill a descriptor field with `s`, padded with UCS-2 spaces
+pub fn fill(field: &mut [u8], s: &str) {
+    for pair in field.chunks_exact_mut(2) {
+        pair.copy_from_slice(&[0x00, 0x20]);
+    }
+    let encoded = encode(s);
+    let len = encoded.len().min(field.len() & !1);
+    field[..len].copy_from_slice(&encoded[..len]);
+}
+
+/// `s` cut to at most `max` UTF-16 units without splitting a surrogate pair
+pub fn truncate(s: &str, max: usize) -> String {
+    let mut units = 0;
+    s.chars()
+        .take_while(|c| {
+            units += c.len_utf16();
+            units <= max
+        })
+        .collect()
+}
+
+/// The Joliet identifier for `name`: characters Joliet forbids replaced
+/// with `_`, cut to [`NAME_MAX`]
+pub fn name(name: &str) -> String {
+    let clean: String = name.chars()
+        .map(|c| if matches!(c, '*' | '/' | ':' | ';' | '?' | '\\') || c.is_control() { '_' } else { c })
+        .collect();
+    truncate(&clean, NAME_MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let encoded = encode("LongMixedCase.TarGz");
+        assert_eq!(&encoded[..4], &[0x00, b'L', 0x00, b'o']);
+        assert_eq!(decode(&encoded), "LongMixedCase.TarGz");
+        assert_eq!(decode(&encode("Grüße 🎉")), "Grüße 🎉");
+    }
+
+    #[test]
+    fn test_name() {
+        assert_eq!(name("a:b;1"), "a_b_1");
+        let long = "x".repeat(200);
+        assert_eq!(name(&long).len(), NAME_MAX);
+        // A surrogate pair is never split
+        let emoji = format!("{}🎉", "y".repeat(NAME_MAX - 1));
+        assert_eq!(name(&emoji), "y".repeat(NAME_MAX - 1));
+    }
+
+    #[test]
+    fn test_fill() {
+        let mut field = [0u8; 8];
+        fill(&mut field, "AB");
+        assert_eq!(field, [0, b'A', 0, b'B', 0, 0x20, 0, 0x20]);
+    }
+}
@@ -14,11 +14,19 @@ use tempfile::TempDir;
 mod iso;
 mod iso_native;
 mod iso_extractor;
+mod eltorito;
+mod elf;
+mod fat;
+mod integrity;
+mod joliet;
+mod rockridge;
 mod config;
 mod injector;
+mod payload;
 mod downloader;
+mod preflight;
 
-use config::HecateConfig;
+use config::{CompressionAlgorithm, HecateConfig};
 use iso::IsoManager;
 use injector::ComponentInjector;
 use downloader::IsoDownloader;
@@ -61,6 +69,26 @@ enum Commands {
         /// Skip building components (use existing binaries)
         #[arg(long)]
         skip_build: bool,
+        
+        /// Private key to sign the ISO checksums with (writes <output>.sig)
+        #[arg(long, requires = "pub_key")]
+        sign_key: Option<PathBuf>,
+        
+        /// Public key matching --sign-key
+        #[arg(long, requires = "sign_key")]
+        pub_key: Option<PathBuf>,
+        
+        /// Payload compression (overrides the configuration file)
+        #[arg(long, value_enum)]
+        compression: Option<CompressionAlgorithm>,
+        
+        /// Compression level (zstd 1-22, xz and gzip 0-9)
+        #[arg(long)]
+        compression_level: Option<i32>,
+        
+        /// Directory for build scratch files (default: $TMPDIR)
+        #[arg(long)]
+        work_dir: Option<PathBuf>,
     },
     
     /// Extract an ISO for manual customization
@@ -98,6 +126,10 @@ enum Commands {
     Verify {
         /// ISO file to verify
         iso: PathBuf,
+        
+        /// Public key the signature must come from (default: trust store)
+        #[arg(long)]
+        pub_key: Option<PathBuf>,
     },
 }
 
@@ -111,8 +143,9 @@ async fn main() -> Result<()> {
     let cli = Cli::parse();
     
     match cli.command {
-        Commands::Build { download, input, output, config, with_binaries, with_source, skip_build } => {
-            build_iso(download, input, output, config, with_binaries, with_source, skip_build).await?;
+        Commands::Build { download, input, output, config, with_binaries, with_source, skip_build, sign_key, pub_key, compression, compression_level, work_dir } => {
+            let signing = sign_key.zip(pub_key);
+            build_iso(download, input, output, config, with_binaries, with_source, skip_build, signing, (compression, compression_level), work_dir).await?;
         }
         Commands::Extract { iso, output } => {
             extract_iso(iso, output).await?;
@@ -123,8 +156,8 @@ async fn main() -> Result<()> {
         Commands::Init { output } => {
             create_config_template(output)?;
         }
-        Commands::Verify { iso } => {
-            verify_iso(iso).await?;
+        Commands::Verify { iso, pub_key } => {
+            verify_iso(iso, pub_key).await?;
         }
     }
     
@@ -196,42 +229,33 @@ fn build_all_components() -> Result<()> {
         "hecate-sign",
     ];
     
-    let total = components.len();
-    for (idx, component) in components.iter().enumerate() {
-        println!("  [{}/{}] Building {}...", idx + 1, total, component);
-        
-        let component_dir = rust_dir.join(component);
-        if !component_dir.exists() {
-            eprintln!("    ⚠️  Component directory not found: {}", component);
-            continue;
-        }
-        
-        // Special handling for hecate-pkg which needs DATABASE_URL
-        let mut cmd = Command::new("cargo");
-        cmd.current_dir(&component_dir)
-            .arg("build")
-            .arg("--release");
-            
-        if *component == "hecate-pkg" {
-            cmd.env("DATABASE_URL", "sqlite:hecate-pkg.db");
-        }
-        
-        let output = cmd.output()
-            .context(format!("Failed to build {}", component))?;
-        
-        if !output.status.success() {
-            eprintln!("    ❌ Build failed for {}", component);
-            eprintln!("    Error: {}", String::from_utf8_lossy(&output.stderr));
-            // Continue with other components instead of failing
+    // One workspace build lets cargo share work and build crates in parallel
+    let mut cmd = Command::new("cargo");
+    cmd.current_dir(&rust_dir)
+        .args(["build", "--release", "--keep-going"])
+        // hecate-pkg needs DATABASE_URL
+        .env("DATABASE_URL", "sqlite:hecate-pkg.db");
+    for component in &components {
+        if rust_dir.join(component).exists() {
+            cmd.args(["-p", component]);
         } else {
-            println!("    ✅ {} built successfully", component);
+            eprintln!("    ⚠️  Component directory not found: {}", component);
         }
     }
     
+    println!("  Building {}...", components.join(", "));
+    let output = cmd.output().context("Failed to run cargo build")?;
+    if !output.status.success() {
+        // Continue with whatever did build instead of failing
+        eprintln!("    ❌ Some components failed to build");
+        eprintln!("    Error: {}", String::from_utf8_lossy(&output.stderr));
+    }
+    
     println!("✅ Component build complete");
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn build_iso(
     download: Option<String>,
     input: PathBuf, 
@@ -240,6 +264,9 @@ async fn build_iso(
     with_binaries: bool,
     with_source: bool,
     skip_build: bool,
+    signing: Option<(PathBuf, PathBuf)>,
+    compression: (Option<CompressionAlgorithm>, Option<i32>),
+    work_dir: Option<PathBuf>,
 ) -> Result<()> {
     println!("{}", "HecateOS ISO Builder".bright_cyan().bold());
     println!("{}", "=".repeat(40).bright_cyan());
@@ -250,20 +277,31 @@ async fn build_iso(
     }
     
     // Load configuration
-    let config = if let Some(path) = config_path {
+    let mut config = if let Some(path) = config_path {
         HecateConfig::from_file(&path)?
     } else {
         HecateConfig::default()
     };
     
+    // A new algorithm starts from its own default level
+    let (algorithm, level) = compression;
+    if let Some(algorithm) = algorithm {
+        if algorithm != config.compression.algorithm {
+            config.compression.level = None;
+        }
+        config.compression.algorithm = algorithm;
+    }
+    if level.is_some() {
+        config.compression.level = level;
+    }
+    config.compression.validate()?;
+    let compression = config.compression;
+    
     // Handle ISO download or use existing
     let iso_path = if let Some(ref version) = download {
         let download_path = PathBuf::from(format!("ubuntu-{}.iso", version));
-        if !download_path.exists() {
-            IsoDownloader::download_ubuntu(&version, &download_path).await?;
-        } else {
-            println!("ℹ️  Using cached ISO: {}", download_path.display());
-        }
+        // Reuses the cached ISO only if it still matches its published checksum
+        IsoDownloader::download_ubuntu(version, &download_path).await?;
         download_path
     } else {
         // Verify input ISO exists
@@ -277,7 +315,16 @@ async fn build_iso(
     };
     
     // Create temporary working directory
-    let temp_dir = TempDir::new().context("Failed to create temp directory")?;
+    let work_parent = match work_dir {
+        Some(dir) => {
+            fs::create_dir_all(&dir)
+                .with_context(|| format!("Failed to create work directory {}", dir.display()))?;
+            dir
+        }
+        None => std::env::temp_dir(),
+    };
+    preflight::check_work_space(&preflight::Statvfs, &iso_path, &work_parent)?;
+    let temp_dir = TempDir::new_in(&work_parent).context("Failed to create temp directory")?;
     let work_dir = temp_dir.path();
     
     println!("📦 Extracting ISO...");
@@ -316,17 +363,43 @@ async fn build_iso(
     println!("  Modifying boot configuration...");
     injector.modify_boot_config(&extract_dir)?;
     
+    println!("  Compressing payload ({} level {})...", compression.algorithm.name(), compression.level());
+    let payload = payload::pack(&extract_dir.join("hecateos"), &compression)?;
+    
     // Repack ISO
     println!("📀 Creating new ISO...");
     let pb = create_progress_bar(100);
     iso_manager.repack(&extract_dir, &output, "HECATEOS", &pb).await?;
     pb.finish_with_message("ISO created");
     
+    // Integrity artifacts
+    println!("🔏 Writing checksums...");
+    let checksums = integrity::write_sidecars(&output)?;
+    let signature = match signing {
+        Some((private_key, public_key)) => Some(integrity::sign(&output, &private_key, &public_key)?),
+        None => None,
+    };
+    
     // Show summary
     let size = fs::metadata(&output)?.len() / 1_000_000;
     println!("\n{}", "✅ Build complete!".green().bold());
     println!("  Output: {}", output.display().to_string().bright_yellow());
     println!("  Size: {} MB", size);
+    if let Some(report) = payload {
+        println!(
+            "  Payload: {:.1} MB -> {:.1} MB ({} level {}, ratio {:.2}x)",
+            report.uncompressed as f64 / 1_000_000.0,
+            report.compressed as f64 / 1_000_000.0,
+            report.compression.algorithm.name(),
+            report.compression.level(),
+            report.ratio()
+        );
+    }
+    println!("  SHA256: {}", checksums.sha256);
+    println!("  BLAKE3: {}", checksums.blake3);
+    if let Some(signature) = signature {
+        println!("  Signature: {}", signature.display());
+    }
     
     println!("\n{}", "Next steps:".bright_cyan());
     println!("  1. Test in VM: qemu-system-x86_64 -m 4G -cdrom {}", output.display());
@@ -386,9 +459,21 @@ fn create_config_template(output: PathBuf) -> Result<()> {
     Ok(())
 }
 
-async fn verify_iso(iso: PathBuf) -> Result<()> {
+async fn verify_iso(iso: PathBuf, pub_key: Option<PathBuf>) -> Result<()> {
     println!("Verifying ISO: {}...", iso.display());
     
+    println!("\n{}", "Integrity:".bright_cyan());
+    if integrity::verify_sidecars(&iso)? {
+        println!("  ✅ SHA256 and BLAKE3 checksums match");
+    } else {
+        println!("  ⚠️  No checksum files found");
+    }
+    let trust_store = PathBuf::from(integrity::TRUST_STORE_PATH);
+    match integrity::verify_signature(&iso, pub_key.as_deref(), &trust_store)? {
+        Some(key_id) => println!("  ✅ Signature valid (key {})", key_id),
+        None => println!("  ⚠️  ISO is not signed"),
+    }
+    
     let temp_dir = TempDir::new()?;
     let extract_dir = temp_dir.path().join("verify");
     
@@ -417,6 +502,15 @@ async fn verify_iso(iso: PathBuf) -> Result<()> {
             }
         }
         
+        // Check the compressed payload
+        for algorithm in [CompressionAlgorithm::Zstd, CompressionAlgorithm::Xz, CompressionAlgorithm::Gzip] {
+            let archive = hecate_dir.join(payload::archive_name(algorithm));
+            if archive.exists() {
+                let size = fs::metadata(&archive)?.len() / 1024;
+                println!("  ✅ Payload archive found: {} ({} KB)", archive.file_name().unwrap().to_string_lossy(), size);
+            }
+        }
+        
         // Check scripts
         if hecate_dir.join("install.sh").exists() {
             println!("  ✅ Install script found");
@@ -0,0 +1,189 @@
+//! Compressed HecateOS payload
+//!
+//! The bulky parts of `hecateos/` (binaries and source) are packed into a
+//! single `payload.tar.<ext>` with the configured algorithm and level; the
+//! installer unpacks it. Configuration and scripts stay plain so they can
+//! be read straight off the ISO.
+
+use anyhow::{Context, Result};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::config::{Compression, CompressionAlgorithm};
+
+/// Directories under `hecateos/` that go into the payload
+pub const PACKED_DIRS: &[&str] = &["bin", "source"];
+
+/// Sizes of a packed payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PayloadReport {
+    pub compression: Compression,
+    pub uncompressed: u64,
+    pub compressed: u64,
+}
+
+impl PayloadReport {
+    /// Uncompressed size over compressed size
+    pub fn ratio(&self) -> f64 {
+        if self.compressed == 0 {
+            return 1.0;
+        }
+        self.uncompressed as f64 / self.compressed as f64
+    }
+}
+
+/// `payload.tar.<ext>` for `algorithm`
+pub fn archive_name(algorithm: CompressionAlgorithm) -> String {
+    format!("payload.tar.{}", algorithm.extension())
+}
+
+fn tree_size(path: &Path) -> Result<u64> {
+    let mut size = 0;
+    for entry in WalkDir::new(path) {
+        let entry = entry?;
+        if entry.file_type().is_file() {
+            size += entry.metadata()?.len();
+        }
+    }
+    Ok(size)
+}
+
+fn append_dirs<W: Write>(writer: W, hecate_dir: &Path, dirs: &[&str]) -> Result<W> {
+    let mut builder = tar::Builder::new(writer);
+    builder.follow_symlinks(false);
+    for dir in dirs {
+        builder.append_dir_all(dir, hecate_dir.join(dir))
+            .with_context(|| format!("Failed to add {} to the payload", dir))?;
+    }
+    Ok(builder.into_inner()?)
+}
+
+/// Pack the [`PACKED_DIRS`] present in `hecate_dir` into its payload
+/// archive and remove them; `None` when there is nothing to pack
+pub fn pack(hecate_dir: &Path, compression: &Compression) -> Result<Option<PayloadReport>> {
+    compression.validate()?;
+
+    let dirs: Vec<&str> = PACKED_DIRS.iter()
+        .copied()
+        .filter(|dir| hecate_dir.join(dir).is_dir())
+        .collect();
+    if dirs.is_empty() {
+        return Ok(None);
+    }
+
+    let mut uncompressed = 0;
+    for dir in &dirs {
+        uncompressed += tree_size(&hecate_dir.join(dir))?;
+    }
+
+    let archive: PathBuf = hecate_dir.join(archive_name(compression.algorithm));
+    let file = File::create(&archive)
+        .with_context(|| format!("Failed to create {}", archive.display()))?;
+    let level = compression.level();
+    match compression.algorithm {
+        CompressionAlgorithm::Zstd => {
+            let mut encoder = zstd::Encoder::new(file, level)?;
+            // Long-distance matching helps across similar binaries
+            encoder.long_distance_matching(true)?;
+            append_dirs(encoder, hecate_dir, &dirs)?.finish()?;
+        }
+        CompressionAlgorithm::Xz => {
+            let encoder = xz2::write::XzEncoder::new(file, level as u32);
+            append_dirs(encoder, hecate_dir, &dirs)?.finish()?;
+        }
+        CompressionAlgorithm::Gzip => {
+            let encoder = flate2::write::GzEncoder::new(file, flate2::Compression::new(level as u32));
+            append_dirs(encoder, hecate_dir, &dirs)?.finish()?;
+        }
+    }
+
+    for dir in &dirs {
+        fs::remove_dir_all(hecate_dir.join(dir))?;
+    }
+
+    Ok(Some(PayloadReport {
+        compression: *compression,
+        uncompressed,
+        compressed: fs::metadata(&archive)?.len(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A compressible but not trivially repetitive tree
+    fn fixed_tree(root: &Path) {
+        fs::create_dir_all(root.join("bin")).unwrap();
+        fs::create_dir_all(root.join("source/src")).unwrap();
+        fs::create_dir_all(root.join("config")).unwrap();
+
+        let mut state = 0x2545_F491u32;
+        let mut text = String::new();
+        let words = ["hecate", "daemon", "governor", "thermal", "profile", "numa", "gpu", "bench"];
+        for _ in 0..60_000 {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            text.push_str(words[(state % words.len() as u32) as usize]);
+            text.push(if state.is_multiple_of(11) { '\n' } else { ' ' });
+        }
+        fs::write(root.join("bin/hecated"), &text).unwrap();
+        fs::write(root.join("source/src/main.rs"), text.to_uppercase()).unwrap();
+        fs::write(root.join("config/hecate.toml"), "[metadata]\n").unwrap();
+    }
+
+    fn packed_size(compression: Compression) -> PayloadReport {
+        let dir = tempfile::tempdir().unwrap();
+        fixed_tree(dir.path());
+        let report = pack(dir.path(), &compression).unwrap().unwrap();
+
+        assert!(!dir.path().join("bin").exists());
+        assert!(!dir.path().join("source").exists());
+        assert!(dir.path().join("config/hecate.toml").exists());
+        assert_eq!(fs::metadata(dir.path().join(archive_name(compression.algorithm))).unwrap().len(), report.compressed);
+        report
+    }
+
+    #[test]
+    fn test_zstd_levels() {
+        let fast = packed_size(Compression { algorithm: CompressionAlgorithm::Zstd, level: Some(1) });
+        let best = packed_size(Compression { algorithm: CompressionAlgorithm::Zstd, level: Some(19) });
+
+        assert_eq!(fast.uncompressed, best.uncompressed);
+        assert!(best.compressed < fast.compressed, "{} >= {}", best.compressed, fast.compressed);
+        assert!(best.ratio() > fast.ratio());
+        assert!(fast.ratio() > 1.0);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for algorithm in [CompressionAlgorithm::Zstd, CompressionAlgorithm::Xz, CompressionAlgorithm::Gzip] {
+            let dir = tempfile::tempdir().unwrap();
+            fixed_tree(dir.path());
+            let original = fs::read(dir.path().join("bin/hecated")).unwrap();
+            pack(dir.path(), &Compression { algorithm, level: None }).unwrap();
+
+            let file = File::open(dir.path().join(archive_name(algorithm))).unwrap();
+            let reader: Box<dyn std::io::Read> = match algorithm {
+                CompressionAlgorithm::Zstd => Box::new(zstd::Decoder::new(file).unwrap()),
+                CompressionAlgorithm::Xz => Box::new(xz2::read::XzDecoder::new(file)),
+                CompressionAlgorithm::Gzip => Box::new(flate2::read::GzDecoder::new(file)),
+            };
+            let out = dir.path().join("out");
+            tar::Archive::new(reader).unpack(&out).unwrap();
+            assert_eq!(fs::read(out.join("bin/hecated")).unwrap(), original);
+            assert!(out.join("source/src/main.rs").exists());
+        }
+    }
+
+    #[test]
+    fn test_invalid_level_and_empty_payload() {
+        let dir = tempfile::tempdir().unwrap();
+        let error = pack(dir.path(), &Compression { algorithm: CompressionAlgorithm::Xz, level: Some(12) }).unwrap_err();
+        assert_eq!(error.to_string(), "xz does not support compression level 12 (supported: 0-9)");
+        assert_eq!(pack(dir.path(), &Compression::default()).unwrap(), None);
+    }
+}
@@ -0,0 +1,94 @@
+//! Disk-space preflight for ISO builds
+//!
+//! A build keeps the extracted tree, the injected payload and the repacked
+//! image on the work filesystem at the same time. Checking up front turns a
+//! confusing mid-build I/O error into a clear message.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// Scratch space needed per byte of input ISO
+pub const SPACE_FACTOR: f64 = 2.5;
+
+/// Estimated scratch space for building from an ISO of `iso_size` bytes
+pub fn required_space(iso_size: u64) -> u64 {
+    (iso_size as f64 * SPACE_FACTOR).ceil() as u64
+}
+
+/// Free space of the filesystem holding a path
+pub trait FreeSpace {
+    fn available(&self, path: &Path) -> Result<u64>;
+}
+
+/// [`FreeSpace`] backed by `statvfs(3)`
+pub struct Statvfs;
+
+impl FreeSpace for Statvfs {
+    fn available(&self, path: &Path) -> Result<u64> {
+        let stat = nix::sys::statvfs::statvfs(path)
+            .with_context(|| format!("Failed to query free space of {}", path.display()))?;
+        // Space available to unprivileged users, not the root reserve
+        Ok(stat.blocks_available() as u64 * stat.fragment_size() as u64)
+    }
+}
+
+/// Fail unless `work_dir` has room to build from `iso`
+pub fn check_work_space(probe: &impl FreeSpace, iso: &Path, work_dir: &Path) -> Result<()> {
+    let iso_size = fs::metadata(iso)
+        .with_context(|| format!("Failed to read {}", iso.display()))?
+        .len();
+    let required = required_space(iso_size);
+    let available = probe.available(work_dir)?;
+
+    if available < required {
+        anyhow::bail!(
+            "Not enough free space in {}: the build needs about {:.1} GB but only {:.1} GB is available.\n\
+             Point TMPDIR at a larger filesystem or pass --work-dir",
+            work_dir.display(),
+            required as f64 / 1_000_000_000.0,
+            available as f64 / 1_000_000_000.0
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reports a fixed amount of free space for any path
+    struct Fixed(u64);
+
+    impl FreeSpace for Fixed {
+        fn available(&self, _path: &Path) -> Result<u64> {
+            Ok(self.0)
+        }
+    }
+
+    #[test]
+    fn test_required_space() {
+        assert_eq!(required_space(0), 0);
+        assert_eq!(required_space(1000), 2500);
+        assert_eq!(required_space(6_000_000_000), 15_000_000_000);
+    }
+
+    #[test]
+    fn test_check_work_space() {
+        let dir = tempfile::tempdir().unwrap();
+        let iso = dir.path().join("ubuntu.iso");
+        fs::File::create(&iso).unwrap().set_len(2_000_000_000).unwrap();
+
+        assert!(check_work_space(&Fixed(5_000_000_000), &iso, dir.path()).is_ok());
+
+        let err = check_work_space(&Fixed(4_999_999_999), &iso, dir.path()).unwrap_err().to_string();
+        assert!(err.contains("5.0 GB"), "{}", err);
+        assert!(err.contains("TMPDIR"), "{}", err);
+    }
+
+    #[test]
+    fn test_statvfs_reports_space() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(Statvfs.available(dir.path()).unwrap() > 0);
+    }
+}
@@ -0,0 +1,187 @@
+//! Rock Ridge entries
+//!
+//! System Use Sharing Protocol (SUSP) entries carrying RRIP 1.10 POSIX
+//! names, permissions and symlinks in the system use area of each ISO 9660
+//! directory record.
+
+/// Longest SUSP entry; the length field is a single byte
+pub const ENTRY_MAX: usize = 255;
+
+/// Length of a CE (continuation area) entry
+pub const CE_LEN: usize = 28;
+
+/// Extension identifier announced in the ER entry
+pub const RRIP_ID: &str = "RRIP_1991A";
+const RRIP_DESCRIPTOR: &str = "THE ROCK RIDGE INTERCHANGE PROTOCOL PROVIDES SUPPORT FOR POSIX FILE SYSTEM SEMANTICS";
+const RRIP_SOURCE: &str = "PLEASE CONTACT DISC PUBLISHER FOR SPECIFICATION SOURCE.  SEE PUBLISHER IDENTIFIER IN PRIMARY VOLUME DESCRIPTOR FOR CONTACT INFORMATION.";
+
+/// Flag on NM and SL entries, and SL components, continued in the next one
+const CONTINUE: u8 = 0x01;
+const SL_CURRENT: u8 = 0x02;
+const SL_PARENT: u8 = 0x04;
+const SL_ROOT: u8 = 0x08;
+
+/// Longest symlink component content that fits a single SL entry
+const SL_COMPONENT_MAX: usize = ENTRY_MAX - 5 - 2;
+
+pub(crate) fn both_endian_32(value: u32) -> [u8; 8] {
+    let mut bytes = [0u8; 8];
+    bytes[..4].copy_from_slice(&value.to_le_bytes());
+    bytes[4..].copy_from_slice(&value.to_be_bytes());
+    bytes
+}
+
+fn entry(signature: &[u8; 2], body: &[u8]) -> Vec<u8> {
+    let mut entry = Vec::with_capacity(4 + body.len());
+    entry.extend_from_slice(signature);
+    entry.push((4 + body.len()) as u8);
+    entry.push(1);
+    entry.extend_from_slice(body);
+    entry
+}
+
+/// SP entry; must come first in the root directory's `.` record
+pub fn sp() -> Vec<u8> {
+    entry(b"SP", &[0xBE, 0xEF, 0])
+}
+
+/// ER entry declaring the Rock Ridge extension
+pub fn er() -> Vec<u8> {
+    let mut body = vec![
+        RRIP_ID.len() as u8,
+        RRIP_DESCRIPTOR.len() as u8,
+        RRIP_SOURCE.len() as u8,
+        1,
+    ];
+    body.extend_from_slice(RRIP_ID.as_bytes());
+    body.extend_from_slice(RRIP_DESCRIPTOR.as_bytes());
+    body.extend_from_slice(RRIP_SOURCE.as_bytes());
+    entry(b"ER", &body)
+}
+
+/// PX entry with the POSIX mode, including the file type bits
+pub fn px(mode: u32, links: u32, uid: u32, gid: u32) -> Vec<u8> {
+    let body: Vec<u8> = [mode, links, uid, gid].into_iter().flat_map(both_endian_32).collect();
+    entry(b"PX", &body)
+}
+
+/// CE entry pointing at the continuation area holding the remaining entries
+pub fn ce(block: u32, offset: u32, length: u32) -> Vec<u8> {
+    let body: Vec<u8> = [block, offset, length].into_iter().flat_map(both_endian_32).collect();
+    entry(b"CE", &body)
+}
+
+/// NM entries with the original name, split when it does not fit one entry
+pub fn nm(name: &str) -> Vec<Vec<u8>> {
+    let chunks: Vec<&[u8]> = name.as_bytes().chunks(ENTRY_MAX - 5).collect();
+    chunks.iter()
+        .enumerate()
+        .map(|(i, chunk)| {
+            let flags = if i + 1 < chunks.len() { CONTINUE } else { 0 };
+            let mut body = vec![flags];
+            body.extend_from_slice(chunk);
+            entry(b"NM", &body)
+        })
+        .collect()
+}
+
+/// SL entries describing a symlink to `target`
+pub fn sl(target: &str) -> Vec<Vec<u8>> {
+    let mut components: Vec<Vec<u8>> = Vec::new();
+    if target.starts_with('/') {
+        components.push(vec![SL_ROOT, 0]);
+    }
+    for part in target.split('/').filter(|part| !part.is_empty()) {
+        match part {
+            "." => components.push(vec![SL_CURRENT, 0]),
+            ".." => components.push(vec![SL_PARENT, 0]),
+            _ => {
+                let chunks: Vec<&[u8]> = part.as_bytes().chunks(SL_COMPONENT_MAX).collect();
+                for (i, chunk) in chunks.iter().enumerate() {
+                    let flags = if i + 1 < chunks.len() { CONTINUE } else { 0 };
+                    let mut component = vec![flags, chunk.len() as u8];
+                    component.extend_from_slice(chunk);
+                    components.push(component);
+                }
+            }
+        }
+    }
+
+    // Pack the components into as few entries as fit
+    let mut bodies: Vec<Vec<u8>> = vec![Vec::new()];
+    for component in components {
+        let current = bodies.last_mut().unwrap();
+        if 5 + current.len() + component.len() > ENTRY_MAX {
+            bodies.push(component);
+        } else {
+            current.extend_from_slice(&component);
+        }
+    }
+
+    let count = bodies.len();
+    bodies.into_iter()
+        .enumerate()
+        .map(|(i, components)| {
+            let mut body = vec![if i + 1 < count { CONTINUE } else { 0 }];
+            body.extend_from_slice(&components);
+            entry(b"SL", &body)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_lengths() {
+        assert_eq!(sp(), vec![b'S', b'P', 7, 1, 0xBE, 0xEF, 0]);
+        assert_eq!(px(0o100644, 1, 0, 0).len(), 36);
+        assert_eq!(ce(40, 0, 237).len(), CE_LEN);
+        let er = er();
+        assert_eq!(er.len(), 8 + 10 + 84 + 135);
+        assert_eq!(er[2] as usize, er.len());
+    }
+
+    #[test]
+    fn test_px() {
+        let px = px(0o100755, 1, 0, 0);
+        assert_eq!(&px[..4], &[b'P', b'X', 36, 1]);
+        assert_eq!(u32::from_le_bytes(px[4..8].try_into().unwrap()), 0o100755);
+        assert_eq!(u32::from_be_bytes(px[8..12].try_into().unwrap()), 0o100755);
+    }
+
+    #[test]
+    fn test_nm_splits_long_names() {
+        assert_eq!(nm("LongMixedCase.TarGz"), vec![[&[b'N', b'M', 24, 1, 0][..], b"LongMixedCase.TarGz"].concat()]);
+
+        let long = "n".repeat(255);
+        let entries = nm(&long);
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().all(|e| e.len() <= ENTRY_MAX && e[2] as usize == e.len()));
+        assert_eq!((entries[0][4], entries[1][4]), (CONTINUE, 0));
+        let rebuilt: Vec<u8> = entries.iter().flat_map(|e| e[5..].to_vec()).collect();
+        assert_eq!(rebuilt, long.as_bytes());
+    }
+
+    #[test]
+    fn test_sl_components() {
+        let entries = sl("/usr/../lib/./libc.so.6");
+        assert_eq!(entries.len(), 1);
+        assert_eq!(&entries[0][5..], &[
+            SL_ROOT, 0,
+            0, 3, b'u', b's', b'r',
+            SL_PARENT, 0,
+            0, 3, b'l', b'i', b'b',
+            SL_CURRENT, 0,
+            0, 9, b'l', b'i', b'b', b'c', b'.', b's', b'o', b'.', b'6',
+        ][..]);
+
+        // Long targets spread over several entries
+        let long = format!("{}/{}", "a".repeat(200), "b".repeat(200));
+        let entries = sl(&long);
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().all(|e| e.len() <= ENTRY_MAX));
+        assert_eq!(entries[0][4], CONTINUE);
+    }
+}
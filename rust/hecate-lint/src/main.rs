@@ -1,10 +1,53 @@
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{bail, Context, Result};
+use clap::{Parser, ValueEnum};
 use colored::*;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
+/// Config file looked up in the linted directory
+const CONFIG_FILE: &str = ".hecate-lint.toml";
+
+/// How serious an issue is; the names match SARIF result levels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+struct Rule {
+    id: &'static str,
+    severity: Severity,
+    description: &'static str,
+}
+
+/// Every rule hecate-lint knows about
+const RULES: &[Rule] = &[
+    Rule { id: "license-header", severity: Severity::Warning, description: "Source files start with a license header" },
+    Rule { id: "todo-comment", severity: Severity::Note, description: "TODO and FIXME comments are tracked" },
+    Rule { id: "line-length", severity: Severity::Warning, description: "Lines stay within the configured length" },
+    Rule { id: "missing-doc", severity: Severity::Warning, description: "Required documentation files exist" },
+    Rule { id: "invalid-toml", severity: Severity::Error, description: "TOML files parse" },
+];
+
+fn rule_ids() -> impl Iterator<Item = &'static str> {
+    RULES.iter().map(|rule| rule.id)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+    /// SARIF 2.1.0, for GitHub code scanning
+    Sarif,
+}
+
 #[derive(Parser)]
 #[command(author, version, about = "HecateOS code quality enforcer")]
 struct Cli {
@@ -16,163 +59,597 @@ struct Cli {
     #[arg(short, long)]
     fix: bool,
 
-    /// Check only specific rules
-    #[arg(short = 'r', long)]
+    /// Check only specific rules (overrides the config file)
+    #[arg(short = 'r', long, value_delimiter = ',')]
     rules: Option<Vec<String>>,
 
+    /// Config file (default: <path>/.hecate-lint.toml, if present)
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+
+    /// Output format
+    #[arg(long, value_enum, default_value = "text")]
+    format: OutputFormat,
+
+    /// Warnings and notes allowed before exiting non-zero; errors always fail
+    #[arg(long, default_value_t = 0)]
+    max_warnings: usize,
+
     /// Verbose output
     #[arg(short, long)]
     verbose: bool,
 }
 
-#[derive(Debug)]
+/// Contents of `.hecate-lint.toml`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct LintConfig {
+    /// Rules to run
+    rules: Vec<String>,
+    /// Maximum line length for the line-length rule
+    line_length: usize,
+    /// Gitignore-style globs, relative to the linted directory
+    ignore: Vec<String>,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self {
+            rules: rule_ids().map(String::from).collect(),
+            line_length: 120,
+            ignore: vec!["target/".to_string(), ".git/".to_string()],
+        }
+    }
+}
+
+impl LintConfig {
+    /// Load `path`, falling back to the defaults when it doesn't exist
+    fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        toml::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 struct LintIssue {
     file: String,
     line: usize,
     rule: String,
+    severity: Severity,
     message: String,
     fixable: bool,
 }
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
-    
-    let mut issues = Vec::new();
-    
-    // Run all lint checks
-    check_rust_files(&cli.path, &mut issues, cli.fix)?;
-    check_documentation(&cli.path, &mut issues)?;
-    check_config_files(&cli.path, &mut issues)?;
-    
-    // Display results
-    if issues.is_empty() {
-        println!("{} No issues found!", "✓".green().bold());
-    } else {
-        println!("{} Found {} issue(s):", "⚠".yellow().bold(), issues.len());
-        for issue in &issues {
-            println!(
-                "  {}:{}  {} - {}{}",
-                issue.file,
-                issue.line,
-                issue.rule.yellow(),
-                issue.message,
-                if issue.fixable { " (fixable)" } else { "" }
-            );
+/// Resolved settings for one run
+struct Linter {
+    root: PathBuf,
+    rules: Vec<String>,
+    line_length: usize,
+    ignore: Gitignore,
+    fix: bool,
+}
+
+impl Linter {
+    fn new(root: &Path, config: LintConfig, rules: Option<Vec<String>>, fix: bool) -> Result<Self> {
+        let rules = rules.unwrap_or(config.rules);
+        if let Some(unknown) = rules.iter().find(|rule| !rule_ids().any(|id| id == rule.as_str())) {
+            bail!("Unknown rule '{}' (available: {})", unknown, rule_ids().collect::<Vec<_>>().join(", "));
         }
-        
-        if cli.fix {
-            println!("\n{} Fixed {} auto-fixable issues", 
-                "✓".green(), 
-                issues.iter().filter(|i| i.fixable).count()
-            );
-        } else if issues.iter().any(|i| i.fixable) {
-            println!("\n{} Run with --fix to automatically fix some issues", 
-                "Tip".cyan().bold());
+
+        let mut builder = GitignoreBuilder::new(root);
+        for pattern in &config.ignore {
+            builder.add_line(None, pattern)
+                .with_context(|| format!("Invalid ignore pattern '{}'", pattern))?;
         }
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            rules,
+            line_length: config.line_length,
+            ignore: builder.build()?,
+            fix,
+        })
+    }
+
+    fn enabled(&self, rule: &str) -> bool {
+        self.rules.iter().any(|enabled| enabled == rule)
     }
-    
-    Ok(())
-}
 
-fn check_rust_files(path: &str, issues: &mut Vec<LintIssue>, fix: bool) -> Result<()> {
-    for entry in WalkDir::new(path)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.path().extension() == Some("rs".as_ref()))
+    /// Files under the root with `extension`, skipping ignored paths
+    fn files(&self, extension: &str) -> Vec<PathBuf> {
+        WalkDir::new(&self.root)
+            .into_iter()
+            .filter_entry(|e| {
+                e.depth() == 0 || !self.ignore.matched(e.path(), e.file_type().is_dir()).is_ignore()
+            })
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file() && e.path().extension() == Some(extension.as_ref()))
+            .map(|e| e.into_path())
+            .collect()
+    }
+
+    /// Run every enabled check; issues are sorted by file, then line
+    fn run(&self) -> Result<Vec<LintIssue>> {
+        let mut issues = Vec::new();
+
+        if ["license-header", "todo-comment", "line-length"].iter().any(|rule| self.enabled(rule)) {
+            issues.extend(self.check_files("rs", |path| self.check_rust_file(path))?);
+        }
+        if self.enabled("missing-doc") {
+            issues.extend(self.check_documentation());
+        }
+        if self.enabled("invalid-toml") {
+            issues.extend(self.check_files("toml", check_config_file)?);
+        }
+
+        issues.sort_by(|a, b| (&a.file, a.line, &a.rule).cmp(&(&b.file, b.line, &b.rule)));
+        Ok(issues)
+    }
+
+    fn check_files<F>(&self, extension: &str, check: F) -> Result<Vec<LintIssue>>
+    where
+        F: Fn(&Path) -> Result<Vec<LintIssue>> + Sync,
     {
-        let file_path = entry.path();
+        let results: Vec<Vec<LintIssue>> = self.files(extension)
+            .par_iter()
+            .map(|path| check(path))
+            .collect::<Result<_>>()?;
+        Ok(results.into_iter().flatten().collect())
+    }
+
+    fn check_rust_file(&self, file_path: &Path) -> Result<Vec<LintIssue>> {
+        let mut issues = Vec::new();
         let content = fs::read_to_string(file_path)?;
-        
+
         // Check for missing license headers
-        if !content.starts_with("//") && !content.starts_with("/*") {
+        if self.enabled("license-header") && !has_license_header(&content) {
             issues.push(LintIssue {
                 file: file_path.display().to_string(),
                 line: 1,
                 rule: "license-header".to_string(),
+                severity: Severity::Warning,
                 message: "Missing license header".to_string(),
                 fixable: true,
             });
-            
-            if fix {
-                let header = "// Copyright (c) 2026 HecateOS Team\n// SPDX-License-Identifier: MIT\n\n";
-                let new_content = format!("{}{}", header, content);
-                fs::write(file_path, new_content)?;
+
+            if self.fix {
+                fs::write(file_path, insert_license_header(&content))?;
             }
         }
-        
+
         // Check for TODO/FIXME comments
-        for (line_num, line) in content.lines().enumerate() {
-            if line.contains("TODO") || line.contains("FIXME") {
-                issues.push(LintIssue {
-                    file: file_path.display().to_string(),
-                    line: line_num + 1,
-                    rule: "todo-comment".to_string(),
-                    message: format!("Found {}", if line.contains("TODO") { "TODO" } else { "FIXME" }),
-                    fixable: false,
-                });
+        if self.enabled("todo-comment") {
+            for (line_num, line) in content.lines().enumerate() {
+                if line.contains("TODO") || line.contains("FIXME") {
+                    issues.push(LintIssue {
+                        file: file_path.display().to_string(),
+                        line: line_num + 1,
+                        rule: "todo-comment".to_string(),
+                        severity: Severity::Note,
+                        message: format!("Found {}", if line.contains("TODO") { "TODO" } else { "FIXME" }),
+                        fixable: false,
+                    });
+                }
             }
         }
-        
+
         // Check for long lines
-        for (line_num, line) in content.lines().enumerate() {
-            if line.len() > 120 {
-                issues.push(LintIssue {
-                    file: file_path.display().to_string(),
-                    line: line_num + 1,
-                    rule: "line-length".to_string(),
-                    message: format!("Line exceeds 120 characters ({})", line.len()),
-                    fixable: false,
-                });
+        if self.enabled("line-length") {
+            for (line_num, line) in content.lines().enumerate() {
+                if line.len() > self.line_length {
+                    issues.push(LintIssue {
+                        file: file_path.display().to_string(),
+                        line: line_num + 1,
+                        rule: "line-length".to_string(),
+                        severity: Severity::Warning,
+                        message: format!("Line exceeds {} characters ({})", self.line_length, line.len()),
+                        fixable: false,
+                    });
+                }
             }
         }
+
+        Ok(issues)
     }
-    
-    Ok(())
-}
 
-fn check_documentation(path: &str, issues: &mut Vec<LintIssue>) -> Result<()> {
-    let required_docs = vec![
-        "README.md",
-        "LICENSE",
-        "CHANGELOG.md",
-    ];
-    
-    for doc in required_docs {
-        let doc_path = Path::new(path).join(doc);
-        if !doc_path.exists() {
-            issues.push(LintIssue {
-                file: doc.to_string(),
+    fn check_documentation(&self) -> Vec<LintIssue> {
+        let required_docs = vec![
+            "README.md",
+            "LICENSE",
+            "CHANGELOG.md",
+        ];
+
+        required_docs.into_iter()
+            .filter(|doc| !self.root.join(doc).exists())
+            .map(|doc| LintIssue {
+                file: self.root.join(doc).display().to_string(),
                 line: 0,
                 rule: "missing-doc".to_string(),
-                message: format!("Required documentation file missing"),
+                severity: Severity::Warning,
+                message: "Required documentation file missing".to_string(),
                 fixable: false,
-            });
-        }
+            })
+            .collect()
+    }
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    let root = Path::new(&cli.path);
+    let config = match &cli.config {
+        Some(path) if !path.exists() => bail!("Config file {} not found", path.display()),
+        Some(path) => LintConfig::load(path)?,
+        None => LintConfig::load(&root.join(CONFIG_FILE))?,
+    };
+    let linter = Linter::new(root, config, cli.rules, cli.fix)?;
+    if cli.verbose && cli.format == OutputFormat::Text {
+        println!("{} Rules: {}", "→".blue(), linter.rules.join(", "));
+    }
+
+    let issues = linter.run()?;
+
+    match cli.format {
+        OutputFormat::Text => print_text(&issues, cli.fix),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&issues)?),
+        OutputFormat::Sarif => println!("{}", serde_json::to_string_pretty(&sarif(&issues, root))?),
+    }
+
+    // Issues fixed in this run no longer count
+    let remaining = issues.iter().filter(|i| !(cli.fix && i.fixable));
+    let (errors, warnings) = remaining.fold((0, 0), |(errors, warnings), issue| match issue.severity {
+        Severity::Error => (errors + 1, warnings),
+        _ => (errors, warnings + 1),
+    });
+    if errors > 0 {
+        bail!("{} error(s) found", errors);
+    }
+    if warnings > cli.max_warnings {
+        bail!("{} warning(s) found (max {})", warnings, cli.max_warnings);
     }
-    
+
     Ok(())
 }
 
-fn check_config_files(path: &str, issues: &mut Vec<LintIssue>) -> Result<()> {
+fn print_text(issues: &[LintIssue], fix: bool) {
+    if issues.is_empty() {
+        println!("{} No issues found!", "✓".green().bold());
+        return;
+    }
+
+    println!("{} Found {} issue(s):", "⚠".yellow().bold(), issues.len());
+    for issue in issues {
+        println!(
+            "  {}:{}  {} - {}{}",
+            issue.file,
+            issue.line,
+            issue.rule.yellow(),
+            issue.message,
+            if issue.fixable { " (fixable)" } else { "" }
+        );
+    }
+
+    if fix {
+        println!("\n{} Fixed {} auto-fixable issues",
+            "✓".green(),
+            issues.iter().filter(|i| i.fixable).count()
+        );
+    } else if issues.iter().any(|i| i.fixable) {
+        println!("\n{} Run with --fix to automatically fix some issues",
+            "Tip".cyan().bold());
+    }
+}
+
+/// A SARIF 2.1.0 log with one run; file URIs are relative to `root`
+fn sarif(issues: &[LintIssue], root: &Path) -> serde_json::Value {
+    let rules: Vec<serde_json::Value> = RULES.iter()
+        .map(|rule| json!({
+            "id": rule.id,
+            "shortDescription": { "text": rule.description },
+            "defaultConfiguration": { "level": rule.severity },
+        }))
+        .collect();
+
+    let results: Vec<serde_json::Value> = issues.iter()
+        .map(|issue| {
+            let path = Path::new(&issue.file);
+            let uri = path.strip_prefix(root).unwrap_or(path)
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let mut location = json!({ "artifactLocation": { "uri": uri } });
+            // Whole-file issues have no line; SARIF lines start at 1
+            if issue.line > 0 {
+                location["region"] = json!({ "startLine": issue.line });
+            }
+            json!({
+                "ruleId": issue.rule,
+                "level": issue.severity,
+                "message": { "text": issue.message },
+                "locations": [{ "physicalLocation": location }],
+                "properties": { "fixable": issue.fixable },
+            })
+        })
+        .collect();
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "hecate-lint",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                },
+            },
+            "results": results,
+        }],
+    })
+}
+
+const LICENSE_HEADER: &str = "// Copyright (c) 2026 HecateOS Team\n// SPDX-License-Identifier: MIT\n\n";
+
+/// Lines searched for an existing SPDX identifier
+const SPDX_SCAN_LINES: usize = 5;
+
+/// Byte offset just past a leading shebang and inner attributes, which
+/// must stay at the top of the file
+fn preamble_end(content: &str) -> usize {
+    let line_end = |from: usize| content[from..].find('\n').map_or(content.len(), |i| from + i + 1);
+
+    let mut end = 0;
+    // `#!` followed by `[` is an inner attribute, not a shebang
+    if content.starts_with("#!") && !content.starts_with("#![") {
+        end = line_end(0);
+    }
+
+    loop {
+        let rest = &content[end..];
+        let start = end + (rest.len() - rest.trim_start().len());
+        if !content[start..].starts_with("#![") {
+            return end;
+        }
+
+        // Attributes may span lines; find the closing bracket
+        let mut depth = 0;
+        let mut in_string = false;
+        let mut close = None;
+        let mut chars = content[start + 2..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' if in_string => { chars.next(); }
+                '"' => in_string = !in_string,
+                '[' if !in_string => depth += 1,
+                ']' if !in_string => {
+                    depth -= 1;
+                    if depth == 0 {
+                        close = Some(start + 2 + i);
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        match close {
+            Some(close) => end = line_end(close),
+            // Unterminated, leave the file alone
+            None => return end,
+        }
+    }
+}
+
+/// Whether the file has a license header: a comment right after the
+/// preamble, or an SPDX identifier in its first lines
+fn has_license_header(content: &str) -> bool {
+    let body = content[preamble_end(content)..].trim_start();
+    body.starts_with("//")
+        || body.starts_with("/*")
+        || content.lines().take(SPDX_SCAN_LINES).any(|line| line.contains("SPDX-License-Identifier"))
+}
+
+/// `content` with [`LICENSE_HEADER`] added after its preamble
+fn insert_license_header(content: &str) -> String {
+    let end = preamble_end(content);
+    if end == 0 {
+        return format!("{}{}", LICENSE_HEADER, content);
+    }
+
+    let preamble = &content[..end];
+    let newline = if preamble.ends_with('\n') { "" } else { "\n" };
+    let body = content[end..].trim_start_matches(['\n', '\r']);
+    format!("{}{}\n{}{}", preamble, newline, LICENSE_HEADER, body)
+}
+
+fn check_config_file(file_path: &Path) -> Result<Vec<LintIssue>> {
     // Check TOML files for validity
-    for entry in WalkDir::new(path)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.path().extension() == Some("toml".as_ref()))
-    {
-        let file_path = entry.path();
-        let content = fs::read_to_string(file_path)?;
-        
-        if let Err(e) = content.parse::<toml_edit::DocumentMut>() {
-            issues.push(LintIssue {
-                file: file_path.display().to_string(),
-                line: 0,
-                rule: "invalid-toml".to_string(),
-                message: format!("Invalid TOML: {}", e),
-                fixable: false,
-            });
+    let content = fs::read_to_string(file_path)?;
+
+    match content.parse::<toml_edit::DocumentMut>() {
+        Ok(_) => Ok(Vec::new()),
+        Err(e) => Ok(vec![LintIssue {
+            file: file_path.display().to_string(),
+            line: 0,
+            rule: "invalid-toml".to_string(),
+            severity: Severity::Error,
+            message: format!("Invalid TOML: {}", e),
+            fixable: false,
+        }]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A small tree with one issue for every rule
+    fn sample_tree() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("target/debug")).unwrap();
+        fs::create_dir_all(root.join("generated")).unwrap();
+        fs::write(root.join("README.md"), "# test\n").unwrap();
+        fs::write(root.join("LICENSE"), "MIT\n").unwrap();
+
+        fs::write(root.join("src/main.rs"), format!("fn main() {{}}\n// TODO: more\nconst X: &str = \"{}\";\n", "x".repeat(90))).unwrap();
+        fs::write(root.join("src/lib.rs"), "// SPDX-License-Identifier: MIT\n// FIXME\n").unwrap();
+        fs::write(root.join("target/debug/build.rs"), "// TODO: ignored by default\n").unwrap();
+        fs::write(root.join("generated/bindings.rs"), "// TODO: generated\n").unwrap();
+        fs::write(root.join("broken.toml"), "[package\n").unwrap();
+        dir
+    }
+
+    fn lint(root: &Path, config: LintConfig, rules: Option<&[&str]>) -> Vec<(String, usize, String)> {
+        let rules = rules.map(|rules| rules.iter().map(|rule| rule.to_string()).collect());
+        Linter::new(root, config, rules, false).unwrap()
+            .run().unwrap()
+            .into_iter()
+            .map(|issue| {
+                let file = Path::new(&issue.file).strip_prefix(root).map(|p| p.display().to_string()).unwrap_or(issue.file);
+                (file, issue.line, issue.rule)
+            })
+            .collect()
+    }
+
+    fn issue(file: &str, line: usize, rule: &str) -> (String, usize, String) {
+        (file.to_string(), line, rule.to_string())
+    }
+
+    #[test]
+    fn test_all_rules_sorted() {
+        let dir = sample_tree();
+        assert_eq!(lint(dir.path(), LintConfig::default(), None), vec![
+            issue("CHANGELOG.md", 0, "missing-doc"),
+            issue("broken.toml", 0, "invalid-toml"),
+            issue("generated/bindings.rs", 1, "todo-comment"),
+            issue("src/lib.rs", 2, "todo-comment"),
+            issue("src/main.rs", 1, "license-header"),
+            issue("src/main.rs", 2, "todo-comment"),
+        ]);
+    }
+
+    #[test]
+    fn test_rule_filtering() {
+        let dir = sample_tree();
+        assert_eq!(lint(dir.path(), LintConfig::default(), Some(&["todo-comment"])), vec![
+            issue("generated/bindings.rs", 1, "todo-comment"),
+            issue("src/lib.rs", 2, "todo-comment"),
+            issue("src/main.rs", 2, "todo-comment"),
+        ]);
+
+        // The config's rules apply unless --rules overrides them
+        let config: LintConfig = toml::from_str("rules = [\"line-length\", \"invalid-toml\"]\nline_length = 100\n").unwrap();
+        assert_eq!(lint(dir.path(), config.clone(), None), vec![
+            issue("broken.toml", 0, "invalid-toml"),
+            issue("src/main.rs", 3, "line-length"),
+        ]);
+        assert_eq!(lint(dir.path(), config, Some(&["missing-doc"])), vec![issue("CHANGELOG.md", 0, "missing-doc")]);
+
+        let error = Linter::new(dir.path(), LintConfig::default(), Some(vec!["no-such-rule".to_string()]), false);
+        assert!(error.is_err());
+    }
+
+    #[test]
+    fn test_ignore_list() {
+        let dir = sample_tree();
+        fs::write(dir.path().join(CONFIG_FILE), "ignore = [\"generated/\", \"*.toml\"]\n").unwrap();
+        let config = LintConfig::load(&dir.path().join(CONFIG_FILE)).unwrap();
+        assert_eq!(config.line_length, 120);
+
+        // Replacing the default ignore list brings target/ back in
+        assert_eq!(lint(dir.path(), config, None), vec![
+            issue("CHANGELOG.md", 0, "missing-doc"),
+            issue("src/lib.rs", 2, "todo-comment"),
+            issue("src/main.rs", 1, "license-header"),
+            issue("src/main.rs", 2, "todo-comment"),
+            issue("target/debug/build.rs", 1, "todo-comment"),
+        ]);
+    }
+
+    #[test]
+    fn test_sarif_output() {
+        let dir = sample_tree();
+        let issues = Linter::new(dir.path(), LintConfig::default(), None, false).unwrap().run().unwrap();
+        let log = sarif(&issues, dir.path());
+
+        assert_eq!(log["version"], "2.1.0");
+        assert!(log["$schema"].as_str().unwrap().contains("sarif-2.1.0"));
+        let run = &log["runs"][0];
+        assert_eq!(run["tool"]["driver"]["name"], "hecate-lint");
+        let rules = run["tool"]["driver"]["rules"].as_array().unwrap();
+        assert_eq!(rules.len(), RULES.len());
+        assert!(rules.iter().all(|rule| rule["id"].is_string() && rule["shortDescription"]["text"].is_string()));
+
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), issues.len());
+        for result in results {
+            assert!(rules.iter().any(|rule| rule["id"] == result["ruleId"]));
+            assert!(["error", "warning", "note"].contains(&result["level"].as_str().unwrap()));
+            assert!(result["message"]["text"].is_string());
+            assert!(result["properties"]["fixable"].is_boolean());
+            let uri = result["locations"][0]["physicalLocation"]["artifactLocation"]["uri"].as_str().unwrap();
+            assert!(!uri.starts_with('/'), "{}", uri);
         }
+
+        let header = results.iter().find(|r| r["ruleId"] == "license-header").unwrap();
+        assert_eq!(header["locations"][0]["physicalLocation"]["artifactLocation"]["uri"], "src/main.rs");
+        assert_eq!(header["locations"][0]["physicalLocation"]["region"]["startLine"], 1);
+        assert_eq!(header["properties"]["fixable"], true);
+        let toml = results.iter().find(|r| r["ruleId"] == "invalid-toml").unwrap();
+        assert_eq!(toml["level"], "error");
+        assert!(toml["locations"][0]["physicalLocation"].get("region").is_none());
     }
-    
-    Ok(())
-}
\ No newline at end of file
+
+    #[test]
+    fn test_json_round_trip() {
+        let dir = sample_tree();
+        let issues = Linter::new(dir.path(), LintConfig::default(), None, false).unwrap().run().unwrap();
+        let json = serde_json::to_string(&issues).unwrap();
+        assert!(json.contains("\"severity\":\"note\""));
+        assert_eq!(serde_json::from_str::<Vec<LintIssue>>(&json).unwrap(), issues);
+    }
+
+    #[test]
+    fn test_license_header_after_inner_attributes() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("lib.rs");
+        fs::write(&file, "#![allow(dead_code)]\n#![cfg_attr(\n    docsrs,\n    feature(doc_cfg)\n)]\n\nfn main() {}\n").unwrap();
+
+        let linter = Linter::new(dir.path(), LintConfig::default(), Some(vec!["license-header".to_string()]), true).unwrap();
+        assert_eq!(linter.run().unwrap().len(), 1);
+        assert_eq!(
+            fs::read_to_string(&file).unwrap(),
+            format!("#![allow(dead_code)]\n#![cfg_attr(\n    docsrs,\n    feature(doc_cfg)\n)]\n\n{}fn main() {{}}\n", LICENSE_HEADER)
+        );
+        // Fixed files are not flagged again
+        assert!(linter.run().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_license_header_after_shebang() {
+        let script = "#!/usr/bin/env run-cargo-script\nfn main() {}\n";
+        assert_eq!(insert_license_header(script), format!("#!/usr/bin/env run-cargo-script\n\n{}fn main() {{}}\n", LICENSE_HEADER));
+        assert!(has_license_header(&insert_license_header(script)));
+
+        assert_eq!(insert_license_header("fn main() {}\n"), format!("{}fn main() {{}}\n", LICENSE_HEADER));
+        assert_eq!(insert_license_header("#![no_std]"), format!("#![no_std]\n\n{}", LICENSE_HEADER));
+    }
+
+    #[test]
+    fn test_existing_spdx_tag_is_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("lib.rs");
+        let content = "#![allow(unused)]\n\n#[doc = \"x\"]\n// SPDX-License-Identifier: Apache-2.0\nfn main() {}\n";
+        fs::write(&file, content).unwrap();
+
+        let linter = Linter::new(dir.path(), LintConfig::default(), Some(vec!["license-header".to_string()]), true).unwrap();
+        assert!(linter.run().unwrap().is_empty());
+        assert_eq!(fs::read_to_string(&file).unwrap(), content);
+    }
+}
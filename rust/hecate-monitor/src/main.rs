@@ -11,6 +11,7 @@ use axum::{
     Router,
 };
 use chrono::{DateTime, Utc};
+use hecate_core::SystemProfile;
 use futures::{sink::SinkExt, stream::StreamExt};
 use serde::{Deserialize, Serialize};
 use std::{
@@ -38,6 +39,8 @@ pub struct SystemMetrics {
     pub disks: Vec<DiskMetrics>,
     pub network: NetworkMetrics,
     pub processes: ProcessMetrics,
+    /// Perfil aplicado por el daemon; en portátiles sigue a la fuente de alimentación
+    pub profile: Option<SystemProfile>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -293,6 +296,9 @@ async fn collect_metrics(system: &System, disks: &Disks, networks: &Networks) ->
         disks: disk_list,
         network,
         processes,
+        profile: hecate_core::power::read_effective_profile(
+            std::path::Path::new(hecate_core::power::EFFECTIVE_PROFILE_PATH),
+        ),
     }
 }
 
@@ -439,6 +445,7 @@ impl Default for SystemMetrics {
                 top_by_cpu: vec![],
                 top_by_memory: vec![],
             },
+            profile: None,
         }
     }
 }
@@ -0,0 +1,5 @@
+-- Version pinning: held packages are skipped by update
+
+ALTER TABLE installed_packages ADD COLUMN held BOOLEAN NOT NULL DEFAULT 0;
+
+CREATE INDEX IF NOT EXISTS idx_installed_packages_held ON installed_packages(held);
@@ -0,0 +1,5 @@
+-- SHA512 checksums: optional, for packages mirrored from SHA512SUMS sources.
+-- Rows written before this migration keep NULL and verify by SHA256/BLAKE3.
+
+ALTER TABLE available_packages ADD COLUMN sha512 TEXT;
+ALTER TABLE installed_packages ADD COLUMN sha512 TEXT;
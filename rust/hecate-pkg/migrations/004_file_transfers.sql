@@ -0,0 +1,15 @@
+-- Files taken over with --overwrite: the previous owner's record, so that
+-- removing the new owner can hand each path back
+
+CREATE TABLE IF NOT EXISTS file_transfers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    path TEXT NOT NULL,
+    from_package TEXT NOT NULL,
+    to_package TEXT NOT NULL,
+    checksum TEXT,
+    size INTEGER NOT NULL,
+    permissions INTEGER NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_file_transfers_to_package ON file_transfers(to_package);
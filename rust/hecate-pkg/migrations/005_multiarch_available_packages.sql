@@ -0,0 +1,41 @@
+-- Multi-arch repositories list a package version once per architecture, so
+-- the architecture becomes part of the key. SQLite cannot change a UNIQUE
+-- constraint in place; the table is rebuilt.
+
+CREATE TABLE available_packages_multiarch (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    repository_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    version TEXT NOT NULL,
+    description TEXT,
+    author TEXT,
+    license TEXT,
+    homepage TEXT,
+    repository_url TEXT,
+    architecture TEXT NOT NULL,
+    size_bytes INTEGER NOT NULL,
+    installed_size_bytes INTEGER NOT NULL,
+    sha256 TEXT NOT NULL,
+    blake3 TEXT NOT NULL,
+    signature TEXT,
+    build_date TIMESTAMP,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    sha512 TEXT,
+    FOREIGN KEY (repository_id) REFERENCES repositories(id) ON DELETE CASCADE,
+    UNIQUE(repository_id, name, version, architecture)
+);
+
+INSERT INTO available_packages_multiarch
+    (id, repository_id, name, version, description, author, license, homepage,
+     repository_url, architecture, size_bytes, installed_size_bytes, sha256,
+     blake3, signature, build_date, created_at, sha512)
+SELECT id, repository_id, name, version, description, author, license, homepage,
+       repository_url, architecture, size_bytes, installed_size_bytes, sha256,
+       blake3, signature, build_date, created_at, sha512
+FROM available_packages;
+
+DROP TABLE available_packages;
+ALTER TABLE available_packages_multiarch RENAME TO available_packages;
+
+CREATE INDEX IF NOT EXISTS idx_available_packages_name ON available_packages(name);
+CREATE INDEX IF NOT EXISTS idx_available_packages_repo ON available_packages(repository_id);
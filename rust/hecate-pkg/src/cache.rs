@@ -7,9 +7,20 @@ use std::path::{Path, PathBuf};
 use tokio::fs;
 use futures::StreamExt;
 use indicatif::{ProgressBar, ProgressStyle, MultiProgress};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+use serde::{Deserialize, Serialize};
 
 use crate::Package;
 
+/// Whether `path` is a package archive as named by [`PackageCache::get_package_path`]
+fn is_package_archive(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.ends_with(".pkg.tar.zst"))
+}
+
 /// Package cache for downloaded packages
 pub struct PackageCache {
     cache_dir: PathBuf,
@@ -28,6 +39,11 @@ impl PackageCache {
         })
     }
 
+    /// Set the size limit enforced by [`PackageCache::enforce_size_limit`]
+    pub fn set_max_cache_size(&mut self, max_cache_size: u64) {
+        self.max_cache_size = max_cache_size;
+    }
+
     /// Get the cache path for a package
     pub fn get_package_path(&self, package: &Package) -> PathBuf {
         let filename = format!("{}-{}.pkg.tar.zst", package.name, package.version);
@@ -70,6 +86,9 @@ impl PackageCache {
     }
 
     /// Clean packages older than specified days
+    ///
+    /// Only package archives are removed; anything else in the cache
+    /// directory is left alone.
     pub async fn clean_old(&self, days: u64) -> Result<u64> {
         use std::time::{SystemTime, Duration};
         
@@ -80,6 +99,9 @@ impl PackageCache {
         while let Some(entry) = dir.next_entry().await? {
             let path = entry.path();
             let metadata = entry.metadata().await?;
+            if !metadata.is_file() || !is_package_archive(&path) {
+                continue;
+            }
             
             if let Ok(modified) = metadata.modified() {
                 if modified < cutoff {
@@ -122,26 +144,9 @@ impl PackageCache {
         })
     }
 
-    /// Verify cache integrity
-    pub async fn verify_integrity(&self) -> Result<Vec<String>> {
-        let mut corrupted = Vec::new();
-
-        let mut dir = fs::read_dir(&self.cache_dir).await?;
-        while let Some(entry) = dir.next_entry().await? {
-            let path = entry.path();
-            
-            if path.extension() == Some(std::ffi::OsStr::new("zst")) {
-                // Try to decompress header to verify integrity
-                if let Ok(file) = std::fs::File::open(&path) {
-                    let decoder = zstd::Decoder::new(file);
-                    if decoder.is_err() {
-                        corrupted.push(path.to_string_lossy().to_string());
-                    }
-                }
-            }
-        }
-
-        Ok(corrupted)
+    /// Prune the oldest packages until the cache fits within `max_cache_size`
+    pub async fn enforce_size_limit(&self) -> Result<u64> {
+        self.prune_to_size(self.max_cache_size).await
     }
 
     /// Prune cache to stay under size limit
@@ -184,7 +189,7 @@ impl PackageCache {
 }
 
 /// Cache statistics
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CacheStats {
     pub total_size: u64,
     pub package_count: usize,
@@ -192,19 +197,146 @@ pub struct CacheStats {
     pub cache_dir: PathBuf,
 }
 
+/// How long to wait for a single host to accept a connection before moving
+/// on to the next mirror
+const MIRROR_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Upper bound for fetching small metadata files such as repository indices
+const METADATA_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Retries of failed downloads, spaced by exponential backoff with jitter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts after the first; 0 disables retrying
+    pub retries: u32,
+    /// Delay before the first retry, doubled for each one after it
+    pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 3,
+            base_delay: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Smallest file fetched as parallel ranges by `download_with_failover`;
+/// below this a single connection is as fast
+const CHUNKED_MIN_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Longest wait between two attempts
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+impl RetryPolicy {
+    /// Wait before retry number `attempt` (from 0): the backoff plus up to
+    /// half of it again at random, so clients don't retry in lockstep
+    pub fn delay(&self, attempt: u32) -> Duration {
+        use std::hash::{BuildHasher, Hasher};
+
+        let backoff = self.base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(MAX_RETRY_DELAY);
+        let random = std::collections::hash_map::RandomState::new().build_hasher().finish();
+        let jitter = backoff.mul_f64((random % 1000) as f64 / 2000.0);
+        backoff + jitter
+    }
+}
+
+/// Whether a failed request may succeed if repeated: timeouts, dropped
+/// connections and 502/503/504 responses
+///
+/// Other statuses such as 404 and refused connections are final; a mirror
+/// that isn't listening is better skipped than waited for.
+pub fn is_transient(err: &anyhow::Error) -> bool {
+    use std::io::ErrorKind;
+
+    for cause in err.chain() {
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            if let Some(status) = e.status() {
+                return matches!(status.as_u16(), 502..=504);
+            }
+            if e.is_timeout() || e.is_body() {
+                return true;
+            }
+        }
+        if let Some(e) = cause.downcast_ref::<std::io::Error>() {
+            return matches!(e.kind(),
+                ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted |
+                ErrorKind::BrokenPipe | ErrorKind::TimedOut | ErrorKind::UnexpectedEof);
+        }
+    }
+    false
+}
+
+/// Token bucket capping the combined rate of every download sharing it
+///
+/// The bucket holds at most one second's worth of bytes. Callers take tokens
+/// for each chunk they receive and sleep off any deficit, so concurrent
+/// downloads split the configured rate between them.
+pub struct RateLimiter {
+    bytes_per_sec: u64,
+    state: tokio::sync::Mutex<(f64, Instant)>, // (available tokens, last refill)
+}
+
+impl RateLimiter {
+    /// Limit to `bytes_per_sec`; 0 means unlimited
+    pub fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec,
+            state: tokio::sync::Mutex::new((bytes_per_sec as f64, Instant::now())),
+        }
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.bytes_per_sec == 0
+    }
+
+    /// Account for `bytes` just received, waiting until the rate allows them
+    pub async fn acquire(&self, bytes: usize) {
+        if self.is_unlimited() {
+            return;
+        }
+
+        let rate = self.bytes_per_sec as f64;
+        let wait = {
+            let mut state = self.state.lock().await;
+            let (tokens, last) = &mut *state;
+            let now = Instant::now();
+            *tokens = (*tokens + now.duration_since(*last).as_secs_f64() * rate).min(rate);
+            *last = now;
+            *tokens -= bytes as f64;
+            if *tokens < 0.0 {
+                Duration::from_secs_f64(-*tokens / rate)
+            } else {
+                Duration::ZERO
+            }
+        };
+
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
 /// Parallel download manager
 pub struct DownloadManager {
     client: reqwest::Client,
     parallel_downloads: usize,
     progress: MultiProgress,
+    limiter: Arc<RateLimiter>,
+    retry: RetryPolicy,
 }
 
 impl DownloadManager {
-    /// Create a new download manager
-    pub fn new(parallel_downloads: usize) -> Self {
+    /// Create a new download manager whose downloads together stay under
+    /// `max_bytes_per_sec` (0 for unlimited)
+    pub fn new(parallel_downloads: usize, max_bytes_per_sec: u64) -> Self {
         let client = reqwest::Client::builder()
             .user_agent("hecate-pkg/0.1.0")
-            .timeout(std::time::Duration::from_secs(300))
+            .timeout(Duration::from_secs(300))
+            .connect_timeout(MIRROR_CONNECT_TIMEOUT)
             .build()
             .expect("Failed to create HTTP client");
 
@@ -212,6 +344,35 @@ impl DownloadManager {
             client,
             parallel_downloads,
             progress: MultiProgress::new(),
+            limiter: Arc::new(RateLimiter::new(max_bytes_per_sec)),
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// Retry transient failures according to `retry`
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Run a GET through `op`, repeating it while it fails transiently and
+    /// retries remain; the last error is returned as is
+    async fn retrying<T, F, Fut>(&self, url: &str, mut op: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match op().await {
+                Err(e) if attempt < self.retry.retries && is_transient(&e) => {
+                    let delay = self.retry.delay(attempt);
+                    warn!("Fetching {} failed ({:#}), retrying in {:.1}s", url, e, delay.as_secs_f64());
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
         }
     }
 
@@ -281,16 +442,216 @@ impl DownloadManager {
         use tokio::io::AsyncWriteExt;
         while let Some(chunk) = stream.next().await {
             let chunk = chunk.context("Failed to read chunk")?;
+            self.limiter.acquire(chunk.len()).await;
             file.write_all(&chunk).await
                 .context("Failed to write chunk")?;
             pb.inc(chunk.len() as u64);
         }
+        file.flush().await.context("Failed to write chunk")?;
 
         pb.finish_with_message(format!("Downloaded {}", filename));
 
         Ok(destination)
     }
 
+    /// Fetch a small file into memory
+    async fn fetch(&self, url: &str) -> Result<Vec<u8>> {
+        let response = self.client.get(url)
+            .timeout(METADATA_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    /// Fetch a small file into memory, trying each URL in order, and check
+    /// it against the SHA256 published next to it as `<url>.sha256`
+    ///
+    /// The data and its checksum come from the same mirror, so a mirror
+    /// serving a truncated or stale file falls through to the next one.
+    pub async fn fetch_verified_with_failover(&self, urls: &[String]) -> Result<Vec<u8>> {
+        let mut errors = Vec::new();
+
+        for url in urls {
+            let result = async {
+                let checksum_url = format!("{}.sha256", url);
+                let checksum = self.retrying(&checksum_url, || self.fetch(&checksum_url)).await
+                    .context("Failed to fetch checksum")?;
+                let data = self.retrying(url, || self.fetch(url)).await?;
+                verify_sha256(&data, &String::from_utf8_lossy(&checksum))?;
+                Ok::<_, anyhow::Error>(data)
+            }.await;
+
+            match result {
+                Ok(data) => {
+                    info!("Fetched and verified {}", url);
+                    return Ok(data);
+                }
+                Err(e) => {
+                    warn!("Mirror failed for {}: {:#}", url, e);
+                    errors.push(format!("{}: {:#}", url, e));
+                }
+            }
+        }
+
+        Err(failover_error(&errors))
+    }
+
+    /// Download a file with resume support, trying each URL in order
+    pub async fn download_with_failover(
+        &self,
+        urls: &[String],
+        destination: &Path,
+        expected_size: u64,
+    ) -> Result<PathBuf> {
+        let mut errors = Vec::new();
+
+        // Large fresh downloads go over several connections; partial ones
+        // resume where they left off
+        let chunked = expected_size >= CHUNKED_MIN_SIZE
+            && self.parallel_downloads > 1
+            && !destination.exists();
+
+        for url in urls {
+            let download = self.retrying(url, || async {
+                if chunked {
+                    self.download_chunked(url, destination, expected_size, self.parallel_downloads).await
+                } else {
+                    self.download_with_resume(url, destination, expected_size).await
+                }
+            });
+            match download.await {
+                Ok(path) => {
+                    info!("Downloaded {} from {}", path.display(), url);
+                    return Ok(path);
+                }
+                Err(e) => {
+                    warn!("Mirror failed for {}: {}", url, e);
+                    errors.push(format!("{}: {}", url, e));
+                }
+            }
+        }
+
+        Err(failover_error(&errors))
+    }
+
+    /// Download `url` as `parts` byte ranges fetched concurrently, each
+    /// written at its offset into a file preallocated to `expected_size`
+    ///
+    /// The data goes to a `.chunked` file next to `destination` that is
+    /// renamed into place once every range is in and the size matches, so
+    /// a failed attempt never leaves a full-length but incomplete file. If
+    /// the server ignores ranges and answers 200, the body is taken as a
+    /// single stream instead.
+    pub async fn download_chunked(
+        &self,
+        url: &str,
+        destination: &Path,
+        expected_size: u64,
+        parts: usize,
+    ) -> Result<PathBuf> {
+        use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+
+        if expected_size == 0 {
+            return Err(anyhow::anyhow!("Chunked download of {} needs its size", url));
+        }
+        let parts = (parts.max(1) as u64).min(expected_size);
+        let chunk_size = expected_size.div_ceil(parts);
+        let ranges: Vec<(u64, u64)> = (0..parts)
+            .map(|i| (i * chunk_size, ((i + 1) * chunk_size).min(expected_size) - 1))
+            .filter(|(start, end)| start <= end)
+            .collect();
+
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let mut partial = destination.as_os_str().to_owned();
+        partial.push(".chunked");
+        let partial = PathBuf::from(partial);
+
+        let pb = self.progress.add(ProgressBar::new(expected_size));
+        pb.set_style(
+            ProgressStyle::default_bar()
+                .template("[{elapsed_precise}] {bar:40.cyan/blue} {bytes}/{total_bytes} {msg}")?
+                .progress_chars("##-"),
+        );
+        pb.set_message(format!("Downloading {} in {} parts",
+            destination.file_name().unwrap_or_default().to_string_lossy(), ranges.len()));
+
+        let result = async {
+            fs::File::create(&partial).await?.set_len(expected_size).await?;
+
+            let request = |(start, end): (u64, u64)| async move {
+                Ok::<_, anyhow::Error>(self.client.get(url)
+                    .header("Range", format!("bytes={}-{}", start, end))
+                    .send()
+                    .await?
+                    .error_for_status()?)
+            };
+            let fetch_range = |(start, end): (u64, u64), response: Option<reqwest::Response>| {
+                let (partial, pb) = (&partial, pb.clone());
+                async move {
+                    let response = match response {
+                        Some(response) => response,
+                        None => request((start, end)).await?,
+                    };
+                    let whole = response.status() != reqwest::StatusCode::PARTIAL_CONTENT;
+
+                    let mut file = fs::OpenOptions::new().write(true).open(partial).await?;
+                    file.seek(std::io::SeekFrom::Start(if whole { 0 } else { start })).await?;
+                    let mut received = 0u64;
+                    let mut stream = response.bytes_stream();
+                    while let Some(chunk) = stream.next().await {
+                        let chunk = chunk?;
+                        self.limiter.acquire(chunk.len()).await;
+                        file.write_all(&chunk).await?;
+                        received += chunk.len() as u64;
+                        pb.inc(chunk.len() as u64);
+                    }
+                    file.flush().await?;
+
+                    let wanted = if whole { expected_size } else { end - start + 1 };
+                    if received != wanted {
+                        return Err(anyhow::anyhow!(
+                            "Range {}-{} of {} returned {} of {} bytes", start, end, url, received, wanted
+                        ));
+                    }
+                    Ok::<_, anyhow::Error>(())
+                }
+            };
+
+            // The first response tells whether the server supports ranges at all
+            let mut slices = ranges.into_iter();
+            let first = slices.next().expect("at least one range");
+            let response = request(first).await?;
+            if response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+                warn!("{} does not support range requests, downloading as a single stream", url);
+                fetch_range(first, Some(response)).await?;
+            } else {
+                let rest = slices.map(|range| fetch_range(range, None));
+                futures::future::try_join_all(std::iter::once(fetch_range(first, Some(response))).chain(rest)).await?;
+            }
+
+            let size = fs::metadata(&partial).await?.len();
+            if size != expected_size {
+                return Err(anyhow::anyhow!(
+                    "Downloaded {} bytes of {} but expected {}", size, url, expected_size
+                ));
+            }
+            fs::rename(&partial, destination).await?;
+            Ok(())
+        }.await;
+
+        if let Err(e) = result {
+            fs::remove_file(&partial).await.ok();
+            pb.abandon_with_message(format!("Failed {}", destination.display()));
+            return Err(e);
+        }
+
+        pb.finish_with_message(format!("Completed {}", destination.file_name().unwrap_or_default().to_string_lossy()));
+        Ok(destination.to_path_buf())
+    }
+
     /// Download with resume support
     pub async fn download_with_resume(
         &self,
@@ -304,22 +665,27 @@ impl DownloadManager {
             let metadata = fs::metadata(destination).await?;
             resume_from = metadata.len();
             
-            if resume_from >= expected_size {
+            if expected_size > 0 && resume_from >= expected_size {
                 // Already fully downloaded
                 return Ok(destination.to_path_buf());
             }
         }
 
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
         // Create request with range header for resume
         let mut request = self.client.get(url);
         if resume_from > 0 {
             request = request.header("Range", format!("bytes={}-", resume_from));
         }
 
-        let response = request.send().await?;
-        
-        if !response.status().is_success() && response.status() != 206 {
-            return Err(anyhow::anyhow!("Download failed with status: {}", response.status()));
+        let response = request.send().await?.error_for_status()?;
+
+        // Servers that ignore the range send the whole file again
+        if response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+            resume_from = 0;
         }
 
         // Create progress bar
@@ -336,6 +702,7 @@ impl DownloadManager {
         let mut file = fs::OpenOptions::new()
             .create(true)
             .append(resume_from > 0)
+            .truncate(resume_from == 0)
             .write(true)
             .open(destination)
             .await?;
@@ -346,9 +713,12 @@ impl DownloadManager {
         
         while let Some(chunk) = stream.next().await {
             let chunk = chunk?;
+            self.limiter.acquire(chunk.len()).await;
             file.write_all(&chunk).await?;
             pb.inc(chunk.len() as u64);
         }
+        // tokio hands writes to a blocking thread; wait for the last one
+        file.flush().await?;
 
         pb.finish_with_message(format!("Completed {}", destination.file_name().unwrap_or_default().to_string_lossy()));
 
@@ -356,6 +726,33 @@ impl DownloadManager {
     }
 }
 
+/// Check `data` against a checksum file in `sha256sum` format, where the
+/// digest may be followed by a file name
+pub fn verify_sha256(data: &[u8], checksum_file: &str) -> Result<()> {
+    use sha2::{Digest, Sha256};
+
+    let expected = checksum_file.split_whitespace().next().unwrap_or_default().to_ascii_lowercase();
+    if expected.len() != 64 || !expected.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(anyhow::anyhow!("Malformed SHA256 checksum '{}'", checksum_file.trim()));
+    }
+
+    let actual = hex::encode(Sha256::digest(data));
+    if actual != expected {
+        return Err(anyhow::anyhow!(
+            "SHA256 checksum mismatch: expected {}, got {}", expected, actual
+        ));
+    }
+    Ok(())
+}
+
+/// Combine per-mirror errors into a single error
+fn failover_error(errors: &[String]) -> anyhow::Error {
+    if errors.is_empty() {
+        return anyhow::anyhow!("No URLs to download from");
+    }
+    anyhow::anyhow!("All mirrors failed:\n  {}", errors.join("\n  "))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -396,6 +793,7 @@ mod tests {
             checksum: crate::PackageChecksum {
                 sha256: String::new(),
                 blake3: String::new(),
+                sha512: None,
             },
             signature: None,
             build_date: chrono::Utc::now(),
@@ -407,4 +805,55 @@ mod tests {
         let delta_path = cache.get_delta_path(&package, "0.9.0");
         assert!(delta_path.to_string_lossy().contains("test-0.9.0-to-1.0.0.delta.zst"));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_size_limit_prunes_oldest() {
+        use std::time::{Duration, SystemTime};
+
+        let dir = tempdir().unwrap();
+        let mut cache = PackageCache::new(dir.path()).unwrap();
+        cache.set_max_cache_size(250);
+
+        // Three 100 byte packages, each newer than the last
+        let base = SystemTime::now() - Duration::from_secs(3600);
+        for (i, name) in ["old", "middle", "new"].iter().enumerate() {
+            let path = dir.path().join(format!("{}-1.0.0.pkg.tar.zst", name));
+            std::fs::write(&path, vec![0u8; 100]).unwrap();
+            let file = std::fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(base + Duration::from_secs(i as u64 * 60)).unwrap();
+        }
+
+        let freed = cache.enforce_size_limit().await.unwrap();
+        assert_eq!(freed, 100);
+        assert!(!dir.path().join("old-1.0.0.pkg.tar.zst").exists());
+        assert!(dir.path().join("middle-1.0.0.pkg.tar.zst").exists());
+        assert!(dir.path().join("new-1.0.0.pkg.tar.zst").exists());
+
+        // Within the limit nothing is removed
+        assert_eq!(cache.enforce_size_limit().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_clean_old_only_removes_package_archives() {
+        use std::time::{Duration, SystemTime};
+
+        let dir = tempdir().unwrap();
+        let cache = PackageCache::new(dir.path()).unwrap();
+
+        let old = SystemTime::now() - Duration::from_secs(10 * 24 * 3600);
+        for name in ["foo-1.0.0.pkg.tar.zst", "foo-1.0.0.pkg.tar.zst.part", "notes.txt", "recent-1.0.0.pkg.tar.zst"] {
+            let path = dir.path().join(name);
+            std::fs::write(&path, vec![0u8; 10]).unwrap();
+            if name != "recent-1.0.0.pkg.tar.zst" {
+                let file = std::fs::File::options().write(true).open(&path).unwrap();
+                file.set_modified(old).unwrap();
+            }
+        }
+
+        assert_eq!(cache.clean_old(7).await.unwrap(), 10);
+        assert!(!dir.path().join("foo-1.0.0.pkg.tar.zst").exists());
+        assert!(dir.path().join("foo-1.0.0.pkg.tar.zst.part").exists());
+        assert!(dir.path().join("notes.txt").exists());
+        assert!(dir.path().join("recent-1.0.0.pkg.tar.zst").exists());
+    }
+}
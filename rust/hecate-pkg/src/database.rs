@@ -6,6 +6,8 @@
 use anyhow::{Result, Context};
 use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
+use sqlx::sqlite::SqliteConnectOptions;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 use chrono::{DateTime, Utc};
 
@@ -14,6 +16,16 @@ use crate::{
     RepositoryIndex, Repository, Architecture, PackageChecksum,
     Dependency,
 };
+use crate::overwrite::FileTransfer;
+
+/// Schema migrations, applied in order and tracked via `PRAGMA user_version`
+const MIGRATIONS: &[&str] = &[
+    include_str!("../migrations/001_initial.sql"),
+    include_str!("../migrations/002_package_holds.sql"),
+    include_str!("../migrations/003_sha512_checksums.sql"),
+    include_str!("../migrations/004_file_transfers.sql"),
+    include_str!("../migrations/005_multiarch_available_packages.sql"),
+];
 
 /// Package database for tracking installations
 pub struct PackageDatabase {
@@ -30,27 +42,41 @@ impl PackageDatabase {
         }
 
         // Connect to database
-        let database_url = format!("sqlite://{}", path.display());
-        let pool = SqlitePool::connect(&database_url)
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true);
+        let pool = SqlitePool::connect_with(options)
             .await
             .context("Failed to connect to database")?;
 
         // Run migrations
-        Self::run_migrations(&pool).await?;
+        Self::run_migrations(&pool, MIGRATIONS).await?;
 
         Ok(Self { pool })
     }
 
-    /// Run database migrations
-    async fn run_migrations(pool: &SqlitePool) -> Result<()> {
-        // Read migration SQL
-        let migration = include_str!("../migrations/001_initial.sql");
-        
-        // Execute migration
-        sqlx::query(migration)
-            .execute(pool)
-            .await
-            .context("Failed to run database migrations")?;
+    /// Run the `migrations` not yet applied, as counted by `user_version`
+    async fn run_migrations(pool: &SqlitePool, migrations: &[&str]) -> Result<()> {
+        let (current,): (i64,) = sqlx::query_as("PRAGMA user_version")
+            .fetch_one(pool)
+            .await?;
+
+        // Execute pending migrations, each with its version bump in one
+        // transaction so a failed migration is not left half applied
+        for (idx, migration) in migrations.iter().enumerate().skip(current as usize) {
+            let mut tx = pool.begin().await?;
+
+            sqlx::query(migration)
+                .execute(&mut *tx)
+                .await
+                .with_context(|| format!("Failed to run database migration {}", idx + 1))?;
+
+            sqlx::query(&format!("PRAGMA user_version = {}", idx + 1))
+                .execute(&mut *tx)
+                .await?;
+
+            tx.commit().await?;
+        }
 
         Ok(())
     }
@@ -69,11 +95,11 @@ impl PackageDatabase {
     pub async fn get_installed_package(&self, name: &str) -> Result<InstalledPackage> {
         // Fetch package data
         let row: (i64, String, String, Option<String>, Option<String>, Option<String>,
-                 String, i64, String, String, String, String, String) = sqlx::query_as(
+                 String, i64, String, String, String, String, String, Option<String>) = sqlx::query_as(
             r#"
             SELECT id, name, version, description, author, license,
                    architecture, size_bytes, install_date, install_path,
-                   install_reason, sha256, blake3
+                   install_reason, sha256, blake3, sha512
             FROM installed_packages
             WHERE name = ?
             "#
@@ -139,6 +165,7 @@ impl PackageDatabase {
             checksum: PackageChecksum {
                 sha256: row.11,
                 blake3: row.12,
+                sha512: row.13,
             },
             signature: None,
             build_date: Utc::now(),
@@ -166,9 +193,26 @@ impl PackageDatabase {
         })
     }
 
+    /// Get the reason an installed package was installed
+    pub async fn get_install_reason(&self, package_name: &str) -> Result<InstallReason> {
+        let row: (String,) = sqlx::query_as(
+            "SELECT install_reason FROM installed_packages WHERE name = ?"
+        )
+        .bind(package_name)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Package {} is not installed", package_name))?;
+
+        Ok(match row.0.as_str() {
+            "dependency" => InstallReason::Dependency,
+            "group" => InstallReason::Group,
+            _ => InstallReason::Explicit,
+        })
+    }
+
     /// Get all installed packages
     pub async fn get_installed_packages(&self) -> Result<Vec<InstalledPackage>> {
-        let names: Vec<(String,)> = sqlx::query_as("SELECT name FROM installed_packages")
+        let names: Vec<(String,)> = sqlx::query_as("SELECT name FROM installed_packages ORDER BY name")
             .fetch_all(&self.pool)
             .await?;
 
@@ -182,7 +226,23 @@ impl PackageDatabase {
         Ok(packages)
     }
 
-    /// Get packages that depend on a specific package
+    /// Get names and versions of explicitly installed packages and of the
+    /// dependencies installed for them, flagging the latter
+    pub async fn get_world_packages(&self) -> Result<Vec<(String, semver::Version, bool)>> {
+        let rows: Vec<(String, String, String)> = sqlx::query_as(
+            "SELECT name, version, install_reason FROM installed_packages
+             WHERE install_reason IN ('explicit', 'dependency') ORDER BY name"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|(name, version, reason)| Ok((name, semver::Version::parse(&version)?, reason == "dependency")))
+            .collect()
+    }
+
+    /// Get packages that depend on a specific package, by name or by
+    /// anything it provides
     pub async fn get_dependents(&self, package_name: &str) -> Result<Vec<String>> {
         let rows: Vec<(String,)> = sqlx::query_as(
             r#"
@@ -190,9 +250,55 @@ impl PackageDatabase {
             FROM installed_packages ip
             JOIN dependencies d ON ip.id = d.package_id
             WHERE d.depends_on = ?
+               OR d.depends_on IN (
+                   SELECT p.provides
+                   FROM provides p
+                   JOIN installed_packages target ON p.package_id = target.id
+                   WHERE target.name = ?
+               )
+            ORDER BY ip.name
             "#
         )
         .bind(package_name)
+        .bind(package_name)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| r.0).collect())
+    }
+
+    /// Paths recorded for an installed package, relative to its install
+    /// root and sorted
+    pub async fn get_package_files(&self, package_name: &str) -> Result<Vec<String>> {
+        let rows: Vec<(String,)> = sqlx::query_as(
+            r#"
+            SELECT f.path
+            FROM installed_files f
+            JOIN installed_packages ip ON f.package_id = ip.id
+            WHERE ip.name = ?
+            ORDER BY f.path
+            "#
+        )
+        .bind(package_name)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| r.0).collect())
+    }
+
+    /// Installed packages that recorded `path` (relative to the install
+    /// root); directories may be shared by several
+    pub async fn get_file_owners(&self, path: &str) -> Result<Vec<String>> {
+        let rows: Vec<(String,)> = sqlx::query_as(
+            r#"
+            SELECT DISTINCT ip.name
+            FROM installed_files f
+            JOIN installed_packages ip ON f.package_id = ip.id
+            WHERE rtrim(f.path, '/') = ?
+            ORDER BY ip.name
+            "#
+        )
+        .bind(path.trim_end_matches('/'))
         .fetch_all(&self.pool)
         .await?;
 
@@ -222,6 +328,12 @@ impl PackageDatabase {
             .execute(&mut *tx)
             .await?;
 
+        // Delete the log of files it took over
+        sqlx::query("DELETE FROM file_transfers WHERE to_package = ?")
+            .bind(package_name)
+            .execute(&mut *tx)
+            .await?;
+
         // Delete provides
         sqlx::query("DELETE FROM provides WHERE package_id = ?")
             .bind(row.0)
@@ -248,29 +360,63 @@ impl PackageDatabase {
 
     /// Record a package installation
     pub async fn record_installation(&self, installed: InstalledPackage) -> Result<()> {
+        self.record_installation_with_transfers(installed, &[]).await
+    }
+
+    /// Record a package installation that took files over from other
+    /// packages
+    ///
+    /// Each path leaves its previous owner's file list and the transfer is
+    /// logged, in the same transaction, for [`Self::restore_file_transfers`].
+    pub async fn record_installation_with_transfers(
+        &self,
+        installed: InstalledPackage,
+        transfers: &[FileTransfer],
+    ) -> Result<()> {
         // Start transaction
         let mut tx = self.pool.begin().await?;
 
+        for transfer in transfers {
+            let path = transfer.file.path.to_string_lossy();
+            sqlx::query(
+                r#"
+                DELETE FROM installed_files
+                WHERE package_id = (SELECT id FROM installed_packages WHERE name = ?)
+                  AND rtrim(path, '/') = ?
+                "#
+            )
+            .bind(&transfer.from_package)
+            .bind(path.trim_end_matches('/'))
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query(
+                r#"
+                INSERT INTO file_transfers (path, from_package, to_package, checksum, size, permissions)
+                VALUES (?, ?, ?, ?, ?, ?)
+                "#
+            )
+            .bind(path.as_ref())
+            .bind(&transfer.from_package)
+            .bind(&installed.package.name)
+            .bind(&transfer.file.checksum)
+            .bind(transfer.file.size as i64)
+            .bind(transfer.file.permissions as i64)
+            .execute(&mut *tx)
+            .await?;
+        }
+
         // Insert package
-        let install_reason = match installed.install_reason {
-            InstallReason::Explicit => "explicit",
-            InstallReason::Dependency => "dependency",
-            InstallReason::Group => "group",
-        };
+        let install_reason = install_reason_str(&installed.install_reason);
 
-        let architecture = match installed.package.architecture {
-            Architecture::X86_64 => "x86_64",
-            Architecture::Aarch64 => "aarch64",
-            Architecture::Riscv64 => "riscv64",
-            Architecture::All => "all",
-        };
+        let architecture = installed.package.architecture.as_str();
 
         let package_id = sqlx::query(
             r#"
             INSERT INTO installed_packages 
             (name, version, description, author, license, architecture, 
-             size_bytes, install_date, install_path, install_reason, sha256, blake3)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+             size_bytes, install_date, install_path, install_reason, sha256, blake3, sha512)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#
         )
         .bind(&installed.package.name)
@@ -285,6 +431,7 @@ impl PackageDatabase {
         .bind(install_reason)
         .bind(&installed.package.checksum.sha256)
         .bind(&installed.package.checksum.blake3)
+        .bind(installed.package.checksum.sha512.as_ref())
         .execute(&mut *tx)
         .await?
         .last_insert_rowid();
@@ -357,24 +504,207 @@ impl PackageDatabase {
         Ok(())
     }
 
-    /// Find orphaned packages (installed as dependencies but no longer needed)
-    pub async fn find_orphans(&self) -> Result<Vec<String>> {
+    /// Files a package took over from other packages
+    pub async fn get_file_transfers(&self, to_package: &str) -> Result<Vec<FileTransfer>> {
+        let rows: Vec<(String, String, Option<String>, i64, i64)> = sqlx::query_as(
+            r#"
+            SELECT path, from_package, checksum, size, permissions
+            FROM file_transfers
+            WHERE to_package = ?
+            ORDER BY id
+            "#
+        )
+        .bind(to_package)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| FileTransfer {
+            from_package: row.1,
+            file: InstalledFile {
+                path: row.0.into(),
+                checksum: row.2.unwrap_or_default(),
+                size: row.3 as u64,
+                permissions: row.4 as u32,
+            },
+        }).collect())
+    }
+
+    /// Hand the files a package took over back to their previous owners
+    ///
+    /// Owners removed in the meantime are skipped. The transfer log of
+    /// `to_package` is cleared either way.
+    pub async fn restore_file_transfers(&self, to_package: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO installed_files (package_id, path, checksum, size, permissions)
+            SELECT ip.id, t.path, t.checksum, t.size, t.permissions
+            FROM file_transfers t
+            JOIN installed_packages ip ON ip.name = t.from_package
+            WHERE t.to_package = ?
+            "#
+        )
+        .bind(to_package)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("DELETE FROM file_transfers WHERE to_package = ?")
+            .bind(to_package)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Get installed packages that declare a conflict with the given package
+    pub async fn get_conflicting_packages(&self, package_name: &str) -> Result<Vec<String>> {
         let rows: Vec<(String,)> = sqlx::query_as(
             r#"
-            SELECT name FROM installed_packages
-            WHERE install_reason = 'dependency'
-            AND name NOT IN (
-                SELECT DISTINCT depends_on FROM dependencies
-                WHERE depends_on IS NOT NULL
-            )
+            SELECT DISTINCT ip.name
+            FROM installed_packages ip
+            JOIN conflicts c ON ip.id = c.package_id
+            WHERE c.conflicts_with = ?
             "#
         )
+        .bind(package_name)
         .fetch_all(&self.pool)
         .await?;
 
         Ok(rows.into_iter().map(|r| r.0).collect())
     }
 
+    /// Change the recorded reason an installed package was installed
+    pub async fn set_install_reason(&self, package_name: &str, reason: &InstallReason) -> Result<()> {
+        let result = sqlx::query("UPDATE installed_packages SET install_reason = ? WHERE name = ?")
+            .bind(install_reason_str(reason))
+            .bind(package_name)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("Package {} is not installed", package_name));
+        }
+
+        Ok(())
+    }
+
+    /// Set or clear the hold flag on an installed package
+    pub async fn set_held(&self, package_name: &str, held: bool) -> Result<()> {
+        let result = sqlx::query("UPDATE installed_packages SET held = ? WHERE name = ?")
+            .bind(held as i32)
+            .bind(package_name)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("Package {} is not installed", package_name));
+        }
+
+        Ok(())
+    }
+
+    /// Check if a package is held at its installed version
+    pub async fn is_held(&self, package_name: &str) -> Result<bool> {
+        let result: (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM installed_packages WHERE name = ? AND held = 1"
+        )
+        .bind(package_name)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(result.0 > 0)
+    }
+
+    /// Get all held packages
+    pub async fn get_held_packages(&self) -> Result<Vec<String>> {
+        let rows: Vec<(String,)> = sqlx::query_as(
+            "SELECT name FROM installed_packages WHERE held = 1 ORDER BY name"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| r.0).collect())
+    }
+
+    /// Find orphaned packages (installed as dependencies but no longer needed)
+    ///
+    /// A dependency-installed package is kept if it can be reached from any
+    /// explicitly or group-installed package through the dependency graph.
+    /// Dependencies resolve through `provides` as well as package names, and
+    /// optional dependencies also keep their target installed.
+    pub async fn find_orphans(&self) -> Result<Vec<String>> {
+        self.find_orphans_without(&HashSet::new()).await
+    }
+
+    /// Dependency packages that would be orphaned if `removed` were uninstalled
+    ///
+    /// Packages in `removed` are neither roots nor traversed, and are never
+    /// reported themselves.
+    pub async fn find_orphans_without(&self, removed: &HashSet<String>) -> Result<Vec<String>> {
+        let packages: Vec<(i64, String, String)> = sqlx::query_as(
+            "SELECT id, name, install_reason FROM installed_packages ORDER BY name"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let dependencies: Vec<(i64, String)> = sqlx::query_as(
+            "SELECT package_id, depends_on FROM dependencies"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let provides: Vec<(i64, String)> = sqlx::query_as(
+            "SELECT package_id, provides FROM provides"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        // Every name a package answers to: its own plus anything it provides
+        let mut providers: HashMap<&str, Vec<i64>> = HashMap::new();
+        for (id, name, _) in &packages {
+            providers.entry(name.as_str()).or_default().push(*id);
+        }
+        for (id, name) in &provides {
+            providers.entry(name.as_str()).or_default().push(*id);
+        }
+
+        let mut edges: HashMap<i64, Vec<&str>> = HashMap::new();
+        for (id, depends_on) in &dependencies {
+            edges.entry(*id).or_default().push(depends_on.as_str());
+        }
+
+        // Walk the graph from everything the user asked for
+        let mut reachable = HashSet::new();
+        let excluded: HashSet<i64> = packages.iter()
+            .filter(|(_, name, _)| removed.contains(name))
+            .map(|(id, _, _)| *id)
+            .collect();
+        let mut queue: Vec<i64> = packages.iter()
+            .filter(|(_, _, reason)| reason != "dependency")
+            .map(|(id, _, _)| *id)
+            .collect();
+
+        while let Some(id) = queue.pop() {
+            if excluded.contains(&id) || !reachable.insert(id) {
+                continue;
+            }
+            for name in edges.get(&id).into_iter().flatten() {
+                if let Some(ids) = providers.get(name) {
+                    queue.extend(ids.iter().filter(|id| !reachable.contains(*id)));
+                }
+            }
+        }
+
+        Ok(packages.into_iter()
+            .filter(|(id, _, reason)| {
+                reason == "dependency" && !reachable.contains(id) && !excluded.contains(id)
+            })
+            .map(|(_, name, _)| name)
+            .collect())
+    }
+
     /// Get all repository indices
     pub async fn get_repository_indices(&self) -> Result<Vec<RepositoryIndex>> {
         let rows: Vec<(i64, String, String, i32, i32, i32, Option<String>, Option<String>, Option<Vec<u8>>)> = sqlx::query_as(
@@ -419,6 +749,40 @@ impl PackageDatabase {
         Ok(indices)
     }
 
+    /// Enable or disable the synced index of a repository
+    pub async fn set_repository_enabled(&self, name: &str, enabled: bool) -> Result<()> {
+        sqlx::query("UPDATE repositories SET enabled = ?, updated_at = CURRENT_TIMESTAMP WHERE name = ?")
+            .bind(enabled as i32)
+            .bind(name)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Drop a repository and everything synced from it
+    pub async fn remove_repository(&self, name: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        for table in ["repository_index", "available_packages"] {
+            sqlx::query(&format!(
+                "DELETE FROM {} WHERE repository_id IN (SELECT id FROM repositories WHERE name = ?)",
+                table
+            ))
+            .bind(name)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        sqlx::query("DELETE FROM repositories WHERE name = ?")
+            .bind(name)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
     /// Update repository index
     pub async fn update_repository_index(&self, index: RepositoryIndex) -> Result<()> {
         // Serialize and compress index
@@ -475,20 +839,15 @@ impl PackageDatabase {
         // Insert available packages
         for (_name, versions) in &index.packages {
             for pkg in versions {
-                let architecture = match pkg.architecture {
-                    Architecture::X86_64 => "x86_64",
-                    Architecture::Aarch64 => "aarch64",
-                    Architecture::Riscv64 => "riscv64",
-                    Architecture::All => "all",
-                };
+                let architecture = pkg.architecture.as_str();
                 
                 sqlx::query(
                     r#"
                     INSERT INTO available_packages
                     (repository_id, name, version, description, author, license,
                      homepage, repository_url, architecture, size_bytes, 
-                     installed_size_bytes, sha256, blake3, signature, build_date)
-                    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                     installed_size_bytes, sha256, blake3, sha512, signature, build_date)
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
                     "#
                 )
                 .bind(repo_id)
@@ -504,6 +863,7 @@ impl PackageDatabase {
                 .bind(pkg.installed_size_bytes as i64)
                 .bind(&pkg.checksum.sha256)
                 .bind(&pkg.checksum.blake3)
+                .bind(pkg.checksum.sha512.as_ref())
                 .bind(pkg.signature.as_ref())
                 .bind(pkg.build_date.to_rfc3339())
                 .execute(&mut *tx)
@@ -513,12 +873,15 @@ impl PackageDatabase {
         
         // Update package groups
         for (group_name, packages) in &index.groups {
-            // Insert or get group ID
-            let group_id = sqlx::query("INSERT OR IGNORE INTO package_groups (name) VALUES (?)")
+            // Insert or get group ID; an ignored insert leaves no row ID
+            sqlx::query("INSERT OR IGNORE INTO package_groups (name) VALUES (?)")
                 .bind(group_name)
                 .execute(&mut *tx)
-                .await?
-                .last_insert_rowid();
+                .await?;
+            let (group_id,): (i64,) = sqlx::query_as("SELECT id FROM package_groups WHERE name = ?")
+                .bind(group_name)
+                .fetch_one(&mut *tx)
+                .await?;
             
             // Clear old members
             sqlx::query("DELETE FROM group_members WHERE group_id = ?")
@@ -581,6 +944,49 @@ impl PackageDatabase {
         Ok(())
     }
 
+    /// Get transactions that were started but never completed or failed
+    pub async fn get_pending_transactions(&self) -> Result<Vec<(i64, String, String)>> {
+        let rows: Vec<(i64, String, String)> = sqlx::query_as(
+            r#"
+            SELECT id, transaction_type, package_name
+            FROM transactions
+            WHERE status = 'pending'
+            ORDER BY id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Delete a transaction record
+    pub async fn delete_transaction(&self, transaction_id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM transactions WHERE id = ?")
+            .bind(transaction_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Check whether a name is satisfied by an installed package, either
+    /// directly or through `provides`
+    pub async fn is_provided(&self, name: &str) -> Result<bool> {
+        let result: (i64,) = sqlx::query_as(
+            r#"
+            SELECT (SELECT COUNT(*) FROM installed_packages WHERE name = ?)
+                 + (SELECT COUNT(*) FROM provides WHERE provides = ?)
+            "#
+        )
+        .bind(name)
+        .bind(name)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(result.0 > 0)
+    }
+
     /// Get package groups
     pub async fn get_groups(&self) -> Result<Vec<(String, String)>> {
         let rows: Vec<(String, Option<String>)> = sqlx::query_as(
@@ -638,6 +1044,13 @@ impl PackageDatabase {
         .await?;
         let explicit = explicit.0;
         
+        let dependency: (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM installed_packages WHERE install_reason = 'dependency'"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        let dependency = dependency.0;
+        
         let available: (i64,) = sqlx::query_as("SELECT COUNT(DISTINCT name) FROM available_packages")
             .fetch_one(&self.pool)
             .await?;
@@ -648,8 +1061,9 @@ impl PackageDatabase {
             .await?;
         let repositories = repositories.0;
         
+        // What is on disk, not the size of the archives
         let total_size: (Option<i64>,) = sqlx::query_as(
-            "SELECT SUM(size_bytes) FROM installed_packages"
+            "SELECT SUM(size) FROM installed_files"
         )
         .fetch_one(&self.pool)
         .await?;
@@ -660,7 +1074,8 @@ impl PackageDatabase {
         Ok(DatabaseStats {
             installed_packages: installed,
             explicit_packages: explicit,
-            dependency_packages: installed - explicit,
+            dependency_packages: dependency,
+            group_packages: installed - explicit - dependency,
             orphaned_packages: orphans,
             available_packages: available,
             repositories,
@@ -675,12 +1090,21 @@ pub struct DatabaseStats {
     pub installed_packages: i64,
     pub explicit_packages: i64,
     pub dependency_packages: i64,
+    pub group_packages: i64,
     pub orphaned_packages: i64,
     pub available_packages: i64,
     pub repositories: i64,
     pub total_installed_size: u64,
 }
 
+fn install_reason_str(reason: &InstallReason) -> &'static str {
+    match reason {
+        InstallReason::Explicit => "explicit",
+        InstallReason::Dependency => "dependency",
+        InstallReason::Group => "group",
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -699,4 +1123,107 @@ mod tests {
         let stats = db.get_stats().await.unwrap();
         assert_eq!(stats.installed_packages, 0);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_failed_migration_is_rolled_back() {
+        let dir = tempdir().unwrap();
+        let options = SqliteConnectOptions::new()
+            .filename(dir.path().join("test.db"))
+            .create_if_missing(true);
+        let pool = SqlitePool::connect_with(options).await.unwrap();
+
+        let migrations = [
+            "CREATE TABLE first (id INTEGER);",
+            "CREATE TABLE second (id INTEGER); INSERT INTO missing VALUES (1);",
+        ];
+        let err = PackageDatabase::run_migrations(&pool, &migrations).await.unwrap_err();
+        assert!(err.to_string().contains("migration 2"), "{:#}", err);
+
+        let (version,): (i64,) = sqlx::query_as("PRAGMA user_version").fetch_one(&pool).await.unwrap();
+        assert_eq!(version, 1);
+        let (tables,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM sqlite_master WHERE name = 'second'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(tables, 0);
+    }
+
+    fn installed(name: &str, reason: InstallReason, depends: &[&str], provides: &[&str]) -> InstalledPackage {
+        InstalledPackage {
+            package: Package {
+                name: name.to_string(),
+                version: semver::Version::new(1, 0, 0),
+                description: String::new(),
+                author: String::new(),
+                license: String::new(),
+                homepage: None,
+                repository: None,
+                dependencies: depends.iter().map(|d| Dependency {
+                    name: d.to_string(),
+                    version_req: "*".to_string(),
+                    optional: false,
+                    build_only: false,
+                }).collect(),
+                conflicts: Vec::new(),
+                provides: provides.iter().map(|p| p.to_string()).collect(),
+                replaces: Vec::new(),
+                categories: Vec::new(),
+                keywords: Vec::new(),
+                architecture: Architecture::All,
+                size_bytes: 0,
+                installed_size_bytes: 0,
+                checksum: PackageChecksum {
+                    sha256: String::new(),
+                    blake3: String::new(),
+                    sha512: None,
+                },
+                signature: None,
+                build_date: Utc::now(),
+            },
+            install_date: Utc::now(),
+            install_path: "/".into(),
+            files: Vec::new(),
+            install_reason: reason,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_orphans_follow_transitive_dependencies() {
+        let dir = tempdir().unwrap();
+        let db = PackageDatabase::open(&dir.path().join("test.db")).await.unwrap();
+
+        // a (explicit) -> b -> c, and a -> "editor" which vim provides
+        db.record_installation(installed("a", InstallReason::Explicit, &["b", "editor"], &[])).await.unwrap();
+        db.record_installation(installed("b", InstallReason::Dependency, &["c"], &[])).await.unwrap();
+        db.record_installation(installed("c", InstallReason::Dependency, &[], &[])).await.unwrap();
+        db.record_installation(installed("vim", InstallReason::Dependency, &[], &["editor"])).await.unwrap();
+
+        // Only depended on by another orphan
+        db.record_installation(installed("x", InstallReason::Dependency, &["y"], &[])).await.unwrap();
+        db.record_installation(installed("y", InstallReason::Dependency, &[], &[])).await.unwrap();
+
+        assert_eq!(db.find_orphans().await.unwrap(), vec!["x".to_string(), "y".to_string()]);
+
+        db.mark_removed("a").await.unwrap();
+        assert_eq!(
+            db.find_orphans().await.unwrap(),
+            vec!["b", "c", "vim", "x", "y"].into_iter().map(String::from).collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sha512_round_trips() {
+        let dir = tempdir().unwrap();
+        let db = PackageDatabase::open(&dir.path().join("test.db")).await.unwrap();
+
+        let mut with_sha512 = installed("a", InstallReason::Explicit, &[], &[]);
+        with_sha512.package.checksum.sha512 = Some("ab".repeat(64));
+        db.record_installation(with_sha512).await.unwrap();
+        db.record_installation(installed("b", InstallReason::Explicit, &[], &[])).await.unwrap();
+
+        let a = db.get_installed_package("a").await.unwrap();
+        assert_eq!(a.package.checksum.sha512, Some("ab".repeat(64)));
+        let b = db.get_installed_package("b").await.unwrap();
+        assert_eq!(b.package.checksum.sha512, None);
+    }
+}
@@ -0,0 +1,91 @@
+//! Package archive checksums
+//!
+//! The same digests are checked whether a cached archive is verified on its
+//! own or while it is being extracted, so that installing reads it once.
+
+use sha2::{Digest, Sha256, Sha512};
+use std::io::Read;
+
+use crate::{CacheStatus, HashPolicy, Package};
+
+/// Digests of a package archive in progress
+///
+/// Computes the checksums chosen by the hash policy that the package
+/// actually publishes, plus SHA512 when published.
+pub struct ArchiveHasher {
+    package: Package,
+    sha256: Option<Sha256>,
+    blake3: Option<blake3::Hasher>,
+    sha512: Option<Sha512>,
+}
+
+impl ArchiveHasher {
+    /// Start hashing `package`'s archive, or describe why it can't be verified
+    pub fn new(package: &Package, policy: HashPolicy) -> Result<Self, String> {
+        let checksum = &package.checksum;
+        let hasher = Self {
+            package: package.clone(),
+            sha256: (policy.sha256() && !checksum.sha256.is_empty()).then(Sha256::new),
+            blake3: (policy.blake3() && !checksum.blake3.is_empty()).then(blake3::Hasher::new),
+            sha512: checksum.sha512.as_ref().filter(|s| !s.is_empty()).map(|_| Sha512::new()),
+        };
+        if hasher.sha256.is_none() && hasher.blake3.is_none() && hasher.sha512.is_none() {
+            return Err(format!("{} has no checksum allowed by the {:?} hash policy", package.name, policy));
+        }
+        Ok(hasher)
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        if let Some(sha256) = &mut self.sha256 {
+            sha256.update(data);
+        }
+        if let Some(blake3) = &mut self.blake3 {
+            blake3.update(data);
+        }
+        if let Some(sha512) = &mut self.sha512 {
+            sha512.update(data);
+        }
+    }
+
+    /// Compare the digests of everything hashed with the published ones
+    pub fn finish(self) -> CacheStatus {
+        let checksum = &self.package.checksum;
+        let name = &self.package.name;
+        if self.sha256.is_some_and(|sha256| hex::encode(sha256.finalize()) != checksum.sha256) {
+            return CacheStatus::Corrupt(format!("SHA256 checksum mismatch for {}", name));
+        }
+        if self.blake3.is_some_and(|blake3| blake3.finalize().to_hex().as_str() != checksum.blake3) {
+            return CacheStatus::Corrupt(format!("BLAKE3 checksum mismatch for {}", name));
+        }
+        if self.sha512.is_some_and(|sha512| Some(hex::encode(sha512.finalize())) != checksum.sha512) {
+            return CacheStatus::Corrupt(format!("SHA512 checksum mismatch for {}", name));
+        }
+        CacheStatus::Valid
+    }
+}
+
+/// Reader that hashes everything read through it
+pub struct HashingReader<R> {
+    inner: R,
+    hasher: ArchiveHasher,
+}
+
+impl<R: Read> HashingReader<R> {
+    pub fn new(inner: R, hasher: ArchiveHasher) -> Self {
+        Self { inner, hasher }
+    }
+
+    /// Read whatever is left, so trailing bytes count too, and check the digests
+    pub fn finish(mut self) -> std::io::Result<CacheStatus> {
+        std::io::copy(&mut self, &mut std::io::sink())?;
+        Ok(self.hasher.finish())
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
@@ -0,0 +1,131 @@
+//! Package lifecycle hooks
+//!
+//! Packages may ship maintainer scripts under `.hecate/hooks/` which run
+//! before and after installation and removal.
+
+use anyhow::{Result, Context};
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+
+use crate::Package;
+
+/// Directory inside a package archive that holds package metadata
+pub const METADATA_DIR: &str = ".hecate";
+
+/// Directory inside a package archive that holds hook scripts
+pub const HOOKS_DIR: &str = ".hecate/hooks";
+
+/// Lifecycle point at which a hook runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookKind {
+    PreInstall,
+    PostInstall,
+    PreRemove,
+    PostRemove,
+}
+
+impl HookKind {
+    /// Script file name inside the hooks directory
+    pub fn script_name(&self) -> &'static str {
+        match self {
+            HookKind::PreInstall => "pre-install",
+            HookKind::PostInstall => "post-install",
+            HookKind::PreRemove => "pre-remove",
+            HookKind::PostRemove => "post-remove",
+        }
+    }
+}
+
+impl fmt::Display for HookKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.script_name())
+    }
+}
+
+/// Captured output of a hook run
+#[derive(Debug, Clone)]
+pub struct HookOutput {
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Runs hook scripts with a fixed environment, working directory and timeout
+pub struct HookRunner {
+    root_dir: PathBuf,
+    timeout: Duration,
+}
+
+impl HookRunner {
+    /// Create a runner for hooks operating on `root_dir`
+    pub fn new(root_dir: &Path, timeout: Duration) -> Self {
+        Self {
+            root_dir: root_dir.to_path_buf(),
+            timeout,
+        }
+    }
+
+    /// Run a hook if the package ships one
+    ///
+    /// Returns `None` when there is no script for `kind`, and an error if the
+    /// script exits non-zero or exceeds the timeout.
+    pub async fn run(
+        &self,
+        hooks_dir: &Path,
+        kind: HookKind,
+        package: &Package,
+    ) -> Result<Option<HookOutput>> {
+        let script = hooks_dir.join(kind.script_name());
+        if !script.is_file() {
+            return Ok(None);
+        }
+
+        let mut command = tokio::process::Command::new("/bin/sh");
+        command
+            .arg(&script)
+            .current_dir(&self.root_dir)
+            .env_clear()
+            .env("PATH", "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin")
+            .env("HECATE_PKG_ROOT", &self.root_dir)
+            .env("HECATE_PKG_NAME", &package.name)
+            .env("HECATE_PKG_VERSION", package.version.to_string())
+            .env("HECATE_PKG_HOOK", kind.script_name())
+            .stdin(Stdio::null())
+            .kill_on_drop(true);
+
+        let output = tokio::time::timeout(self.timeout, command.output())
+            .await
+            .map_err(|_| anyhow::anyhow!(
+                "{} hook for {} timed out after {}s",
+                kind, package.name, self.timeout.as_secs()
+            ))?
+            .with_context(|| format!("Failed to run {} hook for {}", kind, package.name))?;
+
+        let result = HookOutput {
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        };
+
+        if !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "{} hook for {} failed ({}): {}",
+                kind, package.name, output.status, result.stderr.trim()
+            ));
+        }
+
+        Ok(Some(result))
+    }
+}
+
+/// Strip `./` components so archive paths compare consistently
+pub fn normalize_entry_path(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|c| !matches!(c, Component::CurDir))
+        .collect()
+}
+
+/// Check whether an archive entry is package metadata rather than a file to install
+pub fn is_metadata_path(path: &Path) -> bool {
+    normalize_entry_path(path).starts_with(METADATA_DIR)
+}
@@ -6,14 +6,26 @@ use anyhow::{Result, Context};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use semver::Version;
+use semver::{Version, VersionReq};
 use chrono::{DateTime, Utc};
+use tracing::{info, warn};
 
 mod database;
 mod cache;
+mod hooks;
+mod world;
+mod overwrite;
+mod digest;
+pub mod lock;
+#[cfg(any(test, feature = "test-support"))]
+pub mod testing;
 
 use database::PackageDatabase;
-use cache::{PackageCache, DownloadManager};
+use cache::{PackageCache, DownloadManager, RetryPolicy};
+use hooks::{HookKind, HookRunner};
+use overwrite::{FileCollision, FileTransfer, OverwritePolicy};
+use digest::{ArchiveHasher, HashingReader};
+use lock::{LockMode, PackageLock};
 
 // ============================================================================
 // PACKAGE TYPES AND METADATA
@@ -53,10 +65,44 @@ pub struct Dependency {
 }
 
 /// Package checksum for integrity verification
+///
+/// An empty digest or a missing `sha512` means the source did not publish
+/// it. `sha512` was added after the first index format; older indexes and
+/// databases simply lack it.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PackageChecksum {
+    #[serde(default)]
     pub sha256: String,
+    #[serde(default)]
     pub blake3: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha512: Option<String>,
+}
+
+/// Which package checksums to verify
+///
+/// Verifying both digests streams every archive through two hashers; on
+/// large installs one is enough when speed matters more than redundancy.
+/// A published SHA512 is checked under every policy, so packages mirrored
+/// from SHA512-only sources verify without changing it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashPolicy {
+    /// SHA256 and BLAKE3
+    #[default]
+    All,
+    Sha256,
+    Blake3,
+}
+
+impl HashPolicy {
+    fn sha256(self) -> bool {
+        self != HashPolicy::Blake3
+    }
+
+    fn blake3(self) -> bool {
+        self != HashPolicy::Sha256
+    }
 }
 
 /// Supported architectures
@@ -68,6 +114,38 @@ pub enum Architecture {
     All,  // Architecture-independent packages
 }
 
+impl Architecture {
+    /// Name used in repository paths and the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Architecture::X86_64 => "x86_64",
+            Architecture::Aarch64 => "aarch64",
+            Architecture::Riscv64 => "riscv64",
+            Architecture::All => "all",
+        }
+    }
+
+    /// Parse a machine name as printed by `uname -m`
+    pub fn from_machine(machine: &str) -> Option<Self> {
+        match machine {
+            "x86_64" | "amd64" => Some(Architecture::X86_64),
+            "aarch64" | "arm64" => Some(Architecture::Aarch64),
+            "riscv64" => Some(Architecture::Riscv64),
+            _ => None,
+        }
+    }
+
+    /// Architecture of the running machine, if it is a supported one
+    pub fn host() -> Option<Self> {
+        hecate_core::HardwareDetector::machine_arch().and_then(|machine| Self::from_machine(&machine))
+    }
+
+    /// Check whether a package built for this architecture runs on `host`
+    pub fn runs_on(self, host: Architecture) -> bool {
+        self == Architecture::All || self == host
+    }
+}
+
 /// Package installation status
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InstalledPackage {
@@ -88,7 +166,7 @@ pub struct InstalledFile {
 }
 
 /// Reason for package installation
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum InstallReason {
     Explicit,      // User requested
     Dependency,    // Pulled in as dependency
@@ -112,6 +190,35 @@ pub struct Repository {
     pub last_update: Option<DateTime<Utc>>,
 }
 
+impl Repository {
+    /// Base URLs to try in order: the primary URL, then each mirror
+    pub fn base_urls(&self) -> Vec<&str> {
+        std::iter::once(self.url.as_str())
+            .chain(self.mirror_urls.iter().map(String::as_str))
+            .collect()
+    }
+}
+
+/// Check a repository's name and URLs before it is saved
+fn validate_repository(repo: &Repository) -> Result<()> {
+    let valid_name = !repo.name.is_empty()
+        && !repo.name.starts_with('.')
+        && repo.name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid_name {
+        return Err(anyhow::anyhow!("Invalid repository name '{}'", repo.name));
+    }
+
+    for url in repo.base_urls() {
+        let parsed = reqwest::Url::parse(url)
+            .with_context(|| format!("Invalid repository URL '{}'", url))?;
+        if !matches!(parsed.scheme(), "http" | "https") || !parsed.has_host() {
+            return Err(anyhow::anyhow!("Repository URL '{}' must be http or https", url));
+        }
+    }
+
+    Ok(())
+}
+
 /// Repository index containing package metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RepositoryIndex {
@@ -121,6 +228,309 @@ pub struct RepositoryIndex {
     pub provides_index: HashMap<String, Vec<String>>,  // provides -> packages
 }
 
+/// Which part of a package a search query matched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MatchedField {
+    ExactName,
+    NamePrefix,
+    NameSubstring,
+    FuzzyName,
+    Keyword,
+    Description,
+}
+
+/// A ranked search hit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub package: Package,
+    pub score: f64,
+    pub matched_field: MatchedField,
+}
+
+/// Minimum Jaro-Winkler similarity for a fuzzy name match
+const FUZZY_THRESHOLD: f64 = 0.85;
+
+/// Score a package against a lowercase query, best match first
+fn rank_match(package: &Package, query: &str, fuzzy: bool) -> Option<(f64, MatchedField)> {
+    let name = package.name.to_lowercase();
+
+    if name == query {
+        return Some((100.0, MatchedField::ExactName));
+    }
+    if name.starts_with(query) {
+        return Some((80.0, MatchedField::NamePrefix));
+    }
+    if name.contains(query) {
+        return Some((60.0, MatchedField::NameSubstring));
+    }
+    if fuzzy {
+        let similarity = strsim::jaro_winkler(query, &name);
+        if similarity >= FUZZY_THRESHOLD {
+            return Some((50.0 * similarity, MatchedField::FuzzyName));
+        }
+    }
+    if package.keywords.iter().any(|k| k.to_lowercase().contains(query)) {
+        return Some((40.0, MatchedField::Keyword));
+    }
+    if package.description.to_lowercase().contains(query) {
+        return Some((20.0, MatchedField::Description));
+    }
+
+    None
+}
+
+/// A problem found by [`PackageManager::check_problems`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Problem {
+    /// A file recorded for an installed package no longer exists
+    MissingFile { package: String, path: PathBuf },
+    /// A file's contents no longer match the checksum recorded at install
+    ModifiedFile { package: String, path: PathBuf },
+    /// A required dependency is not installed or provided by anything
+    MissingDependency { package: String, dependency: String },
+    /// A transaction was started but never completed, e.g. after a crash
+    StaleTransaction { id: i64, kind: String, package: String },
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Problem::MissingFile { package, path } => {
+                write!(f, "{}: missing file {}", package, path.display())
+            }
+            Problem::ModifiedFile { package, path } => {
+                write!(f, "{}: modified file {}", package, path.display())
+            }
+            Problem::MissingDependency { package, dependency } => {
+                write!(f, "{}: dependency {} is not installed", package, dependency)
+            }
+            Problem::StaleTransaction { id, kind, package } => {
+                write!(f, "{}: unfinished {} transaction #{}", package, kind, id)
+            }
+        }
+    }
+}
+
+/// Whether an installed path, relative to the install root, is configuration
+fn is_config_file(path: &Path) -> bool {
+    path.strip_prefix("/").unwrap_or(path).starts_with("etc")
+}
+
+/// SHA256 of a file, hex encoded
+fn file_checksum(path: &Path) -> Result<String> {
+    use sha2::{Sha256, Digest};
+
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Remove `dir` and the directories below it that hold no files
+fn prune_empty_dirs(dir: &Path) -> Result<()> {
+    if !dir.is_dir() || dir.is_symlink() {
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(dir)? {
+        prune_empty_dirs(&entry?.path())?;
+    }
+    if std::fs::read_dir(dir)?.next().is_none() {
+        std::fs::remove_dir(dir)?;
+    }
+    Ok(())
+}
+
+/// State of a package's archive in the cache
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CacheStatus {
+    /// Not downloaded
+    Missing,
+    /// Present but failing a checksum; partial downloads look like this too
+    Corrupt(String),
+    /// Matches both the SHA256 and BLAKE3 checksums
+    Valid,
+}
+
+impl CacheStatus {
+    pub fn is_valid(&self) -> bool {
+        *self == CacheStatus::Valid
+    }
+}
+
+/// A package archive unpacked into its staging directory, checked against
+/// its checksums and ready to move into place
+struct StagedPackage {
+    package: Package,
+    dir: PathBuf,
+    /// Everything the package installs, as recorded in the database
+    files: Vec<InstalledFile>,
+    /// Paths of `files` that are not directories
+    paths: Vec<PathBuf>,
+}
+
+impl StagedPackage {
+    /// Where the package's hook scripts were staged
+    fn hooks_dir(&self) -> PathBuf {
+        self.dir.join(hooks::HOOKS_DIR)
+    }
+}
+
+/// A cached archive found unreadable or failing its checksums on install
+///
+/// The install is rolled back and the archive dropped from the cache.
+#[derive(Debug, thiserror::Error)]
+#[error("{package} is corrupt, install rolled back: {reason}")]
+struct CorruptArchive {
+    package: String,
+    reason: String,
+}
+
+/// A package together with the repository it was resolved from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolvedPackage {
+    pub package: Package,
+    pub repository: String,
+}
+
+/// A package in a [`TransactionPlan`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedPackage {
+    pub package: Package,
+    /// Requested by the user, or pulled in as a dependency
+    pub reason: InstallReason,
+    /// Bytes still to download; zero if already cached or being removed
+    pub download_size: u64,
+    /// Bytes on disk once installed, or freed by removal
+    pub installed_size: u64,
+}
+
+/// Ordered list of packages an install or removal would touch
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TransactionPlan {
+    pub packages: Vec<PlannedPackage>,
+}
+
+impl TransactionPlan {
+    pub fn is_empty(&self) -> bool {
+        self.packages.is_empty()
+    }
+
+    /// Total bytes to download
+    pub fn download_size(&self) -> u64 {
+        self.packages.iter().map(|p| p.download_size).sum()
+    }
+
+    /// Total bytes installed or freed
+    pub fn installed_size(&self) -> u64 {
+        self.packages.iter().map(|p| p.installed_size).sum()
+    }
+}
+
+/// An installed package with a newer version in the repositories
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutdatedPackage {
+    pub name: String,
+    pub installed: Version,
+    pub available: Package,
+    pub held: bool,
+}
+
+/// An installed package as listed by `hecate-pkg list`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstalledSummary {
+    pub name: String,
+    pub version: Version,
+    pub description: String,
+    pub install_reason: InstallReason,
+    pub size_bytes: u64,
+    pub held: bool,
+}
+
+/// What `hecate-pkg info` reports about a package
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageInfo {
+    /// The installed version if there is one, otherwise the newest available
+    pub package: Package,
+    /// Repository offering the package, when it is not installed
+    pub repository: Option<String>,
+    /// Set only for installed packages
+    pub install_reason: Option<InstallReason>,
+    pub held: bool,
+    /// Installed files as absolute paths on the target system
+    pub files: Vec<PathBuf>,
+}
+
+/// Figures shown by `hecate-pkg stats`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageStats {
+    pub database: DatabaseStats,
+    pub cache: CacheStats,
+    /// Enabled repositories in the configuration
+    pub repositories: usize,
+    /// Installed packages with a newer version available, holds excluded
+    pub updates_available: usize,
+}
+
+/// Render [`PackageStats`] as the text printed by `hecate-pkg stats`
+pub fn format_stats(stats: &PackageStats) -> String {
+    use indicatif::HumanBytes;
+
+    let db = &stats.database;
+    let mut out = String::new();
+    out.push_str(&format!("Total packages installed: {}\n", db.installed_packages));
+    out.push_str(&format!("Explicitly installed: {}\n", db.explicit_packages));
+    out.push_str(&format!("Dependencies: {}\n", db.dependency_packages));
+    if db.group_packages > 0 {
+        out.push_str(&format!("Installed with groups: {}\n", db.group_packages));
+    }
+    out.push_str(&format!("Orphaned packages: {}\n", db.orphaned_packages));
+    out.push('\n');
+    out.push_str(&format!("Total disk usage: {}\n", HumanBytes(db.total_installed_size)));
+    out.push_str(&format!(
+        "Cache size: {} ({} packages)\n", HumanBytes(stats.cache.total_size), stats.cache.package_count
+    ));
+    out.push('\n');
+    out.push_str(&format!("Repositories: {}\n", stats.repositories));
+    out.push_str(&format!("Available packages: {}\n", db.available_packages));
+    out.push_str(&format!("Available updates: {}\n", stats.updates_available));
+    out
+}
+
+/// Split a `name@version` spec into a name and an optional version requirement
+///
+/// A bare version pins exactly (`foo@1.2.3` means `=1.2.3`); anything else is
+/// parsed as a semver requirement (`foo@^1.2`).
+pub fn parse_package_spec(spec: &str) -> Result<(String, Option<VersionReq>)> {
+    match spec.split_once('@') {
+        Some((name, version)) => {
+            let req = match Version::parse(version) {
+                Ok(v) => VersionReq::parse(&format!("={}", v))?,
+                Err(_) => VersionReq::parse(version)
+                    .map_err(|e| anyhow::anyhow!("Invalid version in '{}': {}", spec, e))?,
+            };
+            Ok((name.to_string(), Some(req)))
+        }
+        None => Ok((spec.to_string(), None)),
+    }
+}
+
+/// Largest repository index accepted once decompressed
+const MAX_INDEX_SIZE: u64 = 256 * 1024 * 1024;
+
+/// Decompress a repository index, refusing to inflate past `max_size` bytes
+fn decompress_index(compressed: &[u8], max_size: u64) -> Result<Vec<u8>> {
+    use std::io::Read;
+
+    let mut data = Vec::new();
+    zstd::stream::read::Decoder::new(compressed)?
+        .take(max_size + 1)
+        .read_to_end(&mut data)?;
+    if data.len() as u64 > max_size {
+        return Err(anyhow::anyhow!("Repository index exceeds {} bytes when decompressed", max_size));
+    }
+    Ok(data)
+}
+
 // ============================================================================
 // PACKAGE MANAGER CORE
 // ============================================================================
@@ -130,11 +540,15 @@ pub struct PackageManager {
     config: PackageConfig,
     database: PackageDatabase,
     cache: PackageCache,
+    downloader: DownloadManager,
     repositories: Vec<Repository>,
+    /// Held for the manager's lifetime unless opened with [`LockMode::None`]
+    _lock: Option<PackageLock>,
 }
 
 /// Package manager configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PackageConfig {
     pub root_dir: PathBuf,
     pub db_path: PathBuf,
@@ -145,6 +559,29 @@ pub struct PackageConfig {
     pub verify_signatures: bool,
     pub auto_remove_orphans: bool,
     pub color_output: bool,
+    pub run_hooks: bool,
+    pub hook_timeout_secs: u64,
+    pub max_cache_size: u64,
+    pub offline: bool,
+    /// Combined download rate limit in bytes per second; 0 means unlimited
+    pub max_download_bytes_per_sec: u64,
+    /// Retries of a download or index fetch after a transient failure
+    pub download_retries: u32,
+    /// Delay before the first retry in milliseconds, doubling after that
+    pub retry_base_delay_ms: u64,
+    /// Checksums to verify on downloaded and cached packages
+    pub hash_policy: HashPolicy,
+    /// Globs of paths a package may take over from another installed package
+    pub overwrite: Vec<String>,
+    /// Architecture installed packages must be built for; detected from the
+    /// running machine when unset
+    pub architecture: Option<Architecture>,
+    /// Install packages built for another architecture; chosen per run
+    #[serde(skip)]
+    pub force_arch: bool,
+    /// How to take the package lock; chosen per run, never read from a file
+    #[serde(skip)]
+    pub lock: LockMode,
 }
 
 impl Default for PackageConfig {
@@ -159,28 +596,64 @@ impl Default for PackageConfig {
             verify_signatures: true,
             auto_remove_orphans: false,
             color_output: true,
+            run_hooks: true,
+            hook_timeout_secs: 300,
+            max_cache_size: 10 * 1024 * 1024 * 1024, // 10GB
+            offline: false,
+            max_download_bytes_per_sec: 0,
+            download_retries: 3,
+            retry_base_delay_ms: 500,
+            hash_policy: HashPolicy::default(),
+            overwrite: Vec::new(),
+            architecture: None,
+            force_arch: false,
+            lock: LockMode::default(),
         }
     }
 }
 
 impl PackageManager {
     /// Create a new package manager instance
+    ///
+    /// Unless `config.lock` is [`LockMode::None`], this takes the package
+    /// lock first and holds it until the manager is dropped.
     pub async fn new(config: PackageConfig) -> Result<Self> {
+        let lock = match config.lock {
+            LockMode::None => None,
+            LockMode::FailFast => Some(PackageLock::acquire(&config.db_path, false)?),
+            LockMode::Wait => {
+                let db_path = config.db_path.clone();
+                Some(tokio::task::spawn_blocking(move || PackageLock::acquire(&db_path, true)).await??)
+            }
+        };
         let database = PackageDatabase::open(&config.db_path).await?;
-        let cache = PackageCache::new(&config.cache_dir)?;
+        let mut cache = PackageCache::new(&config.cache_dir)?;
+        cache.set_max_cache_size(config.max_cache_size);
+        let downloader = DownloadManager::new(config.parallel_downloads, config.max_download_bytes_per_sec)
+            .with_retry(RetryPolicy {
+                retries: config.download_retries,
+                base_delay: std::time::Duration::from_millis(config.retry_base_delay_ms),
+            });
         let repositories = Self::load_repositories(&config).await?;
 
         Ok(Self {
             config,
             database,
             cache,
+            downloader,
             repositories,
+            _lock: lock,
         })
     }
 
+    /// Directory holding one `.repo` TOML file per repository
+    fn repos_dir(config: &PackageConfig) -> PathBuf {
+        config.root_dir.join("etc/hecate-pkg/repos.d")
+    }
+
     /// Load repository configurations
     async fn load_repositories(config: &PackageConfig) -> Result<Vec<Repository>> {
-        let repos_dir = config.root_dir.join("etc/hecate-pkg/repos.d");
+        let repos_dir = Self::repos_dir(config);
         let mut repositories = Vec::new();
 
         if repos_dir.exists() {
@@ -202,25 +675,124 @@ impl PackageManager {
         Ok(repositories)
     }
 
+    /// Configured repositories in priority order
+    pub fn repositories(&self) -> &[Repository] {
+        &self.repositories
+    }
+
+    /// Add a repository, persisting it as `<name>.repo` in `repos.d`
+    pub async fn add_repository(&mut self, repo: Repository) -> Result<()> {
+        validate_repository(&repo)?;
+        if self.repositories.iter().any(|r| r.name == repo.name) {
+            return Err(anyhow::anyhow!("Repository {} already exists", repo.name));
+        }
+
+        let repos_dir = Self::repos_dir(&self.config);
+        std::fs::create_dir_all(&repos_dir)?;
+        let path = repos_dir.join(format!("{}.repo", repo.name));
+        if path.exists() {
+            return Err(anyhow::anyhow!("{} already exists", path.display()));
+        }
+        std::fs::write(&path, toml::to_string(&repo)?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+
+        self.repositories.push(repo);
+        self.repositories.sort_by_key(|r| r.priority);
+        Ok(())
+    }
+
+    /// Remove a repository's configuration and its synced index
+    pub async fn remove_repository(&mut self, name: &str) -> Result<()> {
+        let path = self.repository_file(name)?;
+        std::fs::remove_file(&path)
+            .with_context(|| format!("Failed to remove {}", path.display()))?;
+        self.database.remove_repository(name).await?;
+
+        self.repositories.retain(|r| r.name != name);
+        Ok(())
+    }
+
+    /// Enable or disable a repository in its configuration file
+    pub async fn set_repository_enabled(&mut self, name: &str, enabled: bool) -> Result<()> {
+        let path = self.repository_file(name)?;
+        let mut repo: Repository = toml::from_str(&std::fs::read_to_string(&path)?)?;
+        repo.enabled = enabled;
+        std::fs::write(&path, toml::to_string(&repo)?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        self.database.set_repository_enabled(name, enabled).await?;
+
+        self.repositories = Self::load_repositories(&self.config).await?;
+        Ok(())
+    }
+
+    /// Find the `.repo` file that defines a repository
+    fn repository_file(&self, name: &str) -> Result<PathBuf> {
+        let repos_dir = Self::repos_dir(&self.config);
+
+        if repos_dir.exists() {
+            for entry in std::fs::read_dir(&repos_dir)? {
+                let path = entry?.path();
+                if path.extension() != Some(std::ffi::OsStr::new("repo")) {
+                    continue;
+                }
+
+                let repo: Repository = toml::from_str(&std::fs::read_to_string(&path)?)
+                    .with_context(|| format!("Invalid repository file {}", path.display()))?;
+                if repo.name == name {
+                    return Ok(path);
+                }
+            }
+        }
+
+        Err(anyhow::anyhow!("Repository {} is not configured", name))
+    }
+
     /// Search for packages
-    pub async fn search(&self, query: &str) -> Result<Vec<Package>> {
-        let mut results = Vec::new();
+    ///
+    /// Returns the latest version of each matching package, ranked exact name
+    /// match first, then name prefix, name substring, keyword and description.
+    pub async fn search(&self, query: &str) -> Result<Vec<SearchResult>> {
+        self.search_with(query, false).await
+    }
+
+    /// Search for packages, also matching names within a small edit distance
+    pub async fn search_fuzzy(&self, query: &str) -> Result<Vec<SearchResult>> {
+        self.search_with(query, true).await
+    }
 
+    async fn search_with(&self, query: &str, fuzzy: bool) -> Result<Vec<SearchResult>> {
+        let query = query.to_lowercase();
+
+        // Latest version of each package across all repositories
+        let mut latest: HashMap<String, Package> = HashMap::new();
         for repo_index in self.database.get_repository_indices().await? {
-            for (name, versions) in &repo_index.packages {
-                if name.contains(query) {
-                    results.extend(versions.clone());
-                } else {
-                    for pkg in versions {
-                        if pkg.description.to_lowercase().contains(&query.to_lowercase()) 
-                            || pkg.keywords.iter().any(|k| k.contains(query)) {
-                            results.push(pkg.clone());
+            for (name, versions) in repo_index.packages {
+                if let Some(newest) = versions.into_iter().max_by(|a, b| a.version.cmp(&b.version)) {
+                    match latest.get(&name) {
+                        Some(existing) if existing.version >= newest.version => {}
+                        _ => {
+                            latest.insert(name, newest);
                         }
                     }
                 }
             }
         }
 
+        let mut results: Vec<SearchResult> = latest.into_values()
+            .filter_map(|package| {
+                rank_match(&package, &query, fuzzy).map(|(score, matched_field)| SearchResult {
+                    package,
+                    score,
+                    matched_field,
+                })
+            })
+            .collect();
+
+        results.sort_by(|a, b| {
+            b.score.total_cmp(&a.score)
+                .then_with(|| a.package.name.cmp(&b.package.name))
+        });
+
         Ok(results)
     }
 
@@ -232,457 +804,3372 @@ impl PackageManager {
         }
 
         // Find package in repositories
-        let package = self.find_package(package_name).await?
-            .ok_or_else(|| anyhow::anyhow!("Package {} not found", package_name))?;
+        let resolved = self.find_package(package_name).await?
+            .ok_or_else(|| self.not_found(package_name))?;
 
-        // Resolve dependencies
-        let install_plan = self.resolve_dependencies(&package).await?;
+        let install_plan = self.resolve_dependencies(&resolved).await?;
+        self.install_resolved(install_plan, &[resolved.package.name], InstallReason::Explicit).await
+    }
 
-        // Download packages
-        for pkg in &install_plan {
-            self.download_package(pkg).await?;
-        }
+    /// Resolve, download and verify packages into the cache without installing
+    ///
+    /// Targets are package specs as accepted by [`parse_package_spec`].
+    /// Returns the cache paths of every package needed, dependencies included,
+    /// so they can be staged for installation on another machine.
+    pub async fn download_only(&self, targets: &[String]) -> Result<Vec<PathBuf>> {
+        let mut plan: Vec<ResolvedPackage> = Vec::new();
 
-        // Verify checksums
-        for pkg in &install_plan {
-            self.verify_package(pkg).await?;
+        for spec in targets {
+            let (name, version_req) = parse_package_spec(spec)?;
+            let resolved = match &version_req {
+                Some(req) => self.find_package_matching(&name, req).await?
+                    .ok_or_else(|| anyhow::anyhow!("No version of {} matching {} found", name, req))?,
+                None => self.find_package(&name).await?
+                    .ok_or_else(|| self.not_found(&name))?,
+            };
+
+            for pkg in self.resolve_dependencies(&resolved).await? {
+                if !plan.iter().any(|p| p.package.name == pkg.package.name) {
+                    plan.push(pkg);
+                }
+            }
         }
 
-        // Install packages in order
-        for pkg in install_plan {
-            self.install_package(pkg).await?;
+        self.download_packages(&plan, true).await?;
+
+        let mut paths = Vec::new();
+        for pkg in &plan {
+            self.verify_package(&pkg.package).await?;
+            paths.push(self.cache.get_package_path(&pkg.package));
         }
 
-        Ok(())
+        Ok(paths)
     }
 
-    /// Remove a package
-    #[async_recursion::async_recursion]
-    pub async fn remove(&mut self, package_name: &str) -> Result<()> {
-        // Check if installed
-        if !self.database.is_installed(package_name).await? {
-            return Err(anyhow::anyhow!("Package {} is not installed", package_name));
-        }
+    /// Work out what installing `targets` would do, without changing anything
+    ///
+    /// Targets are package specs as accepted by [`parse_package_spec`].
+    /// Packages come back in installation order, dependencies before the
+    /// packages that need them. Targets already installed at the requested
+    /// version are left out.
+    pub async fn plan_install(&self, targets: &[String]) -> Result<TransactionPlan> {
+        self.plan_install_as(targets, InstallReason::Explicit).await
+    }
 
-        // Check for dependent packages
-        let dependents = self.database.get_dependents(package_name).await?;
-        if !dependents.is_empty() {
-            return Err(anyhow::anyhow!(
-                "Cannot remove {}: required by {:?}", 
-                package_name, dependents
-            ));
-        }
+    /// [`PackageManager::plan_install`], recording targets with `target_reason`
+    async fn plan_install_as(&self, targets: &[String], target_reason: InstallReason) -> Result<TransactionPlan> {
+        let mut requested = Vec::new();
+        for spec in targets {
+            let (name, version_req) = parse_package_spec(spec)?;
+            let installed = self.database.is_installed(&name).await?;
+            if installed && version_req.is_none() {
+                continue;
+            }
 
-        // Get installed package info
-        let installed = self.database.get_installed_package(package_name).await?;
+            let resolved = match &version_req {
+                Some(req) => self.find_package_matching(&name, req).await?
+                    .ok_or_else(|| anyhow::anyhow!("No version of {} matching {} found", name, req))?,
+                None => self.find_package(&name).await?
+                    .ok_or_else(|| self.not_found(&name))?,
+            };
 
-        // Remove files
-        for file in installed.files.iter().rev() {
-            if file.path.exists() {
-                if file.path.is_dir() {
-                    std::fs::remove_dir(&file.path)?;
-                } else {
-                    std::fs::remove_file(&file.path)?;
+            if installed {
+                let current = self.database.get_installed_package(&name).await?;
+                if current.package.version == resolved.package.version {
+                    continue;
                 }
             }
+
+            requested.push(resolved);
         }
 
-        // Update database
-        self.database.mark_removed(package_name).await?;
+        let mut plan = TransactionPlan::default();
+        for target in &requested {
+            for pkg in self.resolve_dependencies(target).await? {
+                if plan.packages.iter().any(|p| p.package.name == pkg.package.name) {
+                    continue;
+                }
 
-        // Remove orphaned dependencies if configured
-        if self.config.auto_remove_orphans {
-            self.remove_orphans().await?;
+                let reason = if requested.iter().any(|r| r.package.name == pkg.package.name) {
+                    target_reason.clone()
+                } else {
+                    InstallReason::Dependency
+                };
+                let cache_path = self.cache.get_package_path(&pkg.package);
+                let download_size = if self.verify_cached_package(&pkg.package, &cache_path).await?.is_valid() {
+                    0
+                } else {
+                    pkg.package.size_bytes
+                };
+
+                plan.packages.push(PlannedPackage {
+                    installed_size: pkg.package.installed_size_bytes,
+                    package: pkg.package,
+                    reason,
+                    download_size,
+                });
+            }
         }
 
-        Ok(())
+        Ok(plan)
     }
 
-    /// Update all packages
-    pub async fn update(&mut self) -> Result<()> {
-        // Update repository indices
-        self.sync_repositories().await?;
+    /// Work out what removing `targets` would do, without changing anything
+    ///
+    /// With `cascade`, dependency-installed packages that nothing else needs
+    /// once the targets are gone are removed too. Packages come back in
+    /// removal order, dependents before their dependencies. Fails if a
+    /// package outside the plan still depends on something in it.
+    pub async fn plan_remove(&self, targets: &[String], cascade: bool) -> Result<TransactionPlan> {
+        use std::collections::HashSet;
 
-        // Get list of installed packages
-        let installed = self.database.get_installed_packages().await?;
+        let mut removing: HashSet<String> = HashSet::new();
+        for name in targets {
+            if !self.database.is_installed(name).await? {
+                return Err(anyhow::anyhow!("Package {} is not installed", name));
+            }
+            removing.insert(name.clone());
+        }
 
-        // Find updates
-        let mut updates = Vec::new();
-        for pkg in installed {
-            if let Some(latest) = self.find_package(&pkg.package.name).await? {
-                if latest.version > pkg.package.version {
-                    updates.push((pkg.package.name.clone(), latest));
+        if cascade {
+            // Only what the removal orphans, not packages that already were
+            let existing: HashSet<String> = self.database.find_orphans().await?.into_iter().collect();
+            for orphan in self.database.find_orphans_without(&removing).await? {
+                if !existing.contains(&orphan) {
+                    removing.insert(orphan);
                 }
             }
         }
 
-        if updates.is_empty() {
-            println!("All packages are up to date");
-            return Ok(());
+        let mut dependents: HashMap<String, Vec<String>> = HashMap::new();
+        for name in &removing {
+            let mut required_by = self.database.get_dependents(name).await?;
+            if let Some(outside) = required_by.iter().find(|d| !removing.contains(*d)) {
+                return Err(anyhow::anyhow!("Cannot remove {}: required by {}", name, outside));
+            }
+            required_by.retain(|d| d != name);
+            dependents.insert(name.clone(), required_by);
         }
 
-        // Apply updates
-        println!("Found {} updates", updates.len());
-        for (name, pkg) in updates {
-            println!("Updating {} from {} to {}", name, 
-                self.database.get_installed_package(&name).await?.package.version,
-                pkg.version
-            );
-            self.upgrade_package(pkg).await?;
+        // Remove a package only once nothing left in the plan depends on it
+        let mut remaining: Vec<String> = removing.into_iter().collect();
+        remaining.sort();
+        let mut order = Vec::new();
+        while !remaining.is_empty() {
+            let ready: Vec<String> = remaining.iter()
+                .filter(|name| dependents[*name].iter().all(|d| !remaining.contains(d)))
+                .cloned()
+                .collect();
+            // A dependency cycle has no safe order; take the rest as they come
+            let batch = if ready.is_empty() { remaining.clone() } else { ready };
+            remaining.retain(|name| !batch.contains(name));
+            order.extend(batch);
         }
 
-        Ok(())
-    }
+        let mut plan = TransactionPlan::default();
+        for name in order {
+            let installed = self.database.get_installed_package(&name).await?;
+            let reason = if targets.contains(&name) {
+                InstallReason::Explicit
+            } else {
+                InstallReason::Dependency
+            };
+            plan.packages.push(PlannedPackage {
+                installed_size: installed.files.iter().map(|f| f.size).sum(),
+                package: installed.package,
+                reason,
+                download_size: 0,
+            });
+        }
 
-    /// Sync repository indices
-    pub async fn sync_repositories(&mut self) -> Result<()> {
-        use futures::stream::{self, StreamExt};
+        Ok(plan)
+    }
 
-        let repos = self.repositories.clone();
-        let tasks = repos.into_iter()
-            .filter(|r| r.enabled)
-            .map(|repo| self.sync_repository(repo));
+    /// Install the newest version of a package matching `version_req`
+    ///
+    /// If a different version is already installed it is replaced, which
+    /// allows downgrading. Holds and the install reason are preserved.
+    pub async fn install_version(&mut self, package_name: &str, version_req: &VersionReq) -> Result<()> {
+        let resolved = self.find_package_matching(package_name, version_req).await?
+            .ok_or_else(|| anyhow::anyhow!(
+                "No version of {} matching {} found", package_name, version_req
+            ))?;
 
-        let results: Vec<Result<()>> = stream::iter(tasks)
-            .buffer_unordered(self.config.parallel_downloads)
-            .collect()
-            .await;
+        if !self.database.is_installed(package_name).await? {
+            let install_plan = self.resolve_dependencies(&resolved).await?;
+            return self.install_resolved(install_plan, &[resolved.package.name], InstallReason::Explicit).await;
+        }
 
-        for result in results {
-            result?;
+        let installed = self.database.get_installed_package(package_name).await?;
+        if installed.package.version == resolved.package.version {
+            return Err(anyhow::anyhow!(
+                "Package {} {} is already installed", package_name, resolved.package.version
+            ));
         }
 
-        Ok(())
-    }
+        self.check_architecture(&resolved.package)?;
+        self.check_conflicts(&resolved.package).await?;
 
-    /// Sync a single repository
-    async fn sync_repository(&self, repo: Repository) -> Result<()> {
-        let index_url = format!("{}/index.json.zst", repo.url);
-        
-        // Download compressed index
-        let response = reqwest::get(&index_url).await?;
-        let compressed_data = response.bytes().await?;
+        let held = self.database.is_held(package_name).await?;
+        let old_version = installed.package.version.to_string();
+        let new_version = resolved.package.version.to_string();
+        let transaction_id = self.database
+            .begin_transaction("upgrade", package_name, Some(&old_version), Some(&new_version))
+            .await?;
 
-        // Decompress
-        let data = zstd::decode_all(compressed_data.as_ref())?;
+        match self.upgrade_package(resolved).await {
+            Ok(()) => {
+                if held {
+                    self.database.set_held(package_name, true).await?;
+                }
+                self.database.complete_transaction(transaction_id).await?;
+                self.cache.enforce_size_limit().await?;
+                Ok(())
+            }
+            Err(e) => {
+                self.database.fail_transaction(transaction_id, &e.to_string()).await?;
+                Err(e)
+            }
+        }
+    }
 
-        // Parse index
-        let index: RepositoryIndex = serde_json::from_slice(&data)?;
+    /// Check, download, verify and install packages that are not yet
+    /// installed, in dependency order
+    ///
+    /// Packages named in `requested` are recorded with `reason`, everything
+    /// else in the plan as a dependency. Nothing is installed until every
+    /// package has been downloaded and verified.
+    async fn install_resolved(
+        &mut self,
+        install_plan: Vec<ResolvedPackage>,
+        requested: &[String],
+        reason: InstallReason,
+    ) -> Result<()> {
+        // Check each package runs here and conflicts with nothing installed
+        for pkg in &install_plan {
+            self.check_architecture(&pkg.package)?;
+            self.check_conflicts(&pkg.package).await?;
+        }
 
-        // Verify signature if enabled
-        if repo.gpg_check {
-            // TODO: Implement GPG verification
+        // Download packages; checksums are verified while extracting
+        self.download_packages(&install_plan, false).await?;
+
+        // Install packages in order
+        for pkg in &install_plan {
+            let reason = if requested.contains(&pkg.package.name) {
+                reason.clone()
+            } else {
+                InstallReason::Dependency
+            };
+            self.install_downloaded(pkg, reason).await?;
         }
 
-        // Save to database
-        self.database.update_repository_index(index).await?;
+        // Keep the cache within its configured size
+        self.cache.enforce_size_limit().await?;
 
         Ok(())
     }
 
-    /// Find a package in repositories
-    async fn find_package(&self, name: &str) -> Result<Option<Package>> {
-        for repo_index in self.database.get_repository_indices().await? {
-            if let Some(versions) = repo_index.packages.get(name) {
-                // Return latest version
-                if let Some(latest) = versions.iter().max_by_key(|p| &p.version) {
-                    return Ok(Some(latest.clone()));
-                }
-            }
-        }
-        Ok(None)
+    /// Package groups known from the repository indices, with descriptions
+    pub async fn groups(&self) -> Result<Vec<(String, String)>> {
+        self.database.get_groups().await
     }
 
-    /// Resolve package dependencies
-    async fn resolve_dependencies(&self, package: &Package) -> Result<Vec<Package>> {
-        let mut to_install = Vec::new();
-        let mut visited = std::collections::HashSet::new();
-
-        self.resolve_deps_recursive(package, &mut to_install, &mut visited).await?;
+    /// Members of a package group, sorted by name
+    pub async fn group_members(&self, group: &str) -> Result<Vec<String>> {
+        let members = self.database.get_group_members(group).await?;
+        if members.is_empty() {
+            return Err(anyhow::anyhow!("Group {} not found", group));
+        }
+        Ok(members)
+    }
 
-        // Reverse to get correct installation order
-        to_install.reverse();
-        Ok(to_install)
+    /// Work out what installing a group would do, without changing anything
+    ///
+    /// `selected` limits the install to some of the group's members. Members
+    /// already installed are left out.
+    pub async fn plan_group_install(&self, group: &str, selected: Option<&[String]>) -> Result<TransactionPlan> {
+        let members = self.select_group_members(group, selected).await?;
+        self.plan_install_as(&members, InstallReason::Group).await
     }
 
-    #[async_recursion::async_recursion]
-    async fn resolve_deps_recursive(
-        &self,
-        package: &Package,
-        to_install: &mut Vec<Package>,
-        visited: &mut std::collections::HashSet<String>,
-    ) -> Result<()> {
-        if visited.contains(&package.name) {
-            return Ok(());
-        }
-        visited.insert(package.name.clone());
+    /// Install the members of a group, or the `selected` ones
+    ///
+    /// Everything is resolved, downloaded and verified before the first
+    /// package is installed. Members are recorded as installed by the group
+    /// and whatever they pull in as dependencies.
+    pub async fn install_group(&mut self, group: &str, selected: Option<&[String]>) -> Result<()> {
+        let members = self.select_group_members(group, selected).await?;
 
-        for dep in &package.dependencies {
-            if dep.optional || dep.build_only {
+        let mut install_plan: Vec<ResolvedPackage> = Vec::new();
+        for name in &members {
+            if self.database.is_installed(name).await? {
                 continue;
             }
 
-            // Skip if already installed and satisfies requirement
-            if self.database.is_installed(&dep.name).await? {
-                let installed = self.database.get_installed_package(&dep.name).await?;
-                let req = semver::VersionReq::parse(&dep.version_req)?;
-                if req.matches(&installed.package.version) {
-                    continue;
+            let resolved = self.find_package(name).await?
+                .ok_or_else(|| self.not_found(name))?;
+            for pkg in self.resolve_dependencies(&resolved).await? {
+                if !install_plan.iter().any(|p| p.package.name == pkg.package.name) {
+                    install_plan.push(pkg);
                 }
             }
+        }
 
-            // Find dependency package
-            if let Some(dep_pkg) = self.find_package(&dep.name).await? {
-                self.resolve_deps_recursive(&dep_pkg, to_install, visited).await?;
-            } else {
-                return Err(anyhow::anyhow!("Dependency {} not found", dep.name));
+        self.install_resolved(install_plan, &members, InstallReason::Group).await
+    }
+
+    /// Members of `group`, narrowed to `selected` if given
+    async fn select_group_members(&self, group: &str, selected: Option<&[String]>) -> Result<Vec<String>> {
+        let members = self.group_members(group).await?;
+        let Some(selected) = selected else {
+            // A whole group takes only the members built for the target
+            if self.config.force_arch {
+                return Ok(members);
+            }
+            let mut fitting = Vec::new();
+            for name in members {
+                match self.find_package(&name).await? {
+                    Some(resolved) if !self.fits_target(&resolved.package) => {
+                        warn!("Skipping {}: not built for this architecture", name);
+                    }
+                    _ => fitting.push(name),
+                }
             }
+            return Ok(fitting);
+        };
+
+        if let Some(stray) = selected.iter().find(|name| !members.contains(name)) {
+            return Err(anyhow::anyhow!("Package {} is not a member of group {}", stray, group));
+        }
+        Ok(selected.to_vec())
+    }
+
+    /// List explicitly installed packages and their dependencies with their
+    /// exact versions
+    pub async fn export_world(&self) -> Result<Vec<WorldEntry>> {
+        Ok(self.database.get_world_packages().await?
+            .into_iter()
+            .map(|(name, version, dependency)| WorldEntry { name, version, dependency })
+            .collect())
+    }
+
+    /// Install a package set produced by [`PackageManager::export_world`]
+    ///
+    /// Packages that are already installed are left alone unless
+    /// `pin_versions` is set and the installed version differs, in which
+    /// case the listed version is installed in its place. Listed explicit
+    /// packages end up marked as explicitly installed. Dependencies are only
+    /// installed when pinning, first, so their listed versions are used.
+    pub async fn import_world(&mut self, list: &[WorldEntry], pin_versions: bool) -> Result<()> {
+        let (dependencies, explicit): (Vec<_>, Vec<_>) = list.iter().partition(|e| e.dependency);
+
+        if pin_versions {
+            for entry in dependencies {
+                let installed = self.database.is_installed(&entry.name).await?;
+                self.import_entry(entry, true).await?;
+                if !installed {
+                    self.database.set_install_reason(&entry.name, &InstallReason::Dependency).await?;
+                }
+            }
+        }
+
+        for entry in explicit {
+            self.import_entry(entry, pin_versions).await?;
+            self.database.set_install_reason(&entry.name, &InstallReason::Explicit).await?;
         }
 
-        to_install.push(package.clone());
         Ok(())
     }
 
-    /// Download a package
-    async fn download_package(&self, package: &Package) -> Result<PathBuf> {
-        let cache_path = self.cache.get_package_path(package);
-        
-        if cache_path.exists() {
-            // Verify cached package
-            if self.verify_cached_package(package, &cache_path).await? {
-                return Ok(cache_path);
+    /// Install one world entry unless it is already in place
+    async fn import_entry(&mut self, entry: &WorldEntry, pin_version: bool) -> Result<()> {
+        if self.database.is_installed(&entry.name).await? {
+            let installed = self.database.get_installed_package(&entry.name).await?;
+            if !pin_version || installed.package.version == entry.version {
+                return Ok(());
             }
         }
 
-        // Find download URL
-        let download_url = self.get_package_url(package).await?;
+        let result = if pin_version {
+            let req = VersionReq::parse(&format!("={}", entry.version))?;
+            self.install_version(&entry.name, &req).await
+        } else {
+            self.install(&entry.name).await
+        };
+        result.with_context(|| format!("Failed to import {}", entry.name))
+    }
 
-        // Download with progress
-        let response = reqwest::get(&download_url).await?;
-        let total_size = response.content_length().unwrap_or(package.size_bytes);
+    /// Summaries of every installed package, sorted by name
+    pub async fn list_installed(&self) -> Result<Vec<InstalledSummary>> {
+        let held = self.database.get_held_packages().await?;
+        let installed = self.database.get_installed_packages().await?;
 
-        let pb = indicatif::ProgressBar::new(total_size);
-        pb.set_style(
-            indicatif::ProgressStyle::default_bar()
-                .template("[{elapsed_precise}] {bar:40.cyan/blue} {bytes}/{total_bytes} {msg}")?
-                .progress_chars("##-"),
-        );
-        pb.set_message(format!("Downloading {}", package.name));
+        Ok(installed.into_iter().map(|installed| InstalledSummary {
+            held: held.contains(&installed.package.name),
+            name: installed.package.name,
+            version: installed.package.version,
+            description: installed.package.description,
+            install_reason: installed.install_reason,
+            size_bytes: installed.package.size_bytes,
+        }).collect())
+    }
 
-        // Stream to file
-        let mut file = tokio::fs::File::create(&cache_path).await?;
-        let mut stream = response.bytes_stream();
+    /// Details of an installed package, or of the newest available version
+    /// when it is not installed
+    pub async fn package_info(&self, package_name: &str) -> Result<PackageInfo> {
+        if self.database.is_installed(package_name).await? {
+            let installed = self.database.get_installed_package(package_name).await?;
+            return Ok(PackageInfo {
+                held: self.database.is_held(package_name).await?,
+                files: installed.files.iter().map(|file| Path::new("/").join(&file.path)).collect(),
+                package: installed.package,
+                repository: None,
+                install_reason: Some(installed.install_reason),
+            });
+        }
 
-        use tokio::io::AsyncWriteExt;
-        use futures::StreamExt;
+        let resolved = self.find_package(package_name).await?
+            .ok_or_else(|| self.not_found(package_name))?;
+        Ok(PackageInfo {
+            package: resolved.package,
+            repository: Some(resolved.repository),
+            install_reason: None,
+            held: false,
+            files: Vec::new(),
+        })
+    }
 
-        while let Some(chunk) = stream.next().await {
-            let chunk = chunk?;
-            file.write_all(&chunk).await?;
-            pb.inc(chunk.len() as u64);
+    /// Files installed by a package, as absolute paths on the target system
+    pub async fn list_files(&self, package_name: &str) -> Result<Vec<PathBuf>> {
+        if !self.database.is_installed(package_name).await? {
+            return Err(anyhow::anyhow!("Package {} is not installed", package_name));
         }
 
-        pb.finish_with_message(format!("Downloaded {}", package.name));
+        let files = self.database.get_package_files(package_name).await?;
+        Ok(files.into_iter().map(|path| Path::new("/").join(path)).collect())
+    }
 
-        Ok(cache_path)
+    /// Installed packages that own `path`
+    ///
+    /// A regular file has at most one owner; directories can be shared.
+    /// `path` is taken relative to the target system, though a path under
+    /// a non-default `root_dir` is accepted as well.
+    pub async fn owner_of(&self, path: &Path) -> Result<Vec<String>> {
+        let path = match path.strip_prefix(&self.config.root_dir) {
+            Ok(relative) if self.config.root_dir != Path::new("/") => relative,
+            _ => path.strip_prefix("/").unwrap_or(path),
+        };
+        self.database.get_file_owners(&path.to_string_lossy()).await
     }
 
-    /// Verify package integrity
-    async fn verify_package(&self, package: &Package) -> Result<()> {
-        let cache_path = self.cache.get_package_path(package);
-        
-        // Calculate checksums
-        let data = tokio::fs::read(&cache_path).await?;
-        
-        use sha2::{Sha256, Digest};
-        let sha256 = hex::encode(Sha256::digest(&data));
-        let blake3 = hex::encode(blake3::hash(&data).as_bytes());
+    /// Installed packages that directly depend on `package_name`
+    pub async fn rdepends(&self, package_name: &str) -> Result<Vec<String>> {
+        if !self.database.is_installed(package_name).await? {
+            return Err(anyhow::anyhow!("Package {} is not installed", package_name));
+        }
 
-        // Verify checksums
-        if sha256 != package.checksum.sha256 {
-            return Err(anyhow::anyhow!("SHA256 checksum mismatch for {}", package.name));
+        self.database.get_dependents(package_name).await
+    }
+
+    /// Explain why a package is installed
+    ///
+    /// Returns the shortest dependency chain from an explicitly installed
+    /// package down to `package_name` (which is the last element), or `None`
+    /// if nothing the user asked for needs it.
+    pub async fn why(&self, package_name: &str) -> Result<Option<Vec<String>>> {
+        use std::collections::{HashSet, VecDeque};
+
+        // Breadth-first walk up the reverse dependency graph
+        let mut parent: HashMap<String, String> = HashMap::new();
+        let mut seen = HashSet::from([package_name.to_string()]);
+        let mut queue = VecDeque::from([package_name.to_string()]);
+
+        while let Some(name) = queue.pop_front() {
+            if !matches!(self.database.get_install_reason(&name).await?, InstallReason::Dependency) {
+                // Rebuild the chain from the explicit package down to the target
+                let mut chain = vec![name.clone()];
+                let mut current = name;
+                while let Some(next) = parent.get(&current) {
+                    chain.push(next.clone());
+                    current = next.clone();
+                }
+                return Ok(Some(chain));
+            }
+
+            for dependent in self.database.get_dependents(&name).await? {
+                if seen.insert(dependent.clone()) {
+                    parent.insert(dependent.clone(), name.clone());
+                    queue.push_back(dependent);
+                }
+            }
         }
 
-        if blake3 != package.checksum.blake3 {
-            return Err(anyhow::anyhow!("BLAKE3 checksum mismatch for {}", package.name));
+        Ok(None)
+    }
+
+    /// Check installed packages for missing or modified files, unmet
+    /// dependencies and unfinished transactions
+    pub async fn check_problems(&self) -> Result<Vec<Problem>> {
+        let mut problems = Vec::new();
+
+        for installed in self.database.get_installed_packages().await? {
+            let name = &installed.package.name;
+
+            for file in &installed.files {
+                let path = installed.install_path.join(&file.path);
+                if path.symlink_metadata().is_err() {
+                    problems.push(Problem::MissingFile {
+                        package: name.clone(),
+                        path: file.path.clone(),
+                    });
+                } else if !file.checksum.is_empty() && path.is_file()
+                    && file_checksum(&path)? != file.checksum
+                {
+                    problems.push(Problem::ModifiedFile {
+                        package: name.clone(),
+                        path: file.path.clone(),
+                    });
+                }
+            }
+
+            for dep in &installed.package.dependencies {
+                if dep.optional || dep.build_only {
+                    continue;
+                }
+                if !self.database.is_provided(&dep.name).await? {
+                    problems.push(Problem::MissingDependency {
+                        package: name.clone(),
+                        dependency: dep.name.clone(),
+                    });
+                }
+            }
         }
 
-        // Verify signature if present
-        if self.config.verify_signatures {
-            if let Some(ref signature) = package.signature {
-                // TODO: Implement signature verification
+        for (id, kind, package) in self.database.get_pending_transactions().await? {
+            problems.push(Problem::StaleTransaction { id, kind, package });
+        }
+
+        Ok(problems)
+    }
+
+    /// Repair what can be repaired, returning the problems that remain
+    ///
+    /// Missing and modified files are re-extracted from the cached package
+    /// archive when it is still available, except modified files under
+    /// `/etc`, which are left as edited; stale transactions are deleted.
+    pub async fn fix_problems(&mut self, problems: &[Problem]) -> Result<Vec<Problem>> {
+        use std::collections::HashSet;
+
+        let mut remaining = Vec::new();
+        let mut damaged: HashMap<&str, HashSet<&Path>> = HashMap::new();
+
+        for problem in problems {
+            match problem {
+                // Configuration is meant to be edited; restoring it would
+                // throw the administrator's changes away
+                Problem::ModifiedFile { path, .. } if is_config_file(path) => remaining.push(problem.clone()),
+                Problem::MissingFile { package, path } | Problem::ModifiedFile { package, path } => {
+                    damaged.entry(package.as_str()).or_default().insert(path.as_path());
+                }
+                Problem::StaleTransaction { id, .. } => {
+                    self.database.delete_transaction(*id).await?;
+                }
+                Problem::MissingDependency { .. } => remaining.push(problem.clone()),
             }
         }
 
-        Ok(())
+        for (package, paths) in damaged {
+            let installed = self.database.get_installed_package(package).await?;
+            let restored = self.restore_files(&installed, &paths).await?;
+
+            for problem in problems {
+                match problem {
+                    Problem::MissingFile { package: p, path } | Problem::ModifiedFile { package: p, path }
+                        if p == package && paths.contains(path.as_path()) && !restored.contains(path) =>
+                    {
+                        remaining.push(problem.clone());
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(remaining)
     }
 
-    /// Install a package from cache
-    async fn install_package(&mut self, package: Package) -> Result<()> {
-        let cache_path = self.cache.get_package_path(&package);
-        let install_root = &self.config.root_dir;
+    /// Re-extract selected files of an installed package from the cache,
+    /// returning the paths that were restored
+    async fn restore_files(&self, installed: &InstalledPackage, paths: &std::collections::HashSet<&Path>) -> Result<Vec<PathBuf>> {
+        let cache_path = self.cache.get_package_path(&installed.package);
+        if !self.verify_cached_package(&installed.package, &cache_path).await?.is_valid() {
+            return Ok(Vec::new());
+        }
 
-        // Extract package
         let tar = std::fs::File::open(&cache_path)?;
         let decoder = zstd::Decoder::new(tar)?;
         let mut archive = tar::Archive::new(decoder);
+        let mut restored = Vec::new();
 
-        let mut installed_files = Vec::new();
-
-        // Track installed files
         for entry in archive.entries()? {
             let mut entry = entry?;
             let path = entry.path()?.to_path_buf();
-            let install_path = install_root.join(&path);
+            if !paths.contains(path.as_path()) {
+                continue;
+            }
 
-            // Create parent directories
+            let install_path = installed.install_path.join(&path);
             if let Some(parent) = install_path.parent() {
                 std::fs::create_dir_all(parent)?;
             }
-
-            // Extract file
             entry.unpack(&install_path)?;
-
-            // Record installed file
-            let metadata = install_path.metadata()?;
-            installed_files.push(InstalledFile {
-                path: path.to_path_buf(),
-                checksum: String::new(),  // TODO: Calculate file checksum
-                size: metadata.len(),
-                permissions: 0o644,  // TODO: Get actual permissions
-            });
+            restored.push(path);
         }
 
-        // Record installation in database
-        let installed = InstalledPackage {
-            package,
-            install_date: Utc::now(),
-            install_path: install_root.to_path_buf(),
-            files: installed_files,
-            install_reason: InstallReason::Explicit,
-        };
+        Ok(restored)
+    }
 
-        self.database.record_installation(installed).await?;
+    /// Hold a package at its installed version so `update` skips it
+    pub async fn hold(&self, package_name: &str) -> Result<()> {
+        self.database.set_held(package_name, true).await
+    }
 
-        Ok(())
+    /// Release a hold placed with [`PackageManager::hold`]
+    pub async fn unhold(&self, package_name: &str) -> Result<()> {
+        self.database.set_held(package_name, false).await
     }
 
-    /// Upgrade a package
-    async fn upgrade_package(&mut self, package: Package) -> Result<()> {
-        let old_version = self.database.get_installed_package(&package.name).await?;
-        
-        // Download new version
-        self.download_package(&package).await?;
-        
-        // Verify new package
-        self.verify_package(&package).await?;
-        
-        // Backup configuration files
-        let config_files = self.backup_config_files(&old_version).await?;
-        
-        // Remove old version
-        self.remove(&package.name).await?;
-        
-        // Install new version
-        self.install_package(package).await?;
-        
-        // Restore configuration files
-        self.restore_config_files(config_files).await?;
-        
-        Ok(())
+    /// List held packages
+    pub async fn held_packages(&self) -> Result<Vec<String>> {
+        self.database.get_held_packages().await
     }
 
-    /// Remove orphaned packages
-    async fn remove_orphans(&mut self) -> Result<()> {
-        let orphans = self.database.find_orphans().await?;
-        
-        for orphan in orphans {
-            println!("Removing orphaned package: {}", orphan);
-            self.remove(&orphan).await?;
+    /// Change why an installed package is considered installed
+    ///
+    /// Marking a package as a dependency makes it a candidate for orphan
+    /// removal once nothing depends on it; marking it explicit keeps it.
+    pub async fn set_install_reason(&self, package_name: &str, reason: InstallReason) -> Result<()> {
+        self.database.set_install_reason(package_name, &reason).await
+    }
+
+    /// Dependency-installed packages nothing installed needs any more
+    pub async fn orphans(&self) -> Result<Vec<String>> {
+        self.database.find_orphans().await
+    }
+
+    /// Remove a package
+    #[async_recursion::async_recursion]
+    pub async fn remove(&mut self, package_name: &str) -> Result<()> {
+        // Check if installed
+        if !self.database.is_installed(package_name).await? {
+            return Err(anyhow::anyhow!("Package {} is not installed", package_name));
         }
-        
+
+        // Check for dependent packages
+        let dependents = self.database.get_dependents(package_name).await?;
+        if !dependents.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Cannot remove {}: required by {:?}", 
+                package_name, dependents
+            ));
+        }
+
+        self.remove_installed(package_name).await?;
+
+        // Remove orphaned dependencies if configured
+        if self.config.auto_remove_orphans {
+            self.remove_orphans().await?;
+        }
+
         Ok(())
     }
 
-    /// Verify cached package
-    async fn verify_cached_package(&self, package: &Package, path: &Path) -> Result<bool> {
-        if !path.exists() {
-            return Ok(false);
+    /// Remove packages as planned by [`PackageManager::plan_remove`]
+    ///
+    /// With `cascade`, dependencies orphaned by the removal go too. The whole
+    /// set is computed before anything is deleted, then removed dependents
+    /// first. Returns the plan that was carried out.
+    pub async fn remove_packages(&mut self, targets: &[String], cascade: bool) -> Result<TransactionPlan> {
+        let plan = self.plan_remove(targets, cascade).await?;
+
+        for planned in &plan.packages {
+            self.remove_installed(&planned.package.name).await
+                .with_context(|| format!("Failed to remove {}", planned.package.name))?;
         }
 
-        let data = tokio::fs::read(path).await?;
-        
-        use sha2::{Sha256, Digest};
-        let sha256 = hex::encode(Sha256::digest(&data));
-        
-        Ok(sha256 == package.checksum.sha256)
+        if self.config.auto_remove_orphans {
+            self.remove_orphans().await?;
+        }
+
+        Ok(plan)
     }
 
-    /// Get package download URL
-    async fn get_package_url(&self, package: &Package) -> Result<String> {
-        // Find repository containing this package
-        for repo in &self.repositories {
-            // Check if repository has this package
-            // TODO: Implement proper URL construction
-            let url = format!("{}/packages/{}-{}.pkg.tar.zst", 
-                repo.url, package.name, package.version);
-            return Ok(url);
+    /// Run hooks, delete files and drop the database record of an installed
+    /// package, without checking dependents
+    async fn remove_installed(&mut self, package_name: &str) -> Result<()> {
+        let installed = self.database.get_installed_package(package_name).await?;
+
+        // A failing pre-remove hook keeps the package installed
+        self.run_hook(HookKind::PreRemove, &installed.package).await?;
+
+        // Remove files, then give back any the package took over
+        Self::remove_files(&installed)?;
+        self.restore_overwritten(package_name).await?;
+
+        // Update database
+        self.database.mark_removed(package_name).await?;
+
+        if let Err(e) = self.run_hook(HookKind::PostRemove, &installed.package).await {
+            warn!("{}", e);
         }
-        
-        Err(anyhow::anyhow!("No repository contains package {}", package.name))
+
+        let hooks_dir = self.hooks_dir(package_name);
+        if hooks_dir.exists() {
+            std::fs::remove_dir_all(&hooks_dir)?;
+        }
+
+        Ok(())
     }
 
-    /// Backup configuration files
-    async fn backup_config_files(&self, installed: &InstalledPackage) -> Result<Vec<PathBuf>> {
-        let mut config_files = Vec::new();
-        
-        for file in &installed.files {
-            if file.path.starts_with("/etc") {
-                let backup_path = file.path.with_extension("hecate-backup");
-                tokio::fs::copy(&file.path, &backup_path).await?;
-                config_files.push(backup_path);
+    /// Installed packages with a newer version in the synced repository indices
+    ///
+    /// Held packages are included and flagged so callers can decide whether to
+    /// skip them.
+    pub async fn outdated(&self) -> Result<Vec<OutdatedPackage>> {
+        let mut outdated = Vec::new();
+        for pkg in self.database.get_installed_packages().await? {
+            let Some(latest) = self.find_package(&pkg.package.name).await? else {
+                continue;
+            };
+
+            if latest.package.version > pkg.package.version {
+                outdated.push(OutdatedPackage {
+                    name: pkg.package.name.clone(),
+                    installed: pkg.package.version,
+                    held: self.database.is_held(&pkg.package.name).await?,
+                    available: latest.package,
+                });
             }
         }
-        
-        Ok(config_files)
+
+        outdated.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(outdated)
     }
 
-    /// Restore configuration files
-    async fn restore_config_files(&self, backups: Vec<PathBuf>) -> Result<()> {
-        for backup in backups {
-            if backup.exists() {
-                let original = backup.with_extension("");
-                
-                // Check if new config differs from old
-                let old_content = tokio::fs::read(&backup).await?;
-                let new_content = tokio::fs::read(&original).await?;
-                
-                if old_content != new_content {
-                    // Keep both versions
-                    let new_path = original.with_extension("hecate-new");
-                    tokio::fs::rename(&original, &new_path).await?;
-                    tokio::fs::rename(&backup, &original).await?;
-                    
-                    println!("Configuration file {} has been modified.", original.display());
-                    println!("  Old version: {}", original.display());
-                    println!("  New version: {}", new_path.display());
-                } else {
-                    // Remove backup
-                    tokio::fs::remove_file(&backup).await?;
+    /// Upgrade an installed package to exactly `version`
+    pub async fn upgrade(&mut self, package_name: &str, version: &Version) -> Result<()> {
+        if !self.database.is_installed(package_name).await? {
+            return Err(anyhow::anyhow!("Package {} is not installed", package_name));
+        }
+
+        let installed = self.database.get_installed_package(package_name).await?;
+        if installed.package.version >= *version {
+            return Err(anyhow::anyhow!(
+                "Package {} {} is not older than {}", package_name, installed.package.version, version
+            ));
+        }
+
+        let version_req = VersionReq::parse(&format!("={}", version))?;
+        self.install_version(package_name, &version_req).await
+    }
+
+    /// Update all packages
+    pub async fn update(&mut self) -> Result<()> {
+        // Update repository indices, or work from the cached ones when offline
+        if self.config.offline {
+            println!("Offline mode: using cached repository indices");
+        } else {
+            self.sync_repositories().await?;
+        }
+
+        // Find updates
+        let mut updates = Vec::new();
+        for outdated in self.outdated().await? {
+            if outdated.held {
+                println!("Skipping held package {} {}", outdated.name, outdated.installed);
+                continue;
+            }
+
+            if let Some(latest) = self.find_package(&outdated.name).await? {
+                updates.push((outdated.name, latest));
+            }
+        }
+
+        if updates.is_empty() {
+            println!("All packages are up to date");
+            return Ok(());
+        }
+
+        // Fetch all updates up front so downloads run in parallel
+        println!("Found {} updates", updates.len());
+        let packages: Vec<ResolvedPackage> = updates.iter().map(|(_, pkg)| pkg.clone()).collect();
+        self.download_packages(&packages, false).await?;
+
+        // Apply updates
+        for (name, pkg) in updates {
+            println!("Updating {} from {} to {}", name, 
+                self.database.get_installed_package(&name).await?.package.version,
+                pkg.package.version
+            );
+            self.upgrade_package(pkg).await?;
+        }
+
+        // Keep the cache within its configured size
+        self.cache.enforce_size_limit().await?;
+
+        Ok(())
+    }
+
+    /// Remove all but the `keep` most recently used cached packages,
+    /// returning the number of bytes freed
+    pub async fn clean_cache(&self, keep: usize) -> Result<u64> {
+        self.cache.clean(keep).await
+    }
+
+    /// Remove cached packages not touched in `days` days, returning the
+    /// number of bytes freed
+    pub async fn clean_cache_older_than(&self, days: u64) -> Result<u64> {
+        self.cache.clean_old(days).await
+    }
+
+    /// Get package cache statistics
+    pub async fn cache_stats(&self) -> Result<CacheStats> {
+        self.cache.get_stats().await
+    }
+
+    /// Database, cache and repository figures for `hecate-pkg stats`
+    pub async fn stats(&self) -> Result<PackageStats> {
+        Ok(PackageStats {
+            database: self.database.get_stats().await?,
+            cache: self.cache.get_stats().await?,
+            repositories: self.repositories.iter().filter(|repo| repo.enabled).count(),
+            updates_available: self.outdated().await?.iter().filter(|pkg| !pkg.held).count(),
+        })
+    }
+
+    /// Sync repository indices
+    pub async fn sync_repositories(&mut self) -> Result<()> {
+        use futures::stream::{self, StreamExt};
+
+        if self.config.offline {
+            return Err(anyhow::anyhow!("Cannot sync repositories in offline mode"));
+        }
+
+        let repos = self.repositories.clone();
+        let tasks = repos.into_iter()
+            .filter(|r| r.enabled)
+            .map(|repo| self.sync_repository(repo));
+
+        let results: Vec<Result<()>> = stream::iter(tasks)
+            .buffer_unordered(self.config.parallel_downloads)
+            .collect()
+            .await;
+
+        for result in results {
+            result?;
+        }
+
+        Ok(())
+    }
+
+    /// Sync a single repository
+    async fn sync_repository(&self, repo: Repository) -> Result<()> {
+        let index_urls: Vec<String> = repo.base_urls()
+            .into_iter()
+            .map(|base| format!("{}/index.json.zst", base))
+            .collect();
+        
+        // Download and verify the compressed index, falling back to mirrors
+        let compressed_data = self.downloader.fetch_verified_with_failover(&index_urls).await
+            .with_context(|| format!("Failed to sync repository {}", repo.name))?;
+
+        // Decompress
+        let data = decompress_index(&compressed_data, MAX_INDEX_SIZE)
+            .with_context(|| format!("Invalid index for repository {}", repo.name))?;
+
+        // Parse index
+        let index: RepositoryIndex = serde_json::from_slice(&data)?;
+
+        // Verify signature if enabled
+        if repo.gpg_check {
+            // TODO: Implement GPG verification
+        }
+
+        // Save to database
+        self.database.update_repository_index(index).await?;
+
+        Ok(())
+    }
+
+    /// Find the newest version of a package matching a version requirement
+    async fn find_package_matching(&self, name: &str, version_req: &VersionReq) -> Result<Option<ResolvedPackage>> {
+        self.find_package_where(name, |p| version_req.matches(&p.version)).await
+    }
+
+    /// Find the newest version of a package accepted by `accept`
+    ///
+    /// Builds for the target architecture win over any other build, in any
+    /// repository. Only when there are none is another architecture picked,
+    /// so that the architecture check can name the mismatch.
+    async fn find_package_where(&self, name: &str, accept: impl Fn(&Package) -> bool) -> Result<Option<ResolvedPackage>> {
+        let indices = self.database.get_repository_indices().await?;
+
+        for target_only in [true, false] {
+            // Indices come back in priority order, so the first repository wins
+            for repo_index in &indices {
+                let Some(versions) = repo_index.packages.get(name) else {
+                    continue;
+                };
+                let best = versions.iter()
+                    .filter(|p| accept(p) && (!target_only || self.fits_target(p)))
+                    .max_by_key(|p| &p.version);
+
+                if let Some(best) = best {
+                    return Ok(Some(ResolvedPackage {
+                        package: best.clone(),
+                        repository: repo_index.repository.name.clone(),
+                    }));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Find a package providing the virtual name `name`
+    ///
+    /// Providers are taken from the indices' `provides_index`, and the build
+    /// chosen for a provider must itself list `name`, since builds for
+    /// different architectures may provide different things.
+    async fn find_provider(&self, name: &str) -> Result<Option<ResolvedPackage>> {
+        let mut providers: Vec<String> = Vec::new();
+        for repo_index in self.database.get_repository_indices().await? {
+            for provider in repo_index.provides_index.get(name).into_iter().flatten() {
+                if !providers.contains(provider) {
+                    providers.push(provider.clone());
+                }
+            }
+        }
+
+        let mut fallback = None;
+        for provider in providers {
+            let provides = |p: &Package| p.provides.iter().any(|v| v == name);
+            let Some(resolved) = self.find_package_where(&provider, provides).await? else {
+                continue;
+            };
+            if self.fits_target(&resolved.package) {
+                return Ok(Some(resolved));
+            }
+            fallback.get_or_insert(resolved);
+        }
+        Ok(fallback)
+    }
+
+    /// Architecture packages are installed for: the configured one, or the
+    /// running machine's
+    fn target_arch(&self) -> Option<Architecture> {
+        self.config.architecture.or_else(Architecture::host)
+    }
+
+    /// Check whether a package can be installed for the target architecture
+    ///
+    /// An unknown target accepts everything; [`Self::check_architecture`]
+    /// reports it when it matters.
+    fn fits_target(&self, package: &Package) -> bool {
+        self.target_arch().is_none_or(|target| package.architecture.runs_on(target))
+    }
+
+    /// Ensure a package is built for the target machine
+    fn check_architecture(&self, package: &Package) -> Result<()> {
+        if self.config.force_arch || package.architecture == Architecture::All {
+            return Ok(());
+        }
+
+        let host = self.target_arch().ok_or_else(|| anyhow::anyhow!(
+            "Cannot determine the machine architecture for {}; set `architecture` in the \
+             configuration or pass --force-arch", package.name
+        ))?;
+        if !package.architecture.runs_on(host) {
+            return Err(anyhow::anyhow!(
+                "{} {} is built for {} but this machine is {}; pass --force-arch to install it anyway",
+                package.name, package.version, package.architecture.as_str(), host.as_str()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Ensure a package does not conflict with anything already installed
+    async fn check_conflicts(&self, package: &Package) -> Result<()> {
+        for name in &package.conflicts {
+            if name != &package.name && self.database.is_installed(name).await? {
+                return Err(anyhow::anyhow!(
+                    "{} {} conflicts with installed package {}",
+                    package.name, package.version, name
+                ));
+            }
+        }
+
+        for name in self.database.get_conflicting_packages(&package.name).await? {
+            if name != package.name {
+                return Err(anyhow::anyhow!(
+                    "Installed package {} conflicts with {}", name, package.name
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Error for a package missing from every repository index
+    fn not_found(&self, package_name: &str) -> anyhow::Error {
+        if self.config.offline {
+            anyhow::anyhow!("Package {} not found in cached repository indices (offline mode)", package_name)
+        } else {
+            anyhow::anyhow!("Package {} not found", package_name)
+        }
+    }
+
+    /// Find the newest version of a package in the repositories
+    async fn find_package(&self, name: &str) -> Result<Option<ResolvedPackage>> {
+        self.find_package_where(name, |_| true).await
+    }
+
+    /// Resolve package dependencies
+    async fn resolve_dependencies(&self, package: &ResolvedPackage) -> Result<Vec<ResolvedPackage>> {
+        let mut to_install = Vec::new();
+        let mut visited = std::collections::HashSet::new();
+
+        // Dependencies are pushed before their dependents, which is install order
+        self.resolve_deps_recursive(package, &mut to_install, &mut visited).await?;
+        Ok(to_install)
+    }
+
+    #[async_recursion::async_recursion]
+    async fn resolve_deps_recursive(
+        &self,
+        package: &ResolvedPackage,
+        to_install: &mut Vec<ResolvedPackage>,
+        visited: &mut std::collections::HashSet<String>,
+    ) -> Result<()> {
+        if visited.contains(&package.package.name) {
+            return Ok(());
+        }
+        visited.insert(package.package.name.clone());
+
+        for dep in &package.package.dependencies {
+            if dep.optional || dep.build_only {
+                continue;
+            }
+
+            // Skip if already installed and satisfies requirement
+            let req = semver::VersionReq::parse(&dep.version_req)?;
+            if self.database.is_installed(&dep.name).await? {
+                let installed = self.database.get_installed_package(&dep.name).await?;
+                if req.matches(&installed.package.version) {
+                    continue;
+                }
+            }
+
+            // Something installed may already provide it. Provides carry no
+            // version, so they only satisfy requirements on any version.
+            if req == semver::VersionReq::STAR
+                && self.database.is_provided(&dep.name).await?
+                && !self.database.is_installed(&dep.name).await?
+            {
+                continue;
+            }
+
+            // Find dependency package, or a package providing it
+            let found = match self.find_package(&dep.name).await? {
+                Some(dep_pkg) => Some(dep_pkg),
+                None => self.find_provider(&dep.name).await?,
+            };
+            if let Some(dep_pkg) = found {
+                self.resolve_deps_recursive(&dep_pkg, to_install, visited).await?;
+            } else if self.config.offline {
+                return Err(anyhow::anyhow!(
+                    "Dependency {} not found in cached repository indices (offline mode)", dep.name
+                ));
+            } else {
+                return Err(anyhow::anyhow!("Dependency {} not found", dep.name));
+            }
+        }
+
+        to_install.push(package.clone());
+        Ok(())
+    }
+
+    /// Download packages into the cache
+    ///
+    /// Packages already cached are skipped. The rest go through the download
+    /// manager, `parallel_downloads` at a time, resuming any partial file
+    /// left behind by an interrupted run. With `verify`, cached and
+    /// downloaded archives are checked against their checksums here;
+    /// installs leave that to extraction, which reads the archive anyway.
+    async fn download_packages(&self, packages: &[ResolvedPackage], verify: bool) -> Result<()> {
+        use futures::stream::{self, StreamExt};
+
+        let mut pending = Vec::new();
+        for resolved in packages {
+            let package = &resolved.package;
+            let cache_path = self.cache.get_package_path(package);
+            if !verify {
+                match std::fs::metadata(&cache_path) {
+                    Ok(metadata) if metadata.len() >= package.size_bytes => continue,
+                    _ => {}
+                }
+            } else {
+                match self.verify_cached_package(package, &cache_path).await? {
+                    CacheStatus::Valid => continue,
+                    // A complete but bad file would be taken as fully downloaded
+                    // rather than resumed, so fetch it afresh
+                    CacheStatus::Corrupt(reason) if std::fs::metadata(&cache_path)?.len() >= package.size_bytes => {
+                        warn!("Discarding cached {}: {}", package.name, reason);
+                        std::fs::remove_file(&cache_path)?;
+                    }
+                    CacheStatus::Corrupt(_) | CacheStatus::Missing => {}
                 }
             }
+
+            if self.config.offline {
+                return Err(anyhow::anyhow!(
+                    "Package {} {} is not in the cache and offline mode is enabled",
+                    package.name, package.version
+                ));
+            }
+
+            let urls = self.get_package_urls(resolved)?;
+            pending.push((package, urls, cache_path));
         }
+
+        // Collected up front: a lazy map's closure in the stream type makes
+        // the future fail the Send bound that async_trait callers need
+        let tasks: Vec<_> = pending.into_iter().map(|(package, urls, cache_path)| async move {
+            self.downloader.download_with_failover(&urls, &cache_path, package.size_bytes).await
+                .with_context(|| format!("Failed to download {}", package.name))?;
+
+            // Verify each package as soon as its download completes
+            if !verify {
+                return Ok(());
+            }
+            if let CacheStatus::Corrupt(reason) = self.verify_cached_package(package, &cache_path).await? {
+                tokio::fs::remove_file(&cache_path).await.ok();
+                return Err(anyhow::anyhow!(
+                    "Downloaded package {} failed checksum verification: {}", package.name, reason
+                ));
+            }
+
+            Ok(())
+        }).collect();
+
+        let results: Vec<Result<()>> = stream::iter(tasks)
+            .buffer_unordered(self.config.parallel_downloads.max(1))
+            .collect()
+            .await;
+
+        results.into_iter().collect()
+    }
+
+    /// Verify package integrity
+    async fn verify_package(&self, package: &Package) -> Result<()> {
+        let cache_path = self.cache.get_package_path(package);
         
+        // Verify checksums
+        match self.verify_cached_package(package, &cache_path).await? {
+            CacheStatus::Valid => {}
+            CacheStatus::Missing => {
+                return Err(anyhow::anyhow!("Package {} is not in the cache", package.name));
+            }
+            CacheStatus::Corrupt(reason) => return Err(anyhow::anyhow!(reason)),
+        }
+
+        // Verify signature if present
+        if self.config.verify_signatures {
+            if let Some(ref signature) = package.signature {
+                // TODO: Implement signature verification
+            }
+        }
+
         Ok(())
     }
-}
 
-// ============================================================================
-// DATABASE
-// ============================================================================
+    /// Install a downloaded package
+    async fn install_downloaded(&mut self, resolved: &ResolvedPackage, install_reason: InstallReason) -> Result<()> {
+        let (staged, collisions) = self.prepare_downloaded(resolved).await?;
+        self.commit_staged(staged, &collisions, install_reason).await
+    }
 
-// Database implementation moved to database.rs module
+    /// [`PackageManager::prepare_install`] for a downloaded package,
+    /// downloading it again once if the cached archive turns out to be corrupt
+    async fn prepare_downloaded(&self, resolved: &ResolvedPackage) -> Result<(StagedPackage, Vec<FileCollision>)> {
+        match self.prepare_install(&resolved.package).await {
+            Err(e) if e.is::<CorruptArchive>() && !self.config.offline => {
+                warn!("{}; downloading it again", e);
+                self.download_packages(std::slice::from_ref(resolved), false).await?;
+                self.prepare_install(&resolved.package).await
+            }
+            result => result,
+        }
+    }
 
-// Re-export types for public API
-pub use database::DatabaseStats;
-pub use cache::CacheStats;
\ No newline at end of file
+    /// Stage a package and check it can go in, without touching the system
+    ///
+    /// The archive is read once, into a staging directory, while its
+    /// checksums are checked. Collisions and the pre-install hook are
+    /// checked against the staged copy. Returns the collisions the overwrite
+    /// policy allows, for [`PackageManager::commit_staged`] to take over.
+    async fn prepare_install(&self, package: &Package) -> Result<(StagedPackage, Vec<FileCollision>)> {
+        let staged = self.stage_archive(package)?;
+
+        let checked = match self.file_collisions(package, &staged.paths).await {
+            Ok(collisions) => self.run_hook_from(&staged.hooks_dir(), HookKind::PreInstall, package).await
+                .map(|()| collisions),
+            Err(e) => Err(e),
+        };
+        match checked {
+            Ok(collisions) => Ok((staged, collisions)),
+            Err(e) => {
+                std::fs::remove_dir_all(&staged.dir)?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Move a staged package into the install root and record it
+    ///
+    /// `collisions` are taken over from their owners. If moving fails, what
+    /// was moved is taken back.
+    async fn commit_staged(
+        &mut self,
+        staged: StagedPackage,
+        collisions: &[FileCollision],
+        install_reason: InstallReason,
+    ) -> Result<()> {
+        let package = &staged.package;
+        let transfers = self.take_over_files(package, collisions).await?;
+
+        let mut created = Vec::new();
+        let mut replaced = Vec::new();
+        if let Err(e) = self.move_staged(&staged, &mut created, &mut replaced) {
+            self.undo_extraction(package, &created, &replaced, &transfers)?;
+            std::fs::remove_dir_all(&staged.dir)?;
+            return Err(e);
+        }
+        let replaced_dir = self.replaced_dir(&package.name);
+        if replaced_dir.exists() {
+            std::fs::remove_dir_all(&replaced_dir)?;
+        }
+
+        // Hooks from a previous version give way to the staged ones
+        let hooks_dir = self.hooks_dir(&package.name);
+        if hooks_dir.exists() {
+            std::fs::remove_dir_all(&hooks_dir)?;
+        }
+        let staged_hooks = staged.hooks_dir();
+        if staged_hooks.is_dir() {
+            if let Some(parent) = hooks_dir.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::rename(&staged_hooks, &hooks_dir)?;
+        }
+        std::fs::remove_dir_all(&staged.dir)?;
+
+        // Record installation in database
+        let installed = InstalledPackage {
+            package: package.clone(),
+            install_date: Utc::now(),
+            install_path: self.config.root_dir.clone(),
+            files: staged.files.clone(),
+            install_reason,
+        };
+
+        self.database.record_installation_with_transfers(installed, &transfers).await?;
+        for transfer in &transfers {
+            info!(
+                "{} took over /{} from {}",
+                package.name, transfer.file.path.display(), transfer.from_package
+            );
+        }
+
+        // The package is in place; a failing post-install is reported, not rolled back
+        if let Err(e) = self.run_hook(HookKind::PostInstall, package).await {
+            warn!("{}", e);
+        }
+
+        Ok(())
+    }
+
+    /// Unpack a package archive into its staging directory, hashing it as it
+    /// is read
+    ///
+    /// A corrupt archive is removed from the cache and reported as
+    /// [`CorruptArchive`].
+    fn stage_archive(&self, package: &Package) -> Result<StagedPackage> {
+        let cache_path = self.cache.get_package_path(package);
+        let dir = self.staging_dir(&package.name);
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir)?;
+        }
+        std::fs::create_dir_all(&dir)?;
+
+        let mut staged = StagedPackage {
+            package: package.clone(),
+            dir,
+            files: Vec::new(),
+            paths: Vec::new(),
+        };
+        if let Err(e) = self.unpack_staged(&cache_path, &mut staged) {
+            std::fs::remove_dir_all(&staged.dir)?;
+            std::fs::remove_file(&cache_path).ok();
+            return Err(match e.downcast::<CorruptArchive>() {
+                Ok(corrupt) => corrupt.into(),
+                Err(e) => CorruptArchive { package: package.name.clone(), reason: format!("{:#}", e) }.into(),
+            });
+        }
+        Ok(staged)
+    }
+
+    /// Unpack `archive_path` into `staged.dir`, recording its files
+    ///
+    /// Fails after unpacking if the archive's checksums don't match.
+    fn unpack_staged(&self, archive_path: &Path, staged: &mut StagedPackage) -> Result<()> {
+        let package = &staged.package;
+        let hasher = ArchiveHasher::new(package, self.config.hash_policy)
+            .map_err(|reason| anyhow::anyhow!(reason))?;
+        let file = std::fs::File::open(archive_path)?;
+        let decoder = zstd::Decoder::new(HashingReader::new(file, hasher))?;
+        let mut archive = tar::Archive::new(decoder);
+
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let path = entry.path()?.to_path_buf();
+            if !entry.unpack_in(&staged.dir)? {
+                return Err(anyhow::anyhow!("{} escapes the install root", path.display()));
+            }
+            if hooks::is_metadata_path(&path) {
+                continue;
+            }
+
+            // Record installed file
+            let staged_path = staged.dir.join(&path);
+            let metadata = staged_path.symlink_metadata()?;
+            let checksum = if metadata.is_file() {
+                file_checksum(&staged_path)?
+            } else {
+                String::new()
+            };
+            if !metadata.is_dir() {
+                staged.paths.push(path.clone());
+            }
+
+            use std::os::unix::fs::PermissionsExt;
+            staged.files.push(InstalledFile {
+                path,
+                checksum,
+                size: metadata.len(),
+                permissions: metadata.permissions().mode() & 0o7777,
+            });
+        }
+
+        if let CacheStatus::Corrupt(reason) = archive.into_inner().finish().into_inner().finish()? {
+            return Err(CorruptArchive { package: package.name.clone(), reason }.into());
+        }
+        Ok(())
+    }
+
+    /// Move staged files into the install root
+    ///
+    /// Paths that did not exist before are pushed to `created` as they are
+    /// moved. Files moved over are first copied to the package's replaced
+    /// directory and pushed to `replaced`, relative to the install root.
+    fn move_staged(&self, staged: &StagedPackage, created: &mut Vec<PathBuf>, replaced: &mut Vec<PathBuf>) -> Result<()> {
+        let install_root = &self.config.root_dir;
+
+        for file in &staged.files {
+            let from = staged.dir.join(&file.path);
+            let to = install_root.join(&file.path);
+
+            // Create parent directories
+            for dir in to.ancestors().skip(1).take_while(|dir| *dir != install_root.as_path()) {
+                if dir.symlink_metadata().is_err() {
+                    created.push(dir.to_path_buf());
+                }
+            }
+            if let Some(parent) = to.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            let metadata = from.symlink_metadata()?;
+            if metadata.is_dir() {
+                if to.symlink_metadata().is_err() {
+                    std::fs::create_dir(&to)?;
+                    created.push(to.clone());
+                }
+                if created.contains(&to) {
+                    std::fs::set_permissions(&to, metadata.permissions())?;
+                }
+                continue;
+            }
+
+            match to.symlink_metadata() {
+                Err(_) => created.push(to.clone()),
+                Ok(existing) if !existing.is_dir() => {
+                    overwrite::preserve(&to, &self.replaced_dir(&staged.package.name).join(&file.path))?;
+                    replaced.push(file.path.clone());
+                }
+                Ok(_) => {}
+            }
+            std::fs::rename(&from, &to)
+                .with_context(|| format!("Failed to move {} into place", to.display()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Take back a failed extraction: delete what it created and put back
+    /// the files it unpacked over or took over
+    ///
+    /// Only the backups this attempt made are dropped; the package's
+    /// overwritten directory may still hold ones an earlier install made.
+    fn undo_extraction(
+        &self,
+        package: &Package,
+        created: &[PathBuf],
+        replaced: &[PathBuf],
+        transfers: &[FileTransfer],
+    ) -> Result<()> {
+        // Deepest first, so directories are empty by the time they come up
+        let mut created = created.to_vec();
+        created.sort_by_key(|path| std::cmp::Reverse(path.components().count()));
+        for path in created {
+            if path.is_dir() && !path.is_symlink() {
+                std::fs::remove_dir(&path).ok();
+            } else if path.symlink_metadata().is_ok() {
+                std::fs::remove_file(&path)?;
+            }
+        }
+
+        let replaced_dir = self.replaced_dir(&package.name);
+        for path in replaced {
+            overwrite::preserve(&replaced_dir.join(path), &self.config.root_dir.join(path))?;
+        }
+        if replaced_dir.exists() {
+            std::fs::remove_dir_all(&replaced_dir)?;
+        }
+
+        let backup_dir = self.overwritten_dir(&package.name);
+        for transfer in transfers {
+            let backup = backup_dir.join(&transfer.file.path);
+            if backup.symlink_metadata().is_ok() {
+                overwrite::preserve(&backup, &self.config.root_dir.join(&transfer.file.path))?;
+                std::fs::remove_file(&backup)?;
+            }
+        }
+        prune_empty_dirs(&backup_dir)?;
+        Ok(())
+    }
+
+    /// Paths in a package archive owned by other installed packages
+    ///
+    /// Paths an installed version took over count as their previous
+    /// owners', who get them back when that version is replaced. Fails if
+    /// any collision is not allowed by the overwrite policy.
+    async fn file_collisions(&self, package: &Package, paths: &[PathBuf]) -> Result<Vec<FileCollision>> {
+        let policy = OverwritePolicy::new(&self.config.overwrite)?;
+        let taken_over = self.database.get_file_transfers(&package.name).await?;
+
+        let mut allowed = Vec::new();
+        let mut refused = Vec::new();
+        for path in paths {
+            let mut owners = self.database.get_file_owners(&path.to_string_lossy()).await?;
+            for transfer in taken_over.iter().filter(|transfer| &transfer.file.path == path) {
+                if self.database.is_installed(&transfer.from_package).await? {
+                    owners.push(transfer.from_package.clone());
+                }
+            }
+            for owner in owners.into_iter().filter(|owner| owner != &package.name) {
+                let collision = FileCollision { path: path.clone(), owner };
+                if policy.allows(path) {
+                    allowed.push(collision);
+                } else {
+                    refused.push(collision);
+                }
+            }
+        }
+
+        if !refused.is_empty() {
+            return Err(overwrite::collision_error(&package.name, &refused));
+        }
+        Ok(allowed)
+    }
+
+    /// Set aside the previous owners' copies of paths a package takes over
+    async fn take_over_files(&self, package: &Package, collisions: &[FileCollision]) -> Result<Vec<FileTransfer>> {
+        let backup_dir = self.overwritten_dir(&package.name);
+        let mut transfers = Vec::new();
+
+        for collision in collisions {
+            let previous = self.database.get_installed_package(&collision.owner).await?;
+            let Some(file) = previous.files.into_iter().find(|f| f.path == collision.path) else {
+                continue;
+            };
+
+            let target = self.config.root_dir.join(&collision.path);
+            if target.symlink_metadata().is_ok() {
+                overwrite::preserve(&target, &backup_dir.join(&collision.path))?;
+            }
+            transfers.push(FileTransfer { from_package: collision.owner.clone(), file });
+        }
+
+        Ok(transfers)
+    }
+
+    /// Put back the files a package took over from packages still installed
+    async fn restore_overwritten(&self, package_name: &str) -> Result<()> {
+        let backup_dir = self.overwritten_dir(package_name);
+
+        for transfer in self.database.get_file_transfers(package_name).await? {
+            let backup = backup_dir.join(&transfer.file.path);
+            if backup.symlink_metadata().is_ok() && self.database.is_installed(&transfer.from_package).await? {
+                overwrite::preserve(&backup, &self.config.root_dir.join(&transfer.file.path))?;
+            }
+        }
+        self.database.restore_file_transfers(package_name).await?;
+
+        if backup_dir.exists() {
+            std::fs::remove_dir_all(&backup_dir)?;
+        }
+        Ok(())
+    }
+
+    /// Directory holding files a package took over from other packages
+    fn overwritten_dir(&self, package_name: &str) -> PathBuf {
+        self.config.root_dir.join("var/lib/hecate-pkg/overwritten").join(package_name)
+    }
+
+    /// Directory holding files an install in progress unpacked over
+    fn replaced_dir(&self, package_name: &str) -> PathBuf {
+        self.config.root_dir.join("var/lib/hecate-pkg/replaced").join(package_name)
+    }
+
+    /// Directory a package is unpacked into before it moves into place
+    fn staging_dir(&self, package_name: &str) -> PathBuf {
+        self.config.root_dir.join("var/lib/hecate-pkg/staging").join(package_name)
+    }
+
+    /// Directory holding the hook scripts of an installed package
+    fn hooks_dir(&self, package_name: &str) -> PathBuf {
+        self.config.root_dir.join("var/lib/hecate-pkg/hooks").join(package_name)
+    }
+
+    /// Run a package hook, if hooks are enabled and the package ships one
+    async fn run_hook(&self, kind: HookKind, package: &Package) -> Result<()> {
+        self.run_hook_from(&self.hooks_dir(&package.name), kind, package).await
+    }
+
+    /// Like [`PackageManager::run_hook`], with the scripts in `hooks_dir`
+    async fn run_hook_from(&self, hooks_dir: &Path, kind: HookKind, package: &Package) -> Result<()> {
+        if !self.config.run_hooks {
+            return Ok(());
+        }
+
+        let runner = HookRunner::new(
+            &self.config.root_dir,
+            std::time::Duration::from_secs(self.config.hook_timeout_secs),
+        );
+
+        if let Some(output) = runner.run(hooks_dir, kind, package).await? {
+            for line in output.stdout.lines().chain(output.stderr.lines()) {
+                info!("{} {}: {}", package.name, kind, line);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Upgrade a package
+    async fn upgrade_package(&mut self, resolved: ResolvedPackage) -> Result<()> {
+        let old_version = self.database.get_installed_package(&resolved.package.name).await?;
+        
+        // Download new version
+        self.download_packages(std::slice::from_ref(&resolved), false).await?;
+        
+        // Stage and check it before the old version goes, so a bad archive,
+        // a refused collision or a failing pre-install leaves that in place
+        let (staged, collisions) = self.prepare_downloaded(&resolved).await?;
+        
+        // Backup configuration files
+        let config_files = self.backup_config_files(&old_version).await?;
+        
+        // Remove old version, keeping dependents in place. Files it took
+        // over go back to their owners; the new version takes them again
+        // if it still ships them.
+        Self::remove_files(&old_version)?;
+        self.restore_overwritten(&resolved.package.name).await?;
+        self.database.mark_removed(&resolved.package.name).await?;
+        
+        // Install new version
+        self.commit_staged(staged, &collisions, old_version.install_reason.clone()).await?;
+        
+        // Restore configuration files
+        self.restore_config_files(config_files).await?;
+        
+        Ok(())
+    }
+
+    /// Delete the files recorded for an installed package
+    fn remove_files(installed: &InstalledPackage) -> Result<()> {
+        for file in installed.files.iter().rev() {
+            let path = installed.install_path.join(&file.path);
+            if path.exists() {
+                if path.is_dir() {
+                    std::fs::remove_dir(&path)?;
+                } else {
+                    std::fs::remove_file(&path)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Remove orphaned packages
+    async fn remove_orphans(&mut self) -> Result<()> {
+        let orphans = self.database.find_orphans().await?;
+        
+        for orphan in orphans {
+            println!("Removing orphaned package: {}", orphan);
+            self.remove(&orphan).await?;
+        }
+        
+        Ok(())
+    }
+
+    /// Verify cached package
+    ///
+    /// The checksums chosen by the hash policy that the package actually
+    /// publishes, plus SHA512 when published, are computed in a single
+    /// streaming pass over the file.
+    async fn verify_cached_package(&self, package: &Package, path: &Path) -> Result<CacheStatus> {
+        use tokio::io::AsyncReadExt;
+
+        let mut file = match tokio::fs::File::open(path).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(CacheStatus::Missing),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut hasher = match ArchiveHasher::new(package, self.config.hash_policy) {
+            Ok(hasher) => hasher,
+            Err(reason) => return Ok(CacheStatus::Corrupt(reason)),
+        };
+
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            let n = file.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+        }
+
+        Ok(hasher.finish())
+    }
+
+    /// Get package download URLs from the owning repository, primary first
+    /// followed by mirrors
+    fn get_package_urls(&self, resolved: &ResolvedPackage) -> Result<Vec<String>> {
+        let package = &resolved.package;
+        let repo = self.repositories.iter()
+            .find(|r| r.name == resolved.repository)
+            .ok_or_else(|| anyhow::anyhow!(
+                "Repository {} providing {} is not configured",
+                resolved.repository, package.name
+            ))?;
+
+        let urls = repo.base_urls()
+            .into_iter()
+            .map(|base| format!("{}/{}/{}-{}.pkg.tar.zst",
+                base, package.architecture.as_str(), package.name, package.version))
+            .collect();
+
+        Ok(urls)
+    }
+
+    /// Backup configuration files
+    async fn backup_config_files(&self, installed: &InstalledPackage) -> Result<Vec<PathBuf>> {
+        let mut config_files = Vec::new();
+        
+        for file in &installed.files {
+            if file.path.starts_with("/etc") {
+                let backup_path = file.path.with_extension("hecate-backup");
+                tokio::fs::copy(&file.path, &backup_path).await?;
+                config_files.push(backup_path);
+            }
+        }
+        
+        Ok(config_files)
+    }
+
+    /// Restore configuration files
+    async fn restore_config_files(&self, backups: Vec<PathBuf>) -> Result<()> {
+        for backup in backups {
+            if backup.exists() {
+                let original = backup.with_extension("");
+                
+                // Check if new config differs from old
+                let old_content = tokio::fs::read(&backup).await?;
+                let new_content = tokio::fs::read(&original).await?;
+                
+                if old_content != new_content {
+                    // Keep both versions
+                    let new_path = original.with_extension("hecate-new");
+                    tokio::fs::rename(&original, &new_path).await?;
+                    tokio::fs::rename(&backup, &original).await?;
+                    
+                    println!("Configuration file {} has been modified.", original.display());
+                    println!("  Old version: {}", original.display());
+                    println!("  New version: {}", new_path.display());
+                } else {
+                    // Remove backup
+                    tokio::fs::remove_file(&backup).await?;
+                }
+            }
+        }
+        
+        Ok(())
+    }
+}
+
+// ============================================================================
+// DATABASE
+// ============================================================================
+
+// Database implementation moved to database.rs module
+
+// Re-export types for public API
+pub use database::DatabaseStats;
+pub use cache::{CacheStats, RateLimiter};
+pub use world::{WorldEntry, WorldFormat, format_world, parse_world};
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{build_archive, repository_index, test_package, FixtureServer};
+    use tempfile::tempdir;
+
+    fn test_config(base: &Path) -> PackageConfig {
+        PackageConfig {
+            root_dir: base.join("root"),
+            db_path: base.join("db/packages.db"),
+            cache_dir: base.join("cache"),
+            log_dir: base.join("log"),
+            ..Default::default()
+        }
+    }
+
+    fn test_repository(name: &str) -> Repository {
+        Repository {
+            name: name.to_string(),
+            url: format!("http://127.0.0.1:9/{}", name),
+            mirror_urls: Vec::new(),
+            enabled: true,
+            priority: 50,
+            gpg_check: false,
+            gpg_key: None,
+            last_update: None,
+        }
+    }
+
+    /// Place a package archive in the cache and fill in its checksums
+    fn stage_package(mgr: &PackageManager, package: Package) -> Package {
+        stage_package_with(mgr, package, &[])
+    }
+
+    /// Like [`stage_package`], with extra files added to the archive
+    fn stage_package_with(mgr: &PackageManager, mut package: Package, extra: &[(&str, &[u8])]) -> Package {
+        let path = format!("usr/share/{}/VERSION", package.name);
+        let content = package.version.to_string();
+        let mut files = vec![(path.as_str(), content.as_bytes())];
+        files.extend_from_slice(extra);
+        let archive = build_archive(&files);
+
+        use sha2::{Sha256, Digest};
+        package.checksum.sha256 = hex::encode(Sha256::digest(&archive));
+        package.checksum.blake3 = hex::encode(blake3::hash(&archive).as_bytes());
+        package.size_bytes = archive.len() as u64;
+        package.installed_size_bytes = content.len() as u64;
+
+        std::fs::write(mgr.cache.get_package_path(&package), &archive).unwrap();
+        package
+    }
+
+    /// Register packages as the index of a repository
+    async fn publish(mgr: &PackageManager, repo: &str, packages: Vec<Package>) {
+        let index = repository_index(test_repository(repo), packages);
+        mgr.database.update_repository_index(index).await.unwrap();
+    }
+
+    fn write_repo_file(config: &PackageConfig, repo: &Repository) {
+        let repos_dir = config.root_dir.join("etc/hecate-pkg/repos.d");
+        std::fs::create_dir_all(&repos_dir).unwrap();
+        std::fs::write(
+            repos_dir.join(format!("{}.repo", repo.name)),
+            toml::to_string(repo).unwrap(),
+        ).unwrap();
+    }
+
+    /// Install a package straight from the cache, without downloading it again
+    async fn install_cached(mgr: &mut PackageManager, package: Package) -> Result<()> {
+        let (staged, collisions) = mgr.prepare_install(&package).await?;
+        mgr.commit_staged(staged, &collisions, InstallReason::Explicit).await
+    }
+
+    async fn installed_version(mgr: &PackageManager, name: &str) -> Version {
+        mgr.database.get_installed_package(name).await.unwrap().package.version
+    }
+
+    #[tokio::test]
+    async fn test_outdated_and_upgrade() {
+        let dir = tempdir().unwrap();
+        let mut mgr = PackageManager::new(test_config(dir.path())).await.unwrap();
+
+        let v1 = stage_package(&mgr, test_package("foo", "1.0.0", &[]));
+        let v2 = stage_package(&mgr, test_package("foo", "1.1.0", &[]));
+        publish(&mgr, "core", vec![v1.clone()]).await;
+        mgr.install("foo").await.unwrap();
+        assert!(mgr.outdated().await.unwrap().is_empty());
+
+        publish(&mgr, "core", vec![v1, v2]).await;
+        let outdated = mgr.outdated().await.unwrap();
+        assert_eq!(outdated.len(), 1);
+        assert_eq!(outdated[0].installed, Version::new(1, 0, 0));
+        assert_eq!(outdated[0].available.version, Version::new(1, 1, 0));
+        assert!(!outdated[0].held);
+
+        assert!(mgr.upgrade("foo", &Version::new(1, 0, 0)).await.is_err());
+        mgr.upgrade("foo", &Version::new(1, 1, 0)).await.unwrap();
+        assert_eq!(installed_version(&mgr, "foo").await, Version::new(1, 1, 0));
+        assert!(mgr.outdated().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_keeps_cache_within_limit() {
+        let dir = tempdir().unwrap();
+        let mut mgr = PackageManager::new(test_config(dir.path())).await.unwrap();
+
+        let v1 = stage_package(&mgr, test_package("foo", "1.0.0", &[]));
+        publish(&mgr, "core", vec![v1.clone()]).await;
+        mgr.install("foo").await.unwrap();
+
+        let v2 = stage_package(&mgr, test_package("foo", "1.1.0", &[]));
+        publish(&mgr, "core", vec![v1, v2.clone()]).await;
+        mgr.cache.set_max_cache_size(0);
+        mgr.upgrade("foo", &Version::new(1, 1, 0)).await.unwrap();
+        assert!(!mgr.cache.get_package_path(&v2).exists());
+    }
+
+    /// Round-trip a value through the JSON printed by `--format json`
+    fn json_round_trip<T: Serialize + serde::de::DeserializeOwned>(value: &T) -> T {
+        serde_json::from_str(&serde_json::to_string_pretty(value).unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_query_results_serialize_to_json() {
+        let dir = tempdir().unwrap();
+        let config = test_config(dir.path());
+        write_repo_file(&config, &test_repository("core"));
+        let mut mgr = PackageManager::new(config).await.unwrap();
+
+        let lib = stage_package(&mgr, test_package("libfoo", "1.0.0", &[]));
+        let foo = stage_package(&mgr, test_package("foo", "1.0.0", &["libfoo"]));
+        let bar = stage_package(&mgr, test_package("bar", "2.0.0", &[]));
+        publish(&mgr, "core", vec![lib, foo, bar]).await;
+        mgr.install("foo").await.unwrap();
+        mgr.hold("foo").await.unwrap();
+
+        let results: Vec<SearchResult> = json_round_trip(&mgr.search("foo").await.unwrap());
+        assert_eq!(results[0].package.name, "foo");
+        assert_eq!(results[0].matched_field, MatchedField::ExactName);
+
+        let installed: Vec<InstalledSummary> = json_round_trip(&mgr.list_installed().await.unwrap());
+        let summary: Vec<(&str, &InstallReason, bool)> = installed.iter()
+            .map(|pkg| (pkg.name.as_str(), &pkg.install_reason, pkg.held))
+            .collect();
+        assert_eq!(summary, vec![
+            ("foo", &InstallReason::Explicit, true),
+            ("libfoo", &InstallReason::Dependency, false),
+        ]);
+
+        let info: PackageInfo = json_round_trip(&mgr.package_info("foo").await.unwrap());
+        assert_eq!(info.package.version, Version::new(1, 0, 0));
+        assert_eq!(info.install_reason, Some(InstallReason::Explicit));
+        assert!(info.held);
+        assert!(info.files.contains(&PathBuf::from("/usr/share/foo/VERSION")));
+
+        let info: PackageInfo = json_round_trip(&mgr.package_info("bar").await.unwrap());
+        assert_eq!(info.repository.as_deref(), Some("core"));
+        assert_eq!(info.install_reason, None);
+        assert!(info.files.is_empty());
+        assert!(mgr.package_info("missing").await.is_err());
+
+        let stats: PackageStats = json_round_trip(&mgr.stats().await.unwrap());
+        assert_eq!(stats.database.installed_packages, 2);
+        assert_eq!(stats.database.available_packages, 3);
+        assert_eq!(stats.repositories, 1);
+    }
+
+    #[tokio::test]
+    async fn test_stats_reflect_database() {
+        let dir = tempdir().unwrap();
+        let config = test_config(dir.path());
+        write_repo_file(&config, &test_repository("core"));
+        let mut mgr = PackageManager::new(config).await.unwrap();
+
+        let lib = stage_package(&mgr, test_package("libfoo", "1.0.0", &[]));
+        let foo = stage_package(&mgr, test_package("foo", "1.0.0", &["libfoo"]));
+        let bar = stage_package(&mgr, test_package("bar", "1.0.0", &[]));
+        let baz = stage_package(&mgr, test_package("baz", "1.0.0", &[]));
+        publish(&mgr, "core", vec![lib.clone(), foo.clone(), bar.clone(), baz.clone()]).await;
+        mgr.install("foo").await.unwrap();
+        mgr.install("bar").await.unwrap();
+        mgr.set_install_reason("bar", InstallReason::Dependency).await.unwrap();
+
+        let bar_next = stage_package(&mgr, test_package("bar", "1.1.0", &[]));
+        publish(&mgr, "core", vec![lib, foo, bar, bar_next, baz]).await;
+
+        let stats = mgr.stats().await.unwrap();
+        assert_eq!(stats.database.installed_packages, 3);
+        assert_eq!(stats.cache.package_count, 5);
+
+        let rendered = format_stats(&stats);
+        for line in [
+            "Total packages installed: 3\n",
+            "Explicitly installed: 1\n",
+            "Dependencies: 2\n",
+            "Orphaned packages: 1\n",
+            // Three VERSION files of five bytes each
+            "Total disk usage: 15 B\n",
+            "(5 packages)\n",
+            "Repositories: 1\n",
+            "Available packages: 4\n",
+            "Available updates: 1\n",
+        ] {
+            assert!(rendered.contains(line), "missing {:?} in:\n{}", line, rendered);
+        }
+        assert!(!rendered.contains("Installed with groups"));
+    }
+
+    #[tokio::test]
+    async fn test_list_files_and_owner_of() {
+        let dir = tempdir().unwrap();
+        let mut mgr = PackageManager::new(test_config(dir.path())).await.unwrap();
+
+        let foo = stage_package_with(&mgr, test_package("foo", "1.0.0", &[]), &[("usr/bin/foo", b"#!/bin/sh\n")]);
+        let bar = stage_package_with(&mgr, test_package("bar", "1.0.0", &[]), &[("usr/lib/bar.so", b"\x7fELF")]);
+        publish(&mgr, "core", vec![foo, bar]).await;
+        mgr.install("foo").await.unwrap();
+        mgr.install("bar").await.unwrap();
+
+        assert_eq!(mgr.list_files("foo").await.unwrap(), vec![
+            PathBuf::from("/usr/bin/foo"),
+            PathBuf::from("/usr/share/foo/VERSION"),
+        ]);
+        assert!(mgr.list_files("missing").await.is_err());
+
+        assert_eq!(mgr.owner_of(Path::new("/usr/bin/foo")).await.unwrap(), vec!["foo"]);
+        assert_eq!(mgr.owner_of(Path::new("usr/lib/bar.so")).await.unwrap(), vec!["bar"]);
+        // Paths inside the install root resolve too
+        let in_root = dir.path().join("root/usr/share/bar/VERSION");
+        assert_eq!(mgr.owner_of(&in_root).await.unwrap(), vec!["bar"]);
+        assert!(mgr.owner_of(Path::new("/usr/bin/nothing")).await.unwrap().is_empty());
+
+        // Removed packages no longer own anything
+        mgr.remove("foo").await.unwrap();
+        assert!(mgr.owner_of(Path::new("/usr/bin/foo")).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_whitelisted_overwrite_transfers_ownership() {
+        let dir = tempdir().unwrap();
+        let mut config = test_config(dir.path());
+        config.overwrite = vec!["/usr/bin/vi".to_string()];
+        let root = config.root_dir.clone();
+        let mut mgr = PackageManager::new(config).await.unwrap();
+
+        let vim = stage_package_with(&mgr, test_package("vim", "9.0.0", &[]), &[("usr/bin/vi", b"vim\n")]);
+        let nvi = stage_package_with(&mgr, test_package("nvi", "1.0.0", &[]), &[("usr/bin/vi", b"nvi\n")]);
+        publish(&mgr, "core", vec![vim, nvi]).await;
+        mgr.install("vim").await.unwrap();
+        mgr.install("nvi").await.unwrap();
+
+        assert_eq!(std::fs::read(root.join("usr/bin/vi")).unwrap(), b"nvi\n");
+        assert_eq!(mgr.owner_of(Path::new("/usr/bin/vi")).await.unwrap(), vec!["nvi"]);
+        assert!(!mgr.list_files("vim").await.unwrap().contains(&PathBuf::from("/usr/bin/vi")));
+
+        // Removing the new owner hands the path and its content back
+        mgr.remove("nvi").await.unwrap();
+        assert_eq!(std::fs::read(root.join("usr/bin/vi")).unwrap(), b"vim\n");
+        assert_eq!(mgr.owner_of(Path::new("/usr/bin/vi")).await.unwrap(), vec!["vim"]);
+        assert!(mgr.database.get_file_transfers("nvi").await.unwrap().is_empty());
+        assert!(mgr.check_problems().await.unwrap().is_empty());
+
+        mgr.remove("vim").await.unwrap();
+        assert!(!root.join("usr/bin/vi").exists());
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_keeps_taken_over_files_restorable() {
+        let dir = tempdir().unwrap();
+        let mut config = test_config(dir.path());
+        config.overwrite = vec!["/usr/bin/vi".to_string()];
+        let root = config.root_dir.clone();
+        let mut mgr = PackageManager::new(config).await.unwrap();
+
+        let vim = stage_package_with(&mgr, test_package("vim", "9.0.0", &[]), &[("usr/bin/vi", b"vim\n")]);
+        let nvi1 = stage_package_with(&mgr, test_package("nvi", "1.0.0", &[]), &[("usr/bin/vi", b"nvi 1\n")]);
+        let nvi2 = stage_package_with(&mgr, test_package("nvi", "2.0.0", &[]), &[("usr/bin/vi", b"nvi 2\n")]);
+        let nvi3 = stage_package(&mgr, test_package("nvi", "3.0.0", &[]));
+        publish(&mgr, "core", vec![vim, nvi1, nvi2, nvi3]).await;
+        mgr.install("vim").await.unwrap();
+        mgr.install_version("nvi", &VersionReq::parse("=1.0.0").unwrap()).await.unwrap();
+
+        // Still shipping the path, the new version takes it over again
+        mgr.upgrade("nvi", &Version::new(2, 0, 0)).await.unwrap();
+        assert_eq!(std::fs::read(root.join("usr/bin/vi")).unwrap(), b"nvi 2\n");
+        assert_eq!(mgr.owner_of(Path::new("/usr/bin/vi")).await.unwrap(), vec!["nvi"]);
+        let transfers = mgr.database.get_file_transfers("nvi").await.unwrap();
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].from_package, "vim");
+
+        // Dropping it from the package hands it back to vim
+        mgr.upgrade("nvi", &Version::new(3, 0, 0)).await.unwrap();
+        assert_eq!(std::fs::read(root.join("usr/bin/vi")).unwrap(), b"vim\n");
+        assert_eq!(mgr.owner_of(Path::new("/usr/bin/vi")).await.unwrap(), vec!["vim"]);
+        assert!(mgr.database.get_file_transfers("nvi").await.unwrap().is_empty());
+        assert!(mgr.check_problems().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_archive_rolls_back_extraction() {
+        let dir = tempdir().unwrap();
+        let mut config = test_config(dir.path());
+        config.overwrite = vec!["/usr/bin/vi".to_string()];
+        let root = config.root_dir.clone();
+        let mut mgr = PackageManager::new(config).await.unwrap();
+
+        let vim = stage_package_with(&mgr, test_package("vim", "9.0.0", &[]), &[("usr/bin/vi", b"vim\n")]);
+        let nvi = stage_package_with(&mgr, test_package("nvi", "1.0.0", &[]), &[
+            ("usr/bin/vi", b"nvi\n"),
+            ("usr/lib/nvi/recover", b"#!/bin/sh\n"),
+        ]);
+        publish(&mgr, "core", vec![vim, nvi.clone()]).await;
+        mgr.install("vim").await.unwrap();
+
+        // Every entry still decodes; only the final digest gives it away
+        let cache_path = mgr.cache.get_package_path(&nvi);
+        let mut archive = std::fs::read(&cache_path).unwrap();
+        archive.extend_from_slice(b"trailing garbage");
+        std::fs::write(&cache_path, &archive).unwrap();
+
+        let err = install_cached(&mut mgr, nvi).await.unwrap_err();
+        assert!(err.to_string().contains("SHA256 checksum mismatch for nvi"), "{}", err);
+        assert!(!cache_path.exists(), "a corrupt archive is dropped from the cache");
+
+        assert_eq!(std::fs::read(root.join("usr/bin/vi")).unwrap(), b"vim\n");
+        assert!(!root.join("usr/lib").exists());
+        assert!(!root.join("usr/share/nvi").exists());
+        assert!(!mgr.overwritten_dir("nvi").exists());
+        assert!(!mgr.staging_dir("nvi").exists());
+        assert!(!mgr.database.is_installed("nvi").await.unwrap());
+        assert_eq!(mgr.owner_of(Path::new("/usr/bin/vi")).await.unwrap(), vec!["vim"]);
+        assert!(mgr.check_problems().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rollback_restores_files_unpacked_over() {
+        let dir = tempdir().unwrap();
+        let config = test_config(dir.path());
+        let root = config.root_dir.clone();
+        let mut mgr = PackageManager::new(config).await.unwrap();
+
+        // Left behind by hand, owned by no package
+        std::fs::create_dir_all(root.join("usr/bin")).unwrap();
+        std::fs::write(root.join("usr/bin/tool"), b"local\n").unwrap();
+
+        let tool = stage_package_with(&mgr, test_package("tool", "1.0.0", &[]), &[("usr/bin/tool", b"tool\n")]);
+        let cache_path = mgr.cache.get_package_path(&tool);
+        let mut archive = std::fs::read(&cache_path).unwrap();
+        archive.extend_from_slice(b"trailing garbage");
+        std::fs::write(&cache_path, &archive).unwrap();
+
+        install_cached(&mut mgr, tool).await.unwrap_err();
+        assert_eq!(std::fs::read(root.join("usr/bin/tool")).unwrap(), b"local\n");
+        assert!(!root.join("usr/share/tool").exists());
+        assert!(!mgr.replaced_dir("tool").exists());
+    }
+
+    #[tokio::test]
+    async fn test_failed_upgrade_keeps_old_version() {
+        let dir = tempdir().unwrap();
+        let config = test_config(dir.path());
+        let root = config.root_dir.clone();
+        let mut mgr = PackageManager::new(config).await.unwrap();
+
+        let v1 = stage_package_with(&mgr, test_package("foo", "1.0.0", &[]), &[("usr/bin/foo", b"1\n")]);
+        let v2 = stage_package_with(&mgr, test_package("foo", "1.1.0", &[]), &[("usr/bin/foo", b"2\n")]);
+        publish(&mgr, "core", vec![v1, v2.clone()]).await;
+        mgr.install_version("foo", &VersionReq::parse("=1.0.0").unwrap()).await.unwrap();
+
+        // Corrupt, with no server to fetch it from again
+        let cache_path = mgr.cache.get_package_path(&v2);
+        let mut archive = std::fs::read(&cache_path).unwrap();
+        archive.extend_from_slice(b"trailing garbage");
+        std::fs::write(&cache_path, &archive).unwrap();
+
+        assert!(mgr.upgrade("foo", &Version::new(1, 1, 0)).await.is_err());
+        assert_eq!(installed_version(&mgr, "foo").await, Version::new(1, 0, 0));
+        assert_eq!(std::fs::read(root.join("usr/bin/foo")).unwrap(), b"1\n");
+        assert_eq!(mgr.owner_of(Path::new("/usr/bin/foo")).await.unwrap(), vec!["foo"]);
+        assert!(mgr.check_problems().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failing_pre_install_hook_keeps_old_version() {
+        let dir = tempdir().unwrap();
+        let config = test_config(dir.path());
+        let root = config.root_dir.clone();
+        let mut mgr = PackageManager::new(config).await.unwrap();
+
+        let v1 = stage_package_with(&mgr, test_package("foo", "1.0.0", &[]), &[
+            ("usr/bin/foo", b"1\n"),
+            (".hecate/hooks/post-remove", b"touch post-remove-ran\n"),
+        ]);
+        let v2 = stage_package_with(&mgr, test_package("foo", "1.1.0", &[]), &[
+            ("usr/bin/foo", b"2\n"),
+            (".hecate/hooks/pre-install", b"echo refusing >&2\nexit 1\n"),
+        ]);
+        publish(&mgr, "core", vec![v1, v2]).await;
+        mgr.install_version("foo", &VersionReq::parse("=1.0.0").unwrap()).await.unwrap();
+
+        let err = mgr.upgrade("foo", &Version::new(1, 1, 0)).await.unwrap_err();
+        assert!(err.to_string().contains("refusing"), "{}", err);
+        assert_eq!(installed_version(&mgr, "foo").await, Version::new(1, 0, 0));
+        assert_eq!(std::fs::read(root.join("usr/bin/foo")).unwrap(), b"1\n");
+        assert_eq!(std::fs::read_to_string(root.join("usr/share/foo/VERSION")).unwrap(), "1.0.0");
+        assert!(mgr.hooks_dir("foo").join("post-remove").exists());
+        assert!(!mgr.staging_dir("foo").exists());
+        assert!(mgr.check_problems().await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_architecture_matching() {
+        assert_eq!(Architecture::from_machine("x86_64"), Some(Architecture::X86_64));
+        assert_eq!(Architecture::from_machine("arm64"), Some(Architecture::Aarch64));
+        assert_eq!(Architecture::from_machine("riscv64"), Some(Architecture::Riscv64));
+        assert_eq!(Architecture::from_machine("ppc64le"), None);
+
+        assert!(Architecture::Aarch64.runs_on(Architecture::Aarch64));
+        assert!(!Architecture::X86_64.runs_on(Architecture::Aarch64));
+        assert!(Architecture::All.runs_on(Architecture::Riscv64));
+    }
+
+    #[tokio::test]
+    async fn test_install_checks_architecture() {
+        let dir = tempdir().unwrap();
+        let mut config = test_config(dir.path());
+        config.architecture = Some(Architecture::Aarch64);
+        let mut mgr = PackageManager::new(config).await.unwrap();
+
+        let with_arch = |name: &str, deps: &[&str], architecture| {
+            let mut package = test_package(name, "1.0.0", deps);
+            package.architecture = architecture;
+            stage_package(&mgr, package)
+        };
+        let native = with_arch("native", &[], Architecture::Aarch64);
+        let noarch = with_arch("noarch", &[], Architecture::All);
+        let foreign = with_arch("foreign", &[], Architecture::X86_64);
+        let needs_foreign = with_arch("needs-foreign", &["foreign"], Architecture::Aarch64);
+        publish(&mgr, "core", vec![native, noarch, foreign, needs_foreign]).await;
+
+        mgr.install("native").await.unwrap();
+        mgr.install("noarch").await.unwrap();
+
+        let err = mgr.install("foreign").await.unwrap_err().to_string();
+        assert!(err.contains("foreign 1.0.0 is built for x86_64 but this machine is aarch64"), "{}", err);
+        assert!(mgr.install("needs-foreign").await.is_err());
+        assert!(!mgr.database.is_installed("foreign").await.unwrap());
+
+        mgr.config.force_arch = true;
+        mgr.install("needs-foreign").await.unwrap();
+        assert!(mgr.database.is_installed("foreign").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_resolution_prefers_target_architecture() {
+        let dir = tempdir().unwrap();
+        let mut mgr = PackageManager::new(test_config(dir.path())).await.unwrap();
+
+        let build = |name: &str, version: &str, architecture, deps: &[&str], provides: &[&str]| {
+            let mut package = test_package(name, version, deps);
+            package.architecture = architecture;
+            package.provides = provides.iter().map(|p| p.to_string()).collect();
+            package
+        };
+        let mut index = RepositoryIndex {
+            repository: test_repository("core"),
+            packages: HashMap::new(),
+            groups: HashMap::new(),
+            provides_index: HashMap::new(),
+        };
+        for pkg in [
+            build("foo", "1.0.0", Architecture::X86_64, &[], &[]),
+            build("foo", "1.0.0", Architecture::Aarch64, &[], &[]),
+            build("foo", "1.1.0", Architecture::X86_64, &[], &[]),
+            build("vim", "9.0.0", Architecture::X86_64, &[], &["editor"]),
+            build("vim", "9.0.0", Architecture::Aarch64, &[], &[]),
+            build("nano", "7.0.0", Architecture::Aarch64, &[], &["editor"]),
+            build("app", "1.0.0", Architecture::All, &["editor"], &[]),
+            build("x86-only", "1.0.0", Architecture::X86_64, &[], &[]),
+        ] {
+            index.packages.entry(pkg.name.clone()).or_default().push(pkg);
+        }
+        index.provides_index.insert("editor".to_string(), vec!["vim".to_string(), "nano".to_string()]);
+        index.groups.insert("tools".to_string(), vec!["foo".to_string(), "x86-only".to_string()]);
+        mgr.database.update_repository_index(index).await.unwrap();
+
+        let planned = |plan: TransactionPlan| -> Vec<(String, String, Architecture)> {
+            plan.packages.into_iter()
+                .map(|p| (p.package.name, p.package.version.to_string(), p.package.architecture))
+                .collect()
+        };
+        let entry = |name: &str, version: &str, architecture| (name.to_string(), version.to_string(), architecture);
+
+        mgr.config.architecture = Some(Architecture::Aarch64);
+        assert_eq!(planned(mgr.plan_install(&["foo".to_string()]).await.unwrap()),
+            vec![entry("foo", "1.0.0", Architecture::Aarch64)]);
+        assert_eq!(planned(mgr.plan_install(&["app".to_string()]).await.unwrap()),
+            vec![entry("nano", "7.0.0", Architecture::Aarch64), entry("app", "1.0.0", Architecture::All)]);
+        assert_eq!(planned(mgr.plan_group_install("tools", None).await.unwrap()),
+            vec![entry("foo", "1.0.0", Architecture::Aarch64)]);
+
+        mgr.config.architecture = Some(Architecture::X86_64);
+        assert_eq!(planned(mgr.plan_install(&["foo".to_string()]).await.unwrap()),
+            vec![entry("foo", "1.1.0", Architecture::X86_64)]);
+        assert_eq!(planned(mgr.plan_install(&["foo@1.0.0".to_string()]).await.unwrap()),
+            vec![entry("foo", "1.0.0", Architecture::X86_64)]);
+        assert_eq!(planned(mgr.plan_install(&["app".to_string()]).await.unwrap()),
+            vec![entry("vim", "9.0.0", Architecture::X86_64), entry("app", "1.0.0", Architecture::All)]);
+        assert_eq!(mgr.plan_group_install("tools", None).await.unwrap().packages.len(), 2);
+
+        // With no build for the target the mismatch is reported, not hidden
+        mgr.config.architecture = Some(Architecture::Riscv64);
+        assert!(mgr.install("x86-only").await.unwrap_err().to_string().contains("built for x86_64"));
+    }
+
+    #[tokio::test]
+    async fn test_versioned_dependency_is_not_met_by_provides() {
+        let dir = tempdir().unwrap();
+        let mut mgr = PackageManager::new(test_config(dir.path())).await.unwrap();
+
+        let mut nvim = test_package("nvim", "0.9.0", &[]);
+        nvim.provides = vec!["vim".to_string()];
+        let nvim = stage_package(&mgr, nvim);
+        let vim = stage_package(&mgr, test_package("vim", "9.0.0", &[]));
+        let app = test_package("app", "1.0.0", &["vim"]);
+        let mut strict = test_package("strict", "1.0.0", &["vim"]);
+        strict.dependencies[0].version_req = ">=9".to_string();
+        publish(&mgr, "core", vec![nvim, vim, app, strict]).await;
+        mgr.install("nvim").await.unwrap();
+
+        let planned = |plan: TransactionPlan| -> Vec<String> {
+            plan.packages.into_iter().map(|p| p.package.name).collect()
+        };
+        assert_eq!(planned(mgr.plan_install(&["app".to_string()]).await.unwrap()), vec!["app"]);
+        assert_eq!(planned(mgr.plan_install(&["strict".to_string()]).await.unwrap()), vec!["vim", "strict"]);
+    }
+
+    #[tokio::test]
+    async fn test_unlisted_collision_fails() {
+        let dir = tempdir().unwrap();
+        let mut config = test_config(dir.path());
+        config.overwrite = vec!["/usr/bin/view".to_string()];
+        let root = config.root_dir.clone();
+        let mut mgr = PackageManager::new(config).await.unwrap();
+
+        let vim = stage_package_with(&mgr, test_package("vim", "9.0.0", &[]), &[("usr/bin/vi", b"vim\n")]);
+        let nvi = stage_package_with(&mgr, test_package("nvi", "1.0.0", &[]), &[("usr/bin/vi", b"nvi\n")]);
+        publish(&mgr, "core", vec![vim, nvi]).await;
+        mgr.install("vim").await.unwrap();
+
+        let err = mgr.install("nvi").await.unwrap_err().to_string();
+        assert!(err.contains("/usr/bin/vi (owned by vim)"), "{}", err);
+        assert!(!mgr.database.is_installed("nvi").await.unwrap());
+        assert_eq!(std::fs::read(root.join("usr/bin/vi")).unwrap(), b"vim\n");
+        assert!(!root.join("usr/share/nvi/VERSION").exists());
+    }
+
+    #[tokio::test]
+    async fn test_mark_as_dependency_makes_orphan() {
+        let dir = tempdir().unwrap();
+        let mut mgr = PackageManager::new(test_config(dir.path())).await.unwrap();
+
+        let lib = stage_package(&mgr, test_package("libfoo", "1.0.0", &[]));
+        let app = stage_package(&mgr, test_package("foo", "1.0.0", &[]));
+        publish(&mgr, "core", vec![lib, app]).await;
+
+        mgr.install("foo").await.unwrap();
+        mgr.install("libfoo").await.unwrap();
+        assert!(mgr.orphans().await.unwrap().is_empty());
+
+        mgr.set_install_reason("foo", InstallReason::Dependency).await.unwrap();
+        assert_eq!(mgr.database.get_install_reason("foo").await.unwrap(), InstallReason::Dependency);
+        assert_eq!(mgr.orphans().await.unwrap(), vec!["foo".to_string()]);
+
+        mgr.set_install_reason("foo", InstallReason::Explicit).await.unwrap();
+        assert!(mgr.orphans().await.unwrap().is_empty());
+
+        assert!(mgr.set_install_reason("bar", InstallReason::Explicit).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_install_group() {
+        let dir = tempdir().unwrap();
+        let mut mgr = PackageManager::new(test_config(dir.path())).await.unwrap();
+
+        let lib = stage_package(&mgr, test_package("libfoo", "1.0.0", &[]));
+        let foo = stage_package(&mgr, test_package("foo", "1.0.0", &["libfoo"]));
+        let bar = stage_package(&mgr, test_package("bar", "1.0.0", &[]));
+        let mut index = RepositoryIndex {
+            repository: test_repository("core"),
+            packages: HashMap::new(),
+            groups: HashMap::new(),
+            provides_index: HashMap::new(),
+        };
+        for pkg in [lib, foo, bar] {
+            index.packages.entry(pkg.name.clone()).or_default().push(pkg);
+        }
+        index.groups.insert("tools".to_string(), vec!["foo".to_string(), "bar".to_string()]);
+        mgr.database.update_repository_index(index.clone()).await.unwrap();
+        // Syncing again must keep the members
+        mgr.database.update_repository_index(index).await.unwrap();
+
+        assert_eq!(mgr.groups().await.unwrap(), vec![("tools".to_string(), String::new())]);
+        assert_eq!(mgr.group_members("tools").await.unwrap(), vec!["bar".to_string(), "foo".to_string()]);
+        assert!(mgr.group_members("missing").await.is_err());
+
+        let plan = mgr.plan_group_install("tools", None).await.unwrap();
+        let planned: Vec<_> = plan.packages.iter()
+            .map(|p| (p.package.name.as_str(), p.reason.clone()))
+            .collect();
+        assert_eq!(planned, vec![
+            ("bar", InstallReason::Group),
+            ("libfoo", InstallReason::Dependency),
+            ("foo", InstallReason::Group),
+        ]);
+
+        let selected = ["baz".to_string()];
+        assert!(mgr.install_group("tools", Some(&selected)).await.is_err());
+
+        let selected = ["foo".to_string()];
+        mgr.install_group("tools", Some(&selected)).await.unwrap();
+        assert_eq!(mgr.database.get_install_reason("foo").await.unwrap(), InstallReason::Group);
+        assert_eq!(mgr.database.get_install_reason("libfoo").await.unwrap(), InstallReason::Dependency);
+        assert!(!mgr.database.is_installed("bar").await.unwrap());
+
+        mgr.install_group("tools", None).await.unwrap();
+        assert_eq!(mgr.database.get_install_reason("bar").await.unwrap(), InstallReason::Group);
+        assert!(mgr.orphans().await.unwrap().is_empty());
+        assert!(mgr.plan_group_install("tools", None).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_install_specific_version() {
+        let dir = tempdir().unwrap();
+        let mut mgr = PackageManager::new(test_config(dir.path())).await.unwrap();
+
+        let v1 = stage_package(&mgr, test_package("foo", "1.0.0", &[]));
+        let v2 = stage_package(&mgr, test_package("foo", "2.0.0", &[]));
+        publish(&mgr, "core", vec![v1, v2]).await;
+
+        mgr.install("foo").await.unwrap();
+        assert_eq!(installed_version(&mgr, "foo").await, Version::new(2, 0, 0));
+
+        // Downgrade
+        mgr.install_version("foo", &VersionReq::parse("=1.0.0").unwrap()).await.unwrap();
+        assert_eq!(installed_version(&mgr, "foo").await, Version::new(1, 0, 0));
+
+        let content = std::fs::read_to_string(
+            dir.path().join("root/usr/share/foo/VERSION")
+        ).unwrap();
+        assert_eq!(content, "1.0.0");
+    }
+
+    #[tokio::test]
+    async fn test_held_package_skipped_by_update() {
+        let dir = tempdir().unwrap();
+        let mut mgr = PackageManager::new(test_config(dir.path())).await.unwrap();
+
+        let v1 = stage_package(&mgr, test_package("foo", "1.0.0", &[]));
+        publish(&mgr, "core", vec![v1.clone()]).await;
+
+        mgr.install_version("foo", &VersionReq::parse("=1.0.0").unwrap()).await.unwrap();
+        mgr.hold("foo").await.unwrap();
+        assert_eq!(mgr.held_packages().await.unwrap(), vec!["foo".to_string()]);
+
+        // A newer version becomes available
+        let v2 = stage_package(&mgr, test_package("foo", "2.0.0", &[]));
+        publish(&mgr, "core", vec![v1, v2]).await;
+
+        mgr.update().await.unwrap();
+        assert_eq!(installed_version(&mgr, "foo").await, Version::new(1, 0, 0));
+
+        mgr.unhold("foo").await.unwrap();
+        mgr.update().await.unwrap();
+        assert_eq!(installed_version(&mgr, "foo").await, Version::new(2, 0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_update_upgrades_seeded_database() {
+        let dir = tempdir().unwrap();
+        let mut mgr = PackageManager::new(test_config(dir.path())).await.unwrap();
+
+        // Nothing installed, nothing to do
+        mgr.update().await.unwrap();
+
+        let foo1 = stage_package(&mgr, test_package("foo", "1.0.0", &[]));
+        let bar1 = stage_package(&mgr, test_package("bar", "1.0.0", &[]));
+        let baz1 = stage_package(&mgr, test_package("baz", "1.0.0", &[]));
+        publish(&mgr, "core", vec![foo1.clone(), bar1.clone(), baz1.clone()]).await;
+        for name in ["foo", "bar", "baz"] {
+            mgr.install(name).await.unwrap();
+        }
+
+        let foo2 = stage_package(&mgr, test_package("foo", "1.2.0", &[]));
+        let bar2 = stage_package(&mgr, test_package("bar", "2.0.0", &[]));
+        publish(&mgr, "core", vec![foo1, foo2, bar1, bar2, baz1]).await;
+
+        mgr.update().await.unwrap();
+        assert_eq!(installed_version(&mgr, "foo").await, Version::new(1, 2, 0));
+        assert_eq!(installed_version(&mgr, "bar").await, Version::new(2, 0, 0));
+        assert_eq!(installed_version(&mgr, "baz").await, Version::new(1, 0, 0));
+        assert!(mgr.outdated().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_install_resumes_partial_download() {
+        let dir = tempdir().unwrap();
+        let config = test_config(dir.path());
+
+        let server = FixtureServer::start().await;
+        let mut repo = test_repository("core");
+        repo.url = format!("{}/core", server.url);
+        write_repo_file(&config, &repo);
+
+        let mut mgr = PackageManager::new(config).await.unwrap();
+        let pkg = stage_package(&mgr, test_package("foo", "1.0.0", &[]));
+        publish(&mgr, "core", vec![pkg.clone()]).await;
+
+        let cache_path = mgr.cache.get_package_path(&pkg);
+        let archive = std::fs::read(&cache_path).unwrap();
+        server.serve("/core/all/foo-1.0.0.pkg.tar.zst", archive.clone());
+
+        // Leave only the first half behind, as an interrupted download would
+        let partial = archive.len() / 2;
+        std::fs::write(&cache_path, &archive[..partial]).unwrap();
+
+        mgr.install("foo").await.unwrap();
+
+        assert_eq!(server.ranges(), vec![Some(partial)]);
+        assert_eq!(std::fs::read(&cache_path).unwrap(), archive);
+        assert_eq!(installed_version(&mgr, "foo").await, Version::new(1, 0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_verify_cached_package_status() {
+        let dir = tempdir().unwrap();
+        let mgr = PackageManager::new(test_config(dir.path())).await.unwrap();
+        let pkg = stage_package(&mgr, test_package("foo", "1.0.0", &[]));
+        let cache_path = mgr.cache.get_package_path(&pkg);
+
+        assert_eq!(mgr.verify_cached_package(&pkg, &cache_path).await.unwrap(), CacheStatus::Valid);
+
+        // A wrong BLAKE3 alone is enough to reject the file
+        let mut wrong_blake3 = pkg.clone();
+        wrong_blake3.checksum.blake3 = "00".repeat(32);
+        assert!(matches!(
+            mgr.verify_cached_package(&wrong_blake3, &cache_path).await.unwrap(),
+            CacheStatus::Corrupt(reason) if reason.contains("BLAKE3")
+        ));
+
+        let mut archive = std::fs::read(&cache_path).unwrap();
+        let last = archive.len() - 1;
+        archive[last] ^= 0xff;
+        std::fs::write(&cache_path, &archive).unwrap();
+        assert!(matches!(
+            mgr.verify_cached_package(&pkg, &cache_path).await.unwrap(),
+            CacheStatus::Corrupt(reason) if reason.contains("SHA256")
+        ));
+
+        std::fs::remove_file(&cache_path).unwrap();
+        assert_eq!(mgr.verify_cached_package(&pkg, &cache_path).await.unwrap(), CacheStatus::Missing);
+    }
+
+    #[tokio::test]
+    async fn test_hash_policy_selects_checksums() {
+        let dir = tempdir().unwrap();
+        let mut mgr = PackageManager::new(test_config(dir.path())).await.unwrap();
+        let pkg = stage_package(&mgr, test_package("foo", "1.0.0", &[]));
+        let cache_path = mgr.cache.get_package_path(&pkg);
+
+        let mut wrong_sha256 = pkg.clone();
+        wrong_sha256.checksum.sha256 = "00".repeat(32);
+        let mut wrong_blake3 = pkg.clone();
+        wrong_blake3.checksum.blake3 = "00".repeat(32);
+
+        for (policy, checks_sha256, checks_blake3) in [
+            (HashPolicy::All, true, true),
+            (HashPolicy::Sha256, true, false),
+            (HashPolicy::Blake3, false, true),
+        ] {
+            mgr.config.hash_policy = policy;
+            assert_eq!(mgr.verify_cached_package(&pkg, &cache_path).await.unwrap(), CacheStatus::Valid);
+            let status = mgr.verify_cached_package(&wrong_sha256, &cache_path).await.unwrap();
+            assert_eq!(status.is_valid(), !checks_sha256, "{:?}", policy);
+            let status = mgr.verify_cached_package(&wrong_blake3, &cache_path).await.unwrap();
+            assert_eq!(status.is_valid(), !checks_blake3, "{:?}", policy);
+        }
+
+        // Only the checksums a package publishes are checked, but at least
+        // one the policy allows must be there
+        let mut blake3_only = pkg.clone();
+        blake3_only.checksum.sha256.clear();
+        mgr.config.hash_policy = HashPolicy::All;
+        assert_eq!(mgr.verify_cached_package(&blake3_only, &cache_path).await.unwrap(), CacheStatus::Valid);
+        mgr.config.hash_policy = HashPolicy::Sha256;
+        assert!(matches!(
+            mgr.verify_cached_package(&blake3_only, &cache_path).await.unwrap(),
+            CacheStatus::Corrupt(reason) if reason.contains("no checksum")
+        ));
+
+        let config: PackageConfig = toml::from_str("hash_policy = \"blake3\"").unwrap();
+        assert_eq!(config.hash_policy, HashPolicy::Blake3);
+    }
+
+    #[tokio::test]
+    async fn test_sha512_checksum_is_optional() {
+        use sha2::{Sha512, Digest};
+
+        let dir = tempdir().unwrap();
+        let mgr = PackageManager::new(test_config(dir.path())).await.unwrap();
+        let pkg = stage_package(&mgr, test_package("foo", "1.0.0", &[]));
+        let cache_path = mgr.cache.get_package_path(&pkg);
+        let digest = hex::encode(Sha512::digest(std::fs::read(&cache_path).unwrap()));
+
+        // Indexes written before SHA512 existed still parse and verify
+        let mut json = serde_json::to_value(&pkg.checksum).unwrap();
+        assert!(json.get("sha512").is_none());
+        json.as_object_mut().unwrap().remove("sha512");
+        let checksum: PackageChecksum = serde_json::from_value(json).unwrap();
+        assert_eq!(checksum.sha512, None);
+        assert_eq!(mgr.verify_cached_package(&pkg, &cache_path).await.unwrap(), CacheStatus::Valid);
+
+        let mut with_sha512 = pkg.clone();
+        with_sha512.checksum.sha512 = Some(digest.clone());
+        assert_eq!(mgr.verify_cached_package(&with_sha512, &cache_path).await.unwrap(), CacheStatus::Valid);
+
+        let mut wrong_sha512 = pkg.clone();
+        wrong_sha512.checksum.sha512 = Some("00".repeat(64));
+        assert!(matches!(
+            mgr.verify_cached_package(&wrong_sha512, &cache_path).await.unwrap(),
+            CacheStatus::Corrupt(reason) if reason.contains("SHA512")
+        ));
+
+        // A SHA512-only source verifies under the default policy
+        let checksum: PackageChecksum = serde_json::from_str(&format!("{{\"sha512\":\"{}\"}}", digest)).unwrap();
+        let mut sha512_only = pkg.clone();
+        sha512_only.checksum = checksum;
+        assert_eq!(mgr.verify_cached_package(&sha512_only, &cache_path).await.unwrap(), CacheStatus::Valid);
+    }
+
+    #[tokio::test]
+    async fn test_install_refetches_corrupt_cached_package() {
+        let dir = tempdir().unwrap();
+        let config = test_config(dir.path());
+
+        let server = FixtureServer::start().await;
+        let mut repo = test_repository("core");
+        repo.url = format!("{}/core", server.url);
+        write_repo_file(&config, &repo);
+
+        let mut mgr = PackageManager::new(config).await.unwrap();
+        let pkg = stage_package(&mgr, test_package("foo", "1.0.0", &[]));
+        publish(&mgr, "core", vec![pkg.clone()]).await;
+
+        let cache_path = mgr.cache.get_package_path(&pkg);
+        let archive = std::fs::read(&cache_path).unwrap();
+        server.serve("/core/all/foo-1.0.0.pkg.tar.zst", archive.clone());
+
+        // Full length but with a flipped byte, so there is nothing to resume
+        let mut corrupt = archive.clone();
+        corrupt[0] ^= 0xff;
+        std::fs::write(&cache_path, &corrupt).unwrap();
+
+        mgr.install("foo").await.unwrap();
+
+        assert_eq!(server.ranges(), vec![None]);
+        assert_eq!(std::fs::read(&cache_path).unwrap(), archive);
+        assert_eq!(installed_version(&mgr, "foo").await, Version::new(1, 0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_download_rate_limit_is_shared() {
+        let dir = tempdir().unwrap();
+        let server = FixtureServer::start().await;
+        server.serve("/a", vec![1u8; 24 * 1024]);
+        server.serve("/b", vec![2u8; 24 * 1024]);
+
+        // 48KiB at 16KiB/s with a one second burst needs at least two seconds,
+        // no matter how many connections share the limit
+        let downloader = DownloadManager::new(2, 16 * 1024);
+        let started = std::time::Instant::now();
+        let (url_a, url_b) = ([format!("{}/a", server.url)], [format!("{}/b", server.url)]);
+        let (path_a, path_b) = (dir.path().join("a"), dir.path().join("b"));
+        let (a, b) = futures::join!(
+            downloader.download_with_failover(&url_a, &path_a, 0),
+            downloader.download_with_failover(&url_b, &path_b, 0),
+        );
+        a.unwrap();
+        b.unwrap();
+
+        assert!(started.elapsed() >= std::time::Duration::from_millis(1900));
+        assert_eq!(std::fs::read(dir.path().join("b")).unwrap().len(), 24 * 1024);
+    }
+
+    #[tokio::test]
+    async fn test_mirror_failover() {
+        let dir = tempdir().unwrap();
+        let config = test_config(dir.path());
+
+        // Reserve a port and close it so the primary URL refuses connections
+        let dead = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let dead_url = format!("http://{}", dead.local_addr().unwrap());
+        drop(dead);
+
+        let server = FixtureServer::start().await;
+        let mut repo = test_repository("core");
+        repo.url = dead_url;
+        repo.mirror_urls = vec![format!("{}/mirror", server.url)];
+        write_repo_file(&config, &repo);
+
+        let mut mgr = PackageManager::new(config).await.unwrap();
+        let pkg = stage_package(&mgr, test_package("foo", "1.0.0", &[]));
+        let cache_path = mgr.cache.get_package_path(&pkg);
+        server.serve("/mirror/all/foo-1.0.0.pkg.tar.zst", std::fs::read(&cache_path).unwrap());
+        std::fs::remove_file(&cache_path).unwrap();
+
+        let index = RepositoryIndex {
+            repository: repo.clone(),
+            packages: HashMap::from([("foo".to_string(), vec![pkg])]),
+            groups: HashMap::new(),
+            provides_index: HashMap::new(),
+        };
+        let json = serde_json::to_vec(&index).unwrap();
+        let compressed = zstd::encode_all(json.as_slice(), 3).unwrap();
+        server.serve_index("/mirror", compressed.clone(), &compressed);
+
+        mgr.sync_repositories().await.unwrap();
+        mgr.install("foo").await.unwrap();
+
+        assert!(cache_path.exists());
+        assert_eq!(installed_version(&mgr, "foo").await, Version::new(1, 0, 0));
+    }
+
+    /// A manager whose only repository is served by a fresh fixture server
+    async fn manager_with_server(config: PackageConfig) -> (PackageManager, FixtureServer, Vec<u8>) {
+        let server = FixtureServer::start().await;
+        let mut repo = test_repository("core");
+        repo.url = server.url.clone();
+        write_repo_file(&config, &repo);
+
+        let index = RepositoryIndex {
+            repository: repo,
+            packages: HashMap::new(),
+            groups: HashMap::new(),
+            provides_index: HashMap::new(),
+        };
+        let compressed = zstd::encode_all(serde_json::to_vec(&index).unwrap().as_slice(), 3).unwrap();
+        (PackageManager::new(config).await.unwrap(), server, compressed)
+    }
+
+    #[tokio::test]
+    async fn test_sync_rejects_truncated_index() {
+        let dir = tempdir().unwrap();
+        let (mut mgr, server, compressed) = manager_with_server(test_config(dir.path())).await;
+
+        let truncated = compressed[..compressed.len() / 2].to_vec();
+        server.serve_index("", truncated, &compressed);
+        let err = mgr.sync_repositories().await.unwrap_err();
+        assert!(format!("{:#}", err).contains("checksum mismatch"), "{:#}", err);
+        assert!(mgr.database.get_repository_indices().await.unwrap().is_empty());
+
+        server.serve_index("", compressed.clone(), &compressed);
+        mgr.sync_repositories().await.unwrap();
+        assert_eq!(mgr.database.get_repository_indices().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_sync_rejects_checksum_mismatch() {
+        let dir = tempdir().unwrap();
+        let (mut mgr, server, compressed) = manager_with_server(test_config(dir.path())).await;
+
+        server.serve_index("", compressed.clone(), b"something else");
+        let err = mgr.sync_repositories().await.unwrap_err();
+        assert!(format!("{:#}", err).contains("checksum mismatch"), "{:#}", err);
+
+        // No checksum published at all
+        server.unserve("/index.json.zst.sha256");
+        let err = mgr.sync_repositories().await.unwrap_err();
+        assert!(format!("{:#}", err).contains("Failed to fetch checksum"), "{:#}", err);
+        assert!(mgr.database.get_repository_indices().await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_decompress_index_size_cap() {
+        let compressed = zstd::encode_all(vec![b'{'; 64 * 1024].as_slice(), 3).unwrap();
+        assert_eq!(decompress_index(&compressed, 64 * 1024).unwrap().len(), 64 * 1024);
+
+        let err = decompress_index(&compressed, 1024).unwrap_err();
+        assert!(err.to_string().contains("exceeds 1024 bytes"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_chunked_download() {
+        let dir = tempdir().unwrap();
+        let server = FixtureServer::start().await;
+        let data: Vec<u8> = (0..1_000_003u32).map(|i| (i % 251) as u8).collect();
+        server.serve("/big.iso", data.clone());
+        let downloader = DownloadManager::new(4, 0);
+        let url = format!("{}/big.iso", server.url);
+
+        let path = dir.path().join("big.iso");
+        downloader.download_chunked(&url, &path, data.len() as u64, 4).await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), data);
+        assert!(!dir.path().join("big.iso.chunked").exists());
+
+        let mut starts: Vec<Option<usize>> = server.ranges();
+        starts.sort();
+        assert_eq!(starts, vec![Some(0), Some(250_001), Some(500_002), Some(750_003)]);
+
+        // A size mismatch fails and leaves nothing behind
+        let wrong = dir.path().join("wrong.iso");
+        assert!(downloader.download_chunked(&url, &wrong, data.len() as u64 + 10, 4).await.is_err());
+        assert!(!wrong.exists());
+        assert!(!dir.path().join("wrong.iso.chunked").exists());
+    }
+
+    #[tokio::test]
+    async fn test_chunked_download_without_range_support() {
+        let dir = tempdir().unwrap();
+        let server = FixtureServer::start().await;
+        server.disable_ranges();
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i % 13) as u8).collect();
+        server.serve("/big.iso", data.clone());
+
+        let path = dir.path().join("big.iso");
+        DownloadManager::new(4, 0)
+            .download_chunked(&format!("{}/big.iso", server.url), &path, data.len() as u64, 4)
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), data);
+        assert_eq!(server.ranges(), vec![None]);
+    }
+
+    fn fast_retry() -> RetryPolicy {
+        RetryPolicy { retries: 3, base_delay: std::time::Duration::from_millis(10) }
+    }
+
+    #[tokio::test]
+    async fn test_download_retries_transient_failures() {
+        let dir = tempdir().unwrap();
+        let server = FixtureServer::start().await;
+        server.serve("/pkg", vec![7u8; 4096]);
+        let downloader = DownloadManager::new(1, 0).with_retry(fast_retry());
+        let url = vec![format!("{}/pkg", server.url)];
+
+        server.fail_next("/pkg", &["503 Service Unavailable", "502 Bad Gateway"]);
+        let path = dir.path().join("pkg");
+        downloader.download_with_failover(&url, &path, 4096).await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), vec![7u8; 4096]);
+        assert_eq!(server.pending_failures("/pkg"), 0);
+
+        // Out of retries: the last error comes back
+        server.fail_next("/pkg", &["504 Gateway Timeout"; 5]);
+        let err = downloader.download_with_failover(&url, &dir.path().join("again"), 4096).await.unwrap_err();
+        assert!(err.to_string().contains("504"), "{}", err);
+        assert_eq!(server.pending_failures("/pkg"), 1);
+
+        // A 404 is final
+        server.fail_next("/pkg", &["404 Not Found", "404 Not Found"]);
+        assert!(downloader.download_with_failover(&url, &dir.path().join("missing"), 4096).await.is_err());
+        assert_eq!(server.pending_failures("/pkg"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_sync_retries_transient_failures() {
+        let dir = tempdir().unwrap();
+        let config = PackageConfig {
+            retry_base_delay_ms: 10,
+            ..test_config(dir.path())
+        };
+        let (mut mgr, server, compressed) = manager_with_server(config).await;
+        server.serve_index("", compressed.clone(), &compressed);
+        server.fail_next("/index.json.zst", &["503 Service Unavailable", "503 Service Unavailable"]);
+
+        mgr.sync_repositories().await.unwrap();
+        assert_eq!(mgr.database.get_repository_indices().await.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_retry_delay_backs_off() {
+        let policy = RetryPolicy { retries: 10, base_delay: std::time::Duration::from_millis(100) };
+        for attempt in 0..4 {
+            let backoff = std::time::Duration::from_millis(100 << attempt);
+            let delay = policy.delay(attempt);
+            assert!(delay >= backoff && delay <= backoff * 3 / 2, "{:?}", delay);
+        }
+        assert!(policy.delay(20) <= std::time::Duration::from_secs(45));
+    }
+
+    #[tokio::test]
+    async fn test_download_from_owning_repository() {
+        let dir = tempdir().unwrap();
+        let config = test_config(dir.path());
+
+        let core_server = FixtureServer::start().await;
+        let extra_server = FixtureServer::start().await;
+        let mut core = test_repository("core");
+        core.url = core_server.url.clone();
+        core.priority = 10;
+        let mut extra = test_repository("extra");
+        extra.url = extra_server.url.clone();
+        extra.priority = 20;
+        write_repo_file(&config, &core);
+        write_repo_file(&config, &extra);
+
+        let mut mgr = PackageManager::new(config).await.unwrap();
+        let foo = stage_package(&mgr, test_package("foo", "1.0.0", &[]));
+        let bar = stage_package(&mgr, test_package("bar", "1.0.0", &[]));
+        let shadowed = stage_package(&mgr, test_package("foo", "9.0.0", &[]));
+        publish(&mgr, "core", vec![foo.clone()]).await;
+        publish(&mgr, "extra", vec![bar.clone(), shadowed.clone()]).await;
+
+        for (server, pkg) in [(&core_server, &foo), (&extra_server, &bar), (&extra_server, &shadowed)] {
+            let cache_path = mgr.cache.get_package_path(pkg);
+            server.serve(
+                &format!("/all/{}-{}.pkg.tar.zst", pkg.name, pkg.version),
+                std::fs::read(&cache_path).unwrap(),
+            );
+            std::fs::remove_file(&cache_path).unwrap();
+        }
+
+        mgr.install("foo").await.unwrap();
+        mgr.install("bar").await.unwrap();
+
+        // foo comes from the higher-priority repository even though extra has a newer one
+        assert_eq!(installed_version(&mgr, "foo").await, Version::new(1, 0, 0));
+        assert_eq!(installed_version(&mgr, "bar").await, Version::new(1, 0, 0));
+        assert_eq!(core_server.ranges().len(), 1);
+        assert_eq!(extra_server.ranges().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_install_and_remove_hooks() {
+        let dir = tempdir().unwrap();
+        let mut mgr = PackageManager::new(test_config(dir.path())).await.unwrap();
+
+        let pkg = stage_package_with(&mgr, test_package("foo", "1.0.0", &[]), &[
+            (".hecate/hooks/post-install", "touch post-install-ran\n".as_bytes()),
+            (".hecate/hooks/post-remove", "echo \"$HECATE_PKG_NAME\" > post-remove-ran\n".as_bytes()),
+        ]);
+        publish(&mgr, "core", vec![pkg]).await;
+
+        let root = dir.path().join("root");
+        mgr.install("foo").await.unwrap();
+        assert!(root.join("post-install-ran").exists());
+        assert!(!root.join(".hecate").exists());
+
+        mgr.remove("foo").await.unwrap();
+        assert_eq!(std::fs::read_to_string(root.join("post-remove-ran")).unwrap(), "foo\n");
+        assert!(!mgr.hooks_dir("foo").exists());
+    }
+
+    #[tokio::test]
+    async fn test_failing_pre_install_hook_aborts() {
+        let dir = tempdir().unwrap();
+        let mut mgr = PackageManager::new(test_config(dir.path())).await.unwrap();
+
+        let pkg = stage_package_with(&mgr, test_package("foo", "1.0.0", &[]), &[
+            (".hecate/hooks/pre-install", "echo refusing >&2\nexit 1\n".as_bytes()),
+        ]);
+        publish(&mgr, "core", vec![pkg]).await;
+
+        let err = mgr.install("foo").await.unwrap_err();
+        assert!(err.to_string().contains("refusing"));
+        assert!(!mgr.database.is_installed("foo").await.unwrap());
+        assert!(!dir.path().join("root/usr/share/foo/VERSION").exists());
+    }
+
+    #[tokio::test]
+    async fn test_hooks_disabled() {
+        let dir = tempdir().unwrap();
+        let mut config = test_config(dir.path());
+        config.run_hooks = false;
+        let mut mgr = PackageManager::new(config).await.unwrap();
+
+        let pkg = stage_package_with(&mgr, test_package("foo", "1.0.0", &[]), &[
+            (".hecate/hooks/post-install", "touch post-install-ran\n".as_bytes()),
+        ]);
+        publish(&mgr, "core", vec![pkg]).await;
+
+        mgr.install("foo").await.unwrap();
+        assert!(!dir.path().join("root/post-install-ran").exists());
+    }
+
+    /// Config whose repository points at a closed port, so any network use fails
+    fn offline_config(base: &Path) -> PackageConfig {
+        let mut config = test_config(base);
+        config.offline = true;
+
+        let dead = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut repo = test_repository("core");
+        repo.url = format!("http://{}", dead.local_addr().unwrap());
+        drop(dead);
+        write_repo_file(&config, &repo);
+
+        config
+    }
+
+    #[tokio::test]
+    async fn test_second_instance_fails_while_locked() {
+        let dir = tempdir().unwrap();
+        let first = PackageManager::new(test_config(dir.path())).await.unwrap();
+
+        let err = PackageManager::new(test_config(dir.path())).await.err().unwrap();
+        assert!(err.to_string().contains("Another hecate-pkg process is running"), "{}", err);
+        assert!(err.to_string().contains(&format!("pid {}", std::process::id())), "{}", err);
+
+        // Readers don't need the lock
+        let reader = PackageManager::new(PackageConfig {
+            lock: LockMode::None,
+            ..test_config(dir.path())
+        }).await.unwrap();
+        assert!(reader.export_world().await.unwrap().is_empty());
+
+        // A waiting instance gets in once the first one is done
+        let waiting = tokio::spawn(PackageManager::new(PackageConfig {
+            lock: LockMode::Wait,
+            ..test_config(dir.path())
+        }));
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert!(!waiting.is_finished());
+        drop(first);
+        waiting.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_offline_install_from_cache() {
+        let dir = tempdir().unwrap();
+        let mut mgr = PackageManager::new(offline_config(dir.path())).await.unwrap();
+
+        let lib = stage_package(&mgr, test_package("libfoo", "1.0.0", &[]));
+        let app = stage_package(&mgr, test_package("foo", "1.0.0", &["libfoo"]));
+        publish(&mgr, "core", vec![lib, app]).await;
+
+        assert!(mgr.sync_repositories().await.is_err());
+        mgr.install("foo").await.unwrap();
+        assert!(mgr.database.is_installed("libfoo").await.unwrap());
+        mgr.update().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_offline_missing_package_errors() {
+        let dir = tempdir().unwrap();
+        let mgr = PackageManager::new(offline_config(dir.path())).await.unwrap();
+
+        let lib = stage_package(&mgr, test_package("libfoo", "1.0.0", &[]));
+        let app = stage_package(&mgr, test_package("foo", "1.0.0", &["libfoo"]));
+        std::fs::remove_file(mgr.cache.get_package_path(&lib)).unwrap();
+        publish(&mgr, "core", vec![lib, app]).await;
+
+        let err = mgr.download_only(&["foo".to_string()]).await.unwrap_err();
+        assert!(err.to_string().contains("libfoo 1.0.0 is not in the cache"));
+
+        let err = mgr.download_only(&["bar".to_string()]).await.unwrap_err();
+        assert!(err.to_string().contains("offline"));
+    }
+
+    #[tokio::test]
+    async fn test_download_only_does_not_install() {
+        let dir = tempdir().unwrap();
+        let mgr = PackageManager::new(test_config(dir.path())).await.unwrap();
+
+        let lib = stage_package(&mgr, test_package("libfoo", "1.0.0", &[]));
+        let app = stage_package(&mgr, test_package("foo", "1.0.0", &["libfoo"]));
+        publish(&mgr, "core", vec![lib.clone(), app.clone()]).await;
+
+        let paths = mgr.download_only(&["foo".to_string()]).await.unwrap();
+        assert_eq!(paths.len(), 2);
+        assert!(paths.contains(&mgr.cache.get_package_path(&lib)));
+        assert!(paths.contains(&mgr.cache.get_package_path(&app)));
+        assert!(!mgr.database.is_installed("foo").await.unwrap());
+        assert!(!dir.path().join("root/usr").exists());
+
+        // Versioned specs pick the matching build
+        let old = stage_package(&mgr, test_package("libfoo", "0.9.0", &[]));
+        publish(&mgr, "core", vec![old.clone(), lib.clone(), app]).await;
+        let paths = mgr.download_only(&["libfoo@0.9".to_string()]).await.unwrap();
+        assert_eq!(paths, vec![mgr.cache.get_package_path(&old)]);
+    }
+
+    #[tokio::test]
+    async fn test_world_export_import_round_trip() {
+        let dir = tempdir().unwrap();
+        let mut mgr = PackageManager::new(test_config(dir.path())).await.unwrap();
+
+        let packages = vec![
+            stage_package(&mgr, test_package("libfoo", "1.0.0", &[])),
+            stage_package(&mgr, test_package("foo", "1.0.0", &["libfoo"])),
+            stage_package(&mgr, test_package("foo", "1.1.0", &["libfoo"])),
+            stage_package(&mgr, test_package("bar", "2.0.0", &[])),
+            stage_package(&mgr, test_package("app", "1.0.0", &["bar"])),
+        ];
+        publish(&mgr, "core", packages.clone()).await;
+
+        mgr.install_version("foo", &VersionReq::parse("=1.0.0").unwrap()).await.unwrap();
+        mgr.install("bar").await.unwrap();
+
+        let world = mgr.export_world().await.unwrap();
+        assert_eq!(world, vec![
+            WorldEntry::new("bar", Version::new(2, 0, 0), false),
+            WorldEntry::new("foo", Version::new(1, 0, 0), false),
+            WorldEntry::new("libfoo", Version::new(1, 0, 0), true),
+        ]);
+        let content = format_world(&world, WorldFormat::Text).unwrap();
+
+        // A fresh machine sharing the same package cache
+        let other = tempdir().unwrap();
+        let mut config = test_config(other.path());
+        config.cache_dir = dir.path().join("cache");
+        let mut fresh = PackageManager::new(config).await.unwrap();
+        publish(&fresh, "core", packages.clone()).await;
+
+        fresh.import_world(&parse_world(&content).unwrap(), true).await.unwrap();
+        assert_eq!(fresh.export_world().await.unwrap(), world);
+        assert!(fresh.database.is_installed("libfoo").await.unwrap());
+
+        // Pinned dependencies are installed at the listed version, and
+        // listed packages already present as dependencies become explicit
+        let newer = stage_package(&mgr, test_package("libfoo", "1.1.0", &[]));
+        let mut packages = packages;
+        packages.push(newer);
+        let third = tempdir().unwrap();
+        let mut config = test_config(third.path());
+        config.cache_dir = dir.path().join("cache");
+        let mut third = PackageManager::new(config).await.unwrap();
+        publish(&third, "core", packages).await;
+        third.install("app").await.unwrap();
+        assert_eq!(third.database.get_installed_package("bar").await.unwrap().install_reason, InstallReason::Dependency);
+
+        third.import_world(&world, true).await.unwrap();
+        assert_eq!(installed_version(&third, "libfoo").await, Version::new(1, 0, 0));
+        assert_eq!(third.database.get_installed_package("bar").await.unwrap().install_reason, InstallReason::Explicit);
+        assert_eq!(third.database.get_installed_package("libfoo").await.unwrap().install_reason, InstallReason::Dependency);
+    }
+
+    #[tokio::test]
+    async fn test_search_ranking() {
+        let dir = tempdir().unwrap();
+        let mgr = PackageManager::new(test_config(dir.path())).await.unwrap();
+
+        let mut vim = test_package("vim", "9.0.0", &[]);
+        vim.keywords = vec!["editor".to_string()];
+        let mut nano = test_package("nano", "7.0.0", &[]);
+        nano.description = "Small text editing tool".to_string();
+        publish(&mgr, "core", vec![
+            test_package("texteditor", "1.0.0", &[]),
+            test_package("editor", "1.0.0", &[]),
+            test_package("edit", "1.0.0", &[]),
+            test_package("edit", "2.0.0", &[]),
+            vim,
+            nano,
+            test_package("unrelated", "1.0.0", &[]),
+        ]).await;
+
+        let results = mgr.search("Edit").await.unwrap();
+        let ranked: Vec<(&str, MatchedField)> = results.iter()
+            .map(|r| (r.package.name.as_str(), r.matched_field))
+            .collect();
+        assert_eq!(ranked, vec![
+            ("edit", MatchedField::ExactName),
+            ("editor", MatchedField::NamePrefix),
+            ("texteditor", MatchedField::NameSubstring),
+            ("vim", MatchedField::Keyword),
+            ("nano", MatchedField::Description),
+        ]);
+        assert_eq!(results[0].package.version, Version::new(2, 0, 0));
+
+        assert!(mgr.search("eidtor").await.unwrap().is_empty());
+        let fuzzy = mgr.search_fuzzy("eidtor").await.unwrap();
+        assert_eq!(fuzzy[0].package.name, "editor");
+        assert_eq!(fuzzy[0].matched_field, MatchedField::FuzzyName);
+    }
+
+    #[tokio::test]
+    async fn test_rdepends_and_why() {
+        let dir = tempdir().unwrap();
+        let mut mgr = PackageManager::new(test_config(dir.path())).await.unwrap();
+
+        let packages = vec![
+            stage_package(&mgr, test_package("base", "1.0.0", &[])),
+            stage_package(&mgr, test_package("lib", "1.0.0", &["base"])),
+            stage_package(&mgr, test_package("app", "1.0.0", &["lib"])),
+            stage_package(&mgr, test_package("tool", "1.0.0", &["base"])),
+        ];
+        publish(&mgr, "core", packages).await;
+
+        mgr.install("app").await.unwrap();
+        mgr.install("tool").await.unwrap();
+
+        assert_eq!(mgr.rdepends("base").await.unwrap(), vec!["lib".to_string(), "tool".to_string()]);
+        assert!(mgr.rdepends("app").await.unwrap().is_empty());
+
+        for target in ["base", "lib", "app"] {
+            let chain = mgr.why(target).await.unwrap().unwrap();
+            assert_eq!(chain.last().map(String::as_str), Some(target));
+
+            // Starts at an explicit package and every step is a real dependency
+            let head = mgr.database.get_install_reason(&chain[0]).await.unwrap();
+            assert!(matches!(head, InstallReason::Explicit));
+            for pair in chain.windows(2) {
+                let installed = mgr.database.get_installed_package(&pair[0]).await.unwrap();
+                assert!(installed.package.dependencies.iter().any(|d| d.name == pair[1]));
+            }
+        }
+        assert_eq!(mgr.why("lib").await.unwrap().unwrap(), vec!["app", "lib"]);
+    }
+
+    #[tokio::test]
+    async fn test_plan_install_includes_transitive_deps() {
+        let dir = tempdir().unwrap();
+        let mgr = PackageManager::new(test_config(dir.path())).await.unwrap();
+
+        let base = stage_package(&mgr, test_package("base", "1.0.0", &[]));
+        let lib = stage_package(&mgr, test_package("lib", "1.0.0", &["base"]));
+        let app = stage_package(&mgr, test_package("app", "1.0.0", &["lib"]));
+        publish(&mgr, "core", vec![base.clone(), lib.clone(), app.clone()]).await;
+
+        // Only lib still has to be downloaded
+        std::fs::remove_file(mgr.cache.get_package_path(&lib)).unwrap();
+
+        let plan = mgr.plan_install(&["app".to_string()]).await.unwrap();
+        let names: Vec<&str> = plan.packages.iter().map(|p| p.package.name.as_str()).collect();
+        assert_eq!(names, vec!["base", "lib", "app"]);
+        assert!(matches!(plan.packages[2].reason, InstallReason::Explicit));
+        assert!(plan.packages[..2].iter().all(|p| matches!(p.reason, InstallReason::Dependency)));
+
+        assert_eq!(plan.download_size(), lib.size_bytes);
+        assert_eq!(
+            plan.installed_size(),
+            base.installed_size_bytes + lib.installed_size_bytes + app.installed_size_bytes
+        );
+        assert!(!mgr.database.is_installed("app").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_plan_remove() {
+        let dir = tempdir().unwrap();
+        let mut mgr = PackageManager::new(test_config(dir.path())).await.unwrap();
+
+        let packages = vec![
+            stage_package(&mgr, test_package("base", "1.0.0", &[])),
+            stage_package(&mgr, test_package("lib", "1.0.0", &["base"])),
+            stage_package(&mgr, test_package("app", "1.0.0", &["lib"])),
+            stage_package(&mgr, test_package("tool", "1.0.0", &["base"])),
+        ];
+        publish(&mgr, "core", packages).await;
+        mgr.install("app").await.unwrap();
+        mgr.install("tool").await.unwrap();
+
+        let plan = mgr.plan_remove(&["app".to_string()], false).await.unwrap();
+        assert_eq!(plan.packages.len(), 1);
+        assert_eq!(plan.installed_size(), "1.0.0".len() as u64);
+        assert!(mgr.plan_remove(&["lib".to_string()], false).await.is_err());
+
+        // base is still needed by tool
+        let plan = mgr.plan_remove(&["app".to_string()], true).await.unwrap();
+        let names: Vec<&str> = plan.packages.iter().map(|p| p.package.name.as_str()).collect();
+        assert_eq!(names, vec!["app", "lib"]);
+        assert!(matches!(plan.packages[1].reason, InstallReason::Dependency));
+
+        let targets = vec!["app".to_string(), "tool".to_string()];
+        let plan = mgr.plan_remove(&targets, true).await.unwrap();
+        let names: Vec<&str> = plan.packages.iter().map(|p| p.package.name.as_str()).collect();
+        assert_eq!(names, vec!["app", "tool", "lib", "base"]);
+        assert_eq!(plan.installed_size(), 4 * "1.0.0".len() as u64);
+    }
+
+    #[tokio::test]
+    async fn test_cascade_removal() {
+        let dir = tempdir().unwrap();
+        let mut mgr = PackageManager::new(test_config(dir.path())).await.unwrap();
+
+        let packages = vec![
+            stage_package(&mgr, test_package("c", "1.0.0", &[])),
+            stage_package(&mgr, test_package("b", "1.0.0", &["c"])),
+            stage_package(&mgr, test_package("a", "1.0.0", &["b"])),
+        ];
+        publish(&mgr, "core", packages).await;
+        mgr.install("a").await.unwrap();
+
+        // a still depends on c
+        assert!(mgr.remove_packages(&["c".to_string()], true).await.is_err());
+        assert!(mgr.database.is_installed("c").await.unwrap());
+
+        let plan = mgr.remove_packages(&["a".to_string()], true).await.unwrap();
+        let names: Vec<&str> = plan.packages.iter().map(|p| p.package.name.as_str()).collect();
+        assert_eq!(names, vec!["a", "b", "c"]);
+
+        for name in ["a", "b", "c"] {
+            assert!(!mgr.database.is_installed(name).await.unwrap());
+            assert!(!dir.path().join("root/usr/share").join(name).join("VERSION").exists());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_repository_add_disable_remove() {
+        let dir = tempdir().unwrap();
+        let config = test_config(dir.path());
+        write_repo_file(&config, &test_repository("core"));
+        let mut mgr = PackageManager::new(config).await.unwrap();
+        let repos_dir = dir.path().join("root/etc/hecate-pkg/repos.d");
+
+        let mut extra = test_repository("extra");
+        extra.url = "https://repo.example.org/extra".to_string();
+        extra.priority = 10;
+        mgr.add_repository(extra.clone()).await.unwrap();
+
+        let saved: Repository = toml::from_str(
+            &std::fs::read_to_string(repos_dir.join("extra.repo")).unwrap()
+        ).unwrap();
+        assert_eq!(saved.url, extra.url);
+        let names: Vec<&str> = mgr.repositories().iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["extra", "core"]);
+
+        assert!(mgr.add_repository(extra.clone()).await.is_err());
+        let mut bad = test_repository("bad");
+        bad.url = "ftp://repo.example.org".to_string();
+        assert!(mgr.add_repository(bad).await.is_err());
+        assert!(!repos_dir.join("bad.repo").exists());
+
+        mgr.set_repository_enabled("extra", false).await.unwrap();
+        let saved: Repository = toml::from_str(
+            &std::fs::read_to_string(repos_dir.join("extra.repo")).unwrap()
+        ).unwrap();
+        assert!(!saved.enabled);
+        assert!(!mgr.repositories().iter().find(|r| r.name == "extra").unwrap().enabled);
+
+        mgr.remove_repository("extra").await.unwrap();
+        assert!(!repos_dir.join("extra.repo").exists());
+        assert_eq!(mgr.repositories().len(), 1);
+        assert!(mgr.remove_repository("extra").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fix_detects_and_repairs_problems() {
+        let dir = tempdir().unwrap();
+        let mut mgr = PackageManager::new(test_config(dir.path())).await.unwrap();
+
+        let lib = stage_package(&mgr, test_package("libfoo", "1.0.0", &[]));
+        let app = stage_package(&mgr, test_package("foo", "1.0.0", &["libfoo"]));
+        publish(&mgr, "core", vec![lib, app]).await;
+        mgr.install("foo").await.unwrap();
+        assert!(mgr.check_problems().await.unwrap().is_empty());
+
+        let root = dir.path().join("root");
+        std::fs::remove_file(root.join("usr/share/foo/VERSION")).unwrap();
+        std::fs::write(root.join("usr/share/libfoo/VERSION"), "tampered").unwrap();
+        let stale = mgr.database.begin_transaction("install", "bar", None, Some("1.0.0")).await.unwrap();
+
+        let problems = mgr.check_problems().await.unwrap();
+        assert_eq!(problems, vec![
+            Problem::MissingFile {
+                package: "foo".to_string(),
+                path: PathBuf::from("usr/share/foo/VERSION"),
+            },
+            Problem::ModifiedFile {
+                package: "libfoo".to_string(),
+                path: PathBuf::from("usr/share/libfoo/VERSION"),
+            },
+            Problem::StaleTransaction {
+                id: stale,
+                kind: "install".to_string(),
+                package: "bar".to_string(),
+            },
+        ]);
+
+        let remaining = mgr.fix_problems(&problems).await.unwrap();
+        assert!(remaining.is_empty());
+        assert_eq!(std::fs::read_to_string(root.join("usr/share/foo/VERSION")).unwrap(), "1.0.0");
+        assert!(mgr.check_problems().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fix_keeps_edited_config_files() {
+        let dir = tempdir().unwrap();
+        let mut mgr = PackageManager::new(test_config(dir.path())).await.unwrap();
+
+        let pkg = stage_package_with(&mgr, test_package("foo", "1.0.0", &[]), &[
+            ("etc/foo.conf", b"default\n"),
+            ("etc/foo.d/extra.conf", b"default\n"),
+        ]);
+        publish(&mgr, "core", vec![pkg]).await;
+        mgr.install("foo").await.unwrap();
+
+        let root = dir.path().join("root");
+        std::fs::write(root.join("etc/foo.conf"), "edited\n").unwrap();
+        std::fs::remove_file(root.join("etc/foo.d/extra.conf")).unwrap();
+
+        let problems = mgr.check_problems().await.unwrap();
+        assert_eq!(problems.len(), 2);
+        let remaining = mgr.fix_problems(&problems).await.unwrap();
+
+        // The edit is kept and still reported; the missing file comes back
+        assert_eq!(std::fs::read_to_string(root.join("etc/foo.conf")).unwrap(), "edited\n");
+        assert_eq!(remaining, vec![Problem::ModifiedFile {
+            package: "foo".to_string(),
+            path: PathBuf::from("etc/foo.conf"),
+        }]);
+        assert_eq!(std::fs::read_to_string(root.join("etc/foo.d/extra.conf")).unwrap(), "default\n");
+    }
+
+    #[tokio::test]
+    async fn test_fix_reports_missing_dependency() {
+        let dir = tempdir().unwrap();
+        let mut mgr = PackageManager::new(test_config(dir.path())).await.unwrap();
+
+        let lib = stage_package(&mgr, test_package("libfoo", "1.0.0", &[]));
+        let app = stage_package(&mgr, test_package("foo", "1.0.0", &["libfoo"]));
+        publish(&mgr, "core", vec![lib, app]).await;
+        mgr.install("foo").await.unwrap();
+
+        // Simulate a database left inconsistent by an interrupted removal
+        mgr.database.mark_removed("libfoo").await.unwrap();
+
+        let problems = mgr.check_problems().await.unwrap();
+        let missing = Problem::MissingDependency {
+            package: "foo".to_string(),
+            dependency: "libfoo".to_string(),
+        };
+        assert_eq!(problems, vec![missing.clone()]);
+        assert_eq!(mgr.fix_problems(&problems).await.unwrap(), vec![missing]);
+    }
+}
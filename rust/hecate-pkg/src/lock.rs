@@ -0,0 +1,101 @@
+//! Process lock for the package database
+//!
+//! Mutating runs hold an exclusive `flock` on a lock file next to the
+//! database, so two of them can't interleave database writes and file
+//! extraction. The lock goes away with the process, even if it crashes.
+
+use anyhow::{Result, Context};
+use nix::errno::Errno;
+use nix::fcntl::{flock, FlockArg};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+
+/// Name of the lock file, kept in the database directory
+const LOCK_FILE: &str = "hecate-pkg.lock";
+
+/// How [`crate::PackageManager::new`] takes the package lock
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LockMode {
+    /// Fail straight away if another process holds the lock
+    #[default]
+    FailFast,
+    /// Block until the other process releases the lock
+    Wait,
+    /// Take no lock, for read-only use such as search, info and list
+    None,
+}
+
+/// Exclusive lock on a package database, released on drop
+#[derive(Debug)]
+pub struct PackageLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl PackageLock {
+    /// Lock file guarding the database at `db_path`
+    pub fn path_for(db_path: &Path) -> PathBuf {
+        db_path.parent().unwrap_or(Path::new(".")).join(LOCK_FILE)
+    }
+
+    /// Take the lock for the database at `db_path`, blocking if `wait` is set
+    pub fn acquire(db_path: &Path, wait: bool) -> Result<Self> {
+        let path = Self::path_for(db_path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .context("Failed to create database directory")?;
+        }
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("Failed to open lock file {}", path.display()))?;
+
+        let arg = if wait { FlockArg::LockExclusive } else { FlockArg::LockExclusiveNonblock };
+        loop {
+            match flock(file.as_raw_fd(), arg) {
+                Ok(()) => break,
+                Err(Errno::EINTR) => continue,
+                Err(Errno::EWOULDBLOCK) => {
+                    let mut holder = String::new();
+                    file.read_to_string(&mut holder).ok();
+                    let holder = match holder.trim() {
+                        "" => String::new(),
+                        pid => format!(" (pid {})", pid),
+                    };
+                    return Err(anyhow::anyhow!(
+                        "Another hecate-pkg process is running{}; wait for it to finish or use --wait",
+                        holder
+                    ));
+                }
+                Err(e) => {
+                    return Err(anyhow::anyhow!("Failed to lock {}: {}", path.display(), e));
+                }
+            }
+        }
+
+        // Record the holder for the error message of whoever comes next
+        file.set_len(0)?;
+        file.rewind()?;
+        write!(file, "{}", std::process::id())?;
+
+        Ok(Self { file, path })
+    }
+
+    /// Path of the lock file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for PackageLock {
+    fn drop(&mut self) {
+        // Closing the file releases the lock; clear the pid before that
+        self.file.set_len(0).ok();
+    }
+}
@@ -3,12 +3,17 @@
 //! Command-line interface for package management
 
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use colored::*;
 use dialoguer::{Confirm, MultiSelect, Select};
-use indicatif::{ProgressBar, ProgressStyle, MultiProgress};
-use hecate_pkg::{PackageManager, PackageConfig, Package};
-use std::path::PathBuf;
+use indicatif::{HumanBytes, ProgressBar, ProgressStyle, MultiProgress};
+use hecate_pkg::{
+    PackageManager, PackageConfig, InstallReason, Repository, TransactionPlan,
+    WorldFormat, format_stats, format_world, parse_package_spec, parse_world,
+};
+use hecate_pkg::lock::LockMode;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
 use tracing::{error, info, warn};
 
 // ============================================================================
@@ -35,6 +40,10 @@ struct Cli {
     #[arg(long, global = true)]
     no_color: bool,
     
+    /// Output format of search, list, info and stats
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+    
     /// Verbose output
     #[arg(short, long, global = true)]
     verbose: bool,
@@ -42,13 +51,39 @@ struct Cli {
     /// Assume yes to all prompts
     #[arg(short, long, global = true)]
     yes: bool,
+    
+    /// Don't run package install/remove hooks
+    #[arg(long, global = true)]
+    no_hooks: bool,
+    
+    /// Use only cached repository indices and packages
+    #[arg(long, global = true)]
+    offline: bool,
+    
+    /// Let installed packages take over paths matching this glob from other packages
+    #[arg(long, global = true, value_name = "GLOB")]
+    overwrite: Vec<String>,
+    
+    /// Install packages built for another architecture (chroots, cross installs)
+    #[arg(long, global = true)]
+    force_arch: bool,
+    
+    /// Wait for another running hecate-pkg instead of failing
+    #[arg(long, global = true)]
+    wait: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Install packages
     Install {
-        /// Packages to install
+        /// Packages to install (`name` or `name@version`)
         packages: Vec<String>,
         
         /// Don't install dependencies
@@ -58,6 +93,14 @@ enum Commands {
         /// Reinstall if already installed
         #[arg(long)]
         reinstall: bool,
+        
+        /// Download and verify packages into the cache without installing
+        #[arg(long)]
+        downloadonly: bool,
+        
+        /// Print the install plan and exit without changing anything
+        #[arg(long)]
+        dry_run: bool,
     },
     
     /// Remove packages
@@ -72,6 +115,10 @@ enum Commands {
         /// Don't remove config files
         #[arg(long)]
         no_save: bool,
+        
+        /// Print the removal plan and exit without changing anything
+        #[arg(long)]
+        dry_run: bool,
     },
     
     /// Update packages
@@ -84,6 +131,34 @@ enum Commands {
         no_deps: bool,
     },
     
+    /// Hold packages at their installed version
+    Hold {
+        /// Packages to hold (lists held packages if empty)
+        packages: Vec<String>,
+    },
+    
+    /// Release held packages
+    Unhold {
+        /// Packages to release
+        packages: Vec<String>,
+    },
+    
+    /// Mark installed packages as explicitly installed or as dependencies
+    #[command(group(clap::ArgGroup::new("reason").required(true).args(["explicit", "dependency"])))]
+    Mark {
+        /// Packages to mark
+        #[arg(required = true)]
+        packages: Vec<String>,
+        
+        /// Keep the packages even when nothing depends on them
+        #[arg(long)]
+        explicit: bool,
+        
+        /// Let orphan removal take the packages once nothing depends on them
+        #[arg(long)]
+        dependency: bool,
+    },
+    
     /// Search for packages
     Search {
         /// Search query
@@ -96,6 +171,10 @@ enum Commands {
         /// Show all versions
         #[arg(short, long)]
         all: bool,
+        
+        /// Tolerate typos in package names
+        #[arg(long)]
+        fuzzy: bool,
     },
     
     /// Show package information
@@ -147,6 +226,10 @@ enum Commands {
         /// Keep last N versions
         #[arg(short, long, default_value = "2")]
         keep: usize,
+        
+        /// Only remove packages not used in this many days
+        #[arg(long)]
+        older_than: Option<u64>,
     },
     
     /// Verify installed packages
@@ -180,6 +263,41 @@ enum Commands {
     
     /// Show package statistics
     Stats,
+    
+    /// List installed packages that depend on a package
+    Rdepends {
+        /// Package name
+        package: String,
+    },
+    
+    /// Explain why a package is installed
+    Why {
+        /// Package name
+        package: String,
+    },
+    
+    /// Show which installed package owns a file
+    Owns {
+        /// File path
+        path: PathBuf,
+    },
+    
+    /// Print the explicitly installed packages and their versions
+    Export {
+        /// Emit JSON instead of `name@version` lines
+        #[arg(long)]
+        json: bool,
+    },
+    
+    /// Install the package set from an exported file
+    Import {
+        /// File written by `hecate-pkg export`
+        file: PathBuf,
+        
+        /// Install the exact versions listed
+        #[arg(long)]
+        pin: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -195,6 +313,10 @@ enum GroupAction {
         /// Select specific packages
         #[arg(short, long)]
         select: bool,
+        
+        /// Print the install plan and exit without changing anything
+        #[arg(long)]
+        dry_run: bool,
     },
     
     /// Show group members
@@ -249,19 +371,23 @@ enum RepoAction {
 async fn main() -> Result<()> {
     let cli = Cli::parse();
     
-    // Initialize logging
-    if cli.verbose {
+    let json = cli.format == OutputFormat::Json;
+    
+    // Initialize logging; JSON output keeps stdout for the document
+    let filter = if cli.verbose { "debug" } else { "info" };
+    if json {
         tracing_subscriber::fmt()
-            .with_env_filter("debug")
+            .with_env_filter(filter)
+            .with_writer(std::io::stderr)
             .init();
     } else {
         tracing_subscriber::fmt()
-            .with_env_filter("info")
+            .with_env_filter(filter)
             .init();
     }
     
     // Set color output
-    if cli.no_color {
+    if cli.no_color || json {
         colored::control::set_override(false);
     }
     
@@ -276,36 +402,69 @@ async fn main() -> Result<()> {
         config.root_dir = root;
     }
     
-    config.color_output = !cli.no_color;
+    config.color_output = !cli.no_color && !json;
+    
+    if cli.no_hooks {
+        config.run_hooks = false;
+    }
+    
+    if cli.offline {
+        config.offline = true;
+    }
+    
+    config.overwrite.extend(cli.overwrite);
+    config.force_arch = cli.force_arch;
+    
+    config.lock = if cli.command.is_read_only() {
+        LockMode::None
+    } else if cli.wait {
+        LockMode::Wait
+    } else {
+        LockMode::FailFast
+    };
     
     // Create package manager
     let mut pkg_mgr = PackageManager::new(config).await?;
     
     // Execute command
     match cli.command {
-        Commands::Install { packages, no_deps, reinstall } => {
-            handle_install(&mut pkg_mgr, packages, no_deps, reinstall, cli.yes).await?;
+        Commands::Install { packages, no_deps, reinstall, downloadonly, dry_run } => {
+            if downloadonly && !dry_run {
+                handle_download(&pkg_mgr, packages).await?;
+            } else {
+                handle_install(&mut pkg_mgr, packages, no_deps, reinstall, cli.yes, dry_run).await?;
+            }
         }
-        Commands::Remove { packages, cascade, no_save } => {
-            handle_remove(&mut pkg_mgr, packages, cascade, no_save, cli.yes).await?;
+        Commands::Remove { packages, cascade, no_save, dry_run } => {
+            handle_remove(&mut pkg_mgr, packages, cascade, no_save, cli.yes, dry_run).await?;
         }
         Commands::Update { packages, no_deps } => {
             handle_update(&mut pkg_mgr, packages, no_deps, cli.yes).await?;
         }
-        Commands::Search { query, description, all } => {
-            handle_search(&pkg_mgr, &query, description, all).await?;
+        Commands::Hold { packages } => {
+            handle_hold(&pkg_mgr, packages, true).await?;
+        }
+        Commands::Unhold { packages } => {
+            handle_hold(&pkg_mgr, packages, false).await?;
+        }
+        Commands::Mark { packages, explicit, .. } => {
+            let reason = if explicit { InstallReason::Explicit } else { InstallReason::Dependency };
+            handle_mark(&pkg_mgr, packages, reason).await?;
+        }
+        Commands::Search { query, description, all, fuzzy } => {
+            handle_search(&pkg_mgr, &query, description, all, fuzzy, cli.format).await?;
         }
         Commands::Info { package, files, deps } => {
-            handle_info(&pkg_mgr, &package, files, deps).await?;
+            handle_info(&pkg_mgr, &package, files, deps, cli.format).await?;
         }
         Commands::List { explicit, deps, orphans, group } => {
-            handle_list(&pkg_mgr, explicit, deps, orphans, group).await?;
+            handle_list(&pkg_mgr, explicit, deps, orphans, group, cli.format).await?;
         }
         Commands::Sync { force } => {
             handle_sync(&mut pkg_mgr, force).await?;
         }
-        Commands::Clean { all, keep } => {
-            handle_clean(&mut pkg_mgr, all, keep, cli.yes).await?;
+        Commands::Clean { all, keep, older_than } => {
+            handle_clean(&mut pkg_mgr, all, keep, older_than, cli.yes).await?;
         }
         Commands::Verify { packages, checksums } => {
             handle_verify(&pkg_mgr, packages, checksums).await?;
@@ -320,13 +479,38 @@ async fn main() -> Result<()> {
             handle_fix(&mut pkg_mgr, check_only, cli.yes).await?;
         }
         Commands::Stats => {
-            handle_stats(&pkg_mgr).await?;
+            handle_stats(&pkg_mgr, cli.format).await?;
+        }
+        Commands::Rdepends { package } => {
+            handle_rdepends(&pkg_mgr, &package).await?;
+        }
+        Commands::Why { package } => {
+            handle_why(&pkg_mgr, &package).await?;
+        }
+        Commands::Owns { path } => {
+            handle_owns(&pkg_mgr, &path).await?;
+        }
+        Commands::Export { json } => {
+            handle_export(&pkg_mgr, json).await?;
+        }
+        Commands::Import { file, pin } => {
+            handle_import(&mut pkg_mgr, &file, pin, cli.yes).await?;
         }
     }
     
     Ok(())
 }
 
+impl Commands {
+    /// Commands that only read the database and so run without the package lock
+    fn is_read_only(&self) -> bool {
+        matches!(self,
+            Commands::Search { .. } | Commands::Info { .. } | Commands::List { .. } |
+            Commands::Verify { .. } | Commands::Stats | Commands::Rdepends { .. } |
+            Commands::Why { .. } | Commands::Owns { .. } | Commands::Export { .. })
+    }
+}
+
 // ============================================================================
 // COMMAND HANDLERS
 // ============================================================================
@@ -337,6 +521,7 @@ async fn handle_install(
     no_deps: bool,
     reinstall: bool,
     auto_yes: bool,
+    dry_run: bool,
 ) -> Result<()> {
     if packages.is_empty() {
         eprintln!("{}", "No packages specified".red());
@@ -345,8 +530,7 @@ async fn handle_install(
     
     println!("{}", "Resolving dependencies...".bright_cyan());
     
-    // TODO: Get install plan from package manager
-    let install_plan: Vec<Package> = vec![]; // Placeholder
+    let install_plan = mgr.plan_install(&packages).await?;
     
     if install_plan.is_empty() {
         println!("{}", "All requested packages are already installed".green());
@@ -354,14 +538,15 @@ async fn handle_install(
     }
     
     // Show install plan
-    println!("\n{}", "Packages to be installed:".bright_yellow());
-    for pkg in &install_plan {
-        // println!("  {} {}", pkg.name.bright_white(), pkg.version.to_string().bright_black());
-    }
+    print_plan("Packages to be installed:", &install_plan);
+    
+    println!("\n{}", format!("Total download size: {}", HumanBytes(install_plan.download_size())).bright_black());
+    println!("{}", format!("Total installed size: {}", HumanBytes(install_plan.installed_size())).bright_black());
     
-    // TODO: Show size information
-    println!("\n{}", "Total download size: 123.4 MB".bright_black());
-    println!("{}", "Total installed size: 456.7 MB".bright_black());
+    if dry_run {
+        println!("\n{}", "Dry run: nothing was installed".yellow());
+        return Ok(());
+    }
     
     // Confirm
     if !auto_yes {
@@ -376,10 +561,15 @@ async fn handle_install(
         }
     }
     
-    // Install packages
+    // Install requested packages in plan order; dependencies come along
     let mp = MultiProgress::new();
+    let requested = install_plan.packages.iter()
+        .filter(|p| matches!(p.reason, InstallReason::Explicit))
+        .filter_map(|p| packages.iter().find(|spec| {
+            parse_package_spec(spec).map(|(name, _)| name == p.package.name).unwrap_or(false)
+        }));
     
-    for package_name in packages {
+    for package_name in requested {
         let pb = mp.add(ProgressBar::new(100));
         pb.set_style(
             ProgressStyle::default_bar()
@@ -388,7 +578,12 @@ async fn handle_install(
         );
         pb.set_message(format!("Installing {}", package_name));
         
-        match mgr.install(&package_name).await {
+        let result = match parse_package_spec(package_name)? {
+            (name, Some(version_req)) => mgr.install_version(&name, &version_req).await,
+            (name, None) => mgr.install(&name).await,
+        };
+        
+        match result {
             Ok(_) => {
                 pb.finish_with_message(format!("✓ {} installed", package_name.green()));
             }
@@ -412,25 +607,45 @@ async fn handle_install(
     Ok(())
 }
 
+async fn handle_download(mgr: &PackageManager, packages: Vec<String>) -> Result<()> {
+    if packages.is_empty() {
+        eprintln!("{}", "No packages specified".red());
+        return Ok(());
+    }
+    
+    println!("{}", "Downloading packages...".bright_cyan());
+    
+    let paths = mgr.download_only(&packages).await?;
+    for path in &paths {
+        println!("  {}", path.display().to_string().bright_white());
+    }
+    
+    println!("\n{} {} packages cached", "Download complete!".green().bold(), paths.len());
+    Ok(())
+}
+
 async fn handle_remove(
     mgr: &mut PackageManager,
     packages: Vec<String>,
     cascade: bool,
     no_save: bool,
     auto_yes: bool,
+    dry_run: bool,
 ) -> Result<()> {
     if packages.is_empty() {
         eprintln!("{}", "No packages specified".red());
         return Ok(());
     }
     
-    // TODO: Check what will be removed
-    let remove_plan: Vec<String> = vec![]; // Placeholder
+    let remove_plan = mgr.plan_remove(&packages, cascade).await?;
     
     // Show removal plan
-    println!("\n{}", "Packages to be removed:".bright_yellow());
-    for pkg in &remove_plan {
-        // println!("  {}", pkg.name.bright_white());
+    print_plan("Packages to be removed:", &remove_plan);
+    println!("\n{}", format!("Total freed size: {}", HumanBytes(remove_plan.installed_size())).bright_black());
+    
+    if dry_run {
+        println!("\n{}", "Dry run: nothing was removed".yellow());
+        return Ok(());
     }
     
     // Confirm
@@ -446,13 +661,11 @@ async fn handle_remove(
         }
     }
     
-    // Remove packages
-    for package_name in packages {
-        print!("Removing {}... ", package_name);
-        match mgr.remove(&package_name).await {
-            Ok(_) => println!("{}", "done".green()),
-            Err(e) => println!("{}: {}", "failed".red(), e),
-        }
+    // Remove packages, dependents first
+    println!("Removing {} packages...", remove_plan.packages.len());
+    let removed = mgr.remove_packages(&packages, cascade).await?;
+    for planned in &removed.packages {
+        println!("  {} {}", "✓".green(), planned.package.name);
     }
     
     println!("\n{}", "Removal complete!".green().bold());
@@ -482,15 +695,80 @@ async fn handle_update(
     Ok(())
 }
 
+async fn handle_hold(
+    mgr: &PackageManager,
+    packages: Vec<String>,
+    hold: bool,
+) -> Result<()> {
+    if packages.is_empty() {
+        let held = mgr.held_packages().await?;
+        if held.is_empty() {
+            println!("{}", "No packages are held".yellow());
+        } else {
+            println!("{}", "Held packages:".bright_cyan());
+            for name in held {
+                println!("  {}", name.bright_white());
+            }
+        }
+        return Ok(());
+    }
+    
+    for package_name in packages {
+        let result = if hold {
+            mgr.hold(&package_name).await
+        } else {
+            mgr.unhold(&package_name).await
+        };
+        
+        match result {
+            Ok(_) if hold => println!("{} {}", "Held".green(), package_name.bright_white()),
+            Ok(_) => println!("{} {}", "Released".green(), package_name.bright_white()),
+            Err(e) => println!("{} {}: {}", "Failed".red(), package_name, e),
+        }
+    }
+    
+    Ok(())
+}
+
+async fn handle_mark(
+    mgr: &PackageManager,
+    packages: Vec<String>,
+    reason: InstallReason,
+) -> Result<()> {
+    let label = match reason {
+        InstallReason::Dependency => "dependency",
+        _ => "explicitly installed",
+    };
+    
+    for package_name in packages {
+        match mgr.set_install_reason(&package_name, reason.clone()).await {
+            Ok(_) => println!("{} {} as {}", "Marked".green(), package_name.bright_white(), label),
+            Err(e) => println!("{} {}: {}", "Failed".red(), package_name, e),
+        }
+    }
+    
+    Ok(())
+}
+
 async fn handle_search(
     mgr: &PackageManager,
     query: &str,
     search_desc: bool,
     show_all: bool,
+    fuzzy: bool,
+    format: OutputFormat,
 ) -> Result<()> {
-    println!("Searching for '{}'...\n", query.bright_cyan());
+    let results = if fuzzy {
+        mgr.search_fuzzy(query).await?
+    } else {
+        mgr.search(query).await?
+    };
+    
+    if format == OutputFormat::Json {
+        return print_json(&results);
+    }
     
-    let results = mgr.search(query).await?;
+    println!("Searching for '{}'...\n", query.bright_cyan());
     
     if results.is_empty() {
         println!("{}", "No packages found".yellow());
@@ -499,7 +777,8 @@ async fn handle_search(
     
     println!("Found {} packages:\n", results.len());
     
-    for pkg in results {
+    for result in results {
+        let pkg = result.package;
         println!("{} {}", 
             pkg.name.bright_white().bold(),
             pkg.version.to_string().bright_black()
@@ -523,25 +802,44 @@ async fn handle_info(
     package: &str,
     show_files: bool,
     show_deps: bool,
+    format: OutputFormat,
 ) -> Result<()> {
-    println!("Package: {}\n", package.bright_white().bold());
+    let mut info = mgr.package_info(package).await?;
+    if !show_files {
+        info.files.clear();
+    }
+    
+    if format == OutputFormat::Json {
+        return print_json(&info);
+    }
     
-    // TODO: Get package info from manager
-    println!("Version: 1.0.0");
-    println!("Description: Package description");
-    println!("License: MIT");
-    println!("Installed Size: 123.4 MB");
+    let pkg = &info.package;
+    println!("Package: {}\n", pkg.name.bright_white().bold());
+    println!("Version: {}", pkg.version);
+    println!("Description: {}", pkg.description);
+    println!("License: {}", pkg.license);
+    println!("Installed Size: {}", HumanBytes(pkg.installed_size_bytes));
+    match (&info.install_reason, &info.repository) {
+        (Some(reason), _) => {
+            let held = if info.held { " (held)" } else { "" };
+            println!("Installed: {:?}{}", reason, held);
+        }
+        (None, Some(repository)) => println!("Repository: {}", repository),
+        (None, None) => {}
+    }
     
     if show_deps {
         println!("\n{}", "Dependencies:".bright_yellow());
-        println!("  dependency-1 >= 1.0");
-        println!("  dependency-2");
+        for dep in &pkg.dependencies {
+            println!("  {} {}", dep.name, dep.version_req);
+        }
     }
     
     if show_files {
         println!("\n{}", "Installed Files:".bright_yellow());
-        println!("  /usr/bin/program");
-        println!("  /usr/share/doc/package/README");
+        for path in &info.files {
+            println!("  {}", path.display());
+        }
     }
     
     Ok(())
@@ -553,11 +851,29 @@ async fn handle_list(
     deps: bool,
     orphans: bool,
     group: Option<String>,
+    format: OutputFormat,
 ) -> Result<()> {
-    println!("{}", "Installed packages:".bright_cyan());
+    let mut packages = mgr.list_installed().await?;
+    if explicit {
+        packages.retain(|pkg| pkg.install_reason == InstallReason::Explicit);
+    }
+    if deps {
+        packages.retain(|pkg| pkg.install_reason == InstallReason::Dependency);
+    }
+    if orphans {
+        let orphans = mgr.orphans().await?;
+        packages.retain(|pkg| orphans.contains(&pkg.name));
+    }
+    if let Some(group) = group {
+        let members = mgr.group_members(&group).await?;
+        packages.retain(|pkg| members.contains(&pkg.name));
+    }
     
-    // TODO: Get package list from manager
-    let packages: Vec<Package> = vec![];
+    if format == OutputFormat::Json {
+        return print_json(&packages);
+    }
+    
+    println!("{}", "Installed packages:".bright_cyan());
     
     if packages.is_empty() {
         println!("{}", "No packages installed".yellow());
@@ -565,7 +881,8 @@ async fn handle_list(
     }
     
     for pkg in &packages {
-        // println!("  {} {}", pkg.name, pkg.version);
+        let held = if pkg.held { " [held]".yellow().to_string() } else { String::new() };
+        println!("  {} {}{}", pkg.name.bright_white(), pkg.version.to_string().bright_black(), held);
     }
     
     println!("\n{} packages installed", packages.len());
@@ -595,14 +912,17 @@ async fn handle_clean(
     mgr: &mut PackageManager,
     all: bool,
     keep: usize,
+    older_than: Option<u64>,
     auto_yes: bool,
 ) -> Result<()> {
     println!("{}", "Cleaning package cache...".bright_cyan());
     
-    // TODO: Calculate space to be freed
-    let space_freed = "123.4 MB";
-    
-    println!("This will free approximately {}", space_freed.bright_yellow());
+    let stats = mgr.cache_stats().await?;
+    println!("Cache holds {} packages ({}) in {}",
+        stats.package_count,
+        HumanBytes(stats.total_size).to_string().bright_yellow(),
+        stats.cache_dir.display()
+    );
     
     if !auto_yes {
         let confirm = Confirm::new()
@@ -616,8 +936,12 @@ async fn handle_clean(
         }
     }
     
-    // TODO: Implement cache cleaning
+    let freed = match older_than {
+        Some(days) if !all => mgr.clean_cache_older_than(days).await?,
+        _ => mgr.clean_cache(if all { 0 } else { keep }).await?,
+    };
     
+    println!("Freed {}", HumanBytes(freed).to_string().bright_white());
     println!("{}", "Cache cleaned successfully!".green().bold());
     Ok(())
 }
@@ -655,39 +979,76 @@ async fn handle_group(
 ) -> Result<()> {
     match action {
         GroupAction::List => {
+            let groups = mgr.groups().await?;
+            if groups.is_empty() {
+                println!("{}", "No groups available".yellow());
+                return Ok(());
+            }
+            
             println!("{}", "Available groups:".bright_cyan());
-            // TODO: Get groups from manager
-            println!("  base");
-            println!("  development");
-            println!("  multimedia");
+            for (name, description) in groups {
+                if description.is_empty() {
+                    println!("  {}", name.bright_white());
+                } else {
+                    println!("  {} - {}", name.bright_white(), description);
+                }
+            }
         }
-        GroupAction::Install { group, select } => {
-            println!("Installing group '{}'...", group.bright_cyan());
-            
-            if select {
-                // TODO: Get group members
-                let members = vec!["package1", "package2", "package3"];
-                
+        GroupAction::Install { group, select, dry_run } => {
+            let selected = if select {
+                let members = mgr.group_members(&group).await?;
                 let selections = MultiSelect::new()
                     .with_prompt("Select packages to install")
                     .items(&members)
+                    .defaults(&vec![true; members.len()])
                     .interact()?;
                 
-                for idx in selections {
-                    println!("Installing {}...", members[idx]);
-                    // TODO: Install selected packages
+                if selections.is_empty() {
+                    println!("{}", "No packages selected".yellow());
+                    return Ok(());
                 }
+                Some(selections.into_iter().map(|idx| members[idx].clone()).collect::<Vec<_>>())
             } else {
-                // Install all group members
-                // TODO: Implement group installation
+                None
+            };
+            
+            println!("{}", "Resolving dependencies...".bright_cyan());
+            let plan = mgr.plan_group_install(&group, selected.as_deref()).await?;
+            if plan.is_empty() {
+                println!("{}", format!("Group '{}' is already installed", group).green());
+                return Ok(());
+            }
+            
+            print_plan("Packages to be installed:", &plan);
+            println!("\n{}", format!("Total download size: {}", HumanBytes(plan.download_size())).bright_black());
+            println!("{}", format!("Total installed size: {}", HumanBytes(plan.installed_size())).bright_black());
+            
+            if dry_run {
+                println!("\n{}", "Dry run: nothing was installed".yellow());
+                return Ok(());
+            }
+            
+            if !auto_yes {
+                let confirm = Confirm::new()
+                    .with_prompt("Proceed with installation?")
+                    .default(true)
+                    .interact()?;
+                
+                if !confirm {
+                    println!("{}", "Installation cancelled".yellow());
+                    return Ok(());
+                }
             }
+            
+            println!("Installing group '{}'...", group.bright_cyan());
+            mgr.install_group(&group, selected.as_deref()).await?;
+            println!("{}", format!("Group '{}' installed successfully!", group).green());
         }
         GroupAction::Show { group } => {
             println!("Group '{}' contains:", group.bright_cyan());
-            // TODO: Get group members
-            println!("  package1");
-            println!("  package2");
-            println!("  package3");
+            for member in mgr.group_members(&group).await? {
+                println!("  {}", member);
+            }
         }
     }
     
@@ -698,29 +1059,39 @@ async fn handle_repo(mgr: &mut PackageManager, action: RepoAction) -> Result<()>
     match action {
         RepoAction::List => {
             println!("{}", "Configured repositories:".bright_cyan());
-            // TODO: Get repositories from manager
-            println!("  [1] core      - https://repo.hecateos.org/core");
-            println!("  [2] extra     - https://repo.hecateos.org/extra");
-            println!("  [3] community - https://repo.hecateos.org/community");
+            for repo in mgr.repositories() {
+                let status = if repo.enabled { "" } else { " (disabled)" };
+                println!("  [{}] {:<12} - {}{}",
+                    repo.priority, repo.name, repo.url, status.bright_black());
+            }
         }
         RepoAction::Add { name, url, priority } => {
             println!("Adding repository '{}'...", name.bright_cyan());
-            // TODO: Add repository
+            mgr.add_repository(Repository {
+                name,
+                url,
+                mirror_urls: Vec::new(),
+                enabled: true,
+                priority,
+                gpg_check: true,
+                gpg_key: None,
+                last_update: None,
+            }).await?;
             println!("{}", "Repository added successfully!".green());
         }
         RepoAction::Remove { name } => {
             println!("Removing repository '{}'...", name.bright_cyan());
-            // TODO: Remove repository
+            mgr.remove_repository(&name).await?;
             println!("{}", "Repository removed successfully!".green());
         }
         RepoAction::Enable { name } => {
             println!("Enabling repository '{}'...", name.bright_cyan());
-            // TODO: Enable repository
+            mgr.set_repository_enabled(&name, true).await?;
             println!("{}", "Repository enabled successfully!".green());
         }
         RepoAction::Disable { name } => {
             println!("Disabling repository '{}'...", name.bright_cyan());
-            // TODO: Disable repository
+            mgr.set_repository_enabled(&name, false).await?;
             println!("{}", "Repository disabled successfully!".green());
         }
     }
@@ -735,8 +1106,7 @@ async fn handle_fix(
 ) -> Result<()> {
     println!("{}", "Checking for broken packages...".bright_cyan());
     
-    // TODO: Check for issues
-    let issues: Vec<String> = vec![];
+    let issues = mgr.check_problems().await?;
     
     if issues.is_empty() {
         println!("{}", "No issues found!".green());
@@ -745,7 +1115,7 @@ async fn handle_fix(
     
     println!("\n{}", "Issues found:".bright_yellow());
     for issue in &issues {
-        // println!("  • {}", issue);
+        println!("  • {}", issue);
     }
     
     if check_only {
@@ -764,28 +1134,113 @@ async fn handle_fix(
     }
     
     println!("\n{}", "Fixing issues...".bright_cyan());
-    // TODO: Fix issues
+    let remaining = mgr.fix_problems(&issues).await?;
+    
+    if !remaining.is_empty() {
+        println!("\n{}", "Could not fix:".bright_yellow());
+        for issue in &remaining {
+            println!("  • {}", issue);
+        }
+        return Err(anyhow::anyhow!("{} issues remain", remaining.len()));
+    }
     
     println!("{}", "Issues fixed successfully!".green().bold());
     Ok(())
 }
 
-async fn handle_stats(mgr: &PackageManager) -> Result<()> {
-    println!("{}", "=== Package Statistics ===".bright_cyan().bold());
+async fn handle_stats(mgr: &PackageManager, format: OutputFormat) -> Result<()> {
+    let stats = mgr.stats().await?;
+    
+    if format == OutputFormat::Json {
+        return print_json(&stats);
+    }
+    
+    println!("{}\n", "=== Package Statistics ===".bright_cyan().bold());
+    print!("{}", format_stats(&stats));
+    
+    Ok(())
+}
+
+async fn handle_rdepends(mgr: &PackageManager, package: &str) -> Result<()> {
+    let dependents = mgr.rdepends(package).await?;
+    
+    if dependents.is_empty() {
+        println!("Nothing depends on {}", package.bright_white());
+        return Ok(());
+    }
+    
+    println!("{} is required by:", package.bright_white().bold());
+    for name in dependents {
+        println!("  {}", name);
+    }
+    
+    Ok(())
+}
+
+async fn handle_why(mgr: &PackageManager, package: &str) -> Result<()> {
+    match mgr.why(package).await? {
+        Some(chain) if chain.len() == 1 => {
+            println!("{} was installed explicitly", package.bright_white().bold());
+        }
+        Some(chain) => {
+            println!("{}", chain.join(" -> ").bright_white());
+        }
+        None => {
+            println!("{} is not needed by any explicitly installed package", package.yellow());
+        }
+    }
+    
+    Ok(())
+}
+
+async fn handle_owns(mgr: &PackageManager, path: &Path) -> Result<()> {
+    let owners = mgr.owner_of(path).await?;
+    if owners.is_empty() {
+        return Err(anyhow::anyhow!("No installed package owns {}", path.display()));
+    }
+    
+    println!("{} is owned by {}", path.display(), owners.join(", ").bright_white().bold());
+    Ok(())
+}
+
+async fn handle_export(mgr: &PackageManager, json: bool) -> Result<()> {
+    let world = mgr.export_world().await?;
+    let format = if json { WorldFormat::Json } else { WorldFormat::Text };
+    
+    print!("{}", format_world(&world, format)?);
+    Ok(())
+}
+
+async fn handle_import(
+    mgr: &mut PackageManager,
+    file: &PathBuf,
+    pin: bool,
+    auto_yes: bool,
+) -> Result<()> {
+    let content = std::fs::read_to_string(file)?;
+    let world = parse_world(&content)?;
     
-    // TODO: Get statistics from manager
-    println!("\nTotal packages installed: {}", "156".bright_white());
-    println!("Explicitly installed: {}", "42".bright_white());
-    println!("Dependencies: {}", "114".bright_white());
-    println!("Orphaned packages: {}", "3".yellow());
+    println!("{}", "Packages to be imported:".bright_yellow());
+    for entry in &world {
+        let note = if entry.dependency { " (dependency)" } else { "" };
+        println!("  {} {}{}", entry.name.bright_white(), entry.version.to_string().bright_black(), note.bright_black());
+    }
     
-    println!("\nTotal disk usage: {}", "2.3 GB".bright_white());
-    println!("Cache size: {}", "456 MB".bright_white());
+    if !auto_yes {
+        let confirm = Confirm::new()
+            .with_prompt("Proceed with import?")
+            .default(true)
+            .interact()?;
+        
+        if !confirm {
+            println!("{}", "Import cancelled".yellow());
+            return Ok(());
+        }
+    }
     
-    println!("\nRepositories: {}", "3".bright_white());
-    println!("Available packages: {}", "12,345".bright_white());
-    println!("Available updates: {}", "7".green());
+    mgr.import_world(&world, pin).await?;
     
+    println!("\n{}", "Import complete!".green().bold());
     Ok(())
 }
 
@@ -793,6 +1248,30 @@ async fn handle_stats(mgr: &PackageManager) -> Result<()> {
 // HELPER FUNCTIONS
 // ============================================================================
 
+/// Print the packages of a plan with their sizes and why they are included
+fn print_plan(heading: &str, plan: &TransactionPlan) {
+    println!("\n{}", heading.bright_yellow());
+    for planned in &plan.packages {
+        let reason = match planned.reason {
+            InstallReason::Explicit => "",
+            InstallReason::Dependency => " (dependency)",
+            InstallReason::Group => " (group)",
+        };
+        println!("  {} {} {}{}",
+            planned.package.name.bright_white(),
+            planned.package.version.to_string().bright_black(),
+            HumanBytes(planned.installed_size),
+            reason.bright_black(),
+        );
+    }
+}
+
+/// Print a value as pretty JSON for `--format json`
+fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
 fn load_config(path: &PathBuf) -> Result<PackageConfig> {
     let content = std::fs::read_to_string(path)?;
     let config: PackageConfig = toml::from_str(&content)?;
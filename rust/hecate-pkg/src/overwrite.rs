@@ -0,0 +1,109 @@
+//! File collisions between packages
+//!
+//! Installing a package that ships a path another installed package owns
+//! fails, unless the path matches an `--overwrite` glob. The new package then
+//! takes the path over; the previous owner's copy is kept aside so removing
+//! the new owner can hand it back.
+
+use anyhow::{Result, Context};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::path::{Path, PathBuf};
+
+use crate::InstalledFile;
+
+/// Paths packages may take over from each other
+#[derive(Debug, Clone)]
+pub struct OverwritePolicy {
+    globs: GlobSet,
+}
+
+impl OverwritePolicy {
+    /// Build a policy from glob patterns
+    ///
+    /// Patterns name paths on the target system, with or without the leading
+    /// `/`. `*` stays within one directory; use `**` to cross directories.
+    pub fn new(patterns: &[String]) -> Result<Self> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            let glob = GlobBuilder::new(pattern.trim_start_matches('/'))
+                .literal_separator(true)
+                .build()
+                .with_context(|| format!("Invalid --overwrite pattern {:?}", pattern))?;
+            builder.add(glob);
+        }
+        Ok(Self { globs: builder.build()? })
+    }
+
+    /// Check whether `path`, relative to the install root, may be taken over
+    pub fn allows(&self, path: &Path) -> bool {
+        self.globs.is_match(path.strip_prefix("/").unwrap_or(path))
+    }
+}
+
+/// A path installing a package would overwrite, and its current owner
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileCollision {
+    pub path: PathBuf,
+    pub owner: String,
+}
+
+/// A file taken over by a package, with the previous owner's record
+#[derive(Debug, Clone)]
+pub struct FileTransfer {
+    pub from_package: String,
+    pub file: InstalledFile,
+}
+
+/// Describe collisions the overwrite policy does not allow
+pub fn collision_error(package: &str, collisions: &[FileCollision]) -> anyhow::Error {
+    let mut message = format!("{} would overwrite files owned by other packages:", package);
+    for collision in collisions {
+        message.push_str(&format!("\n  /{} (owned by {})", collision.path.display(), collision.owner));
+    }
+    message.push_str("\nPass --overwrite <glob> to let it take these paths over");
+    anyhow::anyhow!(message)
+}
+
+/// Copy the file or symlink at `from` to `to`, creating parent directories
+pub fn preserve(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if to.symlink_metadata().is_ok() {
+        std::fs::remove_file(to)?;
+    }
+
+    let metadata = from.symlink_metadata()
+        .with_context(|| format!("Failed to read {}", from.display()))?;
+    if metadata.file_type().is_symlink() {
+        std::os::unix::fs::symlink(std::fs::read_link(from)?, to)?;
+    } else {
+        std::fs::copy(from, to)
+            .with_context(|| format!("Failed to copy {} to {}", from.display(), to.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_matches_target_paths() {
+        let policy = OverwritePolicy::new(&[
+            "/usr/bin/vi".to_string(),
+            "etc/alternatives/*".to_string(),
+            "/usr/share/man/**".to_string(),
+        ]).unwrap();
+
+        assert!(policy.allows(Path::new("usr/bin/vi")));
+        assert!(policy.allows(Path::new("/usr/bin/vi")));
+        assert!(!policy.allows(Path::new("usr/bin/vim")));
+        assert!(policy.allows(Path::new("etc/alternatives/editor")));
+        assert!(!policy.allows(Path::new("etc/alternatives/nested/editor")));
+        assert!(policy.allows(Path::new("usr/share/man/man1/vi.1.gz")));
+
+        assert!(!OverwritePolicy::new(&[]).unwrap().allows(Path::new("usr/bin/vi")));
+        assert!(OverwritePolicy::new(&["usr/[bin".to_string()]).is_err());
+    }
+}
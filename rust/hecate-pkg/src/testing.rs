@@ -0,0 +1,191 @@
+//! Fixtures for tests that need packages and a repository server
+//!
+//! Shared with other crates' tests through the `test-support` feature.
+
+use chrono::Utc;
+use semver::Version;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::{Architecture, Dependency, Package, PackageChecksum, Repository, RepositoryIndex};
+
+/// A package with no archive or checksums, depending on `depends`
+pub fn test_package(name: &str, version: &str, depends: &[&str]) -> Package {
+    Package {
+        name: name.to_string(),
+        version: Version::parse(version).unwrap(),
+        description: format!("{} test package", name),
+        author: String::new(),
+        license: "MIT".to_string(),
+        homepage: None,
+        repository: None,
+        dependencies: depends.iter().map(|d| Dependency {
+            name: d.to_string(),
+            version_req: "*".to_string(),
+            optional: false,
+            build_only: false,
+        }).collect(),
+        conflicts: Vec::new(),
+        provides: Vec::new(),
+        replaces: Vec::new(),
+        categories: Vec::new(),
+        keywords: Vec::new(),
+        architecture: Architecture::All,
+        size_bytes: 0,
+        installed_size_bytes: 0,
+        checksum: PackageChecksum {
+            sha256: String::new(),
+            blake3: String::new(),
+            sha512: None,
+        },
+        signature: None,
+        build_date: Utc::now(),
+    }
+}
+
+/// Build a zstd-compressed tarball holding the given files
+pub fn build_archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (path, data) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, path, *data).unwrap();
+    }
+    let tar_data = builder.into_inner().unwrap();
+    zstd::encode_all(tar_data.as_slice(), 3).unwrap()
+}
+
+/// Index of `repository` listing `packages`
+pub fn repository_index(repository: Repository, packages: impl IntoIterator<Item = Package>) -> RepositoryIndex {
+    let mut index = RepositoryIndex {
+        repository,
+        packages: HashMap::new(),
+        groups: HashMap::new(),
+        provides_index: HashMap::new(),
+    };
+    for pkg in packages {
+        index.packages.entry(pkg.name.clone()).or_default().push(pkg);
+    }
+    index
+}
+
+/// Minimal HTTP server for download tests, honoring `Range: bytes=N-`
+pub struct FixtureServer {
+    pub url: String,
+    files: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+    ranges: Arc<Mutex<Vec<Option<usize>>>>,
+    failures: Arc<Mutex<HashMap<String, Vec<&'static str>>>>,
+    ignore_ranges: Arc<std::sync::atomic::AtomicBool>,
+}
+
+impl FixtureServer {
+    pub async fn start() -> Self {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let files: Arc<Mutex<HashMap<String, Vec<u8>>>> = Arc::default();
+        let ranges: Arc<Mutex<Vec<Option<usize>>>> = Arc::default();
+        let failures: Arc<Mutex<HashMap<String, Vec<&'static str>>>> = Arc::default();
+
+        let ignore_ranges: Arc<std::sync::atomic::AtomicBool> = Arc::default();
+
+        let (served, log, failing, no_ranges) = (files.clone(), ranges.clone(), failures.clone(), ignore_ranges.clone());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let (served, log, failing, no_ranges) = (served.clone(), log.clone(), failing.clone(), no_ranges.clone());
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buf = [0u8; 1024];
+                    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                        match socket.read(&mut buf).await {
+                            Ok(0) | Err(_) => break,
+                            Ok(n) => request.extend_from_slice(&buf[..n]),
+                        }
+                    }
+
+                    let request = String::from_utf8_lossy(&request).to_string();
+                    let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+                    let range = request.lines().find_map(|line| {
+                        let (name, value) = line.split_once(':')?;
+                        if !name.eq_ignore_ascii_case("range") {
+                            return None;
+                        }
+                        let (start, end) = value.trim().strip_prefix("bytes=")?.split_once('-')?;
+                        Some((start.parse::<usize>().ok()?, end.parse::<usize>().ok()))
+                    }).filter(|_| !no_ranges.load(std::sync::atomic::Ordering::SeqCst));
+
+                    let failure = failing.lock().unwrap().get_mut(&path).and_then(|queue| queue.pop());
+                    let data = served.lock().unwrap().get(&path).cloned();
+                    let response = match data {
+                        _ if failure.is_some() => format!(
+                            "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                            failure.unwrap()
+                        ).into_bytes(),
+                        Some(data) => {
+                            log.lock().unwrap().push(range.map(|(start, _)| start));
+                            let (status, start, end) = match range {
+                                Some((start, end)) if start < data.len() => {
+                                    let end = end.map_or(data.len(), |end| (end + 1).min(data.len()));
+                                    ("206 Partial Content", start, end)
+                                }
+                                _ => ("200 OK", 0, data.len()),
+                            };
+                            let mut response = format!(
+                                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                                status, end - start
+                            ).into_bytes();
+                            response.extend_from_slice(&data[start..end]);
+                            response
+                        }
+                        None => b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_vec(),
+                    };
+
+                    socket.write_all(&response).await.ok();
+                    socket.shutdown().await.ok();
+                });
+            }
+        });
+
+        Self { url, files, ranges, failures, ignore_ranges }
+    }
+
+    /// Answer every request with the whole file, like a server without range support
+    pub fn disable_ranges(&self) {
+        self.ignore_ranges.store(true, std::sync::atomic::Ordering::SeqCst);
+    }
+
+    /// Answer the next requests for `path` with `statuses`, in order
+    pub fn fail_next(&self, path: &str, statuses: &[&'static str]) {
+        let queue = statuses.iter().rev().copied().collect();
+        self.failures.lock().unwrap().insert(path.to_string(), queue);
+    }
+
+    pub fn pending_failures(&self, path: &str) -> usize {
+        self.failures.lock().unwrap().get(path).map_or(0, Vec::len)
+    }
+
+    pub fn serve(&self, path: &str, data: Vec<u8>) {
+        self.files.lock().unwrap().insert(path.to_string(), data);
+    }
+
+    /// Stop serving `path`, so requests for it get a 404
+    pub fn unserve(&self, path: &str) {
+        self.files.lock().unwrap().remove(path);
+    }
+
+    /// Serve a compressed index at `<prefix>/index.json.zst` along with
+    /// the `.sha256` of `checksummed`
+    pub fn serve_index(&self, prefix: &str, data: Vec<u8>, checksummed: &[u8]) {
+        use sha2::{Digest, Sha256};
+        let checksum = format!("{}  index.json.zst\n", hex::encode(Sha256::digest(checksummed)));
+        self.serve(&format!("{}/index.json.zst.sha256", prefix), checksum.into_bytes());
+        self.serve(&format!("{}/index.json.zst", prefix), data);
+    }
+
+    pub fn ranges(&self) -> Vec<Option<usize>> {
+        self.ranges.lock().unwrap().clone()
+    }
+}
@@ -0,0 +1,107 @@
+//! World files: the set of explicitly installed packages
+//!
+//! Used to reproduce an installation on another machine. The text format is
+//! one `name@version` per line, followed by ` dependency` for packages only
+//! installed to satisfy others; the JSON format is an array of
+//! `{"name": ..., "version": ..., "dependency": ...}` objects.
+
+use anyhow::{Result, Context};
+use serde::{Deserialize, Serialize};
+use semver::Version;
+
+/// Output format for world files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorldFormat {
+    Text,
+    Json,
+}
+
+/// A single entry of a world file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorldEntry {
+    pub name: String,
+    pub version: Version,
+    /// Installed as a dependency rather than asked for; recorded so that
+    /// importing with pinned versions pins dependencies too
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dependency: bool,
+}
+
+impl WorldEntry {
+    pub fn new(name: &str, version: Version, dependency: bool) -> Self {
+        Self { name: name.to_string(), version, dependency }
+    }
+}
+
+/// Render a package set as a world file
+pub fn format_world(entries: &[WorldEntry], format: WorldFormat) -> Result<String> {
+    match format {
+        WorldFormat::Text => Ok(entries.iter()
+            .map(|e| if e.dependency {
+                format!("{}@{} dependency\n", e.name, e.version)
+            } else {
+                format!("{}@{}\n", e.name, e.version)
+            })
+            .collect()),
+        WorldFormat::Json => Ok(serde_json::to_string_pretty(entries)? + "\n"),
+    }
+}
+
+/// Parse a world file, detecting JSON or text format from its content
+///
+/// Blank lines and lines starting with `#` are ignored in the text format.
+pub fn parse_world(content: &str) -> Result<Vec<WorldEntry>> {
+    if content.trim_start().starts_with('[') {
+        return serde_json::from_str(content).context("Invalid JSON world file");
+    }
+
+    let mut entries = Vec::new();
+    for (line_no, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (spec, dependency) = match line.split_once(char::is_whitespace) {
+            Some((spec, marker)) if marker.trim() == "dependency" => (spec, true),
+            Some(_) => anyhow::bail!("Line {}: expected name@version [dependency]", line_no + 1),
+            None => (line, false),
+        };
+        let (name, version) = spec.split_once('@')
+            .ok_or_else(|| anyhow::anyhow!("Line {}: expected name@version", line_no + 1))?;
+        let version = Version::parse(version.trim())
+            .with_context(|| format!("Line {}: invalid version", line_no + 1))?;
+
+        entries.push(WorldEntry::new(name.trim(), version, dependency));
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_world_formats_round_trip() {
+        let entries = vec![
+            WorldEntry::new("bar", Version::new(2, 1, 0), false),
+            WorldEntry::new("foo", Version::new(1, 0, 0), false),
+            WorldEntry::new("libfoo", Version::new(0, 3, 0), true),
+        ];
+
+        for format in [WorldFormat::Text, WorldFormat::Json] {
+            let content = format_world(&entries, format).unwrap();
+            assert_eq!(parse_world(&content).unwrap(), entries);
+        }
+
+        let parsed = parse_world("# machine a\n\nfoo@1.0.0\n").unwrap();
+        assert_eq!(parsed, vec![WorldEntry::new("foo", Version::new(1, 0, 0), false)]);
+        assert!(parse_world("foo\n").is_err());
+        assert!(parse_world("foo@1.0.0 extra\n").is_err());
+
+        // Entries without the field, as written before dependencies were recorded
+        let parsed = parse_world(r#"[{"name": "foo", "version": "1.0.0"}]"#).unwrap();
+        assert!(!parsed[0].dependency);
+    }
+}
@@ -59,6 +59,7 @@ fn test_package_metadata() {
         checksum: hecate_pkg::PackageChecksum {
             sha256: "abc123".to_string(),
             blake3: "def456".to_string(),
+            sha512: None,
         },
         signature: None,
         build_date: chrono::Utc::now(),
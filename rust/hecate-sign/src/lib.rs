@@ -3,17 +3,54 @@
 //! Provides cryptographic signing and verification for packages, updates, and ISO images
 
 use anyhow::{Result, Context};
-use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use ed25519_dalek::{Signature, Signer as _, SigningKey, Verifier, VerifyingKey};
 use serde::{Deserialize, Serialize};
 use sha2::{Sha256, Sha512, Digest};
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use chrono::{DateTime, Utc};
 
+#[cfg(feature = "pkcs11")]
+pub mod pkcs11;
+
+/// Manifest format version written by this library
+pub const MANIFEST_VERSION: &str = "1.0.0";
+
+/// Newest manifest format major version this library can read
+pub const MAX_MANIFEST_MAJOR: u64 = 1;
+
 /// Signature manifest for a file or package
+///
+/// Manifests are stored as JSON:
+///
+/// ```json
+/// {
+///   "version": "1.0.0",
+///   "timestamp": "2025-01-01T00:00:00Z",
+///   "signer": { "name": "...", "email": null, "key_id": "...", "public_key": "<hex>" },
+///   "files": [
+///     {
+///       "path": "relative/path",
+///       "size": 1234,
+///       "checksums": { "sha256": "<hex>", "sha512": "<hex>", "blake3": "<hex>" },
+///       "signature": "<hex>"
+///     }
+///   ],
+///   "metadata": { "purpose": "Package", "expires": null, "revoked": false, "parent_signature": null }
+/// }
+/// ```
+///
+/// `version` is the format version, `major.minor` or `major.minor.patch`.
+/// Minor versions only add optional fields, so readers ignore fields they
+/// don't know and fill fields an older manifest lacks with defaults. A new
+/// major version may change the meaning of existing fields; manifests with
+/// a major version above [`MAX_MANIFEST_MAJOR`] are rejected rather than
+/// misread.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SignatureManifest {
+    /// Format version, see above
     pub version: String,
     pub timestamp: DateTime<Utc>,
     pub signer: SignerInfo,
@@ -21,10 +58,49 @@ pub struct SignatureManifest {
     pub metadata: SignatureMetadata,
 }
 
+impl SignatureManifest {
+    /// Parse a manifest from JSON, checking its format version first
+    pub fn from_json(content: &str) -> Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(content)?;
+        let version = value.get("version")
+            .and_then(|version| version.as_str())
+            .context("Manifest has no format version")?;
+        check_manifest_version(version)?;
+
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Read a manifest from `path`
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::from_json(&content)
+            .with_context(|| format!("Invalid signature manifest {}", path.display()))
+    }
+}
+
+/// Fail unless manifests of format `version` can be read
+fn check_manifest_version(version: &str) -> Result<()> {
+    let mut parts = version.split('.').map(str::parse::<u64>);
+    let major = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(major)), Some(Ok(_)), None | Some(Ok(_)), None) => major,
+        _ => anyhow::bail!("Invalid manifest format version '{}'", version),
+    };
+
+    if major > MAX_MANIFEST_MAJOR {
+        anyhow::bail!(
+            "Manifest format {} is newer than this hecate-sign supports (up to {}.x); upgrade hecate-sign to read it",
+            version, MAX_MANIFEST_MAJOR
+        );
+    }
+    Ok(())
+}
+
 /// Information about the signer
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SignerInfo {
     pub name: String,
+    #[serde(default)]
     pub email: Option<String>,
     pub key_id: String,
     pub public_key: String,
@@ -40,19 +116,66 @@ pub struct FileSignature {
 }
 
 /// Multiple checksums for verification
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Only the digests chosen by the signing [`HashPolicy`] are present.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct FileChecksums {
-    pub sha256: String,
-    pub sha512: String,
-    pub blake3: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha512: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blake3: Option<String>,
+}
+
+/// Which digests to compute when signing
+///
+/// Hashing dominates signing large trees, and the signature already covers
+/// the contents, so the extra digests can be skipped when speed matters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashPolicy {
+    /// SHA256, SHA512 and BLAKE3
+    #[default]
+    All,
+    Sha256,
+    Blake3,
+}
+
+impl HashPolicy {
+    /// Checksums of `contents` under this policy
+    pub fn checksums(self, contents: &[u8]) -> FileChecksums {
+        let all = self == HashPolicy::All;
+        FileChecksums {
+            sha256: (all || self == HashPolicy::Sha256).then(|| hex::encode(Sha256::digest(contents))),
+            sha512: all.then(|| hex::encode(Sha512::digest(contents))),
+            blake3: (all || self == HashPolicy::Blake3).then(|| hex::encode(blake3::hash(contents).as_bytes())),
+        }
+    }
+}
+
+impl std::str::FromStr for HashPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "all" => Ok(HashPolicy::All),
+            "sha256" => Ok(HashPolicy::Sha256),
+            "blake3" => Ok(HashPolicy::Blake3),
+            _ => Err(anyhow::anyhow!("Unknown hash policy '{}' (expected all, sha256 or blake3)", s)),
+        }
+    }
 }
 
 /// Additional metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SignatureMetadata {
     pub purpose: SignaturePurpose,
+    #[serde(default)]
     pub expires: Option<DateTime<Utc>>,
+    #[serde(default)]
     pub revoked: bool,
+    #[serde(default)]
     pub parent_signature: Option<String>,
 }
 
@@ -66,7 +189,31 @@ pub enum SignaturePurpose {
     Certificate,
 }
 
-/// Key pair for signing
+/// Holder of an ed25519 private key that can sign with it
+///
+/// The private key need not be visible to this process at all, as with
+/// keys kept on a hardware token.
+pub trait Signer {
+    /// Public half of the signing key
+    fn verifying_key(&self) -> VerifyingKey;
+
+    /// Sign `message` with the private key
+    fn sign(&self, message: &[u8]) -> Result<Signature>;
+
+    /// Key ID (first 16 chars of hex-encoded public key)
+    fn key_id(&self) -> String {
+        key_id_of(&self.verifying_key())
+    }
+}
+
+fn key_id_of(public_key: &VerifyingKey) -> String {
+    hex::encode(public_key.to_bytes())
+        .chars()
+        .take(16)
+        .collect()
+}
+
+/// Key pair for signing, loaded from or saved to files
 pub struct KeyPair {
     signing_key: SigningKey,
     verifying_key: VerifyingKey,
@@ -134,38 +281,38 @@ impl KeyPair {
 
     /// Get key ID (first 16 chars of hex-encoded public key)
     pub fn key_id(&self) -> String {
-        hex::encode(self.verifying_key.to_bytes())
-            .chars()
-            .take(16)
-            .collect()
+        key_id_of(&self.verifying_key)
+    }
+}
+
+impl Signer for KeyPair {
+    fn verifying_key(&self) -> VerifyingKey {
+        self.verifying_key
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Signature> {
+        Ok(self.signing_key.sign(message))
     }
 }
 
-/// Sign a single file
-pub fn sign_file(file_path: &Path, key_pair: &KeyPair) -> Result<FileSignature> {
+/// Sign a single file, computing the digests chosen by `policy`
+pub fn sign_file<S: Signer + ?Sized>(file_path: &Path, signer: &S, policy: HashPolicy) -> Result<FileSignature> {
     let mut file = File::open(file_path)?;
     let mut contents = Vec::new();
     file.read_to_end(&mut contents)?;
     
     let size = contents.len() as u64;
     
-    // Calculate checksums
-    let sha256 = hex::encode(Sha256::digest(&contents));
-    let sha512 = hex::encode(Sha512::digest(&contents));
-    let blake3 = hex::encode(blake3::hash(&contents).as_bytes());
+    let checksums = policy.checksums(&contents);
     
     // Sign the SHA256 hash
-    let signature = key_pair.signing_key.sign(&contents);
+    let signature = signer.sign(&contents)?;
     let signature_hex = hex::encode(signature.to_bytes());
     
     Ok(FileSignature {
         path: file_path.to_string_lossy().to_string(),
         size,
-        checksums: FileChecksums {
-            sha256,
-            sha512,
-            blake3,
-        },
+        checksums,
         signature: signature_hex,
     })
 }
@@ -185,10 +332,22 @@ pub fn verify_file(
         return Ok(false);
     }
     
-    // Verify checksums
-    let sha256 = hex::encode(Sha256::digest(&contents));
-    if sha256 != file_sig.checksums.sha256 {
-        return Ok(false);
+    // Verify whichever checksums were recorded
+    let expected = &file_sig.checksums;
+    if let Some(sha256) = &expected.sha256 {
+        if *sha256 != hex::encode(Sha256::digest(&contents)) {
+            return Ok(false);
+        }
+    }
+    if let Some(sha512) = &expected.sha512 {
+        if *sha512 != hex::encode(Sha512::digest(&contents)) {
+            return Ok(false);
+        }
+    }
+    if let Some(blake3) = &expected.blake3 {
+        if *blake3 != hex::encode(blake3::hash(&contents).as_bytes()) {
+            return Ok(false);
+        }
     }
     
     // Verify signature
@@ -202,39 +361,55 @@ pub fn verify_file(
 }
 
 /// Sign multiple files and create a manifest
-pub fn sign_directory(
+pub fn sign_directory<S: Signer + ?Sized>(
     dir_path: &Path,
-    key_pair: &KeyPair,
+    signer: &S,
     signer_name: String,
     purpose: SignaturePurpose,
+    policy: HashPolicy,
 ) -> Result<SignatureManifest> {
-    let mut files = Vec::new();
-    
     // Walk directory and sign all files
-    for entry in walkdir::WalkDir::new(dir_path)
+    let relative_paths: Vec<String> = walkdir::WalkDir::new(dir_path)
         .into_iter()
         .filter_map(|e| e.ok())
         .filter(|e| e.file_type().is_file())
-    {
-        let path = entry.path();
-        let relative_path = path.strip_prefix(dir_path)
-            .unwrap_or(path)
-            .to_string_lossy()
-            .to_string();
-        
-        let mut file_sig = sign_file(path, key_pair)?;
-        file_sig.path = relative_path;
+        .map(|entry| {
+            let path = entry.path();
+            path.strip_prefix(dir_path)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect();
+    
+    sign_files(dir_path, &relative_paths, signer, signer_name, purpose, policy)
+}
+
+/// Sign the given files, relative to `base_path`, into one manifest
+pub fn sign_files<S: Signer + ?Sized>(
+    base_path: &Path,
+    relative_paths: &[String],
+    signer: &S,
+    signer_name: String,
+    purpose: SignaturePurpose,
+    policy: HashPolicy,
+) -> Result<SignatureManifest> {
+    let mut files = Vec::new();
+    
+    for relative_path in relative_paths {
+        let mut file_sig = sign_file(&base_path.join(relative_path), signer, policy)?;
+        file_sig.path = relative_path.clone();
         files.push(file_sig);
     }
     
     Ok(SignatureManifest {
-        version: "1.0.0".to_string(),
+        version: MANIFEST_VERSION.to_string(),
         timestamp: Utc::now(),
         signer: SignerInfo {
             name: signer_name,
             email: None,
-            key_id: key_pair.key_id(),
-            public_key: hex::encode(key_pair.verifying_key.to_bytes()),
+            key_id: signer.key_id(),
+            public_key: hex::encode(signer.verifying_key().to_bytes()),
         },
         files,
         metadata: SignatureMetadata {
@@ -246,39 +421,209 @@ pub fn sign_directory(
     })
 }
 
+/// Days before expiry at which verification starts warning
+pub const DEFAULT_EXPIRY_WARNING_DAYS: i64 = 30;
+
+/// Something with an expiry date that verification looked at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expiring {
+    /// The signing key, by key id, as recorded in the trust store
+    SigningKey(String),
+    Manifest,
+}
+
+/// A problem that doesn't invalidate the signature yet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerificationWarning {
+    ExpiresInDays { what: Expiring, days: i64 },
+}
+
+impl std::fmt::Display for VerificationWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerificationWarning::ExpiresInDays { what: Expiring::SigningKey(key_id), days } => {
+                write!(f, "Signing key {} expires in {} days", key_id, days)
+            }
+            VerificationWarning::ExpiresInDays { what: Expiring::Manifest, days } => {
+                write!(f, "Signature expires in {} days", days)
+            }
+        }
+    }
+}
+
+/// Outcome of verifying a manifest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verification {
+    pub valid: bool,
+    pub warnings: Vec<VerificationWarning>,
+    /// Files the verification cache showed unchanged, which were not rehashed
+    pub unchanged: usize,
+}
+
+/// Files that verified before, so unchanged ones need not be rehashed
+///
+/// A file counts as unchanged while its size and modification time match
+/// what they were when it last verified under the same signature and key.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct VerificationCache {
+    #[serde(skip)]
+    path: PathBuf,
+    /// By canonical file path
+    entries: HashMap<String, VerifiedFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct VerifiedFile {
+    size: u64,
+    /// Nanoseconds since the Unix epoch
+    mtime: u128,
+    /// The signature the file last verified with, which covers its contents
+    signature: String,
+    public_key: String,
+}
+
+impl VerificationCache {
+    /// Cache file kept next to the trust store at `trust_store`
+    pub fn path_for(trust_store: &Path) -> PathBuf {
+        trust_store.with_file_name("verify-cache.json")
+    }
+
+    /// Load the cache at `path`; a missing or unreadable cache starts empty
+    pub fn load(path: &Path) -> Self {
+        let entries = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<Self>(&content).ok())
+            .map(|cache| cache.entries)
+            .unwrap_or_default();
+        Self { path: path.to_path_buf(), entries }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        std::fs::write(&self.path, serde_json::to_string(self)?)
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+
+    /// Identity of `file_path` as it is on disk now
+    fn current(file_path: &Path, file_sig: &FileSignature, public_key: &VerifyingKey) -> Option<(String, VerifiedFile)> {
+        let metadata = std::fs::metadata(file_path).ok()?;
+        let mtime = metadata.modified().ok()?.duration_since(std::time::UNIX_EPOCH).ok()?.as_nanos();
+        let key = std::fs::canonicalize(file_path).ok()?.to_string_lossy().to_string();
+        Some((key, VerifiedFile {
+            size: metadata.len(),
+            mtime,
+            signature: file_sig.signature.clone(),
+            public_key: hex::encode(public_key.to_bytes()),
+        }))
+    }
+}
+
+/// Whole days until `expires`, rounded up so that "in 3 days" stays 3
+/// for the whole of the first day
+fn days_until(expires: DateTime<Utc>) -> i64 {
+    let seconds = (expires - Utc::now()).num_seconds();
+    seconds.div_euclid(86400) + i64::from(seconds.rem_euclid(86400) > 0)
+}
+
 /// Verify a signature manifest
 pub fn verify_manifest(
     manifest: &SignatureManifest,
     base_path: &Path,
 ) -> Result<bool> {
+    Ok(verify_manifest_with(manifest, base_path, None, DEFAULT_EXPIRY_WARNING_DAYS, None)?.valid)
+}
+
+/// Verify a signature manifest, warning when it or its signing key expire
+/// within `warn_days`
+///
+/// The signing key's expiry and revocation are only known from
+/// `trust_store`; without one only the manifest's own are checked. With a
+/// `cache`, files unchanged since they last verified are not rehashed, and
+/// the cache is updated with every file that verifies.
+pub fn verify_manifest_with(
+    manifest: &SignatureManifest,
+    base_path: &Path,
+    trust_store: Option<&TrustStore>,
+    warn_days: i64,
+    mut cache: Option<&mut VerificationCache>,
+) -> Result<Verification> {
+    check_manifest_version(&manifest.version)?;
+    let invalid = Ok(Verification { valid: false, warnings: Vec::new(), unchanged: 0 });
+
     // Parse public key from manifest
     let public_key_bytes = hex::decode(&manifest.signer.public_key)?;
     let public_key = VerifyingKey::from_bytes(
         &public_key_bytes.try_into()
             .map_err(|_| anyhow::anyhow!("Invalid public key size"))?
     )?;
+    // The key id is not signed; taken from the manifest, it could name a
+    // trusted, unrevoked key while the files are signed by another one
+    let key_id = key_id_of(&public_key);
+    if key_id != manifest.signer.key_id {
+        return invalid;
+    }
     
     // Check expiration
     if let Some(expires) = manifest.metadata.expires {
         if Utc::now() > expires {
-            return Ok(false);
+            return invalid;
         }
     }
     
-    // Check revocation
+    // Check revocation, of the manifest and of its signing key
     if manifest.metadata.revoked {
-        return Ok(false);
+        return invalid;
+    }
+    if trust_store.is_some_and(|store| store.is_revoked(&key_id)) {
+        return invalid;
+    }
+    // A key the store knows but no longer trusts has expired
+    if trust_store.is_some_and(|store| store.key(&key_id).is_some() && !store.is_trusted(&key_id)) {
+        return invalid;
     }
     
     // Verify each file
+    let mut unchanged = 0;
     for file_sig in &manifest.files {
         let file_path = base_path.join(&file_sig.path);
+        let Some(cache) = cache.as_deref_mut() else {
+            if !verify_file(&file_path, file_sig, &public_key)? {
+                return invalid;
+            }
+            continue;
+        };
+
+        let current = VerificationCache::current(&file_path, file_sig, &public_key);
+        if let Some((key, file)) = &current {
+            if cache.entries.get(key) == Some(file) {
+                unchanged += 1;
+                continue;
+            }
+        }
         if !verify_file(&file_path, file_sig, &public_key)? {
-            return Ok(false);
+            if let Some((key, _)) = current {
+                cache.entries.remove(&key);
+            }
+            return invalid;
+        }
+        if let Some((key, file)) = current {
+            cache.entries.insert(key, file);
+        }
+    }
+    
+    let mut warnings = Vec::new();
+    let key_expires = trust_store
+        .and_then(|store| store.key(&key_id))
+        .and_then(|key| key.expires);
+    for (what, expires) in [
+        (Expiring::SigningKey(key_id), key_expires),
+        (Expiring::Manifest, manifest.metadata.expires),
+    ] {
+        if let Some(days) = expires.map(days_until).filter(|days| (0..=warn_days).contains(days)) {
+            warnings.push(VerificationWarning::ExpiresInDays { what, days });
         }
     }
     
-    Ok(true)
+    Ok(Verification { valid: true, warnings, unchanged })
 }
 
 /// Trust store for managing trusted public keys
@@ -295,6 +640,53 @@ pub struct TrustedKey {
     pub added: DateTime<Utc>,
     pub expires: Option<DateTime<Utc>>,
     pub revoked: bool,
+    /// Root keys may sign revocation lists
+    #[serde(default)]
+    pub root: bool,
+}
+
+/// Signed list of revoked key ids, published so that every machine
+/// learns about a compromised key without being edited by hand
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevocationList {
+    pub issued: DateTime<Utc>,
+    pub revoked: Vec<String>,
+    /// Root key that signed the list
+    pub key_id: String,
+    pub signature: String,
+}
+
+impl RevocationList {
+    /// Sign a list revoking `revoked` key ids with a root key
+    pub fn sign<S: Signer + ?Sized>(revoked: Vec<String>, signer: &S) -> Result<Self> {
+        let mut list = Self {
+            issued: Utc::now(),
+            revoked,
+            key_id: signer.key_id(),
+            signature: String::new(),
+        };
+        list.signature = hex::encode(signer.sign(&list.signed_bytes()?)?.to_bytes());
+        Ok(list)
+    }
+
+    /// Load a list from an `http(s)://` URL or a file
+    pub fn load(source: &str) -> Result<Self> {
+        let content = if source.starts_with("http://") || source.starts_with("https://") {
+            reqwest::blocking::get(source)
+                .and_then(|response| response.error_for_status())
+                .and_then(|response| response.text())
+                .with_context(|| format!("Failed to fetch revocation list from {}", source))?
+        } else {
+            std::fs::read_to_string(source)
+                .with_context(|| format!("Failed to read revocation list {}", source))?
+        };
+        serde_json::from_str(&content).with_context(|| format!("Invalid revocation list in {}", source))
+    }
+
+    /// What the signature covers: everything but the signature itself
+    fn signed_bytes(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(&(&self.issued, &self.revoked, &self.key_id))?)
+    }
 }
 
 impl TrustStore {
@@ -322,6 +714,15 @@ impl TrustStore {
 
     /// Add a trusted key
     pub fn add_key(&mut self, name: String, public_key: &VerifyingKey) -> Result<()> {
+        self.push_key(name, public_key, false)
+    }
+
+    /// Add a trusted root key, which may also sign revocation lists
+    pub fn add_root_key(&mut self, name: String, public_key: &VerifyingKey) -> Result<()> {
+        self.push_key(name, public_key, true)
+    }
+
+    fn push_key(&mut self, name: String, public_key: &VerifyingKey, root: bool) -> Result<()> {
         let key_bytes = public_key.to_bytes();
         let key_hex = hex::encode(key_bytes);
         let key_id = key_hex.chars().take(16).collect();
@@ -333,6 +734,7 @@ impl TrustStore {
             added: Utc::now(),
             expires: Some(Utc::now() + chrono::Duration::days(365 * 2)),
             revoked: false,
+            root,
         });
         
         self.save()?;
@@ -348,6 +750,66 @@ impl TrustStore {
         )
     }
 
+    /// Check if a key has been revoked, locally or by a revocation list
+    pub fn is_revoked(&self, key_id: &str) -> bool {
+        self.trusted_keys.iter().any(|k| k.key_id == key_id && k.revoked)
+    }
+
+    /// Merge a revocation list signed by a trusted root key into the store
+    ///
+    /// Returns the ids of keys that were newly revoked. Revocations are never
+    /// undone, so replaying an older list changes nothing.
+    pub fn apply_revocations(&mut self, list: &RevocationList) -> Result<Vec<String>> {
+        let root = self.trusted_keys.iter()
+            .find(|k| k.key_id == list.key_id && k.root)
+            .filter(|k| self.is_trusted(&k.key_id))
+            .with_context(|| format!("Revocation list is signed by {}, which is not a trusted root key", list.key_id))?;
+
+        let public_key = VerifyingKey::from_bytes(
+            &hex::decode(&root.public_key)?.try_into()
+                .map_err(|_| anyhow::anyhow!("Invalid public key size"))?
+        )?;
+        let signature = Signature::from_bytes(
+            &hex::decode(&list.signature)?.try_into()
+                .map_err(|_| anyhow::anyhow!("Invalid signature size"))?
+        );
+        if public_key.verify(&list.signed_bytes()?, &signature).is_err() {
+            anyhow::bail!("Invalid signature on revocation list from {}", list.key_id);
+        }
+
+        let mut newly_revoked = Vec::new();
+        for key in &mut self.trusted_keys {
+            if !key.revoked && list.revoked.contains(&key.key_id) {
+                key.revoked = true;
+                newly_revoked.push(key.key_id.clone());
+            }
+        }
+        if !newly_revoked.is_empty() {
+            self.save()?;
+        }
+        Ok(newly_revoked)
+    }
+
+    /// All keys in the store, trusted or not
+    pub fn keys(&self) -> &[TrustedKey] {
+        &self.trusted_keys
+    }
+
+    /// Look up a key by id
+    pub fn key(&self, key_id: &str) -> Option<&TrustedKey> {
+        self.trusted_keys.iter().find(|k| k.key_id == key_id)
+    }
+
+    /// Trusted keys that expire within `days`, soonest first
+    pub fn expiring_soon(&self, days: i64) -> Vec<&TrustedKey> {
+        let mut keys: Vec<&TrustedKey> = self.trusted_keys.iter()
+            .filter(|k| self.is_trusted(&k.key_id))
+            .filter(|k| k.expires.is_some_and(|e| days_until(e) <= days))
+            .collect();
+        keys.sort_by_key(|k| k.expires);
+        keys
+    }
+
     /// Revoke a key
     pub fn revoke_key(&mut self, key_id: &str) -> Result<()> {
         for key in &mut self.trusted_keys {
@@ -378,7 +840,7 @@ mod tests {
         std::fs::write(&file_path, b"test content").unwrap();
         
         let keypair = KeyPair::generate();
-        let signature = sign_file(&file_path, &keypair).unwrap();
+        let signature = sign_file(&file_path, &keypair, HashPolicy::default()).unwrap();
         
         assert!(verify_file(&file_path, &signature, &keypair.verifying_key).unwrap());
     }
@@ -395,9 +857,281 @@ mod tests {
             &keypair,
             "Test Signer".to_string(),
             SignaturePurpose::Package,
+            HashPolicy::default(),
         ).unwrap();
         
         assert_eq!(manifest.files.len(), 2);
         assert!(verify_manifest(&manifest, dir.path()).unwrap());
     }
-}
\ No newline at end of file
+
+    fn store_expiring_in(dir: &Path, days: &[i64]) -> TrustStore {
+        let mut store = TrustStore::load(&dir.join("trust.json")).unwrap();
+        for &days in days {
+            let key = KeyPair::generate();
+            store.add_key(format!("key-{}", days), &key.verifying_key).unwrap();
+            store.trusted_keys.last_mut().unwrap().expires = Some(Utc::now() + chrono::Duration::days(days));
+        }
+        store
+    }
+
+    #[test]
+    fn test_expiring_soon() {
+        let dir = tempdir().unwrap();
+        let mut store = store_expiring_in(dir.path(), &[400, 3, 30]);
+
+        let names = |keys: Vec<&TrustedKey>| keys.iter().map(|k| k.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(store.expiring_soon(30)), vec!["key-3", "key-30"]);
+        assert_eq!(names(store.expiring_soon(7)), vec!["key-3"]);
+        assert_eq!(names(store.expiring_soon(365)), vec!["key-3", "key-30"]);
+
+        // Revoked keys need no renewal
+        let revoked = store.expiring_soon(7)[0].key_id.clone();
+        store.revoke_key(&revoked).unwrap();
+        assert!(store.expiring_soon(7).is_empty());
+    }
+
+    #[test]
+    fn test_verification_warns_before_expiry() {
+        let dir = tempdir().unwrap();
+        let files = dir.path().join("files");
+        std::fs::create_dir(&files).unwrap();
+        std::fs::write(files.join("file1.txt"), b"content1").unwrap();
+
+        let verify = |key_days: i64, manifest_days: i64| {
+            let keypair = KeyPair::generate();
+            let mut store = TrustStore::load(&dir.path().join("trust.json")).unwrap();
+            store.add_key("signer".into(), &keypair.verifying_key).unwrap();
+            store.trusted_keys.last_mut().unwrap().expires = Some(Utc::now() + chrono::Duration::days(key_days));
+            let mut manifest = sign_directory(
+                &files, &keypair, "Test Signer".into(), SignaturePurpose::Package, HashPolicy::default(),
+            ).unwrap();
+            manifest.metadata.expires = Some(Utc::now() + chrono::Duration::days(manifest_days));
+            let result = verify_manifest_with(&manifest, &files, Some(&store), DEFAULT_EXPIRY_WARNING_DAYS, None).unwrap();
+            (manifest.signer.key_id, result)
+        };
+        let sign = |key_days: i64, manifest_days: i64| {
+            let (key_id, result) = verify(key_days, manifest_days);
+            assert!(result.valid);
+            (key_id, result.warnings)
+        };
+
+        let (_, warnings) = sign(400, 400);
+        assert!(warnings.is_empty());
+
+        let (key_id, warnings) = sign(3, 30);
+        assert_eq!(warnings, vec![
+            VerificationWarning::ExpiresInDays { what: Expiring::SigningKey(key_id.clone()), days: 3 },
+            VerificationWarning::ExpiresInDays { what: Expiring::Manifest, days: 30 },
+        ]);
+        assert_eq!(warnings[0].to_string(), format!("Signing key {} expires in 3 days", key_id));
+
+        let (_, warnings) = sign(400, 3);
+        assert_eq!(warnings, vec![VerificationWarning::ExpiresInDays { what: Expiring::Manifest, days: 3 }]);
+
+        // An expired signing key fails rather than warning with negative days
+        let (_, result) = verify(-1, 30);
+        assert!(!result.valid);
+        assert!(result.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_revocation_list() {
+        let dir = tempdir().unwrap();
+        let files = dir.path().join("files");
+        std::fs::create_dir(&files).unwrap();
+        std::fs::write(files.join("file1.txt"), b"content1").unwrap();
+
+        let root = KeyPair::generate();
+        let compromised = KeyPair::generate();
+        let mut store = TrustStore::load(&dir.path().join("trust.json")).unwrap();
+        store.add_root_key("root".into(), &root.verifying_key).unwrap();
+        store.add_key("release".into(), &compromised.verifying_key).unwrap();
+
+        let manifest = sign_directory(
+            &files, &compromised, "Release".into(), SignaturePurpose::Package, HashPolicy::default(),
+        ).unwrap();
+        let verify = |store: &TrustStore| {
+            verify_manifest_with(&manifest, &files, Some(store), DEFAULT_EXPIRY_WARNING_DAYS, None).unwrap().valid
+        };
+        assert!(verify(&store));
+
+        // Published as a file and loaded back
+        let list = RevocationList::sign(vec![compromised.key_id()], &root).unwrap();
+        let list_path = dir.path().join("revoked.json");
+        std::fs::write(&list_path, serde_json::to_string(&list).unwrap()).unwrap();
+        let list = RevocationList::load(list_path.to_str().unwrap()).unwrap();
+
+        // Only a root key may revoke, and only with a valid signature
+        let forged = RevocationList::sign(vec![compromised.key_id()], &compromised).unwrap();
+        assert!(store.apply_revocations(&forged).is_err());
+        let mut tampered = list.clone();
+        tampered.revoked.push(root.key_id());
+        assert!(store.apply_revocations(&tampered).is_err());
+        assert!(verify(&store));
+
+        assert_eq!(store.apply_revocations(&list).unwrap(), vec![compromised.key_id()]);
+        assert!(store.apply_revocations(&list).unwrap().is_empty());
+        assert!(!verify(&store));
+
+        // Claiming another key's id does not dodge the revocation
+        let mut relabeled = manifest.clone();
+        relabeled.signer.key_id = root.key_id();
+        assert!(!verify_manifest_with(&relabeled, &files, Some(&store), DEFAULT_EXPIRY_WARNING_DAYS, None).unwrap().valid);
+
+        // The revocation is kept in the saved store
+        let reloaded = TrustStore::load(&dir.path().join("trust.json")).unwrap();
+        assert!(reloaded.is_revoked(&compromised.key_id()));
+        assert!(reloaded.is_trusted(&root.key_id()));
+    }
+
+    #[test]
+    fn test_verification_cache_skips_unchanged_files() {
+        use std::time::{Duration, SystemTime};
+
+        let dir = tempdir().unwrap();
+        let files = dir.path().join("files");
+        std::fs::create_dir(&files).unwrap();
+        std::fs::write(files.join("untouched.txt"), b"content1").unwrap();
+        std::fs::write(files.join("touched.txt"), b"content2").unwrap();
+        let set_mtime = |name: &str, secs: u64| {
+            File::options().write(true).open(files.join(name)).unwrap()
+                .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(secs)).unwrap();
+        };
+        set_mtime("untouched.txt", 1_700_000_000);
+        set_mtime("touched.txt", 1_700_000_000);
+
+        let keypair = KeyPair::generate();
+        let manifest = sign_directory(
+            &files, &keypair, "Test Signer".into(), SignaturePurpose::Package, HashPolicy::default(),
+        ).unwrap();
+        let cache_path = VerificationCache::path_for(&dir.path().join("trust.json"));
+        assert_eq!(cache_path, dir.path().join("verify-cache.json"));
+        let verify = |cache: Option<&mut VerificationCache>| {
+            verify_manifest_with(&manifest, &files, None, DEFAULT_EXPIRY_WARNING_DAYS, cache).unwrap()
+        };
+
+        let mut cache = VerificationCache::load(&cache_path);
+        let first = verify(Some(&mut cache));
+        assert!(first.valid);
+        assert_eq!(first.unchanged, 0);
+        cache.save().unwrap();
+
+        let mut cache = VerificationCache::load(&cache_path);
+        assert_eq!(verify(Some(&mut cache)).unchanged, 2);
+
+        // A modified file is rehashed and caught
+        std::fs::write(files.join("touched.txt"), b"tampered").unwrap();
+        set_mtime("touched.txt", 1_700_000_100);
+        assert!(!verify(Some(&mut cache)).valid);
+
+        // Restored with a new mtime, it is rehashed while the other is skipped
+        std::fs::write(files.join("touched.txt"), b"content2").unwrap();
+        set_mtime("touched.txt", 1_700_000_200);
+        let result = verify(Some(&mut cache));
+        assert!(result.valid);
+        assert_eq!(result.unchanged, 1);
+        assert_eq!(verify(Some(&mut cache)).unchanged, 2);
+
+        // Without a cache (--paranoid) everything is rehashed
+        assert_eq!(verify(None).unchanged, 0);
+    }
+
+    #[test]
+    fn test_hash_policies() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("test.txt");
+        std::fs::write(&file_path, b"test content").unwrap();
+        let keypair = KeyPair::generate();
+
+        let present = |policy| {
+            let sig = sign_file(&file_path, &keypair, policy).unwrap();
+            assert!(verify_file(&file_path, &sig, &keypair.verifying_key).unwrap());
+            let c = sig.checksums;
+            (c.sha256.is_some(), c.sha512.is_some(), c.blake3.is_some())
+        };
+        assert_eq!(present(HashPolicy::All), (true, true, true));
+        assert_eq!(present(HashPolicy::Sha256), (true, false, false));
+        assert_eq!(present(HashPolicy::Blake3), (false, false, true));
+
+        assert_eq!("blake3".parse::<HashPolicy>().unwrap(), HashPolicy::Blake3);
+        assert!("md5".parse::<HashPolicy>().is_err());
+    }
+
+    #[test]
+    fn test_verify_checks_present_digests() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("test.txt");
+        std::fs::write(&file_path, b"test content").unwrap();
+        let keypair = KeyPair::generate();
+
+        for policy in [HashPolicy::All, HashPolicy::Sha256, HashPolicy::Blake3] {
+            let sig = sign_file(&file_path, &keypair, policy).unwrap();
+            let mut tampered = sig.clone();
+            let checksums = &mut tampered.checksums;
+            for digest in [&mut checksums.sha256, &mut checksums.sha512, &mut checksums.blake3].into_iter().flatten() {
+                *digest = "00".repeat(digest.len() / 2);
+            }
+            assert!(!verify_file(&file_path, &tampered, &keypair.verifying_key).unwrap(), "{:?}", policy);
+        }
+
+        // Manifests from before the policy carry all three and still verify
+        let legacy: FileChecksums = serde_json::from_str(
+            r#"{"sha256": "a", "sha512": "b", "blake3": "c"}"#,
+        ).unwrap();
+        assert_eq!(legacy.sha512.as_deref(), Some("b"));
+        let sha256_only = serde_json::to_value(HashPolicy::Sha256.checksums(b"x")).unwrap();
+        assert_eq!(sha256_only.as_object().unwrap().len(), 1);
+    }
+
+    /// A manifest signing `file.txt` in `dir`, as JSON, with its format version replaced
+    fn manifest_json(dir: &Path, version: &str) -> serde_json::Value {
+        std::fs::write(dir.join("file.txt"), b"content").unwrap();
+        let keypair = KeyPair::generate();
+        let manifest = sign_directory(dir, &keypair, "Test Signer".to_string(), SignaturePurpose::Package, HashPolicy::default()).unwrap();
+        let mut json = serde_json::to_value(&manifest).unwrap();
+        json["version"] = version.into();
+        json
+    }
+
+    #[test]
+    fn test_older_manifest_gets_defaults() {
+        let dir = tempdir().unwrap();
+        let mut json = manifest_json(dir.path(), "0.9");
+        // Fields 0.9 manifests did not have
+        json["metadata"].as_object_mut().unwrap().remove("revoked");
+        json["metadata"].as_object_mut().unwrap().remove("parent_signature");
+        json["signer"].as_object_mut().unwrap().remove("email");
+
+        let path = dir.path().join("old.sig");
+        std::fs::write(&path, json.to_string()).unwrap();
+        let manifest = SignatureManifest::load(&path).unwrap();
+        assert_eq!(manifest.version, "0.9");
+        assert!(!manifest.metadata.revoked);
+        assert!(manifest.metadata.parent_signature.is_none());
+        assert!(verify_manifest(&manifest, dir.path()).unwrap());
+
+        // Newer minor versions may add fields, which are ignored
+        let mut json = manifest_json(dir.path(), "1.4.0");
+        json["metadata"]["transparency_log"] = "https://log.example".into();
+        assert!(SignatureManifest::from_json(&json.to_string()).is_ok());
+    }
+
+    #[test]
+    fn test_newer_manifest_is_rejected() {
+        let dir = tempdir().unwrap();
+        let json = manifest_json(dir.path(), "2.0");
+
+        let path = dir.path().join("new.sig");
+        std::fs::write(&path, json.to_string()).unwrap();
+        let err = format!("{:#}", SignatureManifest::load(&path).unwrap_err());
+        assert!(err.contains("Manifest format 2.0 is newer"), "{}", err);
+
+        // Also when the manifest was deserialized without the check
+        let manifest: SignatureManifest = serde_json::from_value(json).unwrap();
+        assert!(verify_manifest(&manifest, dir.path()).is_err());
+
+        for version in ["", "1", "one.zero", "1.0.0.0"] {
+            assert!(SignatureManifest::from_json(&manifest_json(dir.path(), version).to_string()).is_err(), "{}", version);
+        }
+    }
+}
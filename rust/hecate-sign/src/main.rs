@@ -3,8 +3,13 @@
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 use colored::*;
-use hecate_sign::{KeyPair, TrustStore, SignaturePurpose, sign_directory, verify_manifest};
-use std::path::PathBuf;
+use hecate_sign::{
+    HashPolicy, KeyPair, RevocationList, Signer, TrustStore, VerificationCache, SignaturePurpose, sign_directory, verify_manifest_with,
+    DEFAULT_EXPIRY_WARNING_DAYS,
+};
+use std::path::{Path, PathBuf};
+
+const TRUST_STORE_PATH: &str = "/etc/hecate/trust.json";
 
 #[derive(Parser)]
 #[command(name = "hecate-sign")]
@@ -34,11 +39,26 @@ enum Commands {
         
         /// Private key file
         #[arg(short = 'k', long)]
-        key: PathBuf,
+        #[cfg_attr(feature = "pkcs11", arg(required_unless_present = "pkcs11_module"))]
+        #[cfg_attr(not(feature = "pkcs11"), arg(required = true))]
+        key: Option<PathBuf>,
         
         /// Public key file
         #[arg(short = 'p', long)]
-        pubkey: PathBuf,
+        #[cfg_attr(feature = "pkcs11", arg(required_unless_present = "pkcs11_module"))]
+        #[cfg_attr(not(feature = "pkcs11"), arg(required = true))]
+        pubkey: Option<PathBuf>,
+        
+        /// PKCS#11 module of the token holding the key, instead of key files;
+        /// the PIN is read from HECATE_PKCS11_PIN
+        #[cfg(feature = "pkcs11")]
+        #[arg(long, requires = "key_label", conflicts_with_all = ["key", "pubkey"])]
+        pkcs11_module: Option<PathBuf>,
+        
+        /// Label of the key on the token
+        #[cfg(feature = "pkcs11")]
+        #[arg(long)]
+        key_label: Option<String>,
         
         /// Signer name
         #[arg(short, long)]
@@ -47,6 +67,10 @@ enum Commands {
         /// Output manifest file
         #[arg(short, long, default_value = "signature.json")]
         output: PathBuf,
+        
+        /// Digests to record: all, sha256 or blake3
+        #[arg(long, default_value = "all")]
+        hashes: HashPolicy,
     },
     
     /// Verify a signature
@@ -57,6 +81,18 @@ enum Commands {
         /// Base path for files
         #[arg(short, long, default_value = ".")]
         base: PathBuf,
+        
+        /// Warn when the signature or signing key expires within this many days
+        #[arg(long, default_value_t = DEFAULT_EXPIRY_WARNING_DAYS)]
+        warn_days: i64,
+        
+        /// Merge a signed revocation list (URL or file) into the trust store first
+        #[arg(long)]
+        revocations: Option<String>,
+        
+        /// Rehash every file, even those unchanged since they last verified
+        #[arg(long)]
+        paranoid: bool,
     },
     
     /// Manage trust store
@@ -75,10 +111,24 @@ enum TrustAction {
         
         /// Public key file
         pubkey: PathBuf,
+        
+        /// Trust the key to sign revocation lists
+        #[arg(long)]
+        root: bool,
+    },
+    
+    /// Merge a signed revocation list (URL or file) into the trust store
+    Revocations {
+        /// URL or file of the list
+        source: String,
     },
     
     /// List trusted keys
-    List,
+    List {
+        /// Flag keys that expire within this many days
+        #[arg(long, default_value_t = DEFAULT_EXPIRY_WARNING_DAYS)]
+        warn_days: i64,
+    },
     
     /// Revoke a key
     Revoke {
@@ -109,36 +159,56 @@ fn main() -> Result<()> {
             println!("\n{}", "⚠ Keep the private key secure!".red().bold());
         }
         
-        Commands::Sign { path, key, pubkey, signer, output } => {
+        #[cfg(feature = "pkcs11")]
+        Commands::Sign { pkcs11_module: Some(module), key_label: Some(label), path, signer, output, hashes, .. } => {
+            println!("Signing {} on the token...", path.display());
+            
+            let pin = std::env::var("HECATE_PKCS11_PIN")
+                .map_err(|_| anyhow::anyhow!("HECATE_PKCS11_PIN must hold the token PIN"))?;
+            let token = hecate_sign::pkcs11::Pkcs11Signer::open(&module, &label, &pin)?;
+            sign(&path, &token, signer, &output, hashes)?;
+        }
+        
+        Commands::Sign { path, key, pubkey, signer, output, hashes, .. } => {
             println!("Signing {}...", path.display());
             
+            let (Some(key), Some(pubkey)) = (key, pubkey) else {
+                anyhow::bail!("--key and --pubkey are required");
+            };
             let keypair = KeyPair::load(&key, &pubkey)?;
-            let manifest = sign_directory(
-                &path,
-                &keypair,
-                signer,
-                SignaturePurpose::Package,
-            )?;
-            
-            let json = serde_json::to_string_pretty(&manifest)?;
-            std::fs::write(&output, json)?;
-            
-            println!("{}", "Signature created successfully!".green());
-            println!("  Manifest: {}", output.display());
-            println!("  Files signed: {}", manifest.files.len());
+            sign(&path, &keypair, signer, &output, hashes)?;
         }
+
         
-        Commands::Verify { manifest, base } => {
+        Commands::Verify { manifest, base, warn_days, revocations, paranoid } => {
             println!("Verifying signature...");
             
-            let content = std::fs::read_to_string(&manifest)?;
-            let manifest: hecate_sign::SignatureManifest = serde_json::from_str(&content)?;
+            let manifest = hecate_sign::SignatureManifest::load(&manifest)?;
+            let mut store = TrustStore::load(&PathBuf::from(TRUST_STORE_PATH))?;
+            if let Some(source) = revocations {
+                apply_revocations(&mut store, &source)?;
+            }
             
-            if verify_manifest(&manifest, &base)? {
+            let mut cache = (!paranoid)
+                .then(|| VerificationCache::load(&VerificationCache::path_for(Path::new(TRUST_STORE_PATH))));
+            let verification = verify_manifest_with(&manifest, &base, Some(&store), warn_days, cache.as_mut())?;
+            if let Some(cache) = cache {
+                // Only costs speed next time, so not worth failing over
+                if let Err(e) = cache.save() {
+                    println!("{}", format!("⚠ Could not save verification cache: {:#}", e).yellow());
+                }
+            }
+            if verification.valid {
                 println!("{}", "✓ Signature valid!".green().bold());
                 println!("  Signer: {}", manifest.signer.name);
                 println!("  Key ID: {}", manifest.signer.key_id);
                 println!("  Timestamp: {}", manifest.timestamp);
+                if verification.unchanged > 0 {
+                    println!("  Unchanged files skipped: {}", verification.unchanged);
+                }
+                for warning in &verification.warnings {
+                    println!("{}", format!("⚠ {}", warning).yellow());
+                }
             } else {
                 println!("{}", "✗ Signature INVALID!".red().bold());
                 std::process::exit(1);
@@ -146,24 +216,52 @@ fn main() -> Result<()> {
         }
         
         Commands::Trust { action } => {
-            let trust_store_path = PathBuf::from("/etc/hecate/trust.json");
+            let trust_store_path = PathBuf::from(TRUST_STORE_PATH);
             let mut store = TrustStore::load(&trust_store_path)?;
             
             match action {
-                TrustAction::Add { name, pubkey } => {
+                TrustAction::Add { name, pubkey, root } => {
                     let key_bytes = std::fs::read(&pubkey)?;
                     let verifying_key = ed25519_dalek::VerifyingKey::from_bytes(
                         &key_bytes.try_into()
                             .map_err(|_| anyhow::anyhow!("Invalid key size"))?
                     )?;
                     
-                    store.add_key(name.clone(), &verifying_key)?;
-                    println!("{} added to trust store", name.green());
+                    if root {
+                        store.add_root_key(name.clone(), &verifying_key)?;
+                        println!("{} added to trust store as a root key", name.green());
+                    } else {
+                        store.add_key(name.clone(), &verifying_key)?;
+                        println!("{} added to trust store", name.green());
+                    }
                 }
                 
-                TrustAction::List => {
+                TrustAction::Revocations { source } => {
+                    apply_revocations(&mut store, &source)?;
+                }
+                
+                TrustAction::List { warn_days } => {
                     println!("{}", "Trusted keys:".bright_cyan());
-                    // Implementation would list keys from store
+                    let expiring: Vec<&str> = store.expiring_soon(warn_days)
+                        .iter()
+                        .map(|key| key.key_id.as_str())
+                        .collect();
+                    
+                    for key in store.keys() {
+                        let expires = key.expires
+                            .map(|e| e.format("%Y-%m-%d").to_string())
+                            .unwrap_or_else(|| "never".to_string());
+                        let status = if key.revoked {
+                            "revoked".red()
+                        } else if !store.is_trusted(&key.key_id) {
+                            "expired".red()
+                        } else if expiring.contains(&key.key_id.as_str()) {
+                            "expiring soon".yellow()
+                        } else {
+                            "trusted".green()
+                        };
+                        println!("  {} {} (expires {}) {}", key.key_id.bright_yellow(), key.name, expires, status);
+                    }
                 }
                 
                 TrustAction::Revoke { key_id } => {
@@ -174,5 +272,37 @@ fn main() -> Result<()> {
         }
     }
 
+    Ok(())
+}
+
+/// Fetch a revocation list and merge it into `store`
+fn apply_revocations(store: &mut TrustStore, source: &str) -> Result<()> {
+    let list = RevocationList::load(source)?;
+    let revoked = store.apply_revocations(&list)?;
+    if revoked.is_empty() {
+        println!("Revocation list from {} holds no new revocations", source);
+    }
+    for key_id in revoked {
+        println!("Key {} revoked by revocation list", key_id.red());
+    }
+    Ok(())
+}
+
+/// Sign everything under `path` into the manifest at `output`
+fn sign<S: Signer + ?Sized>(
+    path: &Path,
+    key: &S,
+    signer: String,
+    output: &Path,
+    hashes: HashPolicy,
+) -> Result<()> {
+    let manifest = sign_directory(path, key, signer, SignaturePurpose::Package, hashes)?;
+    
+    let json = serde_json::to_string_pretty(&manifest)?;
+    std::fs::write(output, json)?;
+    
+    println!("{}", "Signature created successfully!".green());
+    println!("  Manifest: {}", output.display());
+    println!("  Files signed: {}", manifest.files.len());
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,159 @@
+//! Signing with ed25519 keys kept on a PKCS#11 token (HSM, YubiHSM, SoftHSM)
+//!
+//! The private key never leaves the token: only the public key is read, and
+//! every signature is computed by the token itself.
+
+use crate::Signer;
+use anyhow::{bail, Context, Result};
+use cryptoki::context::{CInitializeArgs, Pkcs11};
+use cryptoki::mechanism::Mechanism;
+use cryptoki::object::{Attribute, AttributeType, ObjectClass, ObjectHandle};
+use cryptoki::session::{Session, UserType};
+use cryptoki::types::AuthPin;
+use ed25519_dalek::{Signature, VerifyingKey};
+use std::path::Path;
+
+/// An ed25519 key on a PKCS#11 token, found by its label
+pub struct Pkcs11Signer {
+    session: Session,
+    private_key: ObjectHandle,
+    verifying_key: VerifyingKey,
+    // Dropped last: finalizes the module once the session is closed
+    _context: Pkcs11,
+}
+
+impl Pkcs11Signer {
+    /// Load the PKCS#11 `module`, log in with `pin` and find the key pair
+    /// labelled `key_label` on the first token that has it
+    pub fn open(module: &Path, key_label: &str, pin: &str) -> Result<Self> {
+        let context = Pkcs11::new(module)
+            .with_context(|| format!("Failed to load PKCS#11 module {}", module.display()))?;
+        context.initialize(CInitializeArgs::OsThreads)?;
+
+        for slot in context.get_slots_with_token()? {
+            let session = context.open_ro_session(slot)?;
+            session.login(UserType::User, Some(&AuthPin::new(pin.to_string())))
+                .with_context(|| format!("Failed to log in to token in slot {}", slot.id()))?;
+
+            let Some(private_key) = find_key(&session, ObjectClass::PRIVATE_KEY, key_label)? else {
+                continue;
+            };
+            let public_key = find_key(&session, ObjectClass::PUBLIC_KEY, key_label)?
+                .with_context(|| format!("Key '{}' has no public key on the token", key_label))?;
+            let verifying_key = read_verifying_key(&session, public_key)?;
+
+            return Ok(Self { session, private_key, verifying_key, _context: context });
+        }
+        bail!("No token has a key labelled '{}'", key_label)
+    }
+}
+
+impl Signer for Pkcs11Signer {
+    fn verifying_key(&self) -> VerifyingKey {
+        self.verifying_key
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Signature> {
+        let signature = self.session.sign(&Mechanism::Eddsa, self.private_key, message)
+            .context("Token refused to sign")?;
+        Ok(Signature::from_bytes(
+            &signature.try_into()
+                .map_err(|_| anyhow::anyhow!("Token returned an invalid signature size"))?
+        ))
+    }
+}
+
+fn find_key(session: &Session, class: ObjectClass, label: &str) -> Result<Option<ObjectHandle>> {
+    let template = [Attribute::Class(class), Attribute::Label(label.as_bytes().to_vec())];
+    Ok(session.find_objects(&template)?.into_iter().next())
+}
+
+/// Read an ed25519 public key from its `CKA_EC_POINT`
+///
+/// Tokens disagree on whether the point is DER-wrapped in an OCTET STRING,
+/// so both forms are accepted.
+fn read_verifying_key(session: &Session, public_key: ObjectHandle) -> Result<VerifyingKey> {
+    let point = session.get_attributes(public_key, &[AttributeType::EcPoint])?
+        .into_iter()
+        .find_map(|attribute| match attribute {
+            Attribute::EcPoint(point) => Some(point),
+            _ => None,
+        })
+        .context("Public key has no EC point")?;
+
+    let raw = match point.as_slice() {
+        [0x04, 0x20, raw @ ..] if raw.len() == 32 => raw,
+        raw => raw,
+    };
+    Ok(VerifyingKey::from_bytes(
+        raw.try_into().map_err(|_| anyhow::anyhow!("Public key is not an ed25519 key"))?
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{sign_directory, verify_manifest, HashPolicy, SignaturePurpose};
+    use tempfile::tempdir;
+
+    /// DER encoding of the Ed25519 curve OID, 1.3.101.112
+    const ED25519_PARAMS: [u8; 5] = [0x06, 0x03, 0x2b, 0x65, 0x70];
+
+    const LABEL: &str = "hecate-ci";
+    const PIN: &str = "123456";
+
+    /// Set up a fresh SoftHSM token holding an ed25519 key pair
+    fn init_softhsm(module: &Path, dir: &Path) {
+        let tokens = dir.join("tokens");
+        std::fs::create_dir(&tokens).unwrap();
+        let conf = dir.join("softhsm2.conf");
+        std::fs::write(&conf, format!("directories.tokendir = {}\n", tokens.display())).unwrap();
+        std::env::set_var("SOFTHSM2_CONF", &conf);
+
+        let context = Pkcs11::new(module).unwrap();
+        context.initialize(CInitializeArgs::OsThreads).unwrap();
+        let slot = context.get_slots_with_token().unwrap()[0];
+        let so_pin = AuthPin::new("so-pin".to_string());
+        context.init_token(slot, &so_pin, "hecate-test").unwrap();
+
+        let session = context.open_rw_session(slot).unwrap();
+        session.login(UserType::So, Some(&so_pin)).unwrap();
+        session.init_pin(&AuthPin::new(PIN.to_string())).unwrap();
+        session.logout().unwrap();
+        session.login(UserType::User, Some(&AuthPin::new(PIN.to_string()))).unwrap();
+
+        let label = Attribute::Label(LABEL.as_bytes().to_vec());
+        session.generate_key_pair(
+            &Mechanism::EccEdwardsKeyPairGen,
+            &[Attribute::Token(true), Attribute::Verify(true), Attribute::EcParams(ED25519_PARAMS.to_vec()), label.clone()],
+            &[Attribute::Token(true), Attribute::Private(true), Attribute::Sensitive(true), Attribute::Sign(true), label],
+        ).unwrap();
+    }
+
+    /// Runs in CI, which installs SoftHSM and points
+    /// `HECATE_TEST_PKCS11_MODULE` at it; skipped elsewhere
+    #[test]
+    fn test_sign_with_softhsm() {
+        let Some(module) = std::env::var_os("HECATE_TEST_PKCS11_MODULE") else {
+            eprintln!("HECATE_TEST_PKCS11_MODULE not set, skipping");
+            return;
+        };
+        let dir = tempdir().unwrap();
+        init_softhsm(Path::new(&module), dir.path());
+
+        let signer = Pkcs11Signer::open(Path::new(&module), LABEL, PIN).unwrap();
+        let files = dir.path().join("files");
+        std::fs::create_dir(&files).unwrap();
+        std::fs::write(files.join("file1.txt"), b"content1").unwrap();
+
+        let manifest = sign_directory(
+            &files, &signer, "CI".into(), SignaturePurpose::Package, HashPolicy::default(),
+        ).unwrap();
+        assert_eq!(manifest.signer.key_id, signer.key_id());
+        assert!(verify_manifest(&manifest, &files).unwrap());
+
+        // The module can only be initialized once at a time
+        drop(signer);
+        assert!(Pkcs11Signer::open(Path::new(&module), "no-such-key", PIN).is_err());
+    }
+}
@@ -0,0 +1,184 @@
+//! Configuration file handling
+//!
+//! `UpdateConfig` is kept as TOML, by default in `/etc/hecate/update.toml`.
+//! A missing file means the defaults.
+
+use anyhow::{Result, Context};
+use crate::reboot::RebootPolicy;
+use crate::scheduler::UpdateScheduler;
+use crate::UpdateConfig;
+use std::path::{Path, PathBuf};
+
+/// Default location of the configuration file
+pub const CONFIG_PATH: &str = "/etc/hecate/update.toml";
+
+/// Keys accepted by [`UpdateConfig::set`]
+pub const CONFIG_KEYS: &[&str] = &[
+    "update_server",
+    "cache_dir",
+    "backup_dir",
+    "enable_live_patching",
+    "enable_hot_swapping",
+    "auto_rollback",
+    "rollback_timeout",
+    "connect_timeout",
+    "request_timeout",
+    "schedule_updates",
+    "maintenance_window.days",
+    "maintenance_window.start_hour",
+    "maintenance_window.end_hour",
+    "maintenance_window.timezone",
+    "max_parallel_downloads",
+    "max_download_bytes_per_sec",
+    "verify_signatures",
+    "snapshot_backend",
+    "snapshot_paths",
+    "reboot_policy",
+    "hot_swap_fallback",
+];
+
+impl UpdateConfig {
+    /// Read the configuration at `path`, or the defaults if there is none
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let config: Self = toml::from_str(&content)
+            .with_context(|| format!("Invalid configuration in {}", path.display()))?;
+        config.validate()
+            .with_context(|| format!("Invalid configuration in {}", path.display()))?;
+        Ok(config)
+    }
+
+    /// Write the configuration to `path` atomically
+    pub fn save(&self, path: &Path) -> Result<()> {
+        self.validate()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let tmp = path.with_extension("toml.tmp");
+        std::fs::write(&tmp, self.to_toml()?)?;
+        std::fs::rename(&tmp, path)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// The configuration as written to the configuration file
+    pub fn to_toml(&self) -> Result<String> {
+        Ok(toml::to_string_pretty(self)?)
+    }
+
+    /// Check values the type system can't: window hours and timezone,
+    /// download limits, timeouts and the snapshot backend
+    pub fn validate(&self) -> Result<()> {
+        UpdateScheduler::new(self.maintenance_window.clone())?;
+
+        if self.max_parallel_downloads == 0 {
+            return Err(anyhow::anyhow!("max_parallel_downloads must be at least 1"));
+        }
+        if self.connect_timeout.is_zero() || self.request_timeout.is_zero() {
+            return Err(anyhow::anyhow!("connect_timeout and request_timeout must be at least 1 second"));
+        }
+        if !["auto", "file", "btrfs", "zfs"].contains(&self.snapshot_backend.as_str()) {
+            return Err(anyhow::anyhow!(
+                "Unknown snapshot backend '{}', expected auto, file, btrfs or zfs", self.snapshot_backend
+            ));
+        }
+        Ok(())
+    }
+
+    /// Set `key` (one of [`CONFIG_KEYS`]) from its command-line form
+    ///
+    /// Lists are comma-separated and timeouts are in seconds. The
+    /// configuration is left untouched if the result would be invalid.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let mut updated = self.clone();
+        let window = &mut updated.maintenance_window;
+
+        match key {
+            "update_server" => updated.update_server = value.to_string(),
+            "cache_dir" => updated.cache_dir = PathBuf::from(value),
+            "backup_dir" => updated.backup_dir = PathBuf::from(value),
+            "enable_live_patching" => updated.enable_live_patching = parse(key, value)?,
+            "enable_hot_swapping" => updated.enable_hot_swapping = parse(key, value)?,
+            "auto_rollback" => updated.auto_rollback = parse(key, value)?,
+            "rollback_timeout" => {
+                updated.rollback_timeout = std::time::Duration::from_secs(parse(key, value)?);
+            }
+            "connect_timeout" => {
+                updated.connect_timeout = std::time::Duration::from_secs(parse(key, value)?);
+            }
+            "request_timeout" => {
+                updated.request_timeout = std::time::Duration::from_secs(parse(key, value)?);
+            }
+            "schedule_updates" => updated.schedule_updates = parse(key, value)?,
+            "maintenance_window.days" => window.days = parse_days(value)?,
+            "maintenance_window.start_hour" => window.start_hour = parse(key, value)?,
+            "maintenance_window.end_hour" => window.end_hour = parse(key, value)?,
+            "maintenance_window.timezone" => window.timezone = value.to_string(),
+            "max_parallel_downloads" => updated.max_parallel_downloads = parse(key, value)?,
+            "max_download_bytes_per_sec" => updated.max_download_bytes_per_sec = parse(key, value)?,
+            "verify_signatures" => updated.verify_signatures = parse(key, value)?,
+            "snapshot_backend" => updated.snapshot_backend = value.to_string(),
+            "snapshot_paths" => {
+                updated.snapshot_paths = split_list(value).map(PathBuf::from).collect();
+            }
+            "reboot_policy" => updated.reboot_policy = parse_reboot_policy(value)?,
+            "hot_swap_fallback" => updated.hot_swap_fallback = parse(key, value)?,
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Unknown configuration key '{}', expected one of: {}", key, CONFIG_KEYS.join(", ")
+                ));
+            }
+        }
+
+        updated.validate()?;
+        *self = updated;
+        Ok(())
+    }
+
+    /// Replace the maintenance window, keeping its timezone
+    pub fn set_maintenance_window(&mut self, days: &str, start_hour: u32, end_hour: u32) -> Result<()> {
+        let mut updated = self.clone();
+        updated.maintenance_window.days = parse_days(days)?;
+        updated.maintenance_window.start_hour = start_hour;
+        updated.maintenance_window.end_hour = end_hour;
+
+        updated.validate()?;
+        *self = updated;
+        Ok(())
+    }
+}
+
+fn parse<T: std::str::FromStr>(key: &str, value: &str) -> Result<T>
+where
+    T::Err: std::fmt::Display,
+{
+    value.parse()
+        .map_err(|e| anyhow::anyhow!("Invalid value '{}' for {}: {}", value, key, e))
+}
+
+fn split_list(value: &str) -> impl Iterator<Item = &str> {
+    value.split(',').map(str::trim).filter(|item| !item.is_empty())
+}
+
+fn parse_days(value: &str) -> Result<Vec<chrono::Weekday>> {
+    split_list(value)
+        .map(|day| day.parse().map_err(|_| anyhow::anyhow!("Invalid day of week '{}'", day)))
+        .collect()
+}
+
+fn parse_reboot_policy(value: &str) -> Result<RebootPolicy> {
+    match value.to_ascii_lowercase().replace(['-', '_'], "").as_str() {
+        "immediate" => Ok(RebootPolicy::Immediate),
+        "atwindowend" => Ok(RebootPolicy::AtWindowEnd),
+        "notify" => Ok(RebootPolicy::Notify),
+        "never" => Ok(RebootPolicy::Never),
+        _ => Err(anyhow::anyhow!(
+            "Invalid reboot policy '{}', expected immediate, at-window-end, notify or never", value
+        )),
+    }
+}
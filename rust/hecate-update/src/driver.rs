@@ -2,12 +2,82 @@
 //!
 //! Handles driver updates and hot-swapping
 
-use anyhow::Result;
-use crate::UpdateInfo;
+use anyhow::{Result, Context};
+use crate::{UpdateInfo, UpdateType};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Errors from hot-swapping a driver
+#[derive(Debug, thiserror::Error)]
+pub enum HotSwapError {
+    /// Unloading the module now would pull it from under its users
+    #[error("Module {module} is in use ({usage}); it can't be hot-swapped")]
+    ModuleInUse {
+        module: String,
+        usage: ModuleUsage,
+    },
+}
+
+/// What is keeping a kernel module loaded, from sysfs
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModuleUsage {
+    /// Reference count from `/sys/module/<name>/refcnt`
+    pub refcount: u32,
+    /// Modules that depend on this one
+    pub holders: Vec<String>,
+    /// Devices bound to the module's drivers, as `<bus>:<driver>/<device>`
+    pub devices: Vec<String>,
+}
+
+impl ModuleUsage {
+    /// Whether unloading the module would break something
+    pub fn is_in_use(&self) -> bool {
+        self.refcount > 0 || !self.holders.is_empty() || !self.devices.is_empty()
+    }
+}
+
+impl std::fmt::Display for ModuleUsage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "refcount {}", self.refcount)?;
+        if !self.holders.is_empty() {
+            write!(f, ", held by {}", self.holders.join(", "))?;
+        }
+        if !self.devices.is_empty() {
+            write!(f, ", bound to {}", self.devices.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse the contents of a module's `refcnt` file
+pub fn parse_refcnt(content: &str) -> Result<u32> {
+    content.trim().parse()
+        .map_err(|_| anyhow::anyhow!("Invalid module refcount '{}'", content.trim()))
+}
+
+/// Sorted names of the entries in `dir`, or none if it doesn't exist
+fn dir_entries(dir: &Path, symlinks_only: bool) -> Result<Vec<String>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+    };
+
+    let mut names = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if symlinks_only && !entry.file_type()?.is_symlink() {
+            continue;
+        }
+        names.push(entry.file_name().to_string_lossy().into_owned());
+    }
+    names.sort();
+    Ok(names)
+}
 
 pub struct DriverManager {
     loaded_drivers: HashMap<String, String>,
+    sysfs_root: PathBuf,
 }
 
 impl DriverManager {
@@ -27,15 +97,87 @@ impl DriverManager {
             }
         }
         
-        Ok(Self { loaded_drivers })
+        Ok(Self { loaded_drivers, sysfs_root: PathBuf::from("/sys") })
     }
 
-    pub async fn check_updates(&self, server: &str) -> Result<Vec<UpdateInfo>> {
-        // TODO: Check for driver updates from server
-        Ok(Vec::new())
+    /// Read module state from `root` instead of `/sys`
+    pub fn with_sysfs_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.sysfs_root = root.into();
+        self
+    }
+
+    /// What is using a loaded module, or `None` if it isn't loaded
+    ///
+    /// Devices are found through the module's `drivers` links, where each
+    /// bound device shows up as a symlink in the driver's directory.
+    pub fn module_usage(&self, module: &str) -> Result<Option<ModuleUsage>> {
+        let module_dir = self.sysfs_root.join("module").join(module);
+        if !module_dir.exists() {
+            return Ok(None);
+        }
+
+        // Built-in modules have no refcount and can't be unloaded anyway
+        let refcnt_path = module_dir.join("refcnt");
+        let refcount = match std::fs::read_to_string(&refcnt_path) {
+            Ok(content) => parse_refcnt(&content)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(anyhow::anyhow!("Module {} is built into the kernel", module));
+            }
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read {}", refcnt_path.display()));
+            }
+        };
+
+        let mut devices = Vec::new();
+        for driver in dir_entries(&module_dir.join("drivers"), false)? {
+            let driver_dir = module_dir.join("drivers").join(&driver);
+            for device in dir_entries(&driver_dir, true)? {
+                // Every driver links back to its module; that's not a device
+                if device != "module" {
+                    devices.push(format!("{}/{}", driver, device));
+                }
+            }
+        }
+
+        Ok(Some(ModuleUsage {
+            refcount,
+            holders: dir_entries(&module_dir.join("holders"), false)?,
+            devices,
+        }))
+    }
+
+    /// Check a driver can be unloaded before hot-swapping it
+    ///
+    /// A module that isn't loaded has nothing to unload and passes.
+    pub fn preflight_hot_swap(&self, update: &UpdateInfo) -> Result<()> {
+        let UpdateType::Driver { name, .. } = &update.update_type else {
+            return Err(anyhow::anyhow!("Update {} is not a driver update", update.id));
+        };
+
+        if let Some(usage) = self.module_usage(name)? {
+            if usage.is_in_use() {
+                return Err(HotSwapError::ModuleInUse { module: name.clone(), usage }.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Driver updates published by `server`
+    pub async fn check_updates(
+        &self,
+        client: &reqwest::Client,
+        server: &str,
+        timeout: std::time::Duration,
+    ) -> Result<Vec<UpdateInfo>> {
+        let updates = crate::fetch_update_list(client, server, "drivers/updates.json", timeout).await?;
+        Ok(updates.into_iter()
+            .filter(|update| matches!(update.update_type, UpdateType::Driver { .. }))
+            .collect())
     }
 
     pub async fn hot_swap(&self, update: &UpdateInfo) -> Result<()> {
+        self.preflight_hot_swap(update)?;
+
         tracing::info!("Hot-swapping driver: {}", update.id);
         // TODO: Unload old driver and load new one
         Ok(())
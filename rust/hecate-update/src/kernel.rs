@@ -3,7 +3,69 @@
 //! Handles live kernel patching and kernel updates
 
 use anyhow::Result;
-use crate::UpdateInfo;
+use crate::{UpdateInfo, UpdateType};
+
+/// Errors from applying a live patch
+#[derive(Debug, thiserror::Error)]
+pub enum LivePatchError {
+    /// The patch was built for a different kernel than the running one
+    #[error("Live patch {patch} targets kernel {target}, but {running} is running")]
+    Incompatible {
+        patch: String,
+        target: String,
+        running: String,
+    },
+}
+
+/// A kernel release as printed by `uname -r`, e.g. `6.8.0-45-generic`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KernelRelease {
+    /// Upstream version, e.g. `6.8.0`
+    pub version: String,
+    /// Distribution ABI number or build, e.g. `45`; none for vanilla kernels
+    pub abi: Option<String>,
+}
+
+impl KernelRelease {
+    /// Split a release string into its upstream version and ABI parts
+    pub fn parse(release: &str) -> Self {
+        let release = release.trim();
+        match release.split_once('-') {
+            Some((version, rest)) => Self {
+                version: version.to_string(),
+                abi: rest.split(['-', '.']).next()
+                    .filter(|abi| !abi.is_empty())
+                    .map(str::to_string),
+            },
+            None => Self { version: release.to_string(), abi: None },
+        }
+    }
+
+    /// Whether a patch for kernel `version` at ABI `patch_level` fits this kernel
+    ///
+    /// Versions compare numerically, so `6.8` and `6.8.0` are the same
+    /// kernel. An empty patch level only matches a kernel without an ABI.
+    pub fn matches(&self, version: &str, patch_level: &str) -> bool {
+        let patch_abi = Some(patch_level.trim()).filter(|abi| !abi.is_empty());
+        let same_version = match (version_parts(&self.version), version_parts(version.trim())) {
+            (Some(running), Some(target)) => running == target,
+            _ => false,
+        };
+        same_version && self.abi.as_deref() == patch_abi
+    }
+}
+
+/// Numeric components of a kernel version with trailing zeros dropped,
+/// or `None` if it isn't purely numeric
+fn version_parts(version: &str) -> Option<Vec<u64>> {
+    let mut parts = version.split('.')
+        .map(|part| part.parse().ok())
+        .collect::<Option<Vec<u64>>>()?;
+    while parts.len() > 1 && parts.last() == Some(&0) {
+        parts.pop();
+    }
+    Some(parts)
+}
 
 pub struct KernelPatchManager {
     current_version: String,
@@ -11,21 +73,65 @@ pub struct KernelPatchManager {
 
 impl KernelPatchManager {
     pub fn new() -> Result<Self> {
-        // Get current kernel version
-        let version = std::fs::read_to_string("/proc/version")
+        // Same string as `uname -r`
+        let version = std::fs::read_to_string("/proc/sys/kernel/osrelease")
+            .map(|release| release.trim().to_string())
             .unwrap_or_else(|_| "Unknown".to_string());
         
-        Ok(Self {
-            current_version: version,
+        Ok(Self::with_release(version))
+    }
+
+    /// Manager for a kernel reporting `release`, rather than the running one
+    pub fn with_release(release: impl Into<String>) -> Self {
+        Self {
+            current_version: release.into(),
+        }
+    }
+
+    /// Release of the kernel patches are checked against
+    pub fn running_release(&self) -> &str {
+        &self.current_version
+    }
+
+    /// Refuse a live patch built for a kernel other than the running one
+    pub fn check_compatible(&self, update: &UpdateInfo) -> Result<(), LivePatchError> {
+        let UpdateType::KernelPatch { version, patch_level, .. } = &update.update_type else {
+            return Ok(());
+        };
+
+        if KernelRelease::parse(&self.current_version).matches(version, patch_level) {
+            return Ok(());
+        }
+
+        let target = if patch_level.is_empty() {
+            version.clone()
+        } else {
+            format!("{}-{}", version, patch_level)
+        };
+        Err(LivePatchError::Incompatible {
+            patch: update.id.clone(),
+            target,
+            running: self.current_version.clone(),
         })
     }
 
-    pub async fn check_updates(&self, server: &str) -> Result<Vec<UpdateInfo>> {
-        // TODO: Check for kernel updates from server
-        Ok(Vec::new())
+    /// Kernel updates published by `server`
+    pub async fn check_updates(
+        &self,
+        client: &reqwest::Client,
+        server: &str,
+        timeout: std::time::Duration,
+    ) -> Result<Vec<UpdateInfo>> {
+        let updates = crate::fetch_update_list(client, server, "kernel/updates.json", timeout).await?;
+        Ok(updates.into_iter()
+            .filter(|update| matches!(update.update_type, UpdateType::KernelPatch { .. }))
+            .collect())
     }
 
     pub async fn apply_live_patch(&self, update: &UpdateInfo) -> Result<()> {
+        // Loading a patch into the wrong kernel can take the system down
+        self.check_compatible(update)?;
+
         tracing::info!("Applying live kernel patch: {}", update.id);
         // TODO: Apply kernel live patch using kpatch or similar
         Ok(())
@@ -11,10 +11,13 @@ use semver::Version;
 use chrono::{DateTime, Utc, Local};
 use async_trait::async_trait;
 
+pub mod config;
 pub mod kernel;
 pub mod driver;
 pub mod rollback;
+pub mod packages;
 pub mod scheduler;
+pub mod reboot;
 pub mod snapshot;
 
 // ============================================================================
@@ -51,13 +54,20 @@ pub enum UpdateType {
     },
 }
 
-/// Security severity levels
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Ord, PartialOrd, Eq)]
+/// Security severity levels, ordered from least to most severe
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Ord, PartialOrd, Eq, clap::ValueEnum)]
 pub enum SecuritySeverity {
-    Critical,
-    High,
-    Medium,
     Low,
+    Medium,
+    High,
+    Critical,
+}
+
+/// When a security update is applied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecurityAction {
+    ApplyNow,
+    ScheduleAt(DateTime<Utc>),
 }
 
 /// Update metadata
@@ -76,6 +86,29 @@ pub struct UpdateInfo {
     pub changelog: Option<String>,
 }
 
+/// Errors from talking to the update server
+#[derive(Debug, thiserror::Error)]
+pub enum ServerError {
+    /// No connection could be made before `connect_timeout`
+    #[error("Could not connect to update server {server} within {}s", .timeout.as_secs_f64())]
+    ConnectTimeout {
+        server: String,
+        timeout: std::time::Duration,
+    },
+    /// The server did not answer before `request_timeout`
+    #[error("Update server {server} did not respond within {}s", .timeout.as_secs_f64())]
+    RequestTimeout {
+        server: String,
+        timeout: std::time::Duration,
+    },
+    /// The request failed before an answer, e.g. with no route to the server
+    #[error("Could not reach update server {server}: {reason}")]
+    Unreachable {
+        server: String,
+        reason: String,
+    },
+}
+
 /// Update checksum for verification
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateChecksum {
@@ -97,6 +130,33 @@ pub enum UpdateStatus {
     RolledBack,
 }
 
+/// Progress event emitted while applying updates
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum UpdateEvent {
+    Started { id: String },
+    /// Download progress of an update's payload, from 0.0 to 1.0
+    Progress { id: String, fraction: f32 },
+    Succeeded { id: String },
+    Failed { id: String, error: String },
+    RollbackStarted,
+}
+
+/// Result of [`UpdateManager::check_updates`]
+#[derive(Debug, Default)]
+pub struct UpdateCheck {
+    pub updates: Vec<UpdateInfo>,
+    /// Sources that could not be checked; their updates are missing
+    pub failures: Vec<SourceFailure>,
+}
+
+/// An update source that could not be checked
+#[derive(Debug)]
+pub struct SourceFailure {
+    /// `kernel` or `driver`
+    pub source: &'static str,
+    pub error: anyhow::Error,
+}
+
 /// System update plan
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdatePlan {
@@ -108,6 +168,24 @@ pub struct UpdatePlan {
     pub auto_rollback: bool,
 }
 
+/// Snapshot of the update system's state, as shown by `hecate-update status`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SystemStatus {
+    pub live_patching: bool,
+    pub hot_swapping: bool,
+    pub auto_rollback: bool,
+    pub reboot_policy: reboot::RebootPolicy,
+    pub reboot_pending: bool,
+    /// Snapshot protecting an update run in progress
+    pub active_snapshot: Option<String>,
+    /// Updates applied but waiting for a reboot or a maintenance window
+    pub pending_updates: usize,
+    /// Start of the next maintenance window, in UTC
+    pub next_window: DateTime<Utc>,
+    /// When updates were last checked for, if ever
+    pub last_check: Option<DateTime<Utc>>,
+}
+
 // ============================================================================
 // UPDATE MANAGER
 // ============================================================================
@@ -119,6 +197,11 @@ pub struct UpdateManager {
     driver_manager: driver::DriverManager,
     rollback_manager: rollback::RollbackManager,
     scheduler: scheduler::UpdateScheduler,
+    reboot: reboot::RebootScheduler,
+    packages: Option<Box<dyn packages::PackageSource>>,
+    client: reqwest::Client,
+    limiter: std::sync::Arc<hecate_pkg::RateLimiter>,
+    events: Option<tokio::sync::mpsc::Sender<UpdateEvent>>,
     state: UpdateState,
 }
 
@@ -132,10 +215,53 @@ pub struct UpdateConfig {
     pub enable_hot_swapping: bool,
     pub auto_rollback: bool,
     pub rollback_timeout: std::time::Duration,
+    /// How long to wait for a connection to a server
+    #[serde(default = "default_connect_timeout")]
+    pub connect_timeout: std::time::Duration,
+    /// How long a request to the update server for its update lists may
+    /// take; payload downloads are not bounded by it
+    #[serde(default = "default_request_timeout")]
+    pub request_timeout: std::time::Duration,
     pub schedule_updates: bool,
     pub maintenance_window: MaintenanceWindow,
     pub max_parallel_downloads: usize,
+    /// Combined download rate limit in bytes per second; 0 means unlimited
+    #[serde(default)]
+    pub max_download_bytes_per_sec: u64,
     pub verify_signatures: bool,
+    /// Snapshot backend: "auto", "file", "btrfs" or "zfs"
+    #[serde(default = "default_snapshot_backend")]
+    pub snapshot_backend: String,
+    /// Paths copied by the file snapshot backend
+    #[serde(default = "default_snapshot_paths")]
+    pub snapshot_paths: Vec<PathBuf>,
+    /// What to do after updates that require a reboot
+    #[serde(default)]
+    pub reboot_policy: reboot::RebootPolicy,
+    /// Install a driver for the next boot when its module is in use,
+    /// instead of failing the hot-swap
+    #[serde(default = "default_true")]
+    pub hot_swap_fallback: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_connect_timeout() -> std::time::Duration {
+    std::time::Duration::from_secs(10)
+}
+
+fn default_request_timeout() -> std::time::Duration {
+    std::time::Duration::from_secs(300)
+}
+
+fn default_snapshot_backend() -> String {
+    "auto".to_string()
+}
+
+fn default_snapshot_paths() -> Vec<PathBuf> {
+    snapshot::DEFAULT_SNAPSHOT_PATHS.iter().map(PathBuf::from).collect()
 }
 
 /// Maintenance window for scheduled updates
@@ -157,6 +283,8 @@ impl Default for UpdateConfig {
             enable_hot_swapping: true,
             auto_rollback: true,
             rollback_timeout: std::time::Duration::from_secs(300),
+            connect_timeout: default_connect_timeout(),
+            request_timeout: default_request_timeout(),
             schedule_updates: false,
             maintenance_window: MaintenanceWindow {
                 days: vec![chrono::Weekday::Sun, chrono::Weekday::Wed],
@@ -165,11 +293,53 @@ impl Default for UpdateConfig {
                 timezone: "UTC".to_string(),
             },
             max_parallel_downloads: 4,
+            max_download_bytes_per_sec: 0,
             verify_signatures: true,
+            snapshot_backend: default_snapshot_backend(),
+            snapshot_paths: default_snapshot_paths(),
+            reboot_policy: reboot::RebootPolicy::default(),
+            hot_swap_fallback: true,
         }
     }
 }
 
+/// Fetch the list of updates the server publishes at `path`
+///
+/// A server without the list has no updates of that kind. The request,
+/// body included, fails after `timeout`.
+pub(crate) async fn fetch_update_list(
+    client: &reqwest::Client,
+    server: &str,
+    path: &str,
+    timeout: std::time::Duration,
+) -> Result<Vec<UpdateInfo>> {
+    let url = format!("{}/{}", server.trim_end_matches('/'), path);
+    let response = client.get(&url).timeout(timeout).send().await?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(Vec::new());
+    }
+
+    response.error_for_status()?
+        .json().await
+        .with_context(|| format!("Invalid update list at {}", url))
+}
+
+/// File in the cache directory holding the time of the last update check
+const LAST_CHECK_FILE: &str = "last-check";
+
+/// File in the cache directory holding the state that outlives a run
+const STATE_FILE: &str = "state.json";
+
+/// The part of [`UpdateState`] kept across runs, so `status` and `rollback`
+/// in a new process see what an earlier run left behind
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PersistedState {
+    #[serde(default)]
+    pending_updates: Vec<String>,
+    #[serde(default)]
+    active_snapshot: Option<String>,
+}
+
 /// Internal update state
 struct UpdateState {
     available_updates: HashMap<String, UpdateInfo>,
@@ -187,14 +357,34 @@ impl UpdateManager {
 
         let kernel_manager = kernel::KernelPatchManager::new()?;
         let driver_manager = driver::DriverManager::new()?;
-        let rollback_manager = rollback::RollbackManager::new(&config.backup_dir)?;
+        let backend = snapshot::backend_from_config(&config).await?;
+        let rollback_manager = rollback::RollbackManager::new(&config.backup_dir, backend)?;
+        // Updates applied by earlier runs satisfy dependencies too
+        let installed_updates = installed_updates(&rollback_manager.get_history(None).await?);
         let scheduler = scheduler::UpdateScheduler::new(config.maintenance_window.clone())?;
-
+        let reboot = reboot::RebootScheduler::new(
+            std::sync::Arc::new(reboot::SystemCommandRunner),
+            &config.cache_dir.join("reboot-required"),
+        );
+
+        let client = reqwest::Client::builder()
+            .user_agent("hecate-update/0.1.0")
+            .connect_timeout(config.connect_timeout)
+            .build()?;
+        let limiter = std::sync::Arc::new(hecate_pkg::RateLimiter::new(config.max_download_bytes_per_sec));
+
+        let state_path = config.cache_dir.join(STATE_FILE);
+        let persisted: PersistedState = match std::fs::read_to_string(&state_path) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid update state in {}", state_path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => PersistedState::default(),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", state_path.display())),
+        };
         let state = UpdateState {
             available_updates: HashMap::new(),
-            installed_updates: HashSet::new(),
-            pending_updates: Vec::new(),
-            active_snapshot: None,
+            installed_updates,
+            pending_updates: persisted.pending_updates,
+            active_snapshot: persisted.active_snapshot,
         };
 
         Ok(Self {
@@ -203,23 +393,61 @@ impl UpdateManager {
             driver_manager,
             rollback_manager,
             scheduler,
+            reboot,
+            packages: None,
+            client,
+            limiter,
+            events: None,
             state,
         })
     }
 
+    /// Run reboot commands through `runner` instead of on the host
+    pub fn with_command_runner(mut self, runner: std::sync::Arc<dyn reboot::CommandRunner>) -> Self {
+        self.reboot = reboot::RebootScheduler::new(runner, &self.config.cache_dir.join("reboot-required"));
+        self
+    }
+
+    /// Check and apply package updates through `source`
+    pub fn with_package_source(mut self, source: Box<dyn packages::PackageSource>) -> Self {
+        self.packages = Some(source);
+        self
+    }
+
+    /// Send progress events to `sender`, or stop sending them with `None`
+    ///
+    /// Events that don't fit in the channel are dropped.
+    pub fn set_event_sender(&mut self, sender: Option<tokio::sync::mpsc::Sender<UpdateEvent>>) {
+        self.events = sender;
+    }
+
+    fn emit(&self, event: UpdateEvent) {
+        if let Some(events) = &self.events {
+            // Drop events rather than stall updates behind a slow subscriber;
+            // a closed receiver only means nobody is listening any more
+            let _ = events.try_send(event);
+        }
+    }
+
     /// Check for available updates
-    pub async fn check_updates(&mut self) -> Result<Vec<UpdateInfo>> {
+    ///
+    /// An unreachable update server only costs its own sources: they are
+    /// returned in [`UpdateCheck::failures`] alongside the updates found
+    /// elsewhere.
+    pub async fn check_updates(&mut self) -> Result<UpdateCheck> {
         tracing::info!("Checking for system updates...");
 
         let mut all_updates = Vec::new();
+        let mut failures = Vec::new();
 
-        // Check kernel updates
-        let kernel_updates = self.check_kernel_updates().await?;
-        all_updates.extend(kernel_updates);
-
-        // Check driver updates
-        let driver_updates = self.check_driver_updates().await?;
-        all_updates.extend(driver_updates);
+        match self.check_kernel_updates().await {
+            Ok(kernel_updates) => all_updates.extend(kernel_updates),
+            Err(error) => failures.push(SourceFailure { source: "kernel", error }),
+        }
+        match self.check_driver_updates().await {
+            Ok(driver_updates) => all_updates.extend(driver_updates),
+            Err(error) => failures.push(SourceFailure { source: "driver", error }),
+        }
 
         // Check package updates
         let package_updates = self.check_package_updates().await?;
@@ -234,8 +462,13 @@ impl UpdateManager {
             self.state.available_updates.insert(update.id.clone(), update.clone());
         }
 
+        // Remembered across runs so `status` can report it
+        let path = self.config.cache_dir.join(LAST_CHECK_FILE);
+        std::fs::write(&path, Utc::now().to_rfc3339())
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+
         tracing::info!("Found {} available updates", all_updates.len());
-        Ok(all_updates)
+        Ok(UpdateCheck { updates: all_updates, failures })
     }
 
     /// Create an update plan
@@ -283,8 +516,11 @@ impl UpdateManager {
 
         // Create snapshot if requested
         if plan.snapshot_before {
-            let snapshot_id = self.create_snapshot().await?;
-            self.state.active_snapshot = Some(snapshot_id);
+            let snapshot = self.rollback_manager
+                .create_snapshot(Some("pre-update"), &plan.order)
+                .await?;
+            self.state.active_snapshot = Some(snapshot.id);
+            self.save_state()?;
         }
 
         // Apply updates in order
@@ -293,13 +529,41 @@ impl UpdateManager {
                 .find(|u| u.id == *update_id)
                 .ok_or_else(|| anyhow::anyhow!("Update {} not in plan", update_id))?;
 
-            match self.apply_single_update(update).await {
+            self.emit(UpdateEvent::Started { id: update_id.clone() });
+            let started = std::time::Instant::now();
+            let result = self.apply_single_update(update).await;
+            if let Err(e) = self.save_state() {
+                tracing::warn!("{:#}", e);
+            }
+            let entry = UpdateHistory {
+                id: update_id.clone(),
+                update_type: update.update_type.clone(),
+                timestamp: Utc::now(),
+                status: match &result {
+                    Ok(()) => UpdateStatus::Installed,
+                    Err(e) => UpdateStatus::Failed { error: format!("{:#}", e) },
+                },
+                duration: started.elapsed(),
+                rollback_available: self.state.active_snapshot.is_some(),
+            };
+            // The update itself is done either way; losing its history entry
+            // must not skip the success or failure handling below
+            if let Err(e) = self.rollback_manager.record_history(entry).await {
+                tracing::warn!("Failed to record {} in history: {:#}", update_id, e);
+            }
+
+            match result {
                 Ok(()) => {
                     tracing::info!("Successfully applied update: {}", update_id);
                     self.state.installed_updates.insert(update_id.clone());
+                    self.emit(UpdateEvent::Succeeded { id: update_id.clone() });
                 }
                 Err(e) => {
                     tracing::error!("Failed to apply update {}: {}", update_id, e);
+                    self.emit(UpdateEvent::Failed {
+                        id: update_id.clone(),
+                        error: format!("{:#}", e),
+                    });
                     
                     if plan.auto_rollback {
                         self.rollback().await?;
@@ -312,6 +576,7 @@ impl UpdateManager {
 
         // Clear active snapshot on success
         self.state.active_snapshot = None;
+        self.save_state()?;
 
         // Schedule reboot if needed
         if plan.requires_reboot {
@@ -333,9 +598,20 @@ impl UpdateManager {
                 }
             }
             UpdateType::Driver { name, hot_swappable, .. } => {
-                if self.config.enable_hot_swapping && *hot_swappable {
-                    self.driver_manager.hot_swap(update).await?;
+                let swapped = if self.config.enable_hot_swapping && *hot_swappable {
+                    match self.driver_manager.hot_swap(update).await {
+                        Ok(()) => true,
+                        Err(e) if self.config.hot_swap_fallback
+                            && e.downcast_ref::<driver::HotSwapError>().is_some() => {
+                            tracing::warn!("{}; installing {} for the next boot instead", e, name);
+                            false
+                        }
+                        Err(e) => return Err(e),
+                    }
                 } else {
+                    false
+                };
+                if !swapped {
                     self.driver_manager.prepare_update(update).await?;
                     self.state.pending_updates.push(update.id.clone());
                 }
@@ -361,19 +637,60 @@ impl UpdateManager {
 
     /// Rollback recent updates
     pub async fn rollback(&mut self) -> Result<()> {
-        tracing::warn!("Initiating rollback...");
+        match self.state.active_snapshot.clone() {
+            Some(snapshot_id) => self.rollback_to(&snapshot_id).await,
+            None => Err(anyhow::anyhow!("No active snapshot for rollback")),
+        }
+    }
+
+    /// Roll back to any recorded snapshot
+    ///
+    /// The updates the snapshot was taken before are recorded in the update
+    /// history as rolled back.
+    pub async fn rollback_to(&mut self, snapshot_id: &str) -> Result<()> {
+        let snapshot = self.rollback_manager.get_snapshot(snapshot_id).await?;
+
+        tracing::warn!("Initiating rollback to {}...", snapshot.id);
+        self.emit(UpdateEvent::RollbackStarted);
+        let started = std::time::Instant::now();
+        self.rollback_manager.rollback_to_snapshot(&snapshot.id).await?;
+
+        let history = self.rollback_manager.get_history(None).await?;
+        for update_id in &snapshot.updates {
+            let Some(applied) = history.iter().find(|h| &h.id == update_id) else {
+                continue;
+            };
+            let entry = UpdateHistory {
+                id: update_id.clone(),
+                update_type: applied.update_type.clone(),
+                timestamp: Utc::now(),
+                status: UpdateStatus::RolledBack,
+                duration: started.elapsed(),
+                rollback_available: false,
+            };
+            if let Err(e) = self.rollback_manager.record_history(entry).await {
+                tracing::warn!("Failed to record rollback of {} in history: {:#}", update_id, e);
+            }
+        }
 
-        if let Some(snapshot_id) = &self.state.active_snapshot {
-            self.rollback_manager.rollback_to_snapshot(snapshot_id).await?;
+        if self.state.active_snapshot.as_deref() == Some(snapshot_id) {
             self.state.active_snapshot = None;
-            tracing::info!("Rollback completed successfully");
-        } else {
-            return Err(anyhow::anyhow!("No active snapshot for rollback"));
         }
+        for update_id in &snapshot.updates {
+            self.state.installed_updates.remove(update_id);
+        }
+        self.state.pending_updates.retain(|id| !snapshot.updates.contains(id));
+        self.save_state()?;
 
+        tracing::info!("Rollback completed successfully");
         Ok(())
     }
 
+    /// Whether `update` is applied now or left for the next maintenance window
+    pub fn security_action(&self, update: &UpdateInfo) -> SecurityAction {
+        security_action(update, &self.scheduler, Utc::now())
+    }
+
     /// Schedule updates for maintenance window
     pub async fn schedule_updates(&mut self, update_ids: Vec<String>) -> Result<()> {
         let plan = self.create_plan(update_ids).await?;
@@ -381,27 +698,220 @@ impl UpdateManager {
         Ok(())
     }
 
-    /// Get update history
-    pub async fn get_history(&self) -> Result<Vec<UpdateHistory>> {
-        self.rollback_manager.get_history().await
+    /// Download an update's payload into the cache and verify its checksums
+    ///
+    /// Downloads share the manager's rate limit.
+    pub async fn download_update(&self, update: &UpdateInfo) -> Result<PathBuf> {
+        use futures::StreamExt;
+        use sha2::{Digest, Sha256};
+        use tokio::io::AsyncWriteExt;
+
+        let destination = self.config.cache_dir.join(&update.id);
+        let response = self.client.get(&update.download_url).send().await
+            .with_context(|| format!("Failed to download {}", update.id))?
+            .error_for_status()?;
+        let total = response.content_length().unwrap_or(update.size_bytes);
+
+        let mut file = tokio::fs::File::create(&destination).await?;
+        let mut received = 0u64;
+        let mut sha256 = Sha256::new();
+        let mut blake3 = blake3::Hasher::new();
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.context("Failed to read chunk")?;
+            self.limiter.acquire(chunk.len()).await;
+            sha256.update(&chunk);
+            blake3.update(&chunk);
+            file.write_all(&chunk).await?;
+
+            received += chunk.len() as u64;
+            if total > 0 {
+                self.emit(UpdateEvent::Progress {
+                    id: update.id.clone(),
+                    fraction: (received as f32 / total as f32).min(1.0),
+                });
+            }
+        }
+        file.flush().await?;
+
+        let sha256 = hex::encode(sha256.finalize());
+        let blake3 = hex::encode(blake3.finalize().as_bytes());
+        let mismatch = (!update.checksum.sha256.is_empty() && update.checksum.sha256 != sha256)
+            || (!update.checksum.blake3.is_empty() && update.checksum.blake3 != blake3);
+        if mismatch {
+            tokio::fs::remove_file(&destination).await.ok();
+            return Err(anyhow::anyhow!("Checksum mismatch for update {}", update.id));
+        }
+
+        Ok(destination)
+    }
+
+    /// Cancel a reboot scheduled after updates
+    pub fn cancel_reboot(&self) -> Result<()> {
+        self.reboot.cancel()
+    }
+
+    /// Whether applied updates are waiting for a reboot
+    pub fn reboot_pending(&self) -> bool {
+        self.reboot.reboot_pending()
+    }
+
+    /// Start of the next maintenance window
+    pub fn next_maintenance_window(&self) -> DateTime<Utc> {
+        self.scheduler.next_window()
+    }
+
+    /// Current state of the update system
+    pub fn status(&self) -> SystemStatus {
+        self.status_at(Utc::now())
+    }
+
+    /// [`UpdateManager::status`] with the next window counted from `now`
+    pub fn status_at(&self, now: DateTime<Utc>) -> SystemStatus {
+        let last_check = std::fs::read_to_string(self.config.cache_dir.join(LAST_CHECK_FILE))
+            .ok()
+            .and_then(|content| DateTime::parse_from_rfc3339(content.trim()).ok())
+            .map(|time| time.with_timezone(&Utc));
+
+        SystemStatus {
+            live_patching: self.config.enable_live_patching,
+            hot_swapping: self.config.enable_hot_swapping,
+            auto_rollback: self.config.auto_rollback,
+            reboot_policy: self.config.reboot_policy,
+            reboot_pending: self.reboot_pending(),
+            active_snapshot: self.state.active_snapshot.clone(),
+            pending_updates: self.state.pending_updates.len(),
+            next_window: self.scheduler.next_window_after(now),
+            last_check,
+        }
+    }
+
+    /// Current configuration
+    pub fn config(&self) -> &UpdateConfig {
+        &self.config
+    }
+
+    /// Applied, failed and rolled back updates, newest first, at most
+    /// `limit` of them
+    pub async fn get_history(&self, limit: Option<usize>) -> Result<Vec<UpdateHistory>> {
+        self.rollback_manager.get_history(limit).await
+    }
+
+    /// Create a snapshot outside of an update run
+    pub async fn create_snapshot(&mut self, name: Option<&str>) -> Result<rollback::SnapshotInfo> {
+        self.rollback_manager.create_snapshot(name, &[]).await
+    }
+
+    /// List recorded snapshots, oldest first
+    pub async fn list_snapshots(&self) -> Result<Vec<rollback::SnapshotInfo>> {
+        self.rollback_manager.list_snapshots().await
+    }
+
+    /// Get details of a single snapshot
+    pub async fn snapshot_info(&self, snapshot_id: &str) -> Result<rollback::SnapshotInfo> {
+        self.rollback_manager.get_snapshot(snapshot_id).await
+    }
+
+    /// Delete a snapshot and its backing data
+    pub async fn delete_snapshot(&mut self, snapshot_id: &str) -> Result<()> {
+        if self.state.active_snapshot.as_deref() == Some(snapshot_id) {
+            return Err(anyhow::anyhow!(
+                "Snapshot {} is protecting an update in progress", snapshot_id
+            ));
+        }
+        self.rollback_manager.delete_snapshot(snapshot_id).await
     }
 
     // ========================================================================
     // PRIVATE METHODS
     // ========================================================================
 
-    async fn check_kernel_updates(&self) -> Result<Vec<UpdateInfo>> {
-        self.kernel_manager.check_updates(&self.config.update_server).await
+    /// Write the pending updates and active snapshot to [`STATE_FILE`]
+    fn save_state(&self) -> Result<()> {
+        let persisted = PersistedState {
+            pending_updates: self.state.pending_updates.clone(),
+            active_snapshot: self.state.active_snapshot.clone(),
+        };
+        let path = self.config.cache_dir.join(STATE_FILE);
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_string_pretty(&persisted)?)
+            .and_then(|()| std::fs::rename(&tmp, &path))
+            .with_context(|| format!("Failed to write {}", path.display()))
     }
 
-    async fn check_driver_updates(&self) -> Result<Vec<UpdateInfo>> {
-        self.driver_manager.check_updates(&self.config.update_server).await
+    /// Kernel updates from the update server
+    ///
+    /// Fails with a [`ServerError`] if the server can't be reached in time.
+    pub async fn check_kernel_updates(&self) -> Result<Vec<UpdateInfo>> {
+        self.kernel_manager.check_updates(&self.client, &self.config.update_server, self.config.request_timeout).await
+            .map_err(|e| self.server_error(e))
+    }
+
+    /// Driver updates from the update server
+    ///
+    /// Fails with a [`ServerError`] if the server can't be reached in time.
+    pub async fn check_driver_updates(&self) -> Result<Vec<UpdateInfo>> {
+        self.driver_manager.check_updates(&self.client, &self.config.update_server, self.config.request_timeout).await
+            .map_err(|e| self.server_error(e))
+    }
+
+    /// Turn a request to the update server that timed out or failed before
+    /// an answer into a [`ServerError`]
+    fn server_error(&self, error: anyhow::Error) -> anyhow::Error {
+        let failed = error.chain()
+            .filter_map(|cause| cause.downcast_ref::<reqwest::Error>())
+            .find(|cause| cause.is_timeout() || cause.is_connect() || cause.is_request());
+
+        let server = self.config.update_server.clone();
+        match failed {
+            Some(cause) if cause.is_timeout() && cause.is_connect() => {
+                ServerError::ConnectTimeout { server, timeout: self.config.connect_timeout }.into()
+            }
+            Some(cause) if cause.is_timeout() => {
+                ServerError::RequestTimeout { server, timeout: self.config.request_timeout }.into()
+            }
+            Some(cause) => {
+                // The innermost cause says why, e.g. "Network is unreachable"
+                let mut reason: &dyn std::error::Error = cause;
+                while let Some(source) = reason.source() {
+                    reason = source;
+                }
+                ServerError::Unreachable { server, reason: reason.to_string() }.into()
+            }
+            None => error,
+        }
     }
 
     async fn check_package_updates(&self) -> Result<Vec<UpdateInfo>> {
-        // Integration with hecate-pkg
-        // TODO: Implement package update checking
-        Ok(Vec::new())
+        let Some(source) = &self.packages else {
+            return Ok(Vec::new());
+        };
+
+        let updates = source.outdated().await?
+            .into_iter()
+            .map(|pkg| UpdateInfo {
+                id: format!("pkg-{}-{}", pkg.name, pkg.available),
+                description: format!("{} {} -> {}: {}", pkg.name, pkg.installed, pkg.available, pkg.description),
+                update_type: UpdateType::Package {
+                    name: pkg.name,
+                    version: pkg.available,
+                },
+                size_bytes: pkg.size_bytes,
+                // hecate-pkg downloads from its own repositories
+                download_url: String::new(),
+                checksum: UpdateChecksum {
+                    sha256: pkg.sha256,
+                    blake3: pkg.blake3,
+                },
+                signature: None,
+                release_date: pkg.release_date,
+                dependencies: Vec::new(),
+                conflicts: Vec::new(),
+                changelog: None,
+            })
+            .collect();
+
+        Ok(updates)
     }
 
     async fn check_firmware_updates(&self) -> Result<Vec<UpdateInfo>> {
@@ -410,73 +920,31 @@ impl UpdateManager {
         Ok(Vec::new())
     }
 
-    async fn create_snapshot(&mut self) -> Result<String> {
-        self.rollback_manager.create_snapshot().await
-    }
-
     async fn apply_package_update(&self, name: &str, version: &Version) -> Result<()> {
-        // Use hecate-pkg to update package
-        // TODO: Implement package update
-        Ok(())
+        let source = self.packages.as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No package manager available to update {}", name))?;
+        source.upgrade(name, version).await
+            .with_context(|| format!("Failed to update package {} to {}", name, version))
     }
 
     async fn apply_firmware_update(&self, update: &UpdateInfo) -> Result<()> {
-        // Apply firmware update
-        // TODO: Implement firmware update
-        Ok(())
+        // TODO: Flash firmware; until then, fail rather than report it applied
+        Err(anyhow::anyhow!("Flashing firmware is not supported yet, {} was not applied", update.id))
     }
 
     async fn apply_security_update(&self, update: &UpdateInfo) -> Result<()> {
-        // Apply security update immediately
-        // TODO: Implement security update
-        Ok(())
+        // TODO: Apply security fixes; until then, fail rather than report it applied
+        Err(anyhow::anyhow!("Applying security fixes is not supported yet, {} was not applied", update.id))
     }
 
     async fn schedule_reboot(&self) -> Result<()> {
-        // Schedule system reboot
-        tracing::info!("Scheduling system reboot...");
-        // TODO: Implement reboot scheduling
-        Ok(())
+        let action = reboot::decide(self.config.reboot_policy, &self.scheduler, Utc::now());
+        tracing::info!("Reboot required, policy {:?}: {:?}", self.config.reboot_policy, action);
+        self.reboot.execute(&action)
     }
 
     fn resolve_dependencies(&self, updates: &[UpdateInfo]) -> Result<Vec<String>> {
-        // Simple topological sort for dependencies
-        let mut order = Vec::new();
-        let mut visited = HashSet::new();
-
-        for update in updates {
-            self.visit_update(&update.id, updates, &mut visited, &mut order)?;
-        }
-
-        Ok(order)
-    }
-
-    fn visit_update(
-        &self,
-        id: &str,
-        updates: &[UpdateInfo],
-        visited: &mut HashSet<String>,
-        order: &mut Vec<String>,
-    ) -> Result<()> {
-        if visited.contains(id) {
-            return Ok(());
-        }
-
-        let update = updates.iter()
-            .find(|u| u.id == id)
-            .ok_or_else(|| anyhow::anyhow!("Update {} not found", id))?;
-
-        // Visit dependencies first
-        for dep in &update.dependencies {
-            if updates.iter().any(|u| u.id == *dep) {
-                self.visit_update(dep, updates, visited, order)?;
-            }
-        }
-
-        visited.insert(id.to_string());
-        order.push(id.to_string());
-
-        Ok(())
+        resolve_update_order(updates, &self.state.installed_updates)
     }
 
     fn estimate_update_time(&self, updates: &[UpdateInfo]) -> std::time::Duration {
@@ -500,6 +968,110 @@ impl UpdateManager {
     }
 }
 
+/// Security updates of at least `min_severity`
+pub fn security_updates<'a>(updates: &'a [UpdateInfo], min_severity: &SecuritySeverity) -> Vec<&'a UpdateInfo> {
+    updates.iter()
+        .filter(|u| matches!(&u.update_type,
+            UpdateType::Security { severity, .. } if severity >= min_severity))
+        .collect()
+}
+
+/// Choose when to apply `update` at time `now`
+///
+/// Updates flagged `immediate` don't wait for a maintenance window, nor does
+/// anything once a window is open; the rest go to the next window.
+pub fn security_action(update: &UpdateInfo, scheduler: &scheduler::UpdateScheduler, now: DateTime<Utc>) -> SecurityAction {
+    let immediate = matches!(update.update_type, UpdateType::Security { immediate: true, .. });
+    if immediate || scheduler.is_in_window_at(now) {
+        SecurityAction::ApplyNow
+    } else {
+        SecurityAction::ScheduleAt(scheduler.next_window_after(now))
+    }
+}
+
+/// Updates left installed by `history`, given newest first
+pub fn installed_updates(history: &[UpdateHistory]) -> HashSet<String> {
+    let mut installed = HashSet::new();
+    for entry in history.iter().rev() {
+        match entry.status {
+            UpdateStatus::Installed => {
+                installed.insert(entry.id.clone());
+            }
+            UpdateStatus::RolledBack => {
+                installed.remove(&entry.id);
+            }
+            _ => {}
+        }
+    }
+    installed
+}
+
+/// Order updates so each comes after its dependencies
+///
+/// Dependencies must either be part of `updates` or already `installed`;
+/// every missing one is reported. A dependency cycle is an error naming the
+/// updates involved.
+pub fn resolve_update_order(updates: &[UpdateInfo], installed: &HashSet<String>) -> Result<Vec<String>> {
+    let missing: Vec<String> = updates.iter()
+        .flat_map(|update| {
+            update.dependencies.iter()
+                .filter(|dep| !installed.contains(*dep) && !updates.iter().any(|u| u.id == **dep))
+                .map(move |dep| format!("{} requires {}", update.id, dep))
+        })
+        .collect();
+    if !missing.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Updates depend on updates that are neither planned nor installed: {}",
+            missing.join(", ")
+        ));
+    }
+
+    let mut order = Vec::new();
+    let mut visited = HashSet::new();
+    let mut stack = Vec::new();
+
+    for update in updates {
+        visit_update(&update.id, updates, &mut visited, &mut stack, &mut order)?;
+    }
+
+    Ok(order)
+}
+
+/// Depth-first visit; `stack` holds the updates currently being visited
+fn visit_update(
+    id: &str,
+    updates: &[UpdateInfo],
+    visited: &mut HashSet<String>,
+    stack: &mut Vec<String>,
+    order: &mut Vec<String>,
+) -> Result<()> {
+    if visited.contains(id) {
+        return Ok(());
+    }
+
+    if let Some(pos) = stack.iter().position(|s| s == id) {
+        let mut cycle = stack[pos..].to_vec();
+        cycle.push(id.to_string());
+        return Err(anyhow::anyhow!("Dependency cycle between updates: {}", cycle.join(" -> ")));
+    }
+
+    // Installed dependencies are not part of the plan
+    let Some(update) = updates.iter().find(|u| u.id == id) else {
+        return Ok(());
+    };
+
+    stack.push(id.to_string());
+    for dep in &update.dependencies {
+        visit_update(dep, updates, visited, stack, order)?;
+    }
+    stack.pop();
+
+    visited.insert(id.to_string());
+    order.push(id.to_string());
+
+    Ok(())
+}
+
 /// Update history entry
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateHistory {
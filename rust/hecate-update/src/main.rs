@@ -7,8 +7,9 @@ use clap::{Parser, Subcommand};
 use colored::*;
 use dialoguer::Confirm;
 use indicatif::{ProgressBar, ProgressStyle};
-use hecate_update::{UpdateManager, UpdateConfig, UpdateType, SecuritySeverity};
-use std::path::PathBuf;
+use hecate_update::{UpdateManager, UpdateConfig, UpdateEvent, UpdateType, SecuritySeverity, SecurityAction};
+use hecate_update::packages::HecatePkgSource;
+use std::path::{Path, PathBuf};
 
 #[derive(Parser)]
 #[command(name = "hecate-update")]
@@ -64,6 +65,15 @@ enum Commands {
         /// Force update even outside maintenance window
         #[arg(short, long)]
         force: bool,
+        
+        /// Apply only security updates; outside the maintenance window
+        /// those not marked immediate are skipped
+        #[arg(long, conflicts_with = "updates")]
+        security_only: bool,
+        
+        /// Least severe security updates to include
+        #[arg(long, value_enum, requires = "security_only")]
+        min_severity: Option<SecuritySeverity>,
     },
     
     /// Schedule updates for maintenance window
@@ -102,7 +112,14 @@ enum Commands {
     },
     
     /// Show update system status
-    Status,
+    Status {
+        /// Print the status as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    
+    /// Cancel a reboot scheduled after updates
+    CancelReboot,
     
     /// Run update service daemon
     Service {
@@ -175,6 +192,13 @@ enum ConfigAction {
     },
 }
 
+impl Commands {
+    /// Whether the command looks up or installs package updates
+    fn needs_packages(&self) -> bool {
+        matches!(self, Commands::Check { .. } | Commands::Apply { .. } | Commands::Schedule { .. })
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
@@ -183,10 +207,12 @@ async fn main() -> Result<()> {
     if cli.verbose {
         tracing_subscriber::fmt()
             .with_env_filter("debug")
+            .with_writer(std::io::stderr)
             .init();
     } else {
         tracing_subscriber::fmt()
             .with_env_filter("info")
+            .with_writer(std::io::stderr)
             .init();
     }
     
@@ -196,22 +222,37 @@ async fn main() -> Result<()> {
     }
     
     // Load configuration
-    let config = if let Some(config_path) = cli.config {
-        load_config(&config_path)?
-    } else {
-        UpdateConfig::default()
-    };
+    let config_path = cli.config.unwrap_or_else(|| PathBuf::from(hecate_update::config::CONFIG_PATH));
+    let config = UpdateConfig::load(&config_path)?;
+    
+    // Editing the configuration needs no update manager
+    if let Commands::Config { action } = cli.command {
+        return handle_config(action, config, &config_path);
+    }
     
     // Create update manager
     let mut manager = UpdateManager::new(config).await?;
     
+    // Only commands that look up or install package updates take the package database lock
+    if cli.command.needs_packages() {
+        let pkg_config = hecate_pkg::PackageConfig {
+            max_download_bytes_per_sec: manager.config().max_download_bytes_per_sec,
+            ..Default::default()
+        };
+        match HecatePkgSource::open(pkg_config).await {
+            Ok(source) => manager = manager.with_package_source(Box::new(source)),
+            Err(e) => tracing::warn!("Package updates unavailable: {}", e),
+        }
+    }
+    
     // Execute command
     match cli.command {
         Commands::Check { all, type_filter } => {
             handle_check(&mut manager, all, type_filter).await?;
         }
-        Commands::Apply { updates, no_snapshot, no_rollback, force } => {
-            handle_apply(&mut manager, updates, no_snapshot, no_rollback, force, cli.yes).await?;
+        Commands::Apply { updates, no_snapshot, no_rollback, force, security_only, min_severity } => {
+            let security = security_only.then(|| min_severity.unwrap_or(SecuritySeverity::Low));
+            handle_apply(&mut manager, updates, security, no_snapshot, no_rollback, force, cli.yes).await?;
         }
         Commands::Schedule { updates } => {
             handle_schedule(&mut manager, updates).await?;
@@ -223,13 +264,15 @@ async fn main() -> Result<()> {
             handle_history(&manager, limit, detailed).await?;
         }
         Commands::Snapshot { action } => {
-            handle_snapshot(action).await?;
+            handle_snapshot(&mut manager, action).await?;
         }
-        Commands::Config { action } => {
-            handle_config(action).await?;
+        Commands::Config { .. } => unreachable!("handled before the update manager is created"),
+        Commands::Status { json } => {
+            handle_status(&manager, json).await?;
         }
-        Commands::Status => {
-            handle_status(&manager).await?;
+        Commands::CancelReboot => {
+            manager.cancel_reboot()?;
+            println!("{}", "Scheduled reboot cancelled".green());
         }
         Commands::Service { foreground } => {
             handle_service(foreground).await?;
@@ -246,9 +289,17 @@ async fn handle_check(
 ) -> Result<()> {
     println!("{}", "Checking for system updates...".bright_cyan());
     
-    let updates = manager.check_updates().await?;
+    let check = manager.check_updates().await?;
+    let updates = check.updates;
+    
+    for failure in &check.failures {
+        println!("{}", format!("Could not check {} updates: {:#}", failure.source, failure.error).red());
+    }
     
     if updates.is_empty() {
+        if !check.failures.is_empty() {
+            return Err(anyhow::anyhow!("Update check incomplete, {} sources failed", check.failures.len()));
+        }
         println!("{}", "System is up to date!".green());
         return Ok(());
     }
@@ -274,29 +325,7 @@ async fn handle_check(
     println!("\n{}", format!("Found {} updates:", filtered.len()).bright_yellow());
     
     for update in filtered {
-        let type_str = match &update.update_type {
-            UpdateType::KernelPatch { version, .. } => {
-                format!("Kernel {}", version).bright_blue()
-            }
-            UpdateType::Driver { name, version, .. } => {
-                format!("Driver {}-{}", name, version).bright_magenta()
-            }
-            UpdateType::Package { name, version } => {
-                format!("Package {}-{}", name, version).bright_white()
-            }
-            UpdateType::Firmware { component, version, .. } => {
-                format!("Firmware {}-{}", component, version).bright_cyan()
-            }
-            UpdateType::Security { cve_id, severity, .. } => {
-                let color = match severity {
-                    SecuritySeverity::Critical => format!("Security {} (CRITICAL)", cve_id).red().bold(),
-                    SecuritySeverity::High => format!("Security {} (HIGH)", cve_id).red(),
-                    SecuritySeverity::Medium => format!("Security {} (MEDIUM)", cve_id).yellow(),
-                    SecuritySeverity::Low => format!("Security {} (LOW)", cve_id).bright_black(),
-                };
-                color
-            }
-        };
+        let type_str = describe_update_type(&update.update_type);
         
         println!("  [{}] {} - {}", 
             update.id.bright_black(),
@@ -341,22 +370,57 @@ async fn handle_check(
         total_size_mb
     );
     
+    if !check.failures.is_empty() {
+        return Err(anyhow::anyhow!("Update check incomplete, {} sources failed", check.failures.len()));
+    }
+    
     Ok(())
 }
 
 async fn handle_apply(
     manager: &mut UpdateManager,
     update_ids: Vec<String>,
+    security: Option<SecuritySeverity>,
     no_snapshot: bool,
     no_rollback: bool,
     force: bool,
     auto_yes: bool,
 ) -> Result<()> {
     // Get available updates
-    let available = manager.check_updates().await?;
+    // Updates from the sources that answered can still be applied
+    let check = manager.check_updates().await?;
+    for failure in &check.failures {
+        println!("{}", format!("Skipping {} updates: {:#}", failure.source, failure.error).yellow());
+    }
+    let available = check.updates;
     
     // Determine which updates to apply
-    let to_apply = if update_ids.is_empty() {
+    let to_apply = if let Some(min_severity) = security {
+        let mut now = Vec::new();
+        let mut later = Vec::new();
+        for update in hecate_update::security_updates(&available, &min_severity) {
+            match manager.security_action(update) {
+                _ if force => now.push(update.id.clone()),
+                SecurityAction::ApplyNow => now.push(update.id.clone()),
+                SecurityAction::ScheduleAt(_) => later.push(update.id.clone()),
+            }
+        }
+        
+        // Nothing runs a schedule once this process exits, so deferred
+        // updates are left for the user to apply rather than queued
+        if !later.is_empty() {
+            println!("{}", format!(
+                "{} security updates wait for the maintenance window at {} and were not applied:",
+                later.len(),
+                manager.next_maintenance_window().with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"),
+            ).yellow());
+            for id in &later {
+                println!("  {}", id);
+            }
+            println!("{}", "Run this command again during the window, or pass --force to apply them now".yellow());
+        }
+        now
+    } else if update_ids.is_empty() {
         // Apply all available
         available.iter().map(|u| u.id.clone()).collect()
     } else {
@@ -416,7 +480,28 @@ async fn handle_apply(
             .progress_chars("##-"),
     );
     
-    match manager.apply_updates(plan).await {
+    let (events_tx, mut events_rx) = tokio::sync::mpsc::channel(64);
+    manager.set_event_sender(Some(events_tx));
+    let bar = pb.clone();
+    let progress = tokio::spawn(async move {
+        while let Some(event) = events_rx.recv().await {
+            match event {
+                UpdateEvent::Started { id } => bar.set_message(format!("Applying {}", id)),
+                UpdateEvent::Progress { id, fraction } => {
+                    bar.set_message(format!("Downloading {} ({:.0}%)", id, fraction * 100.0));
+                }
+                UpdateEvent::Succeeded { .. } => bar.inc(1),
+                UpdateEvent::Failed { id, .. } => bar.set_message(format!("Failed {}", id)),
+                UpdateEvent::RollbackStarted => bar.set_message("Rolling back..."),
+            }
+        }
+    });
+    
+    let result = manager.apply_updates(plan).await;
+    manager.set_event_sender(None);
+    progress.await?;
+    
+    match result {
         Ok(()) => {
             pb.finish_with_message("✓ All updates applied successfully");
             println!("\n{}", "Updates applied successfully!".green().bold());
@@ -466,18 +551,48 @@ async fn handle_rollback(
     }
     
     println!("{}", "Initiating rollback...".bright_cyan());
-    manager.rollback().await?;
+    match snapshot {
+        Some(snapshot_id) => manager.rollback_to(&snapshot_id).await?,
+        None => manager.rollback().await?,
+    }
     println!("{}", "Rollback completed successfully!".green().bold());
     
     Ok(())
 }
 
+/// One-line, colored description of an update's type
+fn describe_update_type(update_type: &UpdateType) -> ColoredString {
+    match update_type {
+        UpdateType::KernelPatch { version, .. } => {
+            format!("Kernel {}", version).bright_blue()
+        }
+        UpdateType::Driver { name, version, .. } => {
+            format!("Driver {}-{}", name, version).bright_magenta()
+        }
+        UpdateType::Package { name, version } => {
+            format!("Package {}-{}", name, version).bright_white()
+        }
+        UpdateType::Firmware { component, version, .. } => {
+            format!("Firmware {}-{}", component, version).bright_cyan()
+        }
+        UpdateType::Security { cve_id, severity, .. } => {
+            let color = match severity {
+                SecuritySeverity::Critical => format!("Security {} (CRITICAL)", cve_id).red().bold(),
+                SecuritySeverity::High => format!("Security {} (HIGH)", cve_id).red(),
+                SecuritySeverity::Medium => format!("Security {} (MEDIUM)", cve_id).yellow(),
+                SecuritySeverity::Low => format!("Security {} (LOW)", cve_id).bright_black(),
+            };
+            color
+        }
+    }
+}
+
 async fn handle_history(
     manager: &UpdateManager,
     limit: usize,
     detailed: bool,
 ) -> Result<()> {
-    let history = manager.get_history().await?;
+    let history = manager.get_history(Some(limit)).await?;
     
     if history.is_empty() {
         println!("{}", "No update history".yellow());
@@ -486,7 +601,7 @@ async fn handle_history(
     
     println!("{}", "Update History:".bright_cyan());
     
-    for (i, entry) in history.iter().take(limit).enumerate() {
+    for (i, entry) in history.iter().enumerate() {
         println!("\n{}. [{}] {}", 
             i + 1,
             entry.id.bright_black(),
@@ -501,72 +616,131 @@ async fn handle_history(
         };
         
         println!("   Status: {}", status_str);
-        println!("   Duration: {:?}", entry.duration);
+        println!("   Duration: {:.1}s", entry.duration.as_secs_f64());
         
         if detailed {
-            // Show more details
+            println!("   Type: {}", describe_update_type(&entry.update_type));
+            println!("   Rollback available: {}", if entry.rollback_available { "yes" } else { "no" });
         }
     }
     
     Ok(())
 }
 
-async fn handle_snapshot(action: SnapshotAction) -> Result<()> {
-    // TODO: Implement snapshot management
+async fn handle_snapshot(manager: &mut UpdateManager, action: SnapshotAction) -> Result<()> {
     match action {
         SnapshotAction::List => {
-            println!("Available snapshots:");
+            let snapshots = manager.list_snapshots().await?;
+            if snapshots.is_empty() {
+                println!("{}", "No snapshots".yellow());
+                return Ok(());
+            }
+            
+            println!("{}", "Available snapshots:".bright_cyan());
+            for snapshot in snapshots {
+                println!("  {} {} {:.1} MB {}",
+                    snapshot.id.bright_white(),
+                    snapshot.created_at.format("%Y-%m-%d %H:%M:%S"),
+                    snapshot.size_bytes as f64 / (1024.0 * 1024.0),
+                    snapshot.name.unwrap_or_default().bright_black()
+                );
+            }
         }
         SnapshotAction::Create { name } => {
             println!("Creating snapshot...");
+            let snapshot = manager.create_snapshot(name.as_deref()).await?;
+            println!("{} {}", "Created snapshot".green(), snapshot.id.bright_white());
         }
         SnapshotAction::Delete { id } => {
             println!("Deleting snapshot {}...", id);
+            manager.delete_snapshot(&id).await?;
+            println!("{}", "Snapshot deleted".green());
         }
         SnapshotAction::Info { id } => {
-            println!("Snapshot {} info:", id);
+            let snapshot = manager.snapshot_info(&id).await?;
+            println!("Snapshot {} info:", snapshot.id.bright_white());
+            println!("  Name: {}", snapshot.name.as_deref().unwrap_or("-"));
+            println!("  Created: {}", snapshot.created_at.format("%Y-%m-%d %H:%M:%S"));
+            println!("  Size: {:.1} MB", snapshot.size_bytes as f64 / (1024.0 * 1024.0));
+            if snapshot.updates.is_empty() {
+                println!("  Precedes updates: -");
+            } else {
+                println!("  Precedes updates: {}", snapshot.updates.join(", "));
+            }
         }
     }
     Ok(())
 }
 
-async fn handle_config(action: ConfigAction) -> Result<()> {
-    // TODO: Implement configuration management
-    match action {
+fn handle_config(action: ConfigAction, mut config: UpdateConfig, path: &Path) -> Result<()> {
+    let message = match action {
         ConfigAction::Show => {
-            println!("Current configuration:");
+            println!("{} {}", "Configuration:".bright_cyan(), path.display());
+            print!("{}", config.to_toml()?);
+            return Ok(());
         }
         ConfigAction::Set { key, value } => {
-            println!("Setting {} = {}", key, value);
+            config.set(&key, &value)?;
+            format!("Set {} = {}", key, value)
         }
         ConfigAction::EnableLivePatch => {
-            println!("Live patching enabled");
+            config.enable_live_patching = true;
+            "Live patching enabled".to_string()
         }
         ConfigAction::DisableLivePatch => {
-            println!("Live patching disabled");
+            config.enable_live_patching = false;
+            "Live patching disabled".to_string()
         }
         ConfigAction::EnableRollback => {
-            println!("Automatic rollback enabled");
+            config.auto_rollback = true;
+            "Automatic rollback enabled".to_string()
         }
         ConfigAction::DisableRollback => {
-            println!("Automatic rollback disabled");
+            config.auto_rollback = false;
+            "Automatic rollback disabled".to_string()
         }
         ConfigAction::SetMaintenanceWindow { days, start, end } => {
-            println!("Maintenance window set: {} from {}:00 to {}:00", days, start, end);
+            config.set_maintenance_window(&days, start, end)?;
+            format!("Maintenance window set: {} from {}:00 to {}:00", days, start, end)
         }
-    }
+    };
+    
+    config.save(path)?;
+    println!("{}", message.green());
     Ok(())
 }
 
-async fn handle_status(manager: &UpdateManager) -> Result<()> {
+async fn handle_status(manager: &UpdateManager, json: bool) -> Result<()> {
+    let status = manager.status();
+    if json {
+        println!("{}", serde_json::to_string_pretty(&status)?);
+        return Ok(());
+    }
+    
     println!("{}", "=== Update System Status ===".bright_cyan().bold());
     
-    // TODO: Show actual status
-    println!("\nLive Patching: {}", "Enabled".green());
-    println!("Hot Swapping: {}", "Enabled".green());
-    println!("Auto Rollback: {}", "Enabled".green());
-    println!("\nMaintenance Window: {} 02:00-06:00", "Sun, Wed".bright_white());
-    println!("Next Window: {}", "2025-02-05 02:00:00".bright_white());
+    let local_time = |time: chrono::DateTime<chrono::Utc>| {
+        time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S %Z").to_string()
+    };
+    let enabled = |on: bool| if on { "Enabled".green() } else { "Disabled".yellow() };
+    println!("\nLive Patching: {}", enabled(status.live_patching));
+    println!("Hot Swapping: {}", enabled(status.hot_swapping));
+    println!("Auto Rollback: {}", enabled(status.auto_rollback));
+    println!("Reboot Policy: {:?}", status.reboot_policy);
+    if status.reboot_pending {
+        println!("{}", "Reboot pending".yellow());
+    }
+    if let Some(snapshot) = &status.active_snapshot {
+        println!("Active Snapshot: {}", snapshot.bright_white());
+    }
+    println!("Pending Updates: {}", status.pending_updates);
+    println!("Last Check: {}", status.last_check.map(local_time).unwrap_or_else(|| "Never".to_string()));
+    
+    let window = &manager.config().maintenance_window;
+    let days: Vec<String> = window.days.iter().map(|d| d.to_string()).collect();
+    println!("\nMaintenance Window: {} {:02}:00-{:02}:00 {}",
+        days.join(", ").bright_white(), window.start_hour, window.end_hour, window.timezone);
+    println!("Next Window: {}", local_time(status.next_window).bright_white());
     
     Ok(())
 }
@@ -583,10 +757,4 @@ async fn handle_service(foreground: bool) -> Result<()> {
     }
     
     Ok(())
-}
-
-fn load_config(path: &PathBuf) -> Result<UpdateConfig> {
-    let content = std::fs::read_to_string(path)?;
-    let config: UpdateConfig = toml::from_str(&content)?;
-    Ok(config)
 }
\ No newline at end of file
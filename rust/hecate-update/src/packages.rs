@@ -0,0 +1,89 @@
+//! Package update module
+//!
+//! Bridges the update system to a package manager. The update manager only
+//! sees the [`PackageSource`] trait; [`HecatePkgSource`] adapts hecate-pkg.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use semver::Version;
+use tokio::sync::Mutex;
+
+/// An installed package with a newer version available
+#[derive(Debug, Clone)]
+pub struct PackageUpgrade {
+    pub name: String,
+    pub installed: Version,
+    pub available: Version,
+    pub description: String,
+    pub size_bytes: u64,
+    pub sha256: String,
+    pub blake3: String,
+    pub release_date: DateTime<Utc>,
+}
+
+/// Source of package upgrades
+#[async_trait]
+pub trait PackageSource: Send + Sync {
+    /// Installed packages that have a newer version available
+    async fn outdated(&self) -> Result<Vec<PackageUpgrade>>;
+
+    /// Upgrade `name` to exactly `version`
+    async fn upgrade(&self, name: &str, version: &Version) -> Result<()>;
+}
+
+/// [`PackageSource`] backed by hecate-pkg
+pub struct HecatePkgSource {
+    manager: Mutex<hecate_pkg::PackageManager>,
+}
+
+impl HecatePkgSource {
+    pub fn new(manager: hecate_pkg::PackageManager) -> Self {
+        Self {
+            manager: Mutex::new(manager),
+        }
+    }
+
+    /// Open the package database described by `config`
+    pub async fn open(config: hecate_pkg::PackageConfig) -> Result<Self> {
+        Ok(Self::new(hecate_pkg::PackageManager::new(config).await?))
+    }
+}
+
+#[async_trait]
+impl PackageSource for HecatePkgSource {
+    async fn outdated(&self) -> Result<Vec<PackageUpgrade>> {
+        let mut manager = self.manager.lock().await;
+
+        // Stale indices still give useful results, so a failed sync is not fatal
+        if let Err(e) = manager.sync_repositories().await {
+            tracing::warn!("Using cached package indices: {}", e);
+        }
+
+        let upgrades = manager.outdated().await?
+            .into_iter()
+            .filter(|pkg| {
+                if pkg.held {
+                    tracing::info!("Skipping held package {} {}", pkg.name, pkg.installed);
+                }
+                !pkg.held
+            })
+            .map(|pkg| PackageUpgrade {
+                name: pkg.name,
+                installed: pkg.installed,
+                available: pkg.available.version,
+                description: pkg.available.description,
+                size_bytes: pkg.available.size_bytes,
+                sha256: pkg.available.checksum.sha256,
+                blake3: pkg.available.checksum.blake3,
+                release_date: pkg.available.build_date,
+            })
+            .collect();
+
+        Ok(upgrades)
+    }
+
+    async fn upgrade(&self, name: &str, version: &Version) -> Result<()> {
+        self.manager.lock().await.upgrade(name, version).await
+    }
+}
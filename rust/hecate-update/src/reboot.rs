@@ -0,0 +1,149 @@
+//! Reboot scheduling module
+//!
+//! Decides when to reboot after updates that need it, and carries that out
+//! through systemd or by leaving a flag file for the user.
+
+use anyhow::{Result, Context};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::scheduler::UpdateScheduler;
+
+/// Transient systemd unit used for delayed reboots, so they can be cancelled
+pub const REBOOT_UNIT: &str = "hecate-update-reboot";
+
+/// What to do when an applied update requires a reboot
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RebootPolicy {
+    /// Reboot as soon as the updates are applied
+    Immediate,
+    /// Reboot when the current (or next) maintenance window closes
+    AtWindowEnd,
+    /// Leave a flag file and tell the user a reboot is needed
+    #[default]
+    Notify,
+    /// Do nothing
+    Never,
+}
+
+/// Concrete action chosen for a [`RebootPolicy`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RebootAction {
+    RebootNow,
+    RebootAt(DateTime<Utc>),
+    Notify,
+    Nothing,
+}
+
+/// Choose the reboot action for `policy` at time `now`
+pub fn decide(policy: RebootPolicy, scheduler: &UpdateScheduler, now: DateTime<Utc>) -> RebootAction {
+    match policy {
+        RebootPolicy::Immediate => RebootAction::RebootNow,
+        RebootPolicy::AtWindowEnd => RebootAction::RebootAt(scheduler.window_end_after(now)),
+        RebootPolicy::Notify => RebootAction::Notify,
+        RebootPolicy::Never => RebootAction::Nothing,
+    }
+}
+
+/// Runs system commands; replaced in tests so nothing actually reboots
+pub trait CommandRunner: Send + Sync {
+    fn run(&self, program: &str, args: &[String]) -> Result<()>;
+}
+
+/// Runs commands on the host
+pub struct SystemCommandRunner;
+
+impl CommandRunner for SystemCommandRunner {
+    fn run(&self, program: &str, args: &[String]) -> Result<()> {
+        let output = std::process::Command::new(program)
+            .args(args)
+            .output()
+            .with_context(|| format!("Failed to run {}", program))?;
+
+        if !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "{} failed: {}",
+                program,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Carries out reboot actions and cancels pending ones
+pub struct RebootScheduler {
+    runner: Arc<dyn CommandRunner>,
+    flag_path: PathBuf,
+}
+
+impl RebootScheduler {
+    /// `flag_path` is written whenever a reboot is pending
+    pub fn new(runner: Arc<dyn CommandRunner>, flag_path: &Path) -> Self {
+        Self {
+            runner,
+            flag_path: flag_path.to_path_buf(),
+        }
+    }
+
+    /// Whether a reboot is pending
+    pub fn reboot_pending(&self) -> bool {
+        self.flag_path.exists()
+    }
+
+    pub fn execute(&self, action: &RebootAction) -> Result<()> {
+        match action {
+            RebootAction::RebootNow => {
+                self.write_flag("reboot started")?;
+                tracing::warn!("Rebooting to finish updates");
+                self.runner.run("systemctl", &["reboot".to_string()])
+            }
+            RebootAction::RebootAt(when) => {
+                let calendar = when.format("%Y-%m-%d %H:%M:%S UTC").to_string();
+                self.runner.run("systemd-run", &[
+                    format!("--unit={}", REBOOT_UNIT),
+                    format!("--on-calendar={}", calendar),
+                    "systemctl".to_string(),
+                    "reboot".to_string(),
+                ])?;
+                self.write_flag(&format!("reboot scheduled for {}", calendar))?;
+                tracing::warn!("Reboot scheduled for {}", calendar);
+                Ok(())
+            }
+            RebootAction::Notify => {
+                self.write_flag("reboot required")?;
+                tracing::warn!("A reboot is required to finish applying updates");
+                Ok(())
+            }
+            RebootAction::Nothing => {
+                tracing::info!("Updates require a reboot; reboot policy is set to never");
+                Ok(())
+            }
+        }
+    }
+
+    /// Cancel a scheduled reboot and clear the pending flag
+    pub fn cancel(&self) -> Result<()> {
+        // Stopping the timer fails harmlessly if nothing was scheduled
+        if let Err(e) = self.runner.run("systemctl", &["stop".to_string(), format!("{}.timer", REBOOT_UNIT)]) {
+            tracing::debug!("No reboot timer to stop: {}", e);
+        }
+
+        if self.flag_path.exists() {
+            std::fs::remove_file(&self.flag_path)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_flag(&self, reason: &str) -> Result<()> {
+        if let Some(parent) = self.flag_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.flag_path, format!("{}\n", reason))
+            .with_context(|| format!("Failed to write {}", self.flag_path.display()))
+    }
+}
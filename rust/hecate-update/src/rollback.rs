@@ -2,79 +2,162 @@
 //!
 //! Handles system snapshots and rollback operations
 
-use anyhow::Result;
+use anyhow::{Result, Context};
 use crate::UpdateHistory;
+use crate::snapshot::SnapshotBackend;
+use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+
+/// File in the backup directory that records every snapshot
+const SNAPSHOT_INDEX: &str = "snapshots.json";
+
+/// File in the backup directory that records update and rollback history
+const HISTORY_FILE: &str = "history.json";
+
+/// Record of a snapshot kept in the snapshot index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    pub id: String,
+    pub name: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub size_bytes: u64,
+    /// IDs of the updates the snapshot was taken before
+    pub updates: Vec<String>,
+}
 
 pub struct RollbackManager {
     backup_dir: PathBuf,
+    backend: Box<dyn SnapshotBackend>,
 }
 
 impl RollbackManager {
-    pub fn new(backup_dir: &Path) -> Result<Self> {
+    /// Keep the snapshot index in `backup_dir`, storing snapshot data with `backend`
+    pub fn new(backup_dir: &Path, backend: Box<dyn SnapshotBackend>) -> Result<Self> {
         std::fs::create_dir_all(backup_dir)?;
         Ok(Self {
             backup_dir: backup_dir.to_path_buf(),
+            backend,
         })
     }
 
-    pub async fn create_snapshot(&self) -> Result<String> {
-        let snapshot_id = format!("snapshot-{}", Utc::now().timestamp());
-        let snapshot_path = self.backup_dir.join(&snapshot_id);
-        
-        tracing::info!("Creating snapshot: {}", snapshot_id);
-        std::fs::create_dir_all(&snapshot_path)?;
-        
-        // TODO: Create actual system snapshot (BTRFS, LVM, or file-based)
-        // For now, just create a marker file
-        std::fs::write(snapshot_path.join("metadata.json"), "{}")?;
-        
-        Ok(snapshot_id)
+    /// Name of the snapshot backend in use
+    pub fn backend_name(&self) -> &'static str {
+        self.backend.name()
+    }
+
+    /// Create a snapshot and record it in the index
+    ///
+    /// `updates` lists the updates about to be applied on top of it, if any.
+    pub async fn create_snapshot(&self, name: Option<&str>, updates: &[String]) -> Result<SnapshotInfo> {
+        let mut index = self.read_index()?;
+        let created_at = Utc::now();
+
+        // Millisecond timestamps can still collide when snapshots are taken back to back
+        let base_id = format!("snapshot-{}", created_at.timestamp_millis());
+        let mut snapshot_id = base_id.clone();
+        let mut suffix = 1;
+        let existing = self.backend.list().await?;
+        while index.iter().any(|s| s.id == snapshot_id) || existing.contains(&snapshot_id) {
+            snapshot_id = format!("{}-{}", base_id, suffix);
+            suffix += 1;
+        }
+
+        tracing::info!("Creating {} snapshot: {}", self.backend.name(), snapshot_id);
+        let size_bytes = self.backend.create(&snapshot_id).await
+            .with_context(|| format!("Failed to create snapshot {}", snapshot_id))?;
+
+        let info = SnapshotInfo {
+            id: snapshot_id,
+            name: name.map(str::to_string),
+            created_at,
+            size_bytes,
+            updates: updates.to_vec(),
+        };
+
+        index.push(info.clone());
+        self.write_index(&index)?;
+
+        Ok(info)
     }
 
     pub async fn rollback_to_snapshot(&self, snapshot_id: &str) -> Result<()> {
         tracing::info!("Rolling back to snapshot: {}", snapshot_id);
-        let snapshot_path = self.backup_dir.join(snapshot_id);
-        
-        if !snapshot_path.exists() {
-            return Err(anyhow::anyhow!("Snapshot {} not found", snapshot_id));
-        }
-        
-        // TODO: Perform actual rollback
-        // This would involve restoring files, configs, and packages
-        
-        Ok(())
+        let snapshot = self.get_snapshot(snapshot_id).await?;
+        self.backend.rollback_to(&snapshot.id).await
+            .with_context(|| format!("Failed to roll back to snapshot {}", snapshot_id))
+    }
+
+    /// Recorded history entries, newest first, at most `limit` of them
+    pub async fn get_history(&self, limit: Option<usize>) -> Result<Vec<UpdateHistory>> {
+        let mut history: Vec<UpdateHistory> = read_json(&self.backup_dir.join(HISTORY_FILE))?;
+        // Entries written in the same instant keep their reverse write order
+        history.reverse();
+        history.sort_by_key(|entry| std::cmp::Reverse(entry.timestamp));
+        history.truncate(limit.unwrap_or(usize::MAX));
+        Ok(history)
     }
 
-    pub async fn get_history(&self) -> Result<Vec<UpdateHistory>> {
-        // TODO: Read actual update history from database or log files
-        Ok(Vec::new())
+    /// Append an entry to the history file
+    pub async fn record_history(&self, entry: UpdateHistory) -> Result<()> {
+        let path = self.backup_dir.join(HISTORY_FILE);
+        let mut history: Vec<UpdateHistory> = read_json(&path)?;
+        history.push(entry);
+        write_json(&path, &history)
     }
 
+    /// Look up a snapshot by ID
+    pub async fn get_snapshot(&self, snapshot_id: &str) -> Result<SnapshotInfo> {
+        self.read_index()?
+            .into_iter()
+            .find(|s| s.id == snapshot_id)
+            .ok_or_else(|| anyhow::anyhow!("Snapshot {} not found", snapshot_id))
+    }
+
+    /// Delete a snapshot's index entry and its backing data
     pub async fn delete_snapshot(&self, snapshot_id: &str) -> Result<()> {
-        let snapshot_path = self.backup_dir.join(snapshot_id);
-        if snapshot_path.exists() {
-            std::fs::remove_dir_all(snapshot_path)?;
+        let mut index = self.read_index()?;
+        let before = index.len();
+        index.retain(|s| s.id != snapshot_id);
+        if index.len() == before {
+            return Err(anyhow::anyhow!("Snapshot {} not found", snapshot_id));
         }
-        Ok(())
+
+        self.backend.delete(snapshot_id).await?;
+        self.write_index(&index)
     }
 
-    pub async fn list_snapshots(&self) -> Result<Vec<String>> {
-        let mut snapshots = Vec::new();
-        
-        for entry in std::fs::read_dir(&self.backup_dir)? {
-            let entry = entry?;
-            if entry.file_type()?.is_dir() {
-                if let Some(name) = entry.file_name().to_str() {
-                    if name.starts_with("snapshot-") {
-                        snapshots.push(name.to_string());
-                    }
-                }
-            }
-        }
-        
-        snapshots.sort();
+    /// All recorded snapshots, oldest first
+    pub async fn list_snapshots(&self) -> Result<Vec<SnapshotInfo>> {
+        let mut snapshots = self.read_index()?;
+        snapshots.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
         Ok(snapshots)
     }
-}
\ No newline at end of file
+
+    fn read_index(&self) -> Result<Vec<SnapshotInfo>> {
+        read_json(&self.backup_dir.join(SNAPSHOT_INDEX))
+    }
+
+    fn write_index(&self, index: &[SnapshotInfo]) -> Result<()> {
+        write_json(&self.backup_dir.join(SNAPSHOT_INDEX), index)
+    }
+}
+
+/// Read a JSON list, treating a missing file as empty
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<Vec<T>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = std::fs::read_to_string(path)?;
+    serde_json::from_str(&content)
+        .with_context(|| format!("Invalid JSON in {}", path.display()))
+}
+
+/// Replace a JSON file atomically so a crash never leaves it half written
+fn write_json<T: Serialize>(path: &Path, items: &[T]) -> Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_string_pretty(items)?)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
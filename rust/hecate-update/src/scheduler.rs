@@ -4,19 +4,33 @@
 
 use anyhow::Result;
 use crate::{UpdatePlan, MaintenanceWindow};
-use chrono::{Datelike, Local, Timelike, Weekday};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
 use tokio::sync::Mutex;
 use std::sync::Arc;
 
 pub struct UpdateScheduler {
     maintenance_window: MaintenanceWindow,
+    timezone: Tz,
     scheduled_plans: Arc<Mutex<Vec<UpdatePlan>>>,
 }
 
 impl UpdateScheduler {
     pub fn new(maintenance_window: MaintenanceWindow) -> Result<Self> {
+        let timezone: Tz = maintenance_window.timezone.parse()
+            .map_err(|e| anyhow::anyhow!("Invalid maintenance window timezone '{}': {}",
+                maintenance_window.timezone, e))?;
+
+        if maintenance_window.start_hour > 23 || maintenance_window.end_hour > 23 {
+            return Err(anyhow::anyhow!("Maintenance window hours must be between 0 and 23"));
+        }
+        if maintenance_window.days.is_empty() {
+            return Err(anyhow::anyhow!("Maintenance window has no days"));
+        }
+
         Ok(Self {
             maintenance_window,
+            timezone,
             scheduled_plans: Arc::new(Mutex::new(Vec::new())),
         })
     }
@@ -34,49 +48,100 @@ impl UpdateScheduler {
     }
 
     pub fn is_in_maintenance_window(&self) -> bool {
-        let now = Local::now();
-        let current_day = now.weekday();
-        let current_hour = now.hour();
-        
-        // Check if today is a maintenance day
-        if !self.maintenance_window.days.contains(&current_day) {
-            return false;
-        }
-        
-        // Check if current hour is within window
-        current_hour >= self.maintenance_window.start_hour 
-            && current_hour < self.maintenance_window.end_hour
+        self.is_in_window_at(Utc::now())
     }
 
-    pub fn next_maintenance_window(&self) -> chrono::DateTime<Local> {
-        let now = Local::now();
-        
-        // Find next maintenance day
-        for days_ahead in 0..7 {
-            let check_date = now + chrono::Duration::days(days_ahead);
-            let check_day = check_date.weekday();
-            
-            if self.maintenance_window.days.contains(&check_day) {
-                // Set time to start of maintenance window
-                let window_start = check_date
-                    .with_hour(self.maintenance_window.start_hour)
-                    .unwrap()
-                    .with_minute(0)
-                    .unwrap()
-                    .with_second(0)
-                    .unwrap();
-                
-                // If it's today but we've passed the window, continue to next day
-                if days_ahead == 0 && now >= window_start {
-                    continue;
+    /// Whether `now` falls inside a maintenance window
+    ///
+    /// Windows belong to the day they start on, so a Saturday 22→04 window
+    /// covers early Sunday morning too. Equal start and end hours mean the
+    /// whole day.
+    pub fn is_in_window_at(&self, now: DateTime<Utc>) -> bool {
+        let today = now.with_timezone(&self.timezone).date_naive();
+
+        [today.pred_opt(), Some(today)].into_iter().flatten().any(|day| {
+            self.window_on(day)
+                .map(|(start, end)| start <= now && now < end)
+                .unwrap_or(false)
+        })
+    }
+
+    /// Start of the next maintenance window
+    pub fn next_window(&self) -> DateTime<Utc> {
+        self.next_window_after(Utc::now())
+    }
+
+    /// Start of the first maintenance window strictly after `now`
+    pub fn next_window_after(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let today = now.with_timezone(&self.timezone).date_naive();
+
+        for days_ahead in 0..=7 {
+            let day = today + Duration::days(days_ahead);
+            if let Some((start, _)) = self.window_on(day) {
+                if start > now {
+                    return start;
                 }
-                
-                return window_start;
             }
         }
-        
-        // Shouldn't reach here if days are configured correctly
-        now + chrono::Duration::days(7)
+
+        // Unreachable with at least one configured day
+        now + Duration::days(7)
+    }
+
+    /// End of the window open at `now`, or of the next one if none is open
+    pub fn window_end_after(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let today = now.with_timezone(&self.timezone).date_naive();
+
+        let open = [today.pred_opt(), Some(today)].into_iter().flatten()
+            .filter_map(|day| self.window_on(day))
+            .find(|(start, end)| *start <= now && now < *end);
+        if let Some((_, end)) = open {
+            return end;
+        }
+
+        let start = self.next_window_after(now);
+        let day = start.with_timezone(&self.timezone).date_naive();
+        self.window_on(day)
+            .map(|(_, end)| end)
+            .unwrap_or(start)
+    }
+
+    /// Concrete start and end of the window opening on local date `day`, if any
+    fn window_on(&self, day: NaiveDate) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        if !self.maintenance_window.days.contains(&day.weekday()) {
+            return None;
+        }
+
+        let window = &self.maintenance_window;
+        let end_day = if window.end_hour <= window.start_hour {
+            day.succ_opt()?
+        } else {
+            day
+        };
+
+        Some((
+            self.local_instant(day, window.start_hour)?,
+            self.local_instant(end_day, window.end_hour)?,
+        ))
+    }
+
+    /// Resolve a local wall-clock hour to an instant
+    ///
+    /// A time skipped by a DST jump moves to the first valid time after the
+    /// gap; a time repeated when clocks go back resolves to its first
+    /// occurrence.
+    fn local_instant(&self, day: NaiveDate, hour: u32) -> Option<DateTime<Utc>> {
+        let mut local = day.and_hms_opt(hour, 0, 0)?;
+
+        // DST gaps are at most a couple of hours
+        for _ in 0..12 {
+            if let Some(instant) = self.timezone.from_local_datetime(&local).earliest() {
+                return Some(instant.with_timezone(&Utc));
+            }
+            local += Duration::minutes(15);
+        }
+
+        None
     }
 
     pub async fn check_system_load(&self) -> Result<f64> {
@@ -1,273 +1,437 @@
-//! Filesystem snapshot management
+//! Filesystem snapshot backends
 //!
-//! Supports BTRFS, LVM, and ZFS snapshots
+//! Snapshots are taken with btrfs or ZFS when the root filesystem supports
+//! them, falling back to copying critical paths into the backup directory.
 
-use anyhow::Result;
+use anyhow::{Result, Context};
+use async_trait::async_trait;
+use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
-use std::process::Command;
-
-#[derive(Debug, Clone)]
-pub enum SnapshotType {
-    Btrfs,
-    Lvm,
-    Zfs,
-    FileBased,
+use tokio::process::Command;
+
+use crate::UpdateConfig;
+
+/// Paths copied by [`FileBackend`] unless configured otherwise
+pub const DEFAULT_SNAPSHOT_PATHS: &[&str] = &[
+    "/etc",
+    "/boot/grub",
+    "/boot/loader",
+    "/usr/local",
+];
+
+/// Storage for system snapshots
+#[async_trait]
+pub trait SnapshotBackend: Send + Sync {
+    /// Name used to select the backend in `UpdateConfig::snapshot_backend`
+    fn name(&self) -> &'static str;
+
+    /// Take a snapshot under `id`, returning the bytes it occupies if known
+    async fn create(&self, id: &str) -> Result<u64>;
+
+    /// Restore the system to the snapshot `id`
+    async fn rollback_to(&self, id: &str) -> Result<()>;
+
+    /// IDs of the snapshots held by this backend
+    async fn list(&self) -> Result<Vec<String>>;
+
+    /// Delete the snapshot `id`
+    async fn delete(&self, id: &str) -> Result<()>;
 }
 
-pub struct SnapshotManager {
-    snapshot_type: SnapshotType,
-    root_path: PathBuf,
+/// Build the backend named by `config.snapshot_backend`
+///
+/// `"auto"` picks btrfs or ZFS when the root filesystem is one of them, and
+/// file copies otherwise.
+pub async fn backend_from_config(config: &UpdateConfig) -> Result<Box<dyn SnapshotBackend>> {
+    let name = match config.snapshot_backend.as_str() {
+        "auto" => match detect_filesystem(Path::new("/")).await.as_deref() {
+            Some("btrfs") => "btrfs",
+            Some("zfs") => "zfs",
+            _ => "file",
+        },
+        other => other,
+    };
+
+    match name {
+        "file" => Ok(Box::new(FileBackend::new(&config.backup_dir, config.snapshot_paths.clone()))),
+        "btrfs" => Ok(Box::new(BtrfsBackend::new(Path::new("/"), Path::new("/.snapshots")))),
+        "zfs" => Ok(Box::new(ZfsBackend::for_mountpoint(Path::new("/")).await?)),
+        other => Err(anyhow::anyhow!("Unknown snapshot backend '{}'", other)),
+    }
 }
 
-impl SnapshotManager {
-    pub fn new() -> Result<Self> {
-        let snapshot_type = Self::detect_filesystem_type()?;
-        
-        Ok(Self {
-            snapshot_type,
-            root_path: PathBuf::from("/"),
-        })
+/// Filesystem type mounted at `path`, if it can be determined
+async fn detect_filesystem(path: &Path) -> Option<String> {
+    let output = Command::new("findmnt")
+        .args(["-n", "-o", "FSTYPE", "--target"])
+        .arg(path)
+        .output()
+        .await
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
     }
 
-    fn detect_filesystem_type() -> Result<SnapshotType> {
-        // Check filesystem type of root
-        let output = Command::new("findmnt")
-            .args(&["-n", "-o", "FSTYPE", "/"])
-            .output()?;
-        
-        let fstype = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        
-        match fstype.as_str() {
-            "btrfs" => Ok(SnapshotType::Btrfs),
-            "zfs" => Ok(SnapshotType::Zfs),
-            _ => {
-                // Check if LVM is available
-                if Path::new("/usr/sbin/lvm").exists() {
-                    Ok(SnapshotType::Lvm)
-                } else {
-                    Ok(SnapshotType::FileBased)
-                }
-            }
-        }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Run a command, returning its stdout or an error carrying its stderr
+async fn run<I, S>(program: &str, args: I) -> Result<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .await
+        .with_context(|| format!("Failed to run {}", program))?;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "{} failed: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
     }
 
-    pub async fn create_snapshot(&self, name: &str) -> Result<String> {
-        match self.snapshot_type {
-            SnapshotType::Btrfs => self.create_btrfs_snapshot(name).await,
-            SnapshotType::Lvm => self.create_lvm_snapshot(name).await,
-            SnapshotType::Zfs => self.create_zfs_snapshot(name).await,
-            SnapshotType::FileBased => self.create_file_snapshot(name).await,
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// ============================================================================
+// FILE COPY BACKEND
+// ============================================================================
+
+/// Copies a set of paths into `backup_dir/<id>`
+pub struct FileBackend {
+    backup_dir: PathBuf,
+    paths: Vec<PathBuf>,
+}
+
+impl FileBackend {
+    pub fn new(backup_dir: &Path, paths: Vec<PathBuf>) -> Self {
+        Self {
+            backup_dir: backup_dir.to_path_buf(),
+            paths,
         }
     }
 
-    async fn create_btrfs_snapshot(&self, name: &str) -> Result<String> {
-        let snapshot_path = format!("/.snapshots/{}", name);
-        
-        // Create snapshots directory if it doesn't exist
-        std::fs::create_dir_all("/.snapshots")?;
-        
-        // Create BTRFS snapshot
-        let output = Command::new("btrfs")
-            .args(&["subvolume", "snapshot", "-r", "/", &snapshot_path])
-            .output()?;
-        
-        if !output.status.success() {
-            return Err(anyhow::anyhow!(
-                "Failed to create BTRFS snapshot: {}",
-                String::from_utf8_lossy(&output.stderr)
-            ));
+    /// Where the copy of `path` lives inside a snapshot
+    fn copy_path(&self, id: &str, path: &Path) -> PathBuf {
+        self.backup_dir.join(id).join(path.strip_prefix("/").unwrap_or(path))
+    }
+}
+
+#[async_trait]
+impl SnapshotBackend for FileBackend {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+
+    async fn create(&self, id: &str) -> Result<u64> {
+        let snapshot_dir = self.backup_dir.join(id);
+        std::fs::create_dir_all(&snapshot_dir)?;
+
+        for path in &self.paths {
+            if path.exists() {
+                copy_tree(path, &self.copy_path(id, path))
+                    .with_context(|| format!("Failed to snapshot {}", path.display()))?;
+            }
         }
-        
-        Ok(snapshot_path)
+
+        Ok(dir_size(&snapshot_dir))
     }
 
-    async fn create_lvm_snapshot(&self, name: &str) -> Result<String> {
-        // Get root volume group and logical volume
-        let output = Command::new("findmnt")
-            .args(&["-n", "-o", "SOURCE", "/"])
-            .output()?;
-        
-        let source = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        
-        // Parse VG/LV from device path
-        // Example: /dev/mapper/vg0-root -> vg0/root
-        if !source.starts_with("/dev/mapper/") {
-            return Err(anyhow::anyhow!("Root is not on LVM"));
+    async fn rollback_to(&self, id: &str) -> Result<()> {
+        if !self.backup_dir.join(id).is_dir() {
+            return Err(anyhow::anyhow!("Snapshot {} has no backing data", id));
         }
-        
-        let lv_name = source.strip_prefix("/dev/mapper/")
-            .unwrap_or("")
-            .replace('-', "/");
-        
-        // Create LVM snapshot
-        let snapshot_name = format!("{}-{}", lv_name.replace('/', "-"), name);
-        let output = Command::new("lvcreate")
-            .args(&["-s", "-n", &snapshot_name, "-L", "5G", &lv_name])
-            .output()?;
-        
-        if !output.status.success() {
-            return Err(anyhow::anyhow!(
-                "Failed to create LVM snapshot: {}",
-                String::from_utf8_lossy(&output.stderr)
-            ));
+
+        for path in &self.paths {
+            let copy = self.copy_path(id, path);
+            if !copy.exists() {
+                // The path did not exist when the snapshot was taken
+                continue;
+            }
+
+            // Drop anything created since the snapshot, then copy everything back
+            if path.exists() {
+                for entry in walkdir::WalkDir::new(path).contents_first(true).min_depth(1) {
+                    let entry = entry?;
+                    let relative = entry.path().strip_prefix(path)?;
+                    if copy.join(relative).symlink_metadata().is_ok() {
+                        continue;
+                    }
+                    if entry.file_type().is_dir() {
+                        std::fs::remove_dir_all(entry.path())?;
+                    } else {
+                        std::fs::remove_file(entry.path())?;
+                    }
+                }
+            }
+
+            copy_tree(&copy, path)
+                .with_context(|| format!("Failed to restore {}", path.display()))?;
+        }
+
+        Ok(())
+    }
+
+    async fn list(&self) -> Result<Vec<String>> {
+        let mut snapshots = Vec::new();
+
+        for entry in std::fs::read_dir(&self.backup_dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                if let Some(name) = entry.file_name().to_str() {
+                    if name.starts_with("snapshot-") {
+                        snapshots.push(name.to_string());
+                    }
+                }
+            }
         }
-        
-        Ok(format!("/dev/mapper/{}", snapshot_name))
+
+        snapshots.sort();
+        Ok(snapshots)
     }
 
-    async fn create_zfs_snapshot(&self, name: &str) -> Result<String> {
-        // Get root dataset
-        let output = Command::new("zfs")
-            .args(&["list", "-H", "-o", "name", "/"])
-            .output()?;
-        
-        let dataset = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        let snapshot_name = format!("{}@{}", dataset, name);
-        
-        // Create ZFS snapshot
-        let output = Command::new("zfs")
-            .args(&["snapshot", &snapshot_name])
-            .output()?;
-        
-        if !output.status.success() {
-            return Err(anyhow::anyhow!(
-                "Failed to create ZFS snapshot: {}",
-                String::from_utf8_lossy(&output.stderr)
-            ));
+    async fn delete(&self, id: &str) -> Result<()> {
+        let snapshot_dir = self.backup_dir.join(id);
+        if snapshot_dir.exists() {
+            std::fs::remove_dir_all(snapshot_dir)?;
         }
-        
-        Ok(snapshot_name)
+        Ok(())
     }
+}
 
-    async fn create_file_snapshot(&self, name: &str) -> Result<String> {
-        // Fall back to file-based backup
-        let backup_dir = format!("/var/backups/hecate-snapshots/{}", name);
-        std::fs::create_dir_all(&backup_dir)?;
-        
-        // Save critical system files
-        let critical_paths = vec![
-            "/etc",
-            "/boot/grub",
-            "/boot/loader",
-            "/usr/local",
-        ];
-        
-        for path in critical_paths {
-            if Path::new(path).exists() {
-                let dest = format!("{}{}", backup_dir, path);
-                if let Some(parent) = Path::new(&dest).parent() {
-                    std::fs::create_dir_all(parent)?;
-                }
-                
-                // Use rsync for efficient copying
-                Command::new("rsync")
-                    .args(&["-av", "--quiet", path, &dest])
-                    .output()?;
+/// Copy a file or directory tree, overwriting existing files and keeping
+/// symlinks and permissions
+fn copy_tree(src: &Path, dst: &Path) -> Result<()> {
+    for entry in walkdir::WalkDir::new(src) {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(src)?;
+        let target = if relative.as_os_str().is_empty() {
+            dst.to_path_buf()
+        } else {
+            dst.join(relative)
+        };
+        let file_type = entry.file_type();
+
+        if file_type.is_dir() {
+            std::fs::create_dir_all(&target)?;
+            continue;
+        }
+
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // Copying onto a symlink would write through it, so clear the way first
+        if let Ok(existing) = target.symlink_metadata() {
+            if existing.is_dir() {
+                std::fs::remove_dir_all(&target)?;
+            } else if existing.file_type().is_symlink() || file_type.is_symlink() {
+                std::fs::remove_file(&target)?;
             }
         }
-        
-        // Save package list
-        Command::new("dpkg")
-            .args(&["-l"])
-            .output()
-            .ok()
-            .and_then(|o| std::fs::write(format!("{}/packages.list", backup_dir), o.stdout).ok());
-        
-        Ok(backup_dir)
+
+        if file_type.is_symlink() {
+            std::os::unix::fs::symlink(std::fs::read_link(entry.path())?, &target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
     }
 
-    pub async fn restore_snapshot(&self, snapshot_path: &str) -> Result<()> {
-        match self.snapshot_type {
-            SnapshotType::Btrfs => self.restore_btrfs_snapshot(snapshot_path).await,
-            SnapshotType::Lvm => self.restore_lvm_snapshot(snapshot_path).await,
-            SnapshotType::Zfs => self.restore_zfs_snapshot(snapshot_path).await,
-            SnapshotType::FileBased => self.restore_file_snapshot(snapshot_path).await,
+    Ok(())
+}
+
+/// Total size of the regular files under a directory
+fn dir_size(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum()
+}
+
+// ============================================================================
+// BTRFS BACKEND
+// ============================================================================
+
+/// Read-only btrfs subvolume snapshots
+///
+/// Rolling back makes a writable copy of the snapshot the default
+/// subvolume, which takes effect on the next boot.
+pub struct BtrfsBackend {
+    subvolume: PathBuf,
+    snapshot_dir: PathBuf,
+}
+
+impl BtrfsBackend {
+    pub fn new(subvolume: &Path, snapshot_dir: &Path) -> Self {
+        Self {
+            subvolume: subvolume.to_path_buf(),
+            snapshot_dir: snapshot_dir.to_path_buf(),
         }
     }
 
-    async fn restore_btrfs_snapshot(&self, snapshot_path: &str) -> Result<()> {
-        // BTRFS snapshot restore requires reboot with different root
-        // This would typically be handled by bootloader configuration
-        tracing::info!("BTRFS snapshot restore requires reboot with snapshot as root");
-        Ok(())
+    fn rollback_path(&self, id: &str) -> PathBuf {
+        self.snapshot_dir.join(format!("{}-rollback", id))
     }
+}
+
+#[async_trait]
+impl SnapshotBackend for BtrfsBackend {
+    fn name(&self) -> &'static str {
+        "btrfs"
+    }
+
+    async fn create(&self, id: &str) -> Result<u64> {
+        std::fs::create_dir_all(&self.snapshot_dir)?;
+
+        let snapshot = self.snapshot_dir.join(id);
+        run("btrfs", [
+            OsStr::new("subvolume"),
+            OsStr::new("snapshot"),
+            OsStr::new("-r"),
+            self.subvolume.as_os_str(),
+            snapshot.as_os_str(),
+        ]).await?;
 
-    async fn restore_lvm_snapshot(&self, snapshot_path: &str) -> Result<()> {
-        // LVM snapshot merge
-        let output = Command::new("lvconvert")
-            .args(&["--merge", snapshot_path])
-            .output()?;
-        
-        if !output.status.success() {
-            return Err(anyhow::anyhow!(
-                "Failed to merge LVM snapshot: {}",
-                String::from_utf8_lossy(&output.stderr)
-            ));
+        // Snapshots share extents with the live subvolume
+        Ok(0)
+    }
+
+    async fn rollback_to(&self, id: &str) -> Result<()> {
+        let snapshot = self.snapshot_dir.join(id);
+        if !snapshot.exists() {
+            return Err(anyhow::anyhow!("Snapshot {} not found in {}", id, self.snapshot_dir.display()));
+        }
+
+        let writable = self.rollback_path(id);
+        if !writable.exists() {
+            run("btrfs", [
+                OsStr::new("subvolume"),
+                OsStr::new("snapshot"),
+                snapshot.as_os_str(),
+                writable.as_os_str(),
+            ]).await?;
         }
-        
-        tracing::info!("LVM snapshot merge initiated, will complete on next reboot");
+
+        let show = run("btrfs", [OsStr::new("subvolume"), OsStr::new("show"), writable.as_os_str()]).await?;
+        let subvolume_id = show.lines()
+            .find_map(|line| line.trim().strip_prefix("Subvolume ID:"))
+            .map(|id| id.trim().to_string())
+            .ok_or_else(|| anyhow::anyhow!("Could not determine subvolume ID of {}", writable.display()))?;
+
+        run("btrfs", [
+            OsStr::new("subvolume"),
+            OsStr::new("set-default"),
+            OsStr::new(&subvolume_id),
+            self.subvolume.as_os_str(),
+        ]).await?;
+
+        tracing::warn!("Rolled back to {}; the restored root becomes active after reboot", id);
         Ok(())
     }
 
-    async fn restore_zfs_snapshot(&self, snapshot_path: &str) -> Result<()> {
-        // ZFS rollback
-        let output = Command::new("zfs")
-            .args(&["rollback", "-r", snapshot_path])
-            .output()?;
-        
-        if !output.status.success() {
-            return Err(anyhow::anyhow!(
-                "Failed to rollback ZFS snapshot: {}",
-                String::from_utf8_lossy(&output.stderr)
-            ));
+    async fn list(&self) -> Result<Vec<String>> {
+        let mut snapshots = Vec::new();
+
+        if self.snapshot_dir.exists() {
+            for entry in std::fs::read_dir(&self.snapshot_dir)? {
+                let entry = entry?;
+                if let Some(name) = entry.file_name().to_str() {
+                    if name.starts_with("snapshot-") && !name.ends_with("-rollback") {
+                        snapshots.push(name.to_string());
+                    }
+                }
+            }
         }
-        
-        Ok(())
+
+        snapshots.sort();
+        Ok(snapshots)
     }
 
-    async fn restore_file_snapshot(&self, backup_dir: &str) -> Result<()> {
-        // Restore from file-based backup
-        let critical_paths = vec![
-            "/etc",
-            "/boot/grub",
-            "/boot/loader",
-            "/usr/local",
-        ];
-        
-        for path in critical_paths {
-            let source = format!("{}{}", backup_dir, path);
-            if Path::new(&source).exists() {
-                Command::new("rsync")
-                    .args(&["-av", "--delete", &source, path])
-                    .output()?;
+    async fn delete(&self, id: &str) -> Result<()> {
+        for path in [self.snapshot_dir.join(id), self.rollback_path(id)] {
+            if path.exists() {
+                run("btrfs", [OsStr::new("subvolume"), OsStr::new("delete"), path.as_os_str()]).await?;
             }
         }
-        
         Ok(())
     }
+}
 
-    pub async fn delete_snapshot(&self, snapshot_path: &str) -> Result<()> {
-        match self.snapshot_type {
-            SnapshotType::Btrfs => {
-                Command::new("btrfs")
-                    .args(&["subvolume", "delete", snapshot_path])
-                    .output()?;
-            }
-            SnapshotType::Lvm => {
-                Command::new("lvremove")
-                    .args(&["-f", snapshot_path])
-                    .output()?;
-            }
-            SnapshotType::Zfs => {
-                Command::new("zfs")
-                    .args(&["destroy", snapshot_path])
-                    .output()?;
-            }
-            SnapshotType::FileBased => {
-                std::fs::remove_dir_all(snapshot_path)?;
-            }
+// ============================================================================
+// ZFS BACKEND
+// ============================================================================
+
+/// ZFS snapshots of a single dataset
+pub struct ZfsBackend {
+    dataset: String,
+}
+
+impl ZfsBackend {
+    pub fn new(dataset: &str) -> Self {
+        Self {
+            dataset: dataset.to_string(),
         }
-        
+    }
+
+    /// Use the dataset mounted at `path`
+    pub async fn for_mountpoint(path: &Path) -> Result<Self> {
+        let output = run("zfs", [OsStr::new("list"), OsStr::new("-H"), OsStr::new("-o"), OsStr::new("name"), path.as_os_str()]).await?;
+        let dataset = output.trim();
+        if dataset.is_empty() {
+            return Err(anyhow::anyhow!("No ZFS dataset mounted at {}", path.display()));
+        }
+        Ok(Self::new(dataset))
+    }
+
+    fn snapshot_name(&self, id: &str) -> String {
+        format!("{}@{}", self.dataset, id)
+    }
+}
+
+#[async_trait]
+impl SnapshotBackend for ZfsBackend {
+    fn name(&self) -> &'static str {
+        "zfs"
+    }
+
+    async fn create(&self, id: &str) -> Result<u64> {
+        let name = self.snapshot_name(id);
+        run("zfs", ["snapshot", name.as_str()]).await?;
+        Ok(0)
+    }
+
+    async fn rollback_to(&self, id: &str) -> Result<()> {
+        // -r destroys any snapshots taken after this one
+        let name = self.snapshot_name(id);
+        run("zfs", ["rollback", "-r", name.as_str()]).await?;
         Ok(())
     }
-}
\ No newline at end of file
+
+    async fn list(&self) -> Result<Vec<String>> {
+        let output = run("zfs", [
+            "list", "-H", "-t", "snapshot", "-o", "name", "-s", "creation", "-d", "1", self.dataset.as_str(),
+        ]).await?;
+
+        let prefix = format!("{}@", self.dataset);
+        Ok(output.lines()
+            .filter_map(|line| line.trim().strip_prefix(&prefix))
+            .filter(|name| name.starts_with("snapshot-"))
+            .map(str::to_string)
+            .collect())
+    }
+
+    async fn delete(&self, id: &str) -> Result<()> {
+        let name = self.snapshot_name(id);
+        run("zfs", ["destroy", name.as_str()]).await?;
+        Ok(())
+    }
+}
@@ -4,6 +4,7 @@ use hecate_update::{
     UpdateManager, UpdateConfig, UpdateType, UpdateInfo,
     SecuritySeverity, UpdateChecksum, UpdateStatus,
 };
+use hecate_pkg::testing::{build_archive, repository_index, test_package, FixtureServer};
 use std::path::PathBuf;
 use tempfile::tempdir;
 
@@ -95,4 +96,1050 @@ fn test_maintenance_window() {
     assert_eq!(window.days.len(), 2);
     assert_eq!(window.start_hour, 2);
     assert_eq!(window.end_hour, 6);
-}
\ No newline at end of file
+}
+
+/// Config that snapshots `<temp>/system` with the file backend
+fn snapshot_test_config(temp_dir: &std::path::Path) -> UpdateConfig {
+    UpdateConfig {
+        cache_dir: temp_dir.join("cache"),
+        backup_dir: temp_dir.join("backups"),
+        snapshot_backend: "file".to_string(),
+        snapshot_paths: vec![temp_dir.join("system")],
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_snapshot_create_list_delete() {
+    let temp_dir = tempdir().unwrap();
+    let backup_dir = temp_dir.path().join("backups");
+    let config = snapshot_test_config(temp_dir.path());
+    
+    let mut manager = UpdateManager::new(config).await.unwrap();
+    assert!(manager.list_snapshots().await.unwrap().is_empty());
+    
+    let first = manager.create_snapshot(Some("before-kernel")).await.unwrap();
+    let second = manager.create_snapshot(None).await.unwrap();
+    assert_ne!(first.id, second.id);
+    
+    let snapshots = manager.list_snapshots().await.unwrap();
+    let ids: Vec<&str> = snapshots.iter().map(|s| s.id.as_str()).collect();
+    assert_eq!(ids, vec![first.id.as_str(), second.id.as_str()]);
+    assert!(backup_dir.join("snapshots.json").exists());
+    
+    let info = manager.snapshot_info(&first.id).await.unwrap();
+    assert_eq!(info.name.as_deref(), Some("before-kernel"));
+    assert!(info.updates.is_empty());
+    
+    manager.delete_snapshot(&first.id).await.unwrap();
+    assert!(!backup_dir.join(&first.id).exists());
+    assert!(manager.snapshot_info(&first.id).await.is_err());
+    assert_eq!(manager.list_snapshots().await.unwrap().len(), 1);
+    assert!(manager.delete_snapshot(&first.id).await.is_err());
+}
+
+#[tokio::test]
+async fn test_rollback_to_snapshot_by_id() {
+    let temp_dir = tempdir().unwrap();
+    let system = temp_dir.path().join("system");
+    std::fs::create_dir_all(&system).unwrap();
+    let mut manager = UpdateManager::new(snapshot_test_config(temp_dir.path())).await.unwrap();
+    
+    std::fs::write(system.join("version"), "1").unwrap();
+    let first = manager.create_snapshot(Some("first")).await.unwrap();
+    std::fs::write(system.join("version"), "2").unwrap();
+    let second = manager.create_snapshot(Some("second")).await.unwrap();
+    std::fs::write(system.join("version"), "3").unwrap();
+    
+    assert!(manager.rollback_to("snapshot-missing").await.is_err());
+    
+    manager.rollback_to(&first.id).await.unwrap();
+    assert_eq!(std::fs::read_to_string(system.join("version")).unwrap(), "1");
+    
+    // Later snapshots stay available
+    manager.rollback_to(&second.id).await.unwrap();
+    assert_eq!(std::fs::read_to_string(system.join("version")).unwrap(), "2");
+    
+    // Snapshots taken by hand protect no updates, so there is nothing to mark
+    assert!(manager.get_history(None).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_file_backend_restores_paths() {
+    use hecate_update::snapshot::{FileBackend, SnapshotBackend};
+    
+    let temp_dir = tempdir().unwrap();
+    let system = temp_dir.path().join("system");
+    std::fs::create_dir_all(system.join("conf.d")).unwrap();
+    std::fs::write(system.join("hosts"), "127.0.0.1 localhost").unwrap();
+    std::fs::write(system.join("conf.d/a.conf"), "a").unwrap();
+    
+    let backend = FileBackend::new(&temp_dir.path().join("backups"), vec![system.clone()]);
+    let size = backend.create("snapshot-1").await.unwrap();
+    assert_eq!(size, "127.0.0.1 localhost".len() as u64 + 1);
+    assert_eq!(backend.list().await.unwrap(), vec!["snapshot-1".to_string()]);
+    
+    std::fs::write(system.join("hosts"), "changed").unwrap();
+    std::fs::remove_file(system.join("conf.d/a.conf")).unwrap();
+    std::fs::write(system.join("conf.d/b.conf"), "b").unwrap();
+    
+    backend.rollback_to("snapshot-1").await.unwrap();
+    assert_eq!(std::fs::read_to_string(system.join("hosts")).unwrap(), "127.0.0.1 localhost");
+    assert_eq!(std::fs::read_to_string(system.join("conf.d/a.conf")).unwrap(), "a");
+    assert!(!system.join("conf.d/b.conf").exists());
+    
+    backend.delete("snapshot-1").await.unwrap();
+    assert!(backend.list().await.unwrap().is_empty());
+    assert!(backend.rollback_to("snapshot-1").await.is_err());
+}
+
+/// Needs root and a btrfs subvolume at `$HECATE_TEST_BTRFS_SUBVOLUME`
+#[cfg(feature = "fs-integration-tests")]
+#[tokio::test]
+async fn test_btrfs_backend() {
+    use hecate_update::snapshot::{BtrfsBackend, SnapshotBackend};
+    
+    let subvolume = PathBuf::from(std::env::var("HECATE_TEST_BTRFS_SUBVOLUME").unwrap());
+    let snapshot_dir = subvolume.join(".snapshots");
+    let backend = BtrfsBackend::new(&subvolume, &snapshot_dir);
+    
+    backend.create("snapshot-btrfs-test").await.unwrap();
+    assert!(backend.list().await.unwrap().contains(&"snapshot-btrfs-test".to_string()));
+    
+    backend.delete("snapshot-btrfs-test").await.unwrap();
+    assert!(!snapshot_dir.join("snapshot-btrfs-test").exists());
+}
+
+/// Needs root and a scratch ZFS dataset named by `$HECATE_TEST_ZFS_DATASET`
+#[cfg(feature = "fs-integration-tests")]
+#[tokio::test]
+async fn test_zfs_backend() {
+    use hecate_update::snapshot::{SnapshotBackend, ZfsBackend};
+    
+    let dataset = std::env::var("HECATE_TEST_ZFS_DATASET").unwrap();
+    let backend = ZfsBackend::new(&dataset);
+    
+    backend.create("snapshot-zfs-test").await.unwrap();
+    assert!(backend.list().await.unwrap().contains(&"snapshot-zfs-test".to_string()));
+    backend.rollback_to("snapshot-zfs-test").await.unwrap();
+    
+    backend.delete("snapshot-zfs-test").await.unwrap();
+    assert!(!backend.list().await.unwrap().contains(&"snapshot-zfs-test".to_string()));
+}
+
+fn window(days: Vec<chrono::Weekday>, start_hour: u32, end_hour: u32, timezone: &str) -> hecate_update::MaintenanceWindow {
+    hecate_update::MaintenanceWindow {
+        days,
+        start_hour,
+        end_hour,
+        timezone: timezone.to_string(),
+    }
+}
+
+fn utc(s: &str) -> chrono::DateTime<chrono::Utc> {
+    s.parse().unwrap()
+}
+
+#[test]
+fn test_next_window_across_spring_forward() {
+    use hecate_update::scheduler::UpdateScheduler;
+    use chrono::Weekday;
+    
+    // New York skips from 02:00 to 03:00 on 2025-03-09
+    let scheduler = UpdateScheduler::new(window(vec![Weekday::Sun], 2, 4, "America/New_York")).unwrap();
+    
+    assert_eq!(scheduler.next_window_after(utc("2025-03-01T12:00:00Z")), utc("2025-03-02T07:00:00Z"));
+    assert_eq!(scheduler.next_window_after(utc("2025-03-08T12:00:00Z")), utc("2025-03-09T07:00:00Z"));
+    assert_eq!(scheduler.next_window_after(utc("2025-03-10T12:00:00Z")), utc("2025-03-16T06:00:00Z"));
+    
+    // The shortened window still ends at 04:00 local
+    assert!(scheduler.is_in_window_at(utc("2025-03-09T07:30:00Z")));
+    assert!(!scheduler.is_in_window_at(utc("2025-03-09T08:00:00Z")));
+    assert!(!scheduler.is_in_window_at(utc("2025-03-09T06:30:00Z")));
+}
+
+#[test]
+fn test_window_wrapping_past_midnight() {
+    use hecate_update::scheduler::UpdateScheduler;
+    use chrono::Weekday;
+    
+    let scheduler = UpdateScheduler::new(window(vec![Weekday::Sat], 22, 4, "UTC")).unwrap();
+    
+    // 2025-03-08 is a Saturday
+    assert!(!scheduler.is_in_window_at(utc("2025-03-08T21:00:00Z")));
+    assert!(scheduler.is_in_window_at(utc("2025-03-08T23:00:00Z")));
+    assert!(scheduler.is_in_window_at(utc("2025-03-09T03:00:00Z")));
+    assert!(!scheduler.is_in_window_at(utc("2025-03-09T05:00:00Z")));
+    assert!(!scheduler.is_in_window_at(utc("2025-03-09T23:00:00Z")));
+    
+    assert_eq!(scheduler.next_window_after(utc("2025-03-08T23:00:00Z")), utc("2025-03-15T22:00:00Z"));
+    
+    assert!(UpdateScheduler::new(window(vec![Weekday::Sat], 22, 4, "Mars/Olympus")).is_err());
+    assert!(UpdateScheduler::new(window(vec![Weekday::Sat], 22, 24, "UTC")).is_err());
+}
+
+/// Records commands instead of running them
+#[derive(Default)]
+struct RecordingRunner {
+    calls: std::sync::Mutex<Vec<Vec<String>>>,
+}
+
+impl hecate_update::reboot::CommandRunner for RecordingRunner {
+    fn run(&self, program: &str, args: &[String]) -> anyhow::Result<()> {
+        let mut call = vec![program.to_string()];
+        call.extend_from_slice(args);
+        self.calls.lock().unwrap().push(call);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_reboot_policy_decisions() {
+    use hecate_update::reboot::{decide, RebootAction, RebootPolicy};
+    use hecate_update::scheduler::UpdateScheduler;
+    use chrono::Weekday;
+    
+    let scheduler = UpdateScheduler::new(window(vec![Weekday::Sat], 22, 4, "UTC")).unwrap();
+    let inside = utc("2025-03-09T01:00:00Z");
+    let outside = utc("2025-03-10T12:00:00Z");
+    
+    assert_eq!(decide(RebootPolicy::Immediate, &scheduler, outside), RebootAction::RebootNow);
+    assert_eq!(decide(RebootPolicy::Notify, &scheduler, outside), RebootAction::Notify);
+    assert_eq!(decide(RebootPolicy::Never, &scheduler, outside), RebootAction::Nothing);
+    
+    // Inside a window, reboot when it closes; otherwise at the end of the next one
+    assert_eq!(
+        decide(RebootPolicy::AtWindowEnd, &scheduler, inside),
+        RebootAction::RebootAt(utc("2025-03-09T04:00:00Z"))
+    );
+    assert_eq!(
+        decide(RebootPolicy::AtWindowEnd, &scheduler, outside),
+        RebootAction::RebootAt(utc("2025-03-16T04:00:00Z"))
+    );
+}
+
+#[test]
+fn test_reboot_scheduling_and_cancel() {
+    use hecate_update::reboot::{RebootAction, RebootScheduler};
+    use std::sync::Arc;
+    
+    let temp_dir = tempdir().unwrap();
+    let flag = temp_dir.path().join("reboot-required");
+    let runner = Arc::new(RecordingRunner::default());
+    let reboot = RebootScheduler::new(runner.clone(), &flag);
+    
+    reboot.execute(&RebootAction::Notify).unwrap();
+    assert!(reboot.reboot_pending());
+    assert!(runner.calls.lock().unwrap().is_empty());
+    
+    reboot.execute(&RebootAction::RebootAt(utc("2025-03-16T04:00:00Z"))).unwrap();
+    let calls = runner.calls.lock().unwrap().clone();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0][0], "systemd-run");
+    assert!(calls[0].contains(&"--on-calendar=2025-03-16 04:00:00 UTC".to_string()));
+    
+    reboot.cancel().unwrap();
+    assert!(!reboot.reboot_pending());
+    let calls = runner.calls.lock().unwrap().clone();
+    assert_eq!(calls.last().unwrap(), &vec![
+        "systemctl".to_string(),
+        "stop".to_string(),
+        "hecate-update-reboot.timer".to_string(),
+    ]);
+    
+    reboot.execute(&RebootAction::Nothing).unwrap();
+    assert!(!reboot.reboot_pending());
+    assert_eq!(runner.calls.lock().unwrap().len(), 2);
+}
+
+/// Package repository served over HTTP for package update tests
+struct FixtureRepo {
+    url: String,
+    server: FixtureServer,
+}
+
+impl FixtureRepo {
+    async fn start() -> Self {
+        let server = FixtureServer::start().await;
+        Self { url: server.url.clone(), server }
+    }
+    
+    fn repository(&self) -> hecate_pkg::Repository {
+        hecate_pkg::Repository {
+            name: "core".to_string(),
+            url: format!("{}/core", self.url),
+            mirror_urls: Vec::new(),
+            enabled: true,
+            priority: 50,
+            gpg_check: false,
+            gpg_key: None,
+            last_update: None,
+        }
+    }
+    
+    /// Build a package whose archive installs `usr/share/<name>/VERSION` and serve it
+    fn add_package(&self, name: &str, version: &str) -> hecate_pkg::Package {
+        use sha2::{Digest, Sha256};
+        
+        let content = version.as_bytes();
+        let archive = build_archive(&[(&format!("usr/share/{}/VERSION", name), content)]);
+        
+        let mut package = test_package(name, version, &[]);
+        package.size_bytes = archive.len() as u64;
+        package.installed_size_bytes = content.len() as u64;
+        package.checksum.sha256 = hex::encode(Sha256::digest(&archive));
+        package.checksum.blake3 = hex::encode(blake3::hash(&archive).as_bytes());
+        
+        self.server.serve(&format!("/core/all/{}-{}.pkg.tar.zst", name, version), archive);
+        package
+    }
+    
+    /// Publish `packages` as the repository index
+    fn publish(&self, packages: &[hecate_pkg::Package]) {
+        let index = repository_index(self.repository(), packages.iter().cloned());
+        let data = zstd::encode_all(serde_json::to_vec(&index).unwrap().as_slice(), 3).unwrap();
+        self.server.serve_index("/core", data.clone(), &data);
+    }
+}
+
+#[tokio::test]
+async fn test_package_updates_from_hecate_pkg() {
+    use hecate_update::packages::HecatePkgSource;
+    
+    let temp_dir = tempdir().unwrap();
+    let root = temp_dir.path().join("root");
+    std::fs::create_dir_all(temp_dir.path().join("system")).unwrap();
+    
+    let repo = FixtureRepo::start().await;
+    let v1 = repo.add_package("foo", "1.0.0");
+    let v2 = repo.add_package("foo", "1.1.0");
+    repo.publish(std::slice::from_ref(&v1));
+    
+    let pkg_config = hecate_pkg::PackageConfig {
+        root_dir: root.clone(),
+        db_path: temp_dir.path().join("pkg/packages.db"),
+        cache_dir: temp_dir.path().join("pkg/cache"),
+        log_dir: temp_dir.path().join("pkg/log"),
+        run_hooks: false,
+        ..Default::default()
+    };
+    let mut pkg_manager = hecate_pkg::PackageManager::new(pkg_config).await.unwrap();
+    pkg_manager.add_repository(repo.repository()).await.unwrap();
+    pkg_manager.sync_repositories().await.unwrap();
+    pkg_manager.install("foo").await.unwrap();
+    
+    // A newer version shows up in the repository
+    repo.publish(&[v1, v2]);
+    
+    let config = UpdateConfig {
+        update_server: repo.url.clone(),
+        ..snapshot_test_config(temp_dir.path())
+    };
+    let mut manager = UpdateManager::new(config).await.unwrap()
+        .with_package_source(Box::new(HecatePkgSource::new(pkg_manager)));
+    
+    let updates = manager.check_updates().await.unwrap().updates;
+    let update = updates.iter()
+        .find(|u| matches!(&u.update_type, UpdateType::Package { name, .. } if name == "foo"))
+        .expect("package update should be reported");
+    assert_eq!(update.update_type, UpdateType::Package {
+        name: "foo".to_string(),
+        version: semver::Version::new(1, 1, 0),
+    });
+    
+    let plan = manager.create_plan(vec![update.id.clone()]).await.unwrap();
+    manager.apply_updates(plan).await.unwrap();
+    
+    let installed = std::fs::read_to_string(root.join("usr/share/foo/VERSION")).unwrap();
+    assert_eq!(installed, "1.1.0");
+    
+    // Nothing left to upgrade
+    let updates = manager.check_updates().await.unwrap().updates;
+    assert!(!updates.iter().any(|u| matches!(u.update_type, UpdateType::Package { .. })));
+}
+
+#[tokio::test]
+async fn test_download_update_respects_rate_limit() {
+    use sha2::{Digest, Sha256};
+    
+    let temp_dir = tempdir().unwrap();
+    let repo = FixtureRepo::start().await;
+    let blob = vec![7u8; 48 * 1024];
+    repo.server.serve("/firmware.bin", blob.clone());
+    
+    let config = UpdateConfig {
+        max_download_bytes_per_sec: 16 * 1024,
+        // Only bounds the update checks, not a slow download
+        request_timeout: std::time::Duration::from_secs(1),
+        ..snapshot_test_config(temp_dir.path())
+    };
+    let manager = UpdateManager::new(config).await.unwrap();
+    
+    let mut update = UpdateInfo {
+        id: "fw-1".to_string(),
+        update_type: UpdateType::Firmware {
+            component: "bios".to_string(),
+            version: "1.2".to_string(),
+            requires_reboot: true,
+        },
+        description: "BIOS update".to_string(),
+        size_bytes: blob.len() as u64,
+        download_url: format!("{}/firmware.bin", repo.url),
+        checksum: UpdateChecksum {
+            sha256: hex::encode(Sha256::digest(&blob)),
+            blake3: hex::encode(blake3::hash(&blob).as_bytes()),
+        },
+        signature: None,
+        release_date: chrono::Utc::now(),
+        dependencies: Vec::new(),
+        conflicts: Vec::new(),
+        changelog: None,
+    };
+    
+    // 48KiB at 16KiB/s with a one second burst takes at least two seconds
+    let started = std::time::Instant::now();
+    let path = manager.download_update(&update).await.unwrap();
+    assert!(started.elapsed() >= std::time::Duration::from_millis(1900));
+    assert_eq!(std::fs::read(path).unwrap(), blob);
+    
+    update.checksum.sha256 = "0".repeat(64);
+    assert!(manager.download_update(&update).await.is_err());
+}
+
+/// Package source with fixed upgrades whose `upgrade` fails for `failing`
+struct FakePackages {
+    names: Vec<&'static str>,
+    failing: Option<&'static str>,
+}
+
+#[async_trait::async_trait]
+impl hecate_update::packages::PackageSource for FakePackages {
+    async fn outdated(&self) -> anyhow::Result<Vec<hecate_update::packages::PackageUpgrade>> {
+        Ok(self.names.iter().map(|name| hecate_update::packages::PackageUpgrade {
+            name: name.to_string(),
+            installed: semver::Version::new(1, 0, 0),
+            available: semver::Version::new(1, 1, 0),
+            description: String::new(),
+            size_bytes: 0,
+            sha256: String::new(),
+            blake3: String::new(),
+            release_date: chrono::Utc::now(),
+        }).collect())
+    }
+    
+    async fn upgrade(&self, name: &str, _version: &semver::Version) -> anyhow::Result<()> {
+        if self.failing == Some(name) {
+            return Err(anyhow::anyhow!("{} is broken", name));
+        }
+        Ok(())
+    }
+}
+
+/// Apply a plan for `names` and collect the events it emits
+async fn apply_and_collect(temp_dir: &std::path::Path, names: Vec<&'static str>, failing: Option<&'static str>) -> Vec<hecate_update::UpdateEvent> {
+    std::fs::create_dir_all(temp_dir.join("system")).unwrap();
+    let config = UpdateConfig {
+        update_server: FixtureRepo::start().await.url,
+        ..snapshot_test_config(temp_dir)
+    };
+    let mut manager = UpdateManager::new(config).await.unwrap()
+        .with_package_source(Box::new(FakePackages { names: names.clone(), failing }));
+    
+    let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+    manager.set_event_sender(Some(tx));
+    
+    manager.check_updates().await.unwrap();
+    let ids = names.iter().map(|name| format!("pkg-{}-1.1.0", name)).collect();
+    let plan = manager.create_plan(ids).await.unwrap();
+    let _ = manager.apply_updates(plan).await;
+    manager.set_event_sender(None);
+    
+    let mut events = Vec::new();
+    while let Some(event) = rx.recv().await {
+        events.push(event);
+    }
+    events
+}
+
+#[tokio::test]
+async fn test_apply_updates_emits_events() {
+    use hecate_update::UpdateEvent;
+    
+    let temp_dir = tempdir().unwrap();
+    let events = apply_and_collect(temp_dir.path(), vec!["a", "b"], None).await;
+    assert_eq!(events, vec![
+        UpdateEvent::Started { id: "pkg-a-1.1.0".to_string() },
+        UpdateEvent::Succeeded { id: "pkg-a-1.1.0".to_string() },
+        UpdateEvent::Started { id: "pkg-b-1.1.0".to_string() },
+        UpdateEvent::Succeeded { id: "pkg-b-1.1.0".to_string() },
+    ]);
+    
+    let temp_dir = tempdir().unwrap();
+    let events = apply_and_collect(temp_dir.path(), vec!["a", "b"], Some("b")).await;
+    assert_eq!(events.len(), 5);
+    assert_eq!(events[2], UpdateEvent::Started { id: "pkg-b-1.1.0".to_string() });
+    assert!(matches!(&events[3], UpdateEvent::Failed { id, error } if id == "pkg-b-1.1.0" && error.contains("b is broken")));
+    assert_eq!(events[4], UpdateEvent::RollbackStarted);
+}
+
+#[tokio::test]
+async fn test_full_event_channel_does_not_block_updates() {
+    use hecate_update::UpdateEvent;
+    
+    let temp_dir = tempdir().unwrap();
+    std::fs::create_dir_all(temp_dir.path().join("system")).unwrap();
+    let config = UpdateConfig {
+        update_server: FixtureRepo::start().await.url,
+        ..snapshot_test_config(temp_dir.path())
+    };
+    let names = vec!["a", "b"];
+    let mut manager = UpdateManager::new(config).await.unwrap()
+        .with_package_source(Box::new(FakePackages { names, failing: None }));
+    
+    // Nobody reads the channel, so it fills after the first event
+    let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+    manager.set_event_sender(Some(tx));
+    
+    manager.check_updates().await.unwrap();
+    let plan = manager.create_plan(vec!["pkg-a-1.1.0".to_string(), "pkg-b-1.1.0".to_string()]).await.unwrap();
+    tokio::time::timeout(std::time::Duration::from_secs(10), manager.apply_updates(plan))
+        .await
+        .expect("a full event channel blocked the updates")
+        .unwrap();
+    manager.set_event_sender(None);
+    
+    assert_eq!(rx.recv().await, Some(UpdateEvent::Started { id: "pkg-a-1.1.0".to_string() }));
+    assert_eq!(rx.recv().await, None);
+}
+
+#[tokio::test]
+async fn test_history_persists_applied_updates() {
+    let temp_dir = tempdir().unwrap();
+    apply_and_collect(temp_dir.path(), vec!["a", "b"], None).await;
+    apply_and_collect(temp_dir.path(), vec!["c"], Some("c")).await;
+    
+    // A fresh manager reads what earlier runs wrote, newest first
+    let manager = UpdateManager::new(snapshot_test_config(temp_dir.path())).await.unwrap();
+    let history = manager.get_history(None).await.unwrap();
+    let ids: Vec<&str> = history.iter().map(|h| h.id.as_str()).collect();
+    assert_eq!(ids, ["pkg-c-1.1.0", "pkg-c-1.1.0", "pkg-b-1.1.0", "pkg-a-1.1.0"]);
+    
+    // The automatic rollback marks the update it undid
+    assert_eq!(history[0].status, UpdateStatus::RolledBack);
+    assert_eq!(history[0].update_type, history[1].update_type);
+    assert!(matches!(&history[1].status, UpdateStatus::Failed { error } if error.contains("c is broken")));
+    assert_eq!(history[2].status, UpdateStatus::Installed);
+    assert_eq!(history[2].update_type, UpdateType::Package {
+        name: "b".to_string(),
+        version: semver::Version::new(1, 1, 0),
+    });
+    assert!(history[1..].iter().all(|h| h.rollback_available));
+    assert!(history.windows(2).all(|w| w[0].timestamp >= w[1].timestamp));
+    
+    let limited = manager.get_history(Some(2)).await.unwrap();
+    assert_eq!(limited.len(), 2);
+    assert_eq!(limited[1].id, "pkg-c-1.1.0");
+    
+    // Only updates that are still in place count as installed
+    let installed = hecate_update::installed_updates(&history);
+    assert_eq!(installed, ["pkg-a-1.1.0".to_string(), "pkg-b-1.1.0".to_string()].into());
+}
+
+fn update_with_deps(id: &str, dependencies: &[&str]) -> UpdateInfo {
+    UpdateInfo {
+        id: id.to_string(),
+        update_type: UpdateType::Package {
+            name: id.to_string(),
+            version: semver::Version::new(1, 0, 0),
+        },
+        description: String::new(),
+        size_bytes: 0,
+        download_url: String::new(),
+        checksum: UpdateChecksum {
+            sha256: String::new(),
+            blake3: String::new(),
+        },
+        signature: None,
+        release_date: chrono::Utc::now(),
+        dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+        conflicts: Vec::new(),
+        changelog: None,
+    }
+}
+
+#[test]
+fn test_update_order_follows_dependencies() {
+    use hecate_update::resolve_update_order;
+    use std::collections::HashSet;
+    
+    let updates = vec![
+        update_with_deps("c", &["b"]),
+        update_with_deps("b", &["a", "installed"]),
+        update_with_deps("a", &[]),
+    ];
+    let installed = HashSet::from(["installed".to_string()]);
+    assert_eq!(resolve_update_order(&updates, &installed).unwrap(), vec!["a", "b", "c"]);
+}
+
+#[test]
+fn test_update_dependency_cycle_is_rejected() {
+    use hecate_update::resolve_update_order;
+    
+    let updates = vec![
+        update_with_deps("a", &["b"]),
+        update_with_deps("b", &["a"]),
+    ];
+    let err = resolve_update_order(&updates, &Default::default()).unwrap_err();
+    assert!(err.to_string().contains("a -> b -> a"), "{}", err);
+}
+
+#[test]
+fn test_missing_update_dependency_is_reported() {
+    use hecate_update::resolve_update_order;
+    
+    let updates = vec![
+        update_with_deps("a", &["gone"]),
+        update_with_deps("b", &["a", "also-gone"]),
+    ];
+    let err = resolve_update_order(&updates, &Default::default()).unwrap_err().to_string();
+    assert!(err.contains("a requires gone"), "{}", err);
+    assert!(err.contains("b requires also-gone"), "{}", err);
+}
+
+fn security_update(id: &str, severity: SecuritySeverity, immediate: bool) -> UpdateInfo {
+    UpdateInfo {
+        update_type: UpdateType::Security {
+            cve_id: format!("CVE-2025-{}", id),
+            severity,
+            immediate,
+        },
+        ..update_with_deps(id, &[])
+    }
+}
+
+#[test]
+fn test_security_updates_filter_by_severity() {
+    use hecate_update::security_updates;
+    
+    let updates = vec![
+        security_update("low", SecuritySeverity::Low, false),
+        update_with_deps("package", &[]),
+        security_update("critical", SecuritySeverity::Critical, true),
+        security_update("high", SecuritySeverity::High, false),
+        security_update("medium", SecuritySeverity::Medium, false),
+    ];
+    let ids = |min| security_updates(&updates, &min).iter().map(|u| u.id.clone()).collect::<Vec<_>>();
+    
+    assert_eq!(ids(SecuritySeverity::Low), vec!["low", "critical", "high", "medium"]);
+    assert_eq!(ids(SecuritySeverity::High), vec!["critical", "high"]);
+    assert_eq!(ids(SecuritySeverity::Critical), vec!["critical"]);
+}
+
+#[test]
+fn test_security_action_immediate_vs_scheduled() {
+    use hecate_update::{security_action, SecurityAction};
+    use hecate_update::scheduler::UpdateScheduler;
+    use chrono::Weekday;
+    
+    let scheduler = UpdateScheduler::new(window(vec![Weekday::Sat], 22, 4, "UTC")).unwrap();
+    let inside = utc("2025-03-09T01:00:00Z");
+    let outside = utc("2025-03-10T12:00:00Z");
+    let immediate = security_update("critical", SecuritySeverity::Critical, true);
+    let deferrable = security_update("high", SecuritySeverity::High, false);
+    
+    assert_eq!(security_action(&immediate, &scheduler, outside), SecurityAction::ApplyNow);
+    assert_eq!(
+        security_action(&deferrable, &scheduler, outside),
+        SecurityAction::ScheduleAt(utc("2025-03-15T22:00:00Z"))
+    );
+    
+    // Nothing waits once the window is open
+    assert_eq!(security_action(&immediate, &scheduler, inside), SecurityAction::ApplyNow);
+    assert_eq!(security_action(&deferrable, &scheduler, inside), SecurityAction::ApplyNow);
+}
+
+#[test]
+fn test_config_set_then_show() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().join("etc/update.toml");
+    
+    // No file yet: the defaults
+    let mut config = UpdateConfig::load(&path).unwrap();
+    assert!(config.enable_live_patching);
+    
+    config.set("enable_live_patching", "false").unwrap();
+    config.set("maintenance_window.start_hour", "23").unwrap();
+    config.set("reboot_policy", "at-window-end").unwrap();
+    config.set_maintenance_window("Sat, Sun", 22, 4).unwrap();
+    config.save(&path).unwrap();
+    
+    let reloaded = UpdateConfig::load(&path).unwrap();
+    assert!(!reloaded.enable_live_patching);
+    assert_eq!(reloaded.maintenance_window.days, vec![chrono::Weekday::Sat, chrono::Weekday::Sun]);
+    assert_eq!(reloaded.maintenance_window.start_hour, 22);
+    assert_eq!(reloaded.reboot_policy, hecate_update::reboot::RebootPolicy::AtWindowEnd);
+    
+    let shown = reloaded.to_toml().unwrap();
+    assert!(shown.contains("enable_live_patching = false"), "{}", shown);
+    assert!(shown.contains("start_hour = 22"), "{}", shown);
+    assert!(!temp_dir.path().join("etc/update.toml.tmp").exists());
+}
+
+#[test]
+fn test_config_rejects_invalid_values() {
+    let mut config = UpdateConfig::default();
+    
+    let err = config.set("maintenance_window.end_hour", "24").unwrap_err();
+    assert!(err.to_string().contains("between 0 and 23"), "{}", err);
+    assert!(config.set_maintenance_window("Mon", 2, 30).is_err());
+    assert_eq!(config.maintenance_window.end_hour, 6);
+    
+    assert!(config.set("enable_live_patching", "maybe").is_err());
+    assert!(config.set("maintenance_window.days", "Funday").is_err());
+    assert!(config.set("snapshot_backend", "lvm").is_err());
+    assert!(config.set("max_parallel_downloads", "0").is_err());
+    
+    let err = config.set("no_such_key", "1").unwrap_err();
+    assert!(err.to_string().contains("Unknown configuration key"), "{}", err);
+    
+    // Hand-edited files are validated on load too
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().join("update.toml");
+    let mut raw = UpdateConfig::default();
+    raw.maintenance_window.start_hour = 42;
+    std::fs::write(&path, toml::to_string(&raw).unwrap()).unwrap();
+    assert!(UpdateConfig::load(&path).is_err());
+}
+
+#[test]
+fn test_kernel_release_compatibility() {
+    use hecate_update::kernel::KernelRelease;
+    
+    let ubuntu = KernelRelease::parse("6.8.0-45-generic\n");
+    assert_eq!(ubuntu.version, "6.8.0");
+    assert_eq!(ubuntu.abi.as_deref(), Some("45"));
+    assert!(ubuntu.matches("6.8.0", "45"));
+    assert!(ubuntu.matches("6.8", "45"));
+    // Same upstream kernel, different ABI
+    assert!(!ubuntu.matches("6.8.0", "44"));
+    assert!(!ubuntu.matches("6.8.0", ""));
+    assert!(!ubuntu.matches("6.8.1", "45"));
+    assert!(!ubuntu.matches("6.9.0", "45"));
+    
+    let fedora = KernelRelease::parse("6.8.9-300.fc40.x86_64");
+    assert!(fedora.matches("6.8.9", "300"));
+    assert!(!fedora.matches("6.8.9", "301"));
+    
+    let vanilla = KernelRelease::parse("6.8.0");
+    assert_eq!(vanilla.abi, None);
+    assert!(vanilla.matches("6.8.0", ""));
+    assert!(!vanilla.matches("6.8.0", "1"));
+    
+    assert!(!KernelRelease::parse("Unknown").matches("Unknown", ""));
+}
+
+#[tokio::test]
+async fn test_live_patch_refused_for_other_kernel() {
+    use hecate_update::kernel::{KernelPatchManager, LivePatchError};
+    
+    let patch = UpdateInfo {
+        update_type: UpdateType::KernelPatch {
+            version: "6.8.0".to_string(),
+            patch_level: "45".to_string(),
+            requires_reboot: false,
+        },
+        ..update_with_deps("kpatch-cve", &[])
+    };
+    
+    KernelPatchManager::with_release("6.8.0-45-generic").apply_live_patch(&patch).await.unwrap();
+    
+    let err = KernelPatchManager::with_release("6.8.0-47-generic")
+        .apply_live_patch(&patch).await.unwrap_err();
+    match err.downcast_ref::<LivePatchError>() {
+        Some(LivePatchError::Incompatible { patch, target, running }) => {
+            assert_eq!(patch, "kpatch-cve");
+            assert_eq!(target, "6.8.0-45");
+            assert_eq!(running, "6.8.0-47-generic");
+        }
+        None => panic!("expected an incompatible patch error, got {}", err),
+    }
+}
+
+fn driver_update(module: &str) -> UpdateInfo {
+    UpdateInfo {
+        update_type: UpdateType::Driver {
+            name: module.to_string(),
+            version: "2.0".to_string(),
+            vendor: "Example".to_string(),
+            hot_swappable: true,
+        },
+        ..update_with_deps(module, &[])
+    }
+}
+
+#[test]
+fn test_module_refcount_parsing() {
+    use hecate_update::driver::parse_refcnt;
+    
+    assert_eq!(parse_refcnt("0\n").unwrap(), 0);
+    assert_eq!(parse_refcnt(" 3 ").unwrap(), 3);
+    assert!(parse_refcnt("").is_err());
+    assert!(parse_refcnt("-1").is_err());
+}
+
+#[tokio::test]
+async fn test_hot_swap_preflight_checks_module_usage() {
+    use hecate_update::driver::{DriverManager, HotSwapError, ModuleUsage};
+    use std::os::unix::fs::symlink;
+    
+    let sysfs = tempdir().unwrap();
+    let module = |name: &str, refcnt: &str| {
+        let dir = sysfs.path().join("module").join(name);
+        std::fs::create_dir_all(dir.join("holders")).unwrap();
+        std::fs::write(dir.join("refcnt"), refcnt).unwrap();
+        dir
+    };
+    
+    module("idle", "0\n");
+    let busy = module("gpu", "0\n");
+    let driver = busy.join("drivers/pci:gpu");
+    std::fs::create_dir_all(&driver).unwrap();
+    for link in ["0000:01:00.0", "module"] {
+        symlink(sysfs.path(), driver.join(link)).unwrap();
+    }
+    std::fs::write(driver.join("bind"), "").unwrap();
+    let held = module("held", "2\n");
+    std::fs::create_dir_all(held.join("holders/held_user")).unwrap();
+    std::fs::create_dir_all(sysfs.path().join("module/builtin")).unwrap();
+    
+    let manager = DriverManager::new().unwrap().with_sysfs_root(sysfs.path());
+    
+    assert_eq!(manager.module_usage("idle").unwrap(), Some(ModuleUsage::default()));
+    assert_eq!(manager.module_usage("gpu").unwrap(), Some(ModuleUsage {
+        refcount: 0,
+        holders: Vec::new(),
+        devices: vec!["pci:gpu/0000:01:00.0".to_string()],
+    }));
+    assert_eq!(manager.module_usage("held").unwrap(), Some(ModuleUsage {
+        refcount: 2,
+        holders: vec!["held_user".to_string()],
+        devices: Vec::new(),
+    }));
+    assert_eq!(manager.module_usage("absent").unwrap(), None);
+    assert!(manager.module_usage("builtin").is_err());
+    
+    manager.hot_swap(&driver_update("idle")).await.unwrap();
+    manager.hot_swap(&driver_update("absent")).await.unwrap();
+    for name in ["gpu", "held"] {
+        let err = manager.hot_swap(&driver_update(name)).await.unwrap_err();
+        assert!(
+            matches!(err.downcast_ref::<HotSwapError>(), Some(HotSwapError::ModuleInUse { module, .. }) if module == name),
+            "{}", err
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_status_serializes_manager_state() {
+    let temp_dir = tempdir().unwrap();
+    let mut config = snapshot_test_config(temp_dir.path());
+    config.enable_hot_swapping = false;
+    config.maintenance_window = window(vec![chrono::Weekday::Wed], 2, 6, "UTC");
+    config.update_server = FixtureRepo::start().await.url;
+    let mut manager = UpdateManager::new(config).await.unwrap();
+    
+    let status = manager.status_at(utc("2025-03-10T12:00:00Z"));
+    assert_eq!(serde_json::to_value(&status).unwrap(), serde_json::json!({
+        "live_patching": true,
+        "hot_swapping": false,
+        "auto_rollback": true,
+        "reboot_policy": "Notify",
+        "reboot_pending": false,
+        "active_snapshot": null,
+        "pending_updates": 0,
+        "next_window": "2025-03-12T02:00:00Z",
+        "last_check": null,
+    }));
+    
+    let before = chrono::Utc::now();
+    manager.check_updates().await.unwrap();
+    
+    // A fresh manager still knows when the last check was
+    let manager = UpdateManager::new(snapshot_test_config(temp_dir.path())).await.unwrap();
+    let last_check = manager.status().last_check.unwrap();
+    assert!(last_check >= before - chrono::Duration::seconds(1) && last_check <= chrono::Utc::now());
+}
+
+#[tokio::test]
+async fn test_status_in_a_new_process_sees_pending_updates_and_snapshot() {
+    use hecate_update::UpdatePlan;
+    
+    let temp_dir = tempdir().unwrap();
+    std::fs::create_dir_all(temp_dir.path().join("system")).unwrap();
+    let config = || UpdateConfig {
+        update_server: "http://127.0.0.1:9".to_string(),
+        ..snapshot_test_config(temp_dir.path())
+    };
+    let plan = |update: UpdateInfo| UpdatePlan {
+        order: vec![update.id.clone()],
+        updates: vec![update],
+        estimated_time: std::time::Duration::ZERO,
+        requires_reboot: false,
+        snapshot_before: true,
+        auto_rollback: false,
+    };
+    
+    // A deferred security fix waits; flashing firmware fails and leaves the
+    // snapshot protecting the run behind
+    let mut manager = UpdateManager::new(config()).await.unwrap();
+    manager.apply_updates(plan(security_update("deferred", SecuritySeverity::High, false))).await.unwrap();
+    let firmware = UpdateInfo {
+        update_type: UpdateType::Firmware {
+            component: "bios".to_string(),
+            version: "2.0".to_string(),
+            requires_reboot: true,
+        },
+        ..update_with_deps("bios-2.0", &[])
+    };
+    assert!(manager.apply_updates(plan(firmware)).await.is_err());
+    let active = manager.status().active_snapshot.unwrap();
+    drop(manager);
+    
+    let mut manager = UpdateManager::new(config()).await.unwrap();
+    let status = manager.status();
+    assert_eq!(status.pending_updates, 1);
+    assert_eq!(status.active_snapshot.as_deref(), Some(active.as_str()));
+    
+    // The new process can roll back to it, which clears it for the next one
+    manager.rollback().await.unwrap();
+    let manager = UpdateManager::new(config()).await.unwrap();
+    assert_eq!(manager.status().active_snapshot, None);
+    assert_eq!(manager.status().pending_updates, 1);
+}
+
+#[tokio::test]
+async fn test_immediate_security_update_is_not_reported_applied() {
+    let temp_dir = tempdir().unwrap();
+    std::fs::create_dir_all(temp_dir.path().join("system")).unwrap();
+    let mut manager = UpdateManager::new(snapshot_test_config(temp_dir.path())).await.unwrap();
+    let update = security_update("critical", SecuritySeverity::Critical, true);
+    let plan = hecate_update::UpdatePlan {
+        order: vec![update.id.clone()],
+        updates: vec![update],
+        estimated_time: std::time::Duration::ZERO,
+        requires_reboot: false,
+        snapshot_before: false,
+        auto_rollback: false,
+    };
+    
+    let err = manager.apply_updates(plan).await.unwrap_err();
+    assert!(format!("{:#}", err).contains("critical was not applied"), "{:#}", err);
+    assert_eq!(manager.status().pending_updates, 0);
+}
+
+#[tokio::test]
+async fn test_kernel_and_driver_updates_from_server() {
+    let temp_dir = tempdir().unwrap();
+    let repo = FixtureRepo::start().await;
+    
+    let kernel = UpdateInfo {
+        update_type: UpdateType::KernelPatch {
+            version: "6.8.1".to_string(),
+            patch_level: "1".to_string(),
+            requires_reboot: true,
+        },
+        ..update_with_deps("kernel-6.8.1", &[])
+    };
+    let driver = driver_update("nvidia");
+    // Each list only contributes updates of its own kind
+    repo.server.serve(
+        "/kernel/updates.json",
+        serde_json::to_vec(&[kernel.clone(), driver.clone()]).unwrap(),
+    );
+    
+    let config = UpdateConfig {
+        update_server: repo.url.clone(),
+        ..snapshot_test_config(temp_dir.path())
+    };
+    let mut manager = UpdateManager::new(config).await.unwrap();
+    let ids: Vec<_> = manager.check_updates().await.unwrap().updates.into_iter().map(|u| u.id).collect();
+    assert_eq!(ids, vec!["kernel-6.8.1".to_string()]);
+    
+    repo.server.serve(
+        "/drivers/updates.json",
+        serde_json::to_vec(&[driver]).unwrap(),
+    );
+    let ids: Vec<_> = manager.check_updates().await.unwrap().updates.into_iter().map(|u| u.id).collect();
+    assert_eq!(ids, vec!["kernel-6.8.1".to_string(), "nvidia".to_string()]);
+}
+
+#[tokio::test]
+async fn test_update_check_times_out_on_unreachable_server() {
+    use hecate_update::ServerError;
+    use std::time::{Duration, Instant};
+    
+    let temp_dir = tempdir().unwrap();
+    let config = UpdateConfig {
+        // Non-routable, so connecting hangs rather than being refused
+        update_server: "http://10.255.255.1".to_string(),
+        connect_timeout: Duration::from_secs(1),
+        request_timeout: Duration::from_secs(2),
+        ..snapshot_test_config(temp_dir.path())
+    };
+    let mut manager = UpdateManager::new(config).await.unwrap();
+    
+    let started = Instant::now();
+    let err = manager.check_kernel_updates().await.unwrap_err();
+    assert!(started.elapsed() < Duration::from_secs(5), "took {:?}", started.elapsed());
+    
+    // Without a route at all, or behind a firewall resetting it, the
+    // connection fails straight away instead
+    match err.downcast_ref::<ServerError>() {
+        Some(ServerError::ConnectTimeout { timeout, .. }) => assert_eq!(*timeout, Duration::from_secs(1)),
+        Some(ServerError::Unreachable { .. }) => {}
+        _ => panic!("expected an unreachable server, got {:#}", err),
+    }
+    
+    // The whole check reports the server's sources instead of failing
+    let started = Instant::now();
+    let check = manager.check_updates().await.unwrap();
+    assert!(started.elapsed() < Duration::from_secs(5), "took {:?}", started.elapsed());
+    assert!(check.updates.is_empty());
+    let sources: Vec<_> = check.failures.iter().map(|f| f.source).collect();
+    assert_eq!(sources, vec!["kernel", "driver"]);
+    assert!(check.failures.iter().all(|f| f.error.downcast_ref::<ServerError>().is_some()));
+}
+
+#[tokio::test]
+async fn test_update_check_times_out_on_silent_server() {
+    use hecate_update::ServerError;
+    use std::time::{Duration, Instant};
+    
+    // Accepts connections but never answers
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let mut sockets = Vec::new();
+        while let Ok((socket, _)) = listener.accept().await {
+            sockets.push(socket);
+        }
+    });
+    
+    let temp_dir = tempdir().unwrap();
+    let config = UpdateConfig {
+        update_server: server,
+        request_timeout: Duration::from_secs(1),
+        ..snapshot_test_config(temp_dir.path())
+    };
+    let mut manager = UpdateManager::new(config).await.unwrap();
+    
+    let started = Instant::now();
+    let err = manager.check_driver_updates().await.unwrap_err();
+    assert!(started.elapsed() < Duration::from_secs(5), "took {:?}", started.elapsed());
+    assert!(matches!(err.downcast_ref::<ServerError>(), Some(ServerError::RequestTimeout { .. })), "{:#}", err);
+    assert!(err.to_string().contains("did not respond within 1s"), "{}", err);
+    
+    let check = manager.check_updates().await.unwrap();
+    assert!(check.updates.is_empty());
+    assert_eq!(check.failures.len(), 2);
+}